@@ -3,31 +3,54 @@
 //!     "0xa8816d3a6e3136e86bc2873b1f94a15cadc8af2703c075f2d546c2ae367f4df9::ocean::OCEAN"
 
 use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
     fmt,
     str::FromStr,
-    sync::Arc,
+    sync::{Arc, Mutex},
     time::{Duration, Instant},
 };
 
 use async_trait::async_trait;
-use clap::Parser;
+use clap::{Parser, ValueEnum};
+use dex_indexer::normalize_coin_type;
 use eyre::{bail, ensure, ContextCompat, Result};
+use futures::future::join_all;
 use itertools::Itertools;
 use object_pool::ObjectPool;
+use serde::{Deserialize, Serialize};
 use simulator::{HttpSimulator, SimulateCtx, Simulator};
-use sui_sdk::SuiClientBuilder;
+use sui_sdk::{SuiClient, SuiClientBuilder};
 use sui_types::{
-    base_types::{ObjectID, ObjectRef, SuiAddress},
-    transaction::TransactionData,
+    base_types::{ObjectID, ObjectRef, SequenceNumber, SuiAddress},
+    transaction::{ObjectReadResult, TransactionData, TransactionDataAPI},
 };
-use tokio::task::JoinSet;
+use tokio::{sync::mpsc, task::JoinSet};
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, info, instrument, Instrument};
 use utils::coin;
 
 use crate::{
+    common::bid,
+    common::competition,
+    common::cancel::until_cancelled,
+    common::dry_run::DryRun,
     common::get_latest_epoch,
-    common::search::{golden_section_search_maximize, SearchGoal},
-    defi::{Defi, Path, TradeType},
+    common::metrics,
+    common::parallel_sim::{self, SimRequest},
+    common::quote_filter::QuoteFilter,
+    defi::{
+        base,
+        cp_math::{self, Curve},
+        flash_lender::FlashLoanConfig,
+        partner::PartnerConfig,
+        quote, registry, Defi, Path, PathTradeResult, TradeType,
+    },
+    search::{golden_section_search_maximize, SearchGoal},
+    strategy::{
+        routes::{self, SizedRoute},
+        sizing::AmountOptimizer,
+    },
     types::Source,
     HttpConfig,
 };
@@ -40,18 +63,26 @@ pub struct Args {
     #[arg(long)]
     pub pool_id: Option<String>,
 
-    #[arg(
-        long,
-        default_value = ""
-    )]
+    #[arg(long)]
     pub sender: String,
 
+    /// Also simulate the trade that was found, and log its profit, gas and effects
+    #[arg(long)]
+    pub dry_run: bool,
+
     #[command(flatten)]
     pub http_config: HttpConfig,
+
+    #[command(flatten)]
+    pub partner_config: PartnerConfig,
 }
 
 pub async fn run(args: Args) -> Result<()> {
-    mev_logger::init_console_logger_with_directives(None, &["arb=debug", "dex_indexer=debug"]);
+    mev_logger::init_console_logger_with_directives(
+        None,
+        &["arb=debug", "poison_dart_core=debug", "dex_indexer=debug", "dry_run_metrics=info"],
+    );
+    registry::register_partners(&args.partner_config);
 
     info!("Running arb with {:?}", args);
     let rpc_url = args.http_config.rpc_url.clone();
@@ -59,13 +90,19 @@ pub async fn run(args: Args) -> Result<()> {
 
     let sender = SuiAddress::from_str(&args.sender).map_err(|e| eyre::eyre!(e))?;
 
-    let simulator_pool = ObjectPool::new(1, move || {
+    let simulator_pool = Arc::new(ObjectPool::new(1, move || {
         tokio::runtime::Runtime::new()
             .unwrap()
             .block_on(async { Box::new(HttpSimulator::new(&rpc_url, &ipc_path).await) as Box<dyn Simulator> })
-    });
-
-    let arb = Arb::new(&args.http_config.rpc_url, Arc::new(simulator_pool)).await?;
+    }));
+
+    let arb = Arb::new(
+        &args.http_config.rpc_url,
+        Arc::clone(&simulator_pool),
+        GraphSettings::default(),
+        &FlashLoanConfig::default(),
+    )
+    .await?;
     let sui = SuiClientBuilder::default().build(&args.http_config.rpc_url).await?;
     let gas_coins = coin::get_gas_coin_refs(&sui, sender, None).await?;
     let epoch = get_latest_epoch(&sui).await?;
@@ -79,15 +116,49 @@ pub async fn run(args: Args) -> Result<()> {
             pool_id,
             gas_coins,
             sim_ctx,
-            true,
+            Sizing::Gss,
             Source::Public,
+            &CancellationToken::new(),
+            None,
         )
         .await?;
 
     info!("{result:#?}");
+
+    if args.dry_run {
+        DryRun::new(simulator_pool, sui).await?.simulate(result.tx_data, 0).await?;
+    }
     Ok(())
 }
 
+/// How the trade size of a profitable path is found.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Sizing {
+    /// Local math for constant-product cycles, grid search otherwise
+    #[default]
+    Local,
+    /// Grid search only
+    Grid,
+    /// Local math or grid search, refined by golden section search
+    Gss,
+}
+
+/// The objects a search pinned, in the order it read them. A replay record carries them so the
+/// search re-runs on the same state.
+#[derive(Default)]
+pub struct PinLog(Mutex<Vec<ObjectReadResult>>);
+
+impl PinLog {
+    fn extend(&self, objects: &[ObjectReadResult]) {
+        self.0.lock().unwrap().extend_from_slice(objects);
+    }
+
+    pub fn take(&self) -> Vec<ObjectReadResult> {
+        std::mem::take(&mut self.0.lock().unwrap())
+    }
+}
+
 #[derive(Debug)]
 pub struct ArbResult {
     pub create_trial_ctx_duration: Duration,
@@ -97,30 +168,169 @@ pub struct ArbResult {
     pub cache_misses: u64,
     pub source: Source,
     pub tx_data: TransactionData,
+    /// Versions of the path objects the profitable simulation ran against
+    pub state_versions: HashMap<ObjectID, SequenceNumber>,
+    /// Pool-disjoint cycles traded along with the best one, each sized on its own
+    pub extra_routes: Vec<TrialResult>,
+}
+
+impl ArbResult {
+    /// The best cycle, then those traded along with it.
+    pub fn routes(&self) -> impl Iterator<Item = &TrialResult> {
+        std::iter::once(&self.best_trial_result).chain(&self.extra_routes)
+    }
+
+    /// Expected profit of all routes.
+    pub fn profit(&self) -> u64 {
+        self.routes().map(|route| route.profit).sum()
+    }
+
+    /// The pools of all routes.
+    pub fn object_ids(&self) -> impl Iterator<Item = ObjectID> + '_ {
+        self.routes().flat_map(|route| route.trade_path.object_ids())
+    }
 }
 
-use crate::strategy::graph_path_finder::BellmanFordPathFinder;
+use crate::graph_path_finder::{BellmanFordPathFinder, GraphSettings, QuoteWeights, SettledPath};
 
-// Hardcoded flag to enable/disable graph-based path finding
+// Hardcoded flag to enable/disable graph-based path finding; configured start tokens enable it too
 const USE_GRAPH_BASED_PATH_FINDING: bool = false;
+// How long the coins a sender was read to hold are trusted
+const HOLDINGS_TTL: Duration = Duration::from_secs(30);
+// Share of the locally quoted profit the simulation at the local size must make for the grid to
+// be skipped.
+const MIN_LOCAL_PROFIT_RATIO: f64 = 0.5;
 
 pub struct Arb {
     defi: Defi,
-    path_finder: Option<BellmanFordPathFinder>,
+    path_finder: Option<Arc<BellmanFordPathFinder>>,
+    // set with start tokens: multi-start paths settle in a token the sender holds
+    holdings: Option<Holdings>,
+}
+
+/// The coin types each sender holds a balance of, read from the RPC at most every `HOLDINGS_TTL`.
+struct Holdings {
+    sui: SuiClient,
+    read: tokio::sync::Mutex<HashMap<SuiAddress, (Instant, Arc<HashSet<String>>)>>,
+}
+
+impl Holdings {
+    async fn of(&self, sender: SuiAddress) -> Result<Arc<HashSet<String>>> {
+        let mut read = self.read.lock().await;
+        if let Some((read_at, held)) = read.get(&sender) {
+            if read_at.elapsed() < HOLDINGS_TTL {
+                return Ok(Arc::clone(held));
+            }
+        }
+        let balances = self.sui.coin_read_api().get_all_balances(sender).await?;
+        let held: Arc<HashSet<String>> = Arc::new(
+            balances
+                .into_iter()
+                .filter(|balance| balance.total_balance > 0)
+                .map(|balance| normalize_coin_type(&balance.coin_type))
+                .collect(),
+        );
+        read.insert(sender, (Instant::now(), Arc::clone(&held)));
+        Ok(held)
+    }
 }
 
 impl Arb {
-    pub async fn new(http_url: &str, simulator_pool: Arc<ObjectPool<Box<dyn Simulator>>>) -> Result<Self> {
-        let defi = Defi::new(http_url, simulator_pool).await?;
+    /// Paths are found within the hop limit, liquidity floor and token lists of `graph_settings`.
+    pub async fn new(
+        http_url: &str,
+        simulator_pool: Arc<ObjectPool<Box<dyn Simulator>>>,
+        graph_settings: GraphSettings,
+        flashloan: &FlashLoanConfig,
+    ) -> Result<Self> {
+        let defi = Defi::new(http_url, simulator_pool, flashloan)
+            .await?
+            .with_graph_settings(graph_settings.clone());
         
         // Initialize the path finder if graph-based path finding is enabled
-        let path_finder = if USE_GRAPH_BASED_PATH_FINDING {
-            Some(BellmanFordPathFinder::new(defi.get_dex_searcher()))
+        let multi_start = !graph_settings.config().start_tokens.is_empty();
+        let (path_finder, holdings) = if USE_GRAPH_BASED_PATH_FINDING || multi_start {
+            let sui = SuiClientBuilder::default().build(http_url).await?;
+            let weights = QuoteWeights::new(defi.get_trader(), defi.get_dex_searcher(), sui.clone());
+            let path_finder =
+                BellmanFordPathFinder::new(defi.get_dex_searcher(), graph_settings).with_weights(Arc::new(weights));
+            let holdings = multi_start.then(|| Holdings {
+                sui,
+                read: Default::default(),
+            });
+            (Some(Arc::new(path_finder)), holdings)
         } else {
-            None
+            (None, None)
         };
         
-        Ok(Self { defi, path_finder })
+        Ok(Self {
+            defi,
+            path_finder,
+            holdings,
+        })
+    }
+
+    /// The trade of `arb_result` built for `sender` instead.
+    pub async fn rebuild_for_sender(&self, sender: SuiAddress, arb_result: &ArbResult) -> Result<TransactionData> {
+        if arb_result.extra_routes.is_empty() {
+            return self
+                .rebuild(sender, arb_result.best_trial_result.amount_in, arb_result)
+                .await;
+        }
+        let routes = arb_result
+            .routes()
+            .map(|route| (route.trade_path.clone(), route.amount_in))
+            .collect_vec();
+        self.defi
+            .build_combined_tx_data(sender, &routes, vec![], arb_result.tx_data.gas_price())
+            .await
+    }
+
+    /// Each route of `arb_result` as a trade of its own, from the same sender and gas coins.
+    pub async fn split_routes(&self, arb_result: &ArbResult) -> Result<Vec<ArbResult>> {
+        let tx_data = &arb_result.tx_data;
+        let mut split = vec![];
+        for route in arb_result.routes() {
+            let route_tx_data = self
+                .defi
+                .build_final_tx_data(
+                    tx_data.sender(),
+                    route.amount_in,
+                    &route.trade_path,
+                    tx_data.gas().to_vec(),
+                    tx_data.gas_price(),
+                    arb_result.source,
+                )
+                .await?;
+            let state_versions = versions_of([&route.trade_path], &arb_result.state_versions);
+            split.push(ArbResult {
+                create_trial_ctx_duration: arb_result.create_trial_ctx_duration,
+                grid_search_duration: arb_result.grid_search_duration,
+                gss_duration: arb_result.gss_duration,
+                best_trial_result: route.clone(),
+                cache_misses: arb_result.cache_misses,
+                source: arb_result.source,
+                tx_data: route_tx_data,
+                state_versions,
+                extra_routes: vec![],
+            });
+        }
+        Ok(split)
+    }
+
+    /// The best route of `arb_result` built for `sender` with `amount_in` instead.
+    pub async fn rebuild(&self, sender: SuiAddress, amount_in: u64, arb_result: &ArbResult) -> Result<TransactionData> {
+        let trial = &arb_result.best_trial_result;
+        self.defi
+            .build_final_tx_data(
+                sender,
+                amount_in,
+                &trial.trade_path,
+                vec![],
+                arb_result.tx_data.gas_price(),
+                arb_result.source,
+            )
+            .await
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -131,85 +341,147 @@ impl Arb {
         pool_id: Option<ObjectID>,
         gas_coins: Vec<ObjectRef>,
         sim_ctx: SimulateCtx,
-        use_gss: bool,
+        sizing: Sizing,
         source: Source,
+        cancel: &CancellationToken,
+        pins: Option<&PinLog>,
     ) -> Result<ArbResult> {
         let gas_price = sim_ctx.epoch.gas_price;
 
         // If graph-based path finding is enabled and the path finder is initialized
-        if USE_GRAPH_BASED_PATH_FINDING && self.path_finder.is_some() {
+        if self.path_finder.is_some() {
             info!("Using graph-based path finding with Bellman-Ford algorithm");
             
-            // Find arbitrage paths using Bellman-Ford
+            // Size paths as Bellman-Ford finds them instead of waiting for the whole search
             let timer = Instant::now();
-            let paths = self.path_finder.as_ref().unwrap()
-                .find_arbitrage_paths(coin_type, pool_id)
-                .await?;
-            let create_trial_ctx_duration = timer.elapsed();
-            
-            if paths.is_empty() {
-                bail!("No arbitrage paths found using Bellman-Ford algorithm");
-            }
-            
-            info!("Found {} arbitrage paths using Bellman-Ford", paths.len());
-            
-            // Grid search on the found paths
+            // gains settled outside the base coin, valued at what these would sell them for
+            let mut sell_paths: HashMap<String, Arc<Vec<Path>>> = HashMap::new();
+            let mut paths_rx = match &self.holdings {
+                Some(holdings) => {
+                    let held = holdings.of(sender).await?;
+                    let settled = self
+                        .path_finder
+                        .as_ref()
+                        .unwrap()
+                        .find_multi_start_paths(pool_id, |token| base::is_base_coin(token) || held.contains(token))
+                        .await?;
+                    let (tx, rx) = mpsc::unbounded_channel();
+                    for SettledPath { settles_in, path } in settled {
+                        if !base::is_base_coin(&settles_in) && !sell_paths.contains_key(&settles_in) {
+                            let paths = self.defi.find_sell_paths(&settles_in).await?;
+                            sell_paths.insert(settles_in, Arc::new(paths));
+                        }
+                        let _ = tx.send(path);
+                    }
+                    rx
+                }
+                None => self
+                    .path_finder
+                    .as_ref()
+                    .unwrap()
+                    .stream_arbitrage_paths(coin_type, pool_id),
+            };
+
             let starting_grid = 1_000_000u64; // 0.001 SUI
             let mut cache_misses = 0;
-            let (max_trial_res, grid_search_duration) = {
-                let timer = Instant::now();
-                let mut joinset: JoinSet<Result<TrialResult>> = JoinSet::new();
-                
-                for path in &paths {
-                    for inc in 1..11 {
+            let mut path_count = 0;
+            let mut max_trial_res = TrialResult::default();
+            let mut joinset: JoinSet<Result<TrialResult>> = JoinSet::new();
+            let quote_filter = QuoteFilter::new(&sim_ctx);
+            // the pools of the paths found so far, read once at a consistent version
+            let mut pinned_ctx = sim_ctx.clone();
+            let mut pinned_versions = HashMap::new();
+
+            loop {
+                tokio::select! {
+                    _ = cancel.cancelled() => bail!("cancelled"),
+                    path = paths_rx.recv() => {
+                        let Some(path) = path else {
+                            break;
+                        };
+                        path_count += 1;
+                        if !quote_filter.keep(&path) {
+                            continue;
+                        }
+
+                        // a constant-product cycle is sized in process, the grid runs when the
+                        // simulation doesn't confirm that size
+                        let local_size = match sizing {
+                            Sizing::Grid => None,
+                            _ => local_best_size(&[path.clone()], &sim_ctx),
+                        };
+                        let pinned_before = pinned_ctx.override_objects.len();
+                        match self.defi.pin_path_objects([&path], &mut pinned_ctx).await {
+                            Ok(versions) => {
+                                pinned_versions.extend(versions);
+                                if let Some(pins) = pins {
+                                    pins.extend(&pinned_ctx.override_objects[pinned_before..]);
+                                }
+                            }
+                            Err(error) => {
+                                debug!(?error, "Pin path objects failed");
+                                continue;
+                            }
+                        }
                         let defi = self.defi.clone();
-                        let path = path.clone();
-                        let sender = sender;
                         let gas_coins = gas_coins.clone();
-                        let sim_ctx = sim_ctx.clone();
-                        let grid = starting_grid.checked_mul(10u64.pow(inc)).context("Grid overflow")?;
-                        
+                        let sim_ctx = pinned_ctx.clone();
+                        let sells = sell_paths.get(&path.coin_in_type()).cloned().unwrap_or_default();
                         joinset.spawn(async move {
-                            let trade_res = defi.find_best_path_exact_in(
-                                &[path],
-                                sender,
-                                grid,
-                                TradeType::Flashloan,
-                                &gas_coins,
-                                &sim_ctx,
-                            ).await?;
-                            
-                            let profit = trade_res.profit();
-                            if profit <= 0 {
-                                return Ok(TrialResult::default());
+                            let trial = |amount_in| {
+                                simulate_settled_path(&defi, &path, &sells, sender, amount_in, &gas_coins, &sim_ctx)
+                            };
+                            if let Some((amount_in, local_profit)) = local_size {
+                                let trial_res = trial(amount_in).await.ok();
+                                if let Some(trial_res) = trial_res.filter(|res| confirms(res, local_profit)) {
+                                    return Ok(trial_res);
+                                }
                             }
-                            
-                            Ok(TrialResult::new(
-                                &trade_res.path.coin_in_type(),
-                                grid,
-                                profit as u64,
-                                trade_res.path,
-                                trade_res.cache_misses,
-                            ))
+                            let trials = join_all(grid(starting_grid)?.into_iter().map(trial)).await;
+                            let best = trials.into_iter().flatten().max_by_key(|trial_res| trial_res.profit);
+                            Ok(best.unwrap_or_default())
                         }.in_current_span());
                     }
-                }
-                
-                let mut max_trial_res = TrialResult::default();
-                while let Some(Ok(trial_res)) = joinset.join_next().await {
-                    if let Ok(trial_res) = trial_res {
-                        if trial_res.cache_misses > cache_misses {
-                            cache_misses = trial_res.cache_misses;
+                    Some(Ok(trial_res)) = joinset.join_next(), if !joinset.is_empty() => {
+                        if let Ok(trial_res) = trial_res {
+                            cache_misses = cache_misses.max(trial_res.cache_misses);
+                            if trial_res > max_trial_res {
+                                max_trial_res = trial_res;
+                            }
                         }
-                        if trial_res > max_trial_res {
-                            max_trial_res = trial_res;
+                        // early exit: the first profitable cycle wins, stop the search
+                        if max_trial_res.profit > 0 {
+                            break;
                         }
                     }
                 }
-                
-                (max_trial_res, timer.elapsed())
+            }
+            drop(paths_rx);
+            let create_trial_ctx_duration = timer.elapsed();
+            let (nodes, edges) = self.path_finder.as_ref().unwrap().graph_size();
+            metrics::graph_size(nodes, edges);
+
+            // let the grid points already in flight finish
+            let grid_search_duration = {
+                let timer = Instant::now();
+                let drain = async {
+                    while let Some(Ok(trial_res)) = joinset.join_next().await {
+                        if let Ok(trial_res) = trial_res {
+                            cache_misses = cache_misses.max(trial_res.cache_misses);
+                            if trial_res > max_trial_res {
+                                max_trial_res = trial_res;
+                            }
+                        }
+                    }
+                    Ok(())
+                };
+                until_cancelled(cancel, drain).await?;
+                timer.elapsed()
             };
-            
+
+            ensure!(path_count > 0, "No arbitrage paths found using Bellman-Ford algorithm");
+            info!("Sized {} arbitrage paths found by Bellman-Ford", path_count);
+
             ensure!(
                 max_trial_res.profit > 0,
                 "cache_misses: {}. No profitable grid found",
@@ -230,13 +502,16 @@ impl Arb {
             if source.deadline().is_some() {
                 source = source.with_arb_found_time(utils::current_time_ms());
             }
-            source = source.with_bid_amount(*profit / 10 * 9);
+            let boost = competition::bid_boost(trade_path.object_ids());
+            source = source.with_bid_amount(bid::bid_amount(*profit, boost));
             
+            ensure!(!cancel.is_cancelled(), "cancelled");
             let tx_data = self
                 .defi
                 .build_final_tx_data(sender, *amount_in, trade_path, gas_coins, gas_price, source)
                 .await?;
-            
+            let state_versions = versions_of([trade_path], &pinned_versions);
+
             return Ok(ArbResult {
                 create_trial_ctx_duration,
                 grid_search_duration,
@@ -245,53 +520,89 @@ impl Arb {
                 cache_misses,
                 source,
                 tx_data,
+                state_versions,
+                extra_routes: vec![],
             });
         }
         
         // Traditional path finding
         let (ctx, create_trial_ctx_duration) = {
             let timer = Instant::now();
+            let pinned_before = sim_ctx.override_objects.len();
             let ctx = Arc::new(
-                TrialCtx::new(
-                    self.defi.clone(),
-                    sender,
-                    coin_type,
-                    pool_id,
-                    gas_coins.clone(),
-                    sim_ctx,
+                until_cancelled(
+                    cancel,
+                    TrialCtx::new(
+                        self.defi.clone(),
+                        sender,
+                        coin_type,
+                        pool_id,
+                        gas_coins.clone(),
+                        sim_ctx,
+                        cancel.clone(),
+                    ),
                 )
                 .await?,
             );
+            // what the trial ctx pinned follows the trigger's own overrides
+            if let Some(pins) = pins {
+                pins.extend(&ctx.sim_ctx.override_objects[pinned_before..]);
+            }
 
             (ctx, timer.elapsed())
         };
 
-        // Grid search
+        // Grid search, unless local math sized every cycle (all constant product) and the simulator
+        // confirms the profit at that size
         let starting_grid = 1_000_000u64; // 0.001 SUI
         let mut cache_misses = 0;
-        let (mut max_trial_res, grid_search_duration) = {
-            let timer = Instant::now();
-            let mut joinset: JoinSet<Result<TrialResult>> = JoinSet::new();
-            for inc in 1..11 {
-                let ctx = ctx.clone();
-                let grid = starting_grid.checked_mul(10u64.pow(inc)).context("Grid overflow")?;
-
-                joinset.spawn(async move { ctx.trial(grid).await }.in_current_span());
+        let timer = Instant::now();
+        let local_size = ctx.local_size.filter(|_| sizing != Sizing::Grid);
+        let local_trial_res = match local_size {
+            Some((amount_in, local_profit)) => until_cancelled(cancel, ctx.trial(amount_in))
+                .await
+                .ok()
+                .filter(|trial_res| confirms(trial_res, local_profit)),
+            None => None,
+        };
+        let (mut max_trial_res, grid_search_duration) = match local_trial_res {
+            Some(trial_res) if ctx.all_constant_product => {
+                debug!(
+                    amount_in = trial_res.amount_in,
+                    profit = trial_res.profit,
+                    "sized by local math"
+                );
+                cache_misses = trial_res.cache_misses;
+                (trial_res, timer.elapsed())
             }
+            local_trial_res => {
+                let mut joinset: JoinSet<Result<TrialResult>> = JoinSet::new();
+                for grid in grid(starting_grid)? {
+                    let ctx = ctx.clone();
+                    joinset.spawn(async move { ctx.trial(grid).await }.in_current_span());
+                }
 
-            let mut max_trial_res = TrialResult::default();
-            while let Some(Ok(trial_res)) = joinset.join_next().await {
-                // debug!(?trial_res, "Grid searching");
-                if let Ok(trial_res) = trial_res {
-                    if trial_res.cache_misses > cache_misses {
-                        cache_misses = trial_res.cache_misses;
-                    }
-                    if trial_res > max_trial_res {
-                        max_trial_res = trial_res;
+                // the local size of the constant-product cycles competes with the grid
+                let mut max_trial_res = local_trial_res.unwrap_or_default();
+                cache_misses = max_trial_res.cache_misses;
+                let grid_search = async {
+                    while let Some(Ok(trial_res)) = joinset.join_next().await {
+                        // debug!(?trial_res, "Grid searching");
+                        if let Ok(trial_res) = trial_res {
+                            if trial_res.cache_misses > cache_misses {
+                                cache_misses = trial_res.cache_misses;
+                            }
+                            if trial_res > max_trial_res {
+                                max_trial_res = trial_res;
+                            }
+                        }
                     }
-                }
+                    Ok(())
+                };
+                // returning drops the joinset, which aborts the grid points still running
+                until_cancelled(cancel, grid_search).await?;
+                (max_trial_res, timer.elapsed())
             }
-            (max_trial_res, timer.elapsed())
         };
 
         ensure!(
@@ -300,14 +611,15 @@ impl Arb {
             cache_misses
         );
 
-        let gss_duration = if use_gss {
+        let gss_duration = if sizing == Sizing::Gss {
             // GSS
             let timer = Instant::now();
             let upper_bound = max_trial_res.amount_in.saturating_mul(10);
             let lower_bound = max_trial_res.amount_in.saturating_div(10);
 
             let goal = TrialGoal;
-            let (_, _, trial_res) = golden_section_search_maximize(lower_bound, upper_bound, goal, &ctx).await;
+            let gss = async { Ok(golden_section_search_maximize(lower_bound, upper_bound, goal, &ctx).await) };
+            let (_, _, trial_res) = until_cancelled(cancel, gss).await?;
             if trial_res.cache_misses > cache_misses {
                 cache_misses = trial_res.cache_misses;
             }
@@ -326,6 +638,14 @@ impl Arb {
             cache_misses
         );
 
+        // shio bids trade one cycle, public trades take pool-disjoint ones along
+        let extra_routes = if source.is_shio() {
+            vec![]
+        } else {
+            let routes = async { Ok(ctx.extra_routes(&max_trial_res).await) };
+            until_cancelled(cancel, routes).await?
+        };
+
         let TrialResult {
             amount_in,
             trade_path,
@@ -337,13 +657,25 @@ impl Arb {
         if source.deadline().is_some() {
             source = source.with_arb_found_time(utils::current_time_ms());
         }
-        // TODO make bid_amount configurable
-        source = source.with_bid_amount(*profit / 10 * 9);
+        let boost = competition::bid_boost(trade_path.object_ids());
+        source = source.with_bid_amount(bid::bid_amount(*profit, boost));
 
-        let tx_data = self
-            .defi
-            .build_final_tx_data(sender, *amount_in, trade_path, gas_coins, gas_price, source)
-            .await?;
+        ensure!(!cancel.is_cancelled(), "cancelled");
+        let tx_data = if extra_routes.is_empty() {
+            self.defi
+                .build_final_tx_data(sender, *amount_in, trade_path, gas_coins, gas_price, source)
+                .await?
+        } else {
+            let routes = std::iter::once(&max_trial_res)
+                .chain(&extra_routes)
+                .map(|route| (route.trade_path.clone(), route.amount_in))
+                .collect_vec();
+            self.defi
+                .build_combined_tx_data(sender, &routes, gas_coins, gas_price)
+                .await?
+        };
+        let route_paths = std::iter::once(trade_path).chain(extra_routes.iter().map(|route| &route.trade_path));
+        let state_versions = versions_of(route_paths, &ctx.pinned_versions);
 
         Ok(ArbResult {
             create_trial_ctx_duration,
@@ -353,6 +685,8 @@ impl Arb {
             cache_misses,
             source,
             tx_data,
+            state_versions,
+            extra_routes,
         })
     }
 }
@@ -364,8 +698,16 @@ pub struct TrialCtx {
     pool_id: Option<ObjectID>,
     buy_paths: Vec<Path>,
     sell_paths: Vec<Path>,
+    // cycles through other pools of the coin, candidates to trade along with the best one
+    route_cycles: Vec<Path>,
     gas_coins: Vec<ObjectRef>,
     sim_ctx: SimulateCtx,
+    pinned_versions: HashMap<ObjectID, SequenceNumber>,
+    // most profitable size and profit of the constant-product cycles, by local math
+    local_size: Option<(u64, u64)>,
+    // whether local math sized every cycle
+    all_constant_product: bool,
+    cancel: CancellationToken,
 }
 
 impl TrialCtx {
@@ -375,7 +717,8 @@ impl TrialCtx {
         coin_type: &str,
         pool_id: Option<ObjectID>,
         gas_coins: Vec<ObjectRef>,
-        sim_ctx: SimulateCtx,
+        mut sim_ctx: SimulateCtx,
+        cancel: CancellationToken,
     ) -> Result<Self> {
         let buy_paths = defi.find_buy_paths(coin_type).await?;
         ensure!(!buy_paths.is_empty(), "no buy paths found for {}", coin_type);
@@ -393,6 +736,56 @@ impl TrialCtx {
             );
         }
 
+        // a buy or sell path is only simulated if one of its cycles passes the quote pre-filter
+        let quote_filter = QuoteFilter::new(&sim_ctx);
+        let split_routes = routes::config().max_routes > 1;
+        let (mut cycles, mut route_cycles) = (Vec::new(), Vec::new());
+        let (mut keep_buy, mut keep_sell) = (vec![false; buy_paths.len()], vec![false; sell_paths.len()]);
+        for ((i, buy), (j, sell)) in buy_paths
+            .iter()
+            .enumerate()
+            .cartesian_product(sell_paths.iter().enumerate())
+        {
+            let through_pool = buy.contains_pool(pool_id) || sell.contains_pool(pool_id);
+            if !buy.is_disjoint(sell) || !(through_pool || split_routes) {
+                continue;
+            }
+            let cycle = Path::new(buy.path.iter().chain(&sell.path).cloned().collect());
+            if !quote_filter.keep(&cycle) {
+                continue;
+            }
+            if through_pool {
+                keep_buy[i] = true;
+                keep_sell[j] = true;
+                cycles.push(cycle);
+            } else {
+                route_cycles.push(cycle);
+            }
+        }
+        let buy_paths = buy_paths
+            .into_iter()
+            .zip(keep_buy)
+            .filter_map(|(p, keep)| keep.then_some(p))
+            .collect_vec();
+        let sell_paths = sell_paths
+            .into_iter()
+            .zip(keep_sell)
+            .filter_map(|(p, keep)| keep.then_some(p))
+            .collect_vec();
+        ensure!(
+            !cycles.is_empty(),
+            "no cycle through {} passes the quote pre-filter",
+            coin_type
+        );
+        let local_size = local_best_size(&cycles, &sim_ctx);
+        let all_constant_product = cycles.iter().all(|cycle| is_locally_sized(cycle, &sim_ctx));
+
+        // every trial quotes these pools, and the routes traded along with the best cycle are sized
+        // on the same snapshot, so read them once at a consistent version
+        let pinned_versions = defi
+            .pin_path_objects(buy_paths.iter().chain(&sell_paths).chain(&route_cycles), &mut sim_ctx)
+            .await?;
+
         Ok(Self {
             defi,
             sender,
@@ -400,8 +793,13 @@ impl TrialCtx {
             pool_id,
             buy_paths,
             sell_paths,
+            route_cycles,
             gas_coins,
             sim_ctx,
+            pinned_versions,
+            local_size,
+            all_constant_product,
+            cancel,
         })
     }
 
@@ -418,18 +816,10 @@ impl TrialCtx {
         tracing::Span::current().record("action", "buy");
 
         let timer = Instant::now();
-        let best_buy_res = self
-            .defi
-            .find_best_path_exact_in(
-                &self.buy_paths,
-                self.sender,
-                amount_in,
-                TradeType::Swap,
-                &self.gas_coins,
-                &self.sim_ctx,
-            )
-            .await?;
+        let buy_sims = self.simulate(self.buy_paths.clone(), amount_in, TradeType::Swap);
+        let best_buy_res = parallel_sim::best_of(buy_sims).await?;
         let buy_elapsed = timer.elapsed();
+        metrics::simulated("search", self.buy_paths.len(), buy_elapsed);
 
         let timer = Instant::now();
         // append sell paths to the best buy path
@@ -442,9 +832,10 @@ impl TrialCtx {
                 // - buy_path and sell_path should not have common pools
                 // - either buy_path or sell_path should contain the swapped_pool
                 if best_buy_path.is_disjoint(p) && (buy_path_contains_pool || p.contains_pool(self.pool_id)) {
-                    let mut path = best_buy_path.clone();
-                    path.path.extend(p.path.clone());
-                    Some(path)
+                    let mut hops = Vec::with_capacity(best_buy_path.path.len() + p.path.len());
+                    hops.extend(best_buy_path.path.iter().cloned());
+                    hops.extend(p.path.iter().cloned());
+                    Some(Path::new(hops))
                 } else {
                     None
                 }
@@ -458,19 +849,12 @@ impl TrialCtx {
         );
 
         tracing::Span::current().record("action", "sell");
-        let best_trade_res = self
-            .defi
-            .find_best_path_exact_in(
-                &trade_paths,
-                self.sender,
-                amount_in,
-                TradeType::Flashloan,
-                &self.gas_coins,
-                &self.sim_ctx,
-            )
-            .await?;
+        let trade_path_count = trade_paths.len();
+        let trade_sims = self.simulate(trade_paths, amount_in, TradeType::Flashloan);
+        let best_trade_res = parallel_sim::best_of(trade_sims).await?;
 
         let sell_elapsed = timer.elapsed();
+        metrics::simulated("search", trade_path_count, sell_elapsed);
         debug!(coin_type = ?self.coin_type, result = %best_trade_res, ?buy_elapsed, ?sell_elapsed, "trial result");
 
         let profit = best_trade_res.profit();
@@ -488,6 +872,213 @@ impl TrialCtx {
 
         Ok(result)
     }
+
+    /// Cycles to trade along with `best`, each sized on its own (see `routes`). The candidates
+    /// disjoint from it that quote best at its size are sized.
+    pub async fn extra_routes(&self, best: &TrialResult) -> Vec<TrialResult> {
+        let config = routes::config();
+        if config.max_routes <= 1 {
+            return vec![];
+        }
+        let candidates = self
+            .route_cycles
+            .iter()
+            .filter(|cycle| cycle.is_disjoint(&best.trade_path))
+            .sorted_by_cached_key(|cycle| Reverse(quote::path_return_bps(cycle, best.amount_in)))
+            .take((config.max_routes - 1) * routes::CANDIDATES_PER_ROUTE)
+            .cloned()
+            .collect_vec();
+        if candidates.is_empty() {
+            return vec![];
+        }
+
+        let timer = Instant::now();
+        let sizes = join_all(candidates.iter().map(|cycle| async move {
+            let optimizer = AmountOptimizer::new(
+                self.defi.get_trader(),
+                cycle.clone(),
+                self.sender,
+                self.gas_coins.clone(),
+                self.sim_ctx.clone(),
+            );
+            optimizer.best_size().await
+        }))
+        .await;
+        let sized = candidates
+            .into_iter()
+            .zip(sizes)
+            .filter_map(|(cycle, size)| {
+                let (amount_in, profit) = size.ok()?;
+                (profit > 0).then(|| (cycle, amount_in, profit.min(u64::MAX as i128) as u64))
+            })
+            .collect_vec();
+
+        let best_route = SizedRoute {
+            pools: best.trade_path.object_ids().collect(),
+            amount_in: best.amount_in,
+            profit: best.profit,
+        };
+        let sized_routes = sized
+            .iter()
+            .map(|(cycle, amount_in, profit)| SizedRoute {
+                pools: cycle.object_ids().collect(),
+                amount_in: *amount_in,
+                profit: *profit,
+            })
+            .collect_vec();
+        let selected = routes::select(
+            &best_route,
+            &sized_routes,
+            config.max_routes,
+            config.route_capital_budget,
+        );
+        debug!(
+            sized = sized.len(),
+            selected = selected.len(),
+            elapsed = ?timer.elapsed(),
+            "sized extra routes"
+        );
+
+        selected
+            .into_iter()
+            .map(|i| {
+                let (cycle, amount_in, profit) = &sized[i];
+                TrialResult::new(&self.coin_type, *amount_in, *profit, cycle.clone(), 0)
+            })
+            .collect()
+    }
+
+    fn simulate(
+        &self,
+        paths: Vec<Path>,
+        amount_in: u64,
+        trade_type: TradeType,
+    ) -> mpsc::UnboundedReceiver<Result<PathTradeResult>> {
+        let request = SimRequest {
+            sender: self.sender,
+            amount_in,
+            trade_type,
+            gas_coins: self.gas_coins.clone(),
+            sim_ctx: self.sim_ctx.clone(),
+        };
+        parallel_sim::simulate_paths(self.defi.get_trader(), paths, request, self.cancel.child_token())
+    }
+}
+
+/// The input maximizing the locally quoted profit over those of `cycles` that are sized locally
+/// (see `is_locally_sized`), and that profit.
+fn local_best_size(cycles: &[Path], sim_ctx: &SimulateCtx) -> Option<(u64, u64)> {
+    cycles
+        .iter()
+        .filter(|cycle| is_locally_sized(cycle, sim_ctx))
+        .filter_map(|cycle| {
+            let amount_in = Curve::of(cycle)?.best_amount_in()?;
+            let profit = cp_math::path_amount_out(cycle, amount_in)?.checked_sub(amount_in)?;
+            Some((amount_in, profit))
+        })
+        .filter(|(_, profit)| *profit > 0)
+        .max_by_key(|(_, profit)| *profit)
+}
+
+/// Whether `cycle` is made only of constant-product pools the opportunity doesn't override: the
+/// reserves of those were read before it.
+fn is_locally_sized(cycle: &Path, sim_ctx: &SimulateCtx) -> bool {
+    let overridden: HashSet<ObjectID> = sim_ctx.override_objects.iter().map(|o| o.id()).collect();
+    !cycle.object_ids().any(|id| overridden.contains(&id)) && Curve::of(cycle).is_some()
+}
+
+/// Whether the simulation at a local size makes enough of the locally quoted `local_profit` to
+/// trust local math over the grid.
+fn confirms(trial_res: &TrialResult, local_profit: u64) -> bool {
+    trial_res.profit > 0 && trial_res.profit as f64 >= local_profit as f64 * MIN_LOCAL_PROFIT_RATIO
+}
+
+/// The grid points, `starting_grid` times 10 to 10^10.
+fn grid(starting_grid: u64) -> Result<Vec<u64>> {
+    (1..11)
+        .map(|inc| starting_grid.checked_mul(10u64.pow(inc)))
+        .collect::<Option<Vec<_>>>()
+        .context("Grid overflow")
+}
+
+/// Simulate `path` as a flashloan of `amount_in`, unprofitable trades as the default result.
+async fn simulate_path(
+    defi: &Defi,
+    path: &Path,
+    sender: SuiAddress,
+    amount_in: u64,
+    gas_coins: &[ObjectRef],
+    sim_ctx: &SimulateCtx,
+) -> Result<TrialResult> {
+    simulate_settled_path(defi, path, &[], sender, amount_in, gas_coins, sim_ctx).await
+}
+
+/// Like `simulate_path`, for a cycle that may settle outside the base coin: its gain is valued at
+/// the best quote of `sell_paths` into the base coin.
+async fn simulate_settled_path(
+    defi: &Defi,
+    path: &Path,
+    sell_paths: &[Path],
+    sender: SuiAddress,
+    amount_in: u64,
+    gas_coins: &[ObjectRef],
+    sim_ctx: &SimulateCtx,
+) -> Result<TrialResult> {
+    let timer = Instant::now();
+    let trade_res = defi
+        .find_best_path_exact_in(
+            std::slice::from_ref(path),
+            sender,
+            amount_in,
+            TradeType::Flashloan,
+            gas_coins,
+            sim_ctx,
+        )
+        .await?;
+    metrics::simulated("search", 1, timer.elapsed());
+
+    let profit = if base::is_base_coin(&trade_res.path.coin_in_type()) {
+        trade_res.profit()
+    } else {
+        settled_profit(&trade_res, sell_paths)
+    };
+    if profit <= 0 {
+        return Ok(TrialResult::default());
+    }
+    Ok(TrialResult::new(
+        &trade_res.path.coin_in_type(),
+        amount_in,
+        profit as u64,
+        trade_res.path,
+        trade_res.cache_misses,
+    ))
+}
+
+/// The versions of `versions` the pools of `paths` were read at.
+fn versions_of<'a>(
+    paths: impl IntoIterator<Item = &'a Path>,
+    versions: &HashMap<ObjectID, SequenceNumber>,
+) -> HashMap<ObjectID, SequenceNumber> {
+    paths
+        .into_iter()
+        .flat_map(|path| path.object_ids())
+        .filter_map(|id| versions.get(&id).map(|version| (id, *version)))
+        .collect()
+}
+
+/// Net profit in the base coin of a cycle settling in another coin, its gain sold through the best
+/// of `sell_paths`.
+fn settled_profit(trade_res: &PathTradeResult, sell_paths: &[Path]) -> i128 {
+    let gain = trade_res.amount_out.saturating_sub(trade_res.amount_in);
+    if gain == 0 {
+        return 0;
+    }
+    let value = sell_paths
+        .iter()
+        .filter_map(|path| quote::path_quote(path, gain))
+        .max()
+        .unwrap_or_default();
+    value as i128 - base::gas_in_base(trade_res.gas_cost)
 }
 
 #[derive(Debug, Default, Clone)]
@@ -497,6 +1088,8 @@ pub struct TrialResult {
     pub profit: u64,
     pub trade_path: Path,
     pub cache_misses: u64,
+    /// Estimated partner fee rebate (MIST), accrued off-chain and not part of `profit`
+    pub partner_rebate: u64,
 }
 
 impl PartialOrd for TrialResult {
@@ -513,12 +1106,15 @@ impl PartialEq for TrialResult {
 
 impl TrialResult {
     pub fn new(coin_type: &str, amount_in: u64, profit: u64, trade_path: Path, cache_misses: u64) -> Self {
+        let partner_rebate = trade_path.estimated_partner_rebate(amount_in, amount_in.saturating_add(profit));
+
         Self {
             coin_type: coin_type.to_string(),
             amount_in,
             profit,
             trade_path,
             cache_misses,
+            partner_rebate,
         }
     }
 }
@@ -551,7 +1147,10 @@ mod tests {
     use sui_types::base_types::SuiAddress;
 
     use super::*;
-    use crate::config::tests::{TEST_ATTACKER, TEST_HTTP_URL};
+    use crate::{
+        config::tests::{TestPool, TEST_ATTACKER, TEST_HTTP_URL},
+        defi::DexEnum,
+    };
 
     #[tokio::test]
     async fn test_find_best_trade_path() {
@@ -571,7 +1170,14 @@ mod tests {
         let sim_ctx = SimulateCtx::new(epoch, vec![]);
 
         let gas_coins = coin::get_gas_coin_refs(&sui, sender, None).await.unwrap();
-        let arb = Arb::new(TEST_HTTP_URL, Arc::new(simulator_pool)).await.unwrap();
+        let arb = Arb::new(
+            TEST_HTTP_URL,
+            Arc::new(simulator_pool),
+            GraphSettings::default(),
+            &FlashLoanConfig::default(),
+        )
+        .await
+        .unwrap();
         let coin_type = "0xce7ff77a83ea0cb6fd39bd8748e2ec89a3f41e8efdc3f4eb123e0ca37b184db2::buck::BUCK";
 
         let arb_res = arb
@@ -581,8 +1187,10 @@ mod tests {
                 None,
                 gas_coins,
                 sim_ctx.clone(),
-                true,
+                Sizing::Gss,
                 Source::Public,
+                &CancellationToken::new(),
+                None,
             )
             .await
             .unwrap();
@@ -601,4 +1209,40 @@ mod tests {
         let db_res = db_sim.simulate(tx_data, sim_ctx).await.unwrap();
         info!(?db_res, "🧀 DB simulation result");
     }
+
+    #[test]
+    fn test_split_routes_carry_their_own_versions() {
+        let cycle = |pools: [u8; 2]| {
+            let [buy, sell] = pools.map(ObjectID::from_single_byte);
+            Path::new(vec![
+                DexEnum::Other(Box::new(TestPool {
+                    id: buy,
+                    coin_in: "SUI",
+                    coin_out: "COIN",
+                })),
+                DexEnum::Other(Box::new(TestPool {
+                    id: sell,
+                    coin_in: "COIN",
+                    coin_out: "SUI",
+                })),
+            ])
+        };
+        let (best, extra) = (cycle([1, 2]), cycle([3, 4]));
+        // the snapshot the search ran on pinned every route's pools, and a pool of no route
+        let version = |pool: u8| SequenceNumber::from_u64(pool as u64 * 10);
+        let pinned: HashMap<ObjectID, SequenceNumber> = (1..=5)
+            .map(|pool| (ObjectID::from_single_byte(pool), version(pool)))
+            .collect();
+
+        let state_versions = versions_of([&best, &extra], &pinned);
+        assert_eq!(state_versions.len(), 4);
+        let split = versions_of([&extra], &state_versions);
+        assert_eq!(
+            split,
+            HashMap::from([
+                (ObjectID::from_single_byte(3), version(3)),
+                (ObjectID::from_single_byte(4), version(4))
+            ])
+        );
+    }
 }
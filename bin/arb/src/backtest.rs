@@ -0,0 +1,308 @@
+//! Replays a range of historical checkpoints through the arbitrage search.
+//!
+//! Every transaction of the range that emitted a swap event of an indexed protocol is a trigger,
+//! as it would have been for the live collector. The pools it touched are overridden with their
+//! state right after it, read back at their historical versions, and `Arb` searches from each
+//! coin it swapped. The rest of the chain is simulated at its latest state.
+//!
+//! With `--archive-dir`, each checkpoint's triggers are kept as `<sequence number>.json` in that
+//! directory: checkpoints found there are read from disk, the others are fetched once and written.
+//!
+//! Example:
+//! cargo run -r --bin arb backtest --from 96000000 --to 96000100 --archive-dir ./archive
+
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+};
+
+use clap::Parser;
+use dex_indexer::types::Protocol;
+use eyre::{bail, ensure, eyre, Result};
+use object_pool::ObjectPool;
+use serde::{Deserialize, Serialize};
+use simulator::{HttpSimulator, SimulateCtx, Simulator};
+use sui_json_rpc_types::{
+    CheckpointId, SuiEvent, SuiObjectDataOptions, SuiTransactionBlockEffects, SuiTransactionBlockEffectsAPI,
+    SuiTransactionBlockResponseOptions,
+};
+use sui_sdk::{SuiClient, SuiClientBuilder};
+use sui_types::{
+    base_types::{ObjectID, SuiAddress},
+    digests::TransactionDigest,
+    object::{Object, Owner},
+    transaction::{InputObjectKind, ObjectReadResult},
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+use crate::{
+    arb::{Arb, Sizing},
+    common::get_latest_epoch,
+    defi::{flash_lender::FlashLoanConfig, Dex},
+    graph_path_finder::GraphSettings,
+    replay::RecordedObject,
+    types::Source,
+    HttpConfig,
+};
+
+// limit of `multi_get_transactions_with_options`
+const MULTI_GET_LIMIT: usize = 50;
+
+/// The swap transactions of a checkpoint, with the pool states they left behind.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedCheckpoint {
+    pub sequence_number: u64,
+    pub timestamp_ms: u64,
+    pub transactions: Vec<ArchivedTx>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedTx {
+    pub digest: TransactionDigest,
+    pub events: Vec<SuiEvent>,
+    /// Shared objects the transaction mutated, at the version it wrote
+    pub override_objects: Vec<RecordedObject>,
+}
+
+#[derive(Clone, Debug, Parser)]
+pub struct Args {
+    /// First checkpoint to replay
+    #[arg(long)]
+    pub from: u64,
+
+    /// Last checkpoint to replay, inclusive
+    #[arg(long)]
+    pub to: u64,
+
+    /// Directory of archived checkpoints, read first and filled with the ones fetched
+    #[arg(long)]
+    pub archive_dir: Option<PathBuf>,
+
+    #[arg(long, default_value = "")]
+    pub sender: String,
+
+    /// Only search from this coin type
+    #[arg(long)]
+    pub coin_type: Option<String>,
+
+    #[command(flatten)]
+    pub http_config: HttpConfig,
+}
+
+#[derive(Debug, Default)]
+struct Summary {
+    checkpoints: u64,
+    swap_txs: usize,
+    searches: usize,
+    opportunities: usize,
+    total_profit: u128,
+    max_profit: u64,
+}
+
+pub async fn run(args: Args) -> Result<()> {
+    mev_logger::init_console_logger_with_directives(None, &["arb=info"]);
+    ensure!(args.from <= args.to, "--from {} is after --to {}", args.from, args.to);
+
+    let sender = SuiAddress::from_str(&args.sender).map_err(|e| eyre!(e))?;
+    let sui = SuiClientBuilder::default().build(&args.http_config.rpc_url).await?;
+    let rpc_url = args.http_config.rpc_url.clone();
+    let ipc_path = args.http_config.ipc_path.clone();
+    let simulator_pool = ObjectPool::new(1, move || {
+        tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(async { Box::new(HttpSimulator::new(&rpc_url, &ipc_path).await) as Box<dyn Simulator> })
+    });
+    let simulator: Arc<dyn Simulator> =
+        Arc::new(HttpSimulator::new(&args.http_config.rpc_url, &args.http_config.ipc_path).await);
+    let arb = Arb::new(
+        &args.http_config.rpc_url,
+        Arc::new(simulator_pool),
+        GraphSettings::default(),
+        &FlashLoanConfig::default(),
+    )
+    .await?;
+    let epoch = get_latest_epoch(&sui).await?;
+
+    if let Some(dir) = &args.archive_dir {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    let mut summary = Summary::default();
+    for seq in args.from..=args.to {
+        let checkpoint = load_checkpoint(&sui, seq, args.archive_dir.as_deref()).await?;
+        summary.checkpoints += 1;
+
+        for tx in &checkpoint.transactions {
+            summary.swap_txs += 1;
+            let override_objects = tx
+                .override_objects
+                .iter()
+                .map(RecordedObject::to_object_read_result)
+                .collect::<Result<Vec<_>>>()?;
+            let sim_ctx = SimulateCtx::new(epoch, override_objects);
+
+            for (coin, pool_id) in involved_coin_pools(&tx.events, &simulator).await {
+                if args.coin_type.as_ref().is_some_and(|c| c != &coin) {
+                    continue;
+                }
+
+                summary.searches += 1;
+                // backtests run to completion, deadlines long passed
+                let cancel = CancellationToken::new();
+                match arb
+                    .find_opportunity(
+                        sender,
+                        &coin,
+                        pool_id,
+                        vec![],
+                        sim_ctx.clone(),
+                        Sizing::default(),
+                        Source::Public,
+                        &cancel,
+                        None,
+                    )
+                    .await
+                {
+                    Ok(res) => {
+                        let trial = &res.best_trial_result;
+                        summary.opportunities += 1;
+                        summary.total_profit += trial.profit as u128;
+                        summary.max_profit = summary.max_profit.max(trial.profit);
+                        info!(
+                            checkpoint = seq,
+                            tx = %tx.digest,
+                            %coin,
+                            amount_in = trial.amount_in,
+                            profit = trial.profit,
+                            path = ?trial.trade_path.path.iter().map(|d| d.object_id()).collect::<Vec<_>>(),
+                            "💰 opportunity"
+                        );
+                    }
+                    Err(error) => debug!(checkpoint = seq, tx = %tx.digest, %coin, "no opportunity: {error:#}"),
+                }
+            }
+        }
+    }
+
+    if summary.swap_txs == 0 {
+        bail!("no swap transactions in checkpoints {}..={}", args.from, args.to);
+    }
+    info!(
+        checkpoints = summary.checkpoints,
+        swap_txs = summary.swap_txs,
+        searches = summary.searches,
+        opportunities = summary.opportunities,
+        total_profit = summary.total_profit,
+        max_profit = summary.max_profit,
+        "backtest done"
+    );
+
+    Ok(())
+}
+
+/// Checkpoint `seq` from the archive, else from the fullnode, archived if there is an archive.
+async fn load_checkpoint(sui: &SuiClient, seq: u64, archive_dir: Option<&Path>) -> Result<ArchivedCheckpoint> {
+    let path = archive_dir.map(|dir| dir.join(format!("{seq}.json")));
+    if let Some(path) = path.as_ref().filter(|path| path.exists()) {
+        let checkpoint: ArchivedCheckpoint = serde_json::from_slice(&std::fs::read(path)?)?;
+        ensure!(
+            checkpoint.sequence_number == seq,
+            "{:?} holds checkpoint {}",
+            path,
+            checkpoint.sequence_number
+        );
+        return Ok(checkpoint);
+    }
+
+    let checkpoint = fetch_checkpoint(sui, seq).await?;
+    if let Some(path) = path {
+        std::fs::write(&path, serde_json::to_vec(&checkpoint)?)?;
+    }
+    Ok(checkpoint)
+}
+
+async fn fetch_checkpoint(sui: &SuiClient, seq: u64) -> Result<ArchivedCheckpoint> {
+    let checkpoint = sui.read_api().get_checkpoint(CheckpointId::SequenceNumber(seq)).await?;
+    let options = SuiTransactionBlockResponseOptions::new().with_effects().with_events();
+
+    let mut transactions = vec![];
+    for digests in checkpoint.transactions.chunks(MULTI_GET_LIMIT) {
+        let resps = sui
+            .read_api()
+            .multi_get_transactions_with_options(digests.to_vec(), options.clone())
+            .await?;
+        for resp in resps {
+            let (Some(effects), Some(events)) = (resp.effects, resp.events) else {
+                continue;
+            };
+            let events: Vec<SuiEvent> = events
+                .data
+                .into_iter()
+                .filter(|event| Protocol::try_from(event).is_ok())
+                .collect();
+            if events.is_empty() {
+                continue;
+            }
+
+            transactions.push(ArchivedTx {
+                digest: resp.digest,
+                events,
+                override_objects: mutated_shared_objects(sui, &effects).await?,
+            });
+        }
+    }
+
+    Ok(ArchivedCheckpoint {
+        sequence_number: seq,
+        timestamp_ms: checkpoint.timestamp_ms,
+        transactions,
+    })
+}
+
+/// The shared objects `effects` mutated, read back at the version the transaction wrote.
+async fn mutated_shared_objects(sui: &SuiClient, effects: &SuiTransactionBlockEffects) -> Result<Vec<RecordedObject>> {
+    let mut objects = vec![];
+    for obj in effects.mutated() {
+        let Owner::Shared { initial_shared_version } = obj.owner else {
+            continue;
+        };
+        let (id, version, _) = obj.reference.to_object_ref();
+        let data = sui
+            .read_api()
+            .try_get_parsed_past_object(id, version, SuiObjectDataOptions::bcs_lossless())
+            .await?
+            .into_object()?;
+        let object: Object = data.try_into().map_err(|e| eyre!("object {}: {}", id, e))?;
+
+        let kind = InputObjectKind::SharedMoveObject {
+            id,
+            initial_shared_version,
+            mutable: true,
+        };
+        match RecordedObject::from_object_read_result(&ObjectReadResult::new(kind, object.into())) {
+            Some(recorded) => objects.push(recorded),
+            None => warn!(%id, version = %version, "skipping object that doesn't serialize"),
+        }
+    }
+    Ok(objects)
+}
+
+/// The coins swapped in `events`, with the pool they were swapped in, as the strategy finds them.
+async fn involved_coin_pools(
+    events: &[SuiEvent],
+    simulator: &Arc<dyn Simulator>,
+) -> HashSet<(String, Option<ObjectID>)> {
+    let mut coin_pools = HashSet::new();
+    for event in events {
+        let Ok(protocol) = Protocol::try_from(event) else {
+            continue;
+        };
+        if let Ok(swap_event) = protocol.sui_event_to_swap_event(event, simulator.clone()).await {
+            coin_pools.insert((swap_event.involved_coin_one_side(), swap_event.pool_id()));
+        }
+    }
+    coin_pools
+}
@@ -0,0 +1,388 @@
+//! End-to-end latency of the trading pipeline, stage by stage.
+//!
+//! Each event goes through the stages a live trigger does: receipt (reading the trigger and
+//! building its simulation context), path finding (the coin's buy and sell paths, joined into
+//! cycles), quoting (every cycle quoted in process), simulation (the best quoted cycles at
+//! `--amount-in`), building the PTB of the best one and submitting it, as a dry run against
+//! `--dry-run-url`. Events are replayed from a replay file, or are synthetic searches of one coin
+//! on the latest state. An event that fails a stage stops there and counts as an error of it.
+//! The report gives p50/p95/p99 per stage, so regressions across refactors show.
+//!
+//! Example:
+//! cargo run -r --bin arb bench --path ./replay/sui-arb.jsonl --events 200
+
+use std::{
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use clap::Parser;
+use eyre::{bail, ensure, eyre, Result};
+use itertools::Itertools;
+use object_pool::ObjectPool;
+use serde::Serialize;
+use simulator::{HttpSimulator, SimulateCtx, Simulator};
+use sui_json_rpc_types::{SuiExecutionStatus, SuiTransactionBlockEffectsAPI};
+use sui_sdk::SuiClientBuilder;
+use sui_types::base_types::{ObjectID, SuiAddress};
+use tracing::{debug, info};
+
+use crate::{
+    common::get_latest_epoch,
+    defi::{flash_lender::FlashLoanConfig, quote, Defi, Path, TradeType},
+    replay::ReplayReader,
+    types::Source,
+    HttpConfig, OutputFormat,
+};
+
+#[derive(Clone, Debug, Parser)]
+pub struct Args {
+    /// Replay file written by `start-bot --replay-record-path` to take the events from
+    #[arg(long, conflicts_with = "coin_type")]
+    pub path: Option<String>,
+
+    /// Run synthetic events searching this coin type on the latest state instead
+    #[arg(long)]
+    pub coin_type: Option<String>,
+
+    /// Pool the synthetic events swapped in; cycles through any pool of the coin when unset
+    #[arg(long, requires = "coin_type")]
+    pub pool_id: Option<String>,
+
+    /// Events to run
+    #[arg(long, default_value_t = 100)]
+    pub events: usize,
+
+    /// Input of the simulated trades, in base coin units
+    #[arg(long, default_value_t = 1_000_000_000)]
+    pub amount_in: u64,
+
+    /// Most cycles simulated per event, the best quoted first
+    #[arg(long, default_value_t = 16)]
+    pub max_cycles: usize,
+
+    /// Endpoint the built trades are dry run against, the RPC endpoint when unset
+    #[arg(long)]
+    pub dry_run_url: Option<String>,
+
+    #[arg(long, default_value = "")]
+    pub sender: String,
+
+    /// Log the report as text or print it as JSON
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    pub output: OutputFormat,
+
+    #[command(flatten)]
+    pub http_config: HttpConfig,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    Receipt,
+    PathFinding,
+    Quote,
+    Simulation,
+    Build,
+    Submission,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 6] = [
+        Stage::Receipt,
+        Stage::PathFinding,
+        Stage::Quote,
+        Stage::Simulation,
+        Stage::Build,
+        Stage::Submission,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Stage::Receipt => "receipt",
+            Stage::PathFinding => "path_finding",
+            Stage::Quote => "quote",
+            Stage::Simulation => "simulation",
+            Stage::Build => "build",
+            Stage::Submission => "submission",
+        }
+    }
+}
+
+/// The latencies of one stage, or of whole events.
+#[derive(Debug, Default)]
+struct Samples {
+    latencies: Vec<Duration>,
+    errors: usize,
+}
+
+impl Samples {
+    fn report(&mut self, stage: &'static str) -> StageReport {
+        self.latencies.sort();
+        StageReport {
+            stage,
+            samples: self.latencies.len(),
+            errors: self.errors,
+            p50_us: percentile(&self.latencies, 50.0).as_micros() as u64,
+            p95_us: percentile(&self.latencies, 95.0).as_micros() as u64,
+            p99_us: percentile(&self.latencies, 99.0).as_micros() as u64,
+            max_us: self.latencies.last().copied().unwrap_or_default().as_micros() as u64,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct StageReport {
+    pub stage: &'static str,
+    pub samples: usize,
+    pub errors: usize,
+    pub p50_us: u64,
+    pub p95_us: u64,
+    pub p99_us: u64,
+    pub max_us: u64,
+}
+
+/// Nearest-rank percentile of `sorted`, zero when empty.
+fn percentile(sorted: &[Duration], pct: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = (pct / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// One event to push through the pipeline.
+struct Event {
+    coin: String,
+    pool_id: Option<ObjectID>,
+    sim_ctx: SimulateCtx,
+}
+
+pub async fn run(args: Args) -> Result<()> {
+    mev_logger::init_console_logger_with_directives(None, &["arb=info"]);
+    ensure!(
+        args.output != OutputFormat::Csv,
+        "--output csv is only supported by pool-ids"
+    );
+    ensure!(args.events > 0, "--events must be positive");
+    ensure!(args.max_cycles > 0, "--max-cycles must be positive");
+
+    let sender = SuiAddress::from_str(&args.sender).map_err(|e| eyre!(e))?;
+    let pool_id = args
+        .pool_id
+        .as_deref()
+        .map(ObjectID::from_hex_literal)
+        .transpose()
+        .map_err(|e| eyre!(e))?;
+    let sui = SuiClientBuilder::default().build(&args.http_config.rpc_url).await?;
+    let dry_run_url = args.dry_run_url.as_ref().unwrap_or(&args.http_config.rpc_url);
+    let dry_run_sui = SuiClientBuilder::default().build(dry_run_url).await?;
+    let rpc_url = args.http_config.rpc_url.clone();
+    let ipc_path = args.http_config.ipc_path.clone();
+    let simulator_pool = ObjectPool::new(1, move || {
+        tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(async { Box::new(HttpSimulator::new(&rpc_url, &ipc_path).await) as Box<dyn Simulator> })
+    });
+    let defi = Defi::new(
+        &args.http_config.rpc_url,
+        Arc::new(simulator_pool),
+        &FlashLoanConfig::default(),
+    )
+    .await?;
+
+    let mut reader = match (&args.path, &args.coin_type) {
+        (Some(path), _) => Some(ReplayReader::open(path)?),
+        (None, Some(_)) => None,
+        (None, None) => bail!("either --path or --coin-type is required"),
+    };
+    let epoch = get_latest_epoch(&sui).await?;
+
+    let mut stages: Vec<Samples> = Stage::ALL.iter().map(|_| Samples::default()).collect();
+    let mut total = Samples::default();
+    for i in 0..args.events {
+        let started = Instant::now();
+
+        // receipt
+        let timer = Instant::now();
+        let event = match &mut reader {
+            Some(reader) => {
+                let Some(record) = reader.next() else {
+                    break;
+                };
+                record.and_then(|record| {
+                    Ok(Event {
+                        coin: record.trigger.coin.clone(),
+                        pool_id: record.trigger.pool_id,
+                        sim_ctx: record.trigger.sim_ctx()?,
+                    })
+                })
+            }
+            None => Ok(Event {
+                coin: args.coin_type.clone().unwrap_or_default(),
+                pool_id,
+                sim_ctx: SimulateCtx::new(epoch, vec![]),
+            }),
+        };
+        let Some(event) = timed(&mut stages, Stage::Receipt, timer, event) else {
+            total.errors += 1;
+            continue;
+        };
+
+        // path finding
+        let timer = Instant::now();
+        let cycles = async {
+            let buy_paths = defi.find_buy_paths(&event.coin).await?;
+            let sell_paths = defi.find_sell_paths(&event.coin).await?;
+            let cycles = buy_paths
+                .iter()
+                .cartesian_product(&sell_paths)
+                .filter(|(buy, sell)| {
+                    let through_pool = event.pool_id.is_none() ||
+                        buy.contains_pool(event.pool_id) ||
+                        sell.contains_pool(event.pool_id);
+                    buy.is_disjoint(sell) && through_pool
+                })
+                .map(|(buy, sell)| Path::new(buy.path.iter().chain(&sell.path).cloned().collect()))
+                .collect_vec();
+            ensure!(!cycles.is_empty(), "no cycles found for {}", event.coin);
+            Ok(cycles)
+        }
+        .await;
+        let Some(cycles) = timed(&mut stages, Stage::PathFinding, timer, cycles) else {
+            total.errors += 1;
+            continue;
+        };
+
+        // quoting, unquotable cycles last
+        let timer = Instant::now();
+        let cycles = cycles
+            .into_iter()
+            .sorted_by_cached_key(|cycle| std::cmp::Reverse(quote::path_return_bps(cycle, args.amount_in)))
+            .take(args.max_cycles)
+            .collect_vec();
+        timed(&mut stages, Stage::Quote, timer, Ok(()));
+
+        // simulation
+        let timer = Instant::now();
+        let best = defi
+            .find_best_path_exact_in(
+                &cycles,
+                sender,
+                args.amount_in,
+                TradeType::Flashloan,
+                &[],
+                &event.sim_ctx,
+            )
+            .await;
+        let Some(best) = timed(&mut stages, Stage::Simulation, timer, best) else {
+            total.errors += 1;
+            continue;
+        };
+
+        // PTB build
+        let timer = Instant::now();
+        let tx_data = defi
+            .build_final_tx_data(
+                sender,
+                args.amount_in,
+                &best.path,
+                vec![],
+                event.sim_ctx.epoch.gas_price,
+                Source::Public,
+            )
+            .await;
+        let Some(tx_data) = timed(&mut stages, Stage::Build, timer, tx_data) else {
+            total.errors += 1;
+            continue;
+        };
+
+        // submission, the trade is most likely unprofitable at a fixed size, reverting is fine
+        let timer = Instant::now();
+        let submitted = dry_run_sui
+            .read_api()
+            .dry_run_transaction_block(tx_data)
+            .await
+            .map_err(Into::into);
+        let Some(resp) = timed(&mut stages, Stage::Submission, timer, submitted) else {
+            total.errors += 1;
+            continue;
+        };
+        if let SuiExecutionStatus::Failure { error } = resp.effects.status() {
+            debug!(event = i, %error, "dry run reverted");
+        }
+
+        total.latencies.push(started.elapsed());
+    }
+
+    let mut reports = Stage::ALL
+        .iter()
+        .zip(&mut stages)
+        .map(|(stage, samples)| samples.report(stage.as_str()))
+        .collect_vec();
+    reports.push(total.report("total"));
+    if reports[0].samples == 0 {
+        bail!("no events run");
+    }
+
+    if args.output == OutputFormat::Json {
+        println!("{}", serde_json::to_string_pretty(&reports)?);
+        return Ok(());
+    }
+    for report in &reports {
+        info!(
+            stage = report.stage,
+            samples = report.samples,
+            errors = report.errors,
+            p50 = ?Duration::from_micros(report.p50_us),
+            p95 = ?Duration::from_micros(report.p95_us),
+            p99 = ?Duration::from_micros(report.p99_us),
+            max = ?Duration::from_micros(report.max_us),
+            "latency"
+        );
+    }
+
+    Ok(())
+}
+
+/// Record the latency of `stage` since `timer`, or its error; the value on success.
+fn timed<T>(stages: &mut [Samples], stage: Stage, timer: Instant, result: Result<T>) -> Option<T> {
+    let elapsed = timer.elapsed();
+    let samples = &mut stages[stage as usize];
+    match result {
+        Ok(value) => {
+            samples.latencies.push(elapsed);
+            Some(value)
+        }
+        Err(error) => {
+            debug!(stage = stage.as_str(), "failed: {error:#}");
+            samples.errors += 1;
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentiles() {
+        assert_eq!(percentile(&[], 50.0), Duration::ZERO);
+
+        let mut samples = Samples {
+            latencies: (1..=100).rev().map(Duration::from_millis).collect(),
+            errors: 3,
+        };
+        let report = samples.report("simulation");
+        assert_eq!(report.samples, 100);
+        assert_eq!(report.errors, 3);
+        assert_eq!(report.p50_us, 50_000);
+        assert_eq!(report.p95_us, 95_000);
+        assert_eq!(report.p99_us, 99_000);
+        assert_eq!(report.max_us, 100_000);
+
+        // a single sample is every percentile
+        assert_eq!(percentile(&[Duration::from_millis(7)], 99.0), Duration::from_millis(7));
+    }
+}
@@ -1,5 +1,13 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    str::FromStr,
+    sync::Arc,
+    time::Duration,
+};
+
 use burberry::{async_trait, Collector, CollectorStream};
-use eyre::Result;
+use dex_indexer::{normalize_coin_type, supported_protocols, types::Protocol, DexIndexer};
+use eyre::{eyre, OptionExt, Result};
 use fastcrypto::encoding::{Base64, Encoding};
 use futures::stream::StreamExt;
 use interprocess::local_socket::{
@@ -7,12 +15,22 @@ use interprocess::local_socket::{
     GenericNamespaced,
 };
 use serde::Deserialize;
-use sui_json_rpc_types::{SuiEvent, SuiTransactionBlockEffects};
-use sui_types::{effects::TransactionEffects, transaction::TransactionData};
-use tokio::{io::AsyncReadExt, pin, time};
-use tracing::{debug, error};
+use sui_json_rpc_types::{
+    CheckpointId, EventFilter, SuiEvent, SuiTransactionBlockEffects, SuiTransactionBlockEffectsAPI,
+    SuiTransactionBlockResponseOptions,
+};
+use sui_sdk::{SuiClient, SuiClientBuilder};
+use sui_types::{
+    base_types::ObjectID, digests::TransactionDigest, effects::TransactionEffects, transaction::TransactionData,
+    Identifier,
+};
+use tokio::{io::AsyncReadExt, pin, sync::broadcast::error::RecvError, time};
+use tracing::{debug, error, info, warn};
 
-use crate::types::Event;
+use crate::{
+    common::alerts::{self, Severity},
+    types::Event,
+};
 
 pub struct PublicTxCollector {
     path: String,
@@ -45,8 +63,13 @@ impl Collector<Event> for PublicTxCollector {
             loop {
                 tokio::select! {
                     result = conn.read_exact(&mut effects_len_buf) => {
-                        if result.is_err() {
+                        if let Err(error) = result {
                             debug!("Failed to read effects length");
+                            alerts::emit(
+                                Severity::Warning,
+                                "Tx socket disconnected",
+                                format!("{}: {}, reconnecting", self.path, error),
+                            );
                             conn = self.connect().await.expect("Failed to reconnect to tx socket");
                             continue;
                         }
@@ -156,6 +179,7 @@ impl Collector<Event> for PrivateTxCollector {
                     Ok(msg) => msg,
                     Err(e) => {
                         error!("Relay websocket error: {:?}", e);
+                        alerts::emit(Severity::Warning, "Relay websocket error", e.to_string());
                         continue;
                     }
                 };
@@ -176,3 +200,348 @@ impl Collector<Event> for PrivateTxCollector {
         Ok(Box::pin(stream))
     }
 }
+
+/// Emits pools the indexer discovers that hold any of the watched coins.
+pub struct NewPoolCollector {
+    indexer: Arc<DexIndexer>,
+    watched_coins: HashSet<String>,
+}
+
+impl NewPoolCollector {
+    pub fn new(indexer: Arc<DexIndexer>, watched_coins: &[String]) -> Self {
+        Self {
+            indexer,
+            watched_coins: watched_coins.iter().map(|c| normalize_coin_type(c)).collect(),
+        }
+    }
+}
+
+#[async_trait]
+impl Collector<Event> for NewPoolCollector {
+    fn name(&self) -> &str {
+        "NewPoolCollector"
+    }
+
+    async fn get_event_stream(&self) -> Result<CollectorStream<'_, Event>> {
+        let mut new_pools = self.indexer.subscribe_new_pools();
+
+        let stream = async_stream::stream! {
+            loop {
+                match new_pools.recv().await {
+                    Ok(pool) => {
+                        if pool.tokens.iter().any(|token| self.watched_coins.contains(&token.token_type)) {
+                            yield Event::NewPool(pool);
+                        }
+                    }
+                    Err(RecvError::Lagged(skipped)) => warn!(skipped, "new pool collector lagged"),
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        };
+
+        Ok(Box::pin(stream))
+    }
+}
+
+// the events of one transaction arrive back to back; a group is complete once this passes quietly
+const WS_GROUP_IDLE: Duration = Duration::from_millis(2);
+// older opportunities are long gone, a longer outage is not backfilled
+const WS_MAX_BACKFILL_CHECKPOINTS: u64 = 100;
+const WS_MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+// transactions remembered to drop the ones both the backfill and the subscription deliver
+const WS_RECENT_TXS: usize = 4096;
+// limit of `multi_get_transactions_with_options`
+const MULTI_GET_LIMIT: usize = 50;
+
+/// Swap events of the indexed protocols from a fullnode websocket subscription, as
+/// `Event::PublicTx` with the effects of their transaction. Reconnects with backoff and, once
+/// back, replays the checkpoints missed while disconnected.
+pub struct WsCollector {
+    rpc_url: String,
+    ws_url: String,
+}
+
+/// Where the subscription got to.
+#[derive(Default)]
+struct WsProgress {
+    last_checkpoint: Option<u64>,
+    recent: VecDeque<TransactionDigest>,
+    recent_set: HashSet<TransactionDigest>,
+}
+
+impl WsProgress {
+    /// Whether `digest` is seen for the first time.
+    fn first_seen(&mut self, digest: TransactionDigest) -> bool {
+        if !self.recent_set.insert(digest) {
+            return false;
+        }
+        self.recent.push_back(digest);
+        if self.recent.len() > WS_RECENT_TXS {
+            if let Some(oldest) = self.recent.pop_front() {
+                self.recent_set.remove(&oldest);
+            }
+        }
+        true
+    }
+
+    fn observe_checkpoint(&mut self, checkpoint: Option<u64>) {
+        if let Some(checkpoint) = checkpoint {
+            self.last_checkpoint = Some(self.last_checkpoint.map_or(checkpoint, |last| last.max(checkpoint)));
+        }
+    }
+}
+
+impl WsCollector {
+    pub fn new(rpc_url: &str, ws_url: &str) -> Self {
+        Self {
+            rpc_url: rpc_url.to_string(),
+            ws_url: ws_url.to_string(),
+        }
+    }
+
+    async fn connect(&self) -> Result<SuiClient> {
+        Ok(SuiClientBuilder::default()
+            .ws_url(&self.ws_url)
+            .build(&self.rpc_url)
+            .await?)
+    }
+
+    /// Any event of the modules emitting the swap events of the supported protocols.
+    fn swap_event_filter() -> Result<EventFilter> {
+        let mut filters = vec![];
+        for protocol in supported_protocols() {
+            let Some(event_type) = protocol.swap_event_type() else {
+                continue;
+            };
+            let mut parts = event_type.split("::");
+            let (package, module) = parts
+                .next()
+                .zip(parts.next())
+                .ok_or_else(|| eyre!("invalid swap event type {}", event_type))?;
+            filters.push(EventFilter::MoveEventModule {
+                package: ObjectID::from_hex_literal(package)?,
+                module: Identifier::from_str(module)?,
+            });
+        }
+        Ok(EventFilter::Any(filters))
+    }
+
+    /// The `PublicTx` event of the transaction that emitted `events`.
+    async fn public_tx(
+        sui: &SuiClient,
+        digest: TransactionDigest,
+        events: Vec<SuiEvent>,
+        progress: &mut WsProgress,
+    ) -> Result<Option<Event>> {
+        if !progress.first_seen(digest) {
+            return Ok(None);
+        }
+        let resp = sui
+            .read_api()
+            .get_transaction_with_options(digest, SuiTransactionBlockResponseOptions::new().with_effects())
+            .await?;
+        progress.observe_checkpoint(resp.checkpoint);
+        let effects = resp.effects.ok_or_eyre("transaction without effects")?;
+        Ok(Some(Event::PublicTx(effects, events)))
+    }
+
+    /// The `PublicTx` events of checkpoint `seq`, swaps only.
+    async fn backfill_checkpoint(sui: &SuiClient, seq: u64, progress: &mut WsProgress) -> Result<Vec<Event>> {
+        let checkpoint = sui.read_api().get_checkpoint(CheckpointId::SequenceNumber(seq)).await?;
+        let options = SuiTransactionBlockResponseOptions::new().with_effects().with_events();
+
+        let mut out = vec![];
+        for digests in checkpoint.transactions.chunks(MULTI_GET_LIMIT) {
+            let resps = sui
+                .read_api()
+                .multi_get_transactions_with_options(digests.to_vec(), options.clone())
+                .await?;
+            for resp in resps {
+                let (Some(effects), Some(events)) = (resp.effects, resp.events) else {
+                    continue;
+                };
+                let events: Vec<SuiEvent> = events
+                    .data
+                    .into_iter()
+                    .filter(|event| Protocol::try_from(event).is_ok())
+                    .collect();
+                if !events.is_empty() && progress.first_seen(*effects.transaction_digest()) {
+                    out.push(Event::PublicTx(effects, events));
+                }
+            }
+        }
+        progress.observe_checkpoint(Some(seq));
+        Ok(out)
+    }
+
+    /// Checkpoints after the last one seen, at most `WS_MAX_BACKFILL_CHECKPOINTS` of them.
+    async fn missed_checkpoints(
+        sui: &SuiClient,
+        progress: &mut WsProgress,
+    ) -> Result<std::ops::RangeInclusive<u64>> {
+        let latest = sui.read_api().get_latest_checkpoint_sequence_number().await?;
+        let from = match progress.last_checkpoint {
+            Some(last) => (last + 1).max(latest.saturating_sub(WS_MAX_BACKFILL_CHECKPOINTS - 1)),
+            // nothing was missed before the first connection
+            None => {
+                progress.observe_checkpoint(Some(latest));
+                latest + 1
+            }
+        };
+        Ok(from..=latest)
+    }
+}
+
+#[async_trait]
+impl Collector<Event> for WsCollector {
+    fn name(&self) -> &str {
+        "WsCollector"
+    }
+
+    async fn get_event_stream(&self) -> Result<CollectorStream<'_, Event>> {
+        let filter = Self::swap_event_filter()?;
+
+        let stream = async_stream::stream! {
+            let mut progress = WsProgress::default();
+            let mut reconnect_delay = Duration::from_secs(1);
+
+            loop {
+                let sui = match self.connect().await {
+                    Ok(sui) => sui,
+                    Err(error) => {
+                        warn!(?error, ?reconnect_delay, "websocket connect failed, retrying");
+                        // the error can carry the url and its credentials
+                        alerts::emit(
+                            Severity::Warning,
+                            "Websocket disconnected",
+                            format!("connect failed, retrying in {:?}", reconnect_delay),
+                        );
+                        time::sleep(reconnect_delay).await;
+                        reconnect_delay = (reconnect_delay * 2).min(WS_MAX_RECONNECT_DELAY);
+                        continue;
+                    }
+                };
+                let subscription = match sui.event_api().subscribe_event(filter.clone()).await {
+                    Ok(subscription) => subscription,
+                    Err(error) => {
+                        warn!(?error, ?reconnect_delay, "event subscription failed, retrying");
+                        time::sleep(reconnect_delay).await;
+                        reconnect_delay = (reconnect_delay * 2).min(WS_MAX_RECONNECT_DELAY);
+                        continue;
+                    }
+                };
+                pin!(subscription);
+                reconnect_delay = Duration::from_secs(1);
+
+                // subscribed first, so nothing falls between the backfill and the live events
+                match Self::missed_checkpoints(&sui, &mut progress).await {
+                    Ok(missed) => {
+                        if !missed.is_empty() {
+                            info!(?missed, "backfilling missed checkpoints");
+                        }
+                        for seq in missed {
+                            match Self::backfill_checkpoint(&sui, seq, &mut progress).await {
+                                Ok(events) => {
+                                    for event in events {
+                                        yield event;
+                                    }
+                                }
+                                Err(error) => warn!(seq, ?error, "checkpoint backfill failed"),
+                            }
+                        }
+                    }
+                    Err(error) => warn!(?error, "latest checkpoint unavailable, nothing backfilled"),
+                }
+
+                // events of the transaction being collected
+                let mut group: Option<(TransactionDigest, Vec<SuiEvent>)> = None;
+                loop {
+                    let next = tokio::select! {
+                        next = subscription.next() => next,
+                        _ = time::sleep(WS_GROUP_IDLE), if group.is_some() => {
+                            let (digest, events) = group.take().expect("group checked above");
+                            match Self::public_tx(&sui, digest, events, &mut progress).await {
+                                Ok(Some(event)) => yield event,
+                                Ok(None) => {}
+                                Err(error) => warn!(%digest, ?error, "effects of a swap unavailable"),
+                            }
+                            continue;
+                        }
+                    };
+                    let event = match next {
+                        Some(Ok(event)) => event,
+                        Some(Err(error)) => {
+                            warn!(?error, "event subscription broke, reconnecting");
+                            alerts::emit(
+                                Severity::Warning,
+                                "Websocket disconnected",
+                                format!("event subscription broke: {}", error),
+                            );
+                            break;
+                        }
+                        None => {
+                            warn!("event subscription closed, reconnecting");
+                            alerts::emit(Severity::Warning, "Websocket disconnected", "event subscription closed");
+                            break;
+                        }
+                    };
+                    if Protocol::try_from(&event).is_err() {
+                        continue;
+                    }
+
+                    let digest = event.id.tx_digest;
+                    match &mut group {
+                        Some((current, events)) if *current == digest => events.push(event),
+                        _ => {
+                            if let Some((digest, events)) = group.replace((digest, vec![event])) {
+                                match Self::public_tx(&sui, digest, events, &mut progress).await {
+                                    Ok(Some(event)) => yield event,
+                                    Ok(None) => {}
+                                    Err(error) => warn!(%digest, ?error, "effects of a swap unavailable"),
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        };
+
+        Ok(Box::pin(stream))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ws_progress_drops_repeated_txs() {
+        let mut progress = WsProgress::default();
+        let digest = TransactionDigest::random();
+        assert!(progress.first_seen(digest));
+        assert!(!progress.first_seen(digest));
+
+        for _ in 0..WS_RECENT_TXS {
+            progress.first_seen(TransactionDigest::random());
+        }
+        // forgotten once enough newer transactions went by
+        assert!(progress.first_seen(digest));
+
+        progress.observe_checkpoint(Some(10));
+        progress.observe_checkpoint(Some(7));
+        progress.observe_checkpoint(None);
+        assert_eq!(progress.last_checkpoint, Some(10));
+    }
+
+    #[test]
+    fn test_swap_event_filter_covers_supported_protocols() {
+        let EventFilter::Any(filters) = WsCollector::swap_event_filter().unwrap() else {
+            panic!("expected an Any filter");
+        };
+        let with_swaps = supported_protocols()
+            .iter()
+            .filter(|protocol| protocol.swap_event_type().is_some())
+            .count();
+        assert_eq!(filters.len(), with_swaps);
+    }
+}
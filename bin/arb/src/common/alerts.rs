@@ -0,0 +1,360 @@
+//! Operator alerts, pushed to Telegram chats and webhooks.
+//!
+//! Channels come from the `[alerts]` section of the config file, each with the lowest severity it
+//! wants:
+//!
+//! ```toml
+//! [alerts]
+//! failure_threshold = 3        # consecutive failed executions before a critical alert
+//! min_balance = 1000000000     # MIST; an account below it raises a warning
+//!
+//! [[alerts.channels]]
+//! kind = "telegram"
+//! bot_token = "123:abc"
+//! chat_id = "-1001234"
+//! min_severity = "warning"
+//!
+//! [[alerts.channels]]
+//! kind = "webhook"
+//! url = "https://discord.com/api/webhooks/..."
+//! ```
+//!
+//! A webhook gets the alert as JSON with a `content` field, which is what Discord displays. The
+//! executor reports landed trades (info) and runs of failed ones (critical), the collectors and
+//! the endpoint prober lost connections (warning, critical once every endpoint lags), and
+//! `watch_balances` accounts running low on SUI (warning). The same warning or critical alert is
+//! sent at most once per `ALERT_COOLDOWN`. Alerts are read at startup only.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        OnceLock,
+    },
+    time::{Duration, Instant},
+};
+
+use burberry::executor::telegram_message::{escape, MessageBuilder, TelegramMessageDispatcher};
+use eyre::{ensure, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sui_sdk::{SuiClient, SUI_COIN_TYPE};
+use sui_types::base_types::SuiAddress;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tracing::{info, warn};
+use utils::coin;
+
+use crate::{defi::base, BUILD_VERSION};
+
+const ALERT_COOLDOWN: Duration = Duration::from_secs(300);
+const DEFAULT_FAILURE_THRESHOLD: u32 = 3;
+
+static ALERTER: OnceLock<Alerter> = OnceLock::new();
+static CONSECUTIVE_FAILURES: AtomicU32 = AtomicU32::new(0);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    #[default]
+    Info,
+    Warning,
+    Critical,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            Severity::Info => "INFO",
+            Severity::Warning => "WARNING",
+            Severity::Critical => "CRITICAL",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AlertSection {
+    #[serde(default)]
+    pub channels: Vec<AlertChannel>,
+    /// Consecutive reverted or rejected executions that raise a critical alert
+    pub failure_threshold: Option<u32>,
+    /// SUI balance (in MIST) under which an account raises a warning
+    pub min_balance: Option<u64>,
+}
+
+#[derive(Clone, PartialEq, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum AlertChannel {
+    Telegram {
+        bot_token: String,
+        chat_id: String,
+        thread_id: Option<String>,
+        #[serde(default)]
+        min_severity: Severity,
+    },
+    Webhook {
+        url: String,
+        #[serde(default)]
+        min_severity: Severity,
+    },
+}
+
+// bot tokens and webhook urls are credentials
+impl fmt::Debug for AlertChannel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AlertChannel::Telegram {
+                chat_id,
+                thread_id,
+                min_severity,
+                ..
+            } => f
+                .debug_struct("Telegram")
+                .field("bot_token", &"[REDACTED]")
+                .field("chat_id", chat_id)
+                .field("thread_id", thread_id)
+                .field("min_severity", min_severity)
+                .finish(),
+            AlertChannel::Webhook { min_severity, .. } => f
+                .debug_struct("Webhook")
+                .field("url", &"[REDACTED]")
+                .field("min_severity", min_severity)
+                .finish(),
+        }
+    }
+}
+
+impl AlertChannel {
+    fn min_severity(&self) -> Severity {
+        match self {
+            AlertChannel::Telegram { min_severity, .. } | AlertChannel::Webhook { min_severity, .. } => *min_severity,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Alert {
+    pub severity: Severity,
+    pub title: String,
+    pub text: String,
+}
+
+struct Alerter {
+    alerts: UnboundedSender<Alert>,
+    failure_threshold: u32,
+}
+
+/// Start delivering alerts to the channels of `section`. Without channels every alert is dropped.
+/// Alerting starts once per process.
+pub fn init(section: &AlertSection) -> Result<()> {
+    if section.channels.is_empty() {
+        return Ok(());
+    }
+    let (alerts, rx) = mpsc::unbounded_channel();
+    let alerter = Alerter {
+        alerts,
+        failure_threshold: section.failure_threshold.unwrap_or(DEFAULT_FAILURE_THRESHOLD),
+    };
+    ensure!(ALERTER.set(alerter).is_ok(), "alerting already started");
+    info!(channels = ?section.channels, "alerting started");
+    tokio::spawn(dispatch(section.channels.clone(), rx));
+    Ok(())
+}
+
+pub fn emit(severity: Severity, title: impl Into<String>, text: impl Into<String>) {
+    let Some(alerter) = ALERTER.get() else {
+        return;
+    };
+    let _ = alerter.alerts.send(Alert {
+        severity,
+        title: title.into(),
+        text: text.into(),
+    });
+}
+
+/// A trade landed; `pnl` is its realized profit in base coin units.
+pub fn trade_landed(digest: &str, pnl: i128) {
+    CONSECUTIVE_FAILURES.store(0, Ordering::Relaxed);
+    if pnl > 0 {
+        let profit = base::format_amount(pnl.min(u64::MAX as i128) as u64);
+        emit(
+            Severity::Info,
+            "Arbitrage executed",
+            format!("profit {}, tx {}", profit, digest),
+        );
+    }
+}
+
+/// An execution reverted or never made it on chain.
+pub fn execution_failed(error: &str) {
+    let failures = CONSECUTIVE_FAILURES.fetch_add(1, Ordering::Relaxed) + 1;
+    let Some(alerter) = ALERTER.get() else {
+        return;
+    };
+    if failures == alerter.failure_threshold {
+        emit(
+            Severity::Critical,
+            "Repeated execution failures",
+            format!("{} executions failed in a row, the last with: {}", failures, error),
+        );
+    }
+}
+
+/// Every `interval`, warn about each of `accounts` holding less than `min_balance` MIST, once
+/// until it recovers.
+pub async fn watch_balances(sui: SuiClient, accounts: Vec<SuiAddress>, min_balance: u64, interval: Duration) {
+    let mut low = vec![false; accounts.len()];
+    let mut interval = tokio::time::interval(interval);
+    loop {
+        interval.tick().await;
+        for (account, low) in accounts.iter().zip(low.iter_mut()) {
+            let balance = match sui
+                .coin_read_api()
+                .get_balance(*account, Some(SUI_COIN_TYPE.to_string()))
+                .await
+            {
+                Ok(balance) => balance.total_balance.min(u64::MAX as u128) as u64,
+                Err(error) => {
+                    warn!(%account, ?error, "failed to read the balance");
+                    continue;
+                }
+            };
+            let is_low = balance < min_balance;
+            if is_low && !*low {
+                emit(
+                    Severity::Warning,
+                    format!("Low balance: {}", account),
+                    format!(
+                        "{} holds {}, below {}",
+                        account,
+                        coin::format_sui_with_symbol(balance),
+                        coin::format_sui_with_symbol(min_balance)
+                    ),
+                );
+            }
+            *low = is_low;
+        }
+    }
+}
+
+async fn dispatch(channels: Vec<AlertChannel>, mut alerts: UnboundedReceiver<Alert>) {
+    let telegram = TelegramMessageDispatcher::new_without_error_report();
+    let http = reqwest::Client::new();
+    let mut last_sent: HashMap<String, Instant> = HashMap::new();
+
+    while let Some(alert) = alerts.recv().await {
+        if alert.severity > Severity::Info {
+            let now = Instant::now();
+            if last_sent
+                .get(&alert.title)
+                .is_some_and(|sent| now.duration_since(*sent) < ALERT_COOLDOWN)
+            {
+                continue;
+            }
+            last_sent.insert(alert.title.clone(), now);
+        }
+
+        for channel in channels.iter().filter(|c| alert.severity >= c.min_severity()) {
+            match channel {
+                AlertChannel::Telegram {
+                    bot_token,
+                    chat_id,
+                    thread_id,
+                    ..
+                } => {
+                    let mut message = MessageBuilder::new()
+                        .bot_token(bot_token)
+                        .chat_id(chat_id)
+                        .text(telegram_text(&alert))
+                        .disable_link_preview(true);
+                    if let Some(thread_id) = thread_id {
+                        message = message.thread_id(thread_id);
+                    }
+                    telegram.send_message(message.build()).await;
+                }
+                AlertChannel::Webhook { url, .. } => {
+                    let sent = http
+                        .post(url)
+                        .json(&webhook_body(&alert))
+                        .send()
+                        .await
+                        .and_then(|resp| resp.error_for_status());
+                    if let Err(error) = sent {
+                        warn!(error = %error.without_url(), title = %alert.title, "alert webhook failed");
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn telegram_text(alert: &Alert) -> String {
+    format!(
+        "*{}* {}\n{}\n*Version*: `{}`",
+        alert.severity,
+        escape(&alert.title),
+        escape(&alert.text),
+        BUILD_VERSION
+    )
+}
+
+fn webhook_body(alert: &Alert) -> serde_json::Value {
+    json!({
+        "severity": alert.severity,
+        "title": alert.title,
+        "text": alert.text,
+        "version": BUILD_VERSION,
+        "content": format!("**[{}] {}**\n{}", alert.severity, alert.title, alert.text),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alert_section() {
+        let section: AlertSection = toml::from_str(
+            r#"
+            failure_threshold = 5
+
+            [[channels]]
+            kind = "telegram"
+            bot_token = "123:secret"
+            chat_id = "-100"
+            min_severity = "warning"
+
+            [[channels]]
+            kind = "webhook"
+            url = "https://discord.com/api/webhooks/1/secret"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(section.failure_threshold, Some(5));
+        assert_eq!(section.channels[0].min_severity(), Severity::Warning);
+        assert_eq!(section.channels[1].min_severity(), Severity::Info);
+        assert!(Severity::Critical > Severity::Warning);
+
+        let debug = format!("{:?}", section);
+        assert!(!debug.contains("secret"));
+
+        assert!(toml::from_str::<AlertSection>("[[channels]]\nkind = \"pager\"").is_err());
+    }
+
+    #[test]
+    fn test_webhook_body() {
+        let alert = Alert {
+            severity: Severity::Critical,
+            title: "RPC endpoints".to_string(),
+            text: "all RPC endpoints are down".to_string(),
+        };
+        let body = webhook_body(&alert);
+        assert_eq!(body["severity"], "critical");
+        assert_eq!(
+            body["content"],
+            "**[CRITICAL] RPC endpoints**\nall RPC endpoints are down"
+        );
+    }
+}
@@ -0,0 +1,57 @@
+//! Shio bid sizing.
+//!
+//! A shio opportunity is won by the highest bid, paid out of the arb's profit. The bot bids
+//! `SUI_SHIO_BID_RATIO` of the expected profit, more on pools rival bots trade (see
+//! `competition`); what is left is its margin for the gas and for the profit falling short on
+//! chain.
+
+use std::sync::OnceLock;
+
+use clap::Parser;
+use eyre::Result;
+
+static CONFIG: OnceLock<BidConfig> = OnceLock::new();
+
+#[derive(Clone, Debug, PartialEq, Parser)]
+pub struct BidConfig {
+    /// Share of the expected profit bid on a shio opportunity, between 0 and 1
+    #[arg(long, env = "SUI_SHIO_BID_RATIO", default_value_t = 0.9)]
+    pub shio_bid_ratio: f64,
+}
+
+impl Default for BidConfig {
+    fn default() -> Self {
+        Self { shio_bid_ratio: 0.9 }
+    }
+}
+
+/// Install the bid configuration; fails if a different one was installed or the default read.
+pub fn init(config: BidConfig) -> Result<()> {
+    utils::set_once(&CONFIG, config, "bid config")
+}
+
+fn config() -> &'static BidConfig {
+    CONFIG.get_or_init(BidConfig::default)
+}
+
+/// The bid on a shio opportunity expected to profit `profit`, with `boost` added to the ratio.
+pub fn bid_amount(profit: u64, boost: f64) -> u64 {
+    bid_for(profit, (config().shio_bid_ratio + boost).min(1.0))
+}
+
+fn bid_for(profit: u64, ratio: f64) -> u64 {
+    // the float product rounds, never bid the whole profit unless asked to
+    ((profit as f64 * ratio) as u64).min(profit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bid_for() {
+        assert_eq!(bid_for(1_000_000, 0.9), 900_000);
+        assert_eq!(bid_for(1_000_000, 0.0), 0);
+        assert_eq!(bid_for(u64::MAX, 1.0), u64::MAX);
+    }
+}
@@ -0,0 +1,40 @@
+//! Per-thread scratch buffers for the trigger hot path.
+//!
+//! Workers handle triggers one at a time on their own thread, so a thread-local buffer is never
+//! shared and keeps its capacity between triggers.
+
+use std::cell::RefCell;
+
+use eyre::Result;
+use serde::Serialize;
+
+thread_local! {
+    static BCS_BUF: RefCell<Vec<u8>> = RefCell::new(Vec::with_capacity(16 * 1024));
+}
+
+/// BCS-serialize `value` into the thread's scratch buffer and hand the bytes to `f`.
+pub fn with_bcs_bytes<T: Serialize + ?Sized, R>(value: &T, f: impl FnOnce(&[u8]) -> R) -> Result<R> {
+    BCS_BUF.with(|buf| {
+        let mut buf = buf.borrow_mut();
+        buf.clear();
+        bcs::serialize_into(&mut *buf, value)?;
+        Ok(f(&buf))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use utils::alloc_counter::count_allocations;
+
+    use super::*;
+
+    #[test]
+    fn test_bcs_buffer_is_reused() {
+        let value = vec![7u64; 512];
+        with_bcs_bytes(&value, |bytes| assert_eq!(bytes, bcs::to_bytes(&value).unwrap())).unwrap();
+
+        let (len, allocs) = count_allocations(|| with_bcs_bytes(&value, |bytes| bytes.len()).unwrap());
+        assert_eq!(len, bcs::serialized_size(&value).unwrap());
+        assert_eq!(allocs, 0);
+    }
+}
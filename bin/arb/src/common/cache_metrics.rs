@@ -0,0 +1,17 @@
+//! Periodic usage report of the bounded in-memory caches (target `cache_metrics`).
+
+use std::time::Duration;
+
+use utils::cache::log_cache_stats;
+
+use crate::{common::object_refs, defi};
+
+pub async fn run(interval: Duration) {
+    loop {
+        tokio::time::sleep(interval).await;
+
+        let mut stats = defi::cache_stats().await;
+        stats.push(object_refs::cache_stats());
+        log_cache_stats(&stats);
+    }
+}
@@ -0,0 +1,53 @@
+//! Cancellation of opportunity processing.
+//!
+//! Every arb item carries a `CancellationToken` from trigger intake to submission. The strategy
+//! cancels it when a newer trigger for the same coin arrives, and Shio items cancel themselves at
+//! their deadline. Dropping an in-flight future also drops the `JoinSet`s it owns, which aborts the
+//! simulations still queued behind it.
+
+use std::{future::Future, time::Duration};
+
+use eyre::{bail, Result};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+/// Run `fut` unless `cancel` fires first.
+pub async fn until_cancelled<T>(cancel: &CancellationToken, fut: impl Future<Output = Result<T>>) -> Result<T> {
+    tokio::select! {
+        biased;
+        _ = cancel.cancelled() => bail!("cancelled"),
+        res = fut => res,
+    }
+}
+
+/// Cancel `cancel` at `deadline_ms` (unix millis). Abort the returned handle once the work is done.
+pub fn cancel_at(cancel: &CancellationToken, deadline_ms: u64) -> JoinHandle<()> {
+    let cancel = cancel.clone();
+    let remaining = Duration::from_millis(deadline_ms.saturating_sub(utils::current_time_ms()));
+    tokio::spawn(async move {
+        tokio::time::sleep(remaining).await;
+        cancel.cancel();
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_until_cancelled_aborts_pending_work() {
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+
+        let res = until_cancelled(&cancel, std::future::pending::<Result<()>>()).await;
+        assert!(res.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_at_past_deadline() {
+        let cancel = CancellationToken::new();
+        cancel_at(&cancel, utils::current_time_ms() - 1).await.unwrap();
+
+        assert!(cancel.is_cancelled());
+    }
+}
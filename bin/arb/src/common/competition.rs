@@ -0,0 +1,368 @@
+//! Rival arbitrage bots.
+//!
+//! The strategy reports every executed transaction that swaps around a cycle (the coins it swaps
+//! in are the coins it swaps out) through at least two pools, sent by an address that isn't ours,
+//! as a rival arb, and then the gas price it paid once read. Rival arbs are kept per pool for
+//! `COMPETITION_WINDOW`. When one of our trades ends stale or reverted and a rival arb landed on
+//! one of its shared objects within `RACE_WINDOW` before, that rival beat us there.
+//!
+//! Before submitting, a worker asks `assess` about its path:
+//!
+//! * contested: rival arbs landed on its pools within the window. A public trade pays the rivals'
+//!   median gas price plus one, at most `SUI_COMPETITION_MAX_GAS_MULTIPLIER` times the reference
+//!   gas price, and a shio bid adds `SUI_COMPETITION_BID_BOOST` to its bid ratio;
+//! * lost: one rival beat us `SUI_COMPETITION_SKIP_LOSSES` times in a row on a pool of the path
+//!   within the window, and the trade is skipped. Landing a trade on the pool clears the run.
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+use clap::Parser;
+use dex_indexer::types::SwapEvent;
+use eyre::Result;
+use sui_types::base_types::{ObjectID, SuiAddress};
+
+use super::outcomes::Outcome;
+
+const COMPETITION_WINDOW: Duration = Duration::from_secs(600);
+const RACE_WINDOW: Duration = Duration::from_secs(5);
+// gas prices kept per rival
+const GAS_PRICE_SAMPLES: usize = 32;
+
+static CONFIG: OnceLock<CompetitionConfig> = OnceLock::new();
+static STATE: OnceLock<Mutex<State>> = OnceLock::new();
+
+#[derive(Clone, Debug, PartialEq, Parser)]
+pub struct CompetitionConfig {
+    /// Added to the shio bid ratio on pools rival bots trade, between 0 and 1
+    #[arg(long, env = "SUI_COMPETITION_BID_BOOST", default_value_t = 0.05)]
+    pub competition_bid_boost: f64,
+
+    /// Highest gas price a public trade on a contested pool pays, as a multiple of the reference
+    /// gas price; 1 never outbids rivals on gas
+    #[arg(long, env = "SUI_COMPETITION_MAX_GAS_MULTIPLIER", default_value_t = 2.0)]
+    pub competition_max_gas_multiplier: f64,
+
+    /// Skip a path once a single rival beat us this many times in a row on one of its pools; 0
+    /// never skips
+    #[arg(long, env = "SUI_COMPETITION_SKIP_LOSSES", default_value_t = 3)]
+    pub competition_skip_losses: usize,
+}
+
+impl Default for CompetitionConfig {
+    fn default() -> Self {
+        Self {
+            competition_bid_boost: 0.05,
+            competition_max_gas_multiplier: 2.0,
+            competition_skip_losses: 3,
+        }
+    }
+}
+
+/// Install the competition configuration, before rival arbs are tracked.
+pub fn init(config: CompetitionConfig) -> Result<()> {
+    utils::set_once(&CONFIG, config, "competition config")
+}
+
+fn config() -> &'static CompetitionConfig {
+    CONFIG.get_or_init(CompetitionConfig::default)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Assessment {
+    /// No rival traded these pools lately
+    Clear,
+    /// Rivals land arbs on these pools; `gas_price` is the median they paid, once known
+    Contested {
+        rivals: Vec<SuiAddress>,
+        gas_price: Option<u64>,
+    },
+    /// `rival` beat us `losses` times in a row on `pool`
+    Lost {
+        rival: SuiAddress,
+        pool: ObjectID,
+        losses: usize,
+    },
+}
+
+struct RivalArb {
+    rival: SuiAddress,
+    at: Instant,
+}
+
+struct LossRun {
+    rival: SuiAddress,
+    losses: usize,
+    last: Instant,
+}
+
+#[derive(Default)]
+struct State {
+    // pool => rival arbs on it, oldest first
+    arbs: HashMap<ObjectID, VecDeque<RivalArb>>,
+    // rival => the gas prices it paid, oldest first
+    gas_prices: HashMap<SuiAddress, VecDeque<u64>>,
+    // pool => the rival that keeps beating us there
+    loss_runs: HashMap<ObjectID, LossRun>,
+}
+
+impl State {
+    fn expire(&mut self, now: Instant) {
+        for arbs in self.arbs.values_mut() {
+            while arbs.front().is_some_and(|arb| now - arb.at >= COMPETITION_WINDOW) {
+                arbs.pop_front();
+            }
+        }
+        self.arbs.retain(|_, arbs| !arbs.is_empty());
+        self.loss_runs.retain(|_, run| now - run.last < COMPETITION_WINDOW);
+        let active: HashSet<SuiAddress> = self.arbs.values().flatten().map(|arb| arb.rival).collect();
+        self.gas_prices.retain(|rival, _| active.contains(rival));
+    }
+
+    fn observe_arb(&mut self, rival: SuiAddress, pools: impl IntoIterator<Item = ObjectID>, now: Instant) {
+        self.expire(now);
+        for pool in pools {
+            self.arbs
+                .entry(pool)
+                .or_default()
+                .push_back(RivalArb { rival, at: now });
+        }
+    }
+
+    fn observe_gas_price(&mut self, rival: SuiAddress, gas_price: u64) {
+        let prices = self.gas_prices.entry(rival).or_default();
+        prices.push_back(gas_price);
+        while prices.len() > GAS_PRICE_SAMPLES {
+            prices.pop_front();
+        }
+    }
+
+    fn settled(&mut self, objects: &[ObjectID], outcome: Outcome, now: Instant) {
+        match outcome {
+            Outcome::LandedProfitable | Outcome::LandedUnprofitable => {
+                for object in objects {
+                    self.loss_runs.remove(object);
+                }
+            }
+            Outcome::Stale | Outcome::Reverted => {
+                for object in objects {
+                    // the rival that landed last on the object right before our trade failed
+                    let Some(winner) = self
+                        .arbs
+                        .get(object)
+                        .and_then(|arbs| arbs.back())
+                        .filter(|arb| now - arb.at < RACE_WINDOW)
+                        .map(|arb| arb.rival)
+                    else {
+                        continue;
+                    };
+                    let run = self.loss_runs.entry(*object).or_insert(LossRun {
+                        rival: winner,
+                        losses: 0,
+                        last: now,
+                    });
+                    if run.rival != winner {
+                        run.rival = winner;
+                        run.losses = 0;
+                    }
+                    run.losses += 1;
+                    run.last = now;
+                }
+            }
+            Outcome::SubmissionError => {}
+        }
+    }
+
+    fn assess(&mut self, pools: &[ObjectID], skip_losses: usize, now: Instant) -> Assessment {
+        self.expire(now);
+        if skip_losses > 0 {
+            let lost = pools.iter().find_map(|pool| {
+                self.loss_runs
+                    .get(pool)
+                    .filter(|run| run.losses >= skip_losses)
+                    .map(|run| (*pool, run))
+            });
+            if let Some((pool, run)) = lost {
+                return Assessment::Lost {
+                    rival: run.rival,
+                    pool,
+                    losses: run.losses,
+                };
+            }
+        }
+
+        let mut rivals: Vec<SuiAddress> = pools
+            .iter()
+            .filter_map(|pool| self.arbs.get(pool))
+            .flatten()
+            .map(|arb| arb.rival)
+            .collect();
+        if rivals.is_empty() {
+            return Assessment::Clear;
+        }
+        rivals.sort();
+        rivals.dedup();
+        let gas_price = rivals
+            .iter()
+            .filter_map(|rival| median(self.gas_prices.get(rival)?))
+            .max();
+        Assessment::Contested { rivals, gas_price }
+    }
+}
+
+fn median(values: &VecDeque<u64>) -> Option<u64> {
+    if values.is_empty() {
+        return None;
+    }
+    let mut sorted: Vec<u64> = values.iter().copied().collect();
+    sorted.sort_unstable();
+    Some(sorted[sorted.len() / 2])
+}
+
+fn state() -> &'static Mutex<State> {
+    STATE.get_or_init(Default::default)
+}
+
+/// Whether the swaps of one transaction close a cycle through at least two pools.
+pub fn is_arb(swaps: &[SwapEvent]) -> bool {
+    let pools: HashSet<ObjectID> = swaps.iter().filter_map(|swap| swap.pool_id()).collect();
+    if pools.len() < 2 {
+        return false;
+    }
+    let coins_in: HashSet<&String> = swaps.iter().flat_map(|swap| &swap.coins_in).collect();
+    let coins_out: HashSet<&String> = swaps.iter().flat_map(|swap| &swap.coins_out).collect();
+    coins_in == coins_out
+}
+
+/// `rival` landed an arb through `pools`.
+pub fn observe_arb(rival: SuiAddress, pools: impl IntoIterator<Item = ObjectID>) {
+    state().lock().unwrap().observe_arb(rival, pools, Instant::now());
+}
+
+/// `rival` paid `gas_price` for an arb.
+pub fn observe_gas_price(rival: SuiAddress, gas_price: u64) {
+    state().lock().unwrap().observe_gas_price(rival, gas_price);
+}
+
+/// One of our trades over the shared `objects` ended in `outcome`.
+pub fn settled(objects: &[ObjectID], outcome: Outcome) {
+    state().lock().unwrap().settled(objects, outcome, Instant::now());
+}
+
+/// How contested a path over `pools` is.
+pub fn assess(pools: impl IntoIterator<Item = ObjectID>) -> Assessment {
+    let pools: Vec<ObjectID> = pools.into_iter().collect();
+    state()
+        .lock()
+        .unwrap()
+        .assess(&pools, config().competition_skip_losses, Instant::now())
+}
+
+/// What to add to the shio bid ratio on `pools`.
+pub fn bid_boost(pools: impl IntoIterator<Item = ObjectID>) -> f64 {
+    match assess(pools) {
+        Assessment::Clear => 0.0,
+        _ => config().competition_bid_boost,
+    }
+}
+
+/// The gas price a public trade assessed `assessment` should pay instead of `gas_price`, if it
+/// should outbid rivals; never above the configured multiple of `reference_gas_price`.
+pub fn priority_gas_price(assessment: &Assessment, gas_price: u64, reference_gas_price: u64) -> Option<u64> {
+    let Assessment::Contested {
+        gas_price: Some(rival_price),
+        ..
+    } = assessment
+    else {
+        return None;
+    };
+    let cap = (reference_gas_price as f64 * config().competition_max_gas_multiplier) as u64;
+    let price = rival_price.saturating_add(1).min(cap);
+    (price > gas_price).then_some(price)
+}
+
+#[cfg(test)]
+mod tests {
+    use dex_indexer::types::Protocol;
+
+    use super::*;
+
+    fn swap(pool: u8, coin_in: &str, coin_out: &str) -> SwapEvent {
+        SwapEvent {
+            protocol: Protocol::Cetus,
+            pool: Some(ObjectID::from_single_byte(pool)),
+            coins_in: vec![coin_in.to_string()],
+            coins_out: vec![coin_out.to_string()],
+            amounts_in: vec![1],
+            amounts_out: vec![1],
+        }
+    }
+
+    #[test]
+    fn test_is_arb() {
+        assert!(is_arb(&[swap(1, "A", "B"), swap(2, "B", "A")]));
+        assert!(is_arb(&[swap(1, "A", "B"), swap(2, "B", "C"), swap(3, "C", "A")]));
+        // a multi-hop swap doesn't come back
+        assert!(!is_arb(&[swap(1, "A", "B"), swap(2, "B", "C")]));
+        assert!(!is_arb(&[swap(1, "A", "B")]));
+    }
+
+    #[test]
+    fn test_rival_beats_us_until_skipped() {
+        let rival = SuiAddress::random_for_testing_only();
+        let pool = ObjectID::from_single_byte(1);
+        let mut state = State::default();
+        let now = Instant::now();
+        assert_eq!(state.assess(&[pool], 2, now), Assessment::Clear);
+
+        state.observe_arb(rival, [pool], now);
+        state.observe_gas_price(rival, 1_000);
+        state.observe_gas_price(rival, 2_000);
+        state.observe_gas_price(rival, 3_000);
+        assert_eq!(
+            state.assess(&[pool], 2, now),
+            Assessment::Contested {
+                rivals: vec![rival],
+                gas_price: Some(2_000)
+            }
+        );
+
+        state.settled(&[pool], Outcome::Stale, now + Duration::from_secs(1));
+        assert!(matches!(
+            state.assess(&[pool], 2, now + Duration::from_secs(1)),
+            Assessment::Contested { .. }
+        ));
+        state.observe_arb(rival, [pool], now + Duration::from_secs(10));
+        state.settled(&[pool], Outcome::Reverted, now + Duration::from_secs(11));
+        assert_eq!(
+            state.assess(&[pool], 2, now + Duration::from_secs(11)),
+            Assessment::Lost { rival, pool, losses: 2 }
+        );
+
+        // landing clears the run, and the rivals leave with the window
+        state.settled(&[pool], Outcome::LandedProfitable, now + Duration::from_secs(12));
+        assert!(matches!(
+            state.assess(&[pool], 2, now + Duration::from_secs(12)),
+            Assessment::Contested { .. }
+        ));
+        assert_eq!(
+            state.assess(&[pool], 2, now + Duration::from_secs(10) + COMPETITION_WINDOW),
+            Assessment::Clear
+        );
+    }
+
+    #[test]
+    fn test_priority_gas_price() {
+        let contested = Assessment::Contested {
+            rivals: vec![],
+            gas_price: Some(1_500),
+        };
+        assert_eq!(priority_gas_price(&contested, 750, 750), Some(1_500));
+        assert_eq!(priority_gas_price(&contested, 750, 1_000), Some(1_501));
+        assert_eq!(priority_gas_price(&contested, 2_000, 1_000), None);
+        assert_eq!(priority_gas_price(&Assessment::Clear, 750, 750), None);
+    }
+}
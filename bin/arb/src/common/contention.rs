@@ -0,0 +1,131 @@
+//! Shared-object congestion tracking.
+//!
+//! Sui cancels transactions that touch a shared object more than its per-commit budget allows. The
+//! executor reports every cancellation for congestion with the objects it names; a path with an
+//! object that cancelled `CONTENTION_FAILURES` of our trades within `CONTENTION_WINDOW` is
+//! contended, and workers trade it in smaller sequential parts instead of one large transaction.
+//! Parts wait for the previous one to land through `watch`, and account leases for their trade to
+//! settle.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+use sui_types::{base_types::ObjectID, digests::TransactionDigest};
+use tokio::sync::oneshot;
+
+use super::outcomes::Outcome;
+
+const CONTENTION_WINDOW: Duration = Duration::from_secs(60);
+const CONTENTION_FAILURES: usize = 2;
+
+#[derive(Default)]
+struct State {
+    // object => when it cancelled our trades, oldest first
+    failures: HashMap<ObjectID, VecDeque<Instant>>,
+    // submitted trades => whoever waits for their outcome
+    watchers: HashMap<TransactionDigest, Vec<oneshot::Sender<Outcome>>>,
+}
+
+impl State {
+    fn congested(&mut self, objects: impl IntoIterator<Item = ObjectID>, now: Instant) {
+        for object in objects {
+            let failures = self.failures.entry(object).or_default();
+            failures.push_back(now);
+            while failures.len() > CONTENTION_FAILURES {
+                failures.pop_front();
+            }
+        }
+    }
+
+    fn contended(&mut self, objects: impl IntoIterator<Item = ObjectID>, now: Instant) -> Vec<ObjectID> {
+        self.failures
+            .retain(|_, failures| failures.back().is_some_and(|t| now - *t < CONTENTION_WINDOW));
+        objects
+            .into_iter()
+            .filter(|object| {
+                self.failures.get(object).is_some_and(|failures| {
+                    failures.len() >= CONTENTION_FAILURES && failures.iter().all(|t| now - *t < CONTENTION_WINDOW)
+                })
+            })
+            .collect()
+    }
+}
+
+static STATE: OnceLock<Mutex<State>> = OnceLock::new();
+
+fn state() -> &'static Mutex<State> {
+    STATE.get_or_init(Default::default)
+}
+
+pub fn is_congestion(error: &str) -> bool {
+    error.contains("SharedObjectCongestion")
+}
+
+/// The objects a congestion error names, e.g. `CongestedObjects([0x5c1d..])`.
+pub fn congested_objects(error: &str) -> Vec<ObjectID> {
+    let Some(start) = error.find("CongestedObjects") else {
+        return vec![];
+    };
+    error[start..]
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|token| token.starts_with("0x"))
+        .filter_map(|token| ObjectID::from_hex_literal(token).ok())
+        .collect()
+}
+
+/// A trade was cancelled for congestion on `objects`.
+pub fn congested(objects: impl IntoIterator<Item = ObjectID>) {
+    state().lock().unwrap().congested(objects, Instant::now());
+}
+
+/// Those of `objects` that keep cancelling our trades.
+pub fn contended(objects: impl IntoIterator<Item = ObjectID>) -> Vec<ObjectID> {
+    state().lock().unwrap().contended(objects, Instant::now())
+}
+
+/// Receive the outcome of the trade submitted as `digest`.
+pub fn watch(digest: TransactionDigest) -> oneshot::Receiver<Outcome> {
+    let (tx, rx) = oneshot::channel();
+    state().lock().unwrap().watchers.entry(digest).or_default().push(tx);
+    rx
+}
+
+/// The trade submitted as `digest` ended in `outcome`.
+pub fn settled(digest: &TransactionDigest, outcome: Outcome) {
+    for watcher in state().lock().unwrap().watchers.remove(digest).unwrap_or_default() {
+        let _ = watcher.send(outcome);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repeated_congestion_makes_an_object_contended() {
+        let error =
+            "ExecutionCancelledDueToSharedObjectCongestion { congested_objects: CongestedObjects([0x5, 0xa1]) }";
+        assert!(is_congestion(error));
+        let objects = congested_objects(error);
+        assert_eq!(
+            objects,
+            vec![
+                ObjectID::from_hex_literal("0x5").unwrap(),
+                ObjectID::from_hex_literal("0xa1").unwrap()
+            ]
+        );
+
+        let mut state = State::default();
+        let now = Instant::now();
+        state.congested(objects.clone(), now);
+        // one cancellation is bad luck
+        assert!(state.contended(objects.clone(), now).is_empty());
+        state.congested([objects[0]], now);
+        assert_eq!(state.contended(objects.clone(), now), vec![objects[0]]);
+        // and it wears off
+        assert!(state.contended(objects, now + CONTENTION_WINDOW).is_empty());
+    }
+}
@@ -0,0 +1,194 @@
+//! Simulation-only execution (target `dry_run_metrics`).
+//!
+//! With `--dry-run`, every transaction the bot would submit is simulated against current state
+//! instead. The simulated outcome, profit and gas are logged per trade and feed the outcome and
+//! experiment statistics as if the trade had landed; a running PnL summary is logged every `run`
+//! interval. Shio bids are simulated on their own, without the opportunity they backrun, so their
+//! figures are only indicative.
+
+use std::{
+    sync::{Arc, Mutex, OnceLock},
+    time::Duration,
+};
+
+use eyre::Result;
+use object_pool::ObjectPool;
+use simulator::{SimEpoch, SimulateCtx, Simulator};
+use sui_json_rpc_types::SuiTransactionBlockEffectsAPI;
+use sui_sdk::SuiClient;
+use sui_types::{
+    base_types::SuiAddress,
+    digests::TransactionDigest,
+    object::Owner,
+    transaction::{TransactionData, TransactionDataAPI},
+};
+use tracing::info;
+
+use super::{get_latest_epoch, outcomes::Outcome};
+use crate::defi::base;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Simulated {
+    pub outcome: Outcome,
+    /// In base coin units, net of gas and of the bid; the gas paid when it reverted
+    pub pnl: i128,
+    pub gas_used: i64,
+}
+
+/// Simulates would-be trades through the simulator pool.
+pub struct DryRun {
+    simulator_pool: Arc<ObjectPool<Box<dyn Simulator>>>,
+    sui: SuiClient,
+    epoch: Mutex<SimEpoch>,
+}
+
+impl DryRun {
+    pub async fn new(simulator_pool: Arc<ObjectPool<Box<dyn Simulator>>>, sui: SuiClient) -> Result<Self> {
+        let epoch = get_latest_epoch(&sui).await?;
+        Ok(Self {
+            simulator_pool,
+            sui,
+            epoch: Mutex::new(epoch),
+        })
+    }
+
+    /// Simulate `tx_data`, paying `bid_amount` out of its profit, then log and record the result.
+    pub async fn simulate(&self, tx_data: TransactionData, bid_amount: u64) -> Result<Simulated> {
+        let (digest, sender) = (tx_data.digest(), tx_data.sender());
+        let epoch = self.epoch().await?;
+        let resp = self
+            .simulator_pool
+            .get()
+            .simulate(tx_data, SimulateCtx::new(epoch, vec![]))
+            .await?;
+
+        let gas_used = resp.effects.gas_cost_summary().net_gas_usage();
+        let (outcome, profit) = Outcome::of_effects(&resp.effects, &resp.balance_changes, sender);
+        let pnl = match profit {
+            Some(profit) => profit - bid_amount as i128,
+            None => -base::gas_in_base(gas_used),
+        };
+        let simulated = Simulated { outcome, pnl, gas_used };
+        record(&simulated);
+
+        let balance_changes: Vec<_> = resp
+            .balance_changes
+            .iter()
+            .filter(|bc| bc.owner == Owner::AddressOwner(sender))
+            .map(|bc| format!("{} {}", bc.amount, bc.coin_type))
+            .collect();
+        info!(
+            target: "dry_run_metrics",
+            %digest,
+            %outcome,
+            pnl = %format_pnl(pnl),
+            gas_used,
+            bid_amount,
+            ?balance_changes,
+            mutated = resp.effects.mutated().len(),
+            "dry run: {:?}",
+            resp.effects.status()
+        );
+        Ok(simulated)
+    }
+
+    async fn epoch(&self) -> Result<SimEpoch> {
+        let epoch = *self.epoch.lock().unwrap();
+        if !epoch.is_stale() {
+            return Ok(epoch);
+        }
+        let epoch = get_latest_epoch(&self.sui).await?;
+        *self.epoch.lock().unwrap() = epoch;
+        Ok(epoch)
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Summary {
+    simulated: u64,
+    profitable: u64,
+    reverted: u64,
+    // failed to simulate at all
+    errors: u64,
+    pnl: i128,
+    gas_used: i64,
+}
+
+impl Summary {
+    fn record(&mut self, simulated: &Simulated) {
+        self.simulated += 1;
+        if simulated.pnl > 0 {
+            self.profitable += 1;
+        }
+        if simulated.outcome == Outcome::Reverted {
+            self.reverted += 1;
+        }
+        self.pnl += simulated.pnl;
+        self.gas_used += simulated.gas_used;
+    }
+}
+
+fn format_pnl(pnl: i128) -> String {
+    let amount = base::format_amount(pnl.unsigned_abs().min(u64::MAX as u128) as u64);
+    if pnl < 0 {
+        format!("-{}", amount)
+    } else {
+        amount
+    }
+}
+
+static SUMMARY: OnceLock<Mutex<Summary>> = OnceLock::new();
+
+fn summary() -> &'static Mutex<Summary> {
+    SUMMARY.get_or_init(Default::default)
+}
+
+fn record(simulated: &Simulated) {
+    summary().lock().unwrap().record(simulated);
+}
+
+/// A would-be trade that failed to simulate.
+pub fn failed(digest: &TransactionDigest, sender: SuiAddress, error: &eyre::Report) {
+    summary().lock().unwrap().errors += 1;
+    info!(target: "dry_run_metrics", %digest, %sender, "dry run failed: {:#}", error);
+}
+
+pub async fn run(interval: Duration) {
+    loop {
+        tokio::time::sleep(interval).await;
+        let summary = *summary().lock().unwrap();
+        info!(
+            target: "dry_run_metrics",
+            simulated = summary.simulated,
+            profitable = summary.profitable,
+            reverted = summary.reverted,
+            errors = summary.errors,
+            gas_used = summary.gas_used,
+            "dry run PnL: {}",
+            format_pnl(summary.pnl)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_adds_up() {
+        let mut summary = Summary::default();
+        summary.record(&Simulated {
+            outcome: Outcome::LandedProfitable,
+            pnl: 500,
+            gas_used: 100,
+        });
+        summary.record(&Simulated {
+            outcome: Outcome::Reverted,
+            pnl: -100,
+            gas_used: 100,
+        });
+        assert_eq!((summary.simulated, summary.profitable, summary.reverted), (2, 1, 1));
+        assert_eq!(summary.pnl, 400);
+        assert_eq!(summary.gas_used, 200);
+    }
+}
@@ -0,0 +1,302 @@
+//! Latency-based RPC endpoint selection.
+//!
+//! Every configured endpoint is probed on an interval for its round-trip latency, its latest
+//! checkpoint and how old that checkpoint is by the wall clock. Latency-critical calls (transaction
+//! submission and the final object refresh before signing) go to the `fastest` endpoint that is
+//! up, within `rpc_max_lag` checkpoints of the highest one seen and whose checkpoint is younger
+//! than `max_checkpoint_age_ms`; background work takes the others in turn, so it doesn't queue in
+//! front of them. Per-endpoint latency, checkpoint lag and age and error counts are logged under
+//! the `rpc_metrics` target after every probe round.
+//!
+//! When no endpoint has a fresh checkpoint, every node we read from is behind the chain: quotes
+//! built on it are stale and trades on them lose to whoever sees the real state. The endpoints are
+//! then `is_stale` until one catches up, trading pauses, and both transitions are sent as a
+//! `StaleDataEvent` for alerting.
+
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use clap::Parser;
+use eyre::{ensure, Result};
+use futures::future::join_all;
+use sui_json_rpc_types::CheckpointId;
+use sui_sdk::{SuiClient, SuiClientBuilder};
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::{info, warn};
+
+use crate::redact_url;
+
+// weight of the newest latency sample
+const LATENCY_EWMA_ALPHA: f64 = 0.3;
+
+#[derive(Clone, Debug, Parser)]
+pub struct RpcConfig {
+    /// More RPC endpoints next to SUI_RPC_URL; latency-critical calls go to the fastest one
+    #[arg(long, env = "SUI_EXTRA_RPC_URLS", value_delimiter = ',')]
+    pub extra_rpc_urls: Vec<String>,
+
+    /// Milliseconds between endpoint probes
+    #[arg(long, env = "SUI_RPC_PROBE_INTERVAL_MS", default_value_t = 1000)]
+    pub rpc_probe_interval_ms: u64,
+
+    /// Endpoints further behind the highest checkpoint than this are not picked for submission
+    #[arg(long, env = "SUI_RPC_MAX_LAG", default_value_t = 3)]
+    pub rpc_max_lag: u64,
+
+    /// Endpoints whose latest checkpoint is older than this (in milliseconds) serve stale data;
+    /// trading pauses while all of them do
+    #[arg(long, env = "SUI_MAX_CHECKPOINT_AGE_MS", default_value_t = 5000)]
+    pub max_checkpoint_age_ms: u64,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct EndpointStats {
+    latency_ms: Option<f64>,
+    checkpoint: u64,
+    // wall clock minus the latest checkpoint's timestamp, when probed
+    checkpoint_age_ms: u64,
+    up: bool,
+    errors: u64,
+}
+
+/// A change of the stale-data guard.
+#[derive(Debug, Clone)]
+pub enum StaleDataEvent {
+    /// No endpoint has a fresh checkpoint, trading is paused
+    Stale { freshest_age_ms: Option<u64> },
+    /// `endpoint` caught up, trading resumes
+    Fresh { endpoint: String, checkpoint_age_ms: u64 },
+}
+
+impl fmt::Display for StaleDataEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StaleDataEvent::Stale {
+                freshest_age_ms: Some(age),
+            } => write!(f, "all RPC endpoints lag, freshest checkpoint is {}ms old", age),
+            StaleDataEvent::Stale { freshest_age_ms: None } => write!(f, "all RPC endpoints are down"),
+            StaleDataEvent::Fresh {
+                endpoint,
+                checkpoint_age_ms,
+            } => write!(f, "{} caught up, checkpoint is {}ms old", endpoint, checkpoint_age_ms),
+        }
+    }
+}
+
+struct Endpoint {
+    url: String,
+    sui: SuiClient,
+    stats: Mutex<EndpointStats>,
+}
+
+pub struct RpcEndpoints {
+    endpoints: Vec<Endpoint>,
+    max_lag: u64,
+    max_checkpoint_age_ms: u64,
+    fastest: AtomicUsize,
+    next_background: AtomicUsize,
+    stale: AtomicBool,
+}
+
+impl RpcEndpoints {
+    /// The first of `urls` is the primary, used until the first probe round.
+    pub async fn new(urls: &[String], max_lag: u64, max_checkpoint_age_ms: u64) -> Result<Self> {
+        ensure!(!urls.is_empty(), "no RPC endpoint");
+        let mut endpoints = Vec::with_capacity(urls.len());
+        for url in urls {
+            endpoints.push(Endpoint {
+                url: url.clone(),
+                sui: SuiClientBuilder::default().build(url).await?,
+                stats: Mutex::new(EndpointStats::default()),
+            });
+        }
+
+        Ok(Self {
+            endpoints,
+            max_lag,
+            max_checkpoint_age_ms,
+            fastest: AtomicUsize::new(0),
+            next_background: AtomicUsize::new(0),
+            stale: AtomicBool::new(false),
+        })
+    }
+
+    /// Whether every endpoint served stale data in the last probe round.
+    pub fn is_stale(&self) -> bool {
+        self.stale.load(Ordering::Relaxed)
+    }
+
+    /// Client for latency-critical calls.
+    pub fn fastest(&self) -> SuiClient {
+        self.endpoints[self.fastest.load(Ordering::Relaxed)].sui.clone()
+    }
+
+    /// Client for background work: every endpoint but the fastest, in turn.
+    pub fn background(&self) -> SuiClient {
+        let fastest = self.fastest.load(Ordering::Relaxed);
+        let others: Vec<&Endpoint> = (0..self.endpoints.len())
+            .filter(|&i| i != fastest)
+            .map(|i| &self.endpoints[i])
+            .collect();
+        if others.is_empty() {
+            return self.fastest();
+        }
+        let next = self.next_background.fetch_add(1, Ordering::Relaxed);
+        others[next % others.len()].sui.clone()
+    }
+
+    pub async fn run(&self, interval: Duration, events: UnboundedSender<StaleDataEvent>) {
+        info!(
+            endpoints = ?self.endpoints.iter().map(|e| redact_url(&e.url)).collect::<Vec<_>>(),
+            "rpc endpoint probing started"
+        );
+        let mut interval = tokio::time::interval(interval);
+
+        loop {
+            interval.tick().await;
+            if let Some(event) = self.probe().await {
+                let _ = events.send(event);
+            }
+        }
+    }
+
+    async fn probe(&self) -> Option<StaleDataEvent> {
+        let results = join_all(self.endpoints.iter().map(|endpoint| async move {
+            let start = Instant::now();
+            let read_api = endpoint.sui.read_api();
+            let checkpoint = match read_api.get_latest_checkpoint_sequence_number().await {
+                Ok(checkpoint) => checkpoint,
+                Err(error) => return (Err(error), start.elapsed()),
+            };
+            let latency = start.elapsed();
+            let timestamp_ms = read_api
+                .get_checkpoint(CheckpointId::SequenceNumber(checkpoint))
+                .await
+                .map(|c| c.timestamp_ms);
+            (timestamp_ms.map(|ts| (checkpoint, ts)), latency)
+        }))
+        .await;
+
+        let stats: Vec<EndpointStats> = self
+            .endpoints
+            .iter()
+            .zip(results)
+            .map(|(endpoint, (checkpoint, latency))| {
+                let mut stats = endpoint.stats.lock().unwrap();
+                match checkpoint {
+                    Ok((checkpoint, timestamp_ms)) => {
+                        let sample = latency.as_secs_f64() * 1000.0;
+                        stats.latency_ms = Some(match stats.latency_ms {
+                            Some(ewma) => ewma + LATENCY_EWMA_ALPHA * (sample - ewma),
+                            None => sample,
+                        });
+                        stats.checkpoint = checkpoint;
+                        stats.checkpoint_age_ms = utils::current_time_ms().saturating_sub(timestamp_ms);
+                        stats.up = true;
+                    }
+                    Err(_) => {
+                        stats.up = false;
+                        stats.errors += 1;
+                    }
+                }
+                *stats
+            })
+            .collect();
+
+        let selected = select_fastest(&stats, self.max_lag, self.max_checkpoint_age_ms);
+        // with nothing fresh, keep the last pick; trading is paused anyway
+        let fastest = selected.unwrap_or_else(|| self.fastest.load(Ordering::Relaxed));
+        let previous = self.fastest.swap(fastest, Ordering::Relaxed);
+        if previous != fastest {
+            info!(
+                from = %redact_url(&self.endpoints[previous].url),
+                to = %redact_url(&self.endpoints[fastest].url),
+                "fastest rpc endpoint changed"
+            );
+        }
+
+        let highest = stats.iter().map(|s| s.checkpoint).max().unwrap_or_default();
+        for (i, (endpoint, stats)) in self.endpoints.iter().zip(&stats).enumerate() {
+            info!(
+                target: "rpc_metrics",
+                endpoint = %redact_url(&endpoint.url),
+                up = stats.up,
+                latency_ms = ?stats.latency_ms.map(|ms| ms.round()),
+                lag = highest.saturating_sub(stats.checkpoint),
+                checkpoint_age_ms = stats.checkpoint_age_ms,
+                errors = stats.errors,
+                fastest = i == fastest,
+            );
+        }
+
+        let was_stale = self.stale.swap(selected.is_none(), Ordering::Relaxed);
+        match (was_stale, selected) {
+            (false, None) => {
+                let freshest_age_ms = stats.iter().filter(|s| s.up).map(|s| s.checkpoint_age_ms).min();
+                let event = StaleDataEvent::Stale { freshest_age_ms };
+                warn!(%event, "stale data, trading paused");
+                Some(event)
+            }
+            (true, Some(fastest)) => {
+                let event = StaleDataEvent::Fresh {
+                    endpoint: redact_url(&self.endpoints[fastest].url),
+                    checkpoint_age_ms: stats[fastest].checkpoint_age_ms,
+                };
+                info!(%event, "data fresh again, trading resumed");
+                Some(event)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The lowest-latency endpoint that is up, within `max_lag` of the highest checkpoint and whose
+/// checkpoint is at most `max_age_ms` old.
+fn select_fastest(stats: &[EndpointStats], max_lag: u64, max_age_ms: u64) -> Option<usize> {
+    let highest = stats.iter().filter(|s| s.up).map(|s| s.checkpoint).max()?;
+    stats
+        .iter()
+        .enumerate()
+        .filter(|(_, s)| s.up && s.checkpoint + max_lag >= highest && s.checkpoint_age_ms <= max_age_ms)
+        .filter_map(|(i, s)| s.latency_ms.map(|latency| (i, latency)))
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(i, _)| i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(latency_ms: f64, checkpoint: u64, up: bool) -> EndpointStats {
+        EndpointStats {
+            latency_ms: Some(latency_ms),
+            checkpoint,
+            checkpoint_age_ms: 500,
+            up,
+            errors: 0,
+        }
+    }
+
+    #[test]
+    fn test_fastest_endpoint_that_keeps_up() {
+        // the fastest endpoint is 10 checkpoints behind, the next one is down
+        let endpoints = [stats(40.0, 100, true), stats(5.0, 90, true), stats(1.0, 100, false)];
+        assert_eq!(select_fastest(&endpoints, 3, 5000), Some(0));
+        assert_eq!(select_fastest(&endpoints, 10, 5000), Some(1));
+        assert_eq!(select_fastest(&[stats(1.0, 100, false)], 3, 5000), None);
+
+        // every endpoint agrees on the checkpoint, but it is a minute old
+        let lagging = EndpointStats {
+            checkpoint_age_ms: 60_000,
+            ..stats(1.0, 100, true)
+        };
+        assert_eq!(select_fastest(&[lagging], 3, 5000), None);
+        assert_eq!(select_fastest(&[lagging, stats(40.0, 100, true)], 3, 5000), Some(1));
+    }
+}
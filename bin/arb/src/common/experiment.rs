@@ -0,0 +1,357 @@
+//! A/B experiments on strategy parameters (target `experiment_metrics`).
+//!
+//! With an experiment arm configured, public opportunities are claimed in turn by the control arm
+//! (the main config) or the experiment arm, and traded with that arm's profit floor and sizing.
+//! Each claim settles with the PnL it made in base coin units: the realized profit when the trade
+//! landed, minus gas when it reverted, zero when nothing was submitted. Comparing PnL per claimed
+//! opportunity, not per trade, also charges an arm for the opportunities it passes up.
+//!
+//! `split` hands out opportunities at a fixed share; `bandit` (UCB1) shifts them to the arm doing
+//! better once both have `MIN_SAMPLES` settled. Shio bids keep the main config, their PnL is settled
+//! by the auction out of our sight.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Mutex, OnceLock},
+    time::Duration,
+};
+
+use clap::{Parser, ValueEnum};
+use eyre::{ensure, Result};
+use serde::{Deserialize, Serialize};
+use sui_types::digests::TransactionDigest;
+use tracing::info;
+
+use crate::arb::Sizing;
+
+// settled claims per arm before the bandit exploits
+const MIN_SAMPLES: u64 = 30;
+
+static EXPERIMENT: OnceLock<Experiment> = OnceLock::new();
+
+#[derive(Clone, Debug, Parser)]
+pub struct ExperimentConfig {
+    /// SUI_MIN_PROFIT_GAS_MULTIPLE of the experiment arm; setting it or SUI_EXPERIMENT_SIZING
+    /// starts an experiment against the main config
+    #[arg(long, env = "SUI_EXPERIMENT_MIN_PROFIT_GAS_MULTIPLE")]
+    pub experiment_min_profit_gas_multiple: Option<f64>,
+
+    /// Sizing of the experiment arm (the main config sizes with local math)
+    #[arg(long, env = "SUI_EXPERIMENT_SIZING", value_enum)]
+    pub experiment_sizing: Option<Sizing>,
+
+    /// How opportunities are split between the arms
+    #[arg(long, env = "SUI_EXPERIMENT_POLICY", value_enum, default_value_t = Policy::Split)]
+    pub experiment_policy: Policy,
+
+    /// Share of opportunities the experiment arm claims under the split policy
+    #[arg(long, env = "SUI_EXPERIMENT_SHARE", default_value_t = 0.5)]
+    pub experiment_share: f64,
+}
+
+impl ExperimentConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.experiment_min_profit_gas_multiple.is_some() || self.experiment_sizing.is_some()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Policy {
+    /// Fixed share per arm
+    Split,
+    /// UCB1 on PnL per opportunity
+    Bandit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Arm {
+    Control,
+    Experiment,
+}
+
+impl fmt::Display for Arm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Arm::Control => f.write_str("control"),
+            Arm::Experiment => f.write_str("experiment"),
+        }
+    }
+}
+
+/// The parameters an arm trades with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ArmParams {
+    /// None: the current `SUI_MIN_PROFIT_GAS_MULTIPLE`, as reloaded from the config file
+    pub min_profit_gas_multiple: Option<f64>,
+    pub sizing: Sizing,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct ArmStats {
+    claimed: u64,
+    settled: u64,
+    pnl: f64,
+    pnl_sq: f64,
+}
+
+impl ArmStats {
+    fn settle(&mut self, pnl: f64) {
+        self.settled += 1;
+        self.pnl += pnl;
+        self.pnl_sq += pnl * pnl;
+    }
+
+    fn mean(&self) -> f64 {
+        if self.settled == 0 {
+            return 0.0;
+        }
+        self.pnl / self.settled as f64
+    }
+
+    fn variance(&self) -> f64 {
+        if self.settled < 2 {
+            return 0.0;
+        }
+        let n = self.settled as f64;
+        ((self.pnl_sq - self.pnl * self.pnl / n) / (n - 1.0)).max(0.0)
+    }
+
+    // optimistic estimate of the mean, scaled by the arm's own spread since PnL has no fixed range
+    fn upper_bound(&self, total_settled: u64) -> f64 {
+        if self.settled < MIN_SAMPLES {
+            return f64::INFINITY;
+        }
+        let exploration = (2.0 * (total_settled as f64).ln() / self.settled as f64).sqrt();
+        self.mean() + self.variance().sqrt() * exploration
+    }
+}
+
+#[derive(Default)]
+struct State {
+    // indexed by `Arm as usize`
+    stats: [ArmStats; 2],
+    // submitted trades awaiting their PnL
+    pending: HashMap<TransactionDigest, Arm>,
+}
+
+pub struct Experiment {
+    params: [ArmParams; 2],
+    policy: Policy,
+    share: f64,
+    state: Mutex<State>,
+}
+
+impl Experiment {
+    pub fn new(config: &ExperimentConfig, control: ArmParams) -> Self {
+        let experiment = ArmParams {
+            min_profit_gas_multiple: config
+                .experiment_min_profit_gas_multiple
+                .or(control.min_profit_gas_multiple),
+            sizing: config.experiment_sizing.unwrap_or(control.sizing),
+        };
+        Self {
+            params: [control, experiment],
+            policy: config.experiment_policy,
+            share: config.experiment_share,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    /// Hand the next opportunity to an arm.
+    pub fn claim(&self) -> Claim<'_> {
+        let mut state = self.state.lock().unwrap();
+        let [control, experiment] = state.stats;
+        let arm = match self.policy {
+            Policy::Split => {
+                let claimed = control.claimed + experiment.claimed;
+                if (experiment.claimed as f64) < self.share * (claimed + 1) as f64 {
+                    Arm::Experiment
+                } else {
+                    Arm::Control
+                }
+            }
+            Policy::Bandit => {
+                let settled = control.settled + experiment.settled;
+                let (control_bound, experiment_bound) = (control.upper_bound(settled), experiment.upper_bound(settled));
+                // until both arms have samples, take turns
+                if experiment_bound > control_bound ||
+                    (experiment_bound == control_bound && experiment.claimed < control.claimed)
+                {
+                    Arm::Experiment
+                } else {
+                    Arm::Control
+                }
+            }
+        };
+        state.stats[arm as usize].claimed += 1;
+
+        Claim {
+            experiment: self,
+            arm,
+            params: self.params[arm as usize],
+            submitted: false,
+        }
+    }
+
+    /// The PnL of the trade submitted as `digest`. Transactions no arm submitted are ignored.
+    pub fn settle(&self, digest: &TransactionDigest, pnl: i128) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(arm) = state.pending.remove(digest) {
+            state.stats[arm as usize].settle(pnl as f64);
+        }
+    }
+
+    pub fn log(&self) {
+        let stats = self.state.lock().unwrap().stats;
+        for arm in [Arm::Control, Arm::Experiment] {
+            let (params, stats) = (self.params[arm as usize], stats[arm as usize]);
+            info!(
+                target: "experiment_metrics",
+                %arm,
+                sizing = ?params.sizing,
+                min_profit_gas_multiple = ?params.min_profit_gas_multiple,
+                claimed = stats.claimed,
+                settled = stats.settled,
+                pnl = stats.pnl,
+                "{:.1} per opportunity",
+                stats.mean()
+            );
+        }
+
+        let [control, experiment] = stats;
+        if let Some(t) = welch_t(&experiment, &control) {
+            info!(
+                target: "experiment_metrics",
+                t,
+                "experiment - control: {:.1} per opportunity (|t| > 2: significant at ~95%)",
+                experiment.mean() - control.mean()
+            );
+        }
+    }
+}
+
+/// Welch's t statistic of `a`'s mean over `b`'s, None until both have a spread.
+fn welch_t(a: &ArmStats, b: &ArmStats) -> Option<f64> {
+    if a.settled < 2 || b.settled < 2 {
+        return None;
+    }
+    let standard_error = (a.variance() / a.settled as f64 + b.variance() / b.settled as f64).sqrt();
+    (standard_error > 0.0).then(|| (a.mean() - b.mean()) / standard_error)
+}
+
+/// An opportunity claimed by an arm. Unless it was submitted, it settles with no PnL when dropped.
+pub struct Claim<'a> {
+    experiment: &'a Experiment,
+    pub arm: Arm,
+    pub params: ArmParams,
+    submitted: bool,
+}
+
+impl Claim<'_> {
+    /// The claimed opportunity was submitted as `digest`; it settles when the executor reports it.
+    pub fn submitted(mut self, digest: TransactionDigest) {
+        self.submitted = true;
+        self.experiment.state.lock().unwrap().pending.insert(digest, self.arm);
+    }
+}
+
+impl Drop for Claim<'_> {
+    fn drop(&mut self) {
+        if !self.submitted {
+            self.experiment.state.lock().unwrap().stats[self.arm as usize].settle(0.0);
+        }
+    }
+}
+
+/// Start the experiment of `config` against the `control` parameters, if it configures one.
+pub fn init(config: &ExperimentConfig, control: ArmParams) -> Result<()> {
+    if !config.is_enabled() {
+        return Ok(());
+    }
+    let experiment = Experiment::new(config, control);
+    info!(control = ?experiment.params[0], experiment = ?experiment.params[1], policy = ?experiment.policy, "experiment started");
+    ensure!(EXPERIMENT.set(experiment).is_ok(), "experiment already started");
+    Ok(())
+}
+
+/// Hand the next opportunity to an arm, None without an experiment.
+pub fn claim() -> Option<Claim<'static>> {
+    EXPERIMENT.get().map(Experiment::claim)
+}
+
+pub fn settle(digest: &TransactionDigest, pnl: i128) {
+    if let Some(experiment) = EXPERIMENT.get() {
+        experiment.settle(digest, pnl);
+    }
+}
+
+pub async fn run(interval: Duration) {
+    let Some(experiment) = EXPERIMENT.get() else {
+        return;
+    };
+    loop {
+        tokio::time::sleep(interval).await;
+        experiment.log();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn experiment(policy: Policy) -> Experiment {
+        let config = ExperimentConfig {
+            experiment_min_profit_gas_multiple: Some(2.0),
+            experiment_sizing: None,
+            experiment_policy: policy,
+            experiment_share: 0.5,
+        };
+        Experiment::new(
+            &config,
+            ArmParams {
+                min_profit_gas_multiple: None,
+                sizing: Sizing::Local,
+            },
+        )
+    }
+
+    #[test]
+    fn test_arms_take_turns_and_settle() {
+        let experiment = experiment(Policy::Split);
+        let arms: Vec<_> = (0..4).map(|_| experiment.claim().arm).collect();
+        assert_eq!(arms, [Arm::Experiment, Arm::Control, Arm::Experiment, Arm::Control]);
+
+        let claim = experiment.claim();
+        assert_eq!(claim.params.min_profit_gas_multiple, Some(2.0));
+        assert_eq!(claim.params.sizing, Sizing::Local);
+        let digest = TransactionDigest::random();
+        claim.submitted(digest);
+        experiment.settle(&digest, 1_000);
+
+        let stats = experiment.state.lock().unwrap().stats;
+        // dropped claims settled with no PnL
+        assert_eq!(stats[Arm::Control as usize].settled, 2);
+        assert_eq!(stats[Arm::Experiment as usize].settled, 3);
+        assert_eq!(stats[Arm::Experiment as usize].pnl, 1_000.0);
+    }
+
+    #[test]
+    fn test_bandit_favors_the_better_arm() {
+        let experiment = experiment(Policy::Bandit);
+        for _ in 0..2 * MIN_SAMPLES {
+            let claim = experiment.claim();
+            let pnl = match claim.arm {
+                Arm::Control => 10,
+                Arm::Experiment => 100,
+            };
+            let digest = TransactionDigest::random();
+            claim.submitted(digest);
+            experiment.settle(&digest, pnl);
+        }
+
+        let arms: Vec<_> = (0..10).map(|_| experiment.claim().arm).collect();
+        assert!(arms.iter().all(|arm| *arm == Arm::Experiment));
+    }
+}
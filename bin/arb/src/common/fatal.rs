@@ -0,0 +1,125 @@
+//! Exit codes and the final error report, so supervisors can tell failure classes apart.
+//!
+//! Fatal errors are tagged where they happen with `.wrap_err(FatalKind::..)`. Whatever reaches
+//! `main` is classified by its tag (untagged errors are `Other`), the human-readable error is
+//! printed, and the last stderr line is a JSON report:
+//!
+//! ```text
+//! {"fatal":{"kind":"rpc_unreachable","exit_code":3,"error":"...","causes":["..."]}}
+//! ```
+
+use std::{any::Any, fmt, process::ExitCode};
+
+use serde::Serialize;
+use serde_json::json;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FatalKind {
+    Other,
+    /// Invalid flags or environment; also what clap exits with on usage errors
+    Config,
+    RpcUnreachable,
+    /// The private key could not be decoded
+    Key,
+    /// A risk check stopped trading
+    RiskHalt,
+    Panic,
+}
+
+impl FatalKind {
+    pub const fn exit_code(self) -> u8 {
+        match self {
+            FatalKind::Other => 1,
+            FatalKind::Config => 2,
+            FatalKind::RpcUnreachable => 3,
+            FatalKind::Key => 4,
+            FatalKind::RiskHalt => 5,
+            FatalKind::Panic => 101,
+        }
+    }
+
+    pub fn of(error: &eyre::Report) -> Self {
+        error.downcast_ref::<FatalKind>().copied().unwrap_or(FatalKind::Other)
+    }
+}
+
+impl fmt::Display for FatalKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            FatalKind::Other => "fatal error",
+            FatalKind::Config => "configuration error",
+            FatalKind::RpcUnreachable => "RPC unreachable",
+            FatalKind::Key => "key error",
+            FatalKind::RiskHalt => "halted by risk check",
+            FatalKind::Panic => "panic",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+fn report(kind: FatalKind, error: String, causes: Vec<String>) -> ExitCode {
+    let report = json!({
+        "fatal": {
+            "kind": kind,
+            "exit_code": kind.exit_code(),
+            "error": error,
+            "causes": causes,
+        }
+    });
+    eprintln!("{}", report);
+    ExitCode::from(kind.exit_code())
+}
+
+/// Print `error` and its report, and return the exit code for its class.
+pub fn exit_with_error(error: &eyre::Report) -> ExitCode {
+    eprintln!("Error: {:?}", error);
+
+    let kind = FatalKind::of(error);
+    // the tag only names the class
+    let tag = kind.to_string();
+    let mut chain = error.chain().map(ToString::to_string).filter(|msg| *msg != tag);
+    let message = chain.next().unwrap_or_else(|| kind.to_string());
+    report(kind, message, chain.collect())
+}
+
+pub fn exit_with_panic(payload: Box<dyn Any + Send>) -> ExitCode {
+    let message = match payload.downcast::<String>() {
+        Ok(s) => *s,
+        Err(payload) => match payload.downcast::<&'static str>() {
+            Ok(s) => s.to_string(),
+            Err(_) => "Box<Any>".to_string(),
+        },
+    };
+    report(FatalKind::Panic, message, vec![])
+}
+
+#[cfg(test)]
+mod tests {
+    use eyre::{eyre, WrapErr};
+
+    use super::*;
+
+    #[test]
+    fn test_fatal_kind_survives_context() {
+        let error = Err::<(), _>(eyre!("connection refused"))
+            .wrap_err(FatalKind::RpcUnreachable)
+            .wrap_err("start_bot")
+            .unwrap_err();
+        assert_eq!(FatalKind::of(&error), FatalKind::RpcUnreachable);
+        assert_eq!(FatalKind::of(&eyre!("boom")), FatalKind::Other);
+
+        let codes: std::collections::HashSet<_> = [
+            FatalKind::Other,
+            FatalKind::Config,
+            FatalKind::RpcUnreachable,
+            FatalKind::Key,
+            FatalKind::RiskHalt,
+            FatalKind::Panic,
+        ]
+        .iter()
+        .map(|kind| kind.exit_code())
+        .collect();
+        assert_eq!(codes.len(), 6);
+    }
+}
@@ -0,0 +1,233 @@
+//! Reference gas price tracking and the fee model built on it.
+//!
+//! `GasOracle` caches the current epoch, and with it the reference gas price, and refetches it
+//! once the epoch is over, so every fee assumption moves to the new price at the epoch boundary.
+//! Next to it, it keeps two rolling windows:
+//! - the gas our dry runs used, in units of the gas price, to size the gas budget of public trades;
+//! - the gas prices of recent shio opportunities, whose premium over the reference price is how
+//!   congested the chain is.
+//!
+//! The profit floor is the typical gas cost of a trade at the congested price, times
+//! `SUI_MIN_PROFIT_GAS_MULTIPLE`, converted to the base coin, and at least `SUI_MIN_PROFIT`, which
+//! is set in the base coin directly.
+
+use std::{
+    collections::VecDeque,
+    sync::{Mutex, RwLock},
+    time::{Duration, Instant},
+};
+
+use clap::Parser;
+use eyre::Result;
+use simulator::SimEpoch;
+use sui_sdk::SuiClient;
+use tracing::info;
+
+use crate::{common::get_latest_epoch, config::GAS_BUDGET};
+
+const WINDOW: usize = 100;
+// below this many dry runs, public trades keep the default budget
+const MIN_GAS_SAMPLES: usize = 10;
+// after an epoch ends, the next one shows up with the next checkpoint; don't refetch on every call
+const EPOCH_RECHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Debug, Parser)]
+pub struct GasConfig {
+    /// Gas budget of a public trade, as a multiple of the most gas a recent trade used
+    #[arg(long, env = "SUI_GAS_BUDGET_MULTIPLIER", default_value_t = 1.5)]
+    pub gas_budget_multiplier: f64,
+
+    /// Minimum profit, as a multiple of the typical gas cost of a trade at the congested gas
+    /// price (0: no floor beyond covering gas)
+    #[arg(long, env = "SUI_MIN_PROFIT_GAS_MULTIPLE", default_value_t = 0.0)]
+    pub min_profit_gas_multiple: f64,
+
+    /// Minimum profit net of gas, in base coin units, whatever gas costs
+    #[arg(long, env = "SUI_MIN_PROFIT", default_value_t = 0)]
+    pub min_profit: u64,
+}
+
+#[derive(Debug, Default)]
+struct GasStats {
+    // gas used / gas price of recent dry runs
+    gas_units: VecDeque<u64>,
+    // gas prices of recent shio opportunities
+    opp_gas_prices: VecDeque<u64>,
+}
+
+impl GasStats {
+    fn record_gas_units(&mut self, units: u64) {
+        push_bounded(&mut self.gas_units, units);
+    }
+
+    fn observe_gas_price(&mut self, gas_price: u64) {
+        push_bounded(&mut self.opp_gas_prices, gas_price);
+    }
+
+    /// Median opportunity gas price over `reference_gas_price`, at least 1.
+    fn congestion(&self, reference_gas_price: u64) -> f64 {
+        match median(&self.opp_gas_prices) {
+            Some(price) if reference_gas_price > 0 => (price as f64 / reference_gas_price as f64).max(1.0),
+            _ => 1.0,
+        }
+    }
+
+    fn gas_budget(&self, units: u64, gas_price: u64, multiplier: f64) -> u64 {
+        if self.gas_units.len() < MIN_GAS_SAMPLES {
+            return GAS_BUDGET;
+        }
+        let max_units = self.gas_units.iter().copied().max().unwrap_or_default().max(units);
+        let budget = (max_units as f64 * gas_price as f64 * multiplier).ceil() as u64;
+        budget.min(GAS_BUDGET)
+    }
+
+    fn profit_floor(&self, reference_gas_price: u64, multiple: f64) -> u64 {
+        let Some(units) = median(&self.gas_units) else {
+            return 0;
+        };
+        (units as f64 * reference_gas_price as f64 * self.congestion(reference_gas_price) * multiple).ceil() as u64
+    }
+}
+
+pub struct GasOracle {
+    sui: SuiClient,
+    // replaced on config reload
+    config: RwLock<GasConfig>,
+    // (epoch, when it was fetched)
+    epoch: Mutex<(SimEpoch, Instant)>,
+    stats: Mutex<GasStats>,
+}
+
+impl GasOracle {
+    pub async fn new(sui: SuiClient, config: GasConfig) -> Result<Self> {
+        let epoch = get_latest_epoch(&sui).await?;
+        info!(
+            epoch = epoch.epoch_id,
+            reference_gas_price = epoch.gas_price,
+            "gas oracle started"
+        );
+
+        Ok(Self {
+            sui,
+            config: RwLock::new(config),
+            epoch: Mutex::new((epoch, Instant::now())),
+            stats: Mutex::new(GasStats::default()),
+        })
+    }
+
+    /// The current epoch, refetched once it is over.
+    pub async fn epoch(&self) -> Result<SimEpoch> {
+        let (epoch, fetched_at) = *self.epoch.lock().unwrap();
+        if !epoch.is_stale() || fetched_at.elapsed() < EPOCH_RECHECK_INTERVAL {
+            return Ok(epoch);
+        }
+
+        let latest = get_latest_epoch(&self.sui).await?;
+        let mut current = self.epoch.lock().unwrap();
+        if latest.epoch_id != current.0.epoch_id {
+            info!(
+                epoch = latest.epoch_id,
+                reference_gas_price = latest.gas_price,
+                previous_gas_price = current.0.gas_price,
+                "new epoch"
+            );
+            // those premiums were paid over the previous reference price
+            self.stats.lock().unwrap().opp_gas_prices.clear();
+        }
+        *current = (latest, Instant::now());
+        Ok(latest)
+    }
+
+    pub fn set_config(&self, config: GasConfig) {
+        info!(?config, "gas config replaced");
+        *self.config.write().unwrap() = config;
+    }
+
+    pub fn reference_gas_price(&self) -> u64 {
+        self.epoch.lock().unwrap().0.gas_price
+    }
+
+    /// The gas price an opportunity transaction pays.
+    pub fn observe_gas_price(&self, gas_price: u64) {
+        self.stats.lock().unwrap().observe_gas_price(gas_price);
+    }
+
+    /// Gas a dry run used (computation plus storage, in MIST) at `gas_price`.
+    pub fn record_gas_used(&self, gas_used: u64, gas_price: u64) {
+        if gas_price > 0 {
+            self.stats
+                .lock()
+                .unwrap()
+                .record_gas_units(gas_used.div_ceil(gas_price));
+        }
+    }
+
+    /// Gas budget for a trade whose dry run used `gas_used` at `gas_price`.
+    pub fn gas_budget(&self, gas_used: u64, gas_price: u64) -> u64 {
+        let units = gas_used.div_ceil(gas_price.max(1));
+        let multiplier = self.config.read().unwrap().gas_budget_multiplier;
+        self.stats.lock().unwrap().gas_budget(units, gas_price, multiplier)
+    }
+
+    /// Minimum profit (MIST) for a trade to be worth submitting.
+    pub fn profit_floor(&self) -> u64 {
+        let multiple = self.config.read().unwrap().min_profit_gas_multiple;
+        self.profit_floor_at(multiple)
+    }
+
+    /// `SUI_MIN_PROFIT`, in base coin units.
+    pub fn min_profit(&self) -> u64 {
+        self.config.read().unwrap().min_profit
+    }
+
+    /// `profit_floor` for another `SUI_MIN_PROFIT_GAS_MULTIPLE`.
+    pub fn profit_floor_at(&self, min_profit_gas_multiple: f64) -> u64 {
+        let reference_gas_price = self.reference_gas_price();
+        self.stats
+            .lock()
+            .unwrap()
+            .profit_floor(reference_gas_price, min_profit_gas_multiple)
+    }
+}
+
+fn push_bounded(window: &mut VecDeque<u64>, value: u64) {
+    if window.len() == WINDOW {
+        window.pop_front();
+    }
+    window.push_back(value);
+}
+
+fn median(window: &VecDeque<u64>) -> Option<u64> {
+    let mut values: Vec<u64> = window.iter().copied().collect();
+    values.sort_unstable();
+    values.get(values.len() / 2).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fee_model_follows_gas_usage_and_congestion() {
+        let mut stats = GasStats::default();
+        assert_eq!(stats.gas_budget(1_000, 750, 1.5), GAS_BUDGET);
+        assert_eq!(stats.profit_floor(750, 1.0), 0);
+        assert_eq!(stats.congestion(750), 1.0);
+
+        for units in 1_000..1_000 + MIN_GAS_SAMPLES as u64 {
+            stats.record_gas_units(units);
+        }
+        // the largest recent trade, with headroom
+        assert_eq!(stats.gas_budget(1_000, 750, 1.5), 1_009 * 750 * 3 / 2);
+        // a bigger trade than any recent one sizes its own budget
+        assert_eq!(stats.gas_budget(2_000, 750, 1.5), 2_000 * 750 * 3 / 2);
+
+        // opportunities pay twice the reference price
+        stats.observe_gas_price(1_500);
+        assert_eq!(stats.congestion(750), 2.0);
+        assert_eq!(stats.profit_floor(750, 1.0), 1_005 * 750 * 2);
+        // a price cut at the epoch boundary lowers the floor
+        stats.opp_gas_prices.clear();
+        assert_eq!(stats.profit_floor(500, 1.0), 1_005 * 500);
+    }
+}
@@ -0,0 +1,218 @@
+//! Prometheus metrics of the running bot, served as text on `GET /metrics`.
+//!
+//! The strategy counts the events it receives, workers the opportunities they find, the simulations
+//! they run and the trades they submit, and the executor the outcome and realized profit of what
+//! landed. Every metric is prefixed `arb_` and scraped through `serve`.
+
+use std::{net::SocketAddr, sync::OnceLock, time::Duration};
+
+use axum::{http::header, routing::get, Router};
+use clap::Parser;
+use eyre::Result;
+use prometheus::{
+    exponential_buckets, Encoder, GaugeVec, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, Opts,
+    Registry, TextEncoder,
+};
+use tracing::info;
+
+use super::outcomes::Outcome;
+use crate::defi::base;
+
+static METRICS: OnceLock<Metrics> = OnceLock::new();
+
+#[derive(Clone, Debug, Default, Parser)]
+pub struct MetricsConfig {
+    /// Address to serve Prometheus metrics on, e.g. `0.0.0.0:9184`; off when unset
+    #[arg(long, env = "SUI_METRICS_ADDR")]
+    pub metrics_addr: Option<SocketAddr>,
+}
+
+struct Metrics {
+    registry: Registry,
+    events: IntCounterVec,
+    opportunities: IntCounterVec,
+    simulations: IntCounterVec,
+    simulation_latency: HistogramVec,
+    prefiltered: IntCounter,
+    submitted: IntCounterVec,
+    outcomes: IntCounterVec,
+    realized_profit: GaugeVec,
+    graph_nodes: IntGauge,
+    graph_edges: IntGauge,
+}
+
+impl Metrics {
+    fn new() -> Result<Self> {
+        let registry = Registry::new_custom(Some("arb".to_string()), None)?;
+        let events = IntCounterVec::new(Opts::new("events_total", "Events received per kind"), &["kind"])?;
+        let opportunities = IntCounterVec::new(
+            Opts::new("opportunities_total", "Profitable trades found per strategy"),
+            &["strategy"],
+        )?;
+        let simulations = IntCounterVec::new(
+            Opts::new("simulations_total", "Transactions simulated per stage"),
+            &["stage"],
+        )?;
+        let simulation_latency = HistogramVec::new(
+            HistogramOpts::new(
+                "simulation_latency_seconds",
+                "Latency of a simulation call per stage, a batch of paths when searching",
+            )
+            .buckets(exponential_buckets(0.001, 2.0, 14)?),
+            &["stage"],
+        )?;
+        let prefiltered = IntCounter::new(
+            "paths_prefiltered_total",
+            "Candidate cycles dropped by their analytic quote before simulation",
+        )?;
+        let submitted = IntCounterVec::new(
+            Opts::new(
+                "transactions_submitted_total",
+                "Trades handed to an executor per strategy",
+            ),
+            &["strategy"],
+        )?;
+        let outcomes = IntCounterVec::new(
+            Opts::new("trade_outcomes_total", "Trade outcomes per strategy"),
+            &["strategy", "outcome"],
+        )?;
+        let realized_profit = GaugeVec::new(
+            Opts::new(
+                "realized_profit",
+                "Realized profit of landed trades, net of gas, in whole coins",
+            ),
+            &["coin_type"],
+        )?;
+        let graph_nodes = IntGauge::new("graph_nodes", "Tokens in the arbitrage graph")?;
+        let graph_edges = IntGauge::new("graph_edges", "Pool edges in the arbitrage graph")?;
+
+        registry.register(Box::new(events.clone()))?;
+        registry.register(Box::new(opportunities.clone()))?;
+        registry.register(Box::new(simulations.clone()))?;
+        registry.register(Box::new(simulation_latency.clone()))?;
+        registry.register(Box::new(prefiltered.clone()))?;
+        registry.register(Box::new(submitted.clone()))?;
+        registry.register(Box::new(outcomes.clone()))?;
+        registry.register(Box::new(realized_profit.clone()))?;
+        registry.register(Box::new(graph_nodes.clone()))?;
+        registry.register(Box::new(graph_edges.clone()))?;
+
+        Ok(Self {
+            registry,
+            events,
+            opportunities,
+            simulations,
+            simulation_latency,
+            prefiltered,
+            submitted,
+            outcomes,
+            realized_profit,
+            graph_nodes,
+            graph_edges,
+        })
+    }
+}
+
+fn metrics() -> &'static Metrics {
+    METRICS.get_or_init(|| Metrics::new().expect("metric definitions are valid"))
+}
+
+/// The strategy received an event.
+pub fn event(kind: &str) {
+    metrics().events.with_label_values(&[kind]).inc();
+}
+
+/// A search found a profitable trade.
+pub fn opportunity(strategy: &str) {
+    metrics().opportunities.with_label_values(&[strategy]).inc();
+}
+
+/// `count` transactions were simulated in one call taking `elapsed`.
+pub fn simulated(stage: &str, count: usize, elapsed: Duration) {
+    let metrics = metrics();
+    metrics.simulations.with_label_values(&[stage]).inc_by(count as u64);
+    metrics
+        .simulation_latency
+        .with_label_values(&[stage])
+        .observe(elapsed.as_secs_f64());
+}
+
+/// The quote pre-filter dropped a candidate cycle.
+pub fn prefiltered() {
+    metrics().prefiltered.inc();
+}
+
+/// A trade was handed to an executor.
+pub fn submitted(strategy: &str) {
+    metrics().submitted.with_label_values(&[strategy]).inc();
+}
+
+pub fn outcome(strategy: &str, outcome: Outcome) {
+    metrics()
+        .outcomes
+        .with_label_values(&[strategy, outcome.as_str()])
+        .inc();
+}
+
+/// `pnl` base coin units were made (or, negative, lost) by a trade that landed.
+pub fn realized_profit(pnl: i128) {
+    let amount = pnl as f64 / 10f64.powi(base::decimals() as i32);
+    metrics()
+        .realized_profit
+        .with_label_values(&[base::coin_type()])
+        .add(amount);
+}
+
+pub fn graph_size(nodes: usize, edges: usize) {
+    let metrics = metrics();
+    metrics.graph_nodes.set(nodes as i64);
+    metrics.graph_edges.set(edges as i64);
+}
+
+/// Nodes and edges of the arbitrage graph as of the last search, zero before it.
+pub fn current_graph_size() -> (i64, i64) {
+    let metrics = metrics();
+    (metrics.graph_nodes.get(), metrics.graph_edges.get())
+}
+
+/// Every metric in the Prometheus text format.
+pub fn render() -> Result<String> {
+    let mut buf = vec![];
+    TextEncoder::new().encode(&metrics().registry.gather(), &mut buf)?;
+    Ok(String::from_utf8(buf)?)
+}
+
+/// Serve `GET /metrics` on `addr` until the process exits.
+pub async fn serve(addr: SocketAddr) -> Result<()> {
+    let app = Router::new().route(
+        "/metrics",
+        get(|| async {
+            let body = render().unwrap_or_else(|error| format!("# {:#}\n", error));
+            ([(header::CONTENT_TYPE, prometheus::TEXT_FORMAT)], body)
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    info!(%addr, "metrics listening");
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rendered_metrics() {
+        event("public_tx");
+        simulated("search", 3, Duration::from_millis(5));
+        outcome("public", Outcome::Reverted);
+        graph_size(12, 40);
+
+        let text = render().unwrap();
+        assert!(text.contains("arb_events_total{kind=\"public_tx\"}"));
+        assert!(text.contains("arb_simulations_total{stage=\"search\"}"));
+        assert!(text.contains("arb_simulation_latency_seconds_bucket"));
+        assert!(text.contains("arb_trade_outcomes_total{outcome=\"reverted\",strategy=\"public\"}"));
+        assert!(text.contains("arb_graph_edges 40"));
+    }
+}
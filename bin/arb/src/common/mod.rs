@@ -1,5 +1,24 @@
+pub mod alerts;
+pub mod bid;
+pub mod buffers;
+pub mod cache_metrics;
+pub mod cancel;
+pub mod competition;
+pub mod contention;
+pub mod dry_run;
+pub mod endpoints;
+pub mod experiment;
+pub mod fatal;
+pub mod gas;
+pub mod metrics;
 pub mod notification;
-pub mod search;
+pub mod object_refs;
+pub mod outcomes;
+pub mod parallel_sim;
+pub mod quote_filter;
+pub mod risk;
+pub mod shutdown;
+pub mod token_meta;
 
 use eyre::Result;
 use simulator::SimEpoch;
@@ -1,10 +1,17 @@
 use std::{fmt::Write, time::Duration};
 
 use burberry::executor::telegram_message::{escape, Message, MessageBuilder};
+use dex_indexer::types::Pool;
 use sui_types::digests::TransactionDigest;
-use utils::{coin, link, telegram};
+use utils::{link, telegram};
 
-use crate::{arb::ArbResult, BUILD_VERSION};
+use crate::{
+    arb::ArbResult,
+    common::endpoints::StaleDataEvent,
+    config::coin_label,
+    defi::{base, upgrades::UpgradeEvent, Dex},
+    BUILD_VERSION,
+};
 
 const SUI_ARB_BOT_TOKEN: &str = "";
 const GROUP_SUI_ARB: &str = "";
@@ -26,7 +33,7 @@ pub fn new_tg_messages(
         r#"*Profit*: `{profit}`
 
 "#,
-        profit = escape(&coin::format_sui_with_symbol(trade_res.profit)),
+        profit = escape(&base::format_amount(res.profit())),
     )
     .unwrap();
 
@@ -41,12 +48,17 @@ pub fn new_tg_messages(
         scan_link = link::tx(&digest, None),
         arb_scan_link = link::tx(&arb_digest, None),
         coin = link::coin(&trade_res.coin_type, None),
-        amount_in = escape(&coin::format_sui_with_symbol(trade_res.amount_in)),
+        amount_in = escape(&base::format_amount(trade_res.amount_in)),
     )
     .unwrap();
 
     for (i, dex) in trade_res.trade_path.path.iter().enumerate() {
-        let tag = format!("{}({}-{})", dex.protocol(), dex.coin_in_type(), dex.coin_out_type());
+        let tag = format!(
+            "{}({}-{})",
+            dex.protocol(),
+            coin_label(&dex.coin_in_type()),
+            coin_label(&dex.coin_out_type())
+        );
         writeln!(
             msg,
             r#" {i}\. {dex}"#,
@@ -54,6 +66,9 @@ pub fn new_tg_messages(
         )
         .unwrap();
     }
+    if !res.extra_routes.is_empty() {
+        writeln!(msg, "*Extra Routes*: {}", res.extra_routes.len()).unwrap();
+    }
 
     writeln!(msg, "*Elapsed*: {}", escape(&format!("{:?}", elapsed))).unwrap();
     writeln!(
@@ -70,6 +85,14 @@ pub fn new_tg_messages(
     .unwrap();
     writeln!(msg, "*Elapsed GSS*: {}", escape(&format!("{:?}", res.gss_duration))).unwrap();
     writeln!(msg, "*Cache Misses*: {}", res.cache_misses).unwrap();
+    if trade_res.partner_rebate > 0 {
+        writeln!(
+            msg,
+            "*Partner Rebate*: {}",
+            escape(&base::format_amount(trade_res.partner_rebate))
+        )
+        .unwrap();
+    }
     writeln!(msg, "\n*{}*", simulator_name,).unwrap();
     writeln!(msg, "*{}*", escape(res.source.to_string().as_str())).unwrap();
     write!(msg, "*Version*: `{version}`", version = BUILD_VERSION).unwrap();
@@ -106,3 +129,73 @@ pub fn new_tg_messages(
 
     vec![msg1, msg2]
 }
+
+pub fn new_upgrade_alert_message(event: &UpgradeEvent) -> Message {
+    let mut msg = String::with_capacity(1024);
+    let title = match event {
+        UpgradeEvent::Switched { .. } => "Package upgrade followed",
+        UpgradeEvent::Disabled { .. } => "Protocol disabled, manual review needed",
+    };
+
+    writeln!(msg, "*{}*", escape(title)).unwrap();
+    writeln!(msg, "{}", escape(&event.to_string())).unwrap();
+    write!(msg, "*Version*: `{version}`", version = BUILD_VERSION).unwrap();
+
+    MessageBuilder::new()
+        .bot_token(telegram::R2D2_TELEGRAM_BOT_TOKEN)
+        .chat_id(telegram::CHAT_MONEY_PRINTER)
+        .thread_id(telegram::CHAT_MONEY_PRINTER_THREAD_ERROR_REPORT)
+        .text(msg)
+        .disable_link_preview(true)
+        .build()
+}
+
+pub fn new_stale_data_alert_message(event: &StaleDataEvent) -> Message {
+    let mut msg = String::with_capacity(1024);
+    let title = match event {
+        StaleDataEvent::Stale { .. } => "Node data stale, trading paused",
+        StaleDataEvent::Fresh { .. } => "Node data fresh again, trading resumed",
+    };
+
+    writeln!(msg, "*{}*", escape(title)).unwrap();
+    writeln!(msg, "{}", escape(&event.to_string())).unwrap();
+    write!(msg, "*Version*: `{version}`", version = BUILD_VERSION).unwrap();
+
+    MessageBuilder::new()
+        .bot_token(telegram::R2D2_TELEGRAM_BOT_TOKEN)
+        .chat_id(telegram::CHAT_MONEY_PRINTER)
+        .thread_id(telegram::CHAT_MONEY_PRINTER_THREAD_ERROR_REPORT)
+        .text(msg)
+        .disable_link_preview(true)
+        .build()
+}
+
+pub fn new_pool_alert_message(pool: &Pool) -> Message {
+    let mut msg = String::with_capacity(1024);
+
+    writeln!(msg, "*New pool for a watched coin*").unwrap();
+    writeln!(
+        msg,
+        "*Pool*: {}",
+        link::object(pool.pool, Some(escape(&pool.protocol.to_string())))
+    )
+    .unwrap();
+    for token in &pool.tokens {
+        writeln!(
+            msg,
+            " \\- {} \\({} decimals\\)",
+            link::coin(&token.token_type, Some(escape(&coin_label(&token.token_type)))),
+            token.decimals
+        )
+        .unwrap();
+    }
+    write!(msg, "*Version*: `{version}`", version = BUILD_VERSION).unwrap();
+
+    MessageBuilder::new()
+        .bot_token(telegram::R2D2_TELEGRAM_BOT_TOKEN)
+        .chat_id(telegram::CHAT_MONEY_PRINTER)
+        .thread_id(telegram::CHAT_MONEY_PRINTER_THREAD_TEST)
+        .text(msg)
+        .disable_link_preview(true)
+        .build()
+}
@@ -0,0 +1,109 @@
+//! Resolve the object inputs of a PTB right before it is signed.
+//!
+//! Dexes cache their `ObjectArg`s for the lifetime of the process, so by the time a trade is
+//! submitted an owned input (e.g. the DeepBook account cap) may have moved to a newer version and
+//! a shared input may no longer exist. Owned refs are always re-read; shared objects are re-checked
+//! once their last resolution is older than `MAX_SHARED_STALENESS`.
+
+use std::{
+    collections::HashMap,
+    sync::{Mutex, OnceLock},
+    time::Duration,
+};
+
+use cached::{Cached, TimedSizedCache};
+use eyre::{eyre, Result};
+use sui_json_rpc_types::SuiObjectDataOptions;
+use sui_sdk::SuiClient;
+use sui_types::{
+    base_types::{ObjectID, SequenceNumber},
+    object::Owner,
+    transaction::{CallArg, ObjectArg, TransactionData, TransactionDataAPI, TransactionKind},
+};
+use utils::cache::{CacheLimits, CacheStats};
+
+const MAX_SHARED_STALENESS: Duration = Duration::from_secs(5);
+const SHARED_VERSIONS_CACHE_SIZE: usize = 10_000;
+
+// shared object => initial_shared_version, dropped once older than the staleness budget
+static SHARED_VERSIONS: OnceLock<Mutex<TimedSizedCache<ObjectID, SequenceNumber>>> = OnceLock::new();
+
+fn shared_versions() -> &'static Mutex<TimedSizedCache<ObjectID, SequenceNumber>> {
+    SHARED_VERSIONS.get_or_init(|| {
+        let limits = CacheLimits::from_env("SHARED_VERSIONS", SHARED_VERSIONS_CACHE_SIZE, MAX_SHARED_STALENESS);
+        Mutex::new(limits.new_cache())
+    })
+}
+
+pub fn cache_stats() -> CacheStats {
+    CacheStats::of("shared_versions", &*shared_versions().lock().unwrap())
+}
+
+/// Rewrite the object inputs of `tx_data` with their current refs.
+pub async fn refresh_object_refs(sui: &SuiClient, mut tx_data: TransactionData) -> Result<TransactionData> {
+    let stale_ids = stale_input_ids(&tx_data);
+    if stale_ids.is_empty() {
+        return Ok(tx_data);
+    }
+
+    let resp = sui
+        .read_api()
+        .multi_get_object_with_options(stale_ids.clone(), SuiObjectDataOptions::new().with_owner())
+        .await?;
+
+    let mut owned_refs = HashMap::new();
+    let mut cache = shared_versions().lock().unwrap();
+    for (id, obj) in stale_ids.iter().zip(resp) {
+        let data = obj.data.ok_or_else(|| eyre!("input object not found: {}", id))?;
+        match data.owner {
+            Some(Owner::Shared { initial_shared_version }) => {
+                cache.cache_set(*id, initial_shared_version);
+            }
+            _ => {
+                owned_refs.insert(*id, data.object_ref());
+            }
+        }
+    }
+
+    if let TransactionKind::ProgrammableTransaction(pt) = tx_data.kind_mut() {
+        for input in pt.inputs.iter_mut() {
+            match input {
+                CallArg::Object(ObjectArg::ImmOrOwnedObject(obj_ref)) => {
+                    if let Some(latest) = owned_refs.get(&obj_ref.0) {
+                        *obj_ref = *latest;
+                    }
+                }
+                CallArg::Object(ObjectArg::SharedObject {
+                    id,
+                    initial_shared_version,
+                    ..
+                }) => {
+                    if let Some(version) = cache.cache_get(id) {
+                        *initial_shared_version = *version;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    drop(cache);
+
+    Ok(tx_data)
+}
+
+// owned inputs, plus shared inputs not resolved within the staleness budget
+fn stale_input_ids(tx_data: &TransactionData) -> Vec<ObjectID> {
+    let TransactionKind::ProgrammableTransaction(pt) = tx_data.kind() else {
+        return vec![];
+    };
+
+    let mut cache = shared_versions().lock().unwrap();
+    pt.inputs
+        .iter()
+        .filter_map(|input| match input {
+            CallArg::Object(ObjectArg::ImmOrOwnedObject((id, _, _))) => Some(*id),
+            CallArg::Object(ObjectArg::SharedObject { id, .. }) => cache.cache_get(id).is_none().then_some(*id),
+            _ => None,
+        })
+        .collect()
+}
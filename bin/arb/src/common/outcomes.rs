@@ -0,0 +1,330 @@
+//! Trade outcome classification and statistics (target `outcome_metrics`).
+//!
+//! Every trade the bot decides to make ends in one `Outcome`. Workers register the path of each
+//! public trade as they submit it (`submitted`); the executor classifies what came back, from the
+//! effects and balance changes when the transaction landed or from the error when it didn't, and
+//! records it. Trades dropped before submission because their pools moved are recorded as `Stale`
+//! right away. Counts are kept per strategy, protocol and pool, logged every `run` interval and
+//! journaled with the executed trade so `export-trades` can report them.
+//!
+//! Shio bids are settled by the auction, not by us; only their pre-submission drops are counted.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Mutex, OnceLock},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use sui_json_rpc_types::{
+    BalanceChange, SuiExecutionStatus, SuiTransactionBlockEffects, SuiTransactionBlockEffectsAPI,
+    SuiTransactionBlockResponse,
+};
+use sui_types::{
+    base_types::{ObjectID, SuiAddress},
+    digests::TransactionDigest,
+    object::Owner,
+};
+use tracing::info;
+
+use super::metrics;
+use crate::{
+    arb::ArbResult,
+    defi::{base, Dex},
+    types::Source,
+};
+
+// the busiest pools logged per round
+const TOP_POOLS: usize = 10;
+
+// errors meaning the transaction lost a race for the objects it was built on
+const STALE_ERRORS: &[&str] = &[
+    "unavailable for consumption",
+    "ObjectVersionUnavailableForConsumption",
+    "ObjectNotFound",
+    "needs to be rebuilt",
+    "equivocated",
+    "SharedObjectCongestion",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    /// Landed, and the base coin balance grew net of gas
+    LandedProfitable,
+    /// Landed, but the base coin balance didn't grow net of gas
+    LandedUnprofitable,
+    /// Landed as a failed transaction, paying gas for nothing
+    Reverted,
+    /// Outbid or built on state that moved: dropped before submission, or rejected for it
+    Stale,
+    /// Never made it on chain for any other reason
+    SubmissionError,
+}
+
+impl Outcome {
+    const ALL: [Outcome; 5] = [
+        Outcome::LandedProfitable,
+        Outcome::LandedUnprofitable,
+        Outcome::Reverted,
+        Outcome::Stale,
+        Outcome::SubmissionError,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Outcome::LandedProfitable => "landed_profitable",
+            Outcome::LandedUnprofitable => "landed_unprofitable",
+            Outcome::Reverted => "reverted",
+            Outcome::Stale => "stale",
+            Outcome::SubmissionError => "submission_error",
+        }
+    }
+
+    /// Classify an executed transaction of `sender`. Returns the outcome and, when it landed, the
+    /// realized profit in base coin units, net of gas.
+    pub fn of_response(resp: &SuiTransactionBlockResponse, sender: SuiAddress) -> (Self, Option<i128>) {
+        let Some(effects) = &resp.effects else {
+            return (Outcome::SubmissionError, None);
+        };
+        Self::of_effects(effects, resp.balance_changes.as_deref().unwrap_or_default(), sender)
+    }
+
+    /// Classify the effects and balance changes of a transaction of `sender`, executed or simulated.
+    pub fn of_effects(
+        effects: &SuiTransactionBlockEffects,
+        balance_changes: &[BalanceChange],
+        sender: SuiAddress,
+    ) -> (Self, Option<i128>) {
+        if let SuiExecutionStatus::Failure { error } = effects.status() {
+            return (Self::of_error(error, Outcome::Reverted), None);
+        }
+
+        let base_change: i128 = balance_changes
+            .iter()
+            .filter(|bc| bc.owner == Owner::AddressOwner(sender) && &bc.coin_type == base::type_tag())
+            .map(|bc| bc.amount)
+            .sum();
+        // a SUI balance change already paid the gas
+        let realized = if base::is_sui() {
+            base_change
+        } else {
+            base_change - base::gas_in_base(effects.gas_cost_summary().net_gas_usage())
+        };
+
+        let outcome = if realized > 0 {
+            Outcome::LandedProfitable
+        } else {
+            Outcome::LandedUnprofitable
+        };
+        (outcome, Some(realized))
+    }
+
+    /// Classify a transaction that failed to submit.
+    pub fn of_submission_error(error: &eyre::Report) -> Self {
+        Self::of_error(&format!("{:#}", error), Outcome::SubmissionError)
+    }
+
+    fn of_error(error: &str, otherwise: Self) -> Self {
+        if STALE_ERRORS.iter().any(|pattern| error.contains(pattern)) {
+            Outcome::Stale
+        } else {
+            otherwise
+        }
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// What a trade is attributed to.
+#[derive(Debug, Clone)]
+pub struct TradeKey {
+    pub strategy: String,
+    pub legs: Vec<(String, ObjectID)>,
+}
+
+impl TradeKey {
+    pub fn new(strategy: &str, legs: impl IntoIterator<Item = (String, ObjectID)>) -> Self {
+        Self {
+            strategy: strategy.to_string(),
+            legs: legs.into_iter().collect(),
+        }
+    }
+
+    pub fn of(arb_result: &ArbResult) -> Self {
+        Self::new(
+            strategy_name(&arb_result.source),
+            arb_result
+                .best_trial_result
+                .trade_path
+                .path
+                .iter()
+                .map(|dex| (dex.protocol().to_string(), dex.object_id())),
+        )
+    }
+}
+
+pub fn strategy_name(source: &Source) -> &'static str {
+    match source {
+        Source::Public => "public",
+        Source::Shio { .. } => "shio",
+        Source::ShioDeadlineMissed { .. } => "shio_deadline_missed",
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Counts([u64; Outcome::ALL.len()]);
+
+impl Counts {
+    fn add(&mut self, outcome: Outcome) {
+        self.0[outcome as usize] += 1;
+    }
+
+    pub fn total(&self) -> u64 {
+        self.0.iter().sum()
+    }
+
+    pub fn get(&self, outcome: Outcome) -> u64 {
+        self.0[outcome as usize]
+    }
+}
+
+impl fmt::Display for Counts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let total = self.total().max(1) as f64;
+        write!(f, "{} trades", self.total())?;
+        for outcome in Outcome::ALL {
+            let count = self.get(outcome);
+            write!(f, ", {} {} ({:.1}%)", outcome, count, count as f64 / total * 100.0)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct OutcomeStats {
+    pub by_strategy: HashMap<String, Counts>,
+    pub by_protocol: HashMap<String, Counts>,
+    pub by_pool: HashMap<ObjectID, Counts>,
+}
+
+impl OutcomeStats {
+    pub fn record(&mut self, key: &TradeKey, outcome: Outcome) {
+        self.by_strategy.entry(key.strategy.clone()).or_default().add(outcome);
+        // a path through the same protocol twice is still one trade of it
+        let mut protocols: Vec<&String> = key.legs.iter().map(|(protocol, _)| protocol).collect();
+        protocols.sort();
+        protocols.dedup();
+        for protocol in protocols {
+            self.by_protocol.entry(protocol.clone()).or_default().add(outcome);
+        }
+        for (_, pool) in &key.legs {
+            self.by_pool.entry(*pool).or_default().add(outcome);
+        }
+    }
+
+    /// Log the rates, busiest pools only.
+    pub fn log(&self) {
+        for (strategy, counts) in &self.by_strategy {
+            info!(target: "outcome_metrics", %strategy, "{}", counts);
+        }
+        for (protocol, counts) in &self.by_protocol {
+            info!(target: "outcome_metrics", %protocol, "{}", counts);
+        }
+        let mut pools: Vec<_> = self.by_pool.iter().collect();
+        pools.sort_by_key(|(_, counts)| std::cmp::Reverse(counts.total()));
+        for (pool, counts) in pools.into_iter().take(TOP_POOLS) {
+            info!(target: "outcome_metrics", %pool, "{}", counts);
+        }
+    }
+}
+
+#[derive(Default)]
+struct State {
+    stats: OutcomeStats,
+    // submitted public trades awaiting their outcome
+    pending: HashMap<TransactionDigest, TradeKey>,
+}
+
+static STATE: OnceLock<Mutex<State>> = OnceLock::new();
+
+fn state() -> &'static Mutex<State> {
+    STATE.get_or_init(Default::default)
+}
+
+/// A public trade was handed to the executor as `digest`.
+pub fn submitted(digest: TransactionDigest, key: TradeKey) {
+    state().lock().unwrap().pending.insert(digest, key);
+}
+
+/// The outcome of the trade submitted as `digest`. Transactions nobody registered (e.g. gas
+/// top-ups) are not trades and are ignored. Returns whether `digest` was a trade.
+pub fn resolve(digest: &TransactionDigest, outcome: Outcome) -> bool {
+    let mut state = state().lock().unwrap();
+    let Some(key) = state.pending.remove(digest) else {
+        return false;
+    };
+    state.stats.record(&key, outcome);
+    metrics::outcome(&key.strategy, outcome);
+    true
+}
+
+/// Submitted public trades whose outcome isn't known yet.
+pub fn unresolved() -> Vec<TransactionDigest> {
+    state().lock().unwrap().pending.keys().copied().collect()
+}
+
+/// The outcome of a trade that was never submitted.
+pub fn record(key: &TradeKey, outcome: Outcome) {
+    state().lock().unwrap().stats.record(key, outcome);
+    metrics::outcome(&key.strategy, outcome);
+}
+
+pub async fn run(interval: Duration) {
+    loop {
+        tokio::time::sleep(interval).await;
+        log();
+    }
+}
+
+/// Log the rates now.
+pub fn log() {
+    state().lock().unwrap().stats.log();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outcomes_are_counted_per_strategy_protocol_and_pool() {
+        assert_eq!(
+            Outcome::of_error(
+                "Transaction needs to be rebuilt because object 0x1 version 0x2 is unavailable for consumption",
+                Outcome::SubmissionError
+            ),
+            Outcome::Stale
+        );
+        assert_eq!(
+            Outcome::of_error("MoveAbort(MoveLocation { .. }, 0) in command 2", Outcome::Reverted),
+            Outcome::Reverted
+        );
+
+        let (pool_a, pool_b) = (ObjectID::random(), ObjectID::random());
+        let key = TradeKey::new("public", [("cetus".to_string(), pool_a), ("cetus".to_string(), pool_b)]);
+        let mut stats = OutcomeStats::default();
+        stats.record(&key, Outcome::LandedProfitable);
+        stats.record(&key, Outcome::Reverted);
+
+        assert_eq!(stats.by_strategy["public"].total(), 2);
+        // both legs trade on cetus, the trades count once for it
+        assert_eq!(stats.by_protocol["cetus"].total(), 2);
+        assert_eq!(stats.by_pool[&pool_a].get(Outcome::Reverted), 1);
+        assert_eq!(stats.by_pool[&pool_b].get(Outcome::LandedProfitable), 1);
+    }
+}
@@ -0,0 +1,196 @@
+//! Parallel simulation of candidate paths.
+//!
+//! `simulate_paths` fans the paths of a search out over the simulator pool, at most
+//! `SUI_SIM_CONCURRENCY` at a time, and streams each result as soon as it completes. The stream
+//! ends once every path is simulated, at `SUI_SIM_DEADLINE_MS`, when the search is cancelled or its
+//! receiver dropped, or right after a path profits at least `SUI_SIM_GOOD_ENOUGH_PROFIT`. The
+//! simulations still queued or running are then aborted.
+
+use std::{
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
+
+use clap::Parser;
+use eyre::{OptionExt, Result};
+use simulator::SimulateCtx;
+use sui_types::base_types::{ObjectRef, SuiAddress};
+use tokio::{
+    sync::{mpsc, Semaphore},
+    task::JoinSet,
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, Instrument};
+
+use crate::defi::{Path, PathTradeResult, TradeType, Trader};
+
+static CONFIG: OnceLock<ParallelSimConfig> = OnceLock::new();
+
+#[derive(Clone, Debug, PartialEq, Parser)]
+pub struct ParallelSimConfig {
+    /// Candidate paths simulated at once by a search
+    #[arg(long, env = "SUI_SIM_CONCURRENCY", default_value_t = 16)]
+    pub sim_concurrency: usize,
+
+    /// Time the simulations of a search get before those still running are dropped (in
+    /// milliseconds)
+    #[arg(long, env = "SUI_SIM_DEADLINE_MS", default_value_t = 1000)]
+    pub sim_deadline_ms: u64,
+
+    /// A search takes the first path profiting at least this much (in base coin units) instead of
+    /// waiting for the best one; unset: always wait for the best
+    #[arg(long, env = "SUI_SIM_GOOD_ENOUGH_PROFIT")]
+    pub sim_good_enough_profit: Option<u64>,
+}
+
+impl Default for ParallelSimConfig {
+    fn default() -> Self {
+        Self {
+            sim_concurrency: 16,
+            sim_deadline_ms: 1000,
+            sim_good_enough_profit: None,
+        }
+    }
+}
+
+/// Install the scheduler configuration before any search is scheduled.
+pub fn init(config: ParallelSimConfig) -> Result<()> {
+    utils::set_once(&CONFIG, config, "parallel simulation config")
+}
+
+fn config() -> &'static ParallelSimConfig {
+    CONFIG.get_or_init(ParallelSimConfig::default)
+}
+
+/// What every path of a search is simulated with.
+#[derive(Clone)]
+pub struct SimRequest {
+    pub sender: SuiAddress,
+    pub amount_in: u64,
+    pub trade_type: TradeType,
+    pub gas_coins: Vec<ObjectRef>,
+    pub sim_ctx: SimulateCtx,
+}
+
+/// Simulate `paths`, streaming results in the order they complete (see the module docs).
+pub fn simulate_paths(
+    trader: Arc<Trader>,
+    paths: Vec<Path>,
+    request: SimRequest,
+    cancel: CancellationToken,
+) -> mpsc::UnboundedReceiver<Result<PathTradeResult>> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let config = config();
+    let good_enough = config.sim_good_enough_profit.map(i128::from);
+    let permits = Arc::new(Semaphore::new(config.sim_concurrency.max(1)));
+
+    let mut joinset: JoinSet<Result<PathTradeResult>> = JoinSet::new();
+    for path in paths {
+        if path.is_empty() {
+            continue;
+        }
+        let (trader, request, permits) = (Arc::clone(&trader), request.clone(), Arc::clone(&permits));
+        joinset.spawn(
+            async move {
+                let _permit = permits.acquire_owned().await;
+                let SimRequest {
+                    sender,
+                    amount_in,
+                    trade_type,
+                    gas_coins,
+                    sim_ctx,
+                } = request;
+                let trade_res = trader
+                    .get_trade_result(&path, sender, amount_in, trade_type, gas_coins, sim_ctx)
+                    .await;
+                trade_res.map(|trade_res| PathTradeResult::new(path, amount_in, trade_res))
+            }
+            .in_current_span(),
+        );
+    }
+
+    let deadline = Duration::from_millis(config.sim_deadline_ms);
+    tokio::spawn(
+        async move {
+            let deadline = tokio::time::sleep(deadline);
+            tokio::pin!(deadline);
+            loop {
+                tokio::select! {
+                    biased;
+                    _ = cancel.cancelled() => break,
+                    _ = tx.closed() => break,
+                    _ = &mut deadline => {
+                        debug!(dropped = joinset.len(), "simulation deadline reached");
+                        break;
+                    }
+                    joined = joinset.join_next() => {
+                        let Some(joined) = joined else {
+                            break;
+                        };
+                        let Ok(result) = joined else {
+                            continue;
+                        };
+                        let done = matches!(
+                            (&result, good_enough),
+                            (Ok(res), Some(good_enough)) if res.profit() >= good_enough
+                        );
+                        if tx.send(result).is_err() || done {
+                            break;
+                        }
+                    }
+                }
+            }
+            // dropping the joinset aborts the simulations left
+        }
+        .in_current_span(),
+    );
+
+    rx
+}
+
+/// The path with the largest output of a `simulate_paths` stream.
+pub async fn best_of(mut sims: mpsc::UnboundedReceiver<Result<PathTradeResult>>) -> Result<PathTradeResult> {
+    let mut best: Option<PathTradeResult> = None;
+    while let Some(result) = sims.recv().await {
+        let Ok(res) = result else {
+            continue;
+        };
+        if best.as_ref().map_or(true, |best| res.amount_out > best.amount_out) {
+            best = Some(res);
+        }
+    }
+
+    best.filter(|best| best.amount_out > 0).ok_or_eyre("zero amount_out")
+}
+
+#[cfg(test)]
+mod tests {
+    use eyre::eyre;
+
+    use super::*;
+    use crate::defi::TradeResult;
+
+    fn sim(amount_out: u64) -> Result<PathTradeResult> {
+        let trade_res = TradeResult {
+            amount_out,
+            gas_cost: 0,
+            cache_misses: 0,
+        };
+        Ok(PathTradeResult::new(Path::default(), 1_000, trade_res))
+    }
+
+    #[tokio::test]
+    async fn test_best_of_skips_failed_simulations() {
+        let (tx, rx) = mpsc::unbounded_channel();
+        for result in [sim(1_100), Err(eyre!("reverted")), sim(1_300), sim(900)] {
+            tx.send(result).unwrap();
+        }
+        drop(tx);
+        assert_eq!(best_of(rx).await.unwrap().amount_out, 1_300);
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        tx.send(Err(eyre!("reverted"))).unwrap();
+        drop(tx);
+        assert!(best_of(rx).await.is_err());
+    }
+}
@@ -0,0 +1,109 @@
+//! Analytic pre-filter of candidate cycles.
+//!
+//! Before a search hands its cycles to the simulator, each one is quoted hop by hop from the pool
+//! state its dexes were loaded with (`Dex::quote`), for `SUI_QUOTE_PROBE_AMOUNT`. Output falls
+//! with size on every curve we quote, so a cycle returning less than `10_000 +
+//! SUI_QUOTE_MARGIN_BPS` basis points at a small probe won't do better at any size and is dropped
+//! unsimulated. A negative margin leaves room for reserves that moved since they were read.
+//!
+//! A cycle is kept whenever it can't be judged: a hop without local math, or a pool the
+//! opportunity overrides, whose cached state predates it. Off unless `SUI_QUOTE_MARGIN_BPS` is set.
+
+use std::{collections::HashSet, sync::OnceLock};
+
+use clap::Parser;
+use eyre::Result;
+use simulator::SimulateCtx;
+use sui_types::base_types::ObjectID;
+
+use crate::{
+    common::metrics,
+    defi::{quote, Path},
+};
+
+static CONFIG: OnceLock<QuoteFilterConfig> = OnceLock::new();
+
+#[derive(Clone, Debug, PartialEq, Parser)]
+pub struct QuoteFilterConfig {
+    /// Drop cycles whose quoted return is below 10_000 + this many basis points before simulating
+    /// them, e.g. `-30`; off when unset
+    #[arg(long, env = "SUI_QUOTE_MARGIN_BPS", allow_hyphen_values = true)]
+    pub quote_margin_bps: Option<i64>,
+
+    /// Amount of the cycle's first coin the pre-filter quotes with
+    #[arg(long, env = "SUI_QUOTE_PROBE_AMOUNT", default_value_t = 10_000_000)]
+    pub quote_probe_amount: u64,
+}
+
+impl Default for QuoteFilterConfig {
+    fn default() -> Self {
+        Self {
+            quote_margin_bps: None,
+            quote_probe_amount: 10_000_000,
+        }
+    }
+}
+
+/// Install the pre-filter configuration, once at startup.
+pub fn init(config: QuoteFilterConfig) -> Result<()> {
+    utils::set_once(&CONFIG, config, "quote filter config")
+}
+
+fn config() -> &'static QuoteFilterConfig {
+    CONFIG.get_or_init(QuoteFilterConfig::default)
+}
+
+/// Decides which cycles of one search are worth simulating.
+pub struct QuoteFilter {
+    margin_bps: Option<i64>,
+    probe_amount: u64,
+    overridden: HashSet<ObjectID>,
+}
+
+impl QuoteFilter {
+    pub fn new(sim_ctx: &SimulateCtx) -> Self {
+        let config = config();
+        Self {
+            margin_bps: config.quote_margin_bps,
+            probe_amount: config.quote_probe_amount,
+            overridden: sim_ctx.override_objects.iter().map(|o| o.id()).collect(),
+        }
+    }
+
+    /// Whether `cycle` should be simulated.
+    pub fn keep(&self, cycle: &Path) -> bool {
+        let Some(margin_bps) = self.margin_bps else {
+            return true;
+        };
+        if cycle.object_ids().any(|id| self.overridden.contains(&id)) {
+            return true;
+        }
+        let keep = passes(quote::path_return_bps(cycle, self.probe_amount), margin_bps);
+        if !keep {
+            metrics::prefiltered();
+        }
+        keep
+    }
+}
+
+// unquotable cycles pass
+fn passes(return_bps: Option<i64>, margin_bps: i64) -> bool {
+    match return_bps {
+        Some(return_bps) => return_bps >= 10_000i64.saturating_add(margin_bps),
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_passes() {
+        assert!(passes(None, 0));
+        assert!(passes(Some(10_000), 0));
+        assert!(!passes(Some(9_999), 0));
+        assert!(passes(Some(9_970), -30));
+        assert!(!passes(Some(10_010), 20));
+    }
+}
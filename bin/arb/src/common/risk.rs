@@ -0,0 +1,407 @@
+//! Risk limits and the circuit breaker.
+//!
+//! Limits come from the `[risk]` section of the config file, are re-applied when it changes, and
+//! can be replaced at runtime through the admin API (`PUT /risk`):
+//!
+//! ```toml
+//! [risk]
+//! max_trade_capital = 100000000000   # base coin units one trade may borrow
+//! max_daily_loss = 5000000000        # base coin units of net realized loss per UTC day
+//! max_consecutive_failures = 5       # reverted or failed executions in a row
+//!
+//! [risk.protocol_exposure]           # base coin units in flight through a protocol's pools
+//! cetus = 500000000000
+//! ```
+//!
+//! Workers register what each public trade puts at risk as they submit it (`submitted`); the
+//! executor asks `check` before sending it and blocks a trade over the capital or a protocol's
+//! exposure limit. Settled trades feed the day's PnL and the failure streak, and once either
+//! limit is reached the breaker trips: a critical alert goes out and the strategy drops every
+//! opportunity, as it does while paused, until `POST /risk/reset`. Unset limits aren't enforced.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    sync::{Mutex, OnceLock},
+};
+
+use dex_indexer::types::Protocol;
+use eyre::{ensure, Result};
+use serde::{Deserialize, Serialize};
+use sui_types::digests::TransactionDigest;
+use tracing::{info, warn};
+
+use super::{
+    alerts::{self, Severity},
+    outcomes::Outcome,
+};
+use crate::defi::{base, Dex, Path};
+
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+static STATE: OnceLock<Mutex<RiskState>> = OnceLock::new();
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RiskLimits {
+    /// Most base coin one trade may borrow, across its routes
+    pub max_trade_capital: Option<u64>,
+    /// Net realized loss in base coin units within a UTC day that trips the breaker
+    pub max_daily_loss: Option<u64>,
+    /// Reverted or failed executions in a row that trip the breaker
+    pub max_consecutive_failures: Option<u32>,
+    /// Protocol name => most base coin in flight through its pools at once
+    #[serde(default)]
+    pub protocol_exposure: BTreeMap<String, u64>,
+}
+
+impl RiskLimits {
+    pub fn validate(&self) -> Result<()> {
+        ensure!(
+            self.max_trade_capital != Some(0),
+            "risk.max_trade_capital must be positive"
+        );
+        ensure!(self.max_daily_loss != Some(0), "risk.max_daily_loss must be positive");
+        ensure!(
+            self.max_consecutive_failures != Some(0),
+            "risk.max_consecutive_failures must be positive"
+        );
+        for (protocol, limit) in &self.protocol_exposure {
+            Protocol::try_from(protocol.as_str())?;
+            ensure!(*limit > 0, "risk.protocol_exposure.{} must be positive", protocol);
+        }
+        Ok(())
+    }
+}
+
+/// What a trade puts at risk: the base coin it borrows, in all and through each protocol.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Exposure {
+    pub capital: u64,
+    pub protocols: BTreeMap<String, u64>,
+}
+
+impl Exposure {
+    /// The input of each route, counted once against every protocol on its path.
+    pub fn of<'a>(routes: impl IntoIterator<Item = (&'a Path, u64)>) -> Self {
+        let mut exposure = Self::default();
+        for (path, amount_in) in routes {
+            exposure.capital = exposure.capital.saturating_add(amount_in);
+            let mut protocols = path
+                .path
+                .iter()
+                .map(|dex| dex.protocol().to_string())
+                .collect::<Vec<_>>();
+            protocols.sort();
+            protocols.dedup();
+            for protocol in protocols {
+                let total = exposure.protocols.entry(protocol).or_default();
+                *total = total.saturating_add(amount_in);
+            }
+        }
+        exposure
+    }
+}
+
+/// Why a trade was blocked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Breach {
+    /// The circuit breaker tripped for this reason
+    Halted(String),
+    TradeCapital {
+        capital: u64,
+        limit: u64,
+    },
+    ProtocolExposure {
+        protocol: String,
+        exposure: u64,
+        limit: u64,
+    },
+}
+
+impl fmt::Display for Breach {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Breach::Halted(reason) => write!(f, "circuit breaker tripped: {}", reason),
+            Breach::TradeCapital { capital, limit } => {
+                write!(f, "trade borrows {}, over max_trade_capital {}", capital, limit)
+            }
+            Breach::ProtocolExposure {
+                protocol,
+                exposure,
+                limit,
+            } => write!(
+                f,
+                "{} in flight through {}, over its exposure limit {}",
+                exposure, protocol, limit
+            ),
+        }
+    }
+}
+
+/// The limits and where trading stands against them, as the admin API reports it.
+#[derive(Debug, Clone, Serialize)]
+pub struct RiskStatus {
+    pub limits: RiskLimits,
+    /// Why the breaker tripped, while it is
+    pub halted: Option<String>,
+    /// Net realized PnL of the current UTC day, in base coin units
+    pub daily_pnl: i128,
+    pub consecutive_failures: u32,
+    /// Protocol => base coin in flight through it
+    pub exposure: BTreeMap<String, u64>,
+}
+
+#[derive(Default)]
+struct RiskState {
+    limits: RiskLimits,
+    // submitted trades, until the executor checks them
+    submitted: HashMap<TransactionDigest, Exposure>,
+    // checked trades, until they settle
+    in_flight: HashMap<TransactionDigest, Exposure>,
+    // UTC day of `daily_pnl`, in days since the epoch
+    day: u64,
+    daily_pnl: i128,
+    consecutive_failures: u32,
+    halted: Option<String>,
+}
+
+impl RiskState {
+    fn exposure(&self) -> BTreeMap<String, u64> {
+        let mut exposure = BTreeMap::<String, u64>::new();
+        for (protocol, amount) in self.in_flight.values().flat_map(|e| &e.protocols) {
+            let total = exposure.entry(protocol.clone()).or_default();
+            *total = total.saturating_add(*amount);
+        }
+        exposure
+    }
+
+    fn check(&mut self, digest: &TransactionDigest) -> Result<(), Breach> {
+        // only registered trades are limited
+        let Some(trade) = self.submitted.remove(digest) else {
+            return Ok(());
+        };
+        if let Some(reason) = &self.halted {
+            return Err(Breach::Halted(reason.clone()));
+        }
+        if let Some(limit) = self.limits.max_trade_capital {
+            if trade.capital > limit {
+                return Err(Breach::TradeCapital {
+                    capital: trade.capital,
+                    limit,
+                });
+            }
+        }
+        let exposure = self.exposure();
+        for (protocol, amount) in &trade.protocols {
+            let Some(&limit) = self.limits.protocol_exposure.get(protocol) else {
+                continue;
+            };
+            let total = exposure
+                .get(protocol)
+                .copied()
+                .unwrap_or_default()
+                .saturating_add(*amount);
+            if total > limit {
+                return Err(Breach::ProtocolExposure {
+                    protocol: protocol.clone(),
+                    exposure: total,
+                    limit,
+                });
+            }
+        }
+        self.in_flight.insert(*digest, trade);
+        Ok(())
+    }
+
+    // the reason the breaker trips for, if this settlement trips it
+    fn settle(&mut self, digest: &TransactionDigest, outcome: Outcome, pnl: i128, now_ms: u64) -> Option<String> {
+        self.in_flight.remove(digest)?;
+
+        let day = now_ms / DAY_MS;
+        if day != self.day {
+            self.day = day;
+            self.daily_pnl = 0;
+        }
+        self.daily_pnl += pnl;
+        match outcome {
+            Outcome::LandedProfitable | Outcome::LandedUnprofitable => self.consecutive_failures = 0,
+            Outcome::Reverted | Outcome::SubmissionError => self.consecutive_failures += 1,
+            Outcome::Stale => {}
+        }
+
+        if self.halted.is_some() {
+            return None;
+        }
+        let reason = if let Some(limit) = self
+            .limits
+            .max_daily_loss
+            .filter(|limit| -self.daily_pnl >= *limit as i128)
+        {
+            format!(
+                "lost {} today, max_daily_loss is {}",
+                base::format_amount((-self.daily_pnl).min(u64::MAX as i128) as u64),
+                base::format_amount(limit)
+            )
+        } else if let Some(limit) = self
+            .limits
+            .max_consecutive_failures
+            .filter(|limit| self.consecutive_failures >= *limit)
+        {
+            format!(
+                "{} executions failed in a row, max_consecutive_failures is {}",
+                self.consecutive_failures, limit
+            )
+        } else {
+            return None;
+        };
+        self.halted = Some(reason.clone());
+        Some(reason)
+    }
+}
+
+fn state() -> &'static Mutex<RiskState> {
+    STATE.get_or_init(Default::default)
+}
+
+/// Replace the limits. A breaker that already tripped stays tripped.
+pub fn set_limits(limits: RiskLimits) {
+    info!(?limits, "risk limits set");
+    state().lock().unwrap().limits = limits;
+}
+
+/// A public trade was submitted as `digest`, putting `exposure` at risk.
+pub fn submitted(digest: TransactionDigest, exposure: Exposure) {
+    state().lock().unwrap().submitted.insert(digest, exposure);
+}
+
+/// Whether the trade `digest` may be sent. A trade that may counts as in flight until it settles.
+pub fn check(digest: &TransactionDigest) -> Result<(), Breach> {
+    state().lock().unwrap().check(digest)
+}
+
+/// The trade `digest` ended in `outcome`, making or losing `pnl` base coin units.
+pub fn settled(digest: &TransactionDigest, outcome: Outcome, pnl: i128) {
+    let tripped = state()
+        .lock()
+        .unwrap()
+        .settle(digest, outcome, pnl, utils::current_time_ms());
+    if let Some(reason) = tripped {
+        warn!(%reason, "circuit breaker tripped, trading halted");
+        alerts::emit(
+            Severity::Critical,
+            "Circuit breaker tripped",
+            format!("trading halted: {}", reason),
+        );
+    }
+}
+
+/// Whether the circuit breaker tripped.
+pub fn is_halted() -> bool {
+    state().lock().unwrap().halted.is_some()
+}
+
+/// Close the breaker and clear the failure streak. Returns whether it had tripped.
+pub fn reset() -> bool {
+    let mut state = state().lock().unwrap();
+    state.consecutive_failures = 0;
+    state.halted.take().is_some()
+}
+
+pub fn status() -> RiskStatus {
+    let state = state().lock().unwrap();
+    RiskStatus {
+        limits: state.limits.clone(),
+        halted: state.halted.clone(),
+        daily_pnl: state.daily_pnl,
+        consecutive_failures: state.consecutive_failures,
+        exposure: state.exposure(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exposure(capital: u64, protocols: &[&str]) -> Exposure {
+        Exposure {
+            capital,
+            protocols: protocols.iter().map(|p| (p.to_string(), capital)).collect(),
+        }
+    }
+
+    #[test]
+    fn test_trade_limits() {
+        let mut state = RiskState {
+            limits: RiskLimits {
+                max_trade_capital: Some(100),
+                protocol_exposure: BTreeMap::from([("cetus".to_string(), 150)]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let (a, b, c, d) = (
+            TransactionDigest::random(),
+            TransactionDigest::random(),
+            TransactionDigest::random(),
+            TransactionDigest::random(),
+        );
+
+        // not a registered trade
+        assert_eq!(state.check(&a), Ok(()));
+
+        state.submitted.insert(a, exposure(101, &["turbos"]));
+        assert_eq!(
+            state.check(&a),
+            Err(Breach::TradeCapital {
+                capital: 101,
+                limit: 100
+            })
+        );
+
+        state.submitted.insert(b, exposure(100, &["cetus", "turbos"]));
+        assert_eq!(state.check(&b), Ok(()));
+        state.submitted.insert(c, exposure(60, &["cetus"]));
+        assert!(matches!(
+            state.check(&c),
+            Err(Breach::ProtocolExposure { exposure: 160, .. })
+        ));
+
+        // `b` settled, its exposure is released
+        assert_eq!(state.settle(&b, Outcome::LandedProfitable, 5, 0), None);
+        state.submitted.insert(d, exposure(60, &["cetus"]));
+        assert_eq!(state.check(&d), Ok(()));
+    }
+
+    #[test]
+    fn test_circuit_breaker() {
+        let mut state = RiskState {
+            limits: RiskLimits {
+                max_daily_loss: Some(100),
+                max_consecutive_failures: Some(3),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut settle = |outcome, pnl, now_ms| {
+            let digest = TransactionDigest::random();
+            state.submitted.insert(digest, Exposure::default());
+            state.check(&digest).unwrap();
+            state.settle(&digest, outcome, pnl, now_ms)
+        };
+
+        assert_eq!(settle(Outcome::Reverted, -10, 0), None);
+        assert_eq!(settle(Outcome::Reverted, -10, 0), None);
+        // a landed trade ends the streak
+        assert_eq!(settle(Outcome::LandedUnprofitable, -50, 0), None);
+        assert_eq!(settle(Outcome::Reverted, -10, 0), None);
+        // the loss of a past day doesn't count
+        assert_eq!(settle(Outcome::LandedUnprofitable, -90, DAY_MS), None);
+        assert!(settle(Outcome::LandedUnprofitable, -10, DAY_MS).is_some());
+        assert!(state.halted.is_some());
+
+        let digest = TransactionDigest::random();
+        state.submitted.insert(digest, Exposure::default());
+        assert!(matches!(state.check(&digest), Err(Breach::Halted(_))));
+    }
+}
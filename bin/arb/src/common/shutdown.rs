@@ -0,0 +1,151 @@
+//! Graceful shutdown.
+//!
+//! On SIGINT or SIGTERM `start_bot` calls `begin`. From then on the strategy drops every event and
+//! the opportunities it queued, and workers stop taking items, while the items they are working on
+//! run to completion, submission included. `drain` waits up to `SUI_SHUTDOWN_TIMEOUT` for that
+//! work (held as `InFlight` guards) and for the executor to settle every public trade submitted.
+//! Whatever is still unsettled is looked up on chain by `reconcile` and journaled as executed, or
+//! as unconfirmed when the chain doesn't have it.
+
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        OnceLock,
+    },
+    time::{Duration, Instant},
+};
+
+use clap::Parser;
+use eyre::Result;
+use sui_json_rpc_types::{SuiTransactionBlockDataAPI, SuiTransactionBlockResponseOptions};
+use sui_sdk::SuiClient;
+use sui_types::digests::TransactionDigest;
+use tokio::signal::unix::{signal as unix_signal, SignalKind};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use super::outcomes::{self, Outcome};
+use crate::journal::{ExecutedTrade, JournalEntry, TradeJournal};
+
+const DRAIN_POLL: Duration = Duration::from_millis(100);
+
+static SHUTDOWN: OnceLock<CancellationToken> = OnceLock::new();
+static IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Clone, Debug, Parser)]
+pub struct ShutdownConfig {
+    /// Seconds to wait on shutdown for in-flight searches and submissions to settle
+    #[arg(long, env = "SUI_SHUTDOWN_TIMEOUT", default_value_t = 30)]
+    pub shutdown_timeout: u64,
+}
+
+fn token() -> &'static CancellationToken {
+    SHUTDOWN.get_or_init(CancellationToken::new)
+}
+
+/// Stop taking new opportunities.
+pub fn begin() {
+    token().cancel();
+}
+
+pub fn is_requested() -> bool {
+    token().is_cancelled()
+}
+
+/// Resolves once shutdown has begun.
+pub async fn requested() {
+    token().cancelled().await
+}
+
+/// Resolves with the name of the first SIGINT or SIGTERM received.
+pub async fn signal() -> Result<&'static str> {
+    let mut sigterm = unix_signal(SignalKind::terminate())?;
+    tokio::select! {
+        res = tokio::signal::ctrl_c() => res.map(|_| "SIGINT").map_err(Into::into),
+        _ = sigterm.recv() => Ok("SIGTERM"),
+    }
+}
+
+/// Work the bot waits for before exiting, for as long as it is held.
+#[must_use]
+pub struct InFlight(());
+
+pub fn in_flight() -> InFlight {
+    IN_FLIGHT.fetch_add(1, Ordering::SeqCst);
+    InFlight(())
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        IN_FLIGHT.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Wait up to `timeout` for in-flight work to finish and submitted trades to settle. Returns the
+/// trades still unsettled.
+pub async fn drain(timeout: Duration) -> Vec<TransactionDigest> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        let in_flight = IN_FLIGHT.load(Ordering::SeqCst);
+        let unsettled = outcomes::unresolved();
+        if in_flight == 0 && unsettled.is_empty() {
+            return unsettled;
+        }
+        if Instant::now() >= deadline {
+            warn!(in_flight, unsettled = unsettled.len(), "shutdown timed out");
+            return unsettled;
+        }
+        tokio::time::sleep(DRAIN_POLL).await;
+    }
+}
+
+/// Look up each trade in `digests` on chain, settle it and journal what became of it.
+pub async fn reconcile(sui: &SuiClient, digests: Vec<TransactionDigest>, journal: Option<&TradeJournal>) {
+    let options = SuiTransactionBlockResponseOptions::new()
+        .with_input()
+        .with_effects()
+        .with_balance_changes();
+    for digest in digests {
+        let resp = sui
+            .read_api()
+            .get_transaction_with_options(digest, options.clone())
+            .await;
+        let sender = resp
+            .as_ref()
+            .ok()
+            .and_then(|resp| Some(*resp.transaction.as_ref()?.data.sender()));
+        let entry = match (resp, sender) {
+            (Ok(resp), Some(sender)) if resp.effects.is_some() => {
+                let (outcome, realized_profit) = Outcome::of_response(&resp, sender);
+                outcomes::resolve(&digest, outcome);
+                info!(%digest, %outcome, "reconciled a trade submitted before shutdown");
+                ExecutedTrade::of_response(&resp, outcome, realized_profit)
+            }
+            (resp, _) => {
+                warn!(%digest, error = ?resp.err(), "a trade submitted before shutdown is unconfirmed");
+                ExecutedTrade::unconfirmed(digest)
+            }
+        };
+        if let Some(journal) = journal {
+            journal.append(JournalEntry::Executed(entry));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_drain_waits_for_in_flight_work() {
+        let guard = in_flight();
+        let start = Instant::now();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            drop(guard);
+        });
+        assert!(drain(Duration::from_secs(10)).await.is_empty());
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(200) && elapsed < Duration::from_secs(10));
+    }
+}
@@ -0,0 +1,234 @@
+//! Coin metadata: decimals and symbol of every coin the bot meets.
+//!
+//! `TokenMetadata` reads each coin's `CoinMetadata` once, keeps it in memory and in a JSON file
+//! (`SUI_TOKEN_METADATA_PATH`) that is loaded back at startup, so a restart doesn't refetch the
+//! coins it already knows. Raw amounts of different coins only compare once scaled by their
+//! decimals, which is what `TokenMeta::normalize` and `TokenMeta::format_amount` do.
+//!
+//! A coin is suspicious when it has no `CoinMetadata` (its decimals can't be trusted) or when it
+//! takes the symbol of a well-known asset without being one of its registered coin types, the
+//! usual shape of an impersonation. Swaps in suspicious coins don't trigger a search unless
+//! `SUI_TRADE_SUSPICIOUS_COINS` is set.
+
+use std::{collections::HashMap, fs, path::PathBuf, sync::Mutex};
+
+use clap::Parser;
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+use sui_json_rpc_types::SuiCoinMetadata;
+use sui_sdk::{SuiClient, SUI_COIN_TYPE};
+use tracing::{info, warn};
+
+use crate::config::{known_assets, token_info};
+
+// a coin without metadata is assumed to have SUI's decimals, only ever for display
+const DEFAULT_DECIMALS: u8 = 9;
+
+#[derive(Clone, Debug, Parser)]
+pub struct TokenMetaConfig {
+    /// Coin metadata is cached in this file across restarts
+    #[arg(long, env = "SUI_TOKEN_METADATA_PATH", default_value = "./token_metadata.json")]
+    pub token_metadata_path: PathBuf,
+
+    /// Search from swaps in coins with missing metadata or an impersonated symbol too
+    #[arg(long, env = "SUI_TRADE_SUSPICIOUS_COINS")]
+    pub trade_suspicious_coins: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Suspicion {
+    MissingMetadata,
+    /// Carries the symbol of this well-known asset
+    Impersonates {
+        asset: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenMeta {
+    pub decimals: u8,
+    pub symbol: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suspicion: Option<Suspicion>,
+}
+
+impl TokenMeta {
+    /// Metadata of `coin_type` from its on-chain `CoinMetadata`, if it has one.
+    fn classify(coin_type: &str, metadata: Option<SuiCoinMetadata>) -> Self {
+        let Some(metadata) = metadata else {
+            return Self {
+                decimals: DEFAULT_DECIMALS,
+                symbol: coin_type.rsplit("::").next().unwrap_or(coin_type).to_string(),
+                suspicion: Some(Suspicion::MissingMetadata),
+            };
+        };
+
+        let symbol = metadata.symbol.trim().to_string();
+        let impersonated = known_assets()
+            .into_iter()
+            .find(|asset| asset.eq_ignore_ascii_case(&symbol))
+            .filter(|_| token_info(coin_type).is_none());
+        Self {
+            decimals: metadata.decimals,
+            symbol,
+            suspicion: impersonated.map(|asset| Suspicion::Impersonates {
+                asset: asset.to_string(),
+            }),
+        }
+    }
+
+    pub fn is_suspicious(&self) -> bool {
+        self.suspicion.is_some()
+    }
+
+    /// `raw` in whole coins.
+    pub fn normalize(&self, raw: u64) -> f64 {
+        raw as f64 / 10f64.powi(self.decimals as i32)
+    }
+
+    /// `raw` in whole coins with the symbol, e.g. "12.5 DEEP".
+    pub fn format_amount(&self, raw: u64) -> String {
+        format!("{} {}", self.normalize(raw), self.symbol)
+    }
+}
+
+pub struct TokenMetadata {
+    sui: SuiClient,
+    config: TokenMetaConfig,
+    tokens: Mutex<HashMap<String, TokenMeta>>,
+}
+
+impl TokenMetadata {
+    /// Starts from the metadata cached in `config.token_metadata_path`, if the file exists.
+    pub fn new(sui: SuiClient, config: TokenMetaConfig) -> Result<Self> {
+        let tokens: HashMap<String, TokenMeta> = match fs::read(&config.token_metadata_path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)?,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(error) => return Err(error.into()),
+        };
+        info!(
+            path = ?config.token_metadata_path,
+            tokens = tokens.len(),
+            "token metadata loaded"
+        );
+
+        Ok(Self {
+            sui,
+            config,
+            tokens: Mutex::new(tokens),
+        })
+    }
+
+    /// Metadata of `coin_type`, fetched on first use. `None` only when the fetch fails.
+    pub async fn get(&self, coin_type: &str) -> Option<TokenMeta> {
+        if let Some(meta) = self.tokens.lock().unwrap().get(coin_type) {
+            return Some(meta.clone());
+        }
+        if coin_type == SUI_COIN_TYPE {
+            return Some(TokenMeta {
+                decimals: 9,
+                symbol: "SUI".to_string(),
+                suspicion: None,
+            });
+        }
+
+        let metadata = match self.sui.coin_read_api().get_coin_metadata(coin_type.into()).await {
+            Ok(metadata) => metadata,
+            Err(error) => {
+                // a failed read says nothing about the coin, try again next time
+                warn!(%coin_type, ?error, "failed to fetch coin metadata");
+                return None;
+            }
+        };
+        let meta = TokenMeta::classify(coin_type, metadata);
+        if let Some(suspicion) = &meta.suspicion {
+            warn!(%coin_type, symbol = %meta.symbol, ?suspicion, "suspicious coin");
+        }
+
+        let mut tokens = self.tokens.lock().unwrap();
+        tokens.insert(coin_type.to_string(), meta.clone());
+        if let Err(error) = self.persist(&tokens) {
+            warn!(?error, "failed to write token metadata");
+        }
+        Some(meta)
+    }
+
+    /// Whether swaps in `coin_type` should be skipped; a coin whose metadata can't be read is
+    /// given the benefit of the doubt.
+    pub async fn should_skip(&self, coin_type: &str) -> bool {
+        if self.config.trade_suspicious_coins {
+            return false;
+        }
+        self.get(coin_type).await.is_some_and(|meta| meta.is_suspicious())
+    }
+
+    /// `raw` amount of `coin_type` in whole coins with its symbol, raw when the metadata is unknown.
+    pub async fn format_amount(&self, coin_type: &str, raw: u64) -> String {
+        match self.get(coin_type).await {
+            Some(meta) => meta.format_amount(raw),
+            None => format!("{} {}", raw, coin_type),
+        }
+    }
+
+    /// Write the cache to disk.
+    pub fn flush(&self) -> Result<()> {
+        self.persist(&self.tokens.lock().unwrap())
+    }
+
+    // written whole to a temporary file and renamed over the cache, never left half written
+    fn persist(&self, tokens: &HashMap<String, TokenMeta>) -> Result<()> {
+        let path = &self.config.token_metadata_path;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec(tokens)?)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(symbol: &str, decimals: u8) -> SuiCoinMetadata {
+        SuiCoinMetadata {
+            decimals,
+            name: symbol.to_string(),
+            symbol: symbol.to_string(),
+            description: String::new(),
+            icon_url: None,
+            id: None,
+        }
+    }
+
+    #[test]
+    fn test_classify() {
+        let usdc = "0xdba34672e30cb065b1f93e3ab55318768fd6fef66c15942c9f7cb846e2f900e7::usdc::USDC";
+        assert_eq!(TokenMeta::classify(usdc, Some(metadata("USDC", 6))).suspicion, None);
+
+        let fake = "0x1234::usdc::USDC";
+        assert_eq!(
+            TokenMeta::classify(fake, Some(metadata("usdc", 6))).suspicion,
+            Some(Suspicion::Impersonates {
+                asset: "USDC".to_string()
+            })
+        );
+
+        let meme = "0x1234::meme::MEME";
+        assert_eq!(TokenMeta::classify(meme, Some(metadata("MEME", 9))).suspicion, None);
+
+        let missing = TokenMeta::classify(meme, None);
+        assert_eq!(missing.symbol, "MEME");
+        assert_eq!(missing.suspicion, Some(Suspicion::MissingMetadata));
+    }
+
+    #[test]
+    fn test_format_amount() {
+        let meta = TokenMeta::classify("0x1234::meme::MEME", Some(metadata("MEME", 6)));
+        assert_eq!(meta.normalize(12_500_000), 12.5);
+        assert_eq!(meta.format_amount(12_500_000), "12.5 MEME");
+    }
+}
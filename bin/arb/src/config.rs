@@ -1,32 +1,438 @@
-use std::collections::HashSet;
-
-use sui_sdk::SUI_COIN_TYPE;
-
-pub const GAS_BUDGET: u64 = 10_000_000_000;
-pub const MAX_SQRT_PRICE_X64: u128 = 79226673515401279992447579055;
-pub const MIN_SQRT_PRICE_X64: u128 = 4295048016;
-
-pub fn pegged_coin_types() -> HashSet<&'static str> {
-    HashSet::from_iter([
-        SUI_COIN_TYPE,
-        // USDC
-        "0x5d4b302506645c37ff133b98c4b50a5ae14841659738d6d733d59d0d217a93bf::coin::COIN",
-        // USDT
-        "0xc060006111016b8a020ad5b33834984a437aaa7d3c74c18e09a95d48aceab08c::coin::COIN",
-        // WETH
-        "0xaf8cd5edc19c4512f4259f0bee101a40d41ebed738ade5874359610ef8eeced5::coin::COIN",
-        // USDC
-        "0xb231fcda8bbddb31f2ef02e6161444aec64a514e2c89279584ac9806ce9cf037::coin::COIN",
-        // USDC
-        "0xdba34672e30cb065b1f93e3ab55318768fd6fef66c15942c9f7cb846e2f900e7::usdc::USDC",
-        // Bucket USD
-        "0xce7ff77a83ea0cb6fd39bd8748e2ec89a3f41e8efdc3f4eb123e0ca37b184db2::buck::BUCK",
-    ])
+//! TOML config file for `start-bot`.
+//!
+//! Every key is optional and, when set, takes precedence over the matching flag or env var:
+//!
+//! ```toml
+//! [rpc]
+//! url = "https://fullnode.mainnet.sui.io"
+//! extra_urls = ["https://rpc.example.com"]
+//!
+//! [gas]
+//! budget_multiplier = 1.5
+//! min_profit_gas_multiple = 0.5
+//! min_profit = 1000000  # base coin units
+//!
+//! [tokens]
+//! allow = ["0x2::sui::SUI", "0x...::usdc::USDC"]
+//! deny = ["0x...::spam::SPAM"]
+//! watch = ["0x...::new::NEW"]
+//!
+//! [protocols]
+//! blue_move = false
+//!
+//! [alerts]  # see `common::alerts`
+//! min_balance = 1000000000
+//!
+//! [risk]  # see `common::risk`
+//! max_daily_loss = 5000000000
+//! ```
+//!
+//! The bot re-reads the file when it changes and applies the tunable part (`[gas]`, the graph
+//! token lists, `[protocols]` and `[risk]`) without a restart. Endpoints, `tokens.watch` and `[alerts]` are
+//! only read at startup. Setting `tokens.allow` or `tokens.deny` replaces both graph token list files. A file
+//! that fails to parse or validate on reload is logged and the last good values stay in place.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use dex_indexer::types::Protocol;
+use eyre::{ensure, Result, WrapErr};
+pub use poison_dart_core::config::*;
+use poison_dart_core::{
+    defi::registry,
+    graph_path_finder::{GraphSettings, TokenLists},
+};
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::common::{
+    alerts::AlertSection,
+    gas::{GasConfig, GasOracle},
+    risk::{self, RiskLimits},
+};
+
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileConfig {
+    #[serde(default)]
+    pub rpc: RpcSection,
+    #[serde(default)]
+    pub gas: GasSection,
+    #[serde(default)]
+    pub tokens: TokenSection,
+    /// Protocol name (`cetus`, `blue_move`, ...) => enabled; protocols not listed stay enabled
+    #[serde(default)]
+    pub protocols: BTreeMap<String, bool>,
+    #[serde(default)]
+    pub alerts: AlertSection,
+    #[serde(default)]
+    pub risk: RiskLimits,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RpcSection {
+    pub url: Option<String>,
+    pub extra_urls: Option<Vec<String>>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GasSection {
+    pub budget_multiplier: Option<f64>,
+    pub min_profit_gas_multiple: Option<f64>,
+    pub min_profit: Option<u64>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TokenSection {
+    /// Only these coin types (and the base coin) enter the graph
+    pub allow: Option<Vec<String>>,
+    /// Coin types kept out of the graph
+    pub deny: Option<Vec<String>>,
+    /// New pools holding these coin types are alerted on and arbed
+    pub watch: Option<Vec<String>>,
+}
+
+impl FileConfig {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content =
+            std::fs::read_to_string(path).wrap_err_with(|| format!("reading config file {}", path.display()))?;
+        let config: Self =
+            toml::from_str(&content).wrap_err_with(|| format!("parsing config file {}", path.display()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if let Some(threshold) = self.alerts.failure_threshold {
+            ensure!(threshold > 0, "alerts.failure_threshold must be positive");
+        }
+        if let Some(multiplier) = self.gas.budget_multiplier {
+            ensure!(multiplier >= 1.0, "gas.budget_multiplier must be at least 1");
+        }
+        if let Some(multiple) = self.gas.min_profit_gas_multiple {
+            ensure!(multiple >= 0.0, "gas.min_profit_gas_multiple must not be negative");
+        }
+        self.risk.validate()?;
+        self.disabled_protocols()?;
+        Ok(())
+    }
+
+    /// `gas` with the values this file sets.
+    pub fn gas_config(&self, mut gas: GasConfig) -> GasConfig {
+        if let Some(multiplier) = self.gas.budget_multiplier {
+            gas.gas_budget_multiplier = multiplier;
+        }
+        if let Some(multiple) = self.gas.min_profit_gas_multiple {
+            gas.min_profit_gas_multiple = multiple;
+        }
+        if let Some(min_profit) = self.gas.min_profit {
+            gas.min_profit = min_profit;
+        }
+        gas
+    }
+
+    /// None when the file sets neither list, leaving the lists from flags in place.
+    pub fn token_lists(&self) -> Option<TokenLists> {
+        let TokenSection { allow, deny, .. } = &self.tokens;
+        if allow.is_none() && deny.is_none() {
+            return None;
+        }
+        Some(TokenLists::new(allow.clone(), deny.clone().unwrap_or_default()))
+    }
+
+    pub fn disabled_protocols(&self) -> Result<HashSet<Protocol>> {
+        self.protocols
+            .iter()
+            .filter(|(_, enabled)| !**enabled)
+            .map(|(name, _)| Protocol::try_from(name.as_str()))
+            .collect()
+    }
+
+    /// Apply the token lists, protocol toggles and risk limits of the file to the running bot, the
+    /// lists to the path searches sharing `graph`.
+    pub fn apply_tunables(&self, graph: &GraphSettings) -> Result<()> {
+        let disabled = self.disabled_protocols()?;
+        if let Some(tokens) = self.token_lists() {
+            graph.set_token_lists(tokens);
+        }
+        info!(?disabled, "disabled protocols");
+        registry::set_disabled_protocols(disabled);
+        risk::set_limits(self.risk.clone());
+        Ok(())
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+/// Poll `path` every `interval` and re-apply its tunables whenever it changes. `gas` is the
+/// config from flags, which the file's `[gas]` section overrides.
+pub async fn watch(
+    path: PathBuf,
+    mut current: FileConfig,
+    gas: GasConfig,
+    oracle: Arc<GasOracle>,
+    graph: GraphSettings,
+    interval: Duration,
+) {
+    let mut last_modified = modified(&path);
+    loop {
+        tokio::time::sleep(interval).await;
+        let modified = modified(&path);
+        if modified == last_modified {
+            continue;
+        }
+        last_modified = modified;
+
+        if let Some(config) = reload(&path, &current, &graph) {
+            oracle.set_config(config.gas_config(gas.clone()));
+            info!(path = %path.display(), "config reloaded");
+            current = config;
+        }
+    }
+}
+
+/// Load `path` and, when it differs from `current`, apply its tunables. Returns the config now in
+/// effect, None when it is still `current`.
+fn reload(path: &Path, current: &FileConfig, graph: &GraphSettings) -> Option<FileConfig> {
+    let config = match FileConfig::load(path) {
+        Ok(config) => config,
+        Err(error) => {
+            warn!(?error, "config reload failed, keeping the current values");
+            return None;
+        }
+    };
+    if &config == current {
+        return None;
+    }
+    if config.rpc != current.rpc || config.tokens.watch != current.tokens.watch || config.alerts != current.alerts {
+        warn!("config: rpc endpoints, tokens.watch and alerts only apply on restart");
+    }
+    // the lists from flags were replaced at startup
+    if config.token_lists().is_none() && current.token_lists().is_some() {
+        warn!("config: graph token lists removed from the file only reset on restart");
+    }
+    match config.apply_tunables(graph) {
+        Ok(()) => Some(config),
+        Err(error) => {
+            warn!(?error, "config reload failed, keeping the current values");
+            None
+        }
+    }
 }
 
 #[cfg(test)]
 pub mod tests {
+    use std::any::Any;
+
+    use eyre::bail;
+    use sui_sdk::SUI_COIN_TYPE;
+    use sui_types::{
+        base_types::{ObjectID, SuiAddress},
+        transaction::{Argument, TransactionData},
+    };
+
+    use super::*;
+    use crate::defi::{sell_paths, Dex, DexEnum, DexSearcher, Path, TradeCtx};
 
     pub const TEST_HTTP_URL: &str = "";
     pub const TEST_ATTACKER: &str = "";
+
+    #[test]
+    fn test_file_config() {
+        let config: FileConfig = toml::from_str(
+            r#"
+            [gas]
+            min_profit_gas_multiple = 2.0
+            min_profit = 5000
+
+            [tokens]
+            deny = ["0xbeef::coin::COIN"]
+
+            [protocols]
+            cetus = true
+            blue_move = false
+            "#,
+        )
+        .unwrap();
+        config.validate().unwrap();
+        assert_eq!(config.rpc, RpcSection::default());
+
+        let gas = GasConfig {
+            gas_budget_multiplier: 1.5,
+            min_profit_gas_multiple: 0.0,
+            min_profit: 0,
+        };
+        let gas = config.gas_config(gas);
+        assert_eq!(gas.gas_budget_multiplier, 1.5);
+        assert_eq!(gas.min_profit_gas_multiple, 2.0);
+        assert_eq!(gas.min_profit, 5_000);
+
+        let tokens = config.token_lists().unwrap();
+        assert!(!tokens.admits("0xbeef::coin::COIN"));
+        assert!(tokens.admits("0xcafe::coin::COIN"));
+        assert_eq!(
+            config.disabled_protocols().unwrap(),
+            HashSet::from([Protocol::BlueMove])
+        );
+
+        assert!(toml::from_str::<FileConfig>("[gas]\nbudget = 1").is_err());
+        let config: FileConfig = toml::from_str("[protocols]\nnot_a_dex = false").unwrap();
+        assert!(config.validate().is_err());
+        let config: FileConfig = toml::from_str("[risk.protocol_exposure]\nnot_a_dex = 1").unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[derive(Clone)]
+    pub struct TestPool {
+        pub id: ObjectID,
+        pub coin_in: &'static str,
+        pub coin_out: &'static str,
+    }
+
+    #[async_trait::async_trait]
+    impl Dex for TestPool {
+        async fn extend_trade_tx(
+            &self,
+            _ctx: &mut TradeCtx,
+            _sender: SuiAddress,
+            _coin_in: Argument,
+            _amount_in: Option<u64>,
+        ) -> Result<Argument> {
+            bail!("test pool")
+        }
+
+        fn coin_in_type(&self) -> String {
+            self.coin_in.to_string()
+        }
+
+        fn coin_out_type(&self) -> String {
+            self.coin_out.to_string()
+        }
+
+        fn protocol(&self) -> Protocol {
+            Protocol::Cetus
+        }
+
+        fn liquidity(&self) -> u128 {
+            u128::MAX
+        }
+
+        fn object_id(&self) -> ObjectID {
+            self.id
+        }
+
+        fn flip(&mut self) {
+            std::mem::swap(&mut self.coin_in, &mut self.coin_out);
+        }
+
+        fn is_a2b(&self) -> bool {
+            true
+        }
+
+        async fn swap_tx(
+            &self,
+            _sender: SuiAddress,
+            _recipient: SuiAddress,
+            _amount_in: u64,
+        ) -> Result<TransactionData> {
+            bail!("test pool")
+        }
+    }
+
+    struct TestPools(Vec<TestPool>);
+
+    #[async_trait::async_trait]
+    impl DexSearcher for TestPools {
+        async fn find_dexes(&self, coin_in_type: &str, coin_out_type: Option<String>) -> Result<Vec<DexEnum>> {
+            Ok(self
+                .0
+                .iter()
+                .filter(|pool| pool.coin_in == coin_in_type)
+                .filter(|pool| {
+                    coin_out_type
+                        .as_deref()
+                        .map_or(true, |coin_out| pool.coin_out == coin_out)
+                })
+                .map(|pool| DexEnum::Other(Box::new(pool.clone())))
+                .collect())
+        }
+
+        async fn find_test_path(&self, _path: &[ObjectID]) -> Result<Path> {
+            bail!("test pools")
+        }
+
+        async fn pool_dexes(&self, _pool_id: &ObjectID, _coin_in_type: &str) -> Result<Vec<DexEnum>> {
+            bail!("test pools")
+        }
+
+        async fn coin_decimals(&self, _coin_type: &str) -> Option<u8> {
+            Some(9)
+        }
+
+        async fn is_blocked_coin(&self, _coin_type: &str) -> bool {
+            false
+        }
+
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reload_reaches_the_running_bot() {
+        let (coin, spam) = ("0xa::a::A", "0xbeef::coin::COIN");
+        let pool = |id, coin_in, coin_out| TestPool {
+            id: ObjectID::from_single_byte(id),
+            coin_in,
+            coin_out,
+        };
+        let pools = TestPools(vec![
+            pool(1, coin, SUI_COIN_TYPE),
+            pool(2, coin, spam),
+            pool(3, spam, SUI_COIN_TYPE),
+        ]);
+        let flags = GasConfig {
+            gas_budget_multiplier: 1.5,
+            min_profit_gas_multiple: 0.0,
+            min_profit: 0,
+        };
+        let graph = GraphSettings::default();
+
+        let path = std::env::temp_dir().join(format!("bot-config-{}.toml", std::process::id()));
+        std::fs::write(&path, "[gas]\nmin_profit = 1000\n").unwrap();
+        let current = FileConfig::load(&path).unwrap();
+        current.apply_tunables(&graph).unwrap();
+        assert_eq!(current.gas_config(flags.clone()).min_profit, 1_000);
+        assert_eq!(sell_paths(&pools, &graph, coin).await.unwrap().len(), 2);
+
+        std::fs::write(
+            &path,
+            format!("[gas]\nmin_profit = 5000\n\n[tokens]\ndeny = [\"{}\"]\n", spam),
+        )
+        .unwrap();
+        let reloaded = reload(&path, &current, &graph).unwrap();
+        assert_eq!(reloaded.gas_config(flags).min_profit, 5_000);
+        // the searches sharing the settings stop routing through the denied coin
+        let paths = sell_paths(&pools, &graph, coin).await.unwrap();
+        assert_eq!(paths.len(), 1);
+        assert_eq!(
+            paths[0].object_ids().collect::<Vec<_>>(),
+            [ObjectID::from_single_byte(1)]
+        );
+
+        // a file that doesn't parse keeps the last good values
+        std::fs::write(&path, "[gas]\nbudget = 1\n").unwrap();
+        assert!(reload(&path, &reloaded, &graph).is_none());
+        assert_eq!(sell_paths(&pools, &graph, coin).await.unwrap().len(), 1);
+        std::fs::remove_file(&path).unwrap();
+    }
 }
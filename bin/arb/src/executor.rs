@@ -1,44 +1,100 @@
+use std::{collections::HashMap, sync::Arc};
+
 use async_trait::async_trait;
 use burberry::Executor;
-use eyre::Result;
+use eyre::{eyre, Result};
 use fastcrypto::hash::HashFunction;
 use shared_crypto::intent::{Intent, IntentMessage};
-use sui_json_rpc_types::{SuiTransactionBlockResponse, SuiTransactionBlockResponseOptions};
+use sui_json_rpc_types::{
+    SuiExecutionStatus, SuiTransactionBlockEffectsAPI, SuiTransactionBlockResponse, SuiTransactionBlockResponseOptions,
+};
 use sui_sdk::{SuiClient, SuiClientBuilder};
 use sui_types::{
+    base_types::{ObjectID, SuiAddress},
     crypto::{Signer, SuiKeyPair},
+    digests::TransactionDigest,
     signature::GenericSignature,
-    transaction::{Transaction, TransactionData},
+    transaction::{Transaction, TransactionData, TransactionDataAPI},
+};
+use tracing::{info, warn};
+
+use crate::{
+    common::{
+        alerts,
+        buffers::with_bcs_bytes,
+        competition, contention,
+        dry_run::{self, DryRun},
+        endpoints::RpcEndpoints,
+        experiment, metrics,
+        outcomes::{self, Outcome},
+        risk, shutdown,
+    },
+    defi::{base, pool_state},
+    graph_path_finder,
+    journal::{ExecutedTrade, JournalEntry, TradeJournal},
+    strategy::dedup,
 };
-use tracing::info;
 
 pub struct PublicTxExecutor {
     sui: SuiClient,
-    keypair: SuiKeyPair,
+    // signs for whichever account a transaction is sent from
+    keypairs: HashMap<SuiAddress, SuiKeyPair>,
+    journal: Option<Arc<TradeJournal>>,
+    // submit through the fastest endpoint when set
+    endpoints: Option<Arc<RpcEndpoints>>,
 }
 
 impl PublicTxExecutor {
     pub async fn new(rpc_url: &str, keypair: SuiKeyPair) -> Result<Self> {
         let sui = SuiClientBuilder::default().build(rpc_url).await?;
-        Ok(Self { sui, keypair })
+        Ok(Self {
+            sui,
+            keypairs: HashMap::from([(SuiAddress::from(&keypair.public()), keypair)]),
+            journal: None,
+            endpoints: None,
+        })
+    }
+
+    /// Also sign for these accounts.
+    pub fn with_keypairs(mut self, keypairs: impl IntoIterator<Item = SuiKeyPair>) -> Self {
+        self.keypairs
+            .extend(keypairs.into_iter().map(|kp| (SuiAddress::from(&kp.public()), kp)));
+        self
+    }
+
+    pub fn with_journal(mut self, journal: Option<Arc<TradeJournal>>) -> Self {
+        self.journal = journal;
+        self
+    }
+
+    pub fn with_endpoints(mut self, endpoints: Arc<RpcEndpoints>) -> Self {
+        self.endpoints = Some(endpoints);
+        self
     }
 
     pub async fn execute_tx(&self, tx_data: TransactionData) -> Result<SuiTransactionBlockResponse> {
+        let keypair = self
+            .keypairs
+            .get(&tx_data.sender())
+            .ok_or_else(|| eyre!("no key for sender {}", tx_data.sender()))?;
         let intent_msg = IntentMessage::new(Intent::sui_transaction(), tx_data);
-        let raw_tx = bcs::to_bytes(&intent_msg)?;
-
-        let digest = {
+        let digest = with_bcs_bytes(&intent_msg, |raw_tx| {
             let mut hasher = sui_types::crypto::DefaultHash::default();
-            hasher.update(raw_tx.clone());
+            hasher.update(raw_tx);
             hasher.finalize().digest
-        };
+        })?;
 
-        let sig = self.keypair.sign(&digest);
+        let sig = keypair.sign(&digest);
         let tx = Transaction::from_generic_sig_data(intent_msg.value, vec![GenericSignature::Signature(sig)]);
 
-        let options = SuiTransactionBlockResponseOptions::default();
-        let tx_resp = self
-            .sui
+        let options = SuiTransactionBlockResponseOptions::new()
+            .with_effects()
+            .with_balance_changes();
+        let sui = match &self.endpoints {
+            Some(endpoints) => endpoints.fastest(),
+            None => self.sui.clone(),
+        };
+        let tx_resp = sui
             .quorum_driver_api()
             .execute_transaction_block(tx, options, None)
             .await?;
@@ -54,10 +110,169 @@ impl Executor<TransactionData> for PublicTxExecutor {
     }
 
     async fn execute(&self, action: TransactionData) -> Result<()> {
-        let resp = self.execute_tx(action).await?;
+        // shutdown waits until the trade is settled and journaled
+        let _in_flight = shutdown::in_flight();
+        let (arb_digest, sender) = (action.digest(), action.sender());
+        if let Err(breach) = risk::check(&arb_digest) {
+            warn!(%arb_digest, %breach, "Trade blocked by a risk check");
+            outcomes::resolve(&arb_digest, Outcome::SubmissionError);
+            contention::settled(&arb_digest, Outcome::SubmissionError);
+            dedup::settled(&arb_digest, Outcome::SubmissionError);
+            experiment::settle(&arb_digest, 0);
+            if let Some(journal) = &self.journal {
+                journal.append(JournalEntry::Executed(ExecutedTrade::blocked(arb_digest, &breach)));
+            }
+            return Ok(());
+        }
+        let shared_objects: Vec<ObjectID> = action.shared_input_objects().into_iter().map(|o| o.id).collect();
+        let resp = match self.execute_tx(action).await {
+            Ok(resp) => resp,
+            Err(error) => {
+                let outcome = Outcome::of_submission_error(&error);
+                competition::settled(&shared_objects, outcome);
+                report_congestion(&format!("{:#}", error), shared_objects);
+                if outcome == Outcome::SubmissionError {
+                    alerts::execution_failed(&format!("{:#}", error));
+                }
+                outcomes::resolve(&arb_digest, outcome);
+                contention::settled(&arb_digest, outcome);
+                dedup::settled(&arb_digest, outcome);
+                experiment::settle(&arb_digest, 0);
+                risk::settled(&arb_digest, outcome, 0);
+                if let Some(journal) = &self.journal {
+                    journal.append(JournalEntry::Executed(ExecutedTrade {
+                        timestamp_ms: utils::current_time_ms(),
+                        arb_digest,
+                        success: false,
+                        gas_used: None,
+                        error: Some(format!("{:#}", error)),
+                        outcome: Some(outcome),
+                        realized_profit: None,
+                    }));
+                }
+                return Err(error);
+            }
+        };
         let digest = resp.digest.base58_encode();
+        let (outcome, realized_profit) = Outcome::of_response(&resp, sender);
+        competition::settled(&shared_objects, outcome);
+        if let Some(SuiExecutionStatus::Failure { error }) = resp.effects.as_ref().map(|e| e.status()) {
+            report_congestion(error, shared_objects);
+        }
+        let is_trade = outcomes::resolve(&resp.digest, outcome);
+        contention::settled(&resp.digest, outcome);
+        dedup::settled(&resp.digest, outcome);
+        // a reverted trade still paid for its gas
+        let pnl = realized_profit.unwrap_or_else(|| {
+            let gas_used = resp
+                .effects
+                .as_ref()
+                .map_or(0, |e| e.gas_cost_summary().net_gas_usage());
+            -base::gas_in_base(gas_used)
+        });
+        experiment::settle(&resp.digest, pnl);
+        risk::settled(&resp.digest, outcome, pnl);
+        if is_trade {
+            metrics::realized_profit(pnl);
+        }
+        match outcome {
+            Outcome::LandedProfitable | Outcome::LandedUnprofitable => alerts::trade_landed(&digest, pnl),
+            Outcome::Reverted | Outcome::SubmissionError => {
+                let error = match resp.effects.as_ref().map(|e| e.status()) {
+                    Some(SuiExecutionStatus::Failure { error }) => error.clone(),
+                    _ => outcome.to_string(),
+                };
+                alerts::execution_failed(&error);
+            }
+            Outcome::Stale => {}
+        }
+
+        // our own trade moved the pools it touched
+        if let Some(effects) = &resp.effects {
+            for obj in effects.mutated() {
+                pool_state::observe_version(obj.object_id(), obj.version());
+            }
+            graph_path_finder::observe_mutated(effects.mutated().iter().map(|obj| obj.object_id()));
+        }
+
+        if let Some(journal) = &self.journal {
+            journal.append(JournalEntry::Executed(ExecutedTrade::of_response(
+                &resp,
+                outcome,
+                realized_profit,
+            )));
+        }
 
-        info!(?digest, status_ok = ?resp.status_ok(), "Executed tx");
+        info!(?digest, status_ok = ?resp.status_ok(), %outcome, "Executed tx");
         Ok(())
     }
 }
+
+/// Simulates what it is handed instead of submitting it (`--dry-run`).
+pub struct DryRunExecutor {
+    dry_run: Arc<DryRun>,
+}
+
+impl DryRunExecutor {
+    pub fn new(dry_run: Arc<DryRun>) -> Self {
+        Self { dry_run }
+    }
+}
+
+#[async_trait]
+impl Executor<TransactionData> for DryRunExecutor {
+    fn name(&self) -> &str {
+        "DryRunExecutor"
+    }
+
+    async fn execute(&self, action: TransactionData) -> Result<()> {
+        let (arb_digest, sender) = (action.digest(), action.sender());
+        match self.dry_run.simulate(action, 0).await {
+            Ok(simulated) => {
+                outcomes::resolve(&arb_digest, simulated.outcome);
+                contention::settled(&arb_digest, simulated.outcome);
+                dedup::settled(&arb_digest, simulated.outcome);
+                experiment::settle(&arb_digest, simulated.pnl);
+            }
+            Err(error) => {
+                dry_run::failed(&arb_digest, sender, &error);
+                outcomes::resolve(&arb_digest, Outcome::SubmissionError);
+                contention::settled(&arb_digest, Outcome::SubmissionError);
+                dedup::settled(&arb_digest, Outcome::SubmissionError);
+                experiment::settle(&arb_digest, 0);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Executor<(TransactionData, u64, TransactionDigest)> for DryRunExecutor {
+    fn name(&self) -> &str {
+        "DryRunExecutor"
+    }
+
+    async fn execute(&self, action: (TransactionData, u64, TransactionDigest)) -> Result<()> {
+        let (tx_data, bid_amount, _opp_tx_digest) = action;
+        let (arb_digest, sender) = (tx_data.digest(), tx_data.sender());
+        if let Err(error) = self.dry_run.simulate(tx_data, bid_amount).await {
+            dry_run::failed(&arb_digest, sender, &error);
+        }
+        Ok(())
+    }
+}
+
+// Blame the objects a congestion error names, or every shared object of the trade if it names none.
+fn report_congestion(error: &str, shared_objects: Vec<ObjectID>) {
+    if !contention::is_congestion(error) {
+        return;
+    }
+    let congested = contention::congested_objects(error);
+    let congested = if congested.is_empty() {
+        shared_objects
+    } else {
+        congested
+    };
+    warn!(?congested, "trade cancelled for shared-object congestion");
+    contention::congested(congested);
+}
@@ -0,0 +1,303 @@
+//! Export the execution journal as accounting-friendly CSV.
+//!
+//! One row per trade leg. Trade-level amounts (cost basis, proceeds, gas) are only set on
+//! the first leg of each trade so that summing a column never double counts. Outcome rates
+//! per strategy, protocol and pool over the exported trades are logged at the end.
+//!
+//! Example:
+//! cargo run -r --bin arb export-trades --journal-path ./journal/trades.jsonl \
+//!     --from 2024-01-01 --to 2024-03-31 --output q1.csv
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufWriter, Write},
+};
+
+use clap::Parser;
+use eyre::{bail, ensure, eyre, Result};
+use sui_types::digests::TransactionDigest;
+use tracing::info;
+use utils::coin;
+
+use crate::{
+    common::outcomes::{Outcome, OutcomeStats, TradeKey},
+    journal::{read_entries, ExecutedTrade, JournalEntry, SubmittedTrade},
+};
+
+const MIST_PER_SUI: f64 = 1_000_000_000.0;
+const MS_PER_DAY: u64 = 24 * 60 * 60 * 1000;
+
+#[derive(Clone, Debug, Parser)]
+pub struct Args {
+    #[arg(long, env = "SUI_TRADE_JOURNAL_PATH", default_value = "./journal/trades.jsonl")]
+    pub journal_path: String,
+
+    /// First day to include (UTC, YYYY-MM-DD)
+    #[arg(long)]
+    pub from: Option<String>,
+
+    /// Last day to include (UTC, YYYY-MM-DD, inclusive)
+    #[arg(long)]
+    pub to: Option<String>,
+
+    /// SUI/USD used for trades journaled without a price
+    #[arg(long)]
+    pub fallback_sui_usd: Option<f64>,
+
+    /// Include trades that were submitted but never confirmed on chain
+    #[arg(long)]
+    pub include_unconfirmed: bool,
+
+    #[arg(long, default_value = "./trades.csv")]
+    pub output: String,
+}
+
+pub async fn run(args: Args) -> Result<()> {
+    mev_logger::init_console_logger_with_directives(None, &["arb=info"]);
+
+    let from_ms = args.from.as_deref().map(parse_date_ms).transpose()?.unwrap_or(0);
+    let to_ms = args
+        .to
+        .as_deref()
+        .map(parse_date_ms)
+        .transpose()?
+        .map(|ms| ms + MS_PER_DAY)
+        .unwrap_or(u64::MAX);
+    ensure!(from_ms < to_ms, "--from must not be after --to");
+
+    let mut submitted = vec![];
+    let mut executed: HashMap<TransactionDigest, ExecutedTrade> = HashMap::new();
+    for entry in read_entries(&args.journal_path)? {
+        match entry {
+            JournalEntry::Submitted(trade) => submitted.push(trade),
+            JournalEntry::Executed(trade) => {
+                executed.insert(trade.arb_digest, trade);
+            }
+            JournalEntry::Detected(_) | JournalEntry::Simulated(_) => {}
+        }
+    }
+
+    let mut writer = BufWriter::new(File::create(&args.output)?);
+    writeln!(writer, "{}", CSV_HEADER.join(","))?;
+
+    let mut rows = 0;
+    let mut outcomes = OutcomeStats::default();
+    for trade in submitted.iter().filter(|t| t.timestamp_ms >= from_ms && t.timestamp_ms < to_ms) {
+        if let Some(base_coin) = trade.base_coin.as_deref().filter(|c| !coin::is_native_coin(c)) {
+            bail!(
+                "trade {} is denominated in {}, only SUI trades can be exported",
+                trade.arb_digest,
+                base_coin
+            );
+        }
+        let outcome = executed.get(&trade.arb_digest);
+        if outcome.is_none() && !args.include_unconfirmed {
+            continue;
+        }
+        if let Some(outcome) = outcome {
+            let key = TradeKey::new(
+                &trade.source,
+                trade.legs.iter().map(|leg| (leg.protocol.clone(), leg.pool_id)),
+            );
+            outcomes.record(&key, trade_outcome(trade, outcome));
+        }
+        for row in trade_rows(trade, outcome, args.fallback_sui_usd) {
+            writeln!(writer, "{}", row.join(","))?;
+            rows += 1;
+        }
+    }
+    writer.flush()?;
+
+    info!("🎉 exported {} rows to {}", rows, args.output);
+    outcomes.log();
+    Ok(())
+}
+
+const CSV_HEADER: [&str; 20] = [
+    "time_utc",
+    "arb_digest",
+    "trigger_digest",
+    "source",
+    "status",
+    "outcome",
+    "leg",
+    "protocol",
+    "pool_id",
+    "coin_in",
+    "coin_out",
+    "cost_basis_sui",
+    "proceeds_sui",
+    "bid_sui",
+    "gas_sui",
+    "net_profit_sui",
+    "partner_rebate_sui",
+    "sui_usd",
+    "net_profit_usd",
+    "gas_usd",
+];
+
+fn trade_rows(
+    trade: &SubmittedTrade,
+    outcome: Option<&ExecutedTrade>,
+    fallback_sui_usd: Option<f64>,
+) -> Vec<Vec<String>> {
+    let status = match outcome {
+        Some(o) if o.success => "success",
+        Some(_) => "failure",
+        None => "unconfirmed",
+    };
+    let succeeded = outcome.is_some_and(|o| o.success);
+    let gas = outcome.and_then(|o| o.gas_used).unwrap_or(0);
+    let sui_usd = trade.sui_usd.or(fallback_sui_usd);
+
+    // A failed tx only costs gas; a successful one realizes what the balance changes show, or
+    // for older journals, the expected profit net of the bid.
+    let gross = match outcome.and_then(|o| o.realized_profit) {
+        Some(realized) if succeeded => realized as i128 + gas as i128,
+        _ if succeeded => trade.expected_profit as i128 - trade.bid_amount as i128,
+        _ => 0,
+    };
+    let net_profit = gross - gas as i128;
+    let proceeds = if succeeded {
+        trade.amount_in as i128 + gross
+    } else {
+        trade.amount_in as i128
+    };
+
+    trade
+        .legs
+        .iter()
+        .enumerate()
+        .map(|(i, leg)| {
+            let first = i == 0;
+            let amount = |v: i128| if first { format_sui(v) } else { String::new() };
+            let usd = |v: i128| match (first, sui_usd) {
+                (true, Some(p)) => format!("{:.4}", v as f64 / MIST_PER_SUI * p),
+                _ => String::new(),
+            };
+
+            vec![
+                format_utc(trade.timestamp_ms),
+                trade.arb_digest.to_string(),
+                trade.trigger_digest.to_string(),
+                trade.source.clone(),
+                status.to_string(),
+                outcome.map(|o| trade_outcome(trade, o).to_string()).unwrap_or_default(),
+                i.to_string(),
+                leg.protocol.clone(),
+                leg.pool_id.to_string(),
+                csv_escape(&leg.coin_in),
+                csv_escape(&leg.coin_out),
+                amount(trade.amount_in as i128),
+                amount(proceeds),
+                amount(if succeeded { trade.bid_amount as i128 } else { 0 }),
+                amount(gas as i128),
+                amount(net_profit),
+                amount(if succeeded { trade.partner_rebate as i128 } else { 0 }),
+                if first {
+                    sui_usd.map(|p| p.to_string()).unwrap_or_default()
+                } else {
+                    String::new()
+                },
+                usd(net_profit),
+                usd(gas as i128),
+            ]
+        })
+        .collect()
+}
+
+/// The journaled outcome, or for journals written before outcomes were classified, one
+/// inferred from the status and the expected profit.
+fn trade_outcome(trade: &SubmittedTrade, executed: &ExecutedTrade) -> Outcome {
+    match executed.outcome {
+        Some(outcome) => outcome,
+        None if !executed.success => Outcome::Reverted,
+        None if trade.expected_profit as i128 - trade.bid_amount as i128 > executed.gas_used.unwrap_or(0) as i128 => {
+            Outcome::LandedProfitable
+        }
+        None => Outcome::LandedUnprofitable,
+    }
+}
+
+fn format_sui(mist: i128) -> String {
+    format!("{:.9}", mist as f64 / MIST_PER_SUI)
+}
+
+fn csv_escape(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// Parse `YYYY-MM-DD` as midnight UTC in unix milliseconds.
+fn parse_date_ms(date: &str) -> Result<u64> {
+    let parts: Vec<&str> = date.split('-').collect();
+    ensure!(parts.len() == 3, "invalid date {}, expected YYYY-MM-DD", date);
+
+    let year: i64 = parts[0].parse().map_err(|_| eyre!("invalid year in {}", date))?;
+    let month: u32 = parts[1].parse().map_err(|_| eyre!("invalid month in {}", date))?;
+    let day: u32 = parts[2].parse().map_err(|_| eyre!("invalid day in {}", date))?;
+    ensure!((1..=12).contains(&month) && (1..=31).contains(&day), "invalid date {}", date);
+
+    let days = days_from_civil(year, month, day);
+    if days < 0 {
+        bail!("date before 1970-01-01: {}", date);
+    }
+    Ok(days as u64 * MS_PER_DAY)
+}
+
+fn format_utc(ms: u64) -> String {
+    let days = (ms / MS_PER_DAY) as i64;
+    let secs = (ms % MS_PER_DAY) / 1000;
+    let (y, m, d) = civil_from_days(days);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        y,
+        m,
+        d,
+        secs / 3600,
+        (secs / 60) % 60,
+        secs % 60
+    )
+}
+
+// Howard Hinnant's days_from_civil / civil_from_days.
+fn days_from_civil(y: i64, m: u32, d: u32) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = (if y >= 0 { y } else { y - 399 }) / 400;
+    let yoe = y - era * 400;
+    let mp = (m as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + d as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+fn civil_from_days(z: i64) -> (i64, u32, u32) {
+    let z = z + 719468;
+    let era = (if z >= 0 { z } else { z - 146096 }) / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = (if mp < 10 { mp + 3 } else { mp - 9 }) as u32;
+    let y = yoe + era * 400 + if m <= 2 { 1 } else { 0 };
+    (y, m, d)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_format_dates() {
+        assert_eq!(parse_date_ms("1970-01-01").unwrap(), 0);
+        assert_eq!(parse_date_ms("2024-03-01").unwrap(), 1_709_251_200_000);
+        assert_eq!(format_utc(1_709_251_200_000 + 3_723_000), "2024-03-01T01:02:03Z");
+        assert!(parse_date_ms("2024-13-01").is_err());
+    }
+}
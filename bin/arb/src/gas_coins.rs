@@ -0,0 +1,158 @@
+//! Keeps each trading account at `gas_coins_per_account` gas coins of about equal balance.
+//!
+//! An account pays each public trade with one of its gas coins (see `AccountPartitioner`), so the
+//! number of coins is how many of its trades can be in flight at once. Trades leave coins behind
+//! unevenly, and gas top-ups add more, so every `gas_coin_interval` seconds an account whose coin
+//! count is off, or whose smallest coin holds less than half its share, has all its SUI merged and
+//! split again. An account is left alone while any of its coins is leased to a trade.
+
+use std::{sync::Arc, time::Duration};
+
+use eyre::{ensure, eyre, Result};
+use sui_json_rpc_types::SuiTransactionBlockEffectsAPI;
+use sui_sdk::{SuiClient, SUI_COIN_TYPE};
+use sui_types::{
+    base_types::{ObjectID, SuiAddress},
+    transaction::{Argument, Command, TransactionData},
+};
+use tracing::{debug, info, warn};
+use utils::coin;
+
+use crate::{
+    common::get_latest_epoch, defi::TradeCtx, executor::PublicTxExecutor, strategy::accounts::AccountPartitioner,
+};
+
+const REBALANCE_GAS_BUDGET: u64 = 50_000_000;
+// what the split hands out; the rest, with the rebalance's own gas, stays in the gas coin
+const SPLIT_SHARE: f64 = 0.95;
+
+pub struct GasCoinBalancer {
+    sui: SuiClient,
+    executor: PublicTxExecutor,
+    accounts: Vec<SuiAddress>,
+    partitioner: Arc<AccountPartitioner>,
+    gas_coins_per_account: usize,
+    interval: Duration,
+}
+
+impl GasCoinBalancer {
+    pub fn new(
+        sui: SuiClient,
+        executor: PublicTxExecutor,
+        accounts: Vec<SuiAddress>,
+        partitioner: Arc<AccountPartitioner>,
+        gas_coins_per_account: usize,
+        interval: Duration,
+    ) -> Self {
+        Self {
+            sui,
+            executor,
+            accounts,
+            partitioner,
+            gas_coins_per_account,
+            interval,
+        }
+    }
+
+    pub async fn run(self) {
+        info!(
+            gas_coins_per_account = self.gas_coins_per_account,
+            accounts = ?self.accounts,
+            "gas coin balancer started"
+        );
+        let mut interval = tokio::time::interval(self.interval);
+
+        loop {
+            interval.tick().await;
+            for &account in &self.accounts {
+                if let Err(error) = self.rebalance(account).await {
+                    warn!(%account, ?error, "gas coin rebalance failed");
+                }
+            }
+        }
+    }
+
+    async fn rebalance(&self, account: SuiAddress) -> Result<()> {
+        let coins = coin::get_coins(&self.sui, account, SUI_COIN_TYPE, 0).await?;
+        let balances: Vec<u64> = coins.iter().map(|coin| coin.balance).collect();
+        if !needs_rebalance(&balances, self.gas_coins_per_account) {
+            return Ok(());
+        }
+        let ids: Vec<ObjectID> = coins.iter().map(|coin| coin.coin_object_id).collect();
+        if self.partitioner.any_gas_coin_leased(&ids) {
+            debug!(%account, "gas coins in use, rebalancing later");
+            return Ok(());
+        }
+
+        let total: u64 = balances.iter().sum();
+        ensure!(
+            total > REBALANCE_GAS_BUDGET,
+            "{} MIST left, too little to pay for a rebalance",
+            total
+        );
+        let per_coin = ((total - REBALANCE_GAS_BUDGET) as f64 * SPLIT_SHARE) as u64 / self.gas_coins_per_account as u64;
+
+        // paying with every coin merges them into the gas coin, which keeps the last share
+        let splits = self.gas_coins_per_account - 1;
+        let mut ctx = TradeCtx::default();
+        let amounts = (0..splits)
+            .map(|_| ctx.pure(per_coin).map_err(|e| eyre!(e)))
+            .collect::<Result<Vec<_>>>()?;
+        ctx.command(Command::SplitCoins(Argument::GasCoin, amounts));
+        let idx = ctx.last_command_idx();
+        let new_coins = (0..splits as u16).map(|i| Argument::NestedResult(idx, i)).collect();
+        let recipient = ctx.pure(account).map_err(|e| eyre!(e))?;
+        ctx.command(Command::TransferObjects(new_coins, recipient));
+
+        let gas_coins = coins.iter().map(|coin| coin.object_ref()).collect();
+        let epoch = get_latest_epoch(&self.sui).await?;
+        let tx_data = TransactionData::new_programmable(
+            account,
+            gas_coins,
+            ctx.ptb.finish(),
+            REBALANCE_GAS_BUDGET,
+            epoch.gas_price,
+        );
+        let resp = self.executor.execute_tx(tx_data).await?;
+        let status = resp.effects.as_ref().map(|effects| effects.status().clone());
+        ensure!(
+            resp.status_ok() == Some(true),
+            "rebalance tx {} failed: {:?}",
+            resp.digest,
+            status
+        );
+
+        info!(
+            %account,
+            digest = %resp.digest,
+            coins_before = coins.len(),
+            per_coin = %coin::format_sui_with_symbol(per_coin),
+            "gas coins rebalanced"
+        );
+        Ok(())
+    }
+}
+
+/// Whether an account holding coins of `balances` should be rebalanced into `target` coins.
+fn needs_rebalance(balances: &[u64], target: usize) -> bool {
+    if balances.len() != target {
+        return true;
+    }
+    let total: u64 = balances.iter().sum();
+    let share = total / target as u64;
+    balances.iter().any(|&balance| balance < share / 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_needs_rebalance() {
+        assert!(needs_rebalance(&[100], 4));
+        assert!(needs_rebalance(&[100, 100, 100, 100, 1], 4));
+        assert!(!needs_rebalance(&[100, 90, 110, 100], 4));
+        // one coin holds less than half its share
+        assert!(needs_rebalance(&[10, 130, 130, 130], 4));
+    }
+}
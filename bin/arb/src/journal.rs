@@ -0,0 +1,319 @@
+//! Append-only execution journal.
+//!
+//! Workers append a `Submitted` entry for every arb they hand to an executor, and the
+//! public tx executor appends an `Executed` entry once the transaction is finalized.
+//! Entries are joined on `arb_digest` when exporting (see `export_trades`). Workers also
+//! append a `Detected` entry for every opportunity found and a `Simulated` entry for its
+//! final dry run, which `storage` keeps for `arb report`.
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use eyre::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sui_json_rpc_types::{SuiExecutionStatus, SuiTransactionBlockEffectsAPI, SuiTransactionBlockResponse};
+use sui_types::{
+    base_types::{ObjectID, SuiAddress},
+    digests::TransactionDigest,
+    transaction::TransactionData,
+};
+use tracing::warn;
+
+use crate::{
+    arb::ArbResult,
+    common::{
+        experiment::Arm,
+        outcomes::{self, Outcome},
+        risk::Breach,
+    },
+    defi::{base, Dex},
+    storage::TradeStore,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum JournalEntry {
+    Detected(DetectedOpportunity),
+    Simulated(SimulatedTrade),
+    Submitted(SubmittedTrade),
+    Executed(ExecutedTrade),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DetectedOpportunity {
+    pub timestamp_ms: u64,
+    /// Digest of the trade as the search built it
+    pub id: TransactionDigest,
+    pub trigger_digest: TransactionDigest,
+    pub source: String,
+    pub coin_type: String,
+    pub amount_in: u64,
+    pub expected_profit: u64,
+    pub legs: Vec<TradeLeg>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimulatedTrade {
+    pub timestamp_ms: u64,
+    /// `DetectedOpportunity::id` of the trade dry run
+    pub opportunity: TransactionDigest,
+    /// Base coin profit of the dry run, after any shio bid
+    pub profit: Option<i64>,
+    pub gas_used: Option<u64>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubmittedTrade {
+    pub timestamp_ms: u64,
+    pub trigger_digest: TransactionDigest,
+    pub arb_digest: TransactionDigest,
+    pub source: String,
+    pub coin_type: String,
+    /// Coin `amount_in`, `expected_profit`, `bid_amount` and `partner_rebate` are in; SUI when unset
+    #[serde(default)]
+    pub base_coin: Option<String>,
+    pub amount_in: u64,
+    pub expected_profit: u64,
+    pub bid_amount: u64,
+    /// Estimated partner fee rebate
+    #[serde(default)]
+    pub partner_rebate: u64,
+    pub legs: Vec<TradeLeg>,
+    /// SUI/USD at submission time, if the price feed was reachable
+    pub sui_usd: Option<f64>,
+    /// Experiment arm that claimed the opportunity, if an experiment was running
+    #[serde(default)]
+    pub arm: Option<Arm>,
+    /// Account the trade was sent from; unset in journals written before accounts were recorded
+    #[serde(default)]
+    pub sender: Option<SuiAddress>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TradeLeg {
+    pub protocol: String,
+    pub pool_id: ObjectID,
+    pub coin_in: String,
+    pub coin_out: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutedTrade {
+    pub timestamp_ms: u64,
+    pub arb_digest: TransactionDigest,
+    pub success: bool,
+    /// Net gas usage in MIST (computation + storage - rebate)
+    pub gas_used: Option<i64>,
+    pub error: Option<String>,
+    /// Unset in journals written before outcomes were classified
+    #[serde(default)]
+    pub outcome: Option<Outcome>,
+    /// Base coin balance change net of gas, when the trade landed
+    #[serde(default)]
+    pub realized_profit: Option<i64>,
+}
+
+impl DetectedOpportunity {
+    pub fn new(trigger_digest: TransactionDigest, arb_result: &ArbResult) -> Self {
+        let trial = &arb_result.best_trial_result;
+
+        Self {
+            timestamp_ms: utils::current_time_ms(),
+            id: arb_result.tx_data.digest(),
+            trigger_digest,
+            source: outcomes::strategy_name(&arb_result.source).to_string(),
+            coin_type: trial.coin_type.clone(),
+            amount_in: arb_result.routes().map(|route| route.amount_in).sum(),
+            expected_profit: arb_result.profit(),
+            legs: trade_legs(arb_result),
+        }
+    }
+}
+
+impl SimulatedTrade {
+    pub fn new(arb_result: &ArbResult, dry_run: &Result<(TransactionData, i128, u64)>) -> Self {
+        let (profit, gas_used, error) = match dry_run {
+            Ok((_, profit, gas_used)) => (Some(*profit as i64), Some(*gas_used), None),
+            Err(error) => (None, None, Some(format!("{error:#}"))),
+        };
+
+        Self {
+            timestamp_ms: utils::current_time_ms(),
+            opportunity: arb_result.tx_data.digest(),
+            profit,
+            gas_used,
+            error,
+        }
+    }
+}
+
+impl ExecutedTrade {
+    /// A trade that made it on chain and was classified `outcome`.
+    pub fn of_response(resp: &SuiTransactionBlockResponse, outcome: Outcome, realized_profit: Option<i128>) -> Self {
+        let effects = resp.effects.as_ref();
+        Self {
+            timestamp_ms: utils::current_time_ms(),
+            arb_digest: resp.digest,
+            success: resp.status_ok().unwrap_or(false),
+            gas_used: effects.map(|e| e.gas_cost_summary().net_gas_usage()),
+            error: effects.and_then(|e| match e.status() {
+                SuiExecutionStatus::Failure { error } => Some(error.clone()),
+                _ => None,
+            }),
+            outcome: Some(outcome),
+            realized_profit: realized_profit.map(|profit| profit as i64),
+        }
+    }
+
+    /// A trade a risk check kept from being sent.
+    pub fn blocked(arb_digest: TransactionDigest, breach: &Breach) -> Self {
+        Self {
+            timestamp_ms: utils::current_time_ms(),
+            arb_digest,
+            success: false,
+            gas_used: None,
+            error: Some(format!("blocked: {}", breach)),
+            outcome: Some(Outcome::SubmissionError),
+            realized_profit: None,
+        }
+    }
+
+    /// A trade submitted before shutdown that the chain didn't know of.
+    pub fn unconfirmed(arb_digest: TransactionDigest) -> Self {
+        Self {
+            timestamp_ms: utils::current_time_ms(),
+            arb_digest,
+            success: false,
+            gas_used: None,
+            error: Some("unconfirmed at shutdown".to_string()),
+            outcome: None,
+            realized_profit: None,
+        }
+    }
+}
+
+// the legs of every route, in order
+fn trade_legs(arb_result: &ArbResult) -> Vec<TradeLeg> {
+    arb_result
+        .routes()
+        .flat_map(|route| &route.trade_path.path)
+        .map(|dex| TradeLeg {
+            protocol: dex.protocol().to_string(),
+            pool_id: dex.object_id(),
+            coin_in: dex.coin_in_type(),
+            coin_out: dex.coin_out_type(),
+        })
+        .collect()
+}
+
+impl SubmittedTrade {
+    pub fn new(
+        trigger_digest: TransactionDigest,
+        arb_digest: TransactionDigest,
+        sender: SuiAddress,
+        arb_result: &ArbResult,
+        sui_usd: Option<f64>,
+    ) -> Self {
+        let trial = &arb_result.best_trial_result;
+        let source = outcomes::strategy_name(&arb_result.source);
+
+        Self {
+            timestamp_ms: utils::current_time_ms(),
+            trigger_digest,
+            arb_digest,
+            source: source.to_string(),
+            coin_type: trial.coin_type.clone(),
+            base_coin: Some(base::coin_type().to_string()),
+            amount_in: arb_result.routes().map(|route| route.amount_in).sum(),
+            expected_profit: arb_result.profit(),
+            bid_amount: arb_result.source.bid_amount(),
+            partner_rebate: arb_result.routes().map(|route| route.partner_rebate).sum(),
+            legs: trade_legs(arb_result),
+            sui_usd,
+            arm: None,
+            sender: Some(sender),
+        }
+    }
+}
+
+/// Writes entries to a JSON lines file, a `TradeStore`, or both.
+pub struct TradeJournal {
+    writer: Option<Mutex<BufWriter<File>>>,
+    store: Option<Arc<TradeStore>>,
+}
+
+impl TradeJournal {
+    pub fn new(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+
+        Ok(Self {
+            writer: Some(Mutex::new(BufWriter::new(file))),
+            store: None,
+        })
+    }
+
+    /// A journal kept in `store` only.
+    pub fn store_only(store: Arc<TradeStore>) -> Self {
+        Self {
+            writer: None,
+            store: Some(store),
+        }
+    }
+
+    pub fn with_store(mut self, store: Arc<TradeStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    pub fn append(&self, entry: JournalEntry) {
+        if let Some(store) = &self.store {
+            if let Err(error) = store.record(&entry) {
+                warn!(?error, "failed to store journal entry");
+            }
+        }
+        let Some(writer) = &self.writer else {
+            return;
+        };
+
+        let line = match serde_json::to_string(&entry) {
+            Ok(line) => line,
+            Err(error) => {
+                warn!(?error, "failed to serialize journal entry");
+                return;
+            }
+        };
+
+        let mut writer = writer.lock().unwrap();
+        if let Err(error) = writeln!(writer, "{}", line).and_then(|_| writer.flush()) {
+            warn!(?error, "failed to write journal entry");
+        }
+    }
+}
+
+/// Read all entries of a journal file. Malformed lines are skipped with a warning.
+pub fn read_entries(path: impl AsRef<Path>) -> Result<Vec<JournalEntry>> {
+    let file = File::open(path.as_ref()).with_context(|| format!("open {:?}", path.as_ref()))?;
+
+    let mut entries = vec![];
+    for (lineno, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&line) {
+            Ok(entry) => entries.push(entry),
+            Err(error) => warn!(?error, lineno, "skipping malformed journal entry"),
+        }
+    }
+
+    Ok(entries)
+}
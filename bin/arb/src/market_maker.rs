@@ -0,0 +1,532 @@
+//! Two-sided quoting on a DeepBook v2 pool, priced off the AMM mid.
+//!
+//! Every refresh cancels our resting orders and re-posts a bid and an ask around the mid price
+//! implied by the AMM routes (DeepBook edges excluded so we never quote off our own book).
+//! Orders are post-only, so a stale quote can never take liquidity. The side that would push the
+//! base inventory past its limit is not quoted.
+//!
+//! The account cap must already hold deposits in the pool's custodian.
+//!
+//! Example:
+//! cargo run -r --bin arb market-make --pool-id <deepbook_v2_pool> --account-cap <account_cap> \
+//!     --spread-bps 30 --order-size 1000000000
+
+use std::{str::FromStr, sync::Arc, time::Duration};
+
+use clap::Parser;
+use dex_indexer::types::{Pool, PoolExtra, Protocol};
+use eyre::{bail, ensure, eyre, OptionExt, Result, WrapErr};
+use object_pool::ObjectPool;
+use simulator::{HttpSimulator, SimulateCtx, Simulator};
+use sui_json_rpc_types::{SuiObjectDataOptions, SuiTransactionBlockEffectsAPI};
+use sui_sdk::{SuiClient, SuiClientBuilder};
+use sui_types::{
+    base_types::{ObjectID, ObjectRef, SuiAddress},
+    crypto::SuiKeyPair,
+    transaction::{Command, ObjectArg, TransactionData, TransactionKind},
+    Identifier, TypeTag, DEEPBOOK_PACKAGE_ID, SUI_CLOCK_OBJECT_ID,
+};
+use tracing::{info, warn};
+use utils::{coin, object::shared_obj_arg};
+
+use crate::{
+    common::{fatal::FatalKind, get_latest_epoch},
+    config::GAS_BUDGET,
+    defi::{flash_lender::FlashLoanConfig, Defi, Dex, IndexerDexSearcher, Path, TradeCtx, TradeType},
+    executor::PublicTxExecutor,
+    HttpConfig,
+};
+
+// clob_v2 prices are quote per base scaled by 1e9
+const FLOAT_SCALING: f64 = 1_000_000_000.0;
+// clob_v2 order restriction: abort instead of crossing the book
+const POST_OR_ABORT: u8 = 3;
+// clob_v2 self-matching prevention: cancel the oldest order
+const CANCEL_OLDEST: u8 = 0;
+const BID_CLIENT_ORDER_ID: u64 = 1;
+const ASK_CLIENT_ORDER_ID: u64 = 2;
+
+#[derive(Clone, Debug, Parser)]
+pub struct Args {
+    #[arg(long, env = "SUI_PRIVATE_KEY")]
+    pub private_key: String,
+
+    /// DeepBook v2 pool to quote on
+    #[arg(long, env = "SUI_DEEPBOOK_POOL_ID")]
+    pub pool_id: String,
+
+    /// AccountCap owned by the sender, with funds deposited in the pool
+    #[arg(long, env = "SUI_DEEPBOOK_ACCOUNT_CAP")]
+    pub account_cap: String,
+
+    #[command(flatten)]
+    pub http_config: HttpConfig,
+
+    #[command(flatten)]
+    pub mm_config: MarketMakerConfig,
+}
+
+#[derive(Clone, Debug, Parser)]
+pub struct MarketMakerConfig {
+    /// Distance of each side from the mid price, in basis points
+    #[arg(long, env = "SUI_MM_SPREAD_BPS", default_value_t = 30)]
+    pub spread_bps: u64,
+
+    /// Base quantity per order (raw units, rounded down to lot_size)
+    #[arg(long, env = "SUI_MM_ORDER_SIZE")]
+    pub order_size: u64,
+
+    /// Stop bidding once the base inventory reaches this amount (raw units)
+    #[arg(long, env = "SUI_MM_MAX_BASE_INVENTORY", default_value_t = u64::MAX)]
+    pub max_base_inventory: u64,
+
+    /// Stop asking once the base inventory falls to this amount (raw units)
+    #[arg(long, env = "SUI_MM_MIN_BASE_INVENTORY", default_value_t = 0)]
+    pub min_base_inventory: u64,
+
+    /// SUI (in MIST) pushed through the AMM routes to sample the mid price
+    #[arg(long, env = "SUI_MM_PROBE_AMOUNT", default_value_t = 1_000_000_000)]
+    pub probe_amount: u64,
+
+    /// Re-quote interval in milliseconds
+    #[arg(long, env = "SUI_MM_REFRESH_INTERVAL", default_value_t = 5000)]
+    pub refresh_interval: u64,
+
+    /// Orders expire this many refresh intervals after being placed
+    #[arg(long, env = "SUI_MM_EXPIRE_INTERVALS", default_value_t = 3)]
+    pub expire_intervals: u64,
+}
+
+pub async fn run(args: Args) -> Result<()> {
+    mev_logger::init_console_logger_with_directives(None, &["arb=debug", "poison_dart_core=debug"]);
+
+    let keypair = SuiKeyPair::decode(&args.private_key).wrap_err(FatalKind::Key)?;
+    let sender = SuiAddress::from(&keypair.public());
+    let pool_id = ObjectID::from_hex_literal(&args.pool_id).wrap_err(FatalKind::Config)?;
+    let account_cap = ObjectID::from_hex_literal(&args.account_cap).wrap_err(FatalKind::Config)?;
+
+    info!(%sender, %pool_id, %account_cap, config = ?args.mm_config, "start market maker");
+
+    let rpc_url = args.http_config.rpc_url.clone();
+    let ipc_path = args.http_config.ipc_path.clone();
+    let simulator_pool = Arc::new(ObjectPool::new(1, move || {
+        tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(async { Box::new(HttpSimulator::new(&rpc_url, &ipc_path).await) as Box<dyn Simulator> })
+    }));
+
+    let searcher = IndexerDexSearcher::new(&args.http_config.rpc_url, Arc::clone(&simulator_pool)).await?;
+    let pool = searcher
+        .get_indexer()
+        .get_pool_by_id(&pool_id)
+        .ok_or_else(|| eyre!("pool not indexed: {}", pool_id))?;
+
+    let simulator = simulator_pool.get();
+    let maker = MarketMaker {
+        sui: SuiClientBuilder::default()
+            .build(&args.http_config.rpc_url)
+            .await
+            .wrap_err(FatalKind::RpcUnreachable)?,
+        defi: Defi::new(&args.http_config.rpc_url, simulator_pool, &FlashLoanConfig::default()).await?,
+        executor: PublicTxExecutor::new(&args.http_config.rpc_url, keypair).await?,
+        book: Book::new(simulator.as_ref().as_ref(), &pool).await?,
+        sender,
+        account_cap,
+        config: args.mm_config,
+    };
+
+    maker.run().await
+}
+
+/// The DeepBook pool we quote on.
+struct Book {
+    pool_arg: ObjectArg,
+    clock: ObjectArg,
+    base_type: String,
+    quote_type: String,
+    type_params: Vec<TypeTag>,
+    tick_size: u64,
+    lot_size: u64,
+}
+
+impl Book {
+    async fn new(simulator: &dyn Simulator, pool: &Pool) -> Result<Self> {
+        ensure!(pool.protocol == Protocol::DeepbookV2, "not a DeepbookV2 pool");
+        let PoolExtra::DeepbookV2 {
+            tick_size, lot_size, ..
+        } = pool.extra
+        else {
+            bail!("missing DeepbookV2 pool extra");
+        };
+        ensure!(tick_size > 0 && lot_size > 0, "invalid tick_size or lot_size");
+
+        let pool_obj = simulator
+            .get_object(&pool.pool)
+            .await
+            .ok_or_else(|| eyre!("pool not found: {}", pool.pool))?;
+        let clock = simulator
+            .get_object(&SUI_CLOCK_OBJECT_ID)
+            .await
+            .ok_or_eyre("clock not found")?;
+
+        // Pool<BaseAsset, QuoteAsset>
+        let base_type = pool.token0_type();
+        let quote_type = pool.token1_type();
+        let type_params = vec![
+            TypeTag::from_str(&base_type).map_err(|e| eyre!(e))?,
+            TypeTag::from_str(&quote_type).map_err(|e| eyre!(e))?,
+        ];
+
+        Ok(Self {
+            pool_arg: shared_obj_arg(&pool_obj, true),
+            clock: shared_obj_arg(&clock, false),
+            base_type,
+            quote_type,
+            type_params,
+            tick_size,
+            lot_size,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Quote {
+    price: u64,
+    quantity: u64,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct Quotes {
+    bid: Option<Quote>,
+    ask: Option<Quote>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Inventory {
+    base: u64,
+    quote: u64,
+}
+
+struct MarketMaker {
+    sui: SuiClient,
+    defi: Defi,
+    executor: PublicTxExecutor,
+    book: Book,
+    sender: SuiAddress,
+    account_cap: ObjectID,
+    config: MarketMakerConfig,
+}
+
+impl MarketMaker {
+    async fn run(&self) -> Result<()> {
+        let interval = Duration::from_millis(self.config.refresh_interval);
+
+        loop {
+            if let Err(error) = self.refresh().await {
+                warn!(?error, "market maker refresh failed");
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    async fn refresh(&self) -> Result<()> {
+        let account_cap = self.account_cap_ref().await?;
+        let gas_coins = coin::get_gas_coin_refs(&self.sui, self.sender, None).await?;
+        let epoch = get_latest_epoch(&self.sui).await?;
+        let sim_ctx = SimulateCtx::new(epoch, vec![]);
+
+        let mid = self.amm_mid_price(&gas_coins, &sim_ctx).await?;
+        let inventory = self.inventory(account_cap, sim_ctx.epoch.gas_price).await?;
+        let quotes = quotes(mid, inventory, &self.config, self.book.tick_size, self.book.lot_size);
+
+        let tx_data = self.requote_tx(account_cap, quotes, gas_coins, sim_ctx.epoch.gas_price)?;
+        let resp = self.executor.execute_tx(tx_data).await?;
+        let status_ok = resp.effects.as_ref().is_some_and(|e| e.status().is_ok());
+
+        info!(
+            target: "market_maker",
+            mid,
+            base_inventory = inventory.base,
+            quote_inventory = inventory.quote,
+            bid = ?quotes.bid,
+            ask = ?quotes.ask,
+            digest = %resp.digest,
+            status_ok,
+            "requoted"
+        );
+        Ok(())
+    }
+
+    /// AMM mid price of base in quote, in clob_v2 price units.
+    ///
+    /// Both legs are bought with the same amount of SUI over the best non-DeepBook route, so the
+    /// route fees roughly cancel out in the ratio.
+    async fn amm_mid_price(&self, gas_coins: &[ObjectRef], sim_ctx: &SimulateCtx) -> Result<f64> {
+        let base_out = self.amm_buy(&self.book.base_type, gas_coins, sim_ctx).await?;
+        let quote_out = self.amm_buy(&self.book.quote_type, gas_coins, sim_ctx).await?;
+        ensure!(base_out > 0, "no AMM liquidity for base");
+
+        Ok(quote_out as f64 / base_out as f64 * FLOAT_SCALING)
+    }
+
+    async fn amm_buy(&self, coin_type: &str, gas_coins: &[ObjectRef], sim_ctx: &SimulateCtx) -> Result<u64> {
+        if coin::is_native_coin(coin_type) {
+            return Ok(self.config.probe_amount);
+        }
+
+        let paths: Vec<Path> = self
+            .defi
+            .find_buy_paths(coin_type)
+            .await?
+            .into_iter()
+            .filter(|path| {
+                !path
+                    .path
+                    .iter()
+                    .any(|dex| matches!(dex.protocol(), Protocol::DeepbookV2 | Protocol::DeepbookV3))
+            })
+            .collect();
+        ensure!(!paths.is_empty(), "no AMM route for {}", coin_type);
+
+        let best = self
+            .defi
+            .find_best_path_exact_in(
+                &paths,
+                self.sender,
+                self.config.probe_amount,
+                TradeType::Swap,
+                gas_coins,
+                sim_ctx,
+            )
+            .await?;
+
+        Ok(best.amount_out)
+    }
+
+    async fn account_cap_ref(&self) -> Result<ObjectRef> {
+        self.sui
+            .read_api()
+            .get_object_with_options(self.account_cap, SuiObjectDataOptions::new())
+            .await?
+            .object_ref_if_exists()
+            .ok_or_else(|| eyre!("account cap not found: {}", self.account_cap))
+    }
+
+    /*
+    public fun account_balance<BaseAsset, QuoteAsset>(
+        pool: &Pool<BaseAsset, QuoteAsset>,
+        account_cap: &AccountCap
+    ): (u64, u64, u64, u64)
+    */
+    /// Custodian balances (available + locked) of our account cap.
+    async fn inventory(&self, account_cap: ObjectRef, gas_price: u64) -> Result<Inventory> {
+        let mut ctx = TradeCtx::default();
+        let pool_arg = ctx.obj(self.book.pool_arg).map_err(|e| eyre!(e))?;
+        let account_cap_arg = ctx
+            .obj(ObjectArg::ImmOrOwnedObject(account_cap))
+            .map_err(|e| eyre!(e))?;
+        ctx.command(Command::move_call(
+            DEEPBOOK_PACKAGE_ID,
+            Identifier::new("clob_v2").map_err(|e| eyre!(e))?,
+            Identifier::new("account_balance").map_err(|e| eyre!(e))?,
+            self.book.type_params.clone(),
+            vec![pool_arg, account_cap_arg],
+        ));
+
+        let results = self
+            .sui
+            .read_api()
+            .dev_inspect_transaction_block(
+                self.sender,
+                TransactionKind::programmable(ctx.ptb.finish()),
+                Some(gas_price.into()),
+                None,
+                None,
+            )
+            .await?
+            .results
+            .ok_or_eyre("account_balance returned nothing")?;
+
+        let values = results
+            .first()
+            .ok_or_eyre("account_balance returned nothing")?
+            .return_values
+            .iter()
+            .map(|(bytes, _)| bcs::from_bytes::<u64>(bytes).map_err(|e| eyre!(e)))
+            .collect::<Result<Vec<_>>>()?;
+        ensure!(values.len() == 4, "unexpected account_balance return values");
+
+        Ok(Inventory {
+            base: values[0] + values[1],
+            quote: values[2] + values[3],
+        })
+    }
+
+    /*
+    public fun cancel_all_orders<BaseAsset, QuoteAsset>(
+        pool: &mut Pool<BaseAsset, QuoteAsset>,
+        account_cap: &AccountCap
+    )
+
+    public fun place_limit_order<BaseAsset, QuoteAsset>(
+        pool: &mut Pool<BaseAsset, QuoteAsset>,
+        client_order_id: u64,
+        price: u64,
+        quantity: u64,
+        self_matching_prevention: u8,
+        is_bid: bool,
+        expire_timestamp: u64,
+        restriction: u8,
+        clock: &Clock,
+        account_cap: &AccountCap,
+        ctx: &mut TxContext
+    ): (u64, u64, bool, u64)
+    */
+    fn requote_tx(
+        &self,
+        account_cap: ObjectRef,
+        quotes: Quotes,
+        gas_coins: Vec<ObjectRef>,
+        gas_price: u64,
+    ) -> Result<TransactionData> {
+        let mut ctx = TradeCtx::default();
+        let module = Identifier::new("clob_v2").map_err(|e| eyre!(e))?;
+        let pool_arg = ctx.obj(self.book.pool_arg).map_err(|e| eyre!(e))?;
+        let account_cap_arg = ctx
+            .obj(ObjectArg::ImmOrOwnedObject(account_cap))
+            .map_err(|e| eyre!(e))?;
+
+        ctx.command(Command::move_call(
+            DEEPBOOK_PACKAGE_ID,
+            module.clone(),
+            Identifier::new("cancel_all_orders").map_err(|e| eyre!(e))?,
+            self.book.type_params.clone(),
+            vec![pool_arg, account_cap_arg],
+        ));
+
+        let expire_timestamp = utils::current_time_ms() + self.config.refresh_interval * self.config.expire_intervals;
+        let sides = [(quotes.bid, true, BID_CLIENT_ORDER_ID), (quotes.ask, false, ASK_CLIENT_ORDER_ID)];
+        for (quote, is_bid, client_order_id) in sides {
+            let Some(quote) = quote else {
+                continue;
+            };
+
+            let arguments = vec![
+                pool_arg,
+                ctx.pure(client_order_id).map_err(|e| eyre!(e))?,
+                ctx.pure(quote.price).map_err(|e| eyre!(e))?,
+                ctx.pure(quote.quantity).map_err(|e| eyre!(e))?,
+                ctx.pure(CANCEL_OLDEST).map_err(|e| eyre!(e))?,
+                ctx.pure(is_bid).map_err(|e| eyre!(e))?,
+                ctx.pure(expire_timestamp).map_err(|e| eyre!(e))?,
+                ctx.pure(POST_OR_ABORT).map_err(|e| eyre!(e))?,
+                ctx.obj(self.book.clock).map_err(|e| eyre!(e))?,
+                account_cap_arg,
+            ];
+            ctx.command(Command::move_call(
+                DEEPBOOK_PACKAGE_ID,
+                module.clone(),
+                Identifier::new("place_limit_order").map_err(|e| eyre!(e))?,
+                self.book.type_params.clone(),
+                arguments,
+            ));
+        }
+
+        Ok(TransactionData::new_programmable(
+            self.sender,
+            gas_coins,
+            ctx.ptb.finish(),
+            GAS_BUDGET,
+            gas_price,
+        ))
+    }
+}
+
+/// Bid and ask around `mid`, rounded away from it to `tick_size`, skipping any side that would
+/// breach the inventory limits or that we cannot fund.
+fn quotes(mid: f64, inventory: Inventory, config: &MarketMakerConfig, tick_size: u64, lot_size: u64) -> Quotes {
+    let quantity = config.order_size / lot_size * lot_size;
+    if quantity == 0 || !mid.is_finite() || mid <= 0.0 {
+        return Quotes::default();
+    }
+
+    let half_spread = config.spread_bps as f64 / 10_000.0;
+    let bid_price = (mid * (1.0 - half_spread) / tick_size as f64).floor() as u64 * tick_size;
+    let ask_price = (mid * (1.0 + half_spread) / tick_size as f64).ceil() as u64 * tick_size;
+
+    let bid_cost = (bid_price as u128 * quantity as u128 / FLOAT_SCALING as u128) as u64;
+    let bid = (bid_price > 0
+        && inventory.base.saturating_add(quantity) <= config.max_base_inventory
+        && bid_cost <= inventory.quote)
+        .then_some(Quote {
+            price: bid_price,
+            quantity,
+        });
+
+    let ask = (inventory.base >= quantity && inventory.base - quantity >= config.min_base_inventory).then_some(Quote {
+        price: ask_price,
+        quantity,
+    });
+
+    Quotes { bid, ask }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> MarketMakerConfig {
+        MarketMakerConfig {
+            spread_bps: 100,
+            order_size: 1_050,
+            max_base_inventory: 10_000,
+            min_base_inventory: 1_000,
+            probe_amount: 1_000_000_000,
+            refresh_interval: 5000,
+            expire_intervals: 3,
+        }
+    }
+
+    #[test]
+    fn test_quotes_round_to_ticks_and_lots() {
+        let inventory = Inventory {
+            base: 5_000,
+            quote: u64::MAX,
+        };
+        let quotes = quotes(2_000_000_000.0, inventory, &config(), 1_000_000, 100);
+
+        assert_eq!(
+            quotes.bid,
+            Some(Quote {
+                price: 1_980_000_000,
+                quantity: 1_000
+            })
+        );
+        assert_eq!(
+            quotes.ask,
+            Some(Quote {
+                price: 2_020_000_000,
+                quantity: 1_000
+            })
+        );
+    }
+
+    #[test]
+    fn test_quotes_respect_inventory_limits() {
+        let full = Inventory {
+            base: 9_500,
+            quote: u64::MAX,
+        };
+        let quotes_full = quotes(2_000_000_000.0, full, &config(), 1_000_000, 100);
+        assert!(quotes_full.bid.is_none());
+        assert!(quotes_full.ask.is_some());
+
+        let empty = Inventory {
+            base: 1_500,
+            quote: u64::MAX,
+        };
+        let quotes_empty = quotes(2_000_000_000.0, empty, &config(), 1_000_000, 100);
+        assert!(quotes_empty.bid.is_some());
+        assert!(quotes_empty.ask.is_none());
+    }
+}
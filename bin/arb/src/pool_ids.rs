@@ -1,15 +1,27 @@
-use std::collections::HashSet;
-use std::fs;
+//! `pool-ids`: list the pool and related objects the simulator should preload.
+//!
+//! Next to the result file goes a snapshot, `<result_path>.snapshot.json`, with the related
+//! objects of every pool, the pool's type, the checkpoint it was taken at, a version bumped on
+//! every write and a checksum of the listed ids. With `--incremental` only pools the previous
+//! snapshot doesn't have, or whose object changed type (migrated), are resolved again; pools whose
+//! object is gone are dropped. `start_bot` checks its preload file against the snapshot's checksum.
+
+use std::collections::{BTreeMap, HashSet};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::Arc;
 
 use clap::Parser;
 use dex_indexer::{types::Protocol, DexIndexer};
-use eyre::Result;
+use eyre::{ensure, Context, Result};
+use fastcrypto::encoding::{Encoding, Hex};
+use fastcrypto::hash::{Blake2b256, HashFunction};
 use mev_logger::LevelFilter;
 use object_pool::ObjectPool;
+use serde::{Deserialize, Serialize};
 use simulator::{DBSimulator, SimulateCtx, Simulator};
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::{BufRead, BufReader, BufWriter, Write};
 use sui_sdk::types::{
     BRIDGE_PACKAGE_ID, DEEPBOOK_PACKAGE_ID, MOVE_STDLIB_PACKAGE_ID, SUI_AUTHENTICATOR_STATE_OBJECT_ID,
@@ -20,20 +32,28 @@ use sui_sdk::SuiClientBuilder;
 use sui_types::base_types::{ObjectID, SuiAddress};
 use sui_types::object::{Object, Owner};
 use sui_types::transaction::{InputObjectKind, ObjectReadResult};
-use tracing::info;
+use tracing::{info, warn};
 
 use crate::common::get_latest_epoch;
-use crate::defi::{DexSearcher, IndexerDexSearcher, TradeType, Trader};
-use crate::HttpConfig;
+use crate::defi::{flash_lender::FlashLoanConfig, DexSearcher, IndexerDexSearcher, TradeType, Trader};
+use crate::{HttpConfig, OutputFormat};
 
 #[derive(Clone, Debug, Parser)]
 pub struct Args {
     #[clap(long, default_value = "./pool_related_ids.txt")]
     pub result_path: String,
 
+    /// Format of the result file; `--test` and the DB simulator only read `text`
+    #[clap(long, value_enum, default_value_t = OutputFormat::Text)]
+    pub output: OutputFormat,
+
     #[command(flatten)]
     pub http_config: HttpConfig,
 
+    /// Reuse the previous snapshot, resolving only new and migrated pools
+    #[clap(long)]
+    pub incremental: bool,
+
     #[clap(long, help = "Run test only")]
     pub test: bool,
 
@@ -72,6 +92,7 @@ pub async fn run(args: Args) -> Result<()> {
         Some(LevelFilter::INFO),
         &[
             "arb=debug",
+            "poison_dart_core=debug",
             // "dex_indexer=warn",
             // "simulator=trace",
             // "sui_types=trace",
@@ -80,29 +101,41 @@ pub async fn run(args: Args) -> Result<()> {
         ],
     );
     if args.test {
+        ensure!(args.output == OutputFormat::Text, "--test reads a text result file");
         return test_pool_related_objects(args).await;
     }
 
     let result_path = args.result_path;
     let rpc_url = args.http_config.rpc_url;
+    let snapshot_path = snapshot_path(Path::new(&result_path));
+
+    // a full run only takes the version from the previous snapshot, so it may be unreadable
+    let previous = match PoolIdSnapshot::load(&snapshot_path) {
+        Ok(previous) => previous,
+        Err(error) if !args.incremental => {
+            warn!(?error, "ignoring the previous snapshot");
+            None
+        }
+        Err(error) => return Err(error),
+    };
+    let reusable = previous.as_ref().filter(|_| args.incremental);
+    if args.incremental && reusable.is_none() {
+        info!(?snapshot_path, "no previous snapshot, resolving every pool");
+    }
 
+    let sui = SuiClientBuilder::default().build(&rpc_url).await?;
+    let checkpoint = sui.read_api().get_latest_checkpoint_sequence_number().await?;
     let dex_indexer = DexIndexer::new(&rpc_url).await?;
     let simulator: Arc<dyn Simulator> = Arc::new(DBSimulator::new_default_slow().await);
 
-    let _ = fs::remove_file(&result_path);
-    let file = File::create(&result_path)?;
-    let mut writer = BufWriter::new(file);
-
-    // load existing ids
-    let mut object_ids: HashSet<String> = fs::read_to_string(&result_path)?
-        .lines()
-        .map(|line| line.to_string())
-        .collect();
-
-    // add new ids
+    let mut records = vec![];
+    let mut pools = BTreeMap::new();
+    let mut refresh = Refresh::default();
     for protocol in supported_protocols() {
         // protocol related ids
-        object_ids.extend(protocol.related_object_ids().await?);
+        for id in protocol.related_object_ids().await? {
+            records.push(PoolIdRecord::new(id, Some(&protocol), None));
+        }
         if protocol == Protocol::Navi {
             // Navi pools are not indexed
             continue;
@@ -110,19 +143,244 @@ pub async fn run(args: Args) -> Result<()> {
 
         // pool related ids
         for pool in dex_indexer.get_all_pools(&protocol)? {
-            object_ids.extend(pool.related_object_ids(simulator.clone()).await);
+            let Some(pool_type) = pool_type(&simulator, &pool.pool).await else {
+                refresh.removed += 1;
+                continue;
+            };
+            let known = reusable.and_then(|snapshot| snapshot.pools.get(&pool.pool));
+            let object_ids = match known {
+                Some(known) if known.pool_type == pool_type => {
+                    refresh.reused += 1;
+                    known.object_ids.clone()
+                }
+                known => {
+                    if known.is_some() {
+                        refresh.migrated += 1;
+                    } else {
+                        refresh.added += 1;
+                    }
+                    let mut ids: Vec<String> = pool.related_object_ids(simulator.clone()).await.into_iter().collect();
+                    ids.sort();
+                    ids
+                }
+            };
+            for id in &object_ids {
+                records.push(PoolIdRecord::new(id.clone(), Some(&protocol), Some(pool.pool)));
+            }
+            let protocol = protocol.to_string();
+            pools.insert(
+                pool.pool,
+                SnapshotPool {
+                    protocol,
+                    pool_type,
+                    object_ids,
+                },
+            );
         }
     }
+    // pools the indexer no longer lists
+    refresh.removed += reusable.map_or(0, |snapshot| {
+        snapshot.pools.keys().filter(|id| !pools.contains_key(id)).count()
+    });
+    for id in global_ids() {
+        records.push(PoolIdRecord::new(id, None, None));
+    }
 
-    object_ids.extend(global_ids());
-
-    let all_ids: Vec<String> = object_ids.into_iter().collect();
-    writeln!(writer, "{}", all_ids.join("\n"))?;
+    // an object shared by several pools is listed once, under the first
+    let mut seen = HashSet::new();
+    records.retain(|record| seen.insert(record.object_id.clone()));
 
+    let file = File::create(&result_path)?;
+    let mut writer = BufWriter::new(file);
+    write_records(&mut writer, &records, args.output)?;
     writer.flush()?;
 
-    info!("🎉 write pool and related object ids to {}", result_path);
+    let ids = records
+        .iter()
+        .map(|record| ObjectID::from_str(&record.object_id))
+        .collect::<Result<Vec<_>, _>>()?;
+    let snapshot = PoolIdSnapshot {
+        format: SNAPSHOT_FORMAT,
+        version: previous.map_or(1, |previous| previous.version + 1),
+        checkpoint,
+        timestamp_ms: utils::current_time_ms(),
+        checksum: checksum(ids),
+        pools,
+    };
+    snapshot.save(&snapshot_path)?;
+
+    info!(
+        "🎉 write {} pool and related object ids to {}",
+        records.len(),
+        result_path
+    );
+    info!(
+        version = snapshot.version,
+        checkpoint,
+        ?refresh,
+        ?snapshot_path,
+        "pool snapshot written"
+    );
+
+    Ok(())
+}
+
+/// Layout of the snapshot file; bumped when it changes incompatibly.
+const SNAPSHOT_FORMAT: u32 = 1;
+
+/// The pools a result file was written from.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PoolIdSnapshot {
+    /// `SNAPSHOT_FORMAT` when written
+    pub format: u32,
+    /// Bumped on every write
+    pub version: u64,
+    /// Latest checkpoint when the pools were read
+    pub checkpoint: u64,
+    pub timestamp_ms: u64,
+    /// Of the object ids in the result file, see `checksum`
+    pub checksum: String,
+    pub pools: BTreeMap<ObjectID, SnapshotPool>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotPool {
+    pub protocol: String,
+    /// Type of the pool object; a pool whose type changed migrated and is resolved again
+    pub pool_type: String,
+    pub object_ids: Vec<String>,
+}
+
+impl PoolIdSnapshot {
+    /// The snapshot at `path`, none if there is no file.
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        let bytes = match fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error.into()),
+        };
+        let snapshot: Self = serde_json::from_slice(&bytes).with_context(|| format!("parse {:?}", path))?;
+        ensure!(
+            snapshot.format == SNAPSHOT_FORMAT,
+            "{:?} has snapshot format {}, expected {}",
+            path,
+            snapshot.format,
+            SNAPSHOT_FORMAT
+        );
+        Ok(Some(snapshot))
+    }
+
+    // written whole to a temporary file and renamed over the snapshot, never left half written
+    fn save(&self, path: &Path) -> Result<()> {
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(self)?)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
+
+/// How the pools of a run compare to the previous snapshot.
+#[derive(Debug, Default)]
+struct Refresh {
+    reused: usize,
+    added: usize,
+    migrated: usize,
+    removed: usize,
+}
+
+fn snapshot_path(result_path: &Path) -> PathBuf {
+    let mut path = OsString::from(result_path.as_os_str());
+    path.push(".snapshot.json");
+    PathBuf::from(path)
+}
+
+/// Hex Blake2b-256 of `ids`, sorted and deduplicated, so it doesn't depend on the order or
+/// format they were written in.
+fn checksum(ids: impl IntoIterator<Item = ObjectID>) -> String {
+    let ids: std::collections::BTreeSet<ObjectID> = ids.into_iter().collect();
+    let mut hasher = Blake2b256::default();
+    for id in ids {
+        hasher.update(id);
+    }
+    Hex::encode(hasher.finalize().digest)
+}
+
+// None once the pool object is deleted or wrapped
+async fn pool_type(simulator: &Arc<dyn Simulator>, pool_id: &ObjectID) -> Option<String> {
+    let object = simulator.get_object(pool_id).await?;
+    Some(object.struct_tag()?.to_string())
+}
 
+/// Check the preload list at `path`, one id per line, against the snapshot written with it. A list
+/// without a snapshot passes.
+pub fn verify_snapshot(path: &Path) -> Result<()> {
+    let snapshot_path = snapshot_path(path);
+    let Some(snapshot) = PoolIdSnapshot::load(&snapshot_path)? else {
+        info!(?path, "no pool snapshot, preload list not verified");
+        return Ok(());
+    };
+    let ids = fs::read_to_string(path)?
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| ObjectID::from_str(line.trim()))
+        .collect::<Result<Vec<_>, _>>()?;
+    ensure!(
+        checksum(ids) == snapshot.checksum,
+        "{:?} doesn't match its snapshot (version {}), rerun pool-ids",
+        path,
+        snapshot.version
+    );
+    info!(
+        version = snapshot.version,
+        checkpoint = snapshot.checkpoint,
+        pools = snapshot.pools.len(),
+        "pool snapshot verified"
+    );
+    Ok(())
+}
+
+/// An object the simulator should preload, with what it was found through.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+struct PoolIdRecord {
+    object_id: String,
+    /// Protocol the object belongs to, none for system objects
+    protocol: Option<String>,
+    /// Pool the object belongs to, none for protocol-wide and system objects
+    pool_id: Option<ObjectID>,
+}
+
+impl PoolIdRecord {
+    fn new(object_id: String, protocol: Option<&Protocol>, pool_id: Option<ObjectID>) -> Self {
+        Self {
+            object_id,
+            protocol: protocol.map(|protocol| protocol.to_string()),
+            pool_id,
+        }
+    }
+}
+
+/// Text is one id per line, the format `--test` and the DB simulator read back.
+fn write_records(writer: &mut impl Write, records: &[PoolIdRecord], output: OutputFormat) -> Result<()> {
+    match output {
+        OutputFormat::Text => {
+            for record in records {
+                writeln!(writer, "{}", record.object_id)?;
+            }
+        }
+        OutputFormat::Json => serde_json::to_writer_pretty(&mut *writer, records)?,
+        OutputFormat::Csv => {
+            writeln!(writer, "object_id,protocol,pool_id")?;
+            for record in records {
+                writeln!(
+                    writer,
+                    "{},{},{}",
+                    record.object_id,
+                    record.protocol.as_deref().unwrap_or(""),
+                    record.pool_id.map(|id| id.to_string()).unwrap_or_default()
+                )?;
+            }
+        }
+    }
     Ok(())
 }
 
@@ -192,7 +450,7 @@ async fn test_pool_related_objects(args: Args) -> Result<()> {
 
     let sim_ctx = SimulateCtx::new(epoch, override_objects);
 
-    let trader = Trader::new(simulator_pool).await?;
+    let trader = Trader::new(simulator_pool, &FlashLoanConfig::default()).await?;
     let result = trader
         .get_trade_result(&path, sender, amount_in, TradeType::Flashloan, vec![], sim_ctx)
         .await?;
@@ -230,3 +488,65 @@ async fn pool_related_objects(file_path: &str) -> Result<Vec<ObjectReadResult>>
 
     Ok(res)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_records() {
+        let pool_id = ObjectID::from_hex_literal("0x3c3d").unwrap();
+        let records = vec![
+            PoolIdRecord::new("0x3c3d".to_string(), Some(&Protocol::Cetus), Some(pool_id)),
+            PoolIdRecord::new("0x6".to_string(), None, None),
+        ];
+
+        let mut text = vec![];
+        write_records(&mut text, &records, OutputFormat::Text).unwrap();
+        assert_eq!(String::from_utf8(text).unwrap(), "0x3c3d\n0x6\n");
+
+        let mut csv = vec![];
+        write_records(&mut csv, &records, OutputFormat::Csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "object_id,protocol,pool_id");
+        assert_eq!(lines[1], format!("0x3c3d,{},{}", Protocol::Cetus, pool_id));
+        assert_eq!(lines[2], "0x6,,");
+
+        let mut json = vec![];
+        write_records(&mut json, &records, OutputFormat::Json).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(json[1]["object_id"], "0x6");
+        assert!(json[1]["pool_id"].is_null());
+    }
+
+    #[test]
+    fn test_verify_snapshot() {
+        let (a, b) = (ObjectID::from_single_byte(1), ObjectID::from_single_byte(2));
+        assert_eq!(checksum([a, b]), checksum([b, a, b]));
+        assert_ne!(checksum([a, b]), checksum([a]));
+
+        let path = std::env::temp_dir().join(format!("pool-ids-{}.txt", std::process::id()));
+        fs::write(&path, format!("{}\n0x2\n", a)).unwrap();
+        // no snapshot yet
+        verify_snapshot(&path).unwrap();
+
+        let snapshot = PoolIdSnapshot {
+            format: SNAPSHOT_FORMAT,
+            version: 3,
+            checkpoint: 100,
+            timestamp_ms: 0,
+            checksum: checksum([a, b]),
+            pools: BTreeMap::new(),
+        };
+        snapshot.save(&snapshot_path(&path)).unwrap();
+        assert_eq!(PoolIdSnapshot::load(&snapshot_path(&path)).unwrap(), Some(snapshot));
+        verify_snapshot(&path).unwrap();
+
+        fs::write(&path, format!("{}\n", a)).unwrap();
+        assert!(verify_snapshot(&path).is_err());
+
+        fs::remove_file(snapshot_path(&path)).unwrap();
+        fs::remove_file(&path).unwrap();
+    }
+}
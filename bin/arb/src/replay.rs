@@ -0,0 +1,523 @@
+//! Recorded triggers and decisions, and a reader that re-drives them through `Arb`.
+//!
+//! The on-disk format is JSON Lines. The first line is always a `Header` carrying the
+//! format version; every following line is a `Record` pairing the trigger the strategy
+//! dispatched to a worker with the decision the worker reached. A trigger carries the objects the
+//! search pinned along with the overrides it was dispatched with, and the replay runs on them, so a
+//! record reproduces against the state it was decided on rather than the chain's current one.
+//!
+//! Example:
+//! cargo run -r --bin arb replay --path ./replay/sui-arb.jsonl --sender 0x...
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+use clap::Parser;
+use eyre::{bail, ensure, eyre, Context, Result};
+use fastcrypto::encoding::{Base64, Encoding};
+use object_pool::ObjectPool;
+use serde::{Deserialize, Serialize};
+use simulator::{HttpSimulator, SimEpoch, SimulateCtx, Simulator};
+use sui_types::{
+    base_types::{ObjectID, SuiAddress},
+    digests::TransactionDigest,
+    object::Object,
+    transaction::{InputObjectKind, ObjectReadResult, ObjectReadResultKind},
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::{
+    arb::{Arb, Sizing},
+    defi::{flash_lender::FlashLoanConfig, Dex},
+    graph_path_finder::GraphSettings,
+    types::Source,
+    HttpConfig, BUILD_VERSION,
+};
+
+pub const REPLAY_FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Header {
+    pub version: u32,
+    pub build_version: String,
+    pub created_at_ms: u64,
+}
+
+/// Everything a worker needs to reproduce `Arb::find_opportunity` for one arb item.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Trigger {
+    pub coin: String,
+    pub pool_id: Option<ObjectID>,
+    pub tx_digest: TransactionDigest,
+    pub source: Source,
+    /// How the worker sized the trade; records from before experiments used local sizing
+    #[serde(default)]
+    pub sizing: Sizing,
+    pub epoch: RecordedEpoch,
+    pub override_objects: Vec<RecordedObject>,
+    /// The objects the search pinned, at the versions it read; records from before pinning have none
+    #[serde(default)]
+    pub pinned_objects: Vec<RecordedObject>,
+    pub received_at_ms: u64,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct RecordedEpoch {
+    pub epoch_id: u64,
+    pub epoch_start_timestamp: u64,
+    pub epoch_duration_ms: u64,
+    pub gas_price: u64,
+}
+
+/// An override object as (input kind, base64 bcs of the object).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedObject {
+    pub kind: InputObjectKind,
+    pub object_bcs: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum Decision {
+    NoOpportunity {
+        reason: String,
+    },
+    Opportunity {
+        amount_in: u64,
+        profit: u64,
+        path: Vec<ObjectID>,
+        arb_tx_digest: TransactionDigest,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Record {
+    pub trigger: Trigger,
+    pub decision: Decision,
+    pub decided_at_ms: u64,
+}
+
+impl From<SimEpoch> for RecordedEpoch {
+    fn from(epoch: SimEpoch) -> Self {
+        Self {
+            epoch_id: epoch.epoch_id,
+            epoch_start_timestamp: epoch.epoch_start_timestamp,
+            epoch_duration_ms: epoch.epoch_duration_ms,
+            gas_price: epoch.gas_price,
+        }
+    }
+}
+
+impl From<RecordedEpoch> for SimEpoch {
+    fn from(epoch: RecordedEpoch) -> Self {
+        Self {
+            epoch_id: epoch.epoch_id,
+            epoch_start_timestamp: epoch.epoch_start_timestamp,
+            epoch_duration_ms: epoch.epoch_duration_ms,
+            gas_price: epoch.gas_price,
+        }
+    }
+}
+
+impl RecordedObject {
+    pub fn from_object_read_result(obj: &ObjectReadResult) -> Option<Self> {
+        match &obj.object {
+            ObjectReadResultKind::Object(object) => Some(Self {
+                kind: obj.input_object_kind,
+                object_bcs: Base64::encode(bcs::to_bytes(object).ok()?),
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn to_object_read_result(&self) -> Result<ObjectReadResult> {
+        let bytes = Base64::decode(&self.object_bcs).map_err(|e| eyre!(e))?;
+        let object: Object = bcs::from_bytes(&bytes)?;
+        Ok(ObjectReadResult::new(self.kind, object.into()))
+    }
+}
+
+impl Trigger {
+    pub fn new(
+        coin: &str,
+        pool_id: Option<ObjectID>,
+        tx_digest: TransactionDigest,
+        sim_ctx: &SimulateCtx,
+        source: Source,
+        sizing: Sizing,
+    ) -> Self {
+        Self {
+            coin: coin.to_string(),
+            pool_id,
+            tx_digest,
+            source,
+            sizing,
+            epoch: sim_ctx.epoch.into(),
+            override_objects: sim_ctx
+                .override_objects
+                .iter()
+                .filter_map(RecordedObject::from_object_read_result)
+                .collect(),
+            pinned_objects: vec![],
+            received_at_ms: utils::current_time_ms(),
+        }
+    }
+
+    pub fn with_pinned(mut self, pinned: &[ObjectReadResult]) -> Self {
+        self.pinned_objects = pinned
+            .iter()
+            .filter_map(RecordedObject::from_object_read_result)
+            .collect();
+        self
+    }
+
+    /// The context the search ran on: the trigger's overrides, then what it pinned. The search
+    /// doesn't read the pinned objects again, overridden as they are.
+    pub fn sim_ctx(&self) -> Result<SimulateCtx> {
+        let override_objects = self
+            .override_objects
+            .iter()
+            .chain(&self.pinned_objects)
+            .map(RecordedObject::to_object_read_result)
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(SimulateCtx::new(self.epoch.into(), override_objects))
+    }
+}
+
+/// Appends records to a replay file. Safe to share between workers.
+pub struct ReplayRecorder {
+    writer: Mutex<BufWriter<File>>,
+}
+
+impl ReplayRecorder {
+    pub fn new(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let is_new = !path.exists() || std::fs::metadata(path)?.len() == 0;
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let mut writer = BufWriter::new(file);
+
+        if is_new {
+            let header = Header {
+                version: REPLAY_FORMAT_VERSION,
+                build_version: BUILD_VERSION.to_string(),
+                created_at_ms: utils::current_time_ms(),
+            };
+            writeln!(writer, "{}", serde_json::to_string(&header)?)?;
+            writer.flush()?;
+        }
+
+        Ok(Self {
+            writer: Mutex::new(writer),
+        })
+    }
+
+    pub fn record(&self, trigger: Trigger, decision: Decision) {
+        let record = Record {
+            trigger,
+            decision,
+            decided_at_ms: utils::current_time_ms(),
+        };
+
+        let line = match serde_json::to_string(&record) {
+            Ok(line) => line,
+            Err(error) => {
+                warn!(?error, "failed to serialize replay record");
+                return;
+            }
+        };
+
+        let mut writer = self.writer.lock().unwrap();
+        if let Err(error) = writeln!(writer, "{}", line).and_then(|_| writer.flush()) {
+            warn!(?error, "failed to write replay record");
+        }
+    }
+}
+
+/// Reads a replay file, checking the header version before yielding records.
+pub struct ReplayReader {
+    header: Header,
+    lines: std::io::Lines<BufReader<File>>,
+}
+
+impl ReplayReader {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let file = File::open(path.as_ref()).with_context(|| format!("open {:?}", path.as_ref()))?;
+        let mut lines = BufReader::new(file).lines();
+
+        let header_line = lines.next().ok_or_else(|| eyre!("empty replay file"))??;
+        let header: Header = serde_json::from_str(&header_line).context("invalid replay header")?;
+        ensure!(
+            header.version == REPLAY_FORMAT_VERSION,
+            "unsupported replay format version {} (expected {})",
+            header.version,
+            REPLAY_FORMAT_VERSION
+        );
+
+        Ok(Self { header, lines })
+    }
+
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+}
+
+impl Iterator for ReplayReader {
+    type Item = Result<Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(e) => return Some(Err(e.into())),
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            return Some(serde_json::from_str(&line).map_err(Into::into));
+        }
+    }
+}
+
+#[derive(Clone, Debug, Parser)]
+pub struct Args {
+    /// Replay file written by `start-bot --replay-record-path`
+    #[arg(long)]
+    pub path: String,
+
+    /// Sender the records are replayed for; the file doesn't record it
+    #[arg(long)]
+    pub sender: String,
+
+    /// Only replay records for this coin type
+    #[arg(long)]
+    pub coin_type: Option<String>,
+
+    /// Replay at most this many records
+    #[arg(long)]
+    pub limit: Option<usize>,
+
+    #[command(flatten)]
+    pub http_config: HttpConfig,
+}
+
+pub async fn run(args: Args) -> Result<()> {
+    mev_logger::init_console_logger_with_directives(None, &["arb=info"]);
+
+    let reader = ReplayReader::open(&args.path)?;
+    info!(header = ?reader.header(), "replaying {}", args.path);
+
+    let sender = SuiAddress::from_str(&args.sender).map_err(|e| eyre!(e))?;
+    let rpc_url = args.http_config.rpc_url.clone();
+    let ipc_path = args.http_config.ipc_path.clone();
+    let simulator_pool = ObjectPool::new(1, move || {
+        tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(async { Box::new(HttpSimulator::new(&rpc_url, &ipc_path).await) as Box<dyn Simulator> })
+    });
+    let arb = Arb::new(
+        &args.http_config.rpc_url,
+        Arc::new(simulator_pool),
+        GraphSettings::default(),
+        &FlashLoanConfig::default(),
+    )
+    .await?;
+
+    let (mut total, mut matched) = (0usize, 0usize);
+    for record in select(reader, args.coin_type.as_deref(), args.limit) {
+        let record = record?;
+        let trigger = &record.trigger;
+
+        let sim_ctx = trigger.sim_ctx()?;
+        // replays run to completion, deadlines long passed
+        let cancel = CancellationToken::new();
+        let replayed = match arb
+            .find_opportunity(
+                sender,
+                &trigger.coin,
+                trigger.pool_id,
+                vec![],
+                sim_ctx,
+                trigger.sizing,
+                trigger.source,
+                &cancel,
+                None,
+            )
+            .await
+        {
+            Ok(res) => Decision::Opportunity {
+                amount_in: res.best_trial_result.amount_in,
+                profit: res.best_trial_result.profit,
+                path: res.best_trial_result.trade_path.path.iter().map(|d| d.object_id()).collect(),
+                arb_tx_digest: res.tx_data.digest(),
+            },
+            Err(error) => Decision::NoOpportunity {
+                reason: format!("{error:#}"),
+            },
+        };
+
+        total += 1;
+        if same_outcome(&record.decision, &replayed) {
+            matched += 1;
+            info!(coin = %trigger.coin, tx = %trigger.tx_digest, "✅ decision reproduced");
+        } else {
+            warn!(coin = %trigger.coin, tx = %trigger.tx_digest, recorded = ?record.decision, ?replayed, "❌ decision differs");
+        }
+    }
+
+    if total == 0 {
+        bail!("no records replayed");
+    }
+    info!("{}/{} decisions reproduced", matched, total);
+
+    Ok(())
+}
+
+/// The records of `coin_type`, when set, at most `limit` of them. Read errors pass through.
+fn select(
+    records: impl Iterator<Item = Result<Record>>,
+    coin_type: Option<&str>,
+    limit: Option<usize>,
+) -> impl Iterator<Item = Result<Record>> {
+    let coin_type = coin_type.map(str::to_string);
+    records
+        .filter(move |record| match (record, &coin_type) {
+            (Ok(record), Some(coin_type)) => &record.trigger.coin == coin_type,
+            _ => true,
+        })
+        .take(limit.unwrap_or(usize::MAX))
+}
+
+// Profit and path must match; the arb digest depends on gas coins and is ignored.
+fn same_outcome(recorded: &Decision, replayed: &Decision) -> bool {
+    match (recorded, replayed) {
+        (Decision::NoOpportunity { .. }, Decision::NoOpportunity { .. }) => true,
+        (
+            Decision::Opportunity {
+                amount_in: a1,
+                profit: p1,
+                path: path1,
+                ..
+            },
+            Decision::Opportunity {
+                amount_in: a2,
+                profit: p2,
+                path: path2,
+                ..
+            },
+        ) => a1 == a2 && p1 == p2 && path1 == path2,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sui_types::base_types::SequenceNumber;
+
+    use super::*;
+
+    #[test]
+    fn test_reader_rejects_unknown_version() {
+        let path = std::env::temp_dir().join(format!("replay-version-{}.jsonl", std::process::id()));
+        let header = Header {
+            version: REPLAY_FORMAT_VERSION + 1,
+            build_version: "test".to_string(),
+            created_at_ms: 0,
+        };
+        std::fs::write(&path, format!("{}\n", serde_json::to_string(&header).unwrap())).unwrap();
+
+        assert!(ReplayReader::open(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    fn record(coin: &str) -> Record {
+        Record {
+            trigger: Trigger {
+                coin: coin.to_string(),
+                pool_id: None,
+                tx_digest: TransactionDigest::ZERO,
+                source: Source::Public,
+                sizing: Sizing::default(),
+                epoch: SimEpoch::default().into(),
+                override_objects: vec![],
+                pinned_objects: vec![],
+                received_at_ms: 0,
+            },
+            decision: Decision::NoOpportunity {
+                reason: "none".to_string(),
+            },
+            decided_at_ms: 0,
+        }
+    }
+
+    #[test]
+    fn test_limit_counts_the_selected_coin_only() {
+        let records = ["A", "B", "A", "B", "A"].map(|coin| Ok(record(coin)));
+
+        let selected = select(records.into_iter(), Some("B"), Some(2))
+            .map(|record| record.unwrap().trigger.coin)
+            .collect::<Vec<_>>();
+        assert_eq!(selected, vec!["B", "B"]);
+    }
+
+    #[test]
+    fn test_replay_runs_on_the_pinned_objects() {
+        let pool = Object::with_id_owner_version_for_testing(
+            ObjectID::random(),
+            SequenceNumber::from_u64(7),
+            SuiAddress::ZERO,
+        );
+        let pinned = ObjectReadResult::new(
+            InputObjectKind::ImmOrOwnedMoveObject(pool.compute_object_reference()),
+            pool.clone().into(),
+        );
+        let line = serde_json::to_string(&Record {
+            trigger: record("A").trigger.with_pinned(&[pinned]),
+            ..record("A")
+        })
+        .unwrap();
+
+        let replayed: Record = serde_json::from_str(&line).unwrap();
+        let sim_ctx = replayed.trigger.sim_ctx().unwrap();
+        assert_eq!(sim_ctx.override_objects.len(), 1);
+        assert_eq!(sim_ctx.override_objects[0].id(), pool.id());
+        assert_eq!(
+            sim_ctx.override_objects[0].as_object().map(|obj| obj.version()),
+            Some(SequenceNumber::from_u64(7))
+        );
+    }
+
+    #[test]
+    fn test_same_outcome_ignores_arb_digest() {
+        let a = Decision::Opportunity {
+            amount_in: 1,
+            profit: 2,
+            path: vec![ObjectID::ZERO],
+            arb_tx_digest: TransactionDigest::ZERO,
+        };
+        let b = Decision::Opportunity {
+            amount_in: 1,
+            profit: 2,
+            path: vec![ObjectID::ZERO],
+            arb_tx_digest: TransactionDigest::random(),
+        };
+
+        assert!(same_outcome(&a, &b));
+        assert!(!same_outcome(
+            &a,
+            &Decision::NoOpportunity {
+                reason: "none".to_string()
+            }
+        ));
+    }
+}
@@ -0,0 +1,59 @@
+//! Print P&L from the trade database by day, by protocol, by path shape and by account.
+//!
+//! Only trades the executor finalized are counted. Realized profit is the base coin balance change
+//! net of gas of the trades that landed; reverted gas is what the trades that reverted paid. The
+//! net column is their difference, shown when the base coin is SUI.
+//!
+//! Example:
+//! cargo run -r --bin arb report --trade-db-path ./journal/trades.db
+
+use clap::Parser;
+use eyre::{ensure, Result};
+
+use crate::storage::{PnlRow, TradeStore};
+
+#[derive(Clone, Debug, Parser)]
+pub struct Args {
+    /// Database written by `start-bot --trade-db-path`
+    #[arg(long, env = "SUI_TRADE_DB_PATH", default_value = "./journal/trades.db")]
+    pub trade_db_path: String,
+}
+
+pub async fn run(args: Args) -> Result<()> {
+    ensure!(
+        std::path::Path::new(&args.trade_db_path).exists(),
+        "no trade database at {}",
+        args.trade_db_path
+    );
+    let store = TradeStore::open(&args.trade_db_path)?;
+
+    let (opportunities, simulations, failed) = store.detection_counts()?;
+    println!("opportunities: {opportunities}, final dry runs: {simulations} ({failed} failed)\n");
+
+    print_table("day", &store.pnl_by_day()?);
+    print_table("protocol", &store.pnl_by_protocol()?);
+    print_table("path shape", &store.pnl_by_path_shape()?);
+    print_table("account", &store.pnl_by_account()?);
+    Ok(())
+}
+
+fn print_table(key: &str, rows: &[PnlRow]) {
+    let width = rows
+        .iter()
+        .map(|row| row.key.len())
+        .chain([key.len()])
+        .max()
+        .unwrap_or(0);
+    println!(
+        "{key:<width$}  {:>7}  {:>7}  {:>8}  {:>16}  {:>14}  {:>16}  base coin",
+        "trades", "landed", "reverted", "realized profit", "reverted gas", "net"
+    );
+    for row in rows {
+        let net = row.net().map(|net| net.to_string()).unwrap_or_default();
+        println!(
+            "{:<width$}  {:>7}  {:>7}  {:>8}  {:>16}  {:>14}  {:>16}  {}",
+            row.key, row.trades, row.landed, row.reverted, row.realized_profit, row.unrealized_gas, net, row.base_coin
+        );
+    }
+    println!();
+}
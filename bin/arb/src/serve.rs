@@ -0,0 +1,340 @@
+//! Self-hosted routing API: the searcher and the Bellman-Ford graph behind a small HTTP server.
+//!
+//! * `GET /pools?coin_in_type=..[&coin_out_type=..]` pools trading `coin_in_type`
+//! * `GET /paths?coin_type=..[&kind=sell|buy|cycle][&pool_id=..]` candidate paths
+//! * `GET /quote?coin_type=..&amount_in=..[&kind=sell|buy][&sender=..][&flashloan=true]` the best
+//!   simulated path for `amount_in`
+//!
+//! Every response is JSON; errors are `{"error": "..."}` with a 4xx/5xx status.
+//!
+//! `POST /rpc` serves the same engine over JSON-RPC 2.0 with the methods `find_dexes`,
+//! `find_arbitrage_paths` and `simulate_path`; params and results are the types in `types`.
+
+use std::{net::SocketAddr, str::FromStr, sync::Arc};
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use clap::Parser;
+use eyre::{eyre, Result, WrapErr};
+use object_pool::ObjectPool;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+use simulator::{HttpSimulator, SimulateCtx, Simulator};
+use sui_sdk::{SuiClient, SuiClientBuilder};
+use sui_types::base_types::{ObjectID, SuiAddress};
+use tracing::info;
+use utils::coin;
+
+use crate::{
+    common::{fatal::FatalKind, get_latest_epoch},
+    defi::{flash_lender::FlashLoanConfig, partner::PartnerConfig, registry, Defi, Path, TradeType},
+    graph_path_finder::{BellmanFordPathFinder, GraphSettings, QuoteWeights},
+    types::{
+        FindArbitragePathsParams, FindDexesParams, PathInfo, PoolInfo, QuoteInfo, RpcError, RpcRequest, RpcResponse,
+        SimulatePathParams, JSONRPC_VERSION,
+    },
+    HttpConfig,
+};
+
+#[derive(Clone, Debug, Parser)]
+pub struct Args {
+    #[arg(long, env = "SUI_SERVE_ADDR", default_value = "127.0.0.1:8080")]
+    pub listen: SocketAddr,
+
+    #[arg(long, env = "SUI_SERVE_SENDER", help = "Sender for quotes that don't pass one")]
+    pub sender: Option<String>,
+
+    #[arg(long, env = "SUI_SERVE_NUM_SIMULATORS", default_value_t = 4)]
+    pub num_simulators: usize,
+
+    #[command(flatten)]
+    pub http_config: HttpConfig,
+
+    #[command(flatten)]
+    pub partner_config: PartnerConfig,
+}
+
+struct ServeState {
+    defi: Defi,
+    path_finder: BellmanFordPathFinder,
+    sui: SuiClient,
+    default_sender: Option<SuiAddress>,
+}
+
+pub async fn run(args: Args) -> Result<()> {
+    mev_logger::init_console_logger_with_directives(None, &["arb=info", "poison_dart_core=info"]);
+    registry::register_partners(&args.partner_config);
+
+    let rpc_url = args.http_config.rpc_url.clone();
+    let ipc_path = args.http_config.ipc_path.clone();
+    let simulator_pool = ObjectPool::new(args.num_simulators, move || {
+        tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(async { Box::new(HttpSimulator::new(&rpc_url, &ipc_path).await) as Box<dyn Simulator> })
+    });
+
+    let defi = Defi::new(
+        &args.http_config.rpc_url,
+        Arc::new(simulator_pool),
+        &FlashLoanConfig::default(),
+    )
+    .await?;
+    let sui = SuiClientBuilder::default()
+        .build(&args.http_config.rpc_url)
+        .await
+        .wrap_err(FatalKind::RpcUnreachable)?;
+    let weights = QuoteWeights::new(defi.get_trader(), defi.get_dex_searcher(), sui.clone());
+    let path_finder =
+        BellmanFordPathFinder::new(defi.get_dex_searcher(), GraphSettings::default()).with_weights(Arc::new(weights));
+    let default_sender = args
+        .sender
+        .as_deref()
+        .map(parse_address)
+        .transpose()
+        .wrap_err(FatalKind::Config)?;
+
+    let state = Arc::new(ServeState {
+        defi,
+        path_finder,
+        sui,
+        default_sender,
+    });
+
+    let app = Router::new()
+        .route("/pools", get(pools))
+        .route("/paths", get(paths))
+        .route("/quote", get(quote))
+        .route("/rpc", post(rpc))
+        .with_state(state);
+
+    let listener = tokio::net::TcpListener::bind(args.listen).await?;
+    info!(addr = %args.listen, "routing API listening");
+    axum::serve(listener, app).await?;
+
+    Ok(())
+}
+
+fn parse_address(s: &str) -> Result<SuiAddress> {
+    SuiAddress::from_str(s).map_err(|e| eyre!("invalid address {}: {}", s, e))
+}
+
+/// 400 for bad input, 500 for everything that failed downstream.
+enum ApiError {
+    BadRequest(String),
+    Internal(eyre::Report),
+}
+
+impl From<eyre::Report> for ApiError {
+    fn from(e: eyre::Report) -> Self {
+        Self::Internal(e)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (status, error) = match self {
+            ApiError::BadRequest(error) => (StatusCode::BAD_REQUEST, error),
+            ApiError::Internal(error) => (StatusCode::INTERNAL_SERVER_ERROR, format!("{error:#}")),
+        };
+        (status, Json(json!({ "error": error }))).into_response()
+    }
+}
+
+type ApiResult<T> = std::result::Result<Json<T>, ApiError>;
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum PathKind {
+    /// coin -> SUI
+    #[default]
+    Sell,
+    /// SUI -> coin
+    Buy,
+    /// SUI -> .. -> SUI through the negative cycles of the graph
+    Cycle,
+}
+
+#[derive(Debug, Deserialize)]
+struct PoolsQuery {
+    coin_in_type: String,
+    coin_out_type: Option<String>,
+}
+
+async fn pools(State(state): State<Arc<ServeState>>, Query(q): Query<PoolsQuery>) -> ApiResult<Vec<PoolInfo>> {
+    let dexes = state.defi.find_dexes(&q.coin_in_type, q.coin_out_type).await?;
+    Ok(Json(dexes.iter().map(PoolInfo::from).collect()))
+}
+
+#[derive(Debug, Deserialize)]
+struct PathsQuery {
+    coin_type: String,
+    #[serde(default)]
+    kind: PathKind,
+    pool_id: Option<String>,
+}
+
+async fn find_paths(state: &ServeState, coin_type: &str, kind: PathKind, pool_id: Option<&str>) -> Result<Vec<Path>> {
+    let pool_id = pool_id.map(ObjectID::from_hex_literal).transpose()?;
+    let mut paths = match kind {
+        PathKind::Sell => state.defi.find_sell_paths(coin_type).await?,
+        PathKind::Buy => state.defi.find_buy_paths(coin_type).await?,
+        PathKind::Cycle => return state.path_finder.find_arbitrage_paths(coin_type, pool_id).await,
+    };
+    if pool_id.is_some() {
+        paths.retain(|path| path.contains_pool(pool_id));
+    }
+
+    Ok(paths)
+}
+
+async fn paths(State(state): State<Arc<ServeState>>, Query(q): Query<PathsQuery>) -> ApiResult<Vec<PathInfo>> {
+    let paths = find_paths(&state, &q.coin_type, q.kind, q.pool_id.as_deref()).await?;
+    Ok(Json(paths.iter().map(PathInfo::from).collect()))
+}
+
+#[derive(Debug, Deserialize)]
+struct QuoteQuery {
+    coin_type: String,
+    amount_in: u64,
+    #[serde(default)]
+    kind: PathKind,
+    pool_id: Option<String>,
+    sender: Option<String>,
+    #[serde(default)]
+    flashloan: bool,
+}
+
+async fn quote(State(state): State<Arc<ServeState>>, Query(q): Query<QuoteQuery>) -> ApiResult<QuoteInfo> {
+    let sender = match (q.sender.as_deref(), state.default_sender) {
+        (Some(sender), _) => parse_address(sender).map_err(|e| ApiError::BadRequest(e.to_string()))?,
+        (None, Some(sender)) => sender,
+        (None, None) => return Err(ApiError::BadRequest("sender is required".to_string())),
+    };
+
+    let paths = find_paths(&state, &q.coin_type, q.kind, q.pool_id.as_deref()).await?;
+    if paths.is_empty() {
+        return Err(ApiError::BadRequest(format!("no paths for {}", q.coin_type)));
+    }
+
+    let gas_coins = coin::get_gas_coin_refs(&state.sui, sender, None).await?;
+    let sim_ctx = SimulateCtx::new(get_latest_epoch(&state.sui).await?, vec![]);
+    let trade_type = if q.flashloan {
+        TradeType::Flashloan
+    } else {
+        TradeType::Swap
+    };
+
+    let res = state
+        .defi
+        .find_best_path_exact_in(&paths, sender, q.amount_in, trade_type, &gas_coins, &sim_ctx)
+        .await?;
+    Ok(Json(QuoteInfo::from(&res)))
+}
+
+async fn rpc(State(state): State<Arc<ServeState>>, body: String) -> Json<RpcResponse> {
+    let request: RpcRequest = match serde_json::from_str::<Value>(&body) {
+        Err(e) => return Json(RpcResponse::error(Value::Null, RpcError::new(RpcError::PARSE_ERROR, e))),
+        Ok(value) => {
+            let id = value.get("id").cloned().unwrap_or(Value::Null);
+            match serde_json::from_value(value) {
+                Ok(request) => request,
+                Err(e) => return Json(RpcResponse::error(id, RpcError::new(RpcError::INVALID_REQUEST, e))),
+            }
+        }
+    };
+    if request.jsonrpc != JSONRPC_VERSION {
+        let error = RpcError::new(RpcError::INVALID_REQUEST, "jsonrpc must be \"2.0\"");
+        return Json(RpcResponse::error(request.id, error));
+    }
+
+    let result = match request.method.as_str() {
+        "find_dexes" => rpc_find_dexes(&state, request.params).await,
+        "find_arbitrage_paths" => rpc_find_arbitrage_paths(&state, request.params).await,
+        "simulate_path" => rpc_simulate_path(&state, request.params).await,
+        method => Err(RpcError::new(
+            RpcError::METHOD_NOT_FOUND,
+            format!("unknown method {method}"),
+        )),
+    };
+
+    Json(match result {
+        Ok(result) => RpcResponse::result(request.id, result),
+        Err(error) => RpcResponse::error(request.id, error),
+    })
+}
+
+fn rpc_params<T: DeserializeOwned>(params: Value) -> std::result::Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|e| RpcError::new(RpcError::INVALID_PARAMS, e))
+}
+
+fn rpc_result<T: Serialize>(result: Result<T>) -> std::result::Result<Value, RpcError> {
+    let result = result.map_err(|e| RpcError::new(RpcError::SERVER_ERROR, format!("{e:#}")))?;
+    serde_json::to_value(result).map_err(|e| RpcError::new(RpcError::SERVER_ERROR, e))
+}
+
+async fn rpc_find_dexes(state: &ServeState, params: Value) -> std::result::Result<Value, RpcError> {
+    let params: FindDexesParams = rpc_params(params)?;
+    params
+        .validate()
+        .map_err(|e| RpcError::new(RpcError::INVALID_PARAMS, e))?;
+
+    rpc_result(
+        state
+            .defi
+            .find_dexes(&params.coin_in_type, params.coin_out_type)
+            .await
+            .map(|dexes| dexes.iter().map(PoolInfo::from).collect::<Vec<_>>()),
+    )
+}
+
+async fn rpc_find_arbitrage_paths(state: &ServeState, params: Value) -> std::result::Result<Value, RpcError> {
+    let params: FindArbitragePathsParams = rpc_params(params)?;
+    params
+        .validate()
+        .map_err(|e| RpcError::new(RpcError::INVALID_PARAMS, e))?;
+
+    rpc_result(
+        state
+            .path_finder
+            .find_arbitrage_paths(&params.start_token, params.pool_id)
+            .await
+            .map(|paths| paths.iter().map(PathInfo::from).collect::<Vec<_>>()),
+    )
+}
+
+async fn rpc_simulate_path(state: &ServeState, params: Value) -> std::result::Result<Value, RpcError> {
+    let params: SimulatePathParams = rpc_params(params)?;
+    params
+        .validate()
+        .map_err(|e| RpcError::new(RpcError::INVALID_PARAMS, e))?;
+
+    let result = async {
+        let path = state.defi.get_dex_searcher().find_test_path(&params.pool_ids).await?;
+        let gas_coins = coin::get_gas_coin_refs(&state.sui, params.sender, None).await?;
+        let sim_ctx = SimulateCtx::new(get_latest_epoch(&state.sui).await?, vec![]);
+        let trade_type = if params.flashloan {
+            TradeType::Flashloan
+        } else {
+            TradeType::Swap
+        };
+
+        let res = state
+            .defi
+            .find_best_path_exact_in(
+                &[path],
+                params.sender,
+                params.amount_in,
+                trade_type,
+                &gas_coins,
+                &sim_ctx,
+            )
+            .await?;
+        eyre::Ok(QuoteInfo::from(&res))
+    };
+    rpc_result(result.await)
+}
@@ -0,0 +1,270 @@
+//! Health check and admin API of a running `start-bot`.
+//!
+//! * `GET /health` 200 while the node data is fresh, 503 once every endpoint lags
+//! * `GET /status` graph size, last event, queued and in-flight searches, pause state
+//! * `POST /pause`, `POST /resume` stop and restart trading without restarting the process
+//! * `GET /risk` the risk limits, the day's PnL, exposure and circuit breaker state
+//! * `PUT /risk` replace the risk limits (the `[risk]` section as JSON); `POST /risk/reset`
+//!   closes a tripped circuit breaker
+//! * `GET /paths?token=..[&pool_id=..]` the Bellman-Ford cycles through `token`
+//!
+//! With `SUI_ADMIN_TOKEN` set, every route but `/health` and `/status` wants it as
+//! `Authorization: Bearer <token>`. While paused the strategy keeps indexing events but drops
+//! every opportunity, as it does on stale node data.
+
+use std::{
+    fmt,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use axum::{
+    extract::{Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use clap::Parser;
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sui_types::base_types::ObjectID;
+use tracing::{info, warn};
+
+use crate::{
+    common::{
+        endpoints::RpcEndpoints,
+        metrics,
+        risk::{self, RiskLimits},
+    },
+    graph_path_finder::BellmanFordPathFinder,
+    types::PathInfo,
+};
+
+static PAUSED: AtomicBool = AtomicBool::new(false);
+static LAST_EVENT_MS: AtomicU64 = AtomicU64::new(0);
+static QUEUED: AtomicUsize = AtomicUsize::new(0);
+static IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Clone, Default, Parser)]
+pub struct AdminConfig {
+    /// Address to serve the health check and admin API on, e.g. `127.0.0.1:9185`; off when unset
+    #[arg(long, env = "SUI_ADMIN_ADDR")]
+    pub admin_addr: Option<SocketAddr>,
+
+    /// Bearer token the admin routes require; open to anyone who reaches the address when unset
+    #[arg(long, env = "SUI_ADMIN_TOKEN")]
+    pub admin_token: Option<String>,
+}
+
+impl fmt::Debug for AdminConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AdminConfig")
+            .field("admin_addr", &self.admin_addr)
+            .field("admin_token", &self.admin_token.as_ref().map(|_| "[REDACTED]"))
+            .finish()
+    }
+}
+
+/// Whether trading was paused through `POST /pause`.
+pub fn is_paused() -> bool {
+    PAUSED.load(Ordering::Relaxed)
+}
+
+/// The strategy received an event.
+pub fn event_received() {
+    LAST_EVENT_MS.store(utils::current_time_ms(), Ordering::Relaxed);
+}
+
+/// `queued` opportunities wait for a worker and `in_flight` are being searched.
+pub fn set_pending(queued: usize, in_flight: usize) {
+    QUEUED.store(queued, Ordering::Relaxed);
+    IN_FLIGHT.store(in_flight, Ordering::Relaxed);
+}
+
+struct AdminState {
+    token: Option<String>,
+    endpoints: Arc<RpcEndpoints>,
+    path_finder: BellmanFordPathFinder,
+}
+
+#[derive(Debug, Serialize)]
+struct Status {
+    paused: bool,
+    stale: bool,
+    risk_halted: bool,
+    graph_nodes: i64,
+    graph_edges: i64,
+    /// Unix time of the last event, 0 before the first one
+    last_event_ms: u64,
+    queued_opportunities: usize,
+    in_flight_searches: usize,
+}
+
+/// Serve the admin API on `addr` until the process exits.
+pub async fn serve(
+    addr: SocketAddr,
+    token: Option<String>,
+    endpoints: Arc<RpcEndpoints>,
+    path_finder: BellmanFordPathFinder,
+) -> Result<()> {
+    if token.is_none() {
+        warn!(%addr, "admin API has no SUI_ADMIN_TOKEN, anyone reaching it can pause trading");
+    }
+    let state = Arc::new(AdminState {
+        token,
+        endpoints,
+        path_finder,
+    });
+
+    let app = Router::new()
+        .route("/health", get(health))
+        .route("/status", get(status))
+        .route("/pause", post(pause))
+        .route("/resume", post(resume))
+        .route("/risk", get(risk_status).put(set_risk_limits))
+        .route("/risk/reset", post(reset_risk))
+        .route("/paths", get(paths))
+        .with_state(state);
+
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    info!(%addr, "admin API listening");
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+/// 401 unless the request carries the configured bearer token.
+fn authorize(state: &AdminState, headers: &HeaderMap) -> std::result::Result<(), Response> {
+    let Some(token) = &state.token else {
+        return Ok(());
+    };
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    if bearer == Some(token.as_str()) {
+        Ok(())
+    } else {
+        Err((StatusCode::UNAUTHORIZED, Json(json!({ "error": "unauthorized" }))).into_response())
+    }
+}
+
+async fn health(State(state): State<Arc<AdminState>>) -> Response {
+    if state.endpoints.is_stale() {
+        (StatusCode::SERVICE_UNAVAILABLE, "stale").into_response()
+    } else {
+        (StatusCode::OK, "ok").into_response()
+    }
+}
+
+async fn status(State(state): State<Arc<AdminState>>) -> Json<Status> {
+    let (graph_nodes, graph_edges) = metrics::current_graph_size();
+    Json(Status {
+        paused: is_paused(),
+        stale: state.endpoints.is_stale(),
+        risk_halted: risk::is_halted(),
+        graph_nodes,
+        graph_edges,
+        last_event_ms: LAST_EVENT_MS.load(Ordering::Relaxed),
+        queued_opportunities: QUEUED.load(Ordering::Relaxed),
+        in_flight_searches: IN_FLIGHT.load(Ordering::Relaxed),
+    })
+}
+
+async fn pause(State(state): State<Arc<AdminState>>, headers: HeaderMap) -> Response {
+    if let Err(response) = authorize(&state, &headers) {
+        return response;
+    }
+    if !PAUSED.swap(true, Ordering::Relaxed) {
+        warn!("trading paused through the admin API");
+    }
+    Json(json!({ "paused": true })).into_response()
+}
+
+async fn resume(State(state): State<Arc<AdminState>>, headers: HeaderMap) -> Response {
+    if let Err(response) = authorize(&state, &headers) {
+        return response;
+    }
+    if PAUSED.swap(false, Ordering::Relaxed) {
+        info!("trading resumed through the admin API");
+    }
+    Json(json!({ "paused": false })).into_response()
+}
+
+async fn risk_status(State(state): State<Arc<AdminState>>, headers: HeaderMap) -> Response {
+    if let Err(response) = authorize(&state, &headers) {
+        return response;
+    }
+    Json(risk::status()).into_response()
+}
+
+async fn set_risk_limits(
+    State(state): State<Arc<AdminState>>,
+    headers: HeaderMap,
+    Json(limits): Json<RiskLimits>,
+) -> Response {
+    if let Err(response) = authorize(&state, &headers) {
+        return response;
+    }
+    if let Err(error) = limits.validate() {
+        return (StatusCode::BAD_REQUEST, Json(json!({ "error": format!("{error:#}") }))).into_response();
+    }
+    warn!(?limits, "risk limits replaced through the admin API");
+    risk::set_limits(limits);
+    Json(risk::status()).into_response()
+}
+
+async fn reset_risk(State(state): State<Arc<AdminState>>, headers: HeaderMap) -> Response {
+    if let Err(response) = authorize(&state, &headers) {
+        return response;
+    }
+    if risk::reset() {
+        info!("circuit breaker reset through the admin API");
+    }
+    Json(risk::status()).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+struct PathsQuery {
+    token: String,
+    pool_id: Option<String>,
+}
+
+async fn paths(State(state): State<Arc<AdminState>>, headers: HeaderMap, Query(q): Query<PathsQuery>) -> Response {
+    if let Err(response) = authorize(&state, &headers) {
+        return response;
+    }
+    let pool_id = match q.pool_id.as_deref().map(ObjectID::from_hex_literal).transpose() {
+        Ok(pool_id) => pool_id,
+        Err(error) => return (StatusCode::BAD_REQUEST, Json(json!({ "error": error.to_string() }))).into_response(),
+    };
+
+    match state.path_finder.find_arbitrage_paths(&q.token, pool_id).await {
+        Ok(paths) => Json(paths.iter().map(PathInfo::from).collect::<Vec<_>>()).into_response(),
+        Err(error) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "error": format!("{error:#}") })),
+        )
+            .into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_admin_config_debug_redacts_token() {
+        let config = AdminConfig {
+            admin_addr: None,
+            admin_token: Some("hunter2".to_string()),
+        };
+        let debug = format!("{:?}", config);
+        assert!(!debug.contains("hunter2"));
+        assert!(debug.contains("[REDACTED]"));
+    }
+}
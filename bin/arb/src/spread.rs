@@ -0,0 +1,203 @@
+//! Cross-venue spread history.
+//!
+//! Every `spread_interval` seconds, each configured coin is quoted against SUI on every venue
+//! that has a direct pool for it, with the same SUI probe amount. The best spread is the gap
+//! between the venue paying out the most and the one paying out the least, in basis points of
+//! the latter. Quotes are simulated swaps, so pool fees are included; gas is not.
+//!
+//! Samples are appended to a JSONL file and logged under the `spread_metrics` target.
+
+use std::{
+    fs::OpenOptions,
+    io::{BufWriter, Write},
+    path::Path,
+    sync::Mutex,
+    time::Duration,
+};
+
+use clap::Parser;
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+use simulator::SimulateCtx;
+use sui_sdk::{SuiClient, SUI_COIN_TYPE};
+use sui_types::base_types::{ObjectID, SuiAddress};
+use tracing::{info, warn};
+use utils::coin;
+
+use crate::{
+    common::get_latest_epoch,
+    config::coin_label,
+    defi::{Defi, Dex, Path as TradePath, TradeType},
+};
+
+#[derive(Clone, Debug, Parser)]
+pub struct SpreadConfig {
+    /// Coin types whose spread against SUI is recorded
+    #[arg(long, env = "SUI_SPREAD_COINS", value_delimiter = ',')]
+    pub spread_coins: Vec<String>,
+
+    /// Seconds between spread samples
+    #[arg(long, env = "SUI_SPREAD_INTERVAL", default_value_t = 5)]
+    pub spread_interval: u64,
+
+    /// SUI (in MIST) quoted on every venue
+    #[arg(long, env = "SUI_SPREAD_PROBE_AMOUNT", default_value_t = 1_000_000_000)]
+    pub spread_probe_amount: u64,
+
+    /// Append spread samples to this file
+    #[arg(long, env = "SUI_SPREAD_HISTORY_PATH", default_value = "./spread_history.jsonl")]
+    pub spread_history_path: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VenueQuote {
+    pub protocol: String,
+    pub pool_id: ObjectID,
+    pub amount_out: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpreadSample {
+    pub timestamp_ms: u64,
+    pub pair: String,
+    pub coin_type: String,
+    pub amount_in: u64,
+    pub venues: Vec<VenueQuote>,
+    /// None with fewer than two venues quoting
+    pub spread_bps: Option<f64>,
+}
+
+impl SpreadSample {
+    fn new(coin_type: &str, amount_in: u64, mut venues: Vec<VenueQuote>) -> Self {
+        venues.sort_by_key(|venue| std::cmp::Reverse(venue.amount_out));
+        let spread_bps = match (venues.first(), venues.last()) {
+            (Some(best), Some(worst)) if venues.len() > 1 && worst.amount_out > 0 => {
+                Some((best.amount_out - worst.amount_out) as f64 / worst.amount_out as f64 * 10_000.0)
+            }
+            _ => None,
+        };
+
+        Self {
+            timestamp_ms: utils::current_time_ms(),
+            pair: format!("SUI/{}", coin_label(coin_type)),
+            coin_type: coin_type.to_string(),
+            amount_in,
+            venues,
+            spread_bps,
+        }
+    }
+}
+
+pub struct SpreadMonitor {
+    defi: Defi,
+    sui: SuiClient,
+    sender: SuiAddress,
+    config: SpreadConfig,
+    writer: Mutex<BufWriter<std::fs::File>>,
+}
+
+impl SpreadMonitor {
+    pub fn new(defi: Defi, sui: SuiClient, sender: SuiAddress, config: SpreadConfig) -> Result<Self> {
+        let path = Path::new(&config.spread_history_path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+
+        Ok(Self {
+            defi,
+            sui,
+            sender,
+            config,
+            writer: Mutex::new(BufWriter::new(file)),
+        })
+    }
+
+    pub async fn run(self) {
+        info!(coins = ?self.config.spread_coins, "spread monitor started");
+        let mut interval = tokio::time::interval(Duration::from_secs(self.config.spread_interval));
+
+        loop {
+            interval.tick().await;
+            for coin_type in &self.config.spread_coins {
+                match self.sample(coin_type).await {
+                    Ok(sample) => self.record(&sample),
+                    Err(error) => warn!(coin_type, ?error, "spread sample failed"),
+                }
+            }
+        }
+    }
+
+    async fn sample(&self, coin_type: &str) -> Result<SpreadSample> {
+        let amount_in = self.config.spread_probe_amount;
+        let dexes = self.defi.find_dexes(SUI_COIN_TYPE, Some(coin_type.to_string())).await?;
+        let gas_coins = coin::get_gas_coin_refs(&self.sui, self.sender, None).await?;
+        let sim_ctx = SimulateCtx::new(get_latest_epoch(&self.sui).await?, vec![]);
+
+        let mut venues = vec![];
+        for dex in dexes {
+            let (protocol, pool_id) = (dex.protocol().to_string(), dex.object_id());
+            let path = TradePath::new(vec![dex]);
+            match self
+                .defi
+                .find_best_path_exact_in(&[path], self.sender, amount_in, TradeType::Swap, &gas_coins, &sim_ctx)
+                .await
+            {
+                Ok(res) => venues.push(VenueQuote {
+                    protocol,
+                    pool_id,
+                    amount_out: res.amount_out,
+                }),
+                // a venue that can't fill the probe just doesn't quote
+                Err(error) => warn!(%pool_id, ?error, "venue quote failed"),
+            }
+        }
+
+        Ok(SpreadSample::new(coin_type, amount_in, venues))
+    }
+
+    fn record(&self, sample: &SpreadSample) {
+        info!(
+            target: "spread_metrics",
+            pair = %sample.pair,
+            venues = sample.venues.len(),
+            spread_bps = ?sample.spread_bps,
+        );
+
+        let line = match serde_json::to_string(sample) {
+            Ok(line) => line,
+            Err(error) => {
+                warn!(?error, "failed to serialize spread sample");
+                return;
+            }
+        };
+        let mut writer = self.writer.lock().unwrap();
+        if let Err(error) = writeln!(writer, "{}", line).and_then(|_| writer.flush()) {
+            warn!(?error, "failed to write spread sample");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn venue(amount_out: u64) -> VenueQuote {
+        VenueQuote {
+            protocol: "Cetus".to_string(),
+            pool_id: ObjectID::random(),
+            amount_out,
+        }
+    }
+
+    #[test]
+    fn test_spread_between_best_and_worst_venue() {
+        let sample = SpreadSample::new("0x2::sui::SUI", 1000, vec![venue(990), venue(1000), venue(995)]);
+        assert_eq!(sample.venues[0].amount_out, 1000);
+        let spread = sample.spread_bps.unwrap();
+        assert!((spread - 10.0 / 990.0 * 10_000.0).abs() < 1e-9);
+
+        let single = SpreadSample::new("0x2::sui::SUI", 1000, vec![venue(1000)]);
+        assert!(single.spread_bps.is_none());
+    }
+}
@@ -1,37 +1,106 @@
 use std::{
+    collections::HashSet,
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
     sync::Arc,
     time::{Duration, Instant},
 };
 
-use ::utils::heartbeat;
+use ::utils::{coin, heartbeat};
 use burberry::{executor::telegram_message::TelegramMessageDispatcher, map_collector, map_executor, Engine};
 use clap::Parser;
-use eyre::Result;
+use dex_indexer::{normalize_coin_type, types::Protocol};
+use eyre::{ensure, eyre, OptionExt, Result, WrapErr};
 use object_pool::ObjectPool;
 use shio::{new_shio_collector_and_executor, ShioRPCExecutor};
-use simulator::{DBSimulator, HttpSimulator, ReplaySimulator, Simulator};
-use sui_types::{base_types::SuiAddress, crypto::SuiKeyPair};
+use simulator::{DBSimulator, HttpSimulator, MemorySimulator, ObjectCache, ReplaySimulator, Simulator};
+use sui_types::{base_types::SuiAddress, crypto::SuiKeyPair, TypeTag};
+use tokio::sync::mpsc;
 use tracing::{info, warn};
 
 use crate::{
-    collector::{PrivateTxCollector, PublicTxCollector},
-    executor::PublicTxExecutor,
-    strategy::ArbStrategy,
+    arb::Sizing,
+    collector::{NewPoolCollector, PrivateTxCollector, PublicTxCollector, WsCollector},
+    common::{
+        alerts::{self, Severity},
+        bid::{self, BidConfig},
+        cache_metrics,
+        competition::{self, CompetitionConfig},
+        dry_run::{self, DryRun},
+        endpoints::{RpcConfig, RpcEndpoints, StaleDataEvent},
+        experiment::{self, ArmParams, ExperimentConfig},
+        fatal::FatalKind,
+        gas::{GasConfig, GasOracle},
+        metrics::{self, MetricsConfig},
+        notification::{new_stale_data_alert_message, new_upgrade_alert_message},
+        outcomes,
+        parallel_sim::{self, ParallelSimConfig},
+        quote_filter::{self, QuoteFilterConfig},
+        shutdown::{self, ShutdownConfig},
+        token_meta::{TokenMetaConfig, TokenMetadata},
+    },
+    config::{self, FileConfig},
+    defi::{
+        base::{self, BaseConfig},
+        descriptor::{self, DescriptorConfig},
+        flash_lender::FlashLoanConfig,
+        partner::PartnerConfig,
+        registry, set_deny_list_senders,
+        upgrades::{UpgradeConfig, UpgradeMonitor},
+        Defi, IndexerDexSearcher,
+    },
+    executor::{DryRunExecutor, PublicTxExecutor},
+    gas_coins::GasCoinBalancer,
+    graph_path_finder::{BellmanFordPathFinder, GraphConfig, GraphSettings, QuoteWeights},
+    journal::TradeJournal,
+    pool_ids, redact_url,
+    replay::ReplayRecorder,
+    server::{self, AdminConfig},
+    spread::{SpreadConfig, SpreadMonitor},
+    storage::TradeStore,
+    strategy::{
+        accounts::{AccountPartitioner, AccountSelection},
+        dedup::{self, DedupConfig},
+        routes::{self, RouteConfig},
+        ArbStrategy,
+    },
+    top_up::{GasTopUp, TopUpConfig},
     types::{Action, Event},
     HttpConfig,
 };
 
-#[derive(Clone, Debug, Parser)]
+#[derive(Clone, Parser)]
 pub struct Args {
     #[arg(long, env = "SUI_PRIVATE_KEY")]
     pub private_key: String,
 
-    #[arg(long, help = "shio executor uses RPC to submit bid")]
+    /// More accounts to spread public trades over, each paying its own gas
+    #[arg(long, env = "SUI_EXTRA_PRIVATE_KEYS", value_delimiter = ',')]
+    pub extra_private_keys: Vec<String>,
+
+    #[arg(long, env = "SUI_SHIO_USE_RPC", help = "shio executor uses RPC to submit bid")]
     pub shio_use_rpc: bool,
 
+    /// Simulate every would-be trade instead of submitting it, and log the running PnL
+    #[arg(long, env = "SUI_DRY_RUN")]
+    pub dry_run: bool,
+
+    /// TOML file overriding flags and env vars; its thresholds, token lists and protocol toggles
+    /// are reloaded when it changes
+    #[arg(long, env = "SUI_BOT_CONFIG")]
+    pub bot_config: Option<PathBuf>,
+
+    /// Only index and trade these protocols (`cetus,turbos,...`); unset: every protocol built in
+    #[arg(long, env = "SUI_PROTOCOLS", value_delimiter = ',')]
+    pub protocols: Vec<String>,
+
     #[command(flatten)]
     pub http_config: HttpConfig,
 
+    #[command(flatten)]
+    rpc_config: RpcConfig,
+
     #[command(flatten)]
     collector_config: CollectorConfig,
 
@@ -40,21 +109,356 @@ pub struct Args {
 
     #[command(flatten)]
     worker_config: WorkerConfig,
+
+    #[command(flatten)]
+    parallel_sim_config: ParallelSimConfig,
+
+    #[command(flatten)]
+    quote_filter_config: QuoteFilterConfig,
+
+    #[command(flatten)]
+    route_config: RouteConfig,
+
+    #[command(flatten)]
+    dedup_config: DedupConfig,
+
+    #[command(flatten)]
+    bid_config: BidConfig,
+
+    #[command(flatten)]
+    competition_config: CompetitionConfig,
+
+    #[command(flatten)]
+    partner_config: PartnerConfig,
+
+    #[command(flatten)]
+    flashloan_config: FlashLoanConfig,
+
+    #[command(flatten)]
+    upgrade_config: UpgradeConfig,
+
+    #[command(flatten)]
+    descriptor_config: DescriptorConfig,
+
+    #[command(flatten)]
+    spread_config: SpreadConfig,
+
+    #[command(flatten)]
+    base_config: BaseConfig,
+
+    #[command(flatten)]
+    token_meta_config: TokenMetaConfig,
+
+    #[command(flatten)]
+    gas_config: GasConfig,
+
+    #[command(flatten)]
+    top_up_config: TopUpConfig,
+
+    #[command(flatten)]
+    experiment_config: ExperimentConfig,
+
+    #[command(flatten)]
+    metrics_config: MetricsConfig,
+
+    #[command(flatten)]
+    admin_config: AdminConfig,
+
+    #[command(flatten)]
+    shutdown_config: ShutdownConfig,
+
+    #[command(flatten)]
+    graph_config: GraphConfig,
+}
+
+impl fmt::Debug for Args {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Args")
+            .field("private_key", &"[REDACTED]")
+            .field(
+                "extra_private_keys",
+                &format!("[{} REDACTED]", self.extra_private_keys.len()),
+            )
+            .field("shio_use_rpc", &self.shio_use_rpc)
+            .field("dry_run", &self.dry_run)
+            .field("bot_config", &self.bot_config)
+            .field("protocols", &self.protocols)
+            .field("http_config", &self.http_config)
+            .field(
+                "extra_rpc_urls",
+                &self
+                    .rpc_config
+                    .extra_rpc_urls
+                    .iter()
+                    .map(|url| redact_url(url))
+                    .collect::<Vec<_>>(),
+            )
+            .field("rpc_probe_interval_ms", &self.rpc_config.rpc_probe_interval_ms)
+            .field("rpc_max_lag", &self.rpc_config.rpc_max_lag)
+            .field("max_checkpoint_age_ms", &self.rpc_config.max_checkpoint_age_ms)
+            .field("collector_config", &self.collector_config)
+            .field("db_sim_config", &self.db_sim_config)
+            .field("worker_config", &self.worker_config)
+            .field("parallel_sim_config", &self.parallel_sim_config)
+            .field("quote_filter_config", &self.quote_filter_config)
+            .field("route_config", &self.route_config)
+            .field("dedup_config", &self.dedup_config)
+            .field("bid_config", &self.bid_config)
+            .field("competition_config", &self.competition_config)
+            .field("partner_config", &self.partner_config)
+            .field("flashloan_config", &self.flashloan_config)
+            .field("upgrade_config", &self.upgrade_config)
+            .field("descriptor_config", &self.descriptor_config)
+            .field("spread_config", &self.spread_config)
+            .field("base_config", &self.base_config)
+            .field("token_meta_config", &self.token_meta_config)
+            .field("gas_config", &self.gas_config)
+            .field("top_up_config", &self.top_up_config)
+            .field("experiment_config", &self.experiment_config)
+            .field("metrics_config", &self.metrics_config)
+            .field("admin_config", &self.admin_config)
+            .field("shutdown_config", &self.shutdown_config)
+            .field("graph_config", &self.graph_config)
+            .finish()
+    }
+}
+
+impl Args {
+    /// Override flags and env vars with the values `file` sets.
+    fn apply_file(&mut self, file: &FileConfig) {
+        if let Some(url) = &file.rpc.url {
+            self.http_config.rpc_url = url.clone();
+        }
+        if let Some(urls) = &file.rpc.extra_urls {
+            self.rpc_config.extra_rpc_urls = urls.clone();
+        }
+        if let Some(watch_coins) = &file.tokens.watch {
+            self.collector_config.watch_coins = watch_coins.clone();
+        }
+        self.gas_config = file.gas_config(self.gas_config.clone());
+    }
+
+    /// The protocols of `--protocols`, each of which must be built in.
+    fn protocol_allowlist(&self) -> Result<HashSet<Protocol>> {
+        self.protocols
+            .iter()
+            .map(|name| {
+                let protocol = Protocol::try_from(name.as_str())?;
+                registry::dex_factory(&protocol).ok_or_else(|| eyre!("{} is not built into this binary", name))?;
+                Ok(protocol)
+            })
+            .collect()
+    }
+
+    /// Check the whole configuration up front and report every problem at once, so a container
+    /// with a bad environment fails at startup instead of after the first trigger.
+    fn validate(&self) -> Result<()> {
+        let mut errors = vec![];
+
+        if !self.http_config.rpc_url.starts_with("http://") && !self.http_config.rpc_url.starts_with("https://") {
+            errors.push(format!(
+                "SUI_RPC_URL must be an http(s) URL, got {}",
+                redact_url(&self.http_config.rpc_url)
+            ));
+        }
+        for url in &self.rpc_config.extra_rpc_urls {
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                errors.push(format!(
+                    "SUI_EXTRA_RPC_URLS must be http(s) URLs, got {}",
+                    redact_url(url)
+                ));
+            }
+        }
+        if self.rpc_config.rpc_probe_interval_ms == 0 {
+            errors.push("SUI_RPC_PROBE_INTERVAL_MS must be positive".to_string());
+        }
+        if self.rpc_config.max_checkpoint_age_ms == 0 {
+            errors.push("SUI_MAX_CHECKPOINT_AGE_MS must be positive".to_string());
+        }
+        for (name, url) in [
+            ("SUI_RELAY_WS_URL", &self.collector_config.relay_ws_url),
+            ("SUI_SHIO_WS_URL", &self.collector_config.shio_ws_url),
+            ("SUI_WS_URL", &self.collector_config.ws_url),
+        ] {
+            if let Some(url) = url {
+                if !url.starts_with("ws://") && !url.starts_with("wss://") {
+                    errors.push(format!("{} must be a ws(s) URL, got {}", name, redact_url(url)));
+                }
+            }
+        }
+
+        let db = &self.db_sim_config;
+        if db.use_db_simulator && db.simulator.is_some_and(|kind| kind != SimulatorKind::Db) {
+            errors.push("SUI_USE_DB_SIMULATOR conflicts with SUI_SIMULATOR".to_string());
+        }
+        if db.simulator_kind() == SimulatorKind::Db {
+            for (name, path) in [
+                ("SUI_DB_PATH", &db.db_path),
+                ("SUI_CONFIG_PATH", &db.config_path),
+                ("SUI_PRELOAD_PATH", &db.preload_path),
+            ] {
+                if !Path::new(path).exists() {
+                    errors.push(format!("{} does not exist: {}", name, path));
+                }
+            }
+        }
+        if db.catchup_interval == 0 {
+            errors.push("SUI_CATCHUP_INTERVAL must be positive".to_string());
+        }
+
+        let worker = &self.worker_config;
+        if worker.workers == 0 {
+            errors.push("SUI_WORKERS must be positive".to_string());
+        }
+        if worker.num_simulators == 0 {
+            errors.push("SUI_NUM_SIMULATORS must be positive".to_string());
+        }
+        if worker.account_lease_ms == 0 {
+            errors.push("SUI_ACCOUNT_LEASE_MS must be positive".to_string());
+        }
+        if self.parallel_sim_config.sim_concurrency == 0 || self.parallel_sim_config.sim_deadline_ms == 0 {
+            errors.push("SUI_SIM_CONCURRENCY and SUI_SIM_DEADLINE_MS must be positive".to_string());
+        }
+        if self.quote_filter_config.quote_margin_bps.is_some() && self.quote_filter_config.quote_probe_amount == 0 {
+            errors.push("SUI_QUOTE_PROBE_AMOUNT must be positive".to_string());
+        }
+        if self.route_config.max_routes == 0 {
+            errors.push("SUI_MAX_ROUTES must be positive".to_string());
+        }
+        if self.route_config.route_capital_budget == Some(0) {
+            errors.push("SUI_ROUTE_CAPITAL_BUDGET must be positive".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.bid_config.shio_bid_ratio) {
+            errors.push("SUI_SHIO_BID_RATIO must be between 0 and 1".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.competition_config.competition_bid_boost) {
+            errors.push("SUI_COMPETITION_BID_BOOST must be between 0 and 1".to_string());
+        }
+        if self.competition_config.competition_max_gas_multiplier < 1.0 {
+            errors.push("SUI_COMPETITION_MAX_GAS_MULTIPLIER must be at least 1".to_string());
+        }
+        if worker.dedicated_short_interval == 0 || worker.dedicated_short_interval > worker.dedicated_long_interval {
+            errors.push(format!(
+                "need 0 < SUI_DEDICATED_SHORT_INTERVAL <= SUI_DEDICATED_LONG_INTERVAL, got {} and {}",
+                worker.dedicated_short_interval, worker.dedicated_long_interval
+            ));
+        }
+
+        if let Err(e) = self.protocol_allowlist() {
+            errors.push(format!("SUI_PROTOCOLS: {}", e));
+        }
+        if let Err(e) = self.upgrade_config.parse_upgrade_caps() {
+            errors.push(format!("SUI_UPGRADE_CAPS: {}", e));
+        }
+        if self.upgrade_config.upgrade_check_interval == 0 {
+            errors.push("SUI_UPGRADE_CHECK_INTERVAL must be positive".to_string());
+        }
+
+        if self.gas_config.gas_budget_multiplier < 1.0 {
+            errors.push("SUI_GAS_BUDGET_MULTIPLIER must be at least 1".to_string());
+        }
+        if self.gas_config.min_profit_gas_multiple < 0.0 {
+            errors.push("SUI_MIN_PROFIT_GAS_MULTIPLE must not be negative".to_string());
+        }
+
+        let experiment = &self.experiment_config;
+        if experiment
+            .experiment_min_profit_gas_multiple
+            .is_some_and(|multiple| multiple < 0.0)
+        {
+            errors.push("SUI_EXPERIMENT_MIN_PROFIT_GAS_MULTIPLE must not be negative".to_string());
+        }
+        if experiment.experiment_share <= 0.0 || experiment.experiment_share >= 1.0 {
+            errors.push("SUI_EXPERIMENT_SHARE must be between 0 and 1 (exclusive)".to_string());
+        }
+
+        let base_coin = &self.base_config.base_coin;
+        if TypeTag::from_str(base_coin).is_err() {
+            errors.push(format!("SUI_BASE_COIN: invalid coin type {}", base_coin));
+        } else if !coin::is_native_coin(&normalize_coin_type(base_coin)) {
+            if self.collector_config.shio_ws_url.is_some() {
+                errors.push("shio bids are paid in SUI, SUI_SHIO_WS_URL needs SUI_BASE_COIN to be SUI".to_string());
+            }
+            if self.base_config.base_price_interval == 0 {
+                errors.push("SUI_BASE_PRICE_INTERVAL must be positive".to_string());
+            }
+        } else if self.top_up_config.gas_target.is_some() {
+            errors.push("SUI_GAS_TARGET needs a non-SUI SUI_BASE_COIN to sell profit from".to_string());
+        }
+
+        if self.worker_config.gas_coins_per_account == 0 || self.worker_config.gas_coin_interval == 0 {
+            errors.push("SUI_GAS_COINS_PER_ACCOUNT and SUI_GAS_COIN_INTERVAL must be positive".to_string());
+        }
+
+        let top_up = &self.top_up_config;
+        if top_up.gas_target.is_some() {
+            if top_up.top_up_max_share <= 0.0 || top_up.top_up_max_share > 1.0 {
+                errors.push("SUI_TOP_UP_MAX_SHARE must be in (0, 1]".to_string());
+            }
+            if top_up.top_up_gas_coins == 0 || top_up.top_up_interval == 0 {
+                errors.push("SUI_TOP_UP_GAS_COINS and SUI_TOP_UP_INTERVAL must be positive".to_string());
+            }
+        }
+
+        for (name, coin_types) in [
+            ("SUI_WATCH_COINS", &self.collector_config.watch_coins),
+            ("SUI_SPREAD_COINS", &self.spread_config.spread_coins),
+        ] {
+            for coin_type in coin_types {
+                if TypeTag::from_str(coin_type).is_err() {
+                    errors.push(format!("{}: invalid coin type {}", name, coin_type));
+                }
+            }
+        }
+        let spread = &self.spread_config;
+        if !spread.spread_coins.is_empty() && (spread.spread_interval == 0 || spread.spread_probe_amount == 0) {
+            errors.push("SUI_SPREAD_INTERVAL and SUI_SPREAD_PROBE_AMOUNT must be positive".to_string());
+        }
+
+        let admin = &self.admin_config;
+        if admin.admin_addr.is_some() && admin.admin_addr == self.metrics_config.metrics_addr {
+            errors.push("SUI_ADMIN_ADDR and SUI_METRICS_ADDR must differ".to_string());
+        }
+        if admin.admin_token.as_deref() == Some("") {
+            errors.push("SUI_ADMIN_TOKEN must not be empty".to_string());
+        }
+
+        ensure!(errors.is_empty(), "invalid configuration:\n  {}", errors.join("\n  "));
+        Ok(())
+    }
 }
 
 #[derive(Clone, Debug, Parser)]
 struct CollectorConfig {
     /// relay tx collector (should be mutually exclusive with public tx collector)
-    #[arg(long)]
+    #[arg(long, env = "SUI_RELAY_WS_URL")]
     pub relay_ws_url: Option<String>,
 
     /// shio collector
-    #[arg(long)]
+    #[arg(long, env = "SUI_SHIO_WS_URL")]
     pub shio_ws_url: Option<String>,
 
     /// public tx collector
     #[arg(long, env = "SUI_TX_SOCKET_PATH", default_value = "/tmp/sui_tx.sock")]
     pub tx_socket_path: String,
+
+    /// Fullnode websocket to subscribe to swap events over, alongside the other collectors
+    #[arg(long, env = "SUI_WS_URL")]
+    pub ws_url: Option<String>,
+
+    /// Alert on, and immediately arb, new pools holding any of these coin types
+    #[arg(long, env = "SUI_WATCH_COINS", value_delimiter = ',')]
+    pub watch_coins: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+enum SimulatorKind {
+    /// Dry runs on the RPC node
+    Http,
+    /// Local execution on the database of a colocated node
+    Db,
+    /// Local execution on objects cached in memory, fetched from the RPC node on a miss
+    Memory,
 }
 
 #[derive(Clone, Debug, Parser)]
@@ -80,94 +484,265 @@ struct DbSimConfig {
     )]
     pub preload_path: String,
 
-    /// use db simulator or not
-    #[arg(long, default_value_t = false)]
+    /// use db simulator or not, same as `--simulator db`
+    #[arg(long, env = "SUI_USE_DB_SIMULATOR", default_value_t = false)]
     pub use_db_simulator: bool,
 
+    /// simulator backing searches and dry runs; `http` unless `SUI_USE_DB_SIMULATOR` is set
+    #[arg(long, env = "SUI_SIMULATOR", value_enum)]
+    pub simulator: Option<SimulatorKind>,
+
     /// catchup interval in seconds
-    #[arg(long, default_value_t = 60)]
+    #[arg(long, env = "SUI_CATCHUP_INTERVAL", default_value_t = 60)]
     pub catchup_interval: u64,
 }
 
+impl DbSimConfig {
+    fn simulator_kind(&self) -> SimulatorKind {
+        match self.simulator {
+            Some(kind) => kind,
+            None if self.use_db_simulator => SimulatorKind::Db,
+            None => SimulatorKind::Http,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Parser)]
 struct WorkerConfig {
     /// Number of workers to process events (public tx, private tx, shio)
     /// 8 is usually enough
-    #[arg(long, default_value_t = 8)]
+    #[arg(long, env = "SUI_WORKERS", default_value_t = 8)]
     pub workers: usize,
 
     /// Number of simulator in simulator pool.
-    #[arg(long, default_value_t = 32)]
+    #[arg(long, env = "SUI_NUM_SIMULATORS", default_value_t = 32)]
     pub num_simulators: usize,
 
     /// If a new coin comes in and it has been processed within the last `max_recent_arbs` times,
     /// it will be ignored.
-    #[arg(long, default_value_t = 20)]
+    #[arg(long, env = "SUI_MAX_RECENT_ARBS", default_value_t = 20)]
     pub max_recent_arbs: usize,
 
     /// short and long interval for dedicated simulator (in milliseconds)
     /// short: 50ms
-    #[arg(long, default_value_t = 50)]
+    #[arg(long, env = "SUI_DEDICATED_SHORT_INTERVAL", default_value_t = 50)]
     pub dedicated_short_interval: u64,
 
     /// long: 200ms
-    #[arg(long, default_value_t = 200)]
+    #[arg(long, env = "SUI_DEDICATED_LONG_INTERVAL", default_value_t = 200)]
     pub dedicated_long_interval: u64,
+
+    /// Record every trigger and decision to this file (see `arb replay`)
+    #[arg(long, env = "SUI_REPLAY_RECORD_PATH")]
+    pub replay_record_path: Option<String>,
+
+    /// Append submitted and executed trades to this journal (see `arb export-trades`)
+    #[arg(long, env = "SUI_TRADE_JOURNAL_PATH")]
+    pub journal_path: Option<String>,
+
+    /// Also keep the journal, with every opportunity and final dry run, in this SQLite database
+    /// (see `arb report`)
+    #[arg(long, env = "SUI_TRADE_DB_PATH")]
+    pub trade_db_path: Option<String>,
+
+    /// How long a trade keeps its account for the pools it touches (in milliseconds)
+    #[arg(long, env = "SUI_ACCOUNT_LEASE_MS", default_value_t = 2000)]
+    pub account_lease_ms: u64,
+
+    /// Account for a trade that conflicts with no other: the least busy, or each in turn
+    #[arg(long, env = "SUI_ACCOUNT_SELECTION", value_enum, default_value_t = AccountSelection::LeastBusy)]
+    pub account_selection: AccountSelection,
+
+    /// Gas coins each account is kept at, one per trade in flight (1: trades pay with all of the
+    /// account's SUI)
+    #[arg(long, env = "SUI_GAS_COINS_PER_ACCOUNT", default_value_t = 1)]
+    pub gas_coins_per_account: usize,
+
+    /// Seconds between checks of each account's gas coins
+    #[arg(long, env = "SUI_GAS_COIN_INTERVAL", default_value_t = 60)]
+    pub gas_coin_interval: u64,
 }
 
-pub async fn run(args: Args) -> Result<()> {
+pub async fn run(mut args: Args) -> Result<()> {
     utils::set_panic_hook();
     mev_logger::init_with_whitelisted_modules(
         "mainnet",
         "sui-arb".to_string(),
-        &["arb", "utils", "shio", "cache_metrics=debug"],
+        &[
+            "arb",
+            "poison_dart_core",
+            "utils",
+            "shio",
+            "cache_metrics=debug",
+            "spread_metrics",
+            "rpc_metrics",
+            "outcome_metrics",
+            "experiment_metrics",
+            "dry_run_metrics",
+        ],
     );
 
-    let keypair = SuiKeyPair::decode(&args.private_key)?;
+    let file_config = match args.bot_config {
+        Some(ref path) => Some(FileConfig::load(path).wrap_err(FatalKind::Config)?),
+        None => None,
+    };
+    // reloads start over from the flags, so a key removed from the file falls back to them
+    let flag_gas_config = args.gas_config.clone();
+    if let Some(ref file_config) = file_config {
+        args.apply_file(file_config);
+    }
+
+    args.validate().wrap_err(FatalKind::Config)?;
+    if !args.protocols.is_empty() {
+        registry::set_protocol_allowlist(args.protocol_allowlist()?);
+    }
+    registry::register_partners(&args.partner_config);
+    parallel_sim::init(args.parallel_sim_config.clone()).wrap_err(FatalKind::Config)?;
+    quote_filter::init(args.quote_filter_config.clone()).wrap_err(FatalKind::Config)?;
+    routes::init(args.route_config.clone()).wrap_err(FatalKind::Config)?;
+    dedup::init(args.dedup_config.clone()).wrap_err(FatalKind::Config)?;
+    bid::init(args.bid_config.clone()).wrap_err(FatalKind::Config)?;
+    competition::init(args.competition_config.clone()).wrap_err(FatalKind::Config)?;
+    let graph_settings = GraphSettings::new(args.graph_config.clone()).wrap_err(FatalKind::Config)?;
+    if let Some(ref file_config) = file_config {
+        file_config
+            .apply_tunables(&graph_settings)
+            .wrap_err(FatalKind::Config)?;
+        alerts::init(&file_config.alerts).wrap_err(FatalKind::Config)?;
+    }
+    descriptor::init(&args.descriptor_config)
+        .await
+        .wrap_err(FatalKind::Config)?;
+    // the control arm trades with the gas config, reloads included
+    let control = ArmParams {
+        min_profit_gas_multiple: None,
+        sizing: Sizing::Local,
+    };
+    experiment::init(&args.experiment_config, control).wrap_err(FatalKind::Config)?;
+
+    let keypair = SuiKeyPair::decode(&args.private_key).wrap_err(FatalKind::Key)?;
     let pubkey = keypair.public();
     let attacker = SuiAddress::from(&pubkey);
+    let extra_keypairs = args
+        .extra_private_keys
+        .iter()
+        .map(|key| SuiKeyPair::decode(key))
+        .collect::<Result<Vec<_>, _>>()
+        .wrap_err(FatalKind::Key)?;
+    let accounts: Vec<SuiAddress> = std::iter::once(attacker)
+        .chain(extra_keypairs.iter().map(|kp| SuiAddress::from(&kp.public())))
+        .collect();
 
     info!(
-        "start_bot with attacker: {}, http_config: {:#?}, collector_config: {:#?}, db_sim_config: {:#?}, worker_config: {:#?}",
-        attacker, args.http_config, args.collector_config, args.db_sim_config, args.worker_config
+        "start_bot with attacker: {}, accounts: {:?}, effective config: {:#?}",
+        attacker, accounts, args
     );
+    set_deny_list_senders(accounts.clone()).wrap_err(FatalKind::Config)?;
 
     let rpc_url = args.http_config.rpc_url;
     let db_path = args.db_sim_config.db_path;
     let tx_socket_path = args.collector_config.tx_socket_path;
+    let store = match args.worker_config.trade_db_path {
+        Some(ref path) => Some(Arc::new(TradeStore::open(path)?)),
+        None => None,
+    };
+    let journal = match (&args.worker_config.journal_path, store) {
+        (Some(path), Some(store)) => Some(Arc::new(TradeJournal::new(path)?.with_store(store))),
+        (Some(path), None) => Some(Arc::new(TradeJournal::new(path)?)),
+        (None, Some(store)) => Some(Arc::new(TradeJournal::store_only(store))),
+        (None, None) => None,
+    };
     let config_path = args.db_sim_config.config_path;
     let update_cache_socket = args.db_sim_config.update_cache_socket;
     let preload_path = args.db_sim_config.preload_path;
     let mut engine = Engine::default();
 
+    let rpc_urls: Vec<String> = std::iter::once(rpc_url.clone())
+        .chain(args.rpc_config.extra_rpc_urls.iter().cloned())
+        .collect();
+    let endpoints = Arc::new(
+        RpcEndpoints::new(
+            &rpc_urls,
+            args.rpc_config.rpc_max_lag,
+            args.rpc_config.max_checkpoint_age_ms,
+        )
+        .await
+        .wrap_err(FatalKind::RpcUnreachable)?,
+    );
+    {
+        let endpoints = Arc::clone(&endpoints);
+        let interval = Duration::from_millis(args.rpc_config.rpc_probe_interval_ms);
+        let (stale_data_alerts, mut stale_data_events) = mpsc::unbounded_channel();
+        tokio::spawn(async move { endpoints.run(interval, stale_data_alerts).await });
+        tokio::spawn(async move {
+            let dispatcher = TelegramMessageDispatcher::new_without_error_report();
+            while let Some(event) = stale_data_events.recv().await {
+                let severity = match event {
+                    StaleDataEvent::Stale { .. } => Severity::Critical,
+                    StaleDataEvent::Fresh { .. } => Severity::Info,
+                };
+                alerts::emit(severity, "RPC endpoints", event.to_string());
+                dispatcher.send_message(new_stale_data_alert_message(&event)).await;
+            }
+        });
+    }
+    if let Some(min_balance) = file_config.as_ref().and_then(|file| file.alerts.min_balance) {
+        tokio::spawn(alerts::watch_balances(
+            endpoints.background(),
+            accounts.clone(),
+            min_balance,
+            Duration::from_secs(60),
+        ));
+    }
+
     if let Some(ref ws_url) = args.collector_config.shio_ws_url {
         let (shio_collector, shio_executor) =
             new_shio_collector_and_executor(keypair, Some(ws_url.clone()), None).await;
         engine.add_collector(map_collector!(shio_collector, Event::Shio));
 
-        if args.shio_use_rpc {
-            let shio_rpc_executor = ShioRPCExecutor::new(SuiKeyPair::decode(&args.private_key)?);
-            engine.add_executor(map_executor!(shio_rpc_executor, Action::ShioSubmitBid));
-        } else {
-            engine.add_executor(map_executor!(shio_executor, Action::ShioSubmitBid));
+        // under a dry run, bids are simulated once the simulator pool is up
+        if !args.dry_run {
+            if args.shio_use_rpc {
+                let shio_rpc_executor = ShioRPCExecutor::new(SuiKeyPair::decode(&args.private_key)?);
+                engine.add_executor(map_executor!(shio_rpc_executor, Action::ShioSubmitBid));
+            } else {
+                engine.add_executor(map_executor!(shio_executor, Action::ShioSubmitBid));
+            }
         }
     } else {
         let public_tx_collector = PublicTxCollector::new(&tx_socket_path);
         engine.add_collector(Box::new(public_tx_collector));
     }
 
-    engine.add_executor(map_executor!(
-        PublicTxExecutor::new(&rpc_url, SuiKeyPair::decode(&args.private_key)?).await?,
-        Action::ExecutePublicTx
-    ));
+    if !args.dry_run {
+        engine.add_executor(map_executor!(
+            PublicTxExecutor::new(&rpc_url, SuiKeyPair::decode(&args.private_key)?)
+                .await
+                .wrap_err(FatalKind::RpcUnreachable)?
+                .with_keypairs(extra_keypairs)
+                .with_endpoints(Arc::clone(&endpoints))
+                .with_journal(journal.clone()),
+            Action::ExecutePublicTx
+        ));
+    }
 
     if let Some(ref relay_ws_url) = args.collector_config.relay_ws_url {
         let private_tx_collector = PrivateTxCollector::new(relay_ws_url);
         engine.add_collector(Box::new(private_tx_collector));
     }
 
-    let simulator_pool: ObjectPool<Box<dyn Simulator>> = match args.db_sim_config.use_db_simulator {
-        true => {
+    if let Some(ref ws_url) = args.collector_config.ws_url {
+        engine.add_collector(Box::new(WsCollector::new(&rpc_url, ws_url)));
+    }
+
+    let simulator_kind = args.db_sim_config.simulator_kind();
+    if Path::new(&preload_path).exists() {
+        pool_ids::verify_snapshot(Path::new(&preload_path)).wrap_err(FatalKind::Config)?;
+    }
+    // shared by every memory simulator, so an object fetched once serves all of them
+    let object_cache = Arc::new(ObjectCache::new());
+    let simulator_pool: ObjectPool<Box<dyn Simulator>> = match simulator_kind {
+        SimulatorKind::Db => {
             let db_path = db_path.to_string();
             let config_path = config_path.to_string();
             let update_cache_socket = update_cache_socket.to_string();
@@ -184,7 +759,18 @@ pub async fn run(args: Args) -> Result<()> {
                 })
             })
         }
-        false => {
+        SimulatorKind::Memory => {
+            let rpc_url = rpc_url.to_string();
+            let object_cache = Arc::clone(&object_cache);
+            ObjectPool::new(args.worker_config.num_simulators, move || {
+                let rpc_url = rpc_url.clone();
+                let object_cache = Arc::clone(&object_cache);
+                tokio::runtime::Runtime::new().unwrap().block_on(async {
+                    Box::new(MemorySimulator::new(&rpc_url, object_cache).await) as Box<dyn Simulator>
+                })
+            })
+        }
+        SimulatorKind::Http => {
             warn!("http simulator is deprecated. use only for testing");
 
             let rpc_url = rpc_url.to_string();
@@ -202,16 +788,26 @@ pub async fn run(args: Args) -> Result<()> {
     };
 
     // TODO: when we have relay (tons of un-executed txs), maybe we should use a simulator pool
-    let own_simulator = if args.db_sim_config.use_db_simulator {
-        Arc::new(DBSimulator::new_slow(&db_path, &config_path, Some(&update_cache_socket), Some(&preload_path)).await)
-            as Arc<dyn Simulator>
-    } else {
-        warn!("http simulator is deprecated. use only for testing");
-        let ipc_path = args.http_config.ipc_path;
-        Arc::new(HttpSimulator::new(&rpc_url, &ipc_path).await) as Arc<dyn Simulator>
+    let own_simulator = match simulator_kind {
+        SimulatorKind::Db => Arc::new(
+            DBSimulator::new_slow(&db_path, &config_path, Some(&update_cache_socket), Some(&preload_path)).await,
+        ) as Arc<dyn Simulator>,
+        SimulatorKind::Memory => {
+            let simulator = MemorySimulator::new(&rpc_url, Arc::clone(&object_cache)).await;
+            // warm the cache with the pool objects `pool-ids` lists, when there is a list
+            if Path::new(&preload_path).exists() {
+                simulator.preload(Path::new(&preload_path)).await?;
+            }
+            Arc::new(simulator) as Arc<dyn Simulator>
+        }
+        SimulatorKind::Http => {
+            warn!("http simulator is deprecated. use only for testing");
+            let ipc_path = args.http_config.ipc_path;
+            Arc::new(HttpSimulator::new(&rpc_url, &ipc_path).await) as Arc<dyn Simulator>
+        }
     };
 
-    let dedicated_simulator = if args.db_sim_config.use_db_simulator {
+    let dedicated_simulator = if simulator_kind == SimulatorKind::Db {
         Some(Arc::new(
             ReplaySimulator::new_slow(
                 &db_path,
@@ -227,14 +823,186 @@ pub async fn run(args: Args) -> Result<()> {
 
     info!("simulator_pool initialized: {:?}", simulator_pool);
 
+    let upgrade_monitor =
+        UpgradeMonitor::new(endpoints.background(), Arc::clone(&own_simulator), &args.upgrade_config)?;
+    let (upgrade_alerts, mut upgrade_events) = mpsc::unbounded_channel();
+    tokio::spawn(upgrade_monitor.run(
+        Duration::from_secs(args.upgrade_config.upgrade_check_interval),
+        upgrade_alerts,
+    ));
+    tokio::spawn(async move {
+        let dispatcher = TelegramMessageDispatcher::new_without_error_report();
+        while let Some(event) = upgrade_events.recv().await {
+            dispatcher.send_message(new_upgrade_alert_message(&event)).await;
+        }
+    });
+
+    let replay_recorder = match args.worker_config.replay_record_path {
+        Some(ref path) => Some(Arc::new(ReplayRecorder::new(path)?)),
+        None => None,
+    };
+
+    let simulator_pool = Arc::new(simulator_pool);
+    if args.dry_run {
+        warn!("dry run: trades are simulated, nothing is submitted");
+        let dry_run = Arc::new(
+            DryRun::new(Arc::clone(&simulator_pool), endpoints.background())
+                .await
+                .wrap_err(FatalKind::RpcUnreachable)?,
+        );
+        engine.add_executor(map_executor!(
+            DryRunExecutor::new(Arc::clone(&dry_run)),
+            Action::ExecutePublicTx
+        ));
+        if args.collector_config.shio_ws_url.is_some() {
+            engine.add_executor(map_executor!(DryRunExecutor::new(dry_run), Action::ShioSubmitBid));
+        }
+        tokio::spawn(dry_run::run(Duration::from_secs(60)));
+    }
+
+    let base_coin = normalize_coin_type(&args.base_config.base_coin);
+    if !coin::is_native_coin(&base_coin) {
+        let defi = Defi::new(&rpc_url, Arc::clone(&simulator_pool), &args.flashloan_config).await?;
+        let decimals = defi
+            .get_dex_searcher()
+            .coin_decimals(&base_coin)
+            .await
+            .ok_or_eyre("unknown decimals for the base coin")
+            .wrap_err(FatalKind::Config)?;
+        base::init(&base_coin, decimals)?;
+
+        // profits can't be compared to gas until SUI is priced in the base coin
+        let sui = endpoints.background();
+        base::refresh_sui_price(&defi, &sui, attacker)
+            .await
+            .wrap_err("failed to price SUI in the base coin")?;
+        let interval = Duration::from_secs(args.base_config.base_price_interval);
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                if let Err(error) = base::refresh_sui_price(&defi, &sui, attacker).await {
+                    warn!(?error, "failed to refresh the SUI price, keeping the last one");
+                }
+            }
+        });
+    }
+    info!(base_coin = base::coin_type(), "base currency");
+
+    if args.top_up_config.gas_target.is_some() && args.dry_run {
+        info!("dry run: gas top-ups are off");
+    } else if args.top_up_config.gas_target.is_some() {
+        let defi = Defi::new(&rpc_url, Arc::clone(&simulator_pool), &args.flashloan_config).await?;
+        let sui = endpoints.background();
+        let extra_keypairs = args
+            .extra_private_keys
+            .iter()
+            .map(|key| SuiKeyPair::decode(key))
+            .collect::<Result<Vec<_>, _>>()?;
+        let executor = PublicTxExecutor::new(&rpc_url, SuiKeyPair::decode(&args.private_key)?)
+            .await
+            .wrap_err(FatalKind::RpcUnreachable)?
+            .with_keypairs(extra_keypairs);
+        let gas_top_up = GasTopUp::new(defi, sui, executor, accounts.clone(), args.top_up_config.clone())?;
+        tokio::spawn(gas_top_up.run());
+    }
+
+    if !args.collector_config.watch_coins.is_empty() {
+        let indexer = IndexerDexSearcher::new(&rpc_url, Arc::clone(&simulator_pool))
+            .await?
+            .get_indexer();
+        let new_pool_collector = NewPoolCollector::new(indexer, &args.collector_config.watch_coins);
+        engine.add_collector(Box::new(new_pool_collector));
+    }
+    if !args.spread_config.spread_coins.is_empty() {
+        let defi = Defi::new(&rpc_url, Arc::clone(&simulator_pool), &args.flashloan_config).await?;
+        let sui = endpoints.background();
+        let spread_monitor = SpreadMonitor::new(defi, sui, attacker, args.spread_config.clone())?;
+        tokio::spawn(spread_monitor.run());
+    }
+
+    let gas_oracle = Arc::new(
+        GasOracle::new(endpoints.background(), args.gas_config.clone())
+            .await
+            .wrap_err(FatalKind::RpcUnreachable)?,
+    );
+    if let (Some(path), Some(file_config)) = (args.bot_config.clone(), file_config) {
+        tokio::spawn(config::watch(
+            path,
+            file_config,
+            flag_gas_config,
+            Arc::clone(&gas_oracle),
+            graph_settings.clone(),
+            Duration::from_secs(5),
+        ));
+    }
+
+    let partitioner = Arc::new(
+        AccountPartitioner::new(
+            accounts.clone(),
+            Duration::from_millis(args.worker_config.account_lease_ms),
+        )
+        .with_selection(args.worker_config.account_selection)
+        .with_gas_coins_per_account(args.worker_config.gas_coins_per_account),
+    );
+    if args.worker_config.gas_coins_per_account > 1 && args.dry_run {
+        info!("dry run: gas coins are not rebalanced");
+    } else if args.worker_config.gas_coins_per_account > 1 {
+        let extra_keypairs = args
+            .extra_private_keys
+            .iter()
+            .map(|key| SuiKeyPair::decode(key))
+            .collect::<Result<Vec<_>, _>>()?;
+        let executor = PublicTxExecutor::new(&rpc_url, SuiKeyPair::decode(&args.private_key)?)
+            .await
+            .wrap_err(FatalKind::RpcUnreachable)?
+            .with_keypairs(extra_keypairs);
+        let balancer = GasCoinBalancer::new(
+            endpoints.background(),
+            executor,
+            accounts,
+            Arc::clone(&partitioner),
+            args.worker_config.gas_coins_per_account,
+            Duration::from_secs(args.worker_config.gas_coin_interval),
+        );
+        tokio::spawn(balancer.run());
+    }
+
+    if let Some(addr) = args.admin_config.admin_addr {
+        let defi = Defi::new(&rpc_url, Arc::clone(&simulator_pool), &args.flashloan_config).await?;
+        let weights = QuoteWeights::new(defi.get_trader(), defi.get_dex_searcher(), endpoints.background());
+        let path_finder =
+            BellmanFordPathFinder::new(defi.get_dex_searcher(), graph_settings.clone()).with_weights(Arc::new(weights));
+        let token = args.admin_config.admin_token.clone();
+        let endpoints = Arc::clone(&endpoints);
+        tokio::spawn(async move {
+            if let Err(error) = server::serve(addr, token, endpoints, path_finder).await {
+                warn!(?error, "admin API stopped");
+            }
+        });
+    }
+
+    let tokens = Arc::new(
+        TokenMetadata::new(endpoints.background(), args.token_meta_config.clone()).wrap_err(FatalKind::Config)?,
+    );
+    // what shutdown needs once the strategy owns the rest
+    let (shutdown_sui, shutdown_journal, shutdown_tokens) =
+        (endpoints.background(), journal.clone(), Arc::clone(&tokens));
     let arb_strategy = ArbStrategy::new(
         attacker,
-        Arc::new(simulator_pool),
+        simulator_pool,
         own_simulator,
         args.worker_config.max_recent_arbs,
         &rpc_url,
         args.worker_config.workers,
         dedicated_simulator,
+        replay_recorder,
+        journal,
+        Arc::clone(&partitioner),
+        gas_oracle,
+        endpoints,
+        tokens,
+        graph_settings,
+        args.flashloan_config.clone(),
     )
     .await;
     engine.add_strategy(Box::new(arb_strategy));
@@ -245,8 +1013,43 @@ pub async fn run(args: Args) -> Result<()> {
     ));
 
     heartbeat::start("sui-arb", Duration::from_secs(30));
+    tokio::spawn(cache_metrics::run(Duration::from_secs(60)));
+    tokio::spawn(outcomes::run(Duration::from_secs(60)));
+    tokio::spawn(experiment::run(Duration::from_secs(60)));
+    if let Some(addr) = args.metrics_config.metrics_addr {
+        tokio::spawn(async move {
+            if let Err(error) = metrics::serve(addr).await {
+                warn!(?error, "metrics server stopped");
+            }
+        });
+    }
 
-    engine.run_and_join().await.unwrap();
+    let run = engine.run_and_join();
+    tokio::pin!(run);
+    tokio::select! {
+        result = &mut run => {
+            result.unwrap();
+            return Ok(());
+        }
+        signal = shutdown::signal() => info!(signal = ?signal, "shutting down"),
+    }
+
+    // the engine keeps running while in-flight work drains, so executors can settle it
+    shutdown::begin();
+    let timeout = Duration::from_secs(args.shutdown_config.shutdown_timeout);
+    let unsettled = tokio::select! {
+        unsettled = shutdown::drain(timeout) => unsettled,
+        result = &mut run => {
+            result.unwrap();
+            outcomes::unresolved()
+        }
+    };
+    shutdown::reconcile(&shutdown_sui, unsettled, shutdown_journal.as_deref()).await;
+    if let Err(error) = shutdown_tokens.flush() {
+        warn!(?error, "failed to write token metadata");
+    }
+    outcomes::log();
+    info!("shutdown complete");
 
     Ok(())
 }
@@ -0,0 +1,373 @@
+//! SQLite store of the trade journal, queried by `arb report`.
+//!
+//! The journal (see `journal`) writes every entry here too when `SUI_TRADE_DB_PATH` is set:
+//! opportunities the search found, the final dry run of each, submitted trades with their legs, and
+//! what became of them on chain. Submitted and executed entries meet in one `trades` row keyed by
+//! `arb_digest`, so P&L is a plain aggregate over `trades` and `trade_legs`.
+
+use std::{path::Path, sync::Mutex};
+
+use eyre::Result;
+use rusqlite::{params, Connection};
+use sui_sdk::SUI_COIN_TYPE;
+use utils::coin;
+
+use crate::journal::{DetectedOpportunity, ExecutedTrade, JournalEntry, SimulatedTrade, SubmittedTrade, TradeLeg};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS opportunities (
+    id TEXT PRIMARY KEY,
+    timestamp_ms INTEGER NOT NULL,
+    trigger_digest TEXT NOT NULL,
+    source TEXT NOT NULL,
+    coin_type TEXT NOT NULL,
+    amount_in INTEGER NOT NULL,
+    expected_profit INTEGER NOT NULL,
+    path_shape TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS simulations (
+    opportunity TEXT NOT NULL,
+    timestamp_ms INTEGER NOT NULL,
+    profit INTEGER,
+    gas_used INTEGER,
+    error TEXT
+);
+CREATE TABLE IF NOT EXISTS trades (
+    arb_digest TEXT PRIMARY KEY,
+    timestamp_ms INTEGER NOT NULL,
+    trigger_digest TEXT NOT NULL,
+    source TEXT NOT NULL,
+    coin_type TEXT NOT NULL,
+    base_coin TEXT NOT NULL,
+    amount_in INTEGER NOT NULL,
+    expected_profit INTEGER NOT NULL,
+    bid_amount INTEGER NOT NULL,
+    path_shape TEXT NOT NULL,
+    executed_ms INTEGER,
+    success INTEGER,
+    outcome TEXT,
+    gas_used INTEGER,
+    realized_profit INTEGER,
+    error TEXT,
+    sender TEXT
+);
+CREATE TABLE IF NOT EXISTS trade_legs (
+    arb_digest TEXT NOT NULL,
+    leg INTEGER NOT NULL,
+    protocol TEXT NOT NULL,
+    pool_id TEXT NOT NULL,
+    coin_in TEXT NOT NULL,
+    coin_out TEXT NOT NULL,
+    PRIMARY KEY (arb_digest, leg)
+);
+CREATE INDEX IF NOT EXISTS trades_timestamp ON trades (timestamp_ms);
+";
+
+/// The protocols of a path in trade order, e.g. `cetus>turbos`.
+pub fn path_shape(legs: &[TradeLeg]) -> String {
+    legs.iter()
+        .map(|leg| leg.protocol.as_str())
+        .collect::<Vec<_>>()
+        .join(">")
+}
+
+pub struct TradeStore {
+    conn: Mutex<Connection>,
+}
+
+impl TradeStore {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    #[cfg(test)]
+    fn in_memory() -> Result<Self> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(conn: Connection) -> Result<Self> {
+        conn.execute_batch(SCHEMA)?;
+        // databases created before trades recorded their account
+        if conn.prepare("SELECT sender FROM trades LIMIT 0").is_err() {
+            conn.execute("ALTER TABLE trades ADD COLUMN sender TEXT", [])?;
+        }
+        Ok(Self { conn: Mutex::new(conn) })
+    }
+
+    pub fn record(&self, entry: &JournalEntry) -> Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        match entry {
+            JournalEntry::Detected(opportunity) => insert_opportunity(&conn, opportunity),
+            JournalEntry::Simulated(simulated) => insert_simulation(&conn, simulated),
+            JournalEntry::Submitted(trade) => insert_trade(&mut conn, trade),
+            // executions of anything but a journaled trade (e.g. gas top-ups) match no row
+            JournalEntry::Executed(trade) => update_trade(&conn, trade),
+        }
+    }
+
+    /// P&L per UTC day (`YYYY-MM-DD`) of trades that reached the executor.
+    pub fn pnl_by_day(&self) -> Result<Vec<PnlRow>> {
+        self.pnl("date(timestamp_ms / 1000, 'unixepoch')", "trades")
+    }
+
+    /// P&L per protocol; a trade counts once for every protocol it went through.
+    pub fn pnl_by_protocol(&self) -> Result<Vec<PnlRow>> {
+        self.pnl(
+            "protocol",
+            "trades JOIN (SELECT DISTINCT arb_digest, protocol FROM trade_legs) USING (arb_digest)",
+        )
+    }
+
+    pub fn pnl_by_path_shape(&self) -> Result<Vec<PnlRow>> {
+        self.pnl("path_shape", "trades")
+    }
+
+    /// P&L per sending account; trades journaled without one are under `unknown`.
+    pub fn pnl_by_account(&self) -> Result<Vec<PnlRow>> {
+        self.pnl("COALESCE(sender, 'unknown')", "trades")
+    }
+
+    /// (opportunities, simulations, simulations that failed)
+    pub fn detection_counts(&self) -> Result<(u64, u64, u64)> {
+        let conn = self.conn.lock().unwrap();
+        let opportunities = conn.query_row("SELECT COUNT(*) FROM opportunities", [], |row| row.get(0))?;
+        let (simulations, failed) = conn.query_row("SELECT COUNT(*), COUNT(error) FROM simulations", [], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?;
+        Ok((opportunities, simulations, failed))
+    }
+
+    fn pnl(&self, key: &str, from: &str) -> Result<Vec<PnlRow>> {
+        let sql = format!(
+            "SELECT {key} AS key, base_coin, COUNT(*),
+                COALESCE(SUM(outcome IN ('landed_profitable', 'landed_unprofitable')), 0),
+                COALESCE(SUM(outcome = 'reverted'), 0),
+                COALESCE(SUM(realized_profit), 0),
+                COALESCE(SUM(CASE WHEN realized_profit IS NULL THEN gas_used END), 0)
+            FROM {from}
+            WHERE executed_ms IS NOT NULL
+            GROUP BY key, base_coin
+            ORDER BY key, base_coin"
+        );
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map([], |row| {
+            Ok(PnlRow {
+                key: row.get(0)?,
+                base_coin: row.get(1)?,
+                trades: row.get(2)?,
+                landed: row.get(3)?,
+                reverted: row.get(4)?,
+                realized_profit: row.get(5)?,
+                unrealized_gas: row.get(6)?,
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+}
+
+/// Executed trades sharing a key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PnlRow {
+    pub key: String,
+    pub base_coin: String,
+    pub trades: u64,
+    pub landed: u64,
+    pub reverted: u64,
+    /// Base coin balance change net of gas of the trades that landed
+    pub realized_profit: i64,
+    /// Gas (MIST) paid by the trades with no realized profit, i.e. that reverted
+    pub unrealized_gas: i64,
+}
+
+impl PnlRow {
+    /// Realized profit less the gas of reverted trades, when both are in SUI.
+    pub fn net(&self) -> Option<i64> {
+        coin::is_native_coin(&self.base_coin).then(|| self.realized_profit - self.unrealized_gas)
+    }
+}
+
+fn insert_opportunity(conn: &Connection, opportunity: &DetectedOpportunity) -> Result<()> {
+    conn.execute(
+        "INSERT OR IGNORE INTO opportunities VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            opportunity.id.to_string(),
+            opportunity.timestamp_ms as i64,
+            opportunity.trigger_digest.to_string(),
+            opportunity.source,
+            opportunity.coin_type,
+            opportunity.amount_in as i64,
+            opportunity.expected_profit as i64,
+            path_shape(&opportunity.legs),
+        ],
+    )?;
+    Ok(())
+}
+
+fn insert_simulation(conn: &Connection, simulated: &SimulatedTrade) -> Result<()> {
+    conn.execute(
+        "INSERT INTO simulations VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            simulated.opportunity.to_string(),
+            simulated.timestamp_ms as i64,
+            simulated.profit,
+            simulated.gas_used.map(|gas| gas as i64),
+            simulated.error,
+        ],
+    )?;
+    Ok(())
+}
+
+fn insert_trade(conn: &mut Connection, trade: &SubmittedTrade) -> Result<()> {
+    let tx = conn.transaction()?;
+    let arb_digest = trade.arb_digest.to_string();
+    tx.execute(
+        "INSERT OR IGNORE INTO trades (arb_digest, timestamp_ms, trigger_digest, source, coin_type, base_coin,
+            amount_in, expected_profit, bid_amount, path_shape, sender)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        params![
+            arb_digest,
+            trade.timestamp_ms as i64,
+            trade.trigger_digest.to_string(),
+            trade.source,
+            trade.coin_type,
+            trade.base_coin.as_deref().unwrap_or(SUI_COIN_TYPE),
+            trade.amount_in as i64,
+            trade.expected_profit as i64,
+            trade.bid_amount as i64,
+            path_shape(&trade.legs),
+            trade.sender.map(|sender| sender.to_string()),
+        ],
+    )?;
+    for (i, leg) in trade.legs.iter().enumerate() {
+        tx.execute(
+            "INSERT OR IGNORE INTO trade_legs VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                arb_digest,
+                i as i64,
+                leg.protocol,
+                leg.pool_id.to_string(),
+                leg.coin_in,
+                leg.coin_out
+            ],
+        )?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+fn update_trade(conn: &Connection, trade: &ExecutedTrade) -> Result<()> {
+    conn.execute(
+        "UPDATE trades SET executed_ms = ?2, success = ?3, outcome = ?4, gas_used = ?5, realized_profit = ?6,
+            error = ?7
+        WHERE arb_digest = ?1",
+        params![
+            trade.arb_digest.to_string(),
+            trade.timestamp_ms as i64,
+            trade.success,
+            trade.outcome.map(|outcome| outcome.as_str()),
+            trade.gas_used,
+            trade.realized_profit,
+            trade.error,
+        ],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use sui_types::{
+        base_types::{ObjectID, SuiAddress},
+        digests::TransactionDigest,
+    };
+
+    use super::*;
+    use crate::common::outcomes::Outcome;
+
+    fn submitted(arb_digest: TransactionDigest, timestamp_ms: u64, protocols: &[&str]) -> JournalEntry {
+        JournalEntry::Submitted(SubmittedTrade {
+            timestamp_ms,
+            trigger_digest: TransactionDigest::random(),
+            arb_digest,
+            source: "public".to_string(),
+            coin_type: "0x2::sui::SUI".to_string(),
+            base_coin: None,
+            amount_in: 1_000,
+            expected_profit: 100,
+            bid_amount: 0,
+            partner_rebate: 0,
+            legs: protocols
+                .iter()
+                .map(|protocol| TradeLeg {
+                    protocol: protocol.to_string(),
+                    pool_id: ObjectID::random(),
+                    coin_in: "a".to_string(),
+                    coin_out: "b".to_string(),
+                })
+                .collect(),
+            sui_usd: None,
+            arm: None,
+            sender: Some(SuiAddress::ZERO),
+        })
+    }
+
+    fn executed(arb_digest: TransactionDigest, outcome: Outcome, realized_profit: Option<i64>) -> JournalEntry {
+        JournalEntry::Executed(ExecutedTrade {
+            timestamp_ms: 0,
+            arb_digest,
+            success: outcome != Outcome::Reverted,
+            gas_used: Some(10),
+            error: None,
+            outcome: Some(outcome),
+            realized_profit,
+        })
+    }
+
+    #[test]
+    fn test_pnl_by_day_protocol_and_shape() {
+        let store = TradeStore::in_memory().unwrap();
+        let day = 86_400_000;
+        let (a, b, c) = (
+            TransactionDigest::random(),
+            TransactionDigest::random(),
+            TransactionDigest::random(),
+        );
+        store.record(&submitted(a, day, &["cetus", "turbos"])).unwrap();
+        store.record(&submitted(b, day + 1, &["cetus", "cetus"])).unwrap();
+        // never executed, not in the P&L
+        store.record(&submitted(c, 2 * day, &["turbos", "cetus"])).unwrap();
+        store.record(&executed(a, Outcome::LandedProfitable, Some(90))).unwrap();
+        store.record(&executed(b, Outcome::Reverted, None)).unwrap();
+        // a gas top-up
+        store
+            .record(&executed(
+                TransactionDigest::random(),
+                Outcome::LandedProfitable,
+                Some(5),
+            ))
+            .unwrap();
+
+        let by_day = store.pnl_by_day().unwrap();
+        assert_eq!(by_day.len(), 1);
+        assert_eq!(by_day[0].key, "1970-01-02");
+        assert_eq!((by_day[0].trades, by_day[0].landed, by_day[0].reverted), (2, 1, 1));
+        assert_eq!(by_day[0].net(), Some(80));
+
+        let by_protocol = store.pnl_by_protocol().unwrap();
+        let keys: Vec<_> = by_protocol.iter().map(|row| (row.key.as_str(), row.trades)).collect();
+        assert_eq!(keys, vec![("cetus", 2), ("turbos", 1)]);
+
+        let by_shape = store.pnl_by_path_shape().unwrap();
+        let keys: Vec<_> = by_shape.iter().map(|row| (row.key.as_str(), row.net())).collect();
+        assert_eq!(keys, vec![("cetus>cetus", Some(-10)), ("cetus>turbos", Some(90))]);
+
+        let by_account = store.pnl_by_account().unwrap();
+        assert_eq!(by_account.len(), 1);
+        assert_eq!(by_account[0].key, SuiAddress::ZERO.to_string());
+        assert_eq!(by_account[0].net(), Some(80));
+    }
+}
@@ -0,0 +1,409 @@
+//! Spread public trades over several accounts so independent opportunities found at the same
+//! time execute in parallel, each paid from its own gas coins.
+//!
+//! A trade leases its account for the pools on its path until the trade settles, or at the latest
+//! until the lease expires. An account takes as many trades at once as it has gas coins (see
+//! `gas_coins`), each paying with a coin of its own, so no two trades in flight spend the same
+//! coin. A trade that shares a pool with a live lease goes to that lease's account, once it has a
+//! coin free, so conflicting trades queue up on one account instead of racing each other from
+//! several; one that shares pools with the leases of two accounts waits for them to be released
+//! until it conflicts with one account at most. Otherwise the account with the fewest live leases
+//! takes it, or, under round robin, the next account in turn. A trade that must be signed by the
+//! primary account (e.g. through DeepBook v2, whose account cap the primary account owns) only
+//! goes there.
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use clap::ValueEnum;
+use eyre::{OptionExt, Result};
+use sui_sdk::{SuiClient, SUI_COIN_TYPE};
+use sui_types::{
+    base_types::{ObjectID, ObjectRef, SuiAddress},
+    digests::TransactionDigest,
+};
+use tokio::sync::Notify;
+use utils::coin;
+
+use crate::common::contention;
+
+/// How an account is picked for a trade that conflicts with no live lease.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum AccountSelection {
+    /// The account with the fewest live leases
+    #[default]
+    LeastBusy,
+    /// Each account in turn
+    RoundRobin,
+}
+
+struct Lease {
+    id: u64,
+    account: usize,
+    pools: HashSet<ObjectID>,
+    expires_at: Instant,
+}
+
+#[derive(Default)]
+struct Leases {
+    live: Mutex<Vec<Lease>>,
+    // gas coin => the lease paying with it
+    gas_coins: Mutex<HashMap<ObjectID, u64>>,
+    // woken when a lease is released, for trades waiting on a busy account
+    released: Notify,
+}
+
+/// A trade's hold on its account and gas coin, released when dropped.
+pub struct AccountLease {
+    id: u64,
+    account: SuiAddress,
+    expires_at: Instant,
+    gas_coins_per_account: usize,
+    leases: Arc<Leases>,
+}
+
+impl AccountLease {
+    pub fn account(&self) -> SuiAddress {
+        self.account
+    }
+
+    /// Gas coins for the trade: all of the account's SUI coins, or with several gas coins per
+    /// account the one this lease pays with, the largest no other trade holds when first asked.
+    pub async fn gas_payment(&self, sui: &SuiClient) -> Result<Vec<ObjectRef>> {
+        if self.gas_coins_per_account <= 1 {
+            return coin::get_gas_coin_refs(sui, self.account, None).await;
+        }
+
+        let coins = coin::get_coins(sui, self.account, SUI_COIN_TYPE, 1).await?;
+        let balances = coins.iter().map(|coin| (coin.object_ref(), coin.balance)).collect();
+        let gas_coin = self
+            .lease_gas_coin(balances)
+            .ok_or_eyre("every gas coin of the account is in use")?;
+        Ok(vec![gas_coin])
+    }
+
+    fn lease_gas_coin(&self, coins: Vec<(ObjectRef, u64)>) -> Option<ObjectRef> {
+        let mut gas_coins = self.leases.gas_coins.lock().unwrap();
+        // a part or a retry of the trade pays with the same coin, at its latest version
+        if let Some((held, _)) = coins
+            .iter()
+            .find(|(obj_ref, _)| gas_coins.get(&obj_ref.0) == Some(&self.id))
+        {
+            return Some(*held);
+        }
+
+        let (gas_coin, _) = coins
+            .into_iter()
+            .filter(|(obj_ref, _)| !gas_coins.contains_key(&obj_ref.0))
+            .max_by_key(|(_, balance)| *balance)?;
+        gas_coins.insert(gas_coin.0, self.id);
+        Some(gas_coin)
+    }
+
+    /// Keep the lease until the trade submitted as `digest` settles, or the lease expires.
+    pub fn hold_until_settled(self, digest: TransactionDigest) {
+        let settled = contention::watch(digest);
+        tokio::spawn(async move {
+            let _ = tokio::time::timeout_at(self.expires_at.into(), settled).await;
+            drop(self);
+        });
+    }
+}
+
+impl Drop for AccountLease {
+    fn drop(&mut self) {
+        self.leases.live.lock().unwrap().retain(|lease| lease.id != self.id);
+        self.leases.gas_coins.lock().unwrap().retain(|_, id| *id != self.id);
+        self.leases.released.notify_waiters();
+    }
+}
+
+pub struct AccountPartitioner {
+    accounts: Vec<SuiAddress>,
+    lease_duration: Duration,
+    leases: Arc<Leases>,
+    next_lease: AtomicU64,
+    selection: AccountSelection,
+    // the next account under round robin
+    next: Mutex<usize>,
+    gas_coins_per_account: usize,
+}
+
+impl AccountPartitioner {
+    /// `accounts` starts with the primary account.
+    pub fn new(accounts: Vec<SuiAddress>, lease_duration: Duration) -> Self {
+        assert!(!accounts.is_empty(), "at least one account");
+        Self {
+            accounts,
+            lease_duration,
+            leases: Default::default(),
+            next_lease: AtomicU64::new(0),
+            selection: AccountSelection::default(),
+            next: Mutex::new(0),
+            gas_coins_per_account: 1,
+        }
+    }
+
+    pub fn with_selection(mut self, selection: AccountSelection) -> Self {
+        self.selection = selection;
+        self
+    }
+
+    /// Pay each trade with one of the account's gas coins instead of all of them, and run that many
+    /// trades from an account at once.
+    pub fn with_gas_coins_per_account(mut self, gas_coins_per_account: usize) -> Self {
+        self.gas_coins_per_account = gas_coins_per_account;
+        self
+    }
+
+    /// Pick the account for a trade over `pools`, the primary one if `primary_only`, and lease it
+    /// for them once it has a gas coin free and the trade conflicts with the leases of no other
+    /// account.
+    pub async fn acquire(&self, pools: impl IntoIterator<Item = ObjectID>, primary_only: bool) -> AccountLease {
+        let pools: HashSet<ObjectID> = pools.into_iter().collect();
+        loop {
+            // registered before the check, so a release in between isn't missed
+            let released = self.leases.released.notified();
+            match self.try_acquire_at(&pools, primary_only, Instant::now()) {
+                Ok(lease) => return lease,
+                Err(expires_at) => {
+                    let _ = tokio::time::timeout_at(expires_at.into(), released).await;
+                }
+            }
+        }
+    }
+
+    /// The lease of an account for `pools`, or if the trade has to wait, when the first lease in
+    /// its way expires.
+    fn try_acquire_at(
+        &self,
+        pools: &HashSet<ObjectID>,
+        primary_only: bool,
+        now: Instant,
+    ) -> Result<AccountLease, Instant> {
+        let mut live = self.leases.live.lock().unwrap();
+        live.retain(|lease| lease.expires_at > now);
+
+        let slots = self.gas_coins_per_account.max(1);
+        let busy = |account: usize| live.iter().filter(|lease| lease.account == account).count();
+        let candidates = if primary_only { 0..1 } else { 0..self.accounts.len() };
+        let conflicts = live
+            .iter()
+            .filter(|lease| !lease.pools.is_disjoint(pools))
+            .collect::<Vec<_>>();
+        let conflicting_accounts: HashSet<usize> = conflicts.iter().map(|lease| lease.account).collect();
+        let first_expiry = |leases: &[&Lease]| leases.iter().map(|lease| lease.expires_at).min().unwrap_or(now);
+
+        let account = match conflicting_accounts.into_iter().collect::<Vec<_>>()[..] {
+            [] => {
+                let free = candidates.filter(|&account| busy(account) < slots).collect::<Vec<_>>();
+                if free.is_empty() {
+                    return Err(first_expiry(&live.iter().collect::<Vec<_>>()));
+                }
+                match self.selection {
+                    AccountSelection::LeastBusy => free.into_iter().min_by_key(|&account| busy(account)).unwrap(),
+                    AccountSelection::RoundRobin => {
+                        let mut next = self.next.lock().unwrap();
+                        let n = self.accounts.len();
+                        let account = (0..n)
+                            .map(|i| (*next + i) % n)
+                            .find(|account| free.contains(account))
+                            .unwrap();
+                        *next = (account + 1) % n;
+                        account
+                    }
+                }
+            }
+            [account] if candidates.contains(&account) && busy(account) < slots => account,
+            // the trade would race the leases of another account, or its account has no coin free
+            _ => return Err(first_expiry(&conflicts)),
+        };
+
+        let id = self.next_lease.fetch_add(1, Ordering::Relaxed);
+        let expires_at = now + self.lease_duration;
+        live.push(Lease {
+            id,
+            account,
+            pools: pools.clone(),
+            expires_at,
+        });
+        Ok(AccountLease {
+            id,
+            account: self.accounts[account],
+            expires_at,
+            gas_coins_per_account: self.gas_coins_per_account,
+            leases: Arc::clone(&self.leases),
+        })
+    }
+
+    /// Whether independent trades can be in flight at once, from separate accounts or gas coins.
+    pub fn is_parallel(&self) -> bool {
+        self.accounts.len() > 1 || self.gas_coins_per_account > 1
+    }
+
+    /// Whether `address` is one of our accounts.
+    pub fn contains(&self, address: &SuiAddress) -> bool {
+        self.accounts.contains(address)
+    }
+
+    /// Whether a trade may still be spending any of `gas_coins`.
+    pub fn any_gas_coin_leased(&self, gas_coins: &[ObjectID]) -> bool {
+        let leased = self.leases.gas_coins.lock().unwrap();
+        gas_coins.iter().any(|id| leased.contains_key(id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sui_types::{base_types::SequenceNumber, digests::ObjectDigest};
+
+    use super::*;
+
+    fn two_accounts() -> Vec<SuiAddress> {
+        vec![
+            SuiAddress::random_for_testing_only(),
+            SuiAddress::random_for_testing_only(),
+        ]
+    }
+
+    #[test]
+    fn test_conflicting_pools_share_an_account() {
+        let accounts = two_accounts();
+        let partitioner =
+            AccountPartitioner::new(accounts.clone(), Duration::from_secs(3)).with_gas_coins_per_account(2);
+        let (a, b, c) = (ObjectID::random(), ObjectID::random(), ObjectID::random());
+        let now = Instant::now();
+        let acquire =
+            |pools: &[ObjectID], now| partitioner.try_acquire_at(&pools.iter().copied().collect(), false, now);
+
+        let first = acquire(&[a, b], now).unwrap();
+        assert_eq!(first.account(), accounts[0]);
+        // independent: the idle account
+        let second = acquire(&[c], now).unwrap();
+        assert_eq!(second.account(), accounts[1]);
+        // shares `b` with the first trade
+        let third = acquire(&[b], now).unwrap();
+        assert_eq!(third.account(), accounts[0]);
+        // the first account has no gas coin left for another trade through `b`
+        assert_eq!(acquire(&[b], now).err(), Some(now + Duration::from_secs(3)));
+
+        // leases expired: back to the least loaded account
+        let later = now + Duration::from_secs(4);
+        assert_eq!(acquire(&[c], later).unwrap().account(), accounts[0]);
+    }
+
+    #[tokio::test]
+    async fn test_trade_over_two_accounts_waits_for_a_release() {
+        let accounts = two_accounts();
+        let partitioner =
+            AccountPartitioner::new(accounts.clone(), Duration::from_secs(3)).with_gas_coins_per_account(2);
+        let (a, b) = (ObjectID::random(), ObjectID::random());
+        let now = Instant::now();
+
+        let on_a = partitioner.try_acquire_at(&[a].into(), false, now).unwrap();
+        let on_b = partitioner.try_acquire_at(&[b].into(), false, now).unwrap();
+        assert_ne!(on_a.account(), on_b.account());
+        // `a` and `b` are leased to different accounts, either would race the other
+        assert_eq!(
+            partitioner.try_acquire_at(&[a, b].into(), false, now).err(),
+            Some(now + Duration::from_secs(3))
+        );
+
+        let waiting = partitioner.acquire([a, b], false);
+        tokio::pin!(waiting);
+        assert!(tokio::time::timeout(Duration::from_millis(50), &mut waiting)
+            .await
+            .is_err());
+        // the trade on `b` settled
+        drop(on_b);
+        let lease = tokio::time::timeout(Duration::from_secs(1), waiting).await.unwrap();
+        assert_eq!(lease.account(), accounts[0]);
+        drop(on_a);
+    }
+
+    #[test]
+    fn test_one_trade_per_gas_coin() {
+        let accounts = two_accounts();
+        let partitioner = AccountPartitioner::new(accounts.clone(), Duration::from_secs(3));
+        let (a, b, c) = (ObjectID::random(), ObjectID::random(), ObjectID::random());
+        let now = Instant::now();
+
+        let first = partitioner.try_acquire_at(&[a].into(), false, now).unwrap();
+        let second = partitioner.try_acquire_at(&[b].into(), false, now).unwrap();
+        assert_ne!(first.account(), second.account());
+        // each account's only gas coin is in flight
+        assert!(partitioner.try_acquire_at(&[c].into(), false, now).is_err());
+        drop(second);
+        assert_eq!(
+            partitioner.try_acquire_at(&[c].into(), false, now).unwrap().account(),
+            accounts[1]
+        );
+    }
+
+    #[test]
+    fn test_primary_only_trades() {
+        let accounts = two_accounts();
+        let partitioner =
+            AccountPartitioner::new(accounts.clone(), Duration::from_secs(3)).with_gas_coins_per_account(2);
+        let (a, b, c) = (ObjectID::random(), ObjectID::random(), ObjectID::random());
+        let now = Instant::now();
+
+        let busy = partitioner.try_acquire_at(&[a].into(), false, now).unwrap();
+        assert_eq!(busy.account(), accounts[0]);
+        // the least busy account would be the other one
+        let primary = partitioner.try_acquire_at(&[b].into(), true, now).unwrap();
+        assert_eq!(primary.account(), accounts[0]);
+
+        // the primary account is full: another trade that needs it waits, the others go elsewhere
+        assert!(partitioner.try_acquire_at(&[c].into(), true, now).is_err());
+        assert_eq!(
+            partitioner.try_acquire_at(&[c].into(), false, now).unwrap().account(),
+            accounts[1]
+        );
+    }
+
+    #[test]
+    fn test_round_robin() {
+        let accounts = two_accounts();
+        let partitioner = AccountPartitioner::new(accounts.clone(), Duration::from_secs(3))
+            .with_selection(AccountSelection::RoundRobin)
+            .with_gas_coins_per_account(3);
+        let (a, b, c) = (ObjectID::random(), ObjectID::random(), ObjectID::random());
+        let now = Instant::now();
+
+        let leases = [[a], [b], [a], [c]].map(|pools| partitioner.try_acquire_at(&pools.into(), false, now).unwrap());
+        let picked = leases.iter().map(AccountLease::account).collect::<Vec<_>>();
+        // conflicts still win over the turn
+        assert_eq!(picked, [accounts[0], accounts[1], accounts[0], accounts[0]]);
+    }
+
+    #[test]
+    fn test_gas_coin_leases() {
+        let partitioner =
+            AccountPartitioner::new(vec![SuiAddress::ZERO], Duration::from_secs(3)).with_gas_coins_per_account(2);
+        let small = (ObjectID::random(), SequenceNumber::new(), ObjectDigest::MIN);
+        let large = (ObjectID::random(), SequenceNumber::new(), ObjectDigest::MIN);
+        let coins = vec![(small, 1), (large, 2)];
+        let now = Instant::now();
+        let first = partitioner.try_acquire_at(&HashSet::new(), false, now).unwrap();
+        let second = partitioner.try_acquire_at(&HashSet::new(), false, now).unwrap();
+
+        assert_eq!(first.lease_gas_coin(coins.clone()), Some(large));
+        // the same trade asking again keeps its coin
+        assert_eq!(first.lease_gas_coin(coins.clone()), Some(large));
+        assert_eq!(second.lease_gas_coin(coins.clone()), Some(small));
+        assert!(partitioner.any_gas_coin_leased(&[small.0]));
+
+        // the first trade settled
+        drop(first);
+        assert!(!partitioner.any_gas_coin_leased(&[large.0]));
+        let third = partitioner.try_acquire_at(&HashSet::new(), false, now).unwrap();
+        assert_eq!(third.lease_gas_coin(coins), Some(large));
+    }
+}
@@ -6,6 +6,7 @@ use std::{
 
 use simulator::SimulateCtx;
 use sui_types::{base_types::ObjectID, digests::TransactionDigest};
+use tokio_util::sync::CancellationToken;
 
 use crate::types::Source;
 
@@ -15,6 +16,8 @@ pub struct ArbItem {
     pub tx_digest: TransactionDigest,
     pub sim_ctx: SimulateCtx,
     pub source: Source,
+    /// Cancelled once the item is superseded by a newer trigger for the same coin, or expires.
+    pub cancel: CancellationToken,
 }
 
 impl ArbItem {
@@ -25,6 +28,7 @@ impl ArbItem {
             tx_digest: entry.digest,
             sim_ctx: entry.sim_ctx,
             source: entry.source,
+            cancel: CancellationToken::new(),
         }
     }
 }
@@ -117,6 +121,11 @@ impl ArbCache {
         });
     }
 
+    /// Number of coins with a queued ArbItem, expired ones included until `remove_expired` runs.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
     /// Attempt to get an ArbItem by coin.
     #[allow(dead_code)]
     pub fn get(&self, coin: &str) -> Option<(TransactionDigest, SimulateCtx)> {
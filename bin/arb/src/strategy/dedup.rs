@@ -0,0 +1,248 @@
+//! Opportunity de-duplication.
+//!
+//! A pool imbalance is often seen through several events in quick succession, and each one finds
+//! the same trade again. An opportunity is fingerprinted by the pools of its path and the direction
+//! each is traded in. A worker must `claim` the fingerprint before pricing the trade for
+//! submission: the claim is refused while another worker holds it or its trade awaits its outcome,
+//! for `SUI_OPPORTUNITY_TTL_MS` after it was last submitted, and while any pool of the path cools
+//! down. A pool cools down for `SUI_POOL_COOLDOWN_MS` after a trade through it reverted or failed to
+//! submit; the strategy doesn't search from a swap on it meanwhile either. Shio bids aren't
+//! de-duplicated, every auction is an opportunity of its own.
+
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    fmt,
+    hash::{Hash, Hasher},
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+use clap::Parser;
+use eyre::Result;
+use sui_types::{base_types::ObjectID, digests::TransactionDigest};
+
+use crate::{common::outcomes::Outcome, defi::Path};
+
+static CONFIG: OnceLock<DedupConfig> = OnceLock::new();
+
+#[derive(Clone, Debug, PartialEq, Parser)]
+pub struct DedupConfig {
+    /// How long, in ms, a submitted opportunity isn't traded again, 0 turns it off
+    #[arg(long, env = "SUI_OPPORTUNITY_TTL_MS", default_value_t = 2_000)]
+    pub opportunity_ttl_ms: u64,
+
+    /// How long, in ms, no trade goes through the pools of a trade that reverted or failed to
+    /// submit, 0 turns it off
+    #[arg(long, env = "SUI_POOL_COOLDOWN_MS", default_value_t = 10_000)]
+    pub pool_cooldown_ms: u64,
+}
+
+impl Default for DedupConfig {
+    fn default() -> Self {
+        Self {
+            opportunity_ttl_ms: 2_000,
+            pool_cooldown_ms: 10_000,
+        }
+    }
+}
+
+/// Install the de-duplication configuration, before the first opportunity is checked.
+pub fn init(config: DedupConfig) -> Result<()> {
+    utils::set_once(&CONFIG, config, "dedup config")
+}
+
+pub fn config() -> &'static DedupConfig {
+    CONFIG.get_or_init(DedupConfig::default)
+}
+
+/// The pools of a path and the coin each is entered with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Fingerprint(u64);
+
+impl Fingerprint {
+    pub fn of(path: &Path) -> Self {
+        let mut hasher = DefaultHasher::new();
+        for dex in &path.path {
+            dex.object_id().hash(&mut hasher);
+            dex.is_a2b().hash(&mut hasher);
+        }
+        Self(hasher.finish())
+    }
+}
+
+/// Why an opportunity isn't traded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Duplicate {
+    InFlight,
+    Recent,
+    CoolingDown(ObjectID),
+}
+
+impl fmt::Display for Duplicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Duplicate::InFlight => write!(f, "the same opportunity is in flight"),
+            Duplicate::Recent => write!(f, "the same opportunity was traded recently"),
+            Duplicate::CoolingDown(pool) => write!(f, "pool {} is cooling down after a failed trade", pool),
+        }
+    }
+}
+
+#[derive(Default)]
+struct State {
+    // opportunity => workers pricing it plus its trades awaiting their outcome
+    in_flight: HashMap<Fingerprint, usize>,
+    // opportunity => when it was last submitted
+    recent: HashMap<Fingerprint, Instant>,
+    // submitted trades => their opportunity and pools
+    pending: HashMap<TransactionDigest, (Fingerprint, Vec<ObjectID>)>,
+    // pool => until when it cools down
+    cooldowns: HashMap<ObjectID, Instant>,
+}
+
+impl State {
+    fn claim(
+        &mut self,
+        fingerprint: Fingerprint,
+        pools: &[ObjectID],
+        ttl: Duration,
+        now: Instant,
+    ) -> Result<(), Duplicate> {
+        self.recent.retain(|_, at| now - *at < ttl);
+        self.cooldowns.retain(|_, until| *until > now);
+
+        if self.in_flight.get(&fingerprint).is_some_and(|&holders| holders > 0) {
+            return Err(Duplicate::InFlight);
+        }
+        if self.recent.contains_key(&fingerprint) {
+            return Err(Duplicate::Recent);
+        }
+        if let Some(pool) = pools.iter().find(|pool| self.cooldowns.contains_key(pool)) {
+            return Err(Duplicate::CoolingDown(*pool));
+        }
+        *self.in_flight.entry(fingerprint).or_default() += 1;
+        Ok(())
+    }
+
+    fn submitted(&mut self, digest: TransactionDigest, fingerprint: Fingerprint, pools: Vec<ObjectID>, now: Instant) {
+        *self.in_flight.entry(fingerprint).or_default() += 1;
+        self.recent.insert(fingerprint, now);
+        self.pending.insert(digest, (fingerprint, pools));
+    }
+
+    fn release(&mut self, fingerprint: Fingerprint) {
+        if let Some(holders) = self.in_flight.get_mut(&fingerprint) {
+            *holders = holders.saturating_sub(1);
+            if *holders == 0 {
+                self.in_flight.remove(&fingerprint);
+            }
+        }
+    }
+
+    fn settled(&mut self, digest: &TransactionDigest, outcome: Outcome, cooldown: Duration, now: Instant) {
+        let Some((fingerprint, pools)) = self.pending.remove(digest) else {
+            return;
+        };
+        self.release(fingerprint);
+        if matches!(outcome, Outcome::Reverted | Outcome::SubmissionError) && !cooldown.is_zero() {
+            for pool in pools {
+                self.cooldowns.insert(pool, now + cooldown);
+            }
+        }
+    }
+
+    fn is_cooling_down(&self, pool: &ObjectID, now: Instant) -> bool {
+        self.cooldowns.get(pool).is_some_and(|until| *until > now)
+    }
+}
+
+static STATE: OnceLock<Mutex<State>> = OnceLock::new();
+
+fn state() -> &'static Mutex<State> {
+    STATE.get_or_init(Default::default)
+}
+
+/// A worker's hold on an opportunity, released when dropped.
+pub struct Ticket {
+    fingerprint: Fingerprint,
+    pools: Vec<ObjectID>,
+}
+
+impl Ticket {
+    /// The opportunity was submitted as `digest`; it stays in flight until the executor settles it.
+    pub fn submitted(&self, digest: TransactionDigest) {
+        state()
+            .lock()
+            .unwrap()
+            .submitted(digest, self.fingerprint, self.pools.clone(), Instant::now());
+    }
+}
+
+impl Drop for Ticket {
+    fn drop(&mut self) {
+        state().lock().unwrap().release(self.fingerprint);
+    }
+}
+
+/// Hold the opportunity trading `path`, unless it is a duplicate.
+pub fn claim(path: &Path) -> Result<Ticket, Duplicate> {
+    let fingerprint = Fingerprint::of(path);
+    let pools: Vec<ObjectID> = path.path.iter().map(|dex| dex.object_id()).collect();
+    let ttl = Duration::from_millis(config().opportunity_ttl_ms);
+    state()
+        .lock()
+        .unwrap()
+        .claim(fingerprint, &pools, ttl, Instant::now())?;
+    Ok(Ticket { fingerprint, pools })
+}
+
+/// The trade submitted as `digest` ended in `outcome`.
+pub fn settled(digest: &TransactionDigest, outcome: Outcome) {
+    let cooldown = Duration::from_millis(config().pool_cooldown_ms);
+    state()
+        .lock()
+        .unwrap()
+        .settled(digest, outcome, cooldown, Instant::now());
+}
+
+/// Whether `pool` cools down after a failed trade.
+pub fn is_cooling_down(pool: ObjectID) -> bool {
+    state().lock().unwrap().is_cooling_down(&pool, Instant::now())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_duplicates_and_cooldowns() {
+        let (ttl, cooldown) = (Duration::from_secs(2), Duration::from_secs(10));
+        let (a, b, c) = (ObjectID::random(), ObjectID::random(), ObjectID::random());
+        let (ab, bc) = (Fingerprint(1), Fingerprint(2));
+        let mut state = State::default();
+        let now = Instant::now();
+
+        state.claim(ab, &[a, b], ttl, now).unwrap();
+        // a second worker found the same trade
+        assert_eq!(state.claim(ab, &[a, b], ttl, now), Err(Duplicate::InFlight));
+        let digest = TransactionDigest::random();
+        state.submitted(digest, ab, vec![a, b], now);
+        // the worker let go, the trade still awaits its outcome
+        state.release(ab);
+        assert_eq!(state.claim(ab, &[a, b], ttl, now), Err(Duplicate::InFlight));
+
+        state.settled(&digest, Outcome::LandedProfitable, cooldown, now);
+        assert_eq!(state.claim(ab, &[a, b], ttl, now), Err(Duplicate::Recent));
+        state.claim(ab, &[a, b], ttl, now + ttl).unwrap();
+        let digest = TransactionDigest::random();
+        state.submitted(digest, ab, vec![a, b], now + ttl);
+        state.release(ab);
+
+        // a revert cools its pools down, other opportunities through them included
+        let later = now + ttl * 2;
+        state.settled(&digest, Outcome::Reverted, cooldown, later);
+        assert!(state.is_cooling_down(&b, later));
+        assert_eq!(state.claim(bc, &[b, c], ttl, later), Err(Duplicate::CoolingDown(b)));
+        state.claim(bc, &[b, c], ttl, later + cooldown).unwrap();
+    }
+}
@@ -1,28 +1,33 @@
+pub mod accounts;
 mod arb_cache;
-pub mod graph_path_finder;
+pub mod dedup;
 mod worker;
 
 use std::{
-    collections::{HashSet, VecDeque},
+    collections::{HashMap, HashSet, VecDeque},
     str::FromStr,
     sync::Arc,
     time::Duration,
 };
 
+use accounts::AccountPartitioner;
 use arb_cache::{ArbCache, ArbItem};
 use async_channel::Sender;
 use burberry::ActionSubmitter;
-use dex_indexer::types::Protocol;
+use dex_indexer::types::{Pool, Protocol, SwapEvent};
 use eyre::{ensure, eyre, Result};
 use fastcrypto::encoding::{Base64, Encoding};
 use object_pool::ObjectPool;
+pub use poison_dart_core::strategy::{routes, sizing};
 use rayon::prelude::*;
 use shio::{ShioItem, ShioObject};
-use simulator::{ReplaySimulator, SimEpoch, SimulateCtx, Simulator};
-use sui_json_rpc_types::{SuiEvent, SuiTransactionBlockEffects, SuiTransactionBlockEffectsAPI};
-use sui_sdk::{SuiClient, SuiClientBuilder};
+use simulator::{ReplaySimulator, SimulateCtx, Simulator};
+use sui_json_rpc_types::{
+    SuiEvent, SuiTransactionBlockDataAPI, SuiTransactionBlockEffects, SuiTransactionBlockEffectsAPI,
+    SuiTransactionBlockResponseOptions,
+};
 use sui_types::{
-    base_types::{MoveObjectType, ObjectID, SuiAddress},
+    base_types::{MoveObjectType, ObjectID, SequenceNumber, SuiAddress},
     committee::ProtocolVersion,
     digests::TransactionDigest,
     object::{MoveObject, Object, Owner, OBJECT_START_VERSION},
@@ -33,12 +38,21 @@ use tokio::{
     runtime::{Builder, Handle, RuntimeFlavor},
     task::JoinSet,
 };
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, error, info, instrument, warn};
 use worker::Worker;
 
 use crate::{
     arb::Arb,
-    common::get_latest_epoch,
+    common::{
+        competition, endpoints::RpcEndpoints, gas::GasOracle, metrics, notification::new_pool_alert_message, risk,
+        shutdown, token_meta::TokenMetadata,
+    },
+    defi::{base, flash_lender::FlashLoanConfig, pool_state},
+    graph_path_finder::{self, GraphSettings},
+    journal::TradeJournal,
+    replay::ReplayRecorder,
+    server,
     types::{Action, Event, Source},
 };
 
@@ -46,20 +60,30 @@ pub struct ArbStrategy {
     sender: SuiAddress,
     arb_item_sender: Option<Sender<ArbItem>>,
     arb_cache: ArbCache,
+    // coin => token of the item a worker is currently processing
+    in_flight: HashMap<String, CancellationToken>,
 
     recent_arbs: VecDeque<String>,
     max_recent_arbs: usize,
 
     simulator_pool: Arc<ObjectPool<Box<dyn Simulator>>>,
-    own_simulator: Arc<dyn Simulator>, // only for execution of pending txs
+    own_simulator: Arc<dyn Simulator>, // only for execution of pending txs, and told of object changes
     rpc_url: String,
     workers: usize,
-    sui: SuiClient,
-    epoch: Option<SimEpoch>,
+    gas: Arc<GasOracle>,
+    endpoints: Arc<RpcEndpoints>,
     dedicated_simulator: Option<Arc<ReplaySimulator>>,
+    replay_recorder: Option<Arc<ReplayRecorder>>,
+    journal: Option<Arc<TradeJournal>>,
+    accounts: Arc<AccountPartitioner>,
+    tokens: Arc<TokenMetadata>,
+    // shared with every worker's path search, token lists reloads included
+    graph_settings: GraphSettings,
+    flashloan_config: FlashLoanConfig,
 }
 
 impl ArbStrategy {
+    #[allow(clippy::too_many_arguments)]
     pub async fn new(
         attacker: SuiAddress,
         simulator_pool: Arc<ObjectPool<Box<dyn Simulator>>>,
@@ -68,23 +92,35 @@ impl ArbStrategy {
         rpc_url: &str,
         workers: usize,
         dedicated_simulator: Option<Arc<ReplaySimulator>>,
+        replay_recorder: Option<Arc<ReplayRecorder>>,
+        journal: Option<Arc<TradeJournal>>,
+        accounts: Arc<AccountPartitioner>,
+        gas: Arc<GasOracle>,
+        endpoints: Arc<RpcEndpoints>,
+        tokens: Arc<TokenMetadata>,
+        graph_settings: GraphSettings,
+        flashloan_config: FlashLoanConfig,
     ) -> Self {
-        let sui = SuiClientBuilder::default().build(&rpc_url).await.unwrap();
-        let epoch = get_latest_epoch(&sui).await.unwrap();
-
         Self {
             sender: attacker,
             arb_item_sender: None,
             arb_cache: ArbCache::new(Duration::from_secs(5)),
+            in_flight: HashMap::new(),
             recent_arbs: VecDeque::with_capacity(recent_arbs),
             max_recent_arbs: recent_arbs,
             simulator_pool,
             own_simulator,
             rpc_url: rpc_url.to_string(),
             workers,
-            sui,
-            epoch: Some(epoch),
+            gas,
+            endpoints,
             dedicated_simulator,
+            replay_recorder,
+            journal,
+            accounts,
+            tokens,
+            graph_settings,
+            flashloan_config,
         }
     }
 
@@ -98,16 +134,41 @@ impl ArbStrategy {
 
     #[instrument(name = "on-new-tx-effects", skip_all, fields(tx = %tx_effects.transaction_digest()))]
     async fn on_new_tx_effects(&mut self, tx_effects: SuiTransactionBlockEffects, events: Vec<SuiEvent>) -> Result<()> {
-        let coin_pools = self.parse_involved_coin_pools(events).await;
+        for obj in tx_effects.mutated() {
+            pool_state::observe_version(obj.object_id(), obj.version());
+        }
+        graph_path_finder::observe_mutated(tx_effects.mutated().iter().map(|obj| obj.object_id()));
+        let changed: Vec<(ObjectID, SequenceNumber)> = tx_effects
+            .mutated()
+            .iter()
+            .chain(tx_effects.created())
+            .chain(tx_effects.unwrapped())
+            .map(|obj| (obj.object_id(), obj.version()))
+            .chain(
+                tx_effects
+                    .deleted()
+                    .iter()
+                    .chain(tx_effects.wrapped())
+                    .map(|obj| (obj.object_id, obj.version)),
+            )
+            .collect();
+        self.own_simulator.observe_versions(&changed);
+
+        let swap_events = self.parse_swap_events(events).await;
+        if competition::is_arb(&swap_events) {
+            self.observe_rival_arb(&tx_effects, &swap_events);
+        }
+        let coin_pools = self.vet_swap_events(swap_events).await;
         if coin_pools.is_empty() {
             return Ok(());
         }
 
         let tx_digest = tx_effects.transaction_digest();
-        let epoch = self.get_latest_epoch().await?;
+        let epoch = self.gas.epoch().await?;
         let sim_ctx = SimulateCtx::new(epoch, vec![]);
 
         for (coin, pool_id) in coin_pools {
+            self.supersede(&coin);
             self.arb_cache
                 .insert(coin, pool_id, *tx_digest, sim_ctx.clone(), Source::Public);
         }
@@ -123,10 +184,11 @@ impl ArbStrategy {
         };
 
         let tx_digest = TransactionDigest::from_str(shio_item.tx_digest()).map_err(|e| eyre!(e))?;
-        let epoch = self.get_latest_epoch().await?;
+        let epoch = self.gas.epoch().await?;
         let mut sim_ctx = SimulateCtx::new(epoch, override_objects);
         // A bid must has the exact gas_price as the opportunity transaction's.
         sim_ctx.with_gas_price(shio_item.gas_price());
+        self.gas.observe_gas_price(shio_item.gas_price());
 
         let source = Source::Shio {
             opp_tx_digest: tx_digest,
@@ -138,13 +200,70 @@ impl ArbStrategy {
         };
 
         for (coin, pool_id) in coin_pools {
+            self.supersede(&coin);
             self.arb_cache.insert(coin, pool_id, tx_digest, sim_ctx.clone(), source);
         }
 
         Ok(())
     }
 
-    async fn parse_involved_coin_pools(&self, events: Vec<SuiEvent>) -> HashSet<(String, Option<ObjectID>)> {
+    /// Alert on a new pool for a watched coin and look for arbitrage on each of its coins right away,
+    /// since a fresh pool is often mispriced against existing venues.
+    #[instrument(name = "on-new-pool", skip_all, fields(pool = %pool.pool))]
+    async fn on_new_pool(&mut self, pool: Pool, submitter: &Arc<dyn ActionSubmitter<Action>>) -> Result<()> {
+        info!(protocol = %pool.protocol, tokens = ?pool.tokens, "new pool for a watched coin");
+        submitter.submit(new_pool_alert_message(&pool).into());
+
+        let epoch = self.gas.epoch().await?;
+        let sim_ctx = SimulateCtx::new(epoch, vec![]);
+        for token in &pool.tokens {
+            if base::is_base_coin(&token.token_type) {
+                continue;
+            }
+            let coin = token.token_type.clone();
+            self.supersede(&coin);
+            // skip the recent_arbs dedup below, a new venue changes the picture
+            if let Some(pos) = self.recent_arbs.iter().position(|x| x == &coin) {
+                self.recent_arbs.remove(pos);
+            }
+            self.arb_cache.insert(
+                coin,
+                Some(pool.pool),
+                TransactionDigest::ZERO,
+                sim_ctx.clone(),
+                Source::Public,
+            );
+        }
+
+        Ok(())
+    }
+
+    // Drop every queued and in-flight opportunity, they won't be traded for `reason`.
+    fn pause(&mut self, reason: &str) {
+        let mut dropped = 0;
+        while self.arb_cache.pop_one().is_some() {
+            dropped += 1;
+        }
+        for (_, cancel) in self.in_flight.drain() {
+            cancel.cancel();
+            dropped += 1;
+        }
+        if dropped > 0 {
+            warn!(dropped, reason, "dropped pending opportunities");
+        }
+    }
+
+    // A newer trigger for `coin` makes the search a worker is running for it pointless.
+    fn supersede(&mut self, coin: &str) {
+        if let Some(cancel) = self.in_flight.remove(coin) {
+            if !cancel.is_cancelled() {
+                debug!(%coin, "superseded by a newer trigger, cancelling in-flight search");
+                cancel.cancel();
+            }
+        }
+    }
+
+    async fn parse_swap_events(&self, events: Vec<SuiEvent>) -> Vec<SwapEvent> {
         let mut join_set = JoinSet::new();
 
         for event in events {
@@ -152,20 +271,74 @@ impl ArbStrategy {
             join_set.spawn(async move {
                 if let Ok(protocol) = Protocol::try_from(&event) {
                     if let Ok(swap_event) = protocol.sui_event_to_swap_event(&event, own_simulator).await {
-                        return Some((swap_event.involved_coin_one_side(), swap_event.pool_id()));
+                        return Some(swap_event);
                     }
                 }
                 None
             });
         }
 
-        let mut coin_pools = HashSet::new();
+        let mut swap_events = vec![];
         while let Some(result) = join_set.join_next().await {
-            if let Ok(Some((coin, pool_id))) = result {
-                coin_pools.insert((coin, pool_id));
+            if let Ok(Some(swap_event)) = result {
+                swap_events.push(swap_event);
             }
         }
+        swap_events
+    }
+
+    /// Report an arb another bot landed, then the gas price it paid once the transaction is read.
+    fn observe_rival_arb(&self, tx_effects: &SuiTransactionBlockEffects, swap_events: &[SwapEvent]) {
+        let Ok(rival) = tx_effects.gas_object().owner.get_owner_address() else {
+            return;
+        };
+        if self.accounts.contains(&rival) {
+            return;
+        }
+        let pools: Vec<ObjectID> = swap_events.iter().filter_map(|swap| swap.pool_id()).collect();
+        debug!(%rival, ?pools, "rival arb landed");
+        competition::observe_arb(rival, pools);
+
+        let sui = self.endpoints.background();
+        let digest = *tx_effects.transaction_digest();
+        tokio::spawn(async move {
+            let options = SuiTransactionBlockResponseOptions::new().with_input();
+            match sui.read_api().get_transaction_with_options(digest, options).await {
+                Ok(resp) => {
+                    if let Some(tx) = resp.transaction {
+                        competition::observe_gas_price(rival, tx.data.gas_data().price);
+                    }
+                }
+                Err(error) => debug!(%digest, ?error, "failed to read a rival arb"),
+            }
+        });
+    }
 
+    /// The (coin, pool) each swap triggers a search from, without the suspicious coins.
+    async fn vet_swap_events(&self, swap_events: Vec<SwapEvent>) -> HashSet<(String, Option<ObjectID>)> {
+        let mut coin_pools = HashSet::new();
+        for swap_event in swap_events {
+            let coin = swap_event.involved_coin_one_side();
+            if self.tokens.should_skip(&coin).await {
+                debug!(%coin, pool = ?swap_event.pool_id(), "skipping swap in a suspicious coin");
+                continue;
+            }
+            if let (Some(coin_in), Some(coin_out), Some(&amount_in), Some(&amount_out)) = (
+                swap_event.coins_in.first(),
+                swap_event.coins_out.first(),
+                swap_event.amounts_in.first(),
+                swap_event.amounts_out.first(),
+            ) {
+                debug!(
+                    protocol = ?swap_event.protocol,
+                    pool = ?swap_event.pool_id(),
+                    "swap {} for {}",
+                    self.tokens.format_amount(coin_in, amount_in).await,
+                    self.tokens.format_amount(coin_out, amount_out).await,
+                );
+            }
+            coin_pools.insert((coin, swap_event.pool_id()));
+        }
         coin_pools
     }
 
@@ -186,20 +359,21 @@ impl ArbStrategy {
             join_set.spawn(async move {
                 if let Ok(protocol) = Protocol::try_from(&event) {
                     if let Ok(swap_event) = protocol.shio_event_to_swap_event(&event, own_simulator).await {
-                        return Some((swap_event.involved_coin_one_side(), swap_event.pool_id()));
+                        return Some(swap_event);
                     }
                 }
                 None
             });
         }
 
-        let mut involved_coin_pools = HashSet::new();
+        let mut swap_events = vec![];
         while let Some(result) = join_set.join_next().await {
-            if let Ok(Some((coin, pool_id))) = result {
-                involved_coin_pools.insert((coin, pool_id));
+            if let Ok(Some(swap_event)) = result {
+                swap_events.push(swap_event);
             }
         }
 
+        let involved_coin_pools = self.vet_swap_events(swap_events).await;
         if involved_coin_pools.is_empty() {
             return None;
         }
@@ -214,20 +388,6 @@ impl ArbStrategy {
 
         Some((involved_coin_pools, override_objects))
     }
-
-    async fn get_latest_epoch(&mut self) -> Result<SimEpoch> {
-        if let Some(epoch) = self.epoch {
-            if !epoch.is_stale() {
-                return Ok(epoch);
-            } else {
-                self.epoch = None;
-            }
-        }
-
-        let epoch = get_latest_epoch(&self.sui).await?;
-        self.epoch = Some(epoch);
-        Ok(epoch)
-    }
 }
 
 fn new_object_read_result(tx_digest: TransactionDigest, shio_obj: &ShioObject) -> Result<ObjectReadResult> {
@@ -319,19 +479,28 @@ impl burberry::Strategy<Event, Action> for ArbStrategy {
             let arb_item_receiver = arb_item_receiver.clone();
             let submitter = submitter.clone();
 
-            let sui = SuiClientBuilder::default().build(&rpc_url).await?;
             let rpc_url = rpc_url.clone();
             let init_tx = init_tx.clone();
             let simulator_pool_arb = self.simulator_pool.clone();
             let simulator_pool_worker = self.simulator_pool.clone();
             let simulator_name = simulator_pool_arb.get().name().to_string();
             let dedicated_simulator = self.dedicated_simulator.clone();
+            let replay_recorder = self.replay_recorder.clone();
+            let journal = self.journal.clone();
+            let accounts = self.accounts.clone();
+            let gas = self.gas.clone();
+            let endpoints = self.endpoints.clone();
+            let graph_settings = self.graph_settings.clone();
+            let flashloan_config = self.flashloan_config.clone();
 
             let _ = std::thread::Builder::new()
                 .stack_size(128 * 1024 * 1024) // 128 MB
                 .name(format!("worker-{id}"))
                 .spawn(move || {
-                    let arb = Arc::new(run_in_tokio!({ Arb::new(&rpc_url, simulator_pool_arb) }).unwrap());
+                    let arb = Arc::new(
+                        run_in_tokio!({ Arb::new(&rpc_url, simulator_pool_arb, graph_settings, &flashloan_config) })
+                            .unwrap(),
+                    );
 
                     // Signal that this worker is initialized
                     run_in_tokio!(init_tx.send(())).unwrap();
@@ -343,9 +512,13 @@ impl burberry::Strategy<Event, Action> for ArbStrategy {
                         simulator_pool: simulator_pool_worker,
                         simulator_name,
                         submitter,
-                        sui,
+                        endpoints,
                         arb,
                         dedicated_simulator,
+                        replay_recorder,
+                        journal,
+                        accounts,
+                        gas,
                     };
                     worker.run().unwrap_or_else(|e| panic!("worker {id} panicked: {e:?}"));
                 });
@@ -360,25 +533,60 @@ impl burberry::Strategy<Event, Action> for ArbStrategy {
         Ok(())
     }
 
-    async fn process_event(&mut self, event: Event, _submitter: Arc<dyn ActionSubmitter<Action>>) {
+    async fn process_event(&mut self, event: Event, submitter: Arc<dyn ActionSubmitter<Action>>) {
+        // nothing new starts once shutting down; items already with a worker run to completion
+        if shutdown::is_requested() {
+            let mut dropped = 0;
+            while self.arb_cache.pop_one().is_some() {
+                dropped += 1;
+            }
+            if dropped > 0 {
+                info!(dropped, "shutting down, dropped pending opportunities");
+            }
+            return;
+        }
+        metrics::event(event.kind());
+        server::event_received();
         let result = match event {
             Event::PublicTx(tx_effects, events) => self.on_new_tx_effects(tx_effects, events).await,
             Event::PrivateTx(tx_data) => self.on_new_tx(tx_data).await,
             Event::Shio(shio_item) => self.on_new_shio_item(shio_item).await,
+            Event::NewPool(pool) => self.on_new_pool(pool, &submitter).await,
         };
         if let Err(error) = result {
             error!(?error, "failed to process event");
             return;
         }
 
+        // quotes on a lagging node are stale, drop them until it catches up
+        if self.endpoints.is_stale() {
+            self.pause("node data is stale");
+            server::set_pending(0, 0);
+            return;
+        }
+        if server::is_paused() {
+            self.pause("trading paused through the admin API");
+            server::set_pending(0, 0);
+            return;
+        }
+        if risk::is_halted() {
+            self.pause("a risk limit tripped the circuit breaker");
+            server::set_pending(0, 0);
+            return;
+        }
+
         // send arb_item to workers if channel is < 10
         let channel_len = self.arb_item_sender.as_ref().unwrap().len();
         if channel_len < 10 {
             let num_to_send = 10 - channel_len;
             for _ in 0..num_to_send {
                 if let Some(item) = self.arb_cache.pop_one() {
-                    if !self.recent_arbs.contains(&item.coin) || item.source.is_shio() {
+                    // a pool cooling down after a failed trade isn't searched from
+                    let skip = !item.source.is_shio() &&
+                        (self.recent_arbs.contains(&item.coin) || item.pool_id.is_some_and(dedup::is_cooling_down));
+                    if !skip {
                         let coin = item.coin.clone();
+                        self.in_flight.insert(coin.clone(), item.cancel.clone());
                         self.arb_item_sender.as_ref().unwrap().send(item).await.unwrap();
 
                         self.recent_arbs.push_back(coin);
@@ -395,6 +603,10 @@ impl burberry::Strategy<Event, Action> for ArbStrategy {
             warn!("arb_item channel stash {}", channel_len);
         }
 
+        // workers cancel their token once they are done with an item
+        self.in_flight.retain(|_, cancel| !cancel.is_cancelled());
+        server::set_pending(self.arb_cache.len(), self.in_flight.len());
+
         let expired_coins = self.arb_cache.remove_expired();
         for coin in expired_coins {
             if let Some(pos) = self.recent_arbs.iter().position(|x| x == &coin) {
@@ -1,29 +1,63 @@
 use std::{
+    collections::{HashMap, HashSet},
     sync::Arc,
     time::{Duration, Instant},
 };
 
 use burberry::ActionSubmitter;
+use dex_indexer::types::Protocol;
 use eyre::{bail, ensure, Context, OptionExt, Result};
+use futures::future::join_all;
 use object_pool::ObjectPool;
 use simulator::{ReplaySimulator, SimulateCtx, Simulator};
 use sui_json_rpc_types::SuiTransactionBlockEffectsAPI;
-use sui_sdk::SuiClient;
 use sui_types::{
-    base_types::{ObjectID, SuiAddress},
+    base_types::{ObjectID, SequenceNumber, SuiAddress},
+    digests::TransactionDigest,
     object::Owner,
-    transaction::{GasData, TransactionData, TransactionDataAPI},
+    transaction::{GasData, ObjectReadResult, TransactionData, TransactionDataAPI},
 };
-use tracing::{error, info, instrument};
-use utils::coin;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, instrument, warn};
 
 use crate::{
-    arb::{Arb, ArbResult},
-    common::notification::new_tg_messages,
+    arb::{Arb, ArbResult, PinLog, Sizing},
+    common::{
+        cancel::{cancel_at, until_cancelled},
+        competition::{self, Assessment},
+        contention,
+        endpoints::RpcEndpoints,
+        experiment::{self, Claim},
+        gas::GasOracle,
+        metrics,
+        notification::new_tg_messages,
+        object_refs::refresh_object_refs,
+        outcomes::{self, Outcome, TradeKey},
+        risk::{self, Exposure},
+        shutdown,
+    },
+    defi::{base, pool_state, Dex, Path},
+    journal::{DetectedOpportunity, JournalEntry, SimulatedTrade, SubmittedTrade, TradeJournal},
+    replay::{Decision, ReplayRecorder, Trigger},
     types::{Action, Source},
 };
 
-use super::arb_cache::ArbItem;
+use super::{
+    accounts::{AccountLease, AccountPartitioner},
+    arb_cache::ArbItem,
+    dedup::{self, Ticket},
+    routes,
+};
+
+// When a path object moved between the profitable simulation and submission, re-check the trade
+// on the latest state (false: always abort).
+const RESIMULATE_ON_DRIFT: bool = true;
+// Share of the simulated profit the re-check must still deliver after a drift.
+const MIN_PROFIT_RATIO_AFTER_DRIFT: f64 = 0.8;
+// A contended path is traded in this many sequential parts.
+const SPLIT_PARTS: u64 = 3;
+// How long a part may take to land before the rest are given up.
+const PART_TIMEOUT: Duration = Duration::from_secs(10);
 
 pub struct Worker {
     pub _id: usize,
@@ -37,8 +71,13 @@ pub struct Worker {
     pub dedicated_simulator: Option<Arc<ReplaySimulator>>,
 
     pub submitter: Arc<dyn ActionSubmitter<Action>>,
-    pub sui: SuiClient,
+    pub endpoints: Arc<RpcEndpoints>,
     pub arb: Arc<Arb>,
+
+    pub replay_recorder: Option<Arc<ReplayRecorder>>,
+    pub journal: Option<Arc<TradeJournal>>,
+    pub accounts: Arc<AccountPartitioner>,
+    pub gas: Arc<GasOracle>,
 }
 
 impl Worker {
@@ -46,6 +85,9 @@ impl Worker {
     pub async fn run(mut self) -> Result<()> {
         loop {
             tokio::select! {
+                biased;
+                // items still queued are dropped, the one being handled finishes first
+                _ = shutdown::requested() => return Ok(()),
                 arb_item = self.arb_item_receiver.recv() => {
                     if let Err(error) = self.handle_arb_item(arb_item.context("arb_item channel error")?).await {
                         error!(?error, "Handle arb_item failed");
@@ -64,97 +106,521 @@ impl Worker {
             tx_digest,
             sim_ctx,
             source,
+            cancel,
         } = arb_item;
 
-        if let Some((arb_result, elapsed)) = arbitrage_one_coin(
+        // cancelling on the way out tells the strategy this item is no longer in flight
+        let _done = cancel.clone().drop_guard();
+        let _in_flight = shutdown::in_flight();
+        // a shio opportunity is worthless past its deadline
+        let expiry = source.deadline().map(|deadline| cancel_at(&cancel, deadline));
+        let result = self
+            .process_arb_item(coin, pool_id, tx_digest, sim_ctx, source, &cancel)
+            .await;
+        if let Some(expiry) = expiry {
+            expiry.abort();
+        }
+
+        result
+    }
+
+    async fn process_arb_item(
+        &mut self,
+        coin: String,
+        pool_id: Option<ObjectID>,
+        tx_digest: TransactionDigest,
+        sim_ctx: SimulateCtx,
+        source: Source,
+        cancel: &CancellationToken,
+    ) -> Result<()> {
+        // shio bids keep the main config, their PnL can't be settled
+        let claim = if source.is_shio() { None } else { experiment::claim() };
+        let sizing = claim.as_ref().map_or(Sizing::Local, |claim| claim.params.sizing);
+
+        let trigger = self
+            .replay_recorder
+            .as_ref()
+            .map(|_| Trigger::new(&coin, pool_id, tx_digest, &sim_ctx, source, sizing));
+        let pins = trigger.as_ref().map(|_| PinLog::default());
+
+        let result = arbitrage_one_coin(
             self.arb.clone(),
             self.sender,
             &coin,
             pool_id,
             sim_ctx.clone(),
-            false,
+            sizing,
             source,
+            cancel,
+            pins.as_ref(),
         )
-        .await
-        {
-            let tx_data = match self.dry_run_tx_data(arb_result.tx_data.clone(), sim_ctx.clone()).await {
+        .await;
+
+        // a cancelled search says nothing about the opportunity
+        let recorder = self.replay_recorder.as_ref().filter(|_| !cancel.is_cancelled());
+        if let (Some(recorder), Some(trigger)) = (recorder, trigger) {
+            let decision = match &result {
+                Ok((arb_result, _)) => Decision::Opportunity {
+                    amount_in: arb_result.best_trial_result.amount_in,
+                    profit: arb_result.best_trial_result.profit,
+                    path: arb_result
+                        .best_trial_result
+                        .trade_path
+                        .path
+                        .iter()
+                        .map(|dex| dex.object_id())
+                        .collect(),
+                    arb_tx_digest: arb_result.tx_data.digest(),
+                },
+                Err(error) => Decision::NoOpportunity {
+                    reason: format!("{error:#}"),
+                },
+            };
+            let pinned = pins.map(|pins| pins.take()).unwrap_or_default();
+            recorder.record(trigger.with_pinned(&pinned), decision);
+        }
+
+        if let Ok((arb_result, elapsed)) = result {
+            metrics::opportunity(outcomes::strategy_name(&arb_result.source));
+            if let Some(journal) = &self.journal {
+                journal.append(JournalEntry::Detected(DetectedOpportunity::new(tx_digest, &arb_result)));
+            }
+            // routes that share no object go out as transactions of their own when the accounts
+            // can run them side by side
+            let split = if arb_result.extra_routes.is_empty() || !self.accounts.is_parallel() {
+                None
+            } else {
+                match self.arb.split_routes(&arb_result).await {
+                    Ok(split) => {
+                        let txs = split.iter().map(|route| route.tx_data.clone()).collect::<Vec<_>>();
+                        routes::independent(&txs).then_some(split)
+                    }
+                    Err(error) => {
+                        warn!(?error, "Splitting routes failed, trading them in one transaction");
+                        None
+                    }
+                }
+            };
+            if let Some(split) = split {
+                info!(
+                    routes = split.len(),
+                    "Routes share no objects, trading them concurrently"
+                );
+                // the experiment settles on the first route
+                let mut claim = claim;
+                join_all(
+                    split
+                        .iter()
+                        .map(|route| self.execute(tx_digest, route, elapsed, sim_ctx.clone(), claim.take(), cancel)),
+                )
+                .await;
+            } else {
+                self.execute(tx_digest, &arb_result, elapsed, sim_ctx, claim, cancel)
+                    .await;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Dry run, price and submit the trade of `arb_result`.
+    async fn execute(
+        &self,
+        tx_digest: TransactionDigest,
+        arb_result: &ArbResult,
+        elapsed: Duration,
+        sim_ctx: SimulateCtx,
+        claim: Option<Claim<'static>>,
+        cancel: &CancellationToken,
+    ) {
+        // another event found the same trade, or its pools just failed us
+        let ticket = if arb_result.source.is_shio() {
+            None
+        } else {
+            match dedup::claim(&arb_result.best_trial_result.trade_path) {
+                Ok(ticket) => Some(ticket),
+                Err(duplicate) => {
+                    info!(%duplicate, "Duplicate opportunity, skip");
+                    return;
+                }
+            }
+        };
+        let drifted = self.drifted_objects(&arb_result.state_versions).await;
+        if !drifted.is_empty() && !RESIMULATE_ON_DRIFT {
+            warn!(?drifted, "State drifted since simulation, abort");
+            outcomes::record(&TradeKey::of(arb_result), Outcome::Stale);
+            return;
+        }
+        // a rival that keeps beating us to one of these pools will take this one too
+        let assessment = competition::assess(arb_result.object_ids());
+        if let Assessment::Lost { rival, pool, losses } = &assessment {
+            info!(%rival, %pool, losses, "A rival keeps winning this path, skip");
+            return;
+        }
+
+        // shio bids stay on the account the shio executor signs with, and DeepBook v2 swaps on the
+        // account that owns the account cap: the primary one
+        let primary_only =
+            arb_result.source.is_shio() || arb_result.routes().any(|route| trades_deepbook_v2(&route.trade_path));
+        let acquire = async { Ok(self.accounts.acquire(arb_result.object_ids(), primary_only).await) };
+        let Ok(lease) = until_cancelled(cancel, acquire).await else {
+            warn!(?arb_result, "Opportunity expired waiting for an account, abort");
+            return;
+        };
+        let mut tx_data = arb_result.tx_data.clone();
+        let account = lease.account();
+        if account != tx_data.sender() {
+            tx_data = match self.arb.rebuild_for_sender(account, arb_result).await {
                 Ok(tx_data) => tx_data,
                 Err(error) => {
-                    error!(?arb_result, ?error, "Dry run final tx_data failed");
-                    return Ok(());
+                    error!(?arb_result, %account, ?error, "Rebuild tx_data for account failed");
+                    return;
                 }
             };
+        }
+
+        let dry_run = until_cancelled(cancel, self.dry_run_tx_data(tx_data, &lease, sim_ctx.clone())).await;
+        if let Some(journal) = &self.journal {
+            journal.append(JournalEntry::Simulated(SimulatedTrade::new(arb_result, &dry_run)));
+        }
+        let (mut tx_data, dry_run_profit, gas_used) = match dry_run {
+            Ok(res) => res,
+            Err(error) => {
+                error!(?arb_result, ?error, "Dry run final tx_data failed");
+                return;
+            }
+        };
+
+        // the final dry run already sees the latest state; after a drift it must still be
+        // close to what we priced the trade on
+        if !drifted.is_empty() {
+            // the dry run pays the shio bid out of the profit
+            let expected = arb_result.profit().saturating_sub(arb_result.source.bid_amount()) as f64;
+            if (dry_run_profit as f64) < expected * MIN_PROFIT_RATIO_AFTER_DRIFT {
+                warn!(?drifted, expected, dry_run_profit, "State drifted and profit dropped, abort");
+                outcomes::record(&TradeKey::of(arb_result), Outcome::Stale);
+                return;
+            }
+            info!(?drifted, expected, dry_run_profit, "State drifted, re-simulated profit still holds");
+        }
+
+        let profit_floor = match claim.as_ref().and_then(|claim| claim.params.min_profit_gas_multiple) {
+            Some(multiple) => self.gas.profit_floor_at(multiple),
+            None => self.gas.profit_floor(),
+        };
+        let profit_floor = base::gas_in_base(profit_floor as i64).max(self.gas.min_profit() as i128);
+        if dry_run_profit < profit_floor {
+            warn!(dry_run_profit, profit_floor, "Profit below the fee floor, abort");
+            return;
+        }
+        // a shio bid's gas budget is tuned to order its digest after the opportunity's
+        if !arb_result.source.is_shio() {
+            let mut gas_used = gas_used;
+            // outbid the rivals on gas as long as the profit still clears the floor
+            let gas_price = tx_data.gas_price();
+            if let Some(price) = competition::priority_gas_price(&assessment, gas_price, self.gas.reference_gas_price())
+            {
+                let units = gas_used.div_ceil(gas_price.max(1));
+                let extra_gas = base::gas_in_base(units.saturating_mul(price - gas_price) as i64);
+                if dry_run_profit - extra_gas >= profit_floor {
+                    info!(?assessment, gas_price = price, "Contested path, raising the gas price");
+                    tx_data.gas_data_mut().price = price;
+                    gas_used = units.saturating_mul(price);
+                }
+            }
+            tx_data.gas_data_mut().budget = self.gas.gas_budget(gas_used, tx_data.gas_price());
+        }
+
+        if cancel.is_cancelled() {
+            warn!(?arb_result, "Opportunity superseded or expired before submission, abort");
+            return;
+        }
+        // the node fell behind while we priced the trade
+        if self.endpoints.is_stale() {
+            warn!(?arb_result, "Node data is stale, abort");
+            outcomes::record(&TradeKey::of(arb_result), Outcome::Stale);
+            return;
+        }
+
+        // a path that keeps getting cancelled for congestion goes out in smaller parts
+        if !arb_result.source.is_shio() {
+            let contended = contention::contended(arb_result.best_trial_result.trade_path.object_ids());
+            if !contended.is_empty() {
+                info!(
+                    ?contended,
+                    "Path keeps hitting shared-object congestion, trading it in parts"
+                );
+                let parts = self
+                    .trade_in_parts(
+                        tx_digest,
+                        arb_result,
+                        &lease,
+                        profit_floor,
+                        sim_ctx,
+                        ticket.as_ref(),
+                        cancel,
+                    )
+                    .await;
+                // the experiment settles on the first part
+                if let (Some(claim), Some(first)) = (claim, parts.first()) {
+                    claim.submitted(*first);
+                }
+                return;
+            }
+        }
+
+        let (arb_tx_digest, sender) = (tx_data.digest(), tx_data.sender());
+        lease.hold_until_settled(arb_tx_digest);
+        let arm = claim.as_ref().map(|claim| claim.arm);
+        let action = match arb_result.source {
+            Source::Shio { bid_amount, .. } => Action::ShioSubmitBid((tx_data, bid_amount, tx_digest)),
+            _ => {
+                outcomes::submitted(arb_tx_digest, TradeKey::of(arb_result));
+                if let Some(ticket) = &ticket {
+                    ticket.submitted(arb_tx_digest);
+                }
+                risk::submitted(
+                    arb_tx_digest,
+                    Exposure::of(arb_result.routes().map(|route| (&route.trade_path, route.amount_in))),
+                );
+                if let Some(claim) = claim {
+                    claim.submitted(arb_tx_digest);
+                }
+                Action::ExecutePublicTx(tx_data)
+            }
+        };
+
+        self.submitter.submit(action);
+        metrics::submitted(outcomes::strategy_name(&arb_result.source));
+
+        if let Some(journal) = &self.journal {
+            let sui_usd = utils::price::sui_usd_price().await.ok();
+            let mut trade = SubmittedTrade::new(tx_digest, arb_tx_digest, sender, arb_result, sui_usd);
+            trade.arm = arm;
+            journal.append(JournalEntry::Submitted(trade));
+        }
 
-            let arb_tx_digest = tx_data.digest();
-            let action = match arb_result.source {
-                Source::Shio { bid_amount, .. } => Action::ShioSubmitBid((tx_data, bid_amount, tx_digest)),
-                _ => Action::ExecutePublicTx(tx_data),
+        let tg_msgs = new_tg_messages(tx_digest, arb_tx_digest, arb_result, elapsed, &self.simulator_name);
+        for tg_msg in tg_msgs {
+            self.submitter.submit(tg_msg.into());
+        }
+
+        // notify dedicated simulator to update more frequently
+        if let Some(dedicated_sim) = &self.dedicated_simulator {
+            dedicated_sim.update_notifier.send(()).await.unwrap();
+        }
+    }
+
+    /// Trade `arb_result` from the account of `lease` as up to `SPLIT_PARTS` sequential transactions of an equal
+    /// share of its size. Each part is rebuilt and dry run on the state the previous one left (see
+    /// `after_part`), so it must clear `profit_floor` on its own, and is only sent once the previous
+    /// one landed. Returns the digests of the parts submitted.
+    async fn trade_in_parts(
+        &self,
+        trigger_digest: TransactionDigest,
+        arb_result: &ArbResult,
+        lease: &AccountLease,
+        profit_floor: i128,
+        sim_ctx: SimulateCtx,
+        ticket: Option<&Ticket>,
+        cancel: &CancellationToken,
+    ) -> Vec<TransactionDigest> {
+        let amount_in = arb_result.best_trial_result.amount_in / SPLIT_PARTS;
+        let sender = lease.account();
+        let key = TradeKey::of(arb_result);
+        let mut parts = vec![];
+        let mut sim_ctx = sim_ctx;
+
+        for part in 1..=SPLIT_PARTS {
+            if cancel.is_cancelled() || self.endpoints.is_stale() || shutdown::is_requested() {
+                break;
+            }
+            let built = async {
+                let tx_data = self.arb.rebuild(sender, amount_in, arb_result).await?;
+                self.dry_run_writes(tx_data, lease, sim_ctx.clone()).await
+            };
+            let (mut tx_data, profit, gas_used, written) = match until_cancelled(cancel, built).await {
+                Ok(res) => res,
+                Err(error) => {
+                    warn!(part, ?error, "Part failed its dry run, stop splitting");
+                    break;
+                }
             };
+            if profit < profit_floor {
+                warn!(part, profit, profit_floor, "Part below the fee floor, stop splitting");
+                break;
+            }
+            tx_data.gas_data_mut().budget = self.gas.gas_budget(gas_used, tx_data.gas_price());
 
-            self.submitter.submit(action);
+            let digest = tx_data.digest();
+            let landed = contention::watch(digest);
+            outcomes::submitted(digest, key.clone());
+            if let Some(ticket) = ticket {
+                ticket.submitted(digest);
+            }
+            risk::submitted(
+                digest,
+                Exposure::of([(&arb_result.best_trial_result.trade_path, amount_in)]),
+            );
+            self.submitter.submit(Action::ExecutePublicTx(tx_data));
+            metrics::submitted(&key.strategy);
+            parts.push(digest);
+            info!(part, amount_in, profit, %digest, "Submitted part");
 
-            let tg_msgs = new_tg_messages(tx_digest, arb_tx_digest, &arb_result, elapsed, &self.simulator_name);
-            for tg_msg in tg_msgs {
-                self.submitter.submit(tg_msg.into());
+            if let Some(journal) = &self.journal {
+                let sui_usd = utils::price::sui_usd_price().await.ok();
+                let mut trade = SubmittedTrade::new(trigger_digest, digest, sender, arb_result, sui_usd);
+                trade.amount_in = amount_in;
+                // the part's own dry run, the search only priced the whole
+                trade.expected_profit = profit as u64;
+                journal.append(JournalEntry::Submitted(trade));
             }
 
-            // notify dedicated simulator to update more frequently
-            if let Some(dedicated_sim) = &self.dedicated_simulator {
-                dedicated_sim.update_notifier.send(()).await.unwrap();
+            // the next part prices on the state this one leaves
+            match tokio::time::timeout(PART_TIMEOUT, landed).await {
+                Ok(Ok(Outcome::LandedProfitable | Outcome::LandedUnprofitable)) => {}
+                outcome => {
+                    warn!(part, ?outcome, "Part didn't land, stop splitting");
+                    break;
+                }
             }
+            sim_ctx = after_part(&sim_ctx, written);
         }
 
-        Ok(())
+        parts
+    }
+
+    /// Objects whose version moved since the profitable simulation.
+    async fn drifted_objects(&self, state_versions: &HashMap<ObjectID, SequenceNumber>) -> Vec<ObjectID> {
+        match &self.dedicated_simulator {
+            Some(dedicated_sim) => drifted_objects(dedicated_sim.as_ref(), state_versions).await,
+            None => drifted_objects(self.simulator_pool.get().as_ref().as_ref(), state_versions).await,
+        }
+    }
+
+    // return a final tx_data with latest versions, the sender's balance change in the dry run, and
+    // the gas it used
+    async fn dry_run_tx_data(
+        &self,
+        tx_data: TransactionData,
+        lease: &AccountLease,
+        sim_ctx: SimulateCtx,
+    ) -> Result<(TransactionData, i128, u64)> {
+        let (tx_data, profit, gas_used, _) = self.dry_run_writes(tx_data, lease, sim_ctx).await?;
+        Ok((tx_data, profit, gas_used))
     }
 
-    // return a final tx_data with latest versions
-    async fn dry_run_tx_data(&self, tx_data: TransactionData, sim_ctx: SimulateCtx) -> Result<TransactionData> {
-        let tx_data: TransactionData = self.fix_object_refs(tx_data).await?;
+    // like `dry_run_tx_data`, also returning what the dry run wrote
+    async fn dry_run_writes(
+        &self,
+        tx_data: TransactionData,
+        lease: &AccountLease,
+        sim_ctx: SimulateCtx,
+    ) -> Result<(TransactionData, i128, u64, Written)> {
+        let tx_data: TransactionData = self.fix_object_refs(tx_data, lease).await?;
 
+        let timer = Instant::now();
         let resp = if let Some(dedicated_sim) = &self.dedicated_simulator {
             dedicated_sim.simulate(tx_data.clone(), sim_ctx).await?
         } else {
             self.simulator_pool.get().simulate(tx_data.clone(), sim_ctx).await?
         };
+        metrics::simulated("dry_run", 1, timer.elapsed());
 
         let status = &resp.effects.status();
         ensure!(status.is_ok(), "Dry run result: {:?}", status);
+        let gas_used = resp.effects.gas_cost_summary().gas_used();
+        self.gas.record_gas_used(gas_used, tx_data.gas_price());
 
         let bc = &resp
             .balance_changes
             .into_iter()
-            .find(|bc| bc.owner == Owner::AddressOwner(self.sender))
+            .find(|bc| bc.owner == Owner::AddressOwner(tx_data.sender()) && &bc.coin_type == base::type_tag())
             .ok_or_eyre("No balance change for attacker")?;
-        ensure!(bc.amount > 0, "Attacker's balance not increased {:?}", bc);
+        // a SUI balance change already has gas taken out
+        let profit = if base::is_sui() {
+            bc.amount
+        } else {
+            bc.amount - base::gas_in_base(resp.effects.gas_cost_summary().net_gas_usage())
+        };
+        ensure!(profit > 0, "Attacker's balance not increased {:?}", bc);
 
-        Ok(tx_data)
+        let effects = &resp.effects;
+        let written = Written {
+            ids: effects
+                .mutated()
+                .iter()
+                .chain(effects.unwrapped())
+                .map(|obj| obj.object_id())
+                .chain(effects.deleted().iter().chain(effects.wrapped()).map(|obj| obj.object_id))
+                .collect(),
+            objects: resp.object_changes,
+        };
+        Ok((tx_data, profit, gas_used, written))
     }
 
-    // Fetch the latest object ref for gas coins.
+    // Fetch the latest object ref for gas coins and PTB inputs.
     // otherwise we need to wait until the index api to return the correct gas coins
-    async fn fix_object_refs(&self, tx_data: TransactionData) -> Result<TransactionData> {
-        let gas_coins = coin::get_gas_coin_refs(&self.sui, self.sender, None).await?;
+    async fn fix_object_refs(&self, tx_data: TransactionData, lease: &AccountLease) -> Result<TransactionData> {
+        // the last reads before signing, from the fastest endpoint
+        let sui = self.endpoints.fastest();
+        let gas_coins = lease.gas_payment(&sui).await?;
 
         let mut tx_data = tx_data;
         let gas_data: &mut GasData = tx_data.gas_data_mut();
         gas_data.payment = gas_coins;
 
-        Ok(tx_data)
+        refresh_object_refs(&sui, tx_data).await
     }
 }
 
+/// Whether `path` swaps through DeepBook v2, which takes the primary account's account cap.
+fn trades_deepbook_v2(path: &Path) -> bool {
+    path.path.iter().any(|dex| dex.protocol() == Protocol::DeepbookV2)
+}
+
+/// The objects a dry run wrote, with their new state when the simulator reports it (the HTTP
+/// simulator doesn't).
+#[derive(Debug, Default)]
+struct Written {
+    ids: HashSet<ObjectID>,
+    objects: Vec<ObjectReadResult>,
+}
+
+/// `sim_ctx` on the state a landed part left: the overrides of the objects it wrote are replaced by
+/// what its dry run wrote, or dropped so the simulator reads them again.
+fn after_part(sim_ctx: &SimulateCtx, written: Written) -> SimulateCtx {
+    let mut sim_ctx = sim_ctx.clone();
+    sim_ctx.override_objects.retain(|obj| !written.ids.contains(&obj.id()));
+    sim_ctx.override_objects.extend(written.objects);
+    sim_ctx
+}
+
+#[allow(clippy::too_many_arguments)]
 async fn arbitrage_one_coin(
     arb: Arc<Arb>,
     attacker: SuiAddress,
     coin_type: &str,
     pool_id: Option<ObjectID>,
     sim_ctx: SimulateCtx,
-    use_gss: bool,
+    sizing: Sizing,
     source: Source,
-) -> Option<(ArbResult, Duration)> {
+    cancel: &CancellationToken,
+    pins: Option<&PinLog>,
+) -> Result<(ArbResult, Duration)> {
     let start = Instant::now();
     let arb_result = match arb
-        .find_opportunity(attacker, coin_type, pool_id, vec![], sim_ctx, use_gss, source)
+        .find_opportunity(
+            attacker,
+            coin_type,
+            pool_id,
+            vec![],
+            sim_ctx,
+            sizing,
+            source,
+            cancel,
+            pins,
+        )
         .await
     {
         Ok(r) => r,
@@ -165,7 +631,7 @@ async fn arbitrage_one_coin(
             } else {
                 info!(elapsed = ?elapsed, %coin_type, "🥱 No opportunity: {error:#}");
             }
-            return None;
+            return Err(error);
         }
     };
 
@@ -180,5 +646,127 @@ async fn arbitrage_one_coin(
         &arb_result.best_trial_result
     );
 
-    Some((arb_result, start.elapsed()))
+    Ok((arb_result, start.elapsed()))
+}
+
+/// Objects of `state_versions` whose version moved. Only objects the shared pool state can't vouch
+/// for are read again, from `simulator`.
+async fn drifted_objects(
+    simulator: &dyn Simulator,
+    state_versions: &HashMap<ObjectID, SequenceNumber>,
+) -> Vec<ObjectID> {
+    let mut drifted = vec![];
+    let mut obj_ids = vec![];
+    for (&id, &version) in state_versions {
+        match pool_state::status(&id, version) {
+            pool_state::Status::Unchanged => {}
+            pool_state::Status::Drifted => drifted.push(id),
+            pool_state::Status::Unknown => obj_ids.push(id),
+        }
+    }
+    if obj_ids.is_empty() {
+        return drifted;
+    }
+
+    let objects = simulator.get_objects(&obj_ids).await;
+    for (id, obj) in obj_ids.into_iter().zip(objects) {
+        if let Some(obj) = &obj {
+            pool_state::insert(obj);
+        }
+        if obj.as_ref().map(|obj| obj.version()) != state_versions.get(&id).copied() {
+            drifted.push(id);
+        }
+    }
+    drifted
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+    use simulator::{SimEpoch, SimulateResult};
+    use sui_types::{object::Object, transaction::InputObjectKind};
+
+    use super::*;
+
+    // serves the objects it holds, simulates nothing
+    struct Objects(HashMap<ObjectID, Object>);
+
+    #[async_trait]
+    impl Simulator for Objects {
+        async fn simulate(&self, _tx: TransactionData, _ctx: SimulateCtx) -> Result<SimulateResult> {
+            bail!("not simulated")
+        }
+
+        async fn get_object(&self, obj_id: &ObjectID) -> Option<Object> {
+            self.0.get(obj_id).cloned()
+        }
+
+        fn name(&self) -> &str {
+            "objects"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_drifted_object_version() {
+        let object = |id, version| {
+            Object::with_id_owner_version_for_testing(id, SequenceNumber::from_u64(version), SuiAddress::ZERO)
+        };
+        let (unchanged, moved, observed) = (
+            object(ObjectID::random(), 5),
+            object(ObjectID::random(), 5),
+            object(ObjectID::random(), 5),
+        );
+        let latest = object(moved.id(), 6);
+        let simulator = Objects(HashMap::from([(unchanged.id(), unchanged.clone()), (moved.id(), latest)]));
+
+        // a write seen on chain drifts the object without reading it
+        pool_state::insert(&observed);
+        pool_state::observe_version(observed.id(), SequenceNumber::from_u64(7));
+
+        let state_versions = [&unchanged, &moved, &observed]
+            .into_iter()
+            .map(|obj| (obj.id(), obj.version()))
+            .collect();
+        let mut drifted = drifted_objects(&simulator, &state_versions).await;
+        drifted.sort();
+        let mut expected = vec![moved.id(), observed.id()];
+        expected.sort();
+        assert_eq!(drifted, expected);
+
+        // the reads went to the pool state, the next check needs none
+        let simulator = Objects(HashMap::new());
+        let state_versions = HashMap::from([(unchanged.id(), unchanged.version())]);
+        assert!(drifted_objects(&simulator, &state_versions).await.is_empty());
+    }
+
+    #[test]
+    fn test_next_part_sees_the_last_one() {
+        let object = |id, version| {
+            let object =
+                Object::with_id_owner_version_for_testing(id, SequenceNumber::from_u64(version), SuiAddress::ZERO);
+            ObjectReadResult::new(
+                InputObjectKind::ImmOrOwnedMoveObject(object.compute_object_reference()),
+                object.into(),
+            )
+        };
+        let (pool, trigger, read) = (ObjectID::random(), ObjectID::random(), ObjectID::random());
+        // the trigger's state with the pool pinned at the search
+        let sim_ctx = SimulateCtx::new(
+            SimEpoch::default(),
+            vec![object(pool, 5), object(trigger, 3), object(read, 2)],
+        );
+
+        // part 1 swapped through the pool and touched a dynamic field the simulator didn't report
+        let written = Written {
+            ids: HashSet::from([pool, read]),
+            objects: vec![object(pool, 6)],
+        };
+        let next = after_part(&sim_ctx, written);
+        let versions: HashMap<ObjectID, u64> = next
+            .override_objects
+            .iter()
+            .map(|obj| (obj.id(), obj.as_object().unwrap().version().value()))
+            .collect();
+        assert_eq!(versions, HashMap::from([(pool, 6), (trigger, 3)]));
+    }
 }
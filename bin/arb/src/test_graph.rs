@@ -1,18 +1,27 @@
-use std::sync::Arc;
+use std::{str::FromStr, sync::Arc};
 use clap::Parser;
-use eyre::Result;
+use eyre::{ensure, Result};
+use serde::Serialize;
 use tracing::{info, debug};
 use dex_indexer::DexIndexer;
-use sui_sdk::SUI_COIN_TYPE;
-use sui_types::base_types::ObjectID;
+use sui_sdk::{SuiClientBuilder, SUI_COIN_TYPE};
+use sui_types::base_types::{ObjectID, SuiAddress};
 use object_pool::ObjectPool;
-use simulator;
+use simulator::{self, SimulateCtx};
+use utils::coin;
 
 use crate::{
     HttpConfig,
-    strategy::graph_path_finder::BellmanFordPathFinder,
+    OutputFormat,
+    common::get_latest_epoch,
+    strategy::sizing::{AmountOptimizer, SizedPath},
+    defi::Dex,
     defi::DexSearcher,
     defi::IndexerDexSearcher,
+    defi::Trader,
+    defi::flash_lender::FlashLoanConfig,
+    defi::Path,
+    graph_path_finder::{BellmanFordPathFinder, GraphConfig, GraphSettings, QuoteWeights, SettledPath},
 };
 
 #[derive(Clone, Debug, Parser)]
@@ -26,14 +35,28 @@ pub struct Args {
     #[arg(long, help = "Maximum number of paths to display", default_value = "10")]
     pub max_paths: usize,
 
+    #[arg(long, help = "Size the displayed paths settling in SUI with flashloans from this sender (optional)")]
+    pub sender: Option<String>,
+
+    #[arg(long, value_enum, help = "Log the paths as text or print them as JSON records", default_value_t = OutputFormat::Text)]
+    pub output: OutputFormat,
+
+    #[arg(long, help = "Write the JSON records to this file instead of stdout (optional)")]
+    pub output_path: Option<String>,
+
     #[command(flatten)]
     pub http_config: HttpConfig,
+
+    #[command(flatten)]
+    pub graph_config: GraphConfig,
 }
 
 pub async fn run(args: Args) -> Result<()> {
-    mev_logger::init_console_logger_with_directives(None, &["arb=debug", "dex_indexer=debug"]);
+    mev_logger::init_console_logger_with_directives(None, &["arb=debug", "poison_dart_core=debug", "dex_indexer=debug"]);
+    ensure!(args.output != OutputFormat::Csv, "--output csv is only supported by pool-ids");
 
-    info!("Testing graph-based path finding with Bellman-Ford algorithm");
+    info!("Testing graph-based path finding with {:?} cycle search", args.graph_config.cycle_search);
+    let graph_settings = GraphSettings::new(args.graph_config.clone())?;
     info!("Loading DEX indexer...");
     
     // Clone the RPC URL for later use
@@ -58,11 +81,14 @@ pub async fn run(args: Args) -> Result<()> {
     let dex_searcher = Arc::new(IndexerDexSearcher::new_with_indexer(
         &rpc_url_searcher, 
         indexer.clone(), 
-        simulator_pool
+        simulator_pool.clone()
     ).await?) as Arc<dyn DexSearcher>;
     
-    // Initialize the Bellman-Ford path finder
-    let path_finder = BellmanFordPathFinder::new(dex_searcher);
+    // Initialize the Bellman-Ford path finder, quoting pools through the simulators
+    let trader = Arc::new(Trader::new(simulator_pool, &FlashLoanConfig::default()).await?);
+    let sui = SuiClientBuilder::default().build(&args.http_config.rpc_url).await?;
+    let weights = QuoteWeights::new(Arc::clone(&trader), dex_searcher.clone(), sui.clone());
+    let path_finder = BellmanFordPathFinder::new(dex_searcher, graph_settings.clone()).with_weights(Arc::new(weights));
     
     // Parse pool ID if provided
     let pool_id = if let Some(pool_id_str) = args.pool_id {
@@ -71,10 +97,66 @@ pub async fn run(args: Args) -> Result<()> {
         None
     };
     
-    // Find arbitrage paths
-    info!("Finding arbitrage paths starting from {}...", args.start_token);
-    let paths = path_finder.find_arbitrage_paths(&args.start_token, pool_id).await?;
+    // Find arbitrage paths, from every start token when several are configured
+    let paths = if args.graph_config.start_tokens.is_empty() {
+        info!("Finding arbitrage paths starting from {}...", args.start_token);
+        path_finder
+            .find_arbitrage_paths(&args.start_token, pool_id)
+            .await?
+            .into_iter()
+            .map(|path| SettledPath { settles_in: args.start_token.clone(), path })
+            .collect()
+    } else {
+        info!("Finding arbitrage paths starting from {:?}...", graph_settings.start_tokens());
+        path_finder.find_multi_start_paths(pool_id, |_| true).await?
+    };
     
+    // Sizing simulates flashloans, which need the sender's gas coins
+    let sizing = match &args.sender {
+        Some(sender) => {
+            let sender = SuiAddress::from_str(sender).map_err(|e| eyre::eyre!(e))?;
+            let gas_coins = coin::get_gas_coin_refs(&sui, sender, None).await?;
+            let sim_ctx = SimulateCtx::new(get_latest_epoch(&sui).await?, vec![]);
+            Some((sender, gas_coins, sim_ctx))
+        }
+        None => None,
+    };
+
+    // Size every displayed path first, the text and JSON outputs report the same numbers
+    let mut records = Vec::new();
+    for (i, SettledPath { settles_in, path }) in paths.iter().take(args.max_paths).enumerate() {
+        let sized = match &sizing {
+            Some((sender, gas_coins, sim_ctx)) if settles_in == SUI_COIN_TYPE => {
+                let optimizer = AmountOptimizer::new(
+                    Arc::clone(&trader),
+                    path.clone(),
+                    *sender,
+                    gas_coins.clone(),
+                    sim_ctx.clone(),
+                );
+                Some(optimizer.optimize().await)
+            }
+            _ => None,
+        };
+        records.push((i, settles_in, path, sized));
+    }
+
+    if args.output == OutputFormat::Json {
+        let records: Vec<PathRecord> = records
+            .iter()
+            .map(|(i, settles_in, path, sized)| {
+                let sized = sized.as_ref().and_then(|sized| sized.as_ref().ok());
+                PathRecord::new(i + 1, settles_in, path, sized)
+            })
+            .collect();
+        let json = serde_json::to_string_pretty(&records)?;
+        match &args.output_path {
+            Some(output_path) => std::fs::write(output_path, json)?,
+            None => println!("{}", json),
+        }
+        return Ok(());
+    }
+
     // Display results
     if paths.is_empty() {
         info!("No arbitrage paths found");
@@ -82,8 +164,8 @@ pub async fn run(args: Args) -> Result<()> {
         info!("Found {} arbitrage paths", paths.len());
         
         // Display the paths (limited by max_paths)
-        for (i, path) in paths.iter().take(args.max_paths).enumerate() {
-            info!("Path {}: {:?}", i + 1, path);
+        for (i, settles_in, path, sized) in &records {
+            info!("Path {} (settles in {}): {:?}", i + 1, settles_in, path);
             
             // Display detailed information about each DEX in the path
             for (j, dex) in path.path.iter().enumerate() {
@@ -95,6 +177,22 @@ pub async fn run(args: Args) -> Result<()> {
                     dex.object_id()
                 );
             }
+
+            match sized {
+                Some(Ok(sized)) => {
+                    info!("  Best size: {} (profit {})", sized.amount_in, sized.profit);
+                    for (j, hop) in sized.hops.iter().enumerate() {
+                        info!("  Step {}: {} -> {}, slippage {:.4}%",
+                            j + 1,
+                            hop.amount_in,
+                            hop.amount_out,
+                            hop.slippage * 100.0
+                        );
+                    }
+                }
+                Some(Err(error)) => info!("  Not sized: {}", error),
+                None => {}
+            }
         }
         
         if paths.len() > args.max_paths {
@@ -104,3 +202,65 @@ pub async fn run(args: Args) -> Result<()> {
     
     Ok(())
 }
+
+/// One path of `--output json`.
+#[derive(Debug, Serialize)]
+struct PathRecord {
+    rank: usize,
+    settles_in: String,
+    hops: Vec<HopRecord>,
+    /// Product of the hop rates, above 1 for a profitable cycle before gas
+    estimated_rate: Option<f64>,
+    /// Best size and its profit, when sized with `--sender`
+    amount_in: Option<u64>,
+    /// Decimal string of the i128 profit
+    expected_profit: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct HopRecord {
+    protocol: String,
+    pool_id: ObjectID,
+    coin_in_type: String,
+    coin_out_type: String,
+    /// Raw units out per raw unit in, net of the fee: the sized swap when there is one, else the
+    /// reserves of a constant-product pool
+    estimated_rate: Option<f64>,
+}
+
+impl PathRecord {
+    fn new(rank: usize, settles_in: &str, path: &Path, sized: Option<&SizedPath>) -> Self {
+        let hops: Vec<HopRecord> = path
+            .path
+            .iter()
+            .enumerate()
+            .map(|(j, dex)| {
+                let sized_rate = sized
+                    .and_then(|sized| sized.hops.get(j))
+                    .filter(|hop| hop.amount_in > 0)
+                    .map(|hop| hop.amount_out as f64 / hop.amount_in as f64);
+                let reserve_rate = dex
+                    .constant_product()
+                    .filter(|cp| cp.reserve_in > 0 && cp.fee_den > 0)
+                    .map(|cp| cp.reserve_out as f64 / cp.reserve_in as f64 * (1.0 - cp.fee_num as f64 / cp.fee_den as f64));
+                HopRecord {
+                    protocol: dex.protocol().to_string(),
+                    pool_id: dex.object_id(),
+                    coin_in_type: dex.coin_in_type(),
+                    coin_out_type: dex.coin_out_type(),
+                    estimated_rate: sized_rate.or(reserve_rate),
+                }
+            })
+            .collect();
+        let estimated_rate = hops.iter().map(|hop| hop.estimated_rate).product::<Option<f64>>();
+
+        Self {
+            rank,
+            settles_in: settles_in.to_string(),
+            hops,
+            estimated_rate,
+            amount_in: sized.map(|sized| sized.amount_in),
+            expected_profit: sized.map(|sized| sized.profit.to_string()),
+        }
+    }
+}
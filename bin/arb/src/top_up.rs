@@ -0,0 +1,175 @@
+//! Automatic gas top-up from trading profits.
+//!
+//! With a non-SUI base coin, profits pile up in the base coin while gas keeps draining SUI. Every
+//! `top_up_interval` seconds, each account whose SUI balance is below `gas_target` sells part of
+//! its base coin for SUI over the best path and splits the proceeds into `top_up_gas_coins` gas
+//! coins, so the bot keeps paying for itself without manual funding.
+
+use std::time::Duration;
+
+use clap::Parser;
+use eyre::{ensure, eyre, OptionExt, Result};
+use simulator::SimulateCtx;
+use sui_json_rpc_types::SuiTransactionBlockEffectsAPI;
+use sui_sdk::{SuiClient, SUI_COIN_TYPE};
+use sui_types::{
+    base_types::SuiAddress,
+    transaction::{Argument, Command, TransactionData},
+};
+use tracing::{info, warn};
+use utils::coin;
+
+use crate::{
+    common::get_latest_epoch,
+    defi::{base, Defi, Dex, TradeCtx, TradeType},
+    executor::PublicTxExecutor,
+};
+
+// the top-up pays its own gas out of what is left, so keep its budget small
+const TOP_UP_GAS_BUDGET: u64 = 50_000_000;
+// sell a little more than the shortfall, and split a little less than the quote into gas coins
+const SELL_MARGIN: f64 = 1.1;
+const SPLIT_MARGIN: f64 = 0.9;
+
+#[derive(Clone, Debug, Parser)]
+pub struct TopUpConfig {
+    /// Keep every account's SUI balance (in MIST) at least this high by selling base coin profit
+    #[arg(long, env = "SUI_GAS_TARGET")]
+    pub gas_target: Option<u64>,
+
+    /// Most of an account's base coin sold in one top-up (0 to 1)
+    #[arg(long, env = "SUI_TOP_UP_MAX_SHARE", default_value_t = 0.1)]
+    pub top_up_max_share: f64,
+
+    /// Number of gas coins a top-up is split into
+    #[arg(long, env = "SUI_TOP_UP_GAS_COINS", default_value_t = 4)]
+    pub top_up_gas_coins: u16,
+
+    /// Seconds between gas balance checks
+    #[arg(long, env = "SUI_TOP_UP_INTERVAL", default_value_t = 60)]
+    pub top_up_interval: u64,
+}
+
+pub struct GasTopUp {
+    defi: Defi,
+    sui: SuiClient,
+    executor: PublicTxExecutor,
+    accounts: Vec<SuiAddress>,
+    gas_target: u64,
+    config: TopUpConfig,
+}
+
+impl GasTopUp {
+    pub fn new(
+        defi: Defi,
+        sui: SuiClient,
+        executor: PublicTxExecutor,
+        accounts: Vec<SuiAddress>,
+        config: TopUpConfig,
+    ) -> Result<Self> {
+        let gas_target = config.gas_target.ok_or_eyre("SUI_GAS_TARGET is not set")?;
+        ensure!(
+            !base::is_sui(),
+            "gas top-up sells base coin profit, the base coin can't be SUI"
+        );
+
+        Ok(Self {
+            defi,
+            sui,
+            executor,
+            accounts,
+            gas_target,
+            config,
+        })
+    }
+
+    pub async fn run(self) {
+        info!(gas_target = self.gas_target, accounts = ?self.accounts, "gas top-up started");
+        let mut interval = tokio::time::interval(Duration::from_secs(self.config.top_up_interval));
+
+        loop {
+            interval.tick().await;
+            for &account in &self.accounts {
+                if let Err(error) = self.top_up(account).await {
+                    warn!(%account, ?error, "gas top-up failed");
+                }
+            }
+        }
+    }
+
+    async fn top_up(&self, account: SuiAddress) -> Result<()> {
+        let balance = self.sui.coin_read_api().get_balance(account, None).await?.total_balance as u64;
+        if balance >= self.gas_target {
+            return Ok(());
+        }
+        let shortfall = self.gas_target - balance;
+        ensure!(
+            balance > TOP_UP_GAS_BUDGET,
+            "{} MIST left, too little to pay for a top-up",
+            balance
+        );
+
+        // one base coin object is enough to sell from, take the largest
+        let base_coin = coin::get_coins(&self.sui, account, base::coin_type(), 1)
+            .await?
+            .into_iter()
+            .max_by_key(|coin| coin.balance)
+            .ok_or_eyre("no base coin to sell")?;
+        let max_amount_in = (base_coin.balance as f64 * self.config.top_up_max_share) as u64;
+        ensure!(max_amount_in > 0, "base coin balance too small to sell");
+
+        // quote the most we would sell, then scale down to the shortfall
+        let paths = self.defi.find_buy_paths(SUI_COIN_TYPE).await?;
+        ensure!(!paths.is_empty(), "no path from the base coin to SUI");
+        let gas_coins = coin::get_gas_coin_refs(&self.sui, account, None).await?;
+        let epoch = get_latest_epoch(&self.sui).await?;
+        let sim_ctx = SimulateCtx::new(epoch, vec![]);
+        let quote = self
+            .defi
+            .find_best_path_exact_in(&paths, account, max_amount_in, TradeType::Swap, &gas_coins, &sim_ctx)
+            .await?;
+        ensure!(quote.amount_out > 0, "SUI quoted at zero");
+
+        let wanted = shortfall as f64 * SELL_MARGIN / quote.amount_out as f64;
+        let amount_in = ((max_amount_in as f64 * wanted).ceil() as u64).clamp(1, max_amount_in);
+        let expected_out = (quote.amount_out as f64 * amount_in as f64 / max_amount_in as f64) as u64;
+
+        let mut ctx = TradeCtx::default();
+        let mut coin_arg = ctx.split_coin(base_coin.object_ref(), amount_in)?;
+        for (i, dex) in quote.path.path.iter().enumerate() {
+            let amount_in = if i == 0 { Some(amount_in) } else { None };
+            coin_arg = dex.extend_trade_tx(&mut ctx, account, coin_arg, amount_in).await?;
+        }
+
+        // split the proceeds into gas coins; the remainder stays in the last one
+        let splits = self.config.top_up_gas_coins - 1;
+        let mut sui_coins = vec![];
+        if splits > 0 {
+            let per_coin = (expected_out as f64 * SPLIT_MARGIN) as u64 / self.config.top_up_gas_coins as u64;
+            let amounts = (0..splits)
+                .map(|_| ctx.pure(per_coin).map_err(|e| eyre!(e)))
+                .collect::<Result<Vec<_>>>()?;
+            ctx.command(Command::SplitCoins(coin_arg, amounts));
+            let idx = ctx.last_command_idx();
+            sui_coins.extend((0..splits).map(|i| Argument::NestedResult(idx, i)));
+        }
+        sui_coins.push(coin_arg);
+        let recipient = ctx.pure(account).map_err(|e| eyre!(e))?;
+        ctx.command(Command::TransferObjects(sui_coins, recipient));
+
+        let tx_data =
+            TransactionData::new_programmable(account, gas_coins, ctx.ptb.finish(), TOP_UP_GAS_BUDGET, epoch.gas_price);
+        let resp = self.executor.execute_tx(tx_data).await?;
+        let status = resp.effects.as_ref().map(|effects| effects.status().clone());
+        ensure!(resp.status_ok() == Some(true), "top-up tx {} failed: {:?}", resp.digest, status);
+
+        info!(
+            %account,
+            digest = %resp.digest,
+            sold = %base::format_amount(amount_in),
+            expected_sui = %coin::format_sui_with_symbol(expected_out),
+            "gas topped up"
+        );
+        Ok(())
+    }
+}
@@ -11,40 +11,62 @@ pub mod types;
 use std::{
     collections::{HashMap, HashSet},
     fmt::Debug,
-    sync::Arc,
-    time::Instant,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
 };
 
 use burberry::Engine;
+use cached::{Cached, TimedSizedCache};
 use collector::QueryEventCollector;
 use eyre::Result;
 use strategy::PoolCreatedStrategy;
 use sui_sdk::{
     types::{base_types::ObjectID, event::EventID},
-    SuiClientBuilder, SUI_COIN_TYPE,
+    SuiClient, SuiClientBuilder, SUI_COIN_TYPE,
 };
-use tokio::task::JoinSet;
-use tracing::info;
+use tokio::{sync::broadcast, task::JoinSet};
+use tracing::{info, warn};
 use types::{DummyExecutor, Event, NoAction, Pool, PoolCache, Protocol};
+use utils::cache::{CacheLimits, CacheStats};
 
 pub const FILE_DB_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/data");
 
+// Don't hammer RPC/blockberry for coins whose decimals we couldn't resolve.
+const UNKNOWN_DECIMALS_TTL: Duration = Duration::from_secs(600);
+const UNKNOWN_DECIMALS_CACHE_SIZE: usize = 10_000;
+
+/// The protocols whose pools are indexed, those with their Cargo feature enabled.
+#[allow(unused_mut)]
 pub fn supported_protocols() -> Vec<Protocol> {
-    vec![
-        Protocol::Cetus,
-        Protocol::Turbos,
-        Protocol::Aftermath,
-        Protocol::KriyaAmm,
-        Protocol::KriyaClmm,
-        Protocol::FlowxClmm,
-        Protocol::DeepbookV2,
-        Protocol::BlueMove,
-    ]
+    let mut protocols = Vec::new();
+
+    #[cfg(feature = "cetus")]
+    protocols.push(Protocol::Cetus);
+    #[cfg(feature = "turbos")]
+    protocols.push(Protocol::Turbos);
+    #[cfg(feature = "aftermath")]
+    protocols.push(Protocol::Aftermath);
+    #[cfg(feature = "kriya_amm")]
+    protocols.push(Protocol::KriyaAmm);
+    #[cfg(feature = "kriya_clmm")]
+    protocols.push(Protocol::KriyaClmm);
+    #[cfg(feature = "flowx_clmm")]
+    protocols.push(Protocol::FlowxClmm);
+    #[cfg(feature = "deepbook_v2")]
+    protocols.push(Protocol::DeepbookV2);
+    #[cfg(feature = "deepbook_v3")]
+    protocols.push(Protocol::DeepbookV3);
+    #[cfg(feature = "blue_move")]
+    protocols.push(Protocol::BlueMove);
+
+    protocols
 }
 
 #[derive(Clone)]
 pub struct DexIndexer {
     pool_cache: PoolCache,
+    sui: SuiClient,
+    unknown_decimals: Arc<Mutex<TimedSizedCache<String, ()>>>,
 
     db: Arc<dyn DB>,
     _live_indexer_tasks: Arc<JoinSet<()>>,
@@ -52,15 +74,20 @@ pub struct DexIndexer {
 
 impl DexIndexer {
     pub async fn new(http_url: &str) -> Result<Self> {
+        Self::with_protocols(http_url, supported_protocols()).await
+    }
+
+    /// Index only `protocols`: the pools of the others are neither loaded nor backfilled.
+    pub async fn with_protocols(http_url: &str, protocols: Vec<Protocol>) -> Result<Self> {
         let sui = SuiClientBuilder::default().build(http_url).await?;
         let db = Arc::new(file_db::FileDB::new(FILE_DB_DIR, &supported_protocols())?);
 
         let timer = Instant::now();
-        info!("loading token pools...");
-        let pool_cache = db.load_token_pools(&supported_protocols())?;
+        info!(?protocols, "loading token pools...");
+        let pool_cache = db.load_token_pools(&protocols)?;
         info!(elapsed = ?timer.elapsed(), token_pools_count = %pool_cache.token_pools.len(), token01_pools_count = %pool_cache.token01_pools.len(), "token pools loaded");
 
-        let strategy = PoolCreatedStrategy::new(db.clone(), sui.clone(), pool_cache.clone())?;
+        let strategy = PoolCreatedStrategy::new(db.clone(), sui.clone(), pool_cache.clone(), protocols)?;
         strategy.backfill_pools().await?;
 
         // Build the bubbery engine
@@ -74,6 +101,11 @@ impl DexIndexer {
 
         Ok(Self {
             pool_cache,
+            sui,
+            unknown_decimals: Arc::new(Mutex::new(
+                CacheLimits::from_env("UNKNOWN_DECIMALS", UNKNOWN_DECIMALS_CACHE_SIZE, UNKNOWN_DECIMALS_TTL)
+                    .new_cache(),
+            )),
             db,
             _live_indexer_tasks: Arc::new(join_set),
         })
@@ -95,6 +127,54 @@ impl DexIndexer {
         self.pool_cache.pool_map.get(pool_id).map(|p| p.clone())
     }
 
+    /// Get the decimals of the given coin type.
+    ///
+    /// Looks at indexed pools first, then CoinMetadata (with blockberry as fallback).
+    /// Returns None when decimals can't be determined; such coins should not be traded.
+    pub async fn get_coin_decimals(&self, coin_type: &str) -> Option<u8> {
+        let coin_type = normalize_coin_type(coin_type);
+        if coin_type == SUI_COIN_TYPE {
+            return Some(9);
+        }
+
+        let indexed = self.get_pools_by_token(&coin_type).and_then(|pools| {
+            pools
+                .iter()
+                .find_map(|pool| pool.token_index(&coin_type).and_then(|i| pool.token(i)))
+                .map(|token| token.decimals)
+        });
+        if indexed.is_some() {
+            return indexed;
+        }
+
+        if self.unknown_decimals.lock().unwrap().cache_get(&coin_type).is_some() {
+            return None;
+        }
+
+        match protocols::get_coin_decimals(&self.sui, &coin_type).await {
+            Ok(decimals) => Some(decimals),
+            Err(error) => {
+                warn!(%coin_type, ?error, "unknown coin decimals, treating coin as untradeable");
+                self.unknown_decimals.lock().unwrap().cache_set(coin_type, ());
+                None
+            }
+        }
+    }
+
+    /// Usage of the indexer's bounded caches. The pool index itself is not a cache and is not
+    /// included.
+    pub async fn cache_stats(&self) -> Vec<CacheStats> {
+        let mut stats = protocols::cache_stats().await;
+        let unknown_decimals = self.unknown_decimals.lock().unwrap();
+        stats.push(CacheStats::of("unknown_decimals", &*unknown_decimals));
+        stats
+    }
+
+    /// Pools indexed after this call, as they are discovered.
+    pub fn subscribe_new_pools(&self) -> broadcast::Receiver<Pool> {
+        self.pool_cache.new_pools.subscribe()
+    }
+
     /// Get the pools count by the given protocol.
     pub fn pool_count(&self, protocol: &Protocol) -> usize {
         self.db.pool_count(protocol).unwrap_or_default()
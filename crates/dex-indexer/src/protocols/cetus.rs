@@ -14,14 +14,14 @@ use simulator::{SimulateCtx, Simulator};
 use sui_sdk::{
     rpc_types::{EventFilter, SuiData, SuiEvent, SuiObjectDataOptions},
     types::{base_types::ObjectID, TypeTag},
-    SuiClient, SuiClientBuilder,
+    SuiClient,
 };
 use sui_types::{
     base_types::SuiAddress, dynamic_field::derive_dynamic_field_id, object::Object, programmable_transaction_builder::ProgrammableTransactionBuilder, transaction::{Command, TransactionData}, Identifier
 };
 use utils::object::*;
 
-use super::{get_coin_decimals, get_pool_coins_type, SUI_RPC_NODE};
+use super::{get_children_ids, get_coin_decimals, get_pool_coins_type};
 use crate::{
     get_coin_in_out_v2,
     types::{Pool, PoolExtra, Protocol, SwapEvent, Token},
@@ -251,26 +251,8 @@ pub async fn cetus_pool_children_ids(pool: &Pool, simulator: Arc<dyn Simulator>)
 
         id
     };
-    let sui_client = SuiClientBuilder::default()
-    .build(SUI_RPC_NODE)
-    .await
-    .unwrap();
-
-    let mut next_cursor = None;
-    let mut tick_vec = Vec::new();
-
-    loop {
-        let ret = sui_client.read_api().get_dynamic_fields(positions_id, next_cursor, None).await?;
-        next_cursor = ret.next_cursor;
-        tick_vec.extend(ret.data); 
-        if !ret.has_next_page {
-            break;
-        }
-    }
 
-    let tick_vec: Vec<String> = tick_vec.iter().map(|field_info| {
-        field_info.object_id.to_string()
-    }).collect();
+    let tick_vec = get_children_ids(positions_id).await?;
    
     result.extend(tick_vec);
 
@@ -289,21 +271,7 @@ pub async fn cetus_pool_children_ids(pool: &Pool, simulator: Arc<dyn Simulator>)
             id
         };
 
-        let mut next_cursor = None;
-        let mut tick_vec = Vec::new();
-    
-        loop {
-            let ret = sui_client.read_api().get_dynamic_fields(id, next_cursor, None).await?;
-            next_cursor = ret.next_cursor;
-            tick_vec.extend(ret.data); 
-            if !ret.has_next_page {
-                break;
-            }
-        }
-
-        let tick_vec: Vec<String> = tick_vec.iter().map(|field_info| {
-            field_info.object_id.to_string()
-        }).collect();
+        let tick_vec = get_children_ids(id).await?;
     
         result.extend(tick_vec);
 
@@ -463,6 +431,28 @@ mod tests {
         println!("{:?}", children_ids);
     } 
 
+    // wUSDC/SUI has thousands of ticks and positions, well beyond a single dynamic-field page
+    #[tokio::test]
+    async fn test_cetus_pool_children_ids_multi_page() {
+        let pool = Pool {
+            protocol: Protocol::Cetus,
+            pool: ObjectID::from_str("0xcf994611fd4c48e277ce3ffd4d4364c914af2c3cbb05f7bf6facd371de688630").unwrap(),
+            tokens: vec![
+                Token::new(
+                    "0x5d4b302506645c37ff133b98c4b50a5ae14841659738d6d733d59d0d217a93bf::coin::COIN",
+                    6,
+                ),
+                Token::new("0x2::sui::SUI", 9),
+            ],
+            extra: PoolExtra::None,
+        };
+
+        let simulator: Arc<dyn Simulator> = Arc::new(DBSimulator::new_test(true).await);
+
+        let children_ids = cetus_pool_children_ids(&pool, simulator).await.unwrap();
+        assert!(children_ids.len() > 2 * crate::protocols::DYNAMIC_FIELD_PAGE_SIZE);
+    }
+
     #[tokio::test]
     async fn test_judge_cetus_pool_children_ids() {
         let pool = Pool {
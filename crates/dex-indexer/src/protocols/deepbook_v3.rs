@@ -0,0 +1,105 @@
+use eyre::{ensure, eyre, Result};
+use move_core_types::language_storage::StructTag;
+use serde::Deserialize;
+use sui_sdk::{
+    rpc_types::{EventFilter, SuiEvent},
+    types::{base_types::ObjectID, Identifier},
+    SuiClient,
+};
+
+use super::get_coin_decimals;
+use crate::types::{Pool, PoolExtra, Protocol, Token};
+
+pub const DEEPBOOK_V3_PACKAGE: &str = "0x2c8d603bc51326b8c13cef9dd07031a408a48dddb541963357661df5d3204809";
+
+// `PoolCreated<BaseAsset, QuoteAsset>` is generic, so the filter takes every event of the `pool`
+// module and the others are skipped when parsed.
+pub fn deepbook_v3_event_filter() -> EventFilter {
+    EventFilter::MoveEventModule {
+        package: DEEPBOOK_V3_PACKAGE.parse().unwrap(),
+        module: Identifier::new("pool").unwrap(),
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeepbookV3PoolCreated {
+    pub pool: ObjectID,
+    pub base_asset: String,
+    pub quote_asset: String,
+    pub taker_fee: u64,
+    pub tick_size: u64,
+    pub lot_size: u64,
+    pub min_size: u64,
+}
+
+impl TryFrom<&SuiEvent> for DeepbookV3PoolCreated {
+    type Error = eyre::Error;
+
+    fn try_from(event: &SuiEvent) -> Result<Self> {
+        let StructTag { name, type_params, .. } = &event.type_;
+        ensure!(name.as_str() == "PoolCreated", "not a PoolCreated event");
+        ensure!(type_params.len() == 2, "PoolCreated without base and quote types");
+
+        let parsed_json = &event.parsed_json;
+        let pool = parsed_json["pool_id"]
+            .as_str()
+            .ok_or_else(|| eyre!("Missing pool_id"))?
+            .parse()?;
+
+        let taker_fee: u64 = parsed_json["taker_fee"]
+            .as_str()
+            .ok_or_else(|| eyre!("Missing taker_fee"))?
+            .parse()?;
+
+        let tick_size: u64 = parsed_json["tick_size"]
+            .as_str()
+            .ok_or_else(|| eyre!("Missing tick_size"))?
+            .parse()?;
+
+        let lot_size: u64 = parsed_json["lot_size"]
+            .as_str()
+            .ok_or_else(|| eyre!("Missing lot_size"))?
+            .parse()?;
+
+        let min_size: u64 = parsed_json["min_size"]
+            .as_str()
+            .ok_or_else(|| eyre!("Missing min_size"))?
+            .parse()?;
+
+        Ok(Self {
+            pool,
+            base_asset: type_params[0].to_string(),
+            quote_asset: type_params[1].to_string(),
+            taker_fee,
+            tick_size,
+            lot_size,
+            min_size,
+        })
+    }
+}
+
+impl DeepbookV3PoolCreated {
+    pub async fn to_pool(&self, sui: &SuiClient) -> Result<Pool> {
+        let base_asset_decimals = get_coin_decimals(sui, &self.base_asset).await?;
+        let quote_asset_decimals = get_coin_decimals(sui, &self.quote_asset).await?;
+
+        let tokens = vec![
+            Token::new(&self.base_asset, base_asset_decimals),
+            Token::new(&self.quote_asset, quote_asset_decimals),
+        ];
+
+        let extra = PoolExtra::DeepbookV3 {
+            taker_fee: self.taker_fee,
+            tick_size: self.tick_size,
+            lot_size: self.lot_size,
+            min_size: self.min_size,
+        };
+
+        Ok(Pool {
+            protocol: Protocol::DeepbookV3,
+            pool: self.pool,
+            tokens,
+            extra,
+        })
+    }
+}
@@ -1,22 +1,34 @@
 pub mod abex;
+#[cfg(feature = "aftermath")]
 pub mod aftermath;
 pub mod babyswap;
+#[cfg(feature = "blue_move")]
 pub mod blue_move;
+#[cfg(feature = "cetus")]
 pub mod cetus;
+#[cfg(feature = "deepbook_v2")]
 pub mod deepbook_v2;
+#[cfg(feature = "deepbook_v3")]
+pub mod deepbook_v3;
 pub mod flowx_amm;
+#[cfg(feature = "flowx_clmm")]
 pub mod flowx_clmm;
 pub mod interest;
+#[cfg(feature = "kriya_amm")]
 pub mod kriya_amm;
+#[cfg(feature = "kriya_clmm")]
 pub mod kriya_clmm;
 pub mod navi;
 pub mod suiswap;
+#[cfg(feature = "turbos")]
 pub mod turbos;
 
-use std::str::FromStr;
+use std::{collections::HashSet, future::Future, str::FromStr, time::Duration};
 
-use cached::proc_macro::cached;
+use cached::{proc_macro::cached, TimedSizedCache};
 use eyre::{bail, ensure, eyre, Result};
+use tokio::sync::OnceCell;
+use tracing::warn;
 
 use sui_sdk::{
     rpc_types::SuiObjectDataOptions,
@@ -24,11 +36,25 @@ use sui_sdk::{
     SuiClient, SuiClientBuilder,
 };
 
+use utils::cache::{CacheLimits, CacheStats};
+
 use crate::{blockberry, normalize_coin_type};
 
 pub const SUI_RPC_NODE: &str = "";
 
-#[cached(key = "String", convert = r##"{ coin_type.to_string() }"##, result = true)]
+// Decimals and pool coin types never change; the limits only bound memory.
+const COIN_DECIMALS_CACHE_SIZE: usize = 100_000;
+const POOL_COINS_TYPE_CACHE_SIZE: usize = 100_000;
+const IMMUTABLE_CACHE_TTL: Duration = Duration::from_secs(24 * 3600);
+const CHILDREN_IDS_CACHE_SIZE: usize = 10_000;
+const CHILDREN_IDS_CACHE_TTL: Duration = Duration::from_secs(300);
+
+#[cached(
+    ty = "TimedSizedCache<String, u8>",
+    create = r##"{ CacheLimits::from_env("COIN_DECIMALS", COIN_DECIMALS_CACHE_SIZE, IMMUTABLE_CACHE_TTL).new_cache() }"##,
+    convert = r##"{ coin_type.to_string() }"##,
+    result = true
+)]
 pub async fn get_coin_decimals(sui: &SuiClient, coin_type: &str) -> Result<u8> {
     let coin_meta = sui.coin_read_api().get_coin_metadata(coin_type.into()).await?;
     if let Some(meta) = coin_meta {
@@ -42,7 +68,12 @@ pub async fn get_coin_decimals(sui: &SuiClient, coin_type: &str) -> Result<u8> {
     }
 }
 
-#[cached(key = "String", convert = r##"{ pool_id.to_string() }"##, result = true)]
+#[cached(
+    ty = "TimedSizedCache<String, (String, String)>",
+    create = r##"{ CacheLimits::from_env("POOL_COINS_TYPE", POOL_COINS_TYPE_CACHE_SIZE, IMMUTABLE_CACHE_TTL).new_cache() }"##,
+    convert = r##"{ pool_id.to_string() }"##,
+    result = true
+)]
 pub async fn get_pool_coins_type(sui: &SuiClient, pool_id: ObjectID) -> Result<(String, String)> {
     let opts = SuiObjectDataOptions::default().with_type();
     let obj = sui
@@ -111,23 +142,88 @@ macro_rules! get_coin_in_out_v2 {
     }};
 }
 
+// Max page size accepted by `suix_getDynamicFields`.
+const DYNAMIC_FIELD_PAGE_SIZE: usize = 50;
+const DYNAMIC_FIELD_MAX_RETRIES: usize = 3;
+
+static SUI_CLIENT: OnceCell<SuiClient> = OnceCell::const_new();
+
 // For generating pool_related_ids.txt only, using HttpClient is acceptable.
+// Large CLMM pools have thousands of ticks, so every page must be followed; results are
+// cached for a few minutes since the same tables are read for each pool of a protocol.
+#[cached(
+    ty = "TimedSizedCache<String, Vec<String>>",
+    create = r##"{ CacheLimits::from_env("CHILDREN_IDS", CHILDREN_IDS_CACHE_SIZE, CHILDREN_IDS_CACHE_TTL).new_cache() }"##,
+    convert = r##"{ id.to_string() }"##,
+    result = true
+)]
 pub async fn get_children_ids(id: ObjectID) -> Result<Vec<String>> {
-    let sui_client = SuiClientBuilder::default().build(SUI_RPC_NODE).await.unwrap();
-    let mut next_cursor = None;
-    let mut children = vec![];
+    let sui_client = SUI_CLIENT
+        .get_or_try_init(|| SuiClientBuilder::default().build(SUI_RPC_NODE))
+        .await?;
+
+    let children = collect_pages(|cursor| async move {
+        let page = sui_client
+            .read_api()
+            .get_dynamic_fields(id, cursor, Some(DYNAMIC_FIELD_PAGE_SIZE))
+            .await?;
+        let ids = page.data.iter().map(|field_info| field_info.object_id).collect();
+        Ok((ids, page.next_cursor, page.has_next_page))
+    })
+    .await?;
+
+    Ok(children.iter().map(|id| id.to_string()).collect())
+}
+
+pub async fn cache_stats() -> Vec<CacheStats> {
+    vec![
+        CacheStats::of("coin_decimals", &*GET_COIN_DECIMALS.lock().await),
+        CacheStats::of("pool_coins_type", &*GET_POOL_COINS_TYPE.lock().await),
+        CacheStats::of("children_ids", &*GET_CHILDREN_IDS.lock().await),
+    ]
+}
+
+/// Follow a cursor-paginated listing to the end.
+///
+/// Each page is retried a few times before giving up, and a cursor that does not advance
+/// is an error rather than an endless loop. Duplicates across pages are dropped.
+async fn collect_pages<F, Fut>(mut fetch: F) -> Result<Vec<ObjectID>>
+where
+    F: FnMut(Option<ObjectID>) -> Fut,
+    Fut: Future<Output = Result<(Vec<ObjectID>, Option<ObjectID>, bool)>>,
+{
+    let mut cursor = None;
+    let mut seen = HashSet::new();
+    let mut ids = vec![];
 
     loop {
-        let ret = sui_client.read_api().get_dynamic_fields(id, next_cursor, None).await?;
-        next_cursor = ret.next_cursor;
-        let children_ids = ret.data.iter().map(|field_info| field_info.object_id.to_string());
-        children.extend(children_ids);
-        if !ret.has_next_page {
+        let mut attempt = 0;
+        let (page, next_cursor, has_next_page) = loop {
+            match fetch(cursor).await {
+                Ok(page) => break page,
+                Err(e) if attempt + 1 < DYNAMIC_FIELD_MAX_RETRIES => {
+                    attempt += 1;
+                    warn!(?cursor, attempt, "failed to fetch page: {:?}", e);
+                    tokio::time::sleep(Duration::from_millis(200 * attempt as u64)).await;
+                }
+                Err(e) => return Err(e),
+            }
+        };
+
+        ids.extend(page.into_iter().filter(|id| seen.insert(*id)));
+
+        if !has_next_page {
             break;
         }
+        ensure!(
+            next_cursor.is_some() && next_cursor != cursor,
+            "pagination cursor did not advance: {:?}",
+            cursor
+        );
+        cursor = next_cursor;
     }
 
-    Ok(children)
+    Ok(ids)
 }
 
 #[macro_export]
@@ -168,6 +264,32 @@ mod tests {
     use super::*;
     use crate::tests::TEST_HTTP_URL;
 
+    #[tokio::test]
+    async fn test_collect_pages_follows_every_page() {
+        let all: Vec<ObjectID> = (0..120u64).map(|i| ObjectID::from_single_byte((i % 100) as u8)).collect();
+        let ids = collect_pages(|cursor| {
+            let all = all.clone();
+            async move {
+                let start = cursor.map(|c| all.iter().position(|id| *id == c).unwrap() + 1).unwrap_or(0);
+                let end = (start + DYNAMIC_FIELD_PAGE_SIZE).min(all.len());
+                let page = all[start..end].to_vec();
+                Ok((page, Some(all[end - 1]), end < all.len()))
+            }
+        })
+        .await
+        .unwrap();
+
+        // 3 pages, the last 20 ids repeat the first ones
+        assert_eq!(ids.len(), 100);
+    }
+
+    #[tokio::test]
+    async fn test_collect_pages_rejects_stuck_cursor() {
+        let stuck = ObjectID::from_single_byte(1);
+        let res = collect_pages(|_| async move { Ok((vec![stuck], Some(stuck), true)) }).await;
+        assert!(res.is_err());
+    }
+
     #[tokio::test]
     async fn test_get_coin_decimals() {
         let sui = SuiClientBuilder::default().build(TEST_HTTP_URL).await.unwrap();
@@ -9,7 +9,7 @@ use simulator::Simulator;
 use sui_sdk::{
     rpc_types::{EventFilter, SuiEvent},
     types::base_types::ObjectID,
-    SuiClient, SuiClientBuilder,
+    SuiClient,
 };
 // use sui_types::{dynamic_field::derive_dynamic_field_id, TypeTag};
 
@@ -17,7 +17,7 @@ use utils::object::{
     extract_object_id_from_move_struct, extract_struct_from_move_struct,
 };
 
-use super::{get_coin_decimals, get_pool_coins_type, SUI_RPC_NODE};
+use super::{get_children_ids, get_coin_decimals, get_pool_coins_type};
 use crate::{
     get_coin_in_out_v2,
     types::{Pool, PoolExtra, Protocol, SwapEvent, Token},
@@ -206,26 +206,8 @@ pub async fn turbos_pool_children_ids(pool: &Pool, simulator: Arc<dyn Simulator>
         extract_object_id_from_move_struct(&id, "bytes")?
     };
 
-    let sui_client = SuiClientBuilder::default()
-    .build(SUI_RPC_NODE)
-    .await
-    .unwrap();
-
-    let mut next_cursor = None;
-    let mut tick_vec = Vec::new();
-
-    loop {
-        let ret = sui_client.read_api().get_dynamic_fields(tickmap_id, next_cursor, None).await?;
-        next_cursor = ret.next_cursor;
-        tick_vec.extend(ret.data); 
-        if !ret.has_next_page {
-            break;
-        }
-    }
 
-    let tick_vec: Vec<String> = tick_vec.iter().map(|field_info| {
-        field_info.object_id.to_string()
-    }).collect();
+    let tick_vec = get_children_ids(tickmap_id).await?;
 
     // println!("tick_vec ======> {:?}", tick_vec);
 
@@ -7,7 +7,7 @@ use tokio::task::JoinSet;
 use tracing::{debug, error, info};
 
 use crate::{
-    supported_protocols, token01_key,
+    token01_key,
     types::{Event, NoAction, PoolCache, Protocol},
     DB,
 };
@@ -18,17 +18,23 @@ pub struct PoolCreatedStrategy {
 
     db: Arc<dyn DB>,
     sui: SuiClient,
+    protocols: Vec<Protocol>,
 }
 
 impl PoolCreatedStrategy {
-    pub fn new(db: Arc<dyn DB>, sui: SuiClient, pool_cache: PoolCache) -> Result<Self> {
-        Ok(Self { pool_cache, db, sui })
+    pub fn new(db: Arc<dyn DB>, sui: SuiClient, pool_cache: PoolCache, protocols: Vec<Protocol>) -> Result<Self> {
+        Ok(Self {
+            pool_cache,
+            db,
+            sui,
+            protocols,
+        })
     }
 
     pub async fn backfill_pools(&self) -> Result<()> {
         let mut joinset = JoinSet::new();
         let cursors = self.db.get_processed_cursors()?;
-        for protocol in supported_protocols() {
+        for protocol in self.protocols.iter().cloned() {
             let (sui, db) = (self.sui.clone(), self.db.clone());
             let pool_cache = self.pool_cache.clone();
             let cursor = cursors.get(&protocol).cloned().flatten();
@@ -85,6 +91,7 @@ async fn backfill_pools_for_protocol(
         token_pools,
         token01_pools,
         pool_map,
+        new_pools,
     } = pool_cache;
 
     while !page.data.is_empty() {
@@ -103,7 +110,10 @@ async fn backfill_pools_for_protocol(
                         token01_pools.entry(key).or_default().insert(pool.clone());
                     }
                     // pool_map
-                    pool_map.insert(pool.pool, pool.clone());
+                    if pool_map.insert(pool.pool, pool.clone()).is_none() {
+                        // no receivers is fine, e.g. during the initial backfill
+                        let _ = new_pools.send(pool.clone());
+                    }
 
                     pools.push(pool)
                 }
@@ -16,14 +16,30 @@ use sui_sdk::{
     types::base_types::ObjectID,
     SuiClient, SUI_COIN_TYPE,
 };
+use tokio::sync::broadcast;
 use tracing::error;
 
+#[cfg(feature = "aftermath")]
+use crate::protocols::aftermath::*;
+#[cfg(feature = "blue_move")]
+use crate::protocols::blue_move::*;
+#[cfg(feature = "cetus")]
+use crate::protocols::cetus::*;
+#[cfg(feature = "deepbook_v2")]
+use crate::protocols::deepbook_v2::*;
+#[cfg(feature = "deepbook_v3")]
+use crate::protocols::deepbook_v3::*;
+#[cfg(feature = "flowx_clmm")]
+use crate::protocols::flowx_clmm::*;
+#[cfg(feature = "kriya_amm")]
+use crate::protocols::kriya_amm::*;
+#[cfg(feature = "kriya_clmm")]
+use crate::protocols::kriya_clmm::*;
+#[cfg(feature = "turbos")]
+use crate::protocols::turbos::*;
 use crate::{
     normalize_coin_type,
-    protocols::{
-        abex::*, aftermath::*, babyswap::*, blue_move::*, cetus::*, deepbook_v2::*, flowx_amm::*, flowx_clmm::*,
-        interest::*, kriya_amm::*, kriya_clmm::*, navi::*, suiswap::*, turbos::*,
-    },
+    protocols::{abex::*, babyswap::*, flowx_amm::*, interest::*, navi::*, suiswap::*},
 };
 
 // token_type -> pools
@@ -31,11 +47,15 @@ pub type TokenPools = DashMap<String, HashSet<Pool>>;
 // (token0_type, token1_type) -> pools
 pub type Token01Pools = DashMap<(String, String), HashSet<Pool>>;
 
+const NEW_POOLS_CAPACITY: usize = 1024;
+
 #[derive(Debug, Clone)]
 pub struct PoolCache {
     pub token_pools: Arc<TokenPools>,
     pub token01_pools: Arc<Token01Pools>,
     pub pool_map: Arc<DashMap<ObjectID, Pool>>,
+    /// Pools indexed for the first time, sent as they are discovered
+    pub new_pools: broadcast::Sender<Pool>,
 }
 
 impl PoolCache {
@@ -44,6 +64,7 @@ impl PoolCache {
             token_pools: Arc::new(token_pools),
             token01_pools: Arc::new(token01_pools),
             pool_map: Arc::new(pool_map),
+            new_pools: broadcast::channel(NEW_POOLS_CAPACITY).0,
         }
     }
 }
@@ -111,6 +132,12 @@ pub enum PoolExtra {
         tick_size: u64,
         lot_size: u64,
     },
+    DeepbookV3 {
+        taker_fee: u64,
+        tick_size: u64,
+        lot_size: u64,
+        min_size: u64,
+    },
 }
 
 impl fmt::Display for Pool {
@@ -197,11 +224,17 @@ impl Pool {
 
         // Children
         let children_ids = match self.protocol {
+            #[cfg(feature = "cetus")]
             Protocol::Cetus => cetus_pool_children_ids(self, simulator).await,
+            #[cfg(feature = "blue_move")]
             Protocol::BlueMove => blue_move_pool_children_ids(self, simulator).await,
+            #[cfg(feature = "turbos")]
             Protocol::Turbos => turbos_pool_children_ids(self, simulator).await,
+            #[cfg(feature = "kriya_clmm")]
             Protocol::KriyaClmm => kriya_clmm_pool_children_ids(self, simulator).await,
+            #[cfg(feature = "flowx_clmm")]
             Protocol::FlowxClmm => flowx_clmm_pool_children_ids(self, simulator).await,
+            #[cfg(feature = "aftermath")]
             Protocol::Aftermath => aftermath_pool_children_ids(self, simulator).await,
             _ => Ok(vec![]),
         };
@@ -335,13 +368,20 @@ impl TryFrom<&ShioEvent> for Protocol {
 impl Protocol {
     pub fn try_from_event_type(event_type: &str) -> Result<Self> {
         match event_type {
+            #[cfg(feature = "cetus")]
             CETUS_SWAP_EVENT => Ok(Protocol::Cetus),
+            #[cfg(feature = "turbos")]
             TURBOS_SWAP_EVENT => Ok(Protocol::Turbos),
+            #[cfg(feature = "aftermath")]
             AFTERMATH_SWAP_EVENT => Ok(Protocol::Aftermath),
+            #[cfg(feature = "kriya_amm")]
             event_type if event_type.starts_with(KRIYA_AMM_SWAP_EVENT) => Ok(Protocol::KriyaAmm),
+            #[cfg(feature = "kriya_clmm")]
             KRIYA_CLMM_SWAP_EVENT => Ok(Protocol::KriyaClmm),
             FLOWX_AMM_SWAP_EVENT => Ok(Protocol::FlowxAmm),
+            #[cfg(feature = "flowx_clmm")]
             FLOWX_CLMM_SWAP_EVENT => Ok(Protocol::FlowxClmm),
+            #[cfg(feature = "blue_move")]
             event_type if event_type.starts_with(BLUE_MOVE_SWAP_EVENT) => Ok(Protocol::BlueMove),
             event_type if event_type.starts_with(SUISWAP_SWAP_EVENT) => Ok(Protocol::SuiSwap),
             event_type if event_type.starts_with(INTEREST_SWAP_EVENT) => Ok(Protocol::Interest),
@@ -351,16 +391,53 @@ impl Protocol {
         }
     }
 
+    /// Type of the event a swap through the protocol emits, a prefix of it for generic events.
+    pub fn swap_event_type(&self) -> Option<&'static str> {
+        match self {
+            #[cfg(feature = "cetus")]
+            Protocol::Cetus => Some(CETUS_SWAP_EVENT),
+            #[cfg(feature = "turbos")]
+            Protocol::Turbos => Some(TURBOS_SWAP_EVENT),
+            #[cfg(feature = "aftermath")]
+            Protocol::Aftermath => Some(AFTERMATH_SWAP_EVENT),
+            #[cfg(feature = "kriya_amm")]
+            Protocol::KriyaAmm => Some(KRIYA_AMM_SWAP_EVENT),
+            #[cfg(feature = "kriya_clmm")]
+            Protocol::KriyaClmm => Some(KRIYA_CLMM_SWAP_EVENT),
+            Protocol::FlowxAmm => Some(FLOWX_AMM_SWAP_EVENT),
+            #[cfg(feature = "flowx_clmm")]
+            Protocol::FlowxClmm => Some(FLOWX_CLMM_SWAP_EVENT),
+            #[cfg(feature = "blue_move")]
+            Protocol::BlueMove => Some(BLUE_MOVE_SWAP_EVENT),
+            Protocol::SuiSwap => Some(SUISWAP_SWAP_EVENT),
+            Protocol::Interest => Some(INTEREST_SWAP_EVENT),
+            Protocol::Abex => Some(ABEX_SWAP_EVENT),
+            Protocol::BabySwap => Some(BABY_SWAP_EVENT),
+            // no swap event, or compiled out
+            _ => None,
+        }
+    }
+
     pub fn event_filter(&self) -> EventFilter {
         match self {
+            #[cfg(feature = "cetus")]
             Protocol::Cetus => cetus_event_filter(),
+            #[cfg(feature = "turbos")]
             Protocol::Turbos => turbos_event_filter(),
+            #[cfg(feature = "aftermath")]
             Protocol::Aftermath => aftermath_event_filter(),
+            #[cfg(feature = "kriya_amm")]
             Protocol::KriyaAmm => kriya_amm_event_filter(),
+            #[cfg(feature = "kriya_clmm")]
             Protocol::KriyaClmm => kriya_clmm_event_filter(),
             Protocol::FlowxAmm => flowx_amm_event_filter(),
+            #[cfg(feature = "flowx_clmm")]
             Protocol::FlowxClmm => flowx_clmm_event_filter(),
+            #[cfg(feature = "deepbook_v2")]
             Protocol::DeepbookV2 => deepbook_v2_event_filter(),
+            #[cfg(feature = "deepbook_v3")]
+            Protocol::DeepbookV3 => deepbook_v3_event_filter(),
+            #[cfg(feature = "blue_move")]
             Protocol::BlueMove => blue_move_event_filter(),
             _ => todo!(),
         }
@@ -368,14 +445,24 @@ impl Protocol {
 
     pub async fn sui_event_to_pool(&self, event: &SuiEvent, sui: &SuiClient) -> Result<Pool> {
         match self {
+            #[cfg(feature = "cetus")]
             Protocol::Cetus => CetusPoolCreated::try_from(event)?.to_pool(sui).await,
+            #[cfg(feature = "turbos")]
             Protocol::Turbos => TurbosPoolCreated::try_from(event)?.to_pool(sui).await,
+            #[cfg(feature = "aftermath")]
             Protocol::Aftermath => AftermathPoolCreated::try_from(event)?.to_pool(sui).await,
+            #[cfg(feature = "kriya_amm")]
             Protocol::KriyaAmm => KriyaAmmPoolCreated::try_from(event)?.to_pool(sui).await,
+            #[cfg(feature = "kriya_clmm")]
             Protocol::KriyaClmm => KriyaClmmPoolCreated::try_from(event)?.to_pool(sui).await,
             Protocol::FlowxAmm => FlowxAmmPoolCreated::try_from(event)?.to_pool(sui).await,
+            #[cfg(feature = "flowx_clmm")]
             Protocol::FlowxClmm => FlowxClmmPoolCreated::try_from(event)?.to_pool(sui).await,
+            #[cfg(feature = "deepbook_v2")]
             Protocol::DeepbookV2 => DeepbookV2PoolCreated::try_from(event)?.to_pool(sui).await,
+            #[cfg(feature = "deepbook_v3")]
+            Protocol::DeepbookV3 => DeepbookV3PoolCreated::try_from(event)?.to_pool(sui).await,
+            #[cfg(feature = "blue_move")]
             Protocol::BlueMove => BlueMovePoolCreated::try_from(event)?.to_pool(sui).await,
             _ => todo!(),
         }
@@ -383,13 +470,20 @@ impl Protocol {
 
     pub async fn sui_event_to_swap_event(&self, event: &SuiEvent, provider: Arc<dyn Simulator>) -> Result<SwapEvent> {
         match self {
+            #[cfg(feature = "cetus")]
             Protocol::Cetus => CetusSwapEvent::try_from(event)?.to_swap_event_v2(provider).await,
+            #[cfg(feature = "turbos")]
             Protocol::Turbos => TurbosSwapEvent::try_from(event)?.to_swap_event_v2(provider).await,
+            #[cfg(feature = "aftermath")]
             Protocol::Aftermath => AftermathSwapEvent::try_from(event)?.to_swap_event().await,
+            #[cfg(feature = "kriya_amm")]
             Protocol::KriyaAmm => KriyaAmmSwapEvent::try_from(event)?.to_swap_event_v2(provider).await,
+            #[cfg(feature = "kriya_clmm")]
             Protocol::KriyaClmm => KriyaClmmSwapEvent::try_from(event)?.to_swap_event_v2(provider).await,
             Protocol::FlowxAmm => FlowxAmmSwapEvent::try_from(event)?.to_swap_event().await,
+            #[cfg(feature = "flowx_clmm")]
             Protocol::FlowxClmm => FlowxClmmSwapEvent::try_from(event)?.to_swap_event_v2(provider).await,
+            #[cfg(feature = "blue_move")]
             Protocol::BlueMove => BlueMoveSwapEvent::try_from(event)?.to_swap_event().await,
             Protocol::SuiSwap => SuiswapSwapEvent::try_from(event)?.to_swap_event().await,
             Protocol::Interest => InterestSwapEvent::try_from(event)?.to_swap_event().await,
@@ -401,13 +495,20 @@ impl Protocol {
 
     pub async fn shio_event_to_swap_event(&self, event: &ShioEvent, provider: Arc<dyn Simulator>) -> Result<SwapEvent> {
         match self {
+            #[cfg(feature = "cetus")]
             Protocol::Cetus => CetusSwapEvent::try_from(event)?.to_swap_event_v2(provider).await,
+            #[cfg(feature = "turbos")]
             Protocol::Turbos => TurbosSwapEvent::try_from(event)?.to_swap_event_v2(provider).await,
+            #[cfg(feature = "aftermath")]
             Protocol::Aftermath => AftermathSwapEvent::try_from(event)?.to_swap_event().await,
+            #[cfg(feature = "kriya_amm")]
             Protocol::KriyaAmm => KriyaAmmSwapEvent::try_from(event)?.to_swap_event_v2(provider).await,
+            #[cfg(feature = "kriya_clmm")]
             Protocol::KriyaClmm => KriyaClmmSwapEvent::try_from(event)?.to_swap_event_v2(provider).await,
             Protocol::FlowxAmm => FlowxAmmSwapEvent::try_from(event)?.to_swap_event().await,
+            #[cfg(feature = "flowx_clmm")]
             Protocol::FlowxClmm => FlowxClmmSwapEvent::try_from(event)?.to_swap_event_v2(provider).await,
+            #[cfg(feature = "blue_move")]
             Protocol::BlueMove => BlueMoveSwapEvent::try_from(event)?.to_swap_event().await,
             Protocol::SuiSwap => SuiswapSwapEvent::try_from(event)?.to_swap_event().await,
             Protocol::Interest => InterestSwapEvent::try_from(event)?.to_swap_event().await,
@@ -419,13 +520,20 @@ impl Protocol {
 
     pub async fn related_object_ids(&self) -> Result<HashSet<String>> {
         let res = match self {
+            #[cfg(feature = "cetus")]
             Protocol::Cetus => cetus_related_object_ids(),
+            #[cfg(feature = "blue_move")]
             Protocol::BlueMove => blue_move_related_object_ids(),
+            #[cfg(feature = "turbos")]
             Protocol::Turbos => turbos_related_object_ids(),
+            #[cfg(feature = "kriya_amm")]
             Protocol::KriyaAmm => kriya_amm_related_object_ids(),
+            #[cfg(feature = "kriya_clmm")]
             Protocol::KriyaClmm => kriya_clmm_related_object_ids(),
+            #[cfg(feature = "flowx_clmm")]
             Protocol::FlowxClmm => flowx_clmm_related_object_ids(),
             Protocol::Navi => navi_related_object_ids(),
+            #[cfg(feature = "aftermath")]
             Protocol::Aftermath => aftermath_related_object_ids().await,
             _ => bail!("Not interesting"),
         }
@@ -0,0 +1,130 @@
+use std::{collections::HashSet, fmt};
+
+use sui_sdk::SUI_COIN_TYPE;
+
+pub const GAS_BUDGET: u64 = 10_000_000_000;
+pub const MAX_SQRT_PRICE_X64: u128 = 79226673515401279992447579055;
+pub const MIN_SQRT_PRICE_X64: u128 = 4295048016;
+
+/// Where a coin comes from, to tell variants of "the same" asset (e.g. native vs Wormhole USDC)
+/// apart in labels. Routing keys on coin types only, so variants are never merged; they convert
+/// through pools like any other pair of coins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Provenance {
+    Native,
+    Wormhole { origin: &'static str },
+}
+
+impl fmt::Display for Provenance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Provenance::Native => write!(f, "native"),
+            Provenance::Wormhole { origin } => write!(f, "wormhole/{}", origin),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TokenInfo {
+    pub coin_type: &'static str,
+    /// Underlying asset, shared by all variants
+    pub asset: &'static str,
+    pub provenance: Provenance,
+    /// Deep enough liquidity to be used as an intermediate hop back to SUI
+    pub pegged: bool,
+}
+
+const TOKEN_REGISTRY: &[TokenInfo] = &[
+    TokenInfo {
+        coin_type: SUI_COIN_TYPE,
+        asset: "SUI",
+        provenance: Provenance::Native,
+        pegged: true,
+    },
+    TokenInfo {
+        coin_type: "0x5d4b302506645c37ff133b98c4b50a5ae14841659738d6d733d59d0d217a93bf::coin::COIN",
+        asset: "USDC",
+        provenance: Provenance::Wormhole { origin: "ethereum" },
+        pegged: true,
+    },
+    TokenInfo {
+        coin_type: "0xc060006111016b8a020ad5b33834984a437aaa7d3c74c18e09a95d48aceab08c::coin::COIN",
+        asset: "USDT",
+        provenance: Provenance::Wormhole { origin: "ethereum" },
+        pegged: true,
+    },
+    TokenInfo {
+        coin_type: "0xaf8cd5edc19c4512f4259f0bee101a40d41ebed738ade5874359610ef8eeced5::coin::COIN",
+        asset: "ETH",
+        provenance: Provenance::Wormhole { origin: "ethereum" },
+        pegged: true,
+    },
+    TokenInfo {
+        coin_type: "0xb231fcda8bbddb31f2ef02e6161444aec64a514e2c89279584ac9806ce9cf037::coin::COIN",
+        asset: "USDC",
+        provenance: Provenance::Wormhole { origin: "solana" },
+        pegged: true,
+    },
+    TokenInfo {
+        coin_type: "0xdba34672e30cb065b1f93e3ab55318768fd6fef66c15942c9f7cb846e2f900e7::usdc::USDC",
+        asset: "USDC",
+        provenance: Provenance::Native,
+        pegged: true,
+    },
+    TokenInfo {
+        coin_type: "0xce7ff77a83ea0cb6fd39bd8748e2ec89a3f41e8efdc3f4eb123e0ca37b184db2::buck::BUCK",
+        asset: "BUCK",
+        provenance: Provenance::Native,
+        pegged: true,
+    },
+];
+
+pub fn token_info(coin_type: &str) -> Option<&'static TokenInfo> {
+    TOKEN_REGISTRY.iter().find(|token| token.coin_type == coin_type)
+}
+
+/// Short label for known tokens so variants can't be mistaken for each other, e.g. `USDC(wormhole/solana)`.
+pub fn coin_label(coin_type: &str) -> String {
+    match token_info(coin_type) {
+        Some(TokenInfo {
+            asset,
+            provenance: Provenance::Native,
+            ..
+        }) => asset.to_string(),
+        Some(TokenInfo { asset, provenance, .. }) => format!("{}({})", asset, provenance),
+        None => coin_type.to_string(),
+    }
+}
+
+/// Assets with a registered coin type, e.g. `USDC`.
+pub fn known_assets() -> HashSet<&'static str> {
+    TOKEN_REGISTRY.iter().map(|token| token.asset).collect()
+}
+
+pub fn pegged_coin_types() -> HashSet<&'static str> {
+    TOKEN_REGISTRY
+        .iter()
+        .filter(|token| token.pegged)
+        .map(|token| token.coin_type)
+        .collect()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    pub const TEST_HTTP_URL: &str = "";
+    pub const TEST_ATTACKER: &str = "";
+
+    #[test]
+    fn test_token_variants_are_distinct() {
+        let usdc: Vec<_> = TOKEN_REGISTRY.iter().filter(|t| t.asset == "USDC").collect();
+        assert!(usdc.len() > 1);
+
+        let provenances: HashSet<_> = usdc.iter().map(|t| t.provenance).collect();
+        assert_eq!(provenances.len(), usdc.len(), "each USDC variant needs its own provenance");
+
+        let coin_types: HashSet<_> = TOKEN_REGISTRY.iter().map(|t| t.coin_type).collect();
+        assert_eq!(coin_types.len(), TOKEN_REGISTRY.len());
+    }
+}
@@ -4,7 +4,6 @@ use dex_indexer::types::{Pool, Protocol};
 use eyre::{ensure, eyre, Result};
 
 use move_core_types::annotated_value::MoveStruct;
-use primitive_types::U256;
 use simulator::Simulator;
 use sui_types::{
     base_types::{ObjectID, ObjectRef, SuiAddress},
@@ -14,17 +13,11 @@ use sui_types::{
 use tokio::sync::OnceCell;
 use utils::{coin, new_test_sui_client, object::*};
 
-use super::TradeCtx;
+use super::{descriptor, upgrades, TradeCtx};
 use crate::{config::*, defi::Dex};
 
-const AFTERMATH_DEX: &str = "0xc4049b2d1cc0f6e017fda8260e4377cecd236bd7f56a54fee120816e72e2e0dd";
-const POOL_REGISTRY: &str = "0xfcc774493db2c45c79f688f88d28023a3e7d98e4ee9f48bbf5c7990f651577ae";
-const PROTOCOL_FEE_VAULT: &str = "0xf194d9b1bcad972e45a7dd67dd49b3ee1e3357a00a50850c52cd51bb450e13b4";
-const TREASURY: &str = "0x28e499dff5e864a2eafe476269a4f5035f1c16f338da7be18b103499abf271ce";
-const INSURANCE_FUND: &str = "0xf0c40d67b078000e18032334c3325c47b9ec9f3d9ae4128be820d54663d14e3b";
-const REFERRAL_VAULT: &str = "0x35d35b0e5b177593d8c3a801462485572fc30861e6ce96a55af6dc4730709278";
 const SLIPPAGE: u128 = 900_000_000_000_000_000;
-const ONE: U256 = U256([1_000_000_000_000_000_000, 0, 0, 0]); // 10^18
+const ONE: f64 = 1_000_000_000_000_000_000.0; // 10^18
 
 #[derive(Clone)]
 pub struct ObjectArgs {
@@ -41,23 +34,23 @@ async fn get_object_args(simulator: Arc<Box<dyn Simulator>>) -> ObjectArgs {
     OBJ_CACHE
         .get_or_init(|| async {
             let pool_registry = simulator
-                .get_object(&ObjectID::from_hex_literal(POOL_REGISTRY).unwrap())
+                .get_object(&descriptor::object_id("aftermath", "pool_registry"))
                 .await
                 .unwrap();
             let protocol_fee_vault = simulator
-                .get_object(&ObjectID::from_hex_literal(PROTOCOL_FEE_VAULT).unwrap())
+                .get_object(&descriptor::object_id("aftermath", "protocol_fee_vault"))
                 .await
                 .unwrap();
             let treasury = simulator
-                .get_object(&ObjectID::from_hex_literal(TREASURY).unwrap())
+                .get_object(&descriptor::object_id("aftermath", "treasury"))
                 .await
                 .unwrap();
             let insurance_fund = simulator
-                .get_object(&ObjectID::from_hex_literal(INSURANCE_FUND).unwrap())
+                .get_object(&descriptor::object_id("aftermath", "insurance_fund"))
                 .await
                 .unwrap();
             let referral_vault = simulator
-                .get_object(&ObjectID::from_hex_literal(REFERRAL_VAULT).unwrap())
+                .get_object(&descriptor::object_id("aftermath", "referral_vault"))
                 .await
                 .unwrap();
 
@@ -85,10 +78,12 @@ pub struct Aftermath {
     treasury: ObjectArg,
     insurance_fund: ObjectArg,
     referral_vault: ObjectArg,
+    // pools hold 2..=8 coins; balances are normalized to 18 decimals via decimal_scalars
     balances: Vec<u128>,
+    decimal_scalars: Vec<u128>,
     weights: Vec<u64>,
-    swap_fee_in: u64,
-    swap_fee_out: u64,
+    fees_swap_in: Vec<u64>,
+    fees_swap_out: Vec<u64>,
     index_in: usize,
     index_out: usize,
 }
@@ -125,7 +120,10 @@ impl Aftermath {
         let weights = extract_u64_vec_from_move_struct(&parsed_pool, "weights")?;
         let fees_swap_in = extract_u64_vec_from_move_struct(&parsed_pool, "fees_swap_in")?;
         let fees_swap_out = extract_u64_vec_from_move_struct(&parsed_pool, "fees_swap_out")?;
-        let index_in = pool.token_index(coin_in_type).unwrap();
+        let decimal_scalars = extract_u128_vec_from_move_struct(&parsed_pool, "decimal_scalars")?;
+        let index_in = pool
+            .token_index(coin_in_type)
+            .ok_or_else(|| eyre!("coin not in pool: {}", coin_in_type))?;
 
         let mut type_params = parsed_pool.type_.type_params.clone();
         let coin_in_type_tag = TypeTag::from_str(&coin_in_type).map_err(|e| eyre!(e))?;
@@ -144,7 +142,9 @@ impl Aftermath {
             let coin_out_type_tag = TypeTag::from_str(&coin_out_type).map_err(|e| eyre!(e))?;
             type_params.push(coin_out_type_tag);
 
-            let index_out = pool.token_index(&coin_out_type).unwrap();
+            let index_out = pool
+                .token_index(&coin_out_type)
+                .ok_or_else(|| eyre!("coin not in pool: {}", coin_out_type))?;
 
             return Ok(vec![Self {
                 pool_arg,
@@ -158,14 +158,16 @@ impl Aftermath {
                 insurance_fund,
                 referral_vault,
                 balances,
+                decimal_scalars,
                 weights,
-                swap_fee_in: fees_swap_in[index_in],
-                swap_fee_out: fees_swap_out[index_out],
+                fees_swap_in,
+                fees_swap_out,
                 index_in,
                 index_out,
             }]);
         }
 
+        // one directed edge per other coin in the pool
         let mut res = Vec::new();
         for (index_out, coin_out) in pool.tokens.iter().enumerate() {
             if coin_out.token_type == coin_in_type {
@@ -187,9 +189,10 @@ impl Aftermath {
                 insurance_fund: insurance_fund.clone(),
                 referral_vault: referral_vault.clone(),
                 balances: balances.clone(),
+                decimal_scalars: decimal_scalars.clone(),
                 weights: weights.clone(),
-                swap_fee_in: fees_swap_in[index_in],
-                swap_fee_out: fees_swap_out[index_out],
+                fees_swap_in: fees_swap_in.clone(),
+                fees_swap_out: fees_swap_out.clone(),
                 index_in,
                 index_out,
             });
@@ -251,17 +254,18 @@ impl Aftermath {
 
     #[inline]
     fn expect_amount_out(&self, amount_in: u64) -> Result<u64> {
+        let (i, o) = (self.index_in, self.index_out);
         let amount_out = calculate_expected_out(
-            self.balances[self.index_in],
-            self.balances[self.index_out],
-            self.weights[self.index_in],
-            self.weights[self.index_out],
-            self.swap_fee_in,
-            self.swap_fee_out,
-            amount_in,
+            self.balances[i],
+            self.balances[o],
+            self.weights[i],
+            self.weights[o],
+            self.fees_swap_in[i],
+            self.fees_swap_out[o],
+            amount_in as u128 * self.decimal_scalars[i],
         )?;
 
-        Ok(amount_out)
+        Ok((amount_out / self.decimal_scalars[o]) as u64)
     }
 }
 
@@ -276,7 +280,7 @@ impl Dex for Aftermath {
     ) -> Result<Argument> {
         let amount_in = amount_in.ok_or_else(|| eyre!("amount_in is required"))?;
 
-        let package = ObjectID::from_hex_literal(AFTERMATH_DEX)?;
+        let package = upgrades::package_id(descriptor::address("aftermath", "package"))?;
         let module = Identifier::new("swap").map_err(|e| eyre!(e))?;
         let function = Identifier::new("swap_exact_in").map_err(|e| eyre!(e))?;
         let type_arguments = self.type_params.clone();
@@ -322,8 +326,20 @@ impl Dex for Aftermath {
         self.pool_arg.id()
     }
 
+    fn pool_arg(&self) -> Option<ObjectArg> {
+        Some(self.pool_arg.clone())
+    }
+
     fn flip(&mut self) {
         std::mem::swap(&mut self.coin_in_type, &mut self.coin_out_type);
+        std::mem::swap(&mut self.index_in, &mut self.index_out);
+        // type params are <pool..., coin_in, coin_out>
+        let n = self.type_params.len();
+        self.type_params.swap(n - 2, n - 1);
+    }
+
+    fn quote(&self, amount_in: u64) -> Option<u64> {
+        self.expect_amount_out(amount_in).ok()
     }
 
     fn is_a2b(&self) -> bool {
@@ -331,7 +347,9 @@ impl Dex for Aftermath {
     }
 }
 
-/// Get an estimate for amount_out using the spot price.
+/// Weighted-pool out-given-in on normalized balances:
+/// `out = B_o * (1 - (B_i / (B_i + A_i * (1 - fee_in))) ^ (w_i / w_o)) * (1 - fee_out)`.
+/// Weights and fees are 18-decimal fixed point.
 pub fn calculate_expected_out(
     balance_in: u128,
     balance_out: u128,
@@ -339,77 +357,16 @@ pub fn calculate_expected_out(
     weight_out: u64,
     swap_fee_in: u64,
     swap_fee_out: u64,
-    amount_in: u64,
-) -> Result<u64> {
-    // Get spot price with fees
-    let spot_price = calc_spot_price_fixed_with_fees(
-        U256::from(balance_in),
-        U256::from(balance_out),
-        U256::from(weight_in),
-        U256::from(weight_out),
-        U256::from(swap_fee_in),
-        U256::from(swap_fee_out),
-    )?;
-
-    // Calculate expected amount out
-    Ok(convert_fixed_to_int(div_down(
-        convert_int_to_fixed(amount_in),
-        spot_price,
-    )?))
-}
+    amount_in: u128,
+) -> Result<u128> {
+    ensure!(balance_in > 0 && balance_out > 0, "empty pool");
+    ensure!(weight_in > 0 && weight_out > 0, "zero weight");
 
-// Helper functions
-fn convert_int_to_fixed(a: u64) -> U256 {
-    U256::from(a) * ONE
-}
-
-fn convert_fixed_to_int(a: U256) -> u64 {
-    (a / ONE).low_u64()
-}
-
-fn div_down(a: U256, b: U256) -> Result<U256> {
-    if b.is_zero() {
-        return Err(eyre!("Division by zero"));
-    }
-    Ok((a * ONE) / b)
-}
+    let amount_in = amount_in as f64 * (1.0 - swap_fee_in as f64 / ONE);
+    let base = balance_in as f64 / (balance_in as f64 + amount_in);
+    let amount_out = balance_out as f64 * (1.0 - base.powf(weight_in as f64 / weight_out as f64));
 
-fn mul_down(a: U256, b: U256) -> Result<U256> {
-    Ok((a * b) / ONE)
-}
-
-fn complement(x: U256) -> U256 {
-    if x < ONE {
-        ONE - x
-    } else {
-        U256::zero()
-    }
-}
-
-// Calculate spot price with fees
-fn calc_spot_price_fixed_with_fees(
-    balance_in: U256,
-    balance_out: U256,
-    weight_in: U256,
-    weight_out: U256,
-    swap_fee_in: U256,
-    swap_fee_out: U256,
-) -> Result<U256> {
-    // First calculate spot price without fees
-    let spot_price_no_fees = calc_spot_price(balance_in, balance_out, weight_in, weight_out)?;
-
-    // Then apply fees
-    let fees_scalar = mul_down(complement(swap_fee_in), complement(swap_fee_out))?;
-
-    div_down(spot_price_no_fees, fees_scalar)
-}
-
-// Calculate spot price without fees
-fn calc_spot_price(balance_in: U256, balance_out: U256, weight_in: U256, weight_out: U256) -> Result<U256> {
-    div_down(
-        div_down(balance_in * ONE, weight_in)?,
-        div_down(balance_out * ONE, weight_out)?,
-    )
+    Ok((amount_out * (1.0 - swap_fee_out as f64 / ONE)) as u128)
 }
 
 #[cfg(test)]
@@ -426,9 +383,29 @@ mod tests {
         defi::{indexer_searcher::IndexerDexSearcher, DexSearcher},
     };
 
+    #[test]
+    fn test_calculate_expected_out() {
+        let half = (ONE / 2.0) as u64;
+
+        // equal weights without fees is constant product
+        let out = calculate_expected_out(1_000_000, 2_000_000, half, half, 0, 0, 1_000).unwrap();
+        assert!(out.abs_diff(2_000_000 * 1_000 / 1_001_000) <= 1);
+
+        // 3-coin pool leg with 20/40 weights: heavier out side moves less
+        let third = (ONE / 5.0) as u64;
+        let out_light = calculate_expected_out(1_000_000, 1_000_000, third, 2 * third, 0, 0, 10_000).unwrap();
+        let out_even = calculate_expected_out(1_000_000, 1_000_000, half, half, 0, 0, 10_000).unwrap();
+        assert!(out_light < out_even);
+
+        // fees reduce output
+        let fee = (ONE * 0.003) as u64;
+        let out_fee = calculate_expected_out(1_000_000, 2_000_000, half, half, fee, 0, 1_000).unwrap();
+        assert!(out_fee < out);
+    }
+
     #[tokio::test]
     async fn test_aftermath_swap_tx() {
-        mev_logger::init_console_logger_with_directives(None, &["arb=debug"]);
+        mev_logger::init_console_logger_with_directives(None, &["poison_dart_core=debug"]);
 
         let simulator_pool = Arc::new(ObjectPool::new(1, move || {
             tokio::runtime::Runtime::new()
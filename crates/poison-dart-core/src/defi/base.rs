@@ -0,0 +1,163 @@
+//! The base (settlement) currency.
+//!
+//! Arbitrage paths start and end in the base coin and profits are measured in it. It is SUI
+//! unless `init` installs another coin (e.g. USDC) at startup. Gas is always paid in SUI, so with
+//! another base it is converted at the SUI price last set with `set_sui_price` (see
+//! `refresh_sui_price`); until a price is set, gas is not counted.
+
+use std::{
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        OnceLock,
+    },
+};
+
+use clap::Parser;
+use eyre::{ensure, eyre, Result};
+use simulator::{SimEpoch, SimulateCtx};
+use sui_sdk::{SuiClient, SUI_COIN_TYPE};
+use sui_types::{base_types::SuiAddress, TypeTag};
+use tracing::info;
+use utils::coin;
+
+use super::{Defi, TradeType};
+
+const MIST_PER_SUI: u64 = 1_000_000_000;
+
+static BASE: OnceLock<Base> = OnceLock::new();
+// base coin units per MIST, as f64 bits; 0 means unknown
+static SUI_PRICE: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Clone, Debug, Parser)]
+pub struct BaseConfig {
+    /// Coin that arbitrage cycles start and end in and profits are measured in
+    #[arg(long, env = "SUI_BASE_COIN", default_value = SUI_COIN_TYPE)]
+    pub base_coin: String,
+
+    /// Seconds between refreshes of the SUI price used to convert gas into a non-SUI base coin
+    #[arg(long, env = "SUI_BASE_PRICE_INTERVAL", default_value_t = 60)]
+    pub base_price_interval: u64,
+}
+
+#[derive(Debug)]
+struct Base {
+    coin_type: String,
+    type_tag: TypeTag,
+    decimals: u8,
+}
+
+impl Base {
+    fn sui() -> Self {
+        Self {
+            coin_type: SUI_COIN_TYPE.to_string(),
+            type_tag: TypeTag::from_str(SUI_COIN_TYPE).unwrap(),
+            decimals: 9,
+        }
+    }
+}
+
+fn base() -> &'static Base {
+    BASE.get_or_init(Base::sui)
+}
+
+/// Install `coin_type` as the base coin. Must run before any path search; fails if the base was
+/// already set or read.
+pub fn init(coin_type: &str, decimals: u8) -> Result<()> {
+    let type_tag = TypeTag::from_str(coin_type).map_err(|e| eyre!("invalid base coin {}: {}", coin_type, e))?;
+    let base = Base {
+        coin_type: dex_indexer::normalize_coin_type(coin_type),
+        type_tag,
+        decimals,
+    };
+    BASE.set(base)
+        .map_err(|_| eyre!("base coin already set to {}", coin_type()))
+}
+
+pub fn coin_type() -> &'static str {
+    &base().coin_type
+}
+
+pub fn type_tag() -> &'static TypeTag {
+    &base().type_tag
+}
+
+pub fn decimals() -> u8 {
+    base().decimals
+}
+
+pub fn is_sui() -> bool {
+    coin::is_native_coin(coin_type())
+}
+
+pub fn is_base_coin(coin_type: &str) -> bool {
+    coin_type == self::coin_type()
+}
+
+/// Set the SUI price in base coin units per MIST.
+pub fn set_sui_price(base_per_mist: f64) {
+    SUI_PRICE.store(base_per_mist.to_bits(), Ordering::Relaxed);
+}
+
+/// Base coin units per MIST; None while unknown.
+pub fn sui_price() -> Option<f64> {
+    if is_sui() {
+        return Some(1.0);
+    }
+    let price = f64::from_bits(SUI_PRICE.load(Ordering::Relaxed));
+    (price > 0.0).then_some(price)
+}
+
+/// `gas_cost` (MIST) in base coin units.
+pub fn gas_in_base(gas_cost: i64) -> i128 {
+    if is_sui() {
+        return gas_cost as i128;
+    }
+    sui_price().map_or(0, |price| (gas_cost as f64 * price).round() as i128)
+}
+
+/// `value` in base coin units, e.g. "1.5 SUI" or "12.25 USDC".
+pub fn format_amount(value: u64) -> String {
+    if is_sui() {
+        return coin::format_sui_with_symbol(value);
+    }
+    let value = value as f64 / 10f64.powi(decimals() as i32);
+    format!("{} {}", value, crate::config::coin_label(coin_type()))
+}
+
+/// Quote 1 SUI into the base coin over the best path and install the result with
+/// `set_sui_price`. A no-op for a SUI base.
+pub async fn refresh_sui_price(defi: &Defi, sui: &SuiClient, sender: SuiAddress) -> Result<f64> {
+    if is_sui() {
+        return Ok(1.0);
+    }
+
+    let paths = defi.find_sell_paths(SUI_COIN_TYPE).await?;
+    ensure!(!paths.is_empty(), "no path from SUI to the base coin {}", coin_type());
+
+    let gas_coins = coin::get_gas_coin_refs(sui, sender, None).await?;
+    let sys_state = sui.governance_api().get_latest_sui_system_state().await?;
+    let sim_ctx = SimulateCtx::new(SimEpoch::from(sys_state), vec![]);
+    let res = defi
+        .find_best_path_exact_in(&paths, sender, MIST_PER_SUI, TradeType::Swap, &gas_coins, &sim_ctx)
+        .await?;
+    ensure!(res.amount_out > 0, "SUI quoted at zero in the base coin");
+
+    let price = res.amount_out as f64 / MIST_PER_SUI as f64;
+    set_sui_price(price);
+    info!(base_coin = coin_type(), sui_price = %format_amount(res.amount_out), "SUI price refreshed");
+    Ok(price)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sui_base_by_default() {
+        assert!(is_sui());
+        assert!(is_base_coin(SUI_COIN_TYPE));
+        assert_eq!(gas_in_base(1_234), 1_234);
+        assert_eq!(sui_price(), Some(1.0));
+    }
+}
@@ -12,17 +12,15 @@ use sui_types::{
 use tokio::sync::OnceCell;
 use utils::{coin, new_test_sui_client, object::*};
 
-use super::{TradeCtx, CETUS_AGGREGATOR};
+use super::{descriptor, upgrades, TradeCtx};
 use crate::{config::*, defi::Dex};
 
-const DEX_INFO: &str = "0x3f2d9f724f4a1ce5e71676448dc452be9a6243dac9c5b975a588c8c867066e92";
-
 static OBJ_CACHE: OnceCell<ObjectArgs> = OnceCell::const_new();
 
 async fn get_object_args(simulator: Arc<Box<dyn Simulator>>) -> ObjectArgs {
     OBJ_CACHE
         .get_or_init(|| async {
-            let id = ObjectID::from_hex_literal(DEX_INFO).unwrap();
+            let id = descriptor::object_id("blue_move", "dex_info");
             let dex_info = simulator.get_object(&id).await.unwrap();
 
             ObjectArgs {
@@ -135,7 +133,7 @@ impl Dex for BlueMove {
     ) -> Result<Argument> {
         let function = if self.is_a2b() { "swap_a2b" } else { "swap_b2a" };
 
-        let package = ObjectID::from_hex_literal(CETUS_AGGREGATOR)?;
+        let package = upgrades::package_id(descriptor::address("cetus_aggregator", "package"))?;
         let module = Identifier::new("bluemove").map_err(|e| eyre!(e))?;
         let function = Identifier::new(function).map_err(|e| eyre!(e))?;
         let type_arguments = self.type_params.clone();
@@ -211,7 +209,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_flowx_swap_tx() {
-        mev_logger::init_console_logger_with_directives(None, &["arb=debug", "dex_indexer=debug"]);
+        mev_logger::init_console_logger_with_directives(None, &["poison_dart_core=debug", "dex_indexer=debug"]);
 
         let http_simulator = HttpSimulator::new(TEST_HTTP_URL, &None).await;
 
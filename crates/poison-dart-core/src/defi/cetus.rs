@@ -1,51 +1,194 @@
-use std::sync::Arc;
+use std::{
+    collections::HashMap,
+    sync::{Arc, OnceLock},
+};
 
-use dex_indexer::types::{Pool, Protocol};
+use dex_indexer::types::{Pool, PoolExtra, Protocol};
 use eyre::{ensure, eyre, OptionExt, Result};
 use move_core_types::annotated_value::MoveStruct;
-use simulator::Simulator;
+use simulator::{SimulateCtx, Simulator};
 use sui_types::{
     base_types::{ObjectID, ObjectRef, SuiAddress},
+    object::Object,
     transaction::{Argument, Command, ObjectArg, ProgrammableTransaction, TransactionData},
     Identifier, TypeTag, SUI_CLOCK_OBJECT_ID,
 };
-use tokio::sync::OnceCell;
+use tokio::sync::Mutex;
+use tracing::warn;
 use utils::{coin, new_test_sui_client, object::*};
 
-use super::{trade::FlashResult, TradeCtx};
+use super::{
+    clmm_math::{self, Clmm, Tick, TickWindow, MAX_TICK, MIN_TICK},
+    descriptor,
+    trade::FlashResult,
+    upgrades, TradeCtx,
+};
 use crate::{config::*, defi::Dex};
 
-const CETUS_DEX: &str = "0xeffc8ae61f439bb34c9b905ff8f29ec56873dcedf81c7123ff2f1f67c45ec302";
-const CONFIG: &str = "0xdaa46292632c3c4d8f31f23ea0f9b36a28ff3677e9684980e4438403a67a3d8f";
-const PARTNER: &str = "0x639b5e433da31739e800cd085f356e64cae222966d0f1b11bd9dc76b322ff58b";
+const FEE_RATE_DENOMINATOR: f64 = 1_000_000.0;
+// of `GlobalConfig.protocol_fee_rate` and `Partner.ref_fee_rate`
+const PROTOCOL_FEE_RATE_DENOMINATOR: f64 = 10_000.0;
+// ticks returned by one `fetch_ticks` call
+const TICKS_PAGE: u64 = 512;
 
 #[derive(Clone)]
 pub struct ObjectArgs {
     config: ObjectArg,
     partner: ObjectArg,
+    protocol_fee_rate: u64,
+    partner_ref_fee_rate: u64,
     clock: ObjectArg,
 }
 
-static OBJ_CACHE: OnceCell<ObjectArgs> = OnceCell::const_new();
+// per partner, though a process trades with one
+static OBJ_CACHE: OnceLock<Mutex<HashMap<Option<ObjectID>, ObjectArgs>>> = OnceLock::new();
+
+/// The shared objects of Cetus swaps crediting `our_partner`, or the protocol's default partner.
+async fn get_object_args(simulator: Arc<Box<dyn Simulator>>, our_partner: Option<ObjectID>) -> ObjectArgs {
+    let mut cache = OBJ_CACHE.get_or_init(Default::default).lock().await;
+    if let Some(args) = cache.get(&our_partner) {
+        return args.clone();
+    }
+
+    let config_id = descriptor::object_id("cetus", "config");
+    let partner_id = our_partner.unwrap_or_else(|| descriptor::object_id("cetus", "partner"));
+
+    let config = simulator.get_object(&config_id).await.unwrap();
+    let partner = simulator.get_object(&partner_id).await.unwrap();
+    let clock = simulator.get_object(&SUI_CLOCK_OBJECT_ID).await.unwrap();
+
+    // Only a partner we own earns us anything.
+    let (protocol_fee_rate, partner_ref_fee_rate) = if our_partner.is_some() {
+        let rates = read_u64_field(&simulator, &config, "protocol_fee_rate").and_then(|protocol_fee_rate| {
+            Ok((protocol_fee_rate, read_u64_field(&simulator, &partner, "ref_fee_rate")?))
+        });
+        rates.unwrap_or_else(|error| {
+            warn!(?error, %partner_id, "failed to read the Cetus partner fee rates, no rebate is estimated");
+            (0, 0)
+        })
+    } else {
+        (0, 0)
+    };
+
+    let args = ObjectArgs {
+        config: shared_obj_arg(&config, false),
+        partner: shared_obj_arg(&partner, true),
+        protocol_fee_rate,
+        partner_ref_fee_rate,
+        clock: shared_obj_arg(&clock, false),
+    };
+    cache.insert(our_partner, args.clone());
+    args
+}
+
+/*
+struct GlobalConfig has key, store {
+    id: UID,
+    protocol_fee_rate: u64,
+    fee_tiers: VecMap<u32, FeeTier>,
+    acl: ACL,
+    package_version: u64,
+}
+
+struct Partner has key, store {
+    id: UID,
+    name: String,
+    ref_fee_rate: u64,
+    start_time: u64,
+    end_time: u64,
+    balances: Bag,
+}
+*/
+fn read_u64_field(simulator: &Arc<Box<dyn Simulator>>, object: &Object, field: &str) -> Result<u64> {
+    let layout = simulator
+        .get_object_layout(&object.id())
+        .ok_or_eyre("layout not found")?;
+    let move_obj = object.data.try_as_move().ok_or_eyre("not a move object")?;
+    let parsed = MoveStruct::simple_deserialize(move_obj.contents(), &layout).map_err(|e| eyre!(e))?;
+
+    extract_u64_from_move_struct(&parsed, field)
+}
+
+/// Share of the amount swapped that the partner is credited. Of the swap fee, the protocol takes
+/// `protocol_fee_rate`, and of that the partner gets `ref_fee_rate`, both over 10_000.
+fn partner_rebate_rate(fee_rate: u64, protocol_fee_rate: u64, ref_fee_rate: u64) -> f64 {
+    (fee_rate as f64 / FEE_RATE_DENOMINATOR) *
+        (protocol_fee_rate as f64 / PROTOCOL_FEE_RATE_DENOMINATOR) *
+        (ref_fee_rate as f64 / PROTOCOL_FEE_RATE_DENOMINATOR)
+}
+
+/*
+public fun fetch_ticks<CoinTypeA, CoinTypeB>(
+    pool: &Pool<CoinTypeA, CoinTypeB>,
+    start: vector<u32>,
+    limit: u64
+)
+emits FetchTicksResultEvent { ticks: vector<Tick> }, `limit` ticks from `start` on
+*/
+async fn fetch_ticks(
+    simulator: Arc<Box<dyn Simulator>>,
+    pool_obj: Object,
+    type_params: Vec<TypeTag>,
+) -> Result<TickWindow> {
+    let package = ObjectID::from_hex_literal(descriptor::address("cetus", "fetcher"))?;
+    let mut ticks: Vec<Tick> = vec![];
+    loop {
+        let start: Vec<u32> = ticks.last().map(|tick| tick.index as u32).into_iter().collect();
 
-async fn get_object_args(simulator: Arc<Box<dyn Simulator>>) -> ObjectArgs {
-    OBJ_CACHE
-        .get_or_init(|| async {
-            let config_id = ObjectID::from_hex_literal(CONFIG).unwrap();
-            let partner_id = ObjectID::from_hex_literal(PARTNER).unwrap();
+        let mut ctx = TradeCtx::default();
+        let arguments = vec![
+            ctx.obj(shared_obj_arg(&pool_obj, false)).map_err(|e| eyre!(e))?,
+            ctx.pure(start).map_err(|e| eyre!(e))?,
+            ctx.pure(TICKS_PAGE).map_err(|e| eyre!(e))?,
+        ];
+        let module = Identifier::new("fetcher_script").map_err(|e| eyre!(e))?;
+        let function = Identifier::new("fetch_ticks").map_err(|e| eyre!(e))?;
+        ctx.command(Command::move_call(
+            package,
+            module,
+            function,
+            type_params.clone(),
+            arguments,
+        ));
+        let tx_data = TransactionData::new_programmable(SuiAddress::ZERO, vec![], ctx.ptb.finish(), GAS_BUDGET, 1_000);
+        let res = simulator.simulate(tx_data, SimulateCtx::default()).await?;
+
+        let page = res
+            .events
+            .data
+            .iter()
+            .filter(|event| event.type_.name.as_str() == "FetchTicksResultEvent")
+            .map(|event| parse_ticks(&event.parsed_json))
+            .next()
+            .ok_or_eyre("no FetchTicksResultEvent")??;
+        let full = page.len() as u64 >= TICKS_PAGE;
+        // a page starts at the last tick of the previous one
+        let last = ticks.last().map_or(i32::MIN, |tick| tick.index);
+        let before = ticks.len();
+        ticks.extend(page.into_iter().filter(|tick| tick.index > last));
+        if !full || ticks.len() == before {
+            break;
+        }
+    }
 
-            let config = simulator.get_object(&config_id).await.unwrap();
-            let partner = simulator.get_object(&partner_id).await.unwrap();
-            let clock = simulator.get_object(&SUI_CLOCK_OBJECT_ID).await.unwrap();
+    Ok(TickWindow::new(ticks, MIN_TICK, MAX_TICK))
+}
 
-            ObjectArgs {
-                config: shared_obj_arg(&config, false),
-                partner: shared_obj_arg(&partner, true),
-                clock: shared_obj_arg(&clock, false),
-            }
+// ticks: [{ index: { bits }, liquidity_net: { bits }, .. }]
+fn parse_ticks(json: &serde_json::Value) -> Result<Vec<Tick>> {
+    json["ticks"]
+        .as_array()
+        .ok_or_eyre("missing ticks")?
+        .iter()
+        .map(|tick| {
+            let index = tick["index"]["bits"].as_u64().ok_or_eyre("missing tick index")? as u32 as i32;
+            let liquidity_net = tick["liquidity_net"]["bits"]
+                .as_str()
+                .ok_or_eyre("missing tick liquidity_net")?
+                .parse::<u128>()? as i128;
+            Ok(Tick { index, liquidity_net })
         })
-        .await
-        .clone()
+        .collect()
 }
 
 #[derive(Clone)]
@@ -53,16 +196,29 @@ pub struct Cetus {
     pool: Pool,
     pool_arg: ObjectArg,
     liquidity: u128,
+    sqrt_price: u128,
+    current_tick: i32,
+    // None until the pool's ticks were fetched
+    ticks: Option<Arc<TickWindow>>,
     coin_in_type: String,
     coin_out_type: String,
     type_params: Vec<TypeTag>,
+    fee_rate: u64,
     config: ObjectArg,
     partner: ObjectArg,
+    protocol_fee_rate: u64,
+    partner_ref_fee_rate: u64,
     clock: ObjectArg,
 }
 
 impl Cetus {
-    pub async fn new(simulator: Arc<Box<dyn Simulator>>, pool: &Pool, coin_in_type: &str) -> Result<Self> {
+    /// The edge of `pool` leaving `coin_in_type`, whose swaps credit `partner` when set.
+    pub async fn new(
+        simulator: Arc<Box<dyn Simulator>>,
+        pool: &Pool,
+        coin_in_type: &str,
+        partner: Option<ObjectID>,
+    ) -> Result<Self> {
         ensure!(pool.protocol == Protocol::Cetus, "not a Cetus pool");
 
         let pool_obj = simulator
@@ -83,6 +239,11 @@ impl Cetus {
         ensure!(!is_pause, "pool is paused");
 
         let liquidity = extract_u128_from_move_struct(&parsed_pool, "liquidity")?;
+        let sqrt_price = extract_u128_from_move_struct(&parsed_pool, "current_sqrt_price")?;
+        let current_tick = {
+            let index = extract_struct_from_move_struct(&parsed_pool, "current_tick_index")?;
+            extract_u32_from_move_struct(&index, "bits")? as i32
+        };
 
         let coin_out_type = if pool.token0_type() == coin_in_type {
             pool.token1_type().to_string()
@@ -91,19 +252,40 @@ impl Cetus {
         };
 
         let type_params = parsed_pool.type_.type_params.clone();
+        let ticks = clmm_math::ticks(
+            pool.pool,
+            fetch_ticks(Arc::clone(&simulator), pool_obj.clone(), type_params.clone()),
+        );
+
+        let fee_rate = match pool.extra {
+            PoolExtra::Cetus { fee_rate } => fee_rate,
+            _ => 0,
+        };
 
         let pool_arg = shared_obj_arg(&pool_obj, true);
-        let ObjectArgs { config, partner, clock } = get_object_args(simulator).await;
+        let ObjectArgs {
+            config,
+            partner,
+            protocol_fee_rate,
+            partner_ref_fee_rate,
+            clock,
+        } = get_object_args(simulator, partner).await;
 
         Ok(Self {
             pool: pool.clone(),
             liquidity,
+            sqrt_price,
+            current_tick,
+            ticks,
             coin_in_type: coin_in_type.to_string(),
             coin_out_type,
             type_params,
+            fee_rate,
             pool_arg,
             config,
             partner,
+            protocol_fee_rate,
+            partner_ref_fee_rate,
             clock,
         })
     }
@@ -200,7 +382,7 @@ impl Dex for Cetus {
             "flash_swap_b2a"
         };
 
-        let package = ObjectID::from_hex_literal(CETUS_DEX)?;
+        let package = upgrades::package_id(descriptor::address("cetus", "package"))?;
         let module = Identifier::new("cetus").map_err(|e| eyre!(e))?;
         let function = Identifier::new(function).map_err(|e| eyre!(e))?;
         let type_arguments = self.type_params.clone();
@@ -223,7 +405,7 @@ impl Dex for Cetus {
             "repay_flash_swap_b2a"
         };
 
-        let package = ObjectID::from_hex_literal(CETUS_DEX)?;
+        let package = upgrades::package_id(descriptor::address("cetus", "package"))?;
         let module = Identifier::new("cetus").map_err(|e| eyre!(e))?;
         let function = Identifier::new(function).map_err(|e| eyre!(e))?;
         let type_arguments = self.type_params.clone();
@@ -243,7 +425,7 @@ impl Dex for Cetus {
     ) -> Result<Argument> {
         let function = if self.is_a2b() { "swap_a2b" } else { "swap_b2a" };
 
-        let package = ObjectID::from_hex_literal(CETUS_DEX)?;
+        let package = upgrades::package_id(descriptor::address("cetus", "package"))?;
         let module = Identifier::new("cetus").map_err(|e| eyre!(e))?;
         let function = Identifier::new(function).map_err(|e| eyre!(e))?;
         let type_arguments = self.type_params.clone();
@@ -274,10 +456,31 @@ impl Dex for Cetus {
         self.pool.pool
     }
 
+    fn pool_arg(&self) -> Option<ObjectArg> {
+        Some(self.pool_arg.clone())
+    }
+
     fn flip(&mut self) {
         std::mem::swap(&mut self.coin_in_type, &mut self.coin_out_type);
     }
 
+    fn partner_rebate_rate(&self) -> f64 {
+        partner_rebate_rate(self.fee_rate, self.protocol_fee_rate, self.partner_ref_fee_rate)
+    }
+
+    fn quote(&self, amount_in: u64) -> Option<u64> {
+        let pool = Clmm {
+            sqrt_price: self.sqrt_price,
+            current_tick: self.current_tick,
+            liquidity: self.liquidity,
+            fee_num: self.fee_rate,
+            fee_den: FEE_RATE_DENOMINATOR as u64,
+            a2b: self.is_a2b(),
+            ticks: Arc::clone(self.ticks.as_ref()?),
+        };
+        pool.amount_out(amount_in)
+    }
+
     fn is_a2b(&self) -> bool {
         self.pool.token_index(&self.coin_in_type) == Some(0)
     }
@@ -320,7 +523,7 @@ mod tests {
     // cargo test --package arb --bin arb --all-features -- defi::cetus::tests::test_cetus_swap_tx --exact --show-output
     #[tokio::test]
     async fn test_cetus_swap_tx() {
-        mev_logger::init_console_logger_with_directives(None, &["arb=debug", "dex_indexer=debug"]);
+        mev_logger::init_console_logger_with_directives(None, &["poison_dart_core=debug", "dex_indexer=debug"]);
 
         let owner = SuiAddress::from_str(TEST_ATTACKER).unwrap();
         let recipient =
@@ -369,4 +572,21 @@ mod tests {
         let db_res = db_sim.simulate(tx_data, ctx).await.unwrap();
         info!("🧀 DB simulate cost {:?}, {:?}", start.elapsed(), db_res);
     }
+
+    #[test]
+    fn test_partner_rebate_rate() {
+        // mainnet: 0.25% pool, GlobalConfig.protocol_fee_rate 2000, Partner.ref_fee_rate 1000
+        let (fee_rate, protocol_fee_rate, ref_fee_rate) = (2500, 2000, 1000);
+        let amount_in = 1_000_000_000u64;
+
+        // as in pool::swap_in_pool and pool::flash_swap_internal
+        let fee_amount = amount_in * fee_rate / 1_000_000;
+        let protocol_fee = (fee_amount * protocol_fee_rate).div_ceil(10_000);
+        let ref_fee = protocol_fee * ref_fee_rate / 10_000;
+        assert_eq!(ref_fee, 50_000);
+
+        let rate = partner_rebate_rate(fee_rate, protocol_fee_rate, ref_fee_rate);
+        assert_eq!((amount_in as f64 * rate).round() as u64, ref_fee);
+        assert_eq!(partner_rebate_rate(fee_rate, protocol_fee_rate, 0), 0.0);
+    }
 }
@@ -0,0 +1,257 @@
+//! In-process quoting for concentrated-liquidity pools (Cetus, Turbos).
+//!
+//! Between two initialized ticks a CLMM is a constant-product curve over its active liquidity `L`:
+//! moving the price from `√P` down to `√P'` takes `L * (1/√P' - 1/√P)` of coin A in and gives
+//! `L * (√P - √P')` of coin B out, and crossing a tick adds or removes its `liquidity_net`. `Clmm`
+//! walks a swap tick by tick from the pool's price over the ticks fetched around it, in floating
+//! point: good for weighting edges and discarding paths, never for what gets submitted. A swap
+//! running past the fetched ticks isn't quoted.
+//!
+//! Fetching ticks takes several object reads, so they are cached per pool and fetched in the
+//! background (`ticks`): a pool quotes once its ticks arrived. Swaps move the price but leave
+//! every tick's `liquidity_net` alone, only liquidity changes do, so the cache lives for a while.
+
+use std::{
+    collections::HashSet,
+    future::Future,
+    sync::{Arc, Mutex, OnceLock},
+    time::Duration,
+};
+
+use cached::{Cached, TimedSizedCache};
+use eyre::Result;
+use sui_types::base_types::ObjectID;
+use tracing::debug;
+use utils::cache::{CacheLimits, CacheStats};
+
+pub const MIN_TICK: i32 = -443_636;
+pub const MAX_TICK: i32 = 443_636;
+
+const TICKS_CACHE_SIZE: usize = 5_000;
+const TICKS_TTL: Duration = Duration::from_secs(300);
+// Q64.64 fixed point of on-chain sqrt prices
+const Q64: f64 = 18_446_744_073_709_551_616.0;
+
+static TICKS: OnceLock<Mutex<TimedSizedCache<ObjectID, Arc<TickWindow>>>> = OnceLock::new();
+// pools whose ticks are being fetched
+static FETCHING: OnceLock<Mutex<HashSet<ObjectID>>> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tick {
+    pub index: i32,
+    /// Liquidity added when the price crosses the tick upwards, removed downwards
+    pub liquidity_net: i128,
+}
+
+/// The initialized ticks of a pool from `lower` to `upper`, sorted by index. Ticks outside the
+/// range weren't fetched.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TickWindow {
+    pub ticks: Vec<Tick>,
+    pub lower: i32,
+    pub upper: i32,
+}
+
+impl TickWindow {
+    pub fn new(mut ticks: Vec<Tick>, lower: i32, upper: i32) -> Self {
+        ticks.sort_by_key(|tick| tick.index);
+        Self {
+            ticks,
+            lower: lower.max(MIN_TICK),
+            upper: upper.min(MAX_TICK),
+        }
+    }
+}
+
+/// One direction of a CLMM pool at its current state.
+#[derive(Debug, Clone)]
+pub struct Clmm {
+    /// Q64.64 square root of the price of coin A in coin B
+    pub sqrt_price: u128,
+    pub current_tick: i32,
+    pub liquidity: u128,
+    pub fee_num: u64,
+    pub fee_den: u64,
+    /// Coin A in, the price falls
+    pub a2b: bool,
+    pub ticks: Arc<TickWindow>,
+}
+
+impl Clmm {
+    /// Output for `amount_in`, with the fee taken off the input. None when the swap would cross
+    /// ticks that weren't fetched.
+    pub fn amount_out(&self, amount_in: u64) -> Option<u64> {
+        if self.fee_den == 0 || self.fee_num >= self.fee_den {
+            return None;
+        }
+        let fee = amount_in as u128 * self.fee_num as u128 / self.fee_den as u128;
+        let mut remaining = (amount_in as u128 - fee) as f64;
+        let mut sqrt_price = self.sqrt_price as f64 / Q64;
+        let mut liquidity = self.liquidity as f64;
+        let mut amount_out = 0.0;
+
+        // the ticks the swap crosses, nearest first, then the edge of the window
+        let ticks = &self.ticks.ticks;
+        let split = ticks.partition_point(|tick| tick.index <= self.current_tick);
+        let steps: Vec<(i32, f64)> = if self.a2b {
+            ticks[..split]
+                .iter()
+                .rev()
+                .map(|tick| (tick.index, -(tick.liquidity_net as f64)))
+                .chain([(self.ticks.lower, 0.0)])
+                .collect()
+        } else {
+            ticks[split..]
+                .iter()
+                .map(|tick| (tick.index, tick.liquidity_net as f64))
+                .chain([(self.ticks.upper, 0.0)])
+                .collect()
+        };
+
+        for (index, liquidity_delta) in steps {
+            if remaining <= 0.0 {
+                break;
+            }
+            let target = tick_sqrt_price(index);
+            if liquidity > 0.0 {
+                if self.a2b {
+                    let to_target = liquidity * (1.0 / target - 1.0 / sqrt_price);
+                    if remaining <= to_target {
+                        let next = 1.0 / (1.0 / sqrt_price + remaining / liquidity);
+                        amount_out += liquidity * (sqrt_price - next);
+                        remaining = 0.0;
+                        break;
+                    }
+                    remaining -= to_target.max(0.0);
+                    amount_out += liquidity * (sqrt_price - target).max(0.0);
+                } else {
+                    let to_target = liquidity * (target - sqrt_price);
+                    if remaining <= to_target {
+                        let next = sqrt_price + remaining / liquidity;
+                        amount_out += liquidity * (1.0 / sqrt_price - 1.0 / next);
+                        remaining = 0.0;
+                        break;
+                    }
+                    remaining -= to_target.max(0.0);
+                    amount_out += liquidity * (1.0 / sqrt_price - 1.0 / target).max(0.0);
+                }
+            }
+            sqrt_price = target;
+            liquidity = (liquidity + liquidity_delta).max(0.0);
+        }
+
+        // ran past the window
+        if remaining > 0.0 {
+            return None;
+        }
+        Some(amount_out.floor().min(u64::MAX as f64) as u64)
+    }
+}
+
+/// `√(1.0001^index)`, the price at a tick.
+pub fn tick_sqrt_price(index: i32) -> f64 {
+    1.0001f64.powf(index as f64 / 2.0)
+}
+
+fn ticks_cache() -> &'static Mutex<TimedSizedCache<ObjectID, Arc<TickWindow>>> {
+    TICKS.get_or_init(|| {
+        let limits = CacheLimits::from_env("TICKS", TICKS_CACHE_SIZE, TICKS_TTL);
+        Mutex::new(limits.new_cache())
+    })
+}
+
+/// The cached ticks of `pool`. On a miss `fetch` is spawned to fill the cache, unless a fetch of
+/// the pool is already running, and None is returned.
+pub fn ticks<F>(pool: ObjectID, fetch: F) -> Option<Arc<TickWindow>>
+where
+    F: Future<Output = Result<TickWindow>> + Send + 'static,
+{
+    if let Some(ticks) = ticks_cache().lock().unwrap().cache_get(&pool) {
+        return Some(Arc::clone(ticks));
+    }
+    if !FETCHING.get_or_init(Default::default).lock().unwrap().insert(pool) {
+        return None;
+    }
+    tokio::spawn(async move {
+        match fetch.await {
+            Ok(ticks) => {
+                ticks_cache().lock().unwrap().cache_set(pool, Arc::new(ticks));
+            }
+            Err(error) => debug!(%pool, ?error, "failed to fetch ticks"),
+        }
+        FETCHING.get_or_init(Default::default).lock().unwrap().remove(&pool);
+    });
+    None
+}
+
+pub fn cache_stats() -> CacheStats {
+    CacheStats::of("ticks", &*ticks_cache().lock().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::defi::cp_math::ConstantProduct;
+
+    #[test]
+    fn test_clmm_quote_across_ticks() {
+        let liquidity = 1_000_000_000u128;
+        // price 1, 0.3% fee, full-range liquidity
+        let pool = Clmm {
+            sqrt_price: Q64 as u128,
+            current_tick: 0,
+            liquidity,
+            fee_num: 3_000,
+            fee_den: 1_000_000,
+            a2b: true,
+            ticks: Arc::new(TickWindow::new(vec![], MIN_TICK, MAX_TICK)),
+        };
+        // within one range the curve is constant product over the virtual reserves
+        let cp = ConstantProduct {
+            reserve_in: liquidity as u64,
+            reserve_out: liquidity as u64,
+            fee_num: 3_000,
+            fee_den: 1_000_000,
+        };
+        for amount_in in [1_000, 1_000_000, 100_000_000] {
+            let out = pool.amount_out(amount_in).unwrap();
+            assert!(out.abs_diff(cp.amount_out(amount_in)) <= 1, "{}", amount_in);
+            let b2a = Clmm {
+                a2b: false,
+                ..pool.clone()
+            };
+            let out = b2a.amount_out(amount_in).unwrap();
+            assert!(out.abs_diff(cp.amount_out(amount_in)) <= 1, "{}", amount_in);
+        }
+
+        // the liquidity ends 100 ticks below the price, about 0.5%
+        let ranged = Clmm {
+            ticks: Arc::new(TickWindow::new(
+                vec![
+                    Tick {
+                        index: -100,
+                        liquidity_net: liquidity as i128,
+                    },
+                    Tick {
+                        index: 100,
+                        liquidity_net: -(liquidity as i128),
+                    },
+                ],
+                MIN_TICK,
+                MAX_TICK,
+            )),
+            ..pool.clone()
+        };
+        assert_eq!(ranged.amount_out(1_000_000), pool.amount_out(1_000_000));
+        // selling 1% of the reserves crosses out of the range, nothing is left to fill the rest
+        assert_eq!(ranged.amount_out(10_000_000), None);
+
+        // nor are ticks past the fetched window known
+        let windowed = Clmm {
+            ticks: Arc::new(TickWindow::new(vec![], -50, 50)),
+            ..pool
+        };
+        assert!(windowed.amount_out(1_000_000).is_some());
+        assert_eq!(windowed.amount_out(10_000_000), None);
+    }
+}
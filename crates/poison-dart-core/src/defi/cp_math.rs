@@ -0,0 +1,133 @@
+//! In-process quoting for constant-product (x * y = k) pools.
+//!
+//! A constant-product swap is exact arithmetic over the two reserves and the pool's fee, so it can
+//! be quoted without a simulator round-trip. A path made only of such pools composes into a single
+//! curve `out = a * x / (b + c * x)`, whose most profitable input has a closed form. The arb search
+//! uses it to size a trade and then simulates only that size; the simulator stays the source of
+//! truth for what actually gets submitted.
+
+use super::{Dex, Path};
+
+/// One direction of a constant-product pool: reserves oriented coin_in -> coin_out, and the share
+/// of the input the pool keeps as fees (`fee_num / fee_den`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConstantProduct {
+    pub reserve_in: u64,
+    pub reserve_out: u64,
+    pub fee_num: u64,
+    pub fee_den: u64,
+}
+
+impl ConstantProduct {
+    /// Exact output for `amount_in`, with the fee rounded down as it is charged on chain.
+    pub fn amount_out(&self, amount_in: u64) -> u64 {
+        let fee = amount_in as u128 * self.fee_num as u128 / self.fee_den as u128;
+        let amount_in = amount_in as u128 - fee;
+        let denominator = self.reserve_in as u128 + amount_in;
+        if denominator == 0 {
+            return 0;
+        }
+        (self.reserve_out as u128 * amount_in / denominator) as u64
+    }
+
+    fn fee_multiplier(&self) -> f64 {
+        1.0 - self.fee_num as f64 / self.fee_den as f64
+    }
+}
+
+/// `out = a * x / (b + c * x)`, the composition of the constant-product pools of a path.
+#[derive(Debug, Clone, Copy)]
+pub struct Curve {
+    a: f64,
+    b: f64,
+    c: f64,
+}
+
+impl Curve {
+    const IDENTITY: Self = Self { a: 1.0, b: 1.0, c: 0.0 };
+
+    /// The curve of `path`, if every hop of it is a constant-product pool.
+    pub fn of(path: &Path) -> Option<Self> {
+        if path.is_empty() {
+            return None;
+        }
+        path.path
+            .iter()
+            .try_fold(Self::IDENTITY, |curve, dex| Some(curve.then(&dex.constant_product()?)))
+    }
+
+    // f2(f1(x)) = a1 a2 x / (b1 b2 + (b2 c1 + a1 c2) x)
+    fn then(self, pool: &ConstantProduct) -> Self {
+        let gamma = pool.fee_multiplier();
+        let (a2, b2, c2) = (pool.reserve_out as f64 * gamma, pool.reserve_in as f64, gamma);
+        Self {
+            a: self.a * a2,
+            b: self.b * b2,
+            c: b2 * self.c + self.a * c2,
+        }
+    }
+
+    pub fn amount_out(&self, amount_in: f64) -> f64 {
+        self.a * amount_in / (self.b + self.c * amount_in)
+    }
+
+    /// The input maximizing `amount_out - amount_in` of a cycle, None if no input is profitable.
+    pub fn best_amount_in(&self) -> Option<u64> {
+        // the marginal rate a / b at zero input must beat 1
+        if self.c <= 0.0 || self.a <= self.b {
+            return None;
+        }
+        let amount_in = ((self.a * self.b).sqrt() - self.b) / self.c;
+        (amount_in >= 1.0).then(|| amount_in.min(u64::MAX as f64) as u64)
+    }
+}
+
+/// Exact output of `path` for `amount_in`, if every hop is a constant-product pool.
+pub fn path_amount_out(path: &Path, amount_in: u64) -> Option<u64> {
+    path.path.iter().try_fold(amount_in, |amount, dex| {
+        Some(dex.constant_product()?.amount_out(amount))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_constant_product_quote_and_sizing() {
+        // 0.3% fee, 1:1 pool
+        let pool = ConstantProduct {
+            reserve_in: 1_000_000,
+            reserve_out: 1_000_000,
+            fee_num: 3_000,
+            fee_den: 1_000_000,
+        };
+        // 1000 in, 3 kept as fee: 1_000_000 * 997 / 1_000_997
+        assert_eq!(pool.amount_out(1_000), 996);
+        assert_eq!(pool.amount_out(0), 0);
+
+        let cheap = ConstantProduct {
+            reserve_in: 1_000_000,
+            reserve_out: 2_000_000,
+            ..pool
+        };
+        let dear = ConstantProduct {
+            reserve_in: 1_000_000,
+            reserve_out: 1_000_000,
+            ..pool
+        };
+        let curve = Curve::IDENTITY.then(&cheap).then(&dear);
+        // the composed curve agrees with quoting hop by hop
+        let hop_by_hop = dear.amount_out(cheap.amount_out(10_000)) as f64;
+        assert!((curve.amount_out(10_000.0) - hop_by_hop).abs() < 2.0);
+
+        let best = curve.best_amount_in().unwrap();
+        let profit = |x: u64| dear.amount_out(cheap.amount_out(x)) as i64 - x as i64;
+        assert!(profit(best) > 0);
+        assert!(profit(best) >= profit(best / 2) && profit(best) >= profit(best * 2));
+
+        // the way back loses to the fee
+        let back = Curve::IDENTITY.then(&pool).then(&pool);
+        assert_eq!(back.best_amount_in(), None);
+    }
+}
@@ -0,0 +1,536 @@
+use std::sync::Arc;
+
+use dex_indexer::types::{Pool, PoolExtra, Protocol};
+use eyre::{ensure, eyre, OptionExt, Result};
+use move_core_types::annotated_value::MoveStruct;
+use simulator::Simulator;
+use sui_types::{
+    base_types::{ObjectID, ObjectRef, SuiAddress},
+    transaction::{Argument, Command, ObjectArg, ProgrammableTransaction, TransactionData},
+    Identifier, TypeTag, DEEPBOOK_PACKAGE_ID, SUI_CLOCK_OBJECT_ID,
+};
+use tokio::sync::OnceCell;
+use utils::{coin, new_test_sui_client, object::shared_obj_arg};
+
+use super::{descriptor, TradeCtx};
+use crate::{config::*, defi::Dex};
+
+const CLIENT_ORDER_ID: u64 = 0;
+// A quote-in swap counts as filled when at most 1/MAX_QUOTE_LEFTOVER_DIVISOR of its quote is left.
+// The book can't take quote worth less than a lot at the next ask, so some is always left.
+const MAX_QUOTE_LEFTOVER_DIVISOR: u64 = 1_000;
+
+#[derive(Clone)]
+pub struct ObjectArgs {
+    clock: ObjectArg,
+    account_cap: ObjectArg,
+}
+
+static OBJ_CACHE: OnceCell<ObjectArgs> = OnceCell::const_new();
+
+async fn get_object_args(simulator: Arc<Box<dyn Simulator>>) -> ObjectArgs {
+    OBJ_CACHE
+        .get_or_init(|| async {
+            let account_cap_id = descriptor::object_id("deepbook_v2", "account_cap");
+            let account_cap = simulator.get_object(&account_cap_id).await.unwrap();
+
+            let clock = simulator.get_object(&SUI_CLOCK_OBJECT_ID).await.unwrap();
+            ObjectArgs {
+                clock: shared_obj_arg(&clock, false),
+                account_cap: ObjectArg::ImmOrOwnedObject(account_cap.compute_object_reference()),
+            }
+        })
+        .await
+        .clone()
+}
+
+#[derive(Clone)]
+pub struct DeepbookV2 {
+    pool: Pool,
+    pool_arg: ObjectArg,
+    coin_in_type: String,
+    coin_out_type: String,
+    type_params: Vec<TypeTag>,
+    lot_size: u64,
+    clock: ObjectArg,
+    account_cap: ObjectArg,
+}
+
+impl DeepbookV2 {
+    pub async fn new(simulator: Arc<Box<dyn Simulator>>, pool: &Pool, coin_in_type: &str) -> Result<Self> {
+        ensure!(pool.protocol == Protocol::DeepbookV2, "not a DeepbookV2 pool");
+
+        let pool_obj = simulator
+            .get_object(&pool.pool)
+            .await
+            .ok_or_else(|| eyre!("pool not found: {}", pool.pool))?;
+
+        let parsed_pool = {
+            let layout = simulator
+                .get_object_layout(&pool.pool)
+                .ok_or_eyre("pool layout not found")?;
+
+            let move_obj = pool_obj.data.try_as_move().ok_or_eyre("not a move object")?;
+            MoveStruct::simple_deserialize(move_obj.contents(), &layout).map_err(|e| eyre!(e))?
+        };
+
+        let coin_out_type = if let Some(0) = pool.token_index(coin_in_type) {
+            pool.token1_type()
+        } else {
+            pool.token0_type()
+        };
+
+        let type_params = parsed_pool.type_.type_params.clone();
+        let lot_size = match pool.extra {
+            PoolExtra::DeepbookV2 { lot_size, .. } => lot_size,
+            _ => 1,
+        };
+        ensure!(lot_size > 0, "invalid lot_size");
+
+        let pool_arg = shared_obj_arg(&pool_obj, true);
+        let ObjectArgs { clock, account_cap } = get_object_args(simulator).await;
+
+        Ok(Self {
+            pool: pool.clone(),
+            pool_arg,
+            coin_in_type: coin_in_type.to_string(),
+            coin_out_type,
+            type_params,
+            lot_size,
+            clock,
+            account_cap,
+        })
+    }
+
+    async fn swap_tx(
+        &self,
+        sender: SuiAddress,
+        recipient: SuiAddress,
+        coin_in: ObjectRef,
+        amount_in: u64,
+    ) -> Result<ProgrammableTransaction> {
+        let mut ctx = TradeCtx::default();
+
+        let coin_in = ctx.split_coin(coin_in, amount_in)?;
+        let coin_out = self.extend_trade_tx(&mut ctx, sender, coin_in, Some(amount_in)).await?;
+        ctx.transfer_arg(recipient, coin_out);
+
+        Ok(ctx.ptb.finish())
+    }
+
+    /*
+    public fun swap_exact_base_for_quote<BaseAsset, QuoteAsset>(
+        pool: &mut Pool<BaseAsset, QuoteAsset>,
+        client_order_id: u64,
+        account_cap: &AccountCap,
+        quantity: u64,
+        base_coin: Coin<BaseAsset>,
+        quote_coin: Coin<QuoteAsset>,
+        clock: &Clock,
+        ctx: &mut TxContext,
+    ): (Coin<BaseAsset>, Coin<QuoteAsset>, u64)
+
+    public fun swap_exact_quote_for_base<BaseAsset, QuoteAsset>(
+        pool: &mut Pool<BaseAsset, QuoteAsset>,
+        client_order_id: u64,
+        account_cap: &AccountCap,
+        quantity: u64,
+        clock: &Clock,
+        quote_coin: Coin<QuoteAsset>,
+        ctx: &mut TxContext,
+    ): (Coin<BaseAsset>, Coin<QuoteAsset>, u64)
+    */
+    /// Market swaps against the book, fill-or-revert: the PTB aborts unless the whole base
+    /// quantity filled, or all but dust of the quote.
+    async fn extend_clob_trade_tx(
+        &self,
+        ctx: &mut TradeCtx,
+        sender: SuiAddress,
+        coin_in: Argument,
+        amount_in: Option<u64>,
+    ) -> Result<Argument> {
+        let base_type = self.type_params[0].clone();
+        let quote_type = self.type_params[1].clone();
+        let is_base_in = self.is_a2b();
+        let coin_in_type = if is_base_in {
+            base_type.clone()
+        } else {
+            quote_type.clone()
+        };
+
+        // Quantities are rounded down to a multiple of lot_size. When the amount is known we split
+        // off the rounded quantity and hand the dust back; otherwise we trade the whole coin and
+        // let clob_v2 abort on a base quantity that isn't a multiple.
+        let (coin_in, quantity, quantity_arg) = match amount_in {
+            Some(amount_in) => {
+                let quantity = amount_in / self.lot_size * self.lot_size;
+                ensure!(quantity > 0, "amount_in below lot_size");
+                let quantity_arg = ctx.pure(quantity).map_err(|e| eyre!(e))?;
+                if quantity < amount_in {
+                    let exact = ctx.split_coin_arg(coin_in, quantity_arg);
+                    ctx.transfer_arg(sender, coin_in);
+                    (exact, Some(quantity), quantity_arg)
+                } else {
+                    (coin_in, Some(quantity), quantity_arg)
+                }
+            }
+            None => {
+                let quantity_arg = ctx.coin_value(coin_in, coin_in_type.clone())?;
+                (coin_in, None, quantity_arg)
+            }
+        };
+
+        let pool_arg = ctx.obj(self.pool_arg).map_err(|e| eyre!(e))?;
+        let client_order_id = ctx.pure(CLIENT_ORDER_ID).map_err(|e| eyre!(e))?;
+        let account_cap_arg = ctx.obj(self.account_cap).map_err(|e| eyre!(e))?;
+        let clock_arg = ctx.obj(self.clock).map_err(|e| eyre!(e))?;
+
+        let (function, arguments) = if is_base_in {
+            let quote_zero = ctx.coin_zero(quote_type)?;
+            (
+                "swap_exact_base_for_quote",
+                vec![
+                    pool_arg,
+                    client_order_id,
+                    account_cap_arg,
+                    quantity_arg,
+                    coin_in,
+                    quote_zero,
+                    clock_arg,
+                ],
+            )
+        } else {
+            (
+                "swap_exact_quote_for_base",
+                vec![
+                    pool_arg,
+                    client_order_id,
+                    account_cap_arg,
+                    quantity_arg,
+                    clock_arg,
+                    coin_in,
+                ],
+            )
+        };
+
+        let module = Identifier::new("clob_v2").map_err(|e| eyre!(e))?;
+        let function = Identifier::new(function).map_err(|e| eyre!(e))?;
+        let type_arguments = self.type_params.clone();
+        ctx.command(Command::move_call(
+            DEEPBOOK_PACKAGE_ID,
+            module,
+            function,
+            type_arguments,
+            arguments,
+        ));
+
+        let last_idx = ctx.last_command_idx();
+        if is_base_in {
+            // returns the unfilled base, the quote and the base quantity filled
+            let (leftover, coin_out) = (Argument::NestedResult(last_idx, 0), Argument::NestedResult(last_idx, 1));
+            let filled = Argument::NestedResult(last_idx, 2);
+            ctx.assert_at_most(quantity_arg, filled)?;
+            // the coin held exactly the quantity, so once it all filled nothing is left
+            ctx.coin_destroy_zero(leftover, coin_in_type)?;
+            Ok(coin_out)
+        } else {
+            // returns the base, the unspent quote and the base quantity filled
+            let (coin_out, leftover) = (Argument::NestedResult(last_idx, 0), Argument::NestedResult(last_idx, 1));
+            let max_leftover = match quantity {
+                Some(quantity) => ctx
+                    .pure(quantity.div_ceil(MAX_QUOTE_LEFTOVER_DIVISOR))
+                    .map_err(|e| eyre!(e))?,
+                None => {
+                    let divisor = ctx.pure(MAX_QUOTE_LEFTOVER_DIVISOR).map_err(|e| eyre!(e))?;
+                    ctx.u64_divide_and_round_up(quantity_arg, divisor)?
+                }
+            };
+            let leftover_value = ctx.coin_value(leftover, coin_in_type)?;
+            ctx.assert_at_most(leftover_value, max_leftover)?;
+            ctx.transfer_arg(sender, leftover);
+            Ok(coin_out)
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Dex for DeepbookV2 {
+    async fn extend_trade_tx(
+        &self,
+        ctx: &mut TradeCtx,
+        sender: SuiAddress,
+        coin_in: Argument,
+        amount_in: Option<u64>,
+    ) -> Result<Argument> {
+        self.extend_clob_trade_tx(ctx, sender, coin_in, amount_in).await
+    }
+
+    fn coin_in_type(&self) -> String {
+        self.coin_in_type.clone()
+    }
+
+    fn coin_out_type(&self) -> String {
+        self.coin_out_type.clone()
+    }
+
+    fn protocol(&self) -> Protocol {
+        Protocol::DeepbookV2
+    }
+
+    fn liquidity(&self) -> u128 {
+        0
+    }
+
+    fn object_id(&self) -> ObjectID {
+        self.pool.pool
+    }
+
+    fn pool_arg(&self) -> Option<ObjectArg> {
+        Some(self.pool_arg.clone())
+    }
+
+    fn flip(&mut self) {
+        std::mem::swap(&mut self.coin_in_type, &mut self.coin_out_type);
+    }
+
+    fn is_a2b(&self) -> bool {
+        self.pool.token_index(&self.coin_in_type) == Some(0)
+    }
+
+    // For testing
+    async fn swap_tx(&self, sender: SuiAddress, recipient: SuiAddress, amount_in: u64) -> Result<TransactionData> {
+        let sui = new_test_sui_client().await;
+
+        let coin_in = coin::get_coin(&sui, sender, &self.coin_in_type, amount_in).await?;
+
+        let pt = self.swap_tx(sender, recipient, coin_in.object_ref(), amount_in).await?;
+
+        let gas_coins = coin::get_gas_coin_refs(&sui, sender, Some(coin_in.coin_object_id)).await?;
+        let gas_price = sui.read_api().get_reference_gas_price().await?;
+        let tx_data = TransactionData::new_programmable(sender, gas_coins, pt, GAS_BUDGET, gas_price);
+
+        Ok(tx_data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use dex_indexer::types::Token;
+    use itertools::Itertools;
+    use object_pool::ObjectPool;
+    use simulator::DBSimulator;
+    use simulator::HttpSimulator;
+    use simulator::Simulator;
+    use sui_types::{base_types::random_object_ref, transaction::ProgrammableMoveCall};
+    use tracing::info;
+
+    use super::*;
+    use crate::{
+        config::tests::TEST_HTTP_URL,
+        defi::{indexer_searcher::IndexerDexSearcher, DexSearcher},
+    };
+
+    #[tokio::test]
+    async fn test_deepbookv2_swap_tx() {
+        mev_logger::init_console_logger_with_directives(None, &["poison_dart_core=debug", "dex_indexer=debug"]);
+
+        let http_simulator = HttpSimulator::new(TEST_HTTP_URL, &None).await;
+
+        let owner = SuiAddress::from_str("0xc0f620f28826593835606e174e6e9912c342101920519a1e376957691178e345").unwrap();
+        let recipient =
+            SuiAddress::from_str("0x0cbe287984143ef232336bb39397bd10607fa274707e8d0f91016dceb31bb829").unwrap();
+        let token_in_type = "0x2::sui::SUI";
+        let token_out_type = "0x5d4b302506645c37ff133b98c4b50a5ae14841659738d6d733d59d0d217a93bf::coin::COIN";
+        let amount_in = 10000;
+
+        let simulator_pool = Arc::new(ObjectPool::new(1, move || {
+            tokio::runtime::Runtime::new()
+                .unwrap()
+                .block_on(async { Box::new(DBSimulator::new_test(true).await) as Box<dyn Simulator> })
+        }));
+
+        // find dexes and swap
+        let searcher = IndexerDexSearcher::new(TEST_HTTP_URL, simulator_pool).await.unwrap();
+        let dexes = searcher
+            .find_dexes(token_in_type, Some(token_out_type.into()))
+            .await
+            .unwrap();
+        info!("🧀 dexes_len: {}", dexes.len());
+        let dex = dexes
+            .into_iter()
+            .filter(|dex| dex.protocol() == Protocol::DeepbookV2)
+            .sorted_by(|a, b| a.liquidity().cmp(&b.liquidity()))
+            .last()
+            .unwrap();
+        let tx_data = dex.swap_tx(owner, recipient, amount_in).await.unwrap();
+        info!("🧀 tx_data: {:?}", tx_data);
+
+        let response = http_simulator.simulate(tx_data, Default::default()).await.unwrap();
+        info!("🧀 {:?}", response);
+    }
+
+    const BASE: &str = "0x2::sui::SUI";
+    const QUOTE: &str = "0x5d4b302506645c37ff133b98c4b50a5ae14841659738d6d733d59d0d217a93bf::coin::COIN";
+
+    fn test_pool(coin_in_type: &str) -> DeepbookV2 {
+        let pool_id = ObjectID::random();
+        let pool = Pool {
+            protocol: Protocol::DeepbookV2,
+            pool: pool_id,
+            tokens: [BASE, QUOTE]
+                .into_iter()
+                .map(|token_type| Token {
+                    token_type: token_type.to_string(),
+                    decimals: 9,
+                })
+                .collect(),
+            extra: PoolExtra::DeepbookV2 {
+                taker_fee_rate: 2_500_000,
+                maker_rebate_rate: 1_500_000,
+                tick_size: 100,
+                lot_size: 100,
+            },
+        };
+        let coin_out_type = if coin_in_type == BASE { QUOTE } else { BASE };
+        let shared = |id| ObjectArg::SharedObject {
+            id,
+            initial_shared_version: 1.into(),
+            mutable: id == pool_id,
+        };
+
+        DeepbookV2 {
+            pool,
+            pool_arg: shared(pool_id),
+            coin_in_type: coin_in_type.to_string(),
+            coin_out_type: coin_out_type.to_string(),
+            type_params: vec![TypeTag::from_str(BASE).unwrap(), TypeTag::from_str(QUOTE).unwrap()],
+            lot_size: 100,
+            clock: shared(SUI_CLOCK_OBJECT_ID),
+            account_cap: ObjectArg::ImmOrOwnedObject(random_object_ref()),
+        }
+    }
+
+    // the command shape of `pt`, move calls as `module::function`
+    fn commands(pt: &ProgrammableTransaction) -> Vec<String> {
+        pt.commands
+            .iter()
+            .map(|command| match command {
+                Command::MoveCall(call) => {
+                    let ProgrammableMoveCall { module, function, .. } = call.as_ref();
+                    format!("{}::{}", module, function)
+                }
+                Command::SplitCoins(..) => "split".to_string(),
+                Command::TransferObjects(..) => "transfer".to_string(),
+                other => format!("{:?}", other),
+            })
+            .collect()
+    }
+
+    async fn trade_ptb(dex: &DeepbookV2, amount_in: Option<u64>) -> ProgrammableTransaction {
+        let mut ctx = TradeCtx::default();
+        let coin_in = ctx.split_coin(random_object_ref(), 1_050).unwrap();
+        let coin_out = dex
+            .extend_trade_tx(&mut ctx, SuiAddress::ZERO, coin_in, amount_in)
+            .await
+            .unwrap();
+        ctx.transfer_arg(SuiAddress::ZERO, coin_out);
+        ctx.ptb.finish()
+    }
+
+    #[tokio::test]
+    async fn test_base_in_ptb() {
+        let dex = test_pool(BASE);
+        assert!(dex.is_a2b());
+
+        // 1_050 is traded as 1_000, the dust goes back, and all of it must fill
+        let pt = trade_ptb(&dex, Some(1_050)).await;
+        assert_eq!(
+            commands(&pt),
+            [
+                "split",
+                "split",
+                "transfer",
+                "coin::zero",
+                "clob_v2::swap_exact_base_for_quote",
+                "u64::max",
+                "u64::diff",
+                "coin::zero",
+                "split",
+                "coin::destroy_zero",
+                "coin::destroy_zero",
+                "coin::destroy_zero",
+                "transfer",
+            ]
+        );
+
+        // an unknown amount trades the whole coin
+        let pt = trade_ptb(&dex, None).await;
+        assert_eq!(
+            commands(&pt),
+            [
+                "split",
+                "coin::value",
+                "coin::zero",
+                "clob_v2::swap_exact_base_for_quote",
+                "u64::max",
+                "u64::diff",
+                "coin::zero",
+                "split",
+                "coin::destroy_zero",
+                "coin::destroy_zero",
+                "coin::destroy_zero",
+                "transfer",
+            ]
+        );
+
+        // less than a lot can't be traded
+        let mut ctx = TradeCtx::default();
+        let coin_in = ctx.split_coin(random_object_ref(), 99).unwrap();
+        assert!(dex
+            .extend_trade_tx(&mut ctx, SuiAddress::ZERO, coin_in, Some(99))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_quote_in_ptb() {
+        let dex = test_pool(QUOTE);
+        assert!(!dex.is_a2b());
+
+        // the quote is rounded to lots too, and at most a thousandth of it may be left unspent
+        let pt = trade_ptb(&dex, Some(1_050)).await;
+        assert_eq!(
+            commands(&pt),
+            [
+                "split",
+                "split",
+                "transfer",
+                "clob_v2::swap_exact_quote_for_base",
+                "coin::value",
+                "u64::max",
+                "u64::diff",
+                "coin::zero",
+                "split",
+                "coin::destroy_zero",
+                "coin::destroy_zero",
+                "transfer",
+                "transfer",
+            ]
+        );
+
+        // an unknown amount bounds the leftover on-chain
+        let pt = trade_ptb(&dex, None).await;
+        assert_eq!(
+            commands(&pt)[1..6],
+            [
+                "coin::value",
+                "clob_v2::swap_exact_quote_for_base",
+                "u64::divide_and_round_up",
+                "coin::value",
+                "u64::max",
+            ]
+        );
+    }
+}
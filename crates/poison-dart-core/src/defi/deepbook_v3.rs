@@ -0,0 +1,426 @@
+//! DeepBook v3 order books.
+//!
+//! A pool is two edges like an AMM pool: base -> quote sells into the bids, quote -> base buys
+//! from the asks, each a market order through `pool::swap_exact_*`. No DEEP is passed in, so the
+//! taker fee is paid in the input coin at the pool's penalty rate, and the unfilled remainder
+//! (base below a lot, or quote the book could not absorb) is returned to the sender.
+//!
+//! Edges are quoted by simulation like every venue. `OrderBook` reads the depth around the mid
+//! price for callers that want the effective rate of a size without simulating it.
+
+use std::sync::Arc;
+
+use dex_indexer::types::{Pool, PoolExtra, Protocol};
+use eyre::{ensure, eyre, OptionExt, Result};
+use move_core_types::annotated_value::MoveStruct;
+use simulator::Simulator;
+use sui_sdk::SuiClient;
+use sui_types::{
+    base_types::{ObjectID, ObjectRef, SuiAddress},
+    transaction::{Argument, Command, ObjectArg, ProgrammableTransaction, TransactionData, TransactionKind},
+    Identifier, TypeTag, SUI_CLOCK_OBJECT_ID,
+};
+use tokio::sync::OnceCell;
+use utils::{coin, new_test_sui_client, object::shared_obj_arg};
+
+use super::{descriptor, upgrades, TradeCtx};
+use crate::{config::*, defi::Dex};
+
+// prices are quote per base scaled by 1e9, fees are scaled by 1e9
+const FLOAT_SCALING: u128 = 1_000_000_000;
+// `constants::fee_penalty_multiplier`: fees paid in the input coin instead of DEEP cost 25% more
+const FEE_PENALTY_MULTIPLIER: u128 = 1_250_000_000;
+
+static CLOCK: OnceCell<ObjectArg> = OnceCell::const_new();
+
+async fn get_clock(simulator: &Arc<Box<dyn Simulator>>) -> ObjectArg {
+    *CLOCK
+        .get_or_init(|| async {
+            let clock = simulator.get_object(&SUI_CLOCK_OBJECT_ID).await.unwrap();
+            shared_obj_arg(&clock, false)
+        })
+        .await
+}
+
+fn deep_type() -> Result<TypeTag> {
+    format!("{}::deep::DEEP", descriptor::address("deepbook_v3", "deep"))
+        .parse()
+        .map_err(|e| eyre!("invalid DEEP type: {}", e))
+}
+
+#[derive(Clone)]
+pub struct DeepbookV3 {
+    pool: Pool,
+    pool_arg: ObjectArg,
+    coin_in_type: String,
+    coin_out_type: String,
+    type_params: Vec<TypeTag>,
+    taker_fee: u64,
+    lot_size: u64,
+    clock: ObjectArg,
+}
+
+impl DeepbookV3 {
+    pub async fn new(simulator: Arc<Box<dyn Simulator>>, pool: &Pool, coin_in_type: &str) -> Result<Self> {
+        ensure!(pool.protocol == Protocol::DeepbookV3, "not a DeepbookV3 pool");
+
+        let pool_obj = simulator
+            .get_object(&pool.pool)
+            .await
+            .ok_or_else(|| eyre!("pool not found: {}", pool.pool))?;
+
+        let parsed_pool = {
+            let layout = simulator
+                .get_object_layout(&pool.pool)
+                .ok_or_eyre("pool layout not found")?;
+
+            let move_obj = pool_obj.data.try_as_move().ok_or_eyre("not a move object")?;
+            MoveStruct::simple_deserialize(move_obj.contents(), &layout).map_err(|e| eyre!(e))?
+        };
+
+        let coin_out_type = if let Some(0) = pool.token_index(coin_in_type) {
+            pool.token1_type()
+        } else {
+            pool.token0_type()
+        };
+
+        let type_params = parsed_pool.type_.type_params.clone();
+        let (taker_fee, lot_size) = match pool.extra {
+            PoolExtra::DeepbookV3 {
+                taker_fee, lot_size, ..
+            } => (taker_fee, lot_size),
+            _ => (0, 1),
+        };
+        ensure!(lot_size > 0, "invalid lot_size");
+
+        let pool_arg = shared_obj_arg(&pool_obj, true);
+        let clock = get_clock(&simulator).await;
+
+        Ok(Self {
+            pool: pool.clone(),
+            pool_arg,
+            coin_in_type: coin_in_type.to_string(),
+            coin_out_type,
+            type_params,
+            taker_fee,
+            lot_size,
+            clock,
+        })
+    }
+
+    /*
+    public fun get_level2_ticks_from_mid<BaseAsset, QuoteAsset>(
+        self: &Pool<BaseAsset, QuoteAsset>,
+        ticks: u64,
+        clock: &Clock,
+    ): (vector<u64>, vector<u64>, vector<u64>, vector<u64>)
+    */
+    /// The `ticks` best price levels on each side of the book.
+    pub async fn order_book(&self, sui: &SuiClient, sender: SuiAddress, ticks: u64) -> Result<OrderBook> {
+        let mut ctx = TradeCtx::default();
+        let pool_arg = ctx.obj(self.pool_arg).map_err(|e| eyre!(e))?;
+        let ticks_arg = ctx.pure(ticks).map_err(|e| eyre!(e))?;
+        let clock_arg = ctx.obj(self.clock).map_err(|e| eyre!(e))?;
+        ctx.command(Command::move_call(
+            self.package()?,
+            Identifier::new("pool").map_err(|e| eyre!(e))?,
+            Identifier::new("get_level2_ticks_from_mid").map_err(|e| eyre!(e))?,
+            self.type_params.clone(),
+            vec![pool_arg, ticks_arg, clock_arg],
+        ));
+
+        let results = sui
+            .read_api()
+            .dev_inspect_transaction_block(
+                sender,
+                TransactionKind::programmable(ctx.ptb.finish()),
+                None,
+                None,
+                None,
+            )
+            .await?
+            .results
+            .ok_or_eyre("get_level2_ticks_from_mid returned nothing")?;
+        let values = results
+            .first()
+            .ok_or_eyre("get_level2_ticks_from_mid returned nothing")?
+            .return_values
+            .iter()
+            .map(|(bytes, _)| bcs::from_bytes::<Vec<u64>>(bytes).map_err(|e| eyre!(e)))
+            .collect::<Result<Vec<_>>>()?;
+        ensure!(values.len() == 4, "unexpected get_level2_ticks_from_mid return values");
+
+        Ok(OrderBook {
+            bids: values[0].iter().copied().zip(values[1].iter().copied()).collect(),
+            asks: values[2].iter().copied().zip(values[3].iter().copied()).collect(),
+            taker_fee: self.taker_fee,
+            lot_size: self.lot_size,
+        })
+    }
+
+    fn package(&self) -> Result<ObjectID> {
+        upgrades::package_id(descriptor::address("deepbook_v3", "package"))
+    }
+
+    async fn swap_tx(
+        &self,
+        sender: SuiAddress,
+        recipient: SuiAddress,
+        coin_in: ObjectRef,
+        amount_in: u64,
+    ) -> Result<ProgrammableTransaction> {
+        let mut ctx = TradeCtx::default();
+
+        let coin_in = ctx.split_coin(coin_in, amount_in)?;
+        let coin_out = self.extend_trade_tx(&mut ctx, sender, coin_in, None).await?;
+        ctx.transfer_arg(recipient, coin_out);
+
+        Ok(ctx.ptb.finish())
+    }
+}
+
+#[async_trait::async_trait]
+impl Dex for DeepbookV3 {
+    /*
+    public fun swap_exact_base_for_quote<BaseAsset, QuoteAsset>(
+        self: &mut Pool<BaseAsset, QuoteAsset>,
+        base_in: Coin<BaseAsset>,
+        deep_in: Coin<DEEP>,
+        min_quote_out: u64,
+        clock: &Clock,
+        ctx: &mut TxContext,
+    ): (Coin<BaseAsset>, Coin<QuoteAsset>, Coin<DEEP>)
+
+    public fun swap_exact_quote_for_base<BaseAsset, QuoteAsset>(
+        self: &mut Pool<BaseAsset, QuoteAsset>,
+        quote_in: Coin<QuoteAsset>,
+        deep_in: Coin<DEEP>,
+        min_base_out: u64,
+        clock: &Clock,
+        ctx: &mut TxContext,
+    ): (Coin<BaseAsset>, Coin<QuoteAsset>, Coin<DEEP>)
+    */
+    async fn extend_trade_tx(
+        &self,
+        ctx: &mut TradeCtx,
+        sender: SuiAddress,
+        coin_in: Argument,
+        _amount_in: Option<u64>,
+    ) -> Result<Argument> {
+        let is_base_in = self.is_a2b();
+        let deep_type = deep_type()?;
+
+        let pool_arg = ctx.obj(self.pool_arg).map_err(|e| eyre!(e))?;
+        let deep_in = ctx.coin_zero(deep_type.clone())?;
+        // the profit check at the end of the trade is the slippage guard
+        let min_out = ctx.pure(0u64).map_err(|e| eyre!(e))?;
+        let clock_arg = ctx.obj(self.clock).map_err(|e| eyre!(e))?;
+
+        let function = if is_base_in {
+            "swap_exact_base_for_quote"
+        } else {
+            "swap_exact_quote_for_base"
+        };
+        ctx.command(Command::move_call(
+            self.package()?,
+            Identifier::new("pool").map_err(|e| eyre!(e))?,
+            Identifier::new(function).map_err(|e| eyre!(e))?,
+            self.type_params.clone(),
+            vec![pool_arg, coin_in, deep_in, min_out, clock_arg],
+        ));
+
+        let last_idx = ctx.last_command_idx();
+        let (base_out, quote_out, deep_out) = (
+            Argument::NestedResult(last_idx, 0),
+            Argument::NestedResult(last_idx, 1),
+            Argument::NestedResult(last_idx, 2),
+        );
+        let (remainder, coin_out) = if is_base_in {
+            (base_out, quote_out)
+        } else {
+            (quote_out, base_out)
+        };
+
+        ctx.coin_destroy_zero(deep_out, deep_type)?;
+        ctx.transfer_arg(sender, remainder);
+
+        Ok(coin_out)
+    }
+
+    fn coin_in_type(&self) -> String {
+        self.coin_in_type.clone()
+    }
+
+    fn coin_out_type(&self) -> String {
+        self.coin_out_type.clone()
+    }
+
+    fn protocol(&self) -> Protocol {
+        Protocol::DeepbookV3
+    }
+
+    fn liquidity(&self) -> u128 {
+        0
+    }
+
+    fn object_id(&self) -> ObjectID {
+        self.pool.pool
+    }
+
+    fn pool_arg(&self) -> Option<ObjectArg> {
+        Some(self.pool_arg.clone())
+    }
+
+    fn flip(&mut self) {
+        std::mem::swap(&mut self.coin_in_type, &mut self.coin_out_type);
+    }
+
+    fn is_a2b(&self) -> bool {
+        self.pool.token_index(&self.coin_in_type) == Some(0)
+    }
+
+    // For testing
+    async fn swap_tx(&self, sender: SuiAddress, recipient: SuiAddress, amount_in: u64) -> Result<TransactionData> {
+        let sui = new_test_sui_client().await;
+
+        let coin_in = coin::get_coin(&sui, sender, &self.coin_in_type, amount_in).await?;
+
+        let pt = self.swap_tx(sender, recipient, coin_in.object_ref(), amount_in).await?;
+
+        let gas_coins = coin::get_gas_coin_refs(&sui, sender, Some(coin_in.coin_object_id)).await?;
+        let gas_price = sui.read_api().get_reference_gas_price().await?;
+        let tx_data = TransactionData::new_programmable(sender, gas_coins, pt, GAS_BUDGET, gas_price);
+
+        Ok(tx_data)
+    }
+}
+
+/// Price levels of a DeepBook v3 pool, best first, as (price, base quantity).
+#[derive(Debug, Clone, Default)]
+pub struct OrderBook {
+    pub bids: Vec<(u64, u64)>,
+    pub asks: Vec<(u64, u64)>,
+    pub taker_fee: u64,
+    pub lot_size: u64,
+}
+
+/// A market order filled against an `OrderBook`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fill {
+    /// Input the book took; the rest is returned unfilled
+    pub amount_in: u64,
+    /// Output net of the taker fee
+    pub amount_out: u64,
+}
+
+impl Fill {
+    /// Output per unit of input taken, 0 when nothing filled.
+    pub fn effective_rate(&self) -> f64 {
+        if self.amount_in == 0 {
+            return 0.0;
+        }
+        self.amount_out as f64 / self.amount_in as f64
+    }
+}
+
+impl OrderBook {
+    /// Fill a market order of `amount_in` base (selling into the bids) or quote (buying from the
+    /// asks) against the levels read, paying the fee in the input coin.
+    pub fn fill(&self, amount_in: u64, is_base_in: bool) -> Fill {
+        let lot_size = self.lot_size.max(1) as u128;
+        let mut remaining = amount_in as u128;
+        let mut amount_out = 0u128;
+
+        if is_base_in {
+            // base below a lot is never matched
+            remaining -= remaining % lot_size;
+            for &(price, quantity) in &self.bids {
+                let base = remaining.min(quantity as u128);
+                amount_out += base * price as u128 / FLOAT_SCALING;
+                remaining -= base;
+                if remaining == 0 {
+                    break;
+                }
+            }
+            remaining += amount_in as u128 % lot_size;
+        } else {
+            for &(price, quantity) in &self.asks {
+                if price == 0 {
+                    continue;
+                }
+                let affordable = remaining * FLOAT_SCALING / price as u128;
+                let base = affordable.min(quantity as u128) / lot_size * lot_size;
+                if base == 0 {
+                    break;
+                }
+                amount_out += base;
+                remaining -= (base * price as u128).div_ceil(FLOAT_SCALING).min(remaining);
+            }
+        }
+
+        let fee_rate = self.taker_fee as u128 * FEE_PENALTY_MULTIPLIER / FLOAT_SCALING;
+        let fee = amount_out * fee_rate / FLOAT_SCALING;
+        Fill {
+            amount_in: amount_in - remaining as u64,
+            amount_out: (amount_out - fee) as u64,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn book() -> OrderBook {
+        OrderBook {
+            // 1 base = 2 quote, then 1.5 quote
+            bids: vec![(2_000_000_000, 100), (1_500_000_000, 100)],
+            // 1 base = 2.5 quote, then 4 quote
+            asks: vec![(2_500_000_000, 100), (4_000_000_000, 100)],
+            taker_fee: 0,
+            lot_size: 10,
+        }
+    }
+
+    #[test]
+    fn test_order_book_fill() {
+        let book = book();
+
+        // sells walk the bids, the dust below a lot stays unfilled
+        let fill = book.fill(155, true);
+        assert_eq!(
+            fill,
+            Fill {
+                amount_in: 150,
+                amount_out: 200 + 75
+            }
+        );
+        assert_eq!(
+            book.fill(500, true),
+            Fill {
+                amount_in: 200,
+                amount_out: 350
+            }
+        );
+
+        // buys walk the asks in whole lots
+        let fill = book.fill(650, false);
+        assert_eq!(
+            fill,
+            Fill {
+                amount_in: 650,
+                amount_out: 200
+            }
+        );
+        assert!((fill.effective_rate() - 200.0 / 650.0).abs() < 1e-12);
+
+        // 0.1% taker fee, paid at the 1.25x penalty
+        let book = OrderBook {
+            bids: vec![(1_000_000_000, 10_000_000)],
+            taker_fee: 1_000_000,
+            lot_size: 1,
+            ..book
+        };
+        assert_eq!(book.fill(1_000_000, true).amount_out, 1_000_000 - 1_250);
+    }
+}
@@ -0,0 +1,133 @@
+//! Package and object ids of the protocols we trade through.
+//!
+//! Package ids, global config objects and version gates live in a versioned descriptor file instead
+//! of source constants, so a protocol moving an address needs a config change, not a rebuild.
+//! `protocols/mainnet.json` is built in; `init` layers the file or URL in `SUI_PROTOCOL_DESCRIPTOR`
+//! over it at startup, entry by entry. Swap builders read ids through `address`.
+
+use std::{collections::BTreeMap, sync::OnceLock, time::Duration};
+
+use clap::Parser;
+use eyre::{ensure, Result, WrapErr};
+use serde::Deserialize;
+use sui_types::base_types::ObjectID;
+use tracing::info;
+
+/// The descriptor format this build reads.
+const DESCRIPTOR_VERSION: u32 = 1;
+const BUILTIN: &str = include_str!("../../protocols/mainnet.json");
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+static DESCRIPTOR: OnceLock<Descriptor> = OnceLock::new();
+
+#[derive(Clone, Debug, Default, Parser)]
+pub struct DescriptorConfig {
+    /// Protocol descriptor (path or http(s) URL) whose ids replace the built-in ones
+    #[arg(long, env = "SUI_PROTOCOL_DESCRIPTOR")]
+    pub protocol_descriptor: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Descriptor {
+    version: u32,
+    // protocol => entry name => id
+    protocols: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl Descriptor {
+    fn parse(json: &str) -> Result<Self> {
+        let descriptor: Self = serde_json::from_str(json)?;
+        ensure!(
+            descriptor.version == DESCRIPTOR_VERSION,
+            "descriptor version {} is not supported, expected {}",
+            descriptor.version,
+            DESCRIPTOR_VERSION
+        );
+        for (protocol, entries) in &descriptor.protocols {
+            for (name, id) in entries {
+                ObjectID::from_hex_literal(id).wrap_err_with(|| format!("{}.{}: invalid id {}", protocol, name, id))?;
+            }
+        }
+
+        Ok(descriptor)
+    }
+
+    /// Replace our entries with those of `other`. Returns the entries that changed.
+    fn merge(&mut self, other: Descriptor) -> Vec<String> {
+        let mut changed = vec![];
+        for (protocol, entries) in other.protocols {
+            let ours = self.protocols.entry(protocol.clone()).or_default();
+            for (name, id) in entries {
+                if ours.get(&name) != Some(&id) {
+                    changed.push(format!("{}.{}", protocol, name));
+                    ours.insert(name, id);
+                }
+            }
+        }
+        changed
+    }
+}
+
+fn builtin() -> Descriptor {
+    Descriptor::parse(BUILTIN).expect("built-in protocol descriptor is valid")
+}
+
+/// Load `config.protocol_descriptor` over the built-in ids. Must run before the first swap is
+/// built, the object args derived from these ids are cached for the life of the process.
+pub async fn init(config: &DescriptorConfig) -> Result<()> {
+    let mut descriptor = builtin();
+    if let Some(source) = &config.protocol_descriptor {
+        let json = if source.starts_with("http://") || source.starts_with("https://") {
+            let client = reqwest::Client::builder().timeout(FETCH_TIMEOUT).build()?;
+            client.get(source).send().await?.error_for_status()?.text().await?
+        } else {
+            tokio::fs::read_to_string(source).await?
+        };
+        let overrides = Descriptor::parse(&json).wrap_err("invalid protocol descriptor")?;
+        let changed = descriptor.merge(overrides);
+        info!(?changed, "protocol descriptor loaded");
+    }
+
+    ensure!(
+        DESCRIPTOR.set(descriptor).is_ok(),
+        "protocol descriptor already in use, init it before building any swap"
+    );
+    Ok(())
+}
+
+/// The id of `protocol`'s `name` entry, e.g. `address("cetus", "package")`.
+pub fn address(protocol: &str, name: &str) -> &'static str {
+    DESCRIPTOR
+        .get_or_init(builtin)
+        .protocols
+        .get(protocol)
+        .and_then(|entries| entries.get(name))
+        .map(String::as_str)
+        .unwrap_or_else(|| panic!("{}.{} missing from the protocol descriptor", protocol, name))
+}
+
+/// `address` as an object id.
+pub fn object_id(protocol: &str, name: &str) -> ObjectID {
+    // every id was parsed when the descriptor was loaded
+    ObjectID::from_hex_literal(address(protocol, name)).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_descriptor_overrides_builtin_entries() {
+        let mut descriptor = builtin();
+        let cetus_config = descriptor.protocols["cetus"]["config"].clone();
+
+        let overrides = Descriptor::parse(r#"{"version": 1, "protocols": {"cetus": {"package": "0x2"}}}"#).unwrap();
+        assert_eq!(descriptor.merge(overrides), vec!["cetus.package".to_string()]);
+        assert_eq!(descriptor.protocols["cetus"]["package"], "0x2");
+        // entries the override doesn't name keep their built-in id
+        assert_eq!(descriptor.protocols["cetus"]["config"], cetus_config);
+
+        assert!(Descriptor::parse(r#"{"version": 2, "protocols": {}}"#).is_err());
+        assert!(Descriptor::parse(r#"{"version": 1, "protocols": {"cetus": {"package": "cetus"}}}"#).is_err());
+    }
+}
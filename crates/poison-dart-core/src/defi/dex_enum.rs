@@ -0,0 +1,307 @@
+//! Static dispatch over the built-in venues.
+//!
+//! Path search clones and queries edges millions of times per trigger; with `Box<dyn Dex>` every
+//! clone is a heap allocation plus a vtable call, and every query a vtable call. `DexEnum` holds
+//! the built-in venues inline so those are a `match`. The `Dex` trait stays the extension point:
+//! venues registered at runtime through `registry::register_dex_factory` travel as `Other`.
+
+use std::{fmt, hash::Hash};
+
+use dex_indexer::types::Protocol;
+use eyre::Result;
+use sui_types::{
+    base_types::{ObjectID, SuiAddress},
+    transaction::{Argument, ObjectArg, TransactionData},
+};
+
+#[cfg(feature = "aftermath")]
+use super::aftermath::Aftermath;
+#[cfg(feature = "blue_move")]
+use super::blue_move::BlueMove;
+#[cfg(feature = "cetus")]
+use super::cetus::Cetus;
+#[cfg(feature = "deepbook_v2")]
+use super::deepbook_v2::DeepbookV2;
+#[cfg(feature = "deepbook_v3")]
+use super::deepbook_v3::DeepbookV3;
+#[cfg(feature = "flowx_clmm")]
+use super::flowx_clmm::FlowxClmm;
+#[cfg(feature = "kriya_amm")]
+use super::kriya_amm::KriyaAmm;
+#[cfg(feature = "kriya_clmm")]
+use super::kriya_clmm::KriyaClmm;
+#[cfg(feature = "turbos")]
+use super::turbos::Turbos;
+use super::{cp_math::ConstantProduct, trade::FlashResult, Dex, TradeCtx};
+
+// Boxing the larger venues would bring back the allocation per clone this type exists to avoid.
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone)]
+pub enum DexEnum {
+    #[cfg(feature = "aftermath")]
+    Aftermath(Aftermath),
+    #[cfg(feature = "blue_move")]
+    BlueMove(BlueMove),
+    #[cfg(feature = "cetus")]
+    Cetus(Cetus),
+    #[cfg(feature = "deepbook_v2")]
+    DeepbookV2(DeepbookV2),
+    #[cfg(feature = "deepbook_v3")]
+    DeepbookV3(DeepbookV3),
+    #[cfg(feature = "flowx_clmm")]
+    FlowxClmm(FlowxClmm),
+    #[cfg(feature = "kriya_amm")]
+    KriyaAmm(KriyaAmm),
+    #[cfg(feature = "kriya_clmm")]
+    KriyaClmm(KriyaClmm),
+    #[cfg(feature = "turbos")]
+    Turbos(Turbos),
+    /// A venue implemented outside this crate.
+    Other(Box<dyn Dex>),
+}
+
+macro_rules! dispatch {
+    ($self:expr, $dex:ident => $body:expr) => {
+        dispatch!($self, $dex => $body, $dex => $body)
+    };
+    ($self:expr, $dex:ident => $body:expr, $other:ident => $other_body:expr) => {
+        match $self {
+            #[cfg(feature = "aftermath")]
+            DexEnum::Aftermath($dex) => $body,
+            #[cfg(feature = "blue_move")]
+            DexEnum::BlueMove($dex) => $body,
+            #[cfg(feature = "cetus")]
+            DexEnum::Cetus($dex) => $body,
+            #[cfg(feature = "deepbook_v2")]
+            DexEnum::DeepbookV2($dex) => $body,
+            #[cfg(feature = "deepbook_v3")]
+            DexEnum::DeepbookV3($dex) => $body,
+            #[cfg(feature = "flowx_clmm")]
+            DexEnum::FlowxClmm($dex) => $body,
+            #[cfg(feature = "kriya_amm")]
+            DexEnum::KriyaAmm($dex) => $body,
+            #[cfg(feature = "kriya_clmm")]
+            DexEnum::KriyaClmm($dex) => $body,
+            #[cfg(feature = "turbos")]
+            DexEnum::Turbos($dex) => $body,
+            DexEnum::Other($other) => $other_body,
+        }
+    };
+}
+
+macro_rules! impl_from_dex {
+    ($feature:literal, $variant:ident) => {
+        #[cfg(feature = $feature)]
+        impl From<$variant> for DexEnum {
+            fn from(dex: $variant) -> Self {
+                DexEnum::$variant(dex)
+            }
+        }
+    };
+}
+
+impl_from_dex!("aftermath", Aftermath);
+impl_from_dex!("blue_move", BlueMove);
+impl_from_dex!("cetus", Cetus);
+impl_from_dex!("deepbook_v2", DeepbookV2);
+impl_from_dex!("deepbook_v3", DeepbookV3);
+impl_from_dex!("flowx_clmm", FlowxClmm);
+impl_from_dex!("kriya_amm", KriyaAmm);
+impl_from_dex!("kriya_clmm", KriyaClmm);
+impl_from_dex!("turbos", Turbos);
+
+impl From<Box<dyn Dex>> for DexEnum {
+    fn from(dex: Box<dyn Dex>) -> Self {
+        DexEnum::Other(dex)
+    }
+}
+
+impl DexEnum {
+    /// The venue as a trait object, for code outside the search hot path.
+    #[allow(dead_code)]
+    pub fn into_boxed(self) -> Box<dyn Dex> {
+        dispatch!(self, dex => Box::new(dex), dex => dex)
+    }
+}
+
+#[async_trait::async_trait]
+impl Dex for DexEnum {
+    fn support_flashloan(&self) -> bool {
+        dispatch!(self, dex => dex.support_flashloan())
+    }
+
+    async fn extend_flashloan_tx(&self, ctx: &mut TradeCtx, amount: u64) -> Result<FlashResult> {
+        dispatch!(self, dex => dex.extend_flashloan_tx(ctx, amount).await)
+    }
+
+    async fn extend_repay_tx(&self, ctx: &mut TradeCtx, coin: Argument, flash_res: FlashResult) -> Result<Argument> {
+        dispatch!(self, dex => dex.extend_repay_tx(ctx, coin, flash_res).await)
+    }
+
+    async fn extend_trade_tx(
+        &self,
+        ctx: &mut TradeCtx,
+        sender: SuiAddress,
+        coin_in: Argument,
+        amount_in: Option<u64>,
+    ) -> Result<Argument> {
+        dispatch!(self, dex => dex.extend_trade_tx(ctx, sender, coin_in, amount_in).await)
+    }
+
+    fn coin_in_type(&self) -> String {
+        dispatch!(self, dex => dex.coin_in_type())
+    }
+
+    fn coin_out_type(&self) -> String {
+        dispatch!(self, dex => dex.coin_out_type())
+    }
+
+    fn protocol(&self) -> Protocol {
+        dispatch!(self, dex => dex.protocol())
+    }
+
+    fn liquidity(&self) -> u128 {
+        dispatch!(self, dex => dex.liquidity())
+    }
+
+    fn object_id(&self) -> ObjectID {
+        dispatch!(self, dex => dex.object_id())
+    }
+
+    fn pool_arg(&self) -> Option<ObjectArg> {
+        dispatch!(self, dex => dex.pool_arg())
+    }
+
+    fn flip(&mut self) {
+        dispatch!(self, dex => dex.flip())
+    }
+
+    fn partner_rebate_rate(&self) -> f64 {
+        dispatch!(self, dex => dex.partner_rebate_rate())
+    }
+
+    fn constant_product(&self) -> Option<ConstantProduct> {
+        dispatch!(self, dex => dex.constant_product())
+    }
+
+    fn quote(&self, amount_in: u64) -> Option<u64> {
+        dispatch!(self, dex => dex.quote(amount_in))
+    }
+
+    fn is_a2b(&self) -> bool {
+        dispatch!(self, dex => dex.is_a2b())
+    }
+
+    async fn swap_tx(&self, sender: SuiAddress, recipient: SuiAddress, amount_in: u64) -> Result<TransactionData> {
+        dispatch!(self, dex => dex.swap_tx(sender, recipient, amount_in).await)
+    }
+}
+
+impl PartialEq for DexEnum {
+    fn eq(&self, other: &Self) -> bool {
+        self.object_id() == other.object_id()
+    }
+}
+
+impl Eq for DexEnum {}
+
+impl Hash for DexEnum {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.object_id().hash(state);
+    }
+}
+
+impl fmt::Debug for DexEnum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}({}, {}, {})",
+            self.protocol(),
+            self.object_id(),
+            self.coin_in_type(),
+            self.coin_out_type()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Instant};
+
+    use object_pool::ObjectPool;
+    use simulator::{HttpSimulator, Simulator};
+    use tracing::info;
+    use utils::alloc_counter::count_allocations;
+
+    use super::*;
+    use crate::{
+        config::tests::TEST_HTTP_URL,
+        defi::{flash_lender::FlashLoanConfig, Defi},
+    };
+
+    const ROUNDS: usize = 10_000;
+
+    // what one search step does to an edge: clone it and read it
+    fn walk<D: Clone>(dexes: &[D], read: impl Fn(&D) -> usize) -> usize {
+        let mut n = 0;
+        for _ in 0..ROUNDS {
+            for dex in dexes {
+                n += read(&dex.clone());
+            }
+        }
+        n
+    }
+
+    #[tokio::test]
+    async fn test_dex_enum_speedup() {
+        mev_logger::init_console_logger_with_directives(None, &["poison_dart_core=debug"]);
+
+        let simulator_pool = ObjectPool::new(1, move || {
+            tokio::runtime::Runtime::new()
+                .unwrap()
+                .block_on(async { Box::new(HttpSimulator::new(&TEST_HTTP_URL, &None).await) as Box<dyn Simulator> })
+        });
+        let defi = Defi::new(TEST_HTTP_URL, Arc::new(simulator_pool), &FlashLoanConfig::default())
+            .await
+            .unwrap();
+
+        let coin_in_type = "0xa8816d3a6e3136e86bc2873b1f94a15cadc8af2703c075f2d546c2ae367f4df9::ocean::OCEAN";
+        let enum_dexes: Vec<DexEnum> = defi
+            .find_sell_paths(coin_in_type)
+            .await
+            .unwrap()
+            .into_iter()
+            .flat_map(|path| path.path)
+            .collect();
+        assert!(!enum_dexes.is_empty());
+        let boxed_dexes: Vec<Box<dyn Dex>> = enum_dexes.iter().cloned().map(DexEnum::into_boxed).collect();
+
+        let timer = Instant::now();
+        let (_, enum_allocs) = count_allocations(|| {
+            walk(&enum_dexes, |dex| {
+                (dex.liquidity() & 1) as usize + dex.object_id().as_ref()[0] as usize
+            })
+        });
+        let enum_elapsed = timer.elapsed();
+
+        let timer = Instant::now();
+        let (_, boxed_allocs) = count_allocations(|| {
+            walk(&boxed_dexes, |dex| {
+                (dex.liquidity() & 1) as usize + dex.object_id().as_ref()[0] as usize
+            })
+        });
+        let boxed_elapsed = timer.elapsed();
+
+        info!(
+            edges = enum_dexes.len(),
+            ?enum_elapsed,
+            ?boxed_elapsed,
+            speedup = boxed_elapsed.as_secs_f64() / enum_elapsed.as_secs_f64(),
+            enum_allocs,
+            boxed_allocs,
+            "enum vs boxed dispatch"
+        );
+        // one box per clone is gone
+        assert!(enum_allocs + ROUNDS * enum_dexes.len() <= boxed_allocs);
+    }
+}
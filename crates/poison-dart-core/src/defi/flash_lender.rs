@@ -0,0 +1,187 @@
+//! Flash-loan providers for paths whose first pool cannot flash swap.
+//!
+//! A flashloan trade borrows its start coin, runs the path and repays inside one PTB. When the
+//! first pool supports flash swaps it lends the coin itself at no cost beyond its swap fee;
+//! otherwise the trade borrows from the cheapest `FlashLender` that lends the start coin. Navi
+//! charges its configured rate; Scallop's rates are read from its market and re-read every
+//! `LENDER_REFRESH`, as it keeps the whole repayment and must be repaid exactly what it charged.
+//! Fees only rank lenders: the simulation decides whether a trade pays.
+
+use std::{sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use clap::Parser;
+use eyre::Result;
+use simulator::Simulator;
+use sui_types::transaction::Argument;
+use tracing::{info, warn};
+
+use super::{navi::Navi, scallop::Scallop, trade::FlashResult, TradeCtx};
+
+const LENDER_REFRESH: Duration = Duration::from_secs(300);
+
+#[derive(Clone, Debug, Parser)]
+pub struct FlashLoanConfig {
+    /// Navi flash-loan fee, in basis points of the amount borrowed
+    #[arg(long, env = "SUI_NAVI_FLASHLOAN_FEE_BPS", default_value_t = 6)]
+    pub navi_flashloan_fee_bps: u64,
+
+    /// Also borrow from Scallop, at the fee rates its market charges
+    #[arg(long, env = "SUI_SCALLOP_FLASHLOAN")]
+    pub scallop_flashloan: bool,
+}
+
+impl Default for FlashLoanConfig {
+    fn default() -> Self {
+        Self {
+            navi_flashloan_fee_bps: 6,
+            scallop_flashloan: false,
+        }
+    }
+}
+
+/// A protocol lending coins for the duration of a PTB.
+#[async_trait]
+pub trait FlashLender: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Whether `coin_type` can be borrowed.
+    fn lends(&self, coin_type: &str) -> bool;
+
+    /// Fee owed on top of `amount` `coin_type`.
+    fn fee(&self, coin_type: &str, amount: u64) -> u64;
+
+    /// Re-read what the lender lends and charges.
+    async fn refresh(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Extend the trade_tx with a borrow of `amount` `coin_type`.
+    fn extend_flashloan_tx(&self, ctx: &mut TradeCtx, coin_type: &str, amount: u64) -> Result<FlashResult>;
+
+    /// Extend the trade_tx with the repayment of an `amount` loan out of `coin`.
+    /// Returns the coin_profit left after repaying.
+    fn extend_repay_tx(
+        &self,
+        ctx: &mut TradeCtx,
+        coin_type: &str,
+        coin: Argument,
+        amount: u64,
+        flash_res: FlashResult,
+    ) -> Result<Argument>;
+}
+
+/// `bps` basis points of `amount`, rounded up so a repayment never falls short.
+pub fn fee_of(amount: u64, bps: u64) -> u64 {
+    (amount as u128 * bps as u128).div_ceil(10_000) as u64
+}
+
+/// Every lender `config` enables, refreshed every `LENDER_REFRESH` for as long as one of them is
+/// in use.
+pub async fn lenders(
+    simulator: Arc<Box<dyn Simulator>>,
+    config: &FlashLoanConfig,
+) -> Result<Vec<Arc<dyn FlashLender>>> {
+    let mut lenders: Vec<Arc<dyn FlashLender>> = vec![Arc::new(
+        Navi::new(simulator.clone(), config.navi_flashloan_fee_bps).await?,
+    )];
+    if config.scallop_flashloan {
+        lenders.push(Arc::new(Scallop::new(simulator).await?));
+    }
+    info!(
+        lenders = ?lenders.iter().map(|l| l.name()).collect::<Vec<_>>(),
+        "flash lenders"
+    );
+
+    let refreshed = lenders.iter().map(Arc::downgrade).collect::<Vec<_>>();
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(LENDER_REFRESH).await;
+            let live = refreshed
+                .iter()
+                .filter_map(|lender| lender.upgrade())
+                .collect::<Vec<_>>();
+            if live.is_empty() {
+                return;
+            }
+            for lender in live {
+                if let Err(error) = lender.refresh().await {
+                    warn!(lender = lender.name(), ?error, "failed to refresh flash lender");
+                }
+            }
+        }
+    });
+    Ok(lenders)
+}
+
+/// The lender charging the least to borrow `amount` `coin_type`; the earlier one on a tie.
+pub fn cheapest<'a>(lenders: &'a [Arc<dyn FlashLender>], coin_type: &str, amount: u64) -> Option<&'a dyn FlashLender> {
+    lenders
+        .iter()
+        .filter(|lender| lender.lends(coin_type))
+        .min_by_key(|lender| lender.fee(coin_type, amount))
+        .map(|lender| lender.as_ref())
+}
+
+#[cfg(test)]
+mod tests {
+    use eyre::bail;
+
+    use super::*;
+
+    struct TestLender {
+        name: &'static str,
+        coin_type: &'static str,
+        fee_bps: u64,
+    }
+
+    #[async_trait]
+    impl FlashLender for TestLender {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        fn lends(&self, coin_type: &str) -> bool {
+            coin_type == self.coin_type
+        }
+
+        fn fee(&self, _coin_type: &str, amount: u64) -> u64 {
+            fee_of(amount, self.fee_bps)
+        }
+
+        fn extend_flashloan_tx(&self, _ctx: &mut TradeCtx, _coin_type: &str, _amount: u64) -> Result<FlashResult> {
+            bail!("test lender")
+        }
+
+        fn extend_repay_tx(
+            &self,
+            _ctx: &mut TradeCtx,
+            _coin_type: &str,
+            _coin: Argument,
+            _amount: u64,
+            _flash_res: FlashResult,
+        ) -> Result<Argument> {
+            bail!("test lender")
+        }
+    }
+
+    #[test]
+    fn test_cheapest_lender_for_the_start_coin() {
+        let lender = |name, coin_type, fee_bps| -> Arc<dyn FlashLender> {
+            Arc::new(TestLender {
+                name,
+                coin_type,
+                fee_bps,
+            })
+        };
+        let lenders = vec![lender("a", "SUI", 9), lender("b", "SUI", 5), lender("c", "USDC", 0)];
+
+        assert_eq!(cheapest(&lenders, "SUI", 1_000_000).map(|l| l.name()), Some("b"));
+        assert_eq!(cheapest(&lenders, "USDC", 1_000_000).map(|l| l.name()), Some("c"));
+        assert!(cheapest(&lenders, "CETUS", 1_000_000).is_none());
+
+        assert_eq!(fee_of(1_000_000, 6), 600);
+        assert_eq!(fee_of(1, 6), 1);
+        assert_eq!(fee_of(1_000, 0), 0);
+    }
+}
@@ -15,20 +15,16 @@ use utils::{
     object::{extract_u128_from_move_struct, shared_obj_arg},
 };
 
-use super::{trade::FlashResult, TradeCtx};
+use super::{descriptor, trade::FlashResult, upgrades, TradeCtx};
 use crate::{config::*, defi::Dex};
 
-const FLOWX_CLMM: &str = "0x25929e7f29e0a30eb4e692952ba1b5b65a3a4d65ab5f2a32e1ba3edcb587f26d";
-const VERSIONED: &str = "0x67624a1533b5aff5d0dfcf5e598684350efd38134d2d245f475524c03a64e656";
-const POOL_REGISTRY: &str = "0x27565d24a4cd51127ac90e4074a841bbe356cca7bf5759ddc14a975be1632abc";
-
 static OBJ_CACHE: OnceCell<ObjectArgs> = OnceCell::const_new();
 
 async fn get_object_args(simulator: Arc<Box<dyn Simulator>>) -> ObjectArgs {
     OBJ_CACHE
         .get_or_init(|| async {
-            let pool_registry_id = ObjectID::from_hex_literal(POOL_REGISTRY).unwrap();
-            let versioned_id = ObjectID::from_hex_literal(VERSIONED).unwrap();
+            let pool_registry_id = descriptor::object_id("flowx_clmm", "pool_registry");
+            let versioned_id = descriptor::object_id("flowx_clmm", "versioned");
 
             let pool_registry = simulator.get_object(&pool_registry_id).await.unwrap();
             let versioned = simulator.get_object(&versioned_id).await.unwrap();
@@ -258,7 +254,7 @@ impl FlowxClmm {
     }
 
     fn borrow_mut_pool(&self, ctx: &mut TradeCtx) -> Result<Argument> {
-        let package = ObjectID::from_hex_literal(FLOWX_CLMM)?;
+        let package = upgrades::package_id(descriptor::address("flowx_clmm", "package"))?;
         let module = Identifier::new("pool_manager").map_err(|e| eyre!(e))?;
         let function = Identifier::new("borrow_mut_pool").map_err(|e| eyre!(e))?;
         let type_arguments = self.type_params.clone();
@@ -284,7 +280,7 @@ impl Dex for FlowxClmm {
     async fn extend_flashloan_tx(&self, ctx: &mut TradeCtx, amount_in: u64) -> Result<FlashResult> {
         let pool = self.borrow_mut_pool(ctx)?;
 
-        let package = ObjectID::from_hex_literal(FLOWX_CLMM)?;
+        let package = upgrades::package_id(descriptor::address("flowx_clmm", "package"))?;
         let module = Identifier::new("pool").map_err(|e| eyre!(e))?;
         let function = Identifier::new("swap").map_err(|e| eyre!(e))?;
         let type_arguments = self.type_params.clone(); // CoinA, CoinB
@@ -317,7 +313,7 @@ impl Dex for FlowxClmm {
     }
 
     async fn extend_repay_tx(&self, ctx: &mut TradeCtx, coin: Argument, flash_res: FlashResult) -> Result<Argument> {
-        let package = ObjectID::from_hex_literal(FLOWX_CLMM)?;
+        let package = upgrades::package_id(descriptor::address("flowx_clmm", "package"))?;
         let module = Identifier::new("pool").map_err(|e| eyre!(e))?;
         let receipt = flash_res.receipt;
         let pool = flash_res.pool.ok_or_eyre("missing pool")?;
@@ -361,7 +357,7 @@ impl Dex for FlowxClmm {
         coin_in: Argument,
         _amount_in: Option<u64>,
     ) -> Result<Argument> {
-        let package = ObjectID::from_hex_literal(FLOWX_CLMM)?;
+        let package = upgrades::package_id(descriptor::address("flowx_clmm", "package"))?;
         let module = Identifier::new("swap_router").map_err(|e| eyre!(e))?;
         let function = Identifier::new("swap_exact_input").map_err(|e| eyre!(e))?;
         let type_arguments = self.type_params.clone();
@@ -438,7 +434,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_flowx_swap_tx() {
-        mev_logger::init_console_logger_with_directives(None, &["arb=debug", "dex_indexer=debug"]);
+        mev_logger::init_console_logger_with_directives(None, &["poison_dart_core=debug", "dex_indexer=debug"]);
 
         let http_simulator = HttpSimulator::new(TEST_HTTP_URL, &None).await;
 
@@ -1,25 +1,26 @@
 use std::{any::Any, sync::Arc};
 
-use dex_indexer::{
-    types::{Pool, Protocol},
-    DexIndexer,
-};
-use eyre::{bail, ensure, OptionExt, Result};
+use dex_indexer::{types::Pool, DexIndexer};
+use eyre::{ensure, eyre, OptionExt, Result};
 use object_pool::ObjectPool;
 use simulator::Simulator;
-use sui_sdk::SUI_COIN_TYPE;
 use sui_types::base_types::ObjectID;
 use tokio::sync::OnceCell;
 use tokio::task::JoinSet;
+use tracing::debug;
+use utils::cache::CacheStats;
 
-use super::{
-    aftermath::Aftermath, cetus::Cetus, deepbook_v2::DeepbookV2, flowx_clmm::FlowxClmm, 
-    turbos::Turbos, Dex, DexSearcher, Path,
-};
-use crate::defi::{blue_move::BlueMove, kriya_amm::KriyaAmm, kriya_clmm::KriyaClmm};
+use super::{base, registry, upgrades, utils::is_blocked_coin, Dex, DexEnum, DexSearcher, Path};
 
 static INDEXER: OnceCell<Arc<DexIndexer>> = OnceCell::const_new();
 
+pub async fn indexer_cache_stats() -> Vec<CacheStats> {
+    match INDEXER.get() {
+        Some(indexer) => indexer.cache_stats().await,
+        None => vec![],
+    }
+}
+
 #[derive(Clone)]
 pub struct IndexerDexSearcher {
     simulator_pool: Arc<ObjectPool<Box<dyn Simulator>>>,
@@ -30,7 +31,9 @@ impl IndexerDexSearcher {
     pub async fn new(http_url: &str, simulator_pool: Arc<ObjectPool<Box<dyn Simulator>>>) -> Result<Self> {
         let indexer = INDEXER
             .get_or_init(|| async {
-                let indexer = DexIndexer::new(http_url).await.unwrap();
+                let indexer = DexIndexer::with_protocols(http_url, registry::indexed_protocols())
+                    .await
+                    .unwrap();
                 Arc::new(indexer)
             })
             .await
@@ -62,52 +65,22 @@ async fn new_dexes(
     pool: &Pool,
     token_in_type: &str,
     token_out_type: Option<String>,
-) -> Result<Vec<Box<dyn Dex>>> {
-    let dexes = match pool.protocol {
-        Protocol::Turbos => {
-            let dex = Turbos::new(simulator, pool, token_in_type).await?;
-            vec![Box::new(dex) as Box<dyn Dex>]
-        }
-
-        Protocol::Cetus => {
-            let dex = Cetus::new(simulator, pool, token_in_type).await?;
-            vec![Box::new(dex) as Box<dyn Dex>]
-        }
-
-        Protocol::Aftermath => Aftermath::new(simulator, pool, token_in_type, token_out_type)
-            .await?
-            .into_iter()
-            .map(|dex| Box::new(dex) as Box<dyn Dex>)
-            .collect(),
-        Protocol::FlowxClmm => {
-            let dex = FlowxClmm::new(simulator, pool, token_in_type).await?;
-            vec![Box::new(dex) as Box<dyn Dex>]
-        }
-
-        Protocol::KriyaAmm => {
-            let dex = KriyaAmm::new(simulator, pool, token_in_type).await?;
-            vec![Box::new(dex) as Box<dyn Dex>]
-        }
-
-        Protocol::KriyaClmm => {
-            let dex = KriyaClmm::new(simulator, pool, token_in_type).await?;
-            vec![Box::new(dex) as Box<dyn Dex>]
-        }
-
-        Protocol::DeepbookV2 => {
-            let dex = DeepbookV2::new(simulator, pool, token_in_type).await?;
-            vec![Box::new(dex) as Box<dyn Dex>]
-        }
-
-        Protocol::BlueMove => {
-            let dex = BlueMove::new(simulator, pool, token_in_type).await?;
-            vec![Box::new(dex) as Box<dyn Dex>]
-        }
-
-        _ => bail!("unsupported protocol: {:?}", pool.protocol),
-    };
-
-    Ok(dexes)
+) -> Result<Vec<DexEnum>> {
+    ensure!(
+        !upgrades::is_protocol_disabled(&pool.protocol),
+        "protocol disabled after package upgrade: {}",
+        pool.protocol
+    );
+    ensure!(
+        registry::is_protocol_enabled(&pool.protocol),
+        "protocol disabled in the config: {}",
+        pool.protocol
+    );
+
+    let factory = registry::dex_factory(&pool.protocol)
+        .ok_or_else(|| eyre!("unsupported protocol: {:?}", pool.protocol))?;
+
+    factory.new_dexes(simulator, pool, token_in_type, token_out_type).await
 }
 
 #[async_trait::async_trait]
@@ -116,7 +89,7 @@ impl DexSearcher for IndexerDexSearcher {
         self
     }
     
-    async fn find_dexes(&self, token_in_type: &str, token_out_type: Option<String>) -> Result<Vec<Box<dyn Dex>>> {
+    async fn find_dexes(&self, token_in_type: &str, token_out_type: Option<String>) -> Result<Vec<DexEnum>> {
         let pools = if let Some(token_out_type) = token_out_type.as_ref() {
             self.indexer.get_pools_by_token01(token_in_type, token_out_type)
         } else {
@@ -152,16 +125,52 @@ impl DexSearcher for IndexerDexSearcher {
 
     async fn find_test_path(&self, path: &[ObjectID]) -> Result<Path> {
         let mut dexes = vec![];
-        let mut coin_in = SUI_COIN_TYPE.to_string();
+        let mut coin_in = base::coin_type().to_string();
 
-        for pool_id in path {
+        for (i, pool_id) in path.iter().enumerate() {
             let simulator = self.simulator_pool.get();
             let pool = self.indexer.get_pool_by_id(pool_id).ok_or_eyre("pool not found")?;
-            let dex = new_dexes(simulator, &pool, &coin_in, None).await?.pop().unwrap();
+
+            // multi-coin pools yield one edge per out coin, pick the one the next hop can take
+            let next_pool = match path.get(i + 1) {
+                Some(next_id) => Some(self.indexer.get_pool_by_id(next_id).ok_or_eyre("pool not found")?),
+                None => None,
+            };
+            let mut candidates = new_dexes(simulator, &pool, &coin_in, None).await?;
+            ensure!(!candidates.is_empty(), "no edge out of pool {} for {}", pool_id, coin_in);
+            let idx = candidates
+                .iter()
+                .position(|dex| match &next_pool {
+                    Some(next) => next.token_index(&dex.coin_out_type()).is_some(),
+                    None => base::is_base_coin(&dex.coin_out_type()),
+                })
+                .unwrap_or(candidates.len() - 1);
+            let dex = candidates.swap_remove(idx);
             coin_in = dex.coin_out_type();
             dexes.push(dex);
         }
 
         Ok(Path { path: dexes })
     }
+
+    async fn pool_dexes(&self, pool_id: &ObjectID, coin_in_type: &str) -> Result<Vec<DexEnum>> {
+        let pool = self.indexer.get_pool_by_id(pool_id).ok_or_eyre("pool not found")?;
+        new_dexes(self.simulator_pool.get(), &pool, coin_in_type, None).await
+    }
+
+    async fn coin_decimals(&self, coin_type: &str) -> Option<u8> {
+        self.indexer.get_coin_decimals(coin_type).await
+    }
+
+    async fn is_blocked_coin(&self, coin_type: &str) -> bool {
+        let simulator = self.simulator_pool.get();
+        match is_blocked_coin(simulator.as_ref().as_ref(), coin_type).await {
+            Ok(blocked) => blocked,
+            Err(error) => {
+                // can't vet it, so don't route through it until the check is retried
+                debug!(%coin_type, ?error, "failed to check the deny list");
+                true
+            }
+        }
+    }
 }
@@ -11,14 +11,22 @@ use sui_types::{
 };
 use utils::{coin, new_test_sui_client, object::*};
 
-use super::{TradeCtx, CETUS_AGGREGATOR};
+use super::{cp_math::ConstantProduct, descriptor, quote::StableSwap, upgrades, TradeCtx};
 use crate::{config::*, defi::Dex};
 
+// lp_fee_percent and protocol_fee_percent are parts per million of the input
+const FEE_SCALING: u64 = 1_000_000;
+
 #[derive(Clone)]
 pub struct KriyaAmm {
     pool: Pool,
     pool_arg: ObjectArg,
     liquidity: u128,
+    // (token_x, token_y) reserves
+    reserves: (u64, u64),
+    // (scale_x, scale_y) of stable pools, which follow x^3 y + x y^3 = k instead of x * y = k
+    stable_scales: Option<(u64, u64)>,
+    fee_percent: u64,
     coin_in_type: String,
     coin_out_type: String,
     type_params: Vec<TypeTag>,
@@ -50,6 +58,26 @@ impl KriyaAmm {
             extract_u64_from_move_struct(&lsp_supply, "value")? as u128
         };
 
+        let reserves = {
+            let token_x = extract_struct_from_move_struct(&parsed_pool, "token_x")?;
+            let token_y = extract_struct_from_move_struct(&parsed_pool, "token_y")?;
+            (
+                extract_u64_from_move_struct(&token_x, "value")?,
+                extract_u64_from_move_struct(&token_y, "value")?,
+            )
+        };
+        let is_stable = extract_bool_from_move_struct(&parsed_pool, "is_stable")?;
+        let stable_scales = if is_stable {
+            Some((
+                extract_u64_from_move_struct(&parsed_pool, "scaleX")?,
+                extract_u64_from_move_struct(&parsed_pool, "scaleY")?,
+            ))
+        } else {
+            None
+        };
+        let fee_percent = extract_u64_from_move_struct(&parsed_pool, "lp_fee_percent")? +
+            extract_u64_from_move_struct(&parsed_pool, "protocol_fee_percent")?;
+
         let coin_out_type = if pool.token0_type() == coin_in_type {
             pool.token1_type().to_string()
         } else {
@@ -64,12 +92,24 @@ impl KriyaAmm {
             pool: pool.clone(),
             pool_arg,
             liquidity,
+            reserves,
+            stable_scales,
+            fee_percent,
             coin_in_type: coin_in_type.to_string(),
             coin_out_type,
             type_params,
         })
     }
 
+    // an (x, y) pair ordered (coin_in, coin_out)
+    fn oriented(&self, (x, y): (u64, u64)) -> (u64, u64) {
+        if self.is_a2b() {
+            (x, y)
+        } else {
+            (y, x)
+        }
+    }
+
     async fn build_swap_tx(
         &self,
         sender: SuiAddress,
@@ -111,7 +151,7 @@ impl Dex for KriyaAmm {
     ) -> Result<Argument> {
         let function = if self.is_a2b() { "swap_a2b" } else { "swap_b2a" };
 
-        let package = ObjectID::from_hex_literal(CETUS_AGGREGATOR)?;
+        let package = upgrades::package_id(descriptor::address("cetus_aggregator", "package"))?;
         let module = Identifier::new("kriya_amm").map_err(|e| eyre!(e))?;
         let function = Identifier::new(function).map_err(|e| eyre!(e))?;
         let type_arguments = self.type_params.clone();
@@ -142,10 +182,44 @@ impl Dex for KriyaAmm {
         self.pool.pool
     }
 
+    fn pool_arg(&self) -> Option<ObjectArg> {
+        Some(self.pool_arg.clone())
+    }
+
     fn flip(&mut self) {
         std::mem::swap(&mut self.coin_in_type, &mut self.coin_out_type);
     }
 
+    fn constant_product(&self) -> Option<ConstantProduct> {
+        if self.stable_scales.is_some() {
+            return None;
+        }
+        let (reserve_in, reserve_out) = self.oriented(self.reserves);
+        Some(ConstantProduct {
+            reserve_in,
+            reserve_out,
+            fee_num: self.fee_percent,
+            fee_den: FEE_SCALING,
+        })
+    }
+
+    fn quote(&self, amount_in: u64) -> Option<u64> {
+        let Some(scales) = self.stable_scales else {
+            return self.constant_product().map(|pool| pool.amount_out(amount_in));
+        };
+        let (reserve_in, reserve_out) = self.oriented(self.reserves);
+        let (scale_in, scale_out) = self.oriented(scales);
+        let pool = StableSwap {
+            reserve_in,
+            reserve_out,
+            scale_in,
+            scale_out,
+            fee_num: self.fee_percent,
+            fee_den: FEE_SCALING,
+        };
+        Some(pool.amount_out(amount_in))
+    }
+
     fn is_a2b(&self) -> bool {
         self.pool.token_index(&self.coin_in_type) == Some(0)
     }
@@ -185,7 +259,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_kriya_amm_swap_tx() {
-        mev_logger::init_console_logger_with_directives(None, &["arb=debug", "dex_indexer=debug"]);
+        mev_logger::init_console_logger_with_directives(None, &["poison_dart_core=debug", "dex_indexer=debug"]);
 
         let http_simulator = HttpSimulator::new(TEST_HTTP_URL, &None).await;
 
@@ -15,12 +15,9 @@ use utils::{
     object::{extract_u128_from_move_struct, shared_obj_arg},
 };
 
-use super::{trade::FlashResult, TradeCtx, CETUS_AGGREGATOR};
+use super::{descriptor, trade::FlashResult, upgrades, TradeCtx};
 use crate::{config::*, defi::Dex};
 
-const KRIYA_CLMM: &str = "0xbd8d4489782042c6fafad4de4bc6a5e0b84a43c6c00647ffd7062d1e2bb7549e";
-const VERSION: &str = "0xf5145a7ac345ca8736cf8c76047d00d6d378f30e81be6f6eb557184d9de93c78";
-
 #[derive(Clone)]
 pub struct ObjectArgs {
     version: ObjectArg,
@@ -32,7 +29,7 @@ static OBJ_CACHE: OnceCell<ObjectArgs> = OnceCell::const_new();
 async fn get_object_args(simulator: Arc<Box<dyn Simulator>>) -> ObjectArgs {
     OBJ_CACHE
         .get_or_init(|| async {
-            let version_id = ObjectID::from_hex_literal(VERSION).unwrap();
+            let version_id = descriptor::object_id("kriya_clmm", "version");
             let version = simulator.get_object(&version_id).await.unwrap();
             let clock = simulator.get_object(&SUI_CLOCK_OBJECT_ID).await.unwrap();
 
@@ -209,7 +206,7 @@ impl Dex for KriyaClmm {
     }
 
     async fn extend_flashloan_tx(&self, ctx: &mut TradeCtx, amount_in: u64) -> Result<FlashResult> {
-        let package = ObjectID::from_hex_literal(KRIYA_CLMM)?;
+        let package = upgrades::package_id(descriptor::address("kriya_clmm", "package"))?;
         let module = Identifier::new("trade").map_err(|e| eyre!(e))?;
         let function = Identifier::new("flash_swap").map_err(|e| eyre!(e))?;
         let type_arguments = self.type_params.clone();
@@ -241,7 +238,7 @@ impl Dex for KriyaClmm {
     }
 
     async fn extend_repay_tx(&self, ctx: &mut TradeCtx, coin: Argument, flash_res: FlashResult) -> Result<Argument> {
-        let package = ObjectID::from_hex_literal(KRIYA_CLMM)?;
+        let package = upgrades::package_id(descriptor::address("kriya_clmm", "package"))?;
         let module = Identifier::new("trade").map_err(|e| eyre!(e))?;
         let receipt = flash_res.receipt;
 
@@ -286,7 +283,7 @@ impl Dex for KriyaClmm {
     ) -> Result<Argument> {
         let function = if self.is_a2b() { "swap_a2b" } else { "swap_b2a" };
 
-        let package = ObjectID::from_hex_literal(CETUS_AGGREGATOR)?;
+        let package = upgrades::package_id(descriptor::address("cetus_aggregator", "package"))?;
         let module = Identifier::new("kriya_clmm").map_err(|e| eyre!(e))?;
         let function = Identifier::new(function).map_err(|e| eyre!(e))?;
         let type_arguments = self.type_params.clone();
@@ -317,6 +314,10 @@ impl Dex for KriyaClmm {
         self.pool.pool
     }
 
+    fn pool_arg(&self) -> Option<ObjectArg> {
+        Some(self.pool_arg.clone())
+    }
+
     fn flip(&mut self) {
         std::mem::swap(&mut self.coin_in_type, &mut self.coin_out_type);
     }
@@ -360,7 +361,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_kriya_clmm_swap_tx() {
-        mev_logger::init_console_logger_with_directives(None, &["arb=debug", "dex_indexer=debug"]);
+        mev_logger::init_console_logger_with_directives(None, &["poison_dart_core=debug", "dex_indexer=debug"]);
 
         let http_simulator = HttpSimulator::new(TEST_HTTP_URL, &None).await;
 
@@ -0,0 +1,754 @@
+#[cfg(feature = "aftermath")]
+mod aftermath;
+pub mod base;
+#[cfg(feature = "blue_move")]
+mod blue_move;
+#[cfg(feature = "cetus")]
+mod cetus;
+pub mod clmm_math;
+pub mod cp_math;
+#[cfg(feature = "deepbook_v2")]
+mod deepbook_v2;
+#[cfg(feature = "deepbook_v3")]
+pub mod deepbook_v3;
+pub mod descriptor;
+mod dex_enum;
+pub mod flash_lender;
+#[cfg(feature = "flowx_clmm")]
+mod flowx_clmm;
+mod indexer_searcher;
+#[cfg(feature = "kriya_amm")]
+mod kriya_amm;
+#[cfg(feature = "kriya_clmm")]
+mod kriya_clmm;
+mod navi;
+pub mod partner;
+pub mod pool_state;
+pub mod quote;
+pub mod registry;
+mod scallop;
+mod shio;
+mod template;
+mod trade;
+#[cfg(feature = "turbos")]
+mod turbos;
+pub mod upgrades;
+mod utils;
+
+use std::{
+    any::Any,
+    collections::{HashMap, HashSet},
+    fmt,
+    sync::Arc,
+};
+
+use ::utils::cache::CacheStats;
+pub use dex_enum::DexEnum;
+use dex_indexer::types::Protocol;
+use eyre::{bail, ensure, Result};
+pub use indexer_searcher::IndexerDexSearcher;
+use object_pool::ObjectPool;
+use simulator::{get_objects_consistent, SimulateCtx, Simulator};
+use sui_types::{
+    base_types::{ObjectID, ObjectRef, SequenceNumber, SuiAddress},
+    object::Owner,
+    transaction::{Argument, ObjectArg, TransactionData},
+};
+use tokio::task::JoinSet;
+use tracing::Instrument;
+pub use trade::{FlashResult, Path, TradeCtx, TradeResult, TradeType, Trader};
+pub use utils::set_deny_list_senders;
+
+use self::{cp_math::ConstantProduct, flash_lender::FlashLoanConfig, utils::new_object_read_result};
+use crate::{config::pegged_coin_types, graph_path_finder::GraphSettings, types::Source};
+
+/// Usage of the bounded caches behind dex discovery and trade building.
+pub async fn cache_stats() -> Vec<CacheStats> {
+    let mut stats = self::utils::cache_stats().await;
+    stats.push(template::cache_stats());
+    stats.push(pool_state::cache_stats());
+    stats.push(clmm_math::cache_stats());
+    stats.extend(indexer_searcher::indexer_cache_stats().await);
+    stats
+}
+
+const MAX_POOL_COUNT: usize = 10;
+// pools below this are skipped whatever `SUI_GRAPH_MIN_LIQUIDITY` says
+const MIN_LIQUIDITY: u128 = 1000;
+
+#[async_trait::async_trait]
+pub trait DexSearcher: Send + Sync {
+    // coin_type: e.g. "0x2::sui::SUI"
+    async fn find_dexes(&self, coin_in_type: &str, coin_out_type: Option<String>) -> Result<Vec<DexEnum>>;
+
+    async fn find_test_path(&self, path: &[ObjectID]) -> Result<Path>;
+
+    /// The dexes of pool `pool_id` out of `coin_in_type`, built from its current state.
+    async fn pool_dexes(&self, pool_id: &ObjectID, coin_in_type: &str) -> Result<Vec<DexEnum>>;
+
+    /// None if the decimals of `coin_type` are unknown.
+    async fn coin_decimals(&self, coin_type: &str) -> Option<u8>;
+
+    /// Whether the deny list of `coin_type` blocks the senders (denied or globally paused).
+    async fn is_blocked_coin(&self, coin_type: &str) -> bool;
+
+    /// Token vetting: only coins passing this are routed through. The base coin is always
+    /// traded, every cycle starts and ends in it.
+    async fn is_tradeable(&self, coin_type: &str) -> bool {
+        self.coin_decimals(coin_type).await.is_some() &&
+            (base::is_base_coin(coin_type) || !self.is_blocked_coin(coin_type).await)
+    }
+    
+    // Support for downcasting
+    #[allow(dead_code)]
+    fn as_any(&self) -> &dyn Any;
+}
+
+#[async_trait::async_trait]
+pub trait Dex: Send + Sync + CloneBoxedDex {
+    fn support_flashloan(&self) -> bool {
+        false
+    }
+
+    /// Extend the trade_tx with a flashloan tx.
+    /// Returns (coin_out, receipt).
+    async fn extend_flashloan_tx(&self, _ctx: &mut TradeCtx, _amount: u64) -> Result<FlashResult> {
+        bail!("flashloan not supported")
+    }
+
+    /// Extend the trade_tx with a repay tx.
+    /// Returns the coin_profit after repaying the flashloan.
+    async fn extend_repay_tx(&self, _ctx: &mut TradeCtx, _coin: Argument, _flash_res: FlashResult) -> Result<Argument> {
+        bail!("flashloan not supported")
+    }
+
+    /// Extend the trade_tx with a swap tx.
+    /// Returns coin_out for the next swap.
+    async fn extend_trade_tx(
+        &self,
+        ctx: &mut TradeCtx,
+        sender: SuiAddress,
+        coin_in: Argument,
+        amount_in: Option<u64>,
+    ) -> Result<Argument>;
+
+    fn coin_in_type(&self) -> String;
+    fn coin_out_type(&self) -> String;
+    fn protocol(&self) -> Protocol;
+    fn liquidity(&self) -> u128;
+    fn object_id(&self) -> ObjectID;
+
+    /// How swaps pass the pool object, None when it isn't a transaction input (e.g. a pool kept
+    /// in a registry).
+    fn pool_arg(&self) -> Option<ObjectArg> {
+        None
+    }
+
+    /// flip the coin_in_type and coin_out_type
+    fn flip(&mut self);
+
+    /// Fraction of the traded notional rebated to our partner account, if any.
+    fn partner_rebate_rate(&self) -> f64 {
+        0.0
+    }
+
+    /// Reserves and fee of a constant-product pool in the current direction, for quoting in
+    /// process (see `cp_math`). None for every other curve.
+    fn constant_product(&self) -> Option<ConstantProduct> {
+        None
+    }
+
+    /// Analytic output for `amount_in` from the cached pool state (see `quote`). None when the
+    /// curve has no local math, or no state to quote it from yet.
+    fn quote(&self, amount_in: u64) -> Option<u64> {
+        self.constant_product().map(|pool| pool.amount_out(amount_in))
+    }
+
+    // for debug
+    fn is_a2b(&self) -> bool;
+    async fn swap_tx(&self, sender: SuiAddress, recipient: SuiAddress, amount_in: u64) -> Result<TransactionData>;
+}
+
+pub trait CloneBoxedDex {
+    fn clone_boxed(&self) -> Box<dyn Dex>;
+}
+
+impl<T> CloneBoxedDex for T
+where
+    T: 'static + Dex + Clone,
+{
+    fn clone_boxed(&self) -> Box<dyn Dex> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn Dex> {
+    fn clone(&self) -> Box<dyn Dex> {
+        self.clone_boxed()
+    }
+}
+
+#[derive(Clone)]
+pub struct Defi {
+    dex_searcher: Arc<dyn DexSearcher>,
+    trader: Arc<Trader>,
+    simulator_pool: Arc<ObjectPool<Box<dyn Simulator>>>,
+    graph_settings: GraphSettings,
+}
+
+impl Defi {
+    /// Borrows through the flash lenders `flashloan` enables when the first pool of a path can't
+    /// lend.
+    pub async fn new(
+        http_url: &str,
+        simulator_pool: Arc<ObjectPool<Box<dyn Simulator>>>,
+        flashloan: &FlashLoanConfig,
+    ) -> Result<Self> {
+        let dex_searcher = IndexerDexSearcher::new(http_url, simulator_pool.clone()).await?;
+        let trade = Trader::new(simulator_pool.clone(), flashloan).await?;
+
+        Ok(Self {
+            dex_searcher: Arc::new(dex_searcher),
+            trader: Arc::new(trade),
+            simulator_pool,
+            graph_settings: GraphSettings::default(),
+        })
+    }
+
+    /// Find paths within the hop limit, liquidity floor and token lists of `settings`.
+    pub fn with_graph_settings(mut self, settings: GraphSettings) -> Self {
+        self.graph_settings = settings;
+        self
+    }
+    
+    // Get the dex searcher
+    pub fn get_dex_searcher(&self) -> Arc<dyn DexSearcher> {
+        self.dex_searcher.clone()
+    }
+
+    pub fn get_trader(&self) -> Arc<Trader> {
+        self.trader.clone()
+    }
+
+    #[allow(dead_code)]
+    pub async fn find_dexes(&self, coin_in_type: &str, coin_out_type: Option<String>) -> Result<Vec<DexEnum>> {
+        self.dex_searcher.find_dexes(coin_in_type, coin_out_type).await
+    }
+
+    pub async fn find_sell_paths(&self, coin_in_type: &str) -> Result<Vec<Path>> {
+        sell_paths(self.dex_searcher.as_ref(), &self.graph_settings, coin_in_type).await
+    }
+
+    pub async fn find_buy_paths(&self, coin_out_type: &str) -> Result<Vec<Path>> {
+        let mut paths = self.find_sell_paths(coin_out_type).await?;
+        for path in &mut paths {
+            path.path.reverse();
+            for dex in &mut path.path {
+                dex.flip();
+            }
+        }
+
+        Ok(paths)
+    }
+
+    /// Pin the pools of `paths` in `sim_ctx` at one consistent snapshot, so every quote against
+    /// these paths sees the same state. Objects already overridden (e.g. by the opportunity tx)
+    /// are left alone. The dynamic fields the simulator saw swaps through a pool read (e.g. CLMM
+    /// ticks) are pinned along with it; a pool without any is taken from `pool_state` when it saw
+    /// the pool recently. Pools keep the mutability swaps pass them with. Returns the pinned
+    /// versions.
+    pub async fn pin_path_objects<'a>(
+        &self,
+        paths: impl IntoIterator<Item = &'a Path>,
+        sim_ctx: &mut SimulateCtx,
+    ) -> Result<HashMap<ObjectID, SequenceNumber>> {
+        let overridden: HashSet<ObjectID> = sim_ctx.override_objects.iter().map(|o| o.id()).collect();
+        // pool => whether swaps take it mutably
+        let pools: HashMap<ObjectID, bool> = paths
+            .into_iter()
+            .flat_map(|path| &path.path)
+            .filter(|dex| !overridden.contains(&dex.object_id()))
+            .map(|dex| {
+                let mutable = matches!(dex.pool_arg(), Some(ObjectArg::SharedObject { mutable: true, .. }));
+                (dex.object_id(), mutable)
+            })
+            .collect();
+
+        let simulator = self.simulator_pool.get();
+        let mut objects = vec![];
+        let mut missing = HashSet::new();
+        for &id in pools.keys() {
+            let related = simulator.related_objects(&[id]);
+            match pool_state::get(&id) {
+                Some(obj) if related.is_empty() => objects.push(obj.as_ref().clone()),
+                _ => {
+                    missing.insert(id);
+                    missing.extend(related.into_iter().filter(|id| !overridden.contains(id)));
+                }
+            }
+        }
+
+        // of what the pools' swaps read, only the dynamic fields move with the pool
+        let missing: Vec<ObjectID> = missing.into_iter().collect();
+        let existing = simulator
+            .get_objects(&missing)
+            .await
+            .into_iter()
+            .flatten()
+            .filter(|obj| pools.contains_key(&obj.id()) || matches!(obj.owner(), Owner::ObjectOwner(_)))
+            .map(|obj| obj.id())
+            .collect::<Vec<_>>();
+        let fetched = get_objects_consistent(simulator.as_ref().as_ref(), &existing).await?;
+        fetched
+            .iter()
+            .filter(|obj| pools.contains_key(&obj.id()))
+            .for_each(pool_state::insert);
+        objects.extend(fetched);
+        let versions = objects.iter().map(|obj| (obj.id(), obj.version())).collect();
+        sim_ctx.override_objects.extend(objects.into_iter().map(|obj| {
+            let mutable = pools.get(&obj.id()).copied().unwrap_or_default();
+            new_object_read_result(obj, mutable)
+        }));
+
+        Ok(versions)
+    }
+
+    pub async fn find_best_path_exact_in(
+        &self,
+        paths: &[Path],
+        sender: SuiAddress,
+        amount_in: u64,
+        trade_type: TradeType,
+        gas_coins: &[ObjectRef],
+        sim_ctx: &SimulateCtx,
+    ) -> Result<PathTradeResult> {
+        let mut joinset = JoinSet::new();
+
+        for (idx, path) in paths.iter().enumerate() {
+            if path.is_empty() {
+                continue;
+            }
+
+            let trade = self.trader.clone();
+            let path = path.clone();
+            let gas_coins = gas_coins.to_vec();
+            let sim_ctx = sim_ctx.clone();
+
+            joinset.spawn(
+                async move {
+                    let result = trade
+                        .get_trade_result(&path, sender, amount_in, trade_type, gas_coins, sim_ctx)
+                        .await;
+
+                    (idx, result)
+                }
+                .in_current_span(),
+            );
+        }
+
+        let (mut best_idx, mut best_trade_res) = (0, TradeResult::default());
+        while let Some(Ok((idx, trade_res))) = joinset.join_next().await {
+            match trade_res {
+                Ok(trade_res) => {
+                    if trade_res > best_trade_res {
+                        best_idx = idx;
+                        best_trade_res = trade_res;
+                    }
+                }
+                Err(_error) => {
+                    // tracing::error!(path = ?paths[idx], ?error, "trade
+                    // error");
+                }
+            }
+        }
+
+        ensure!(best_trade_res.amount_out > 0, "zero amount_out");
+
+        Ok(PathTradeResult::new(paths[best_idx].clone(), amount_in, best_trade_res))
+    }
+
+    pub async fn build_final_tx_data(
+        &self,
+        sender: SuiAddress,
+        amount_in: u64,
+        path: &Path,
+        gas_coins: Vec<ObjectRef>,
+        gas_price: u64,
+        source: Source,
+    ) -> Result<TransactionData> {
+        let (tx_data, _) = self
+            .trader
+            .get_flashloan_trade_tx(path, sender, amount_in, gas_coins, gas_price, source)
+            .await?;
+
+        Ok(tx_data)
+    }
+
+    /// A public trade of several routes, each a path and its input, in one atomic PTB.
+    pub async fn build_combined_tx_data(
+        &self,
+        sender: SuiAddress,
+        routes: &[(Path, u64)],
+        gas_coins: Vec<ObjectRef>,
+        gas_price: u64,
+    ) -> Result<TransactionData> {
+        self.trader
+            .get_multi_flashloan_trade_tx(routes, sender, gas_coins, gas_price)
+            .await
+    }
+}
+
+/// Paths selling `coin_in_type` for the base coin through the pools `dex_searcher` knows, what
+/// `Defi::find_sell_paths` searches. A cycle is a buy path into the coin then a sell path out of
+/// it, so each gets half of `max_cycle_len` swaps; pools under the liquidity floor and coins the
+/// token lists keep out are skipped.
+pub async fn sell_paths(
+    dex_searcher: &dyn DexSearcher,
+    settings: &GraphSettings,
+    coin_in_type: &str,
+) -> Result<Vec<Path>> {
+    if base::is_base_coin(coin_in_type) {
+        return Ok(vec![Path::default()]);
+    }
+
+    let max_hops = settings.config().max_cycle_len / 2;
+    let min_liquidity = settings.config().min_pool_liquidity.max(MIN_LIQUIDITY);
+    let tokens = settings.token_lists();
+
+    let mut all_hops = HashMap::new();
+    let mut stack = vec![coin_in_type.to_string()];
+    let mut visited = HashSet::new();
+    let mut visited_dexes = HashSet::new();
+
+    for nth_hop in 0..max_hops {
+        let is_last_hop = nth_hop == max_hops - 1;
+        let mut new_stack = vec![];
+
+        while let Some(coin_type) = stack.pop() {
+            if visited.contains(&coin_type) || base::is_base_coin(&coin_type) {
+                continue;
+            }
+            visited.insert(coin_type.clone());
+
+            if !tokens.admits(&coin_type) || !dex_searcher.is_tradeable(&coin_type).await {
+                continue;
+            }
+
+            // pegged coins trade against SUI directly
+            let is_pegged = base::is_sui() && pegged_coin_types().contains(coin_type.as_str());
+            let coin_out_type = if is_pegged || is_last_hop {
+                Some(base::coin_type().to_string())
+            } else {
+                None
+            };
+            let mut dexes = if let Ok(dexes) = dex_searcher.find_dexes(&coin_type, coin_out_type).await {
+                dexes
+            } else {
+                continue;
+            };
+
+            dexes.retain(|dex| dex.liquidity() >= min_liquidity && tokens.admits(&dex.coin_out_type()));
+
+            if dexes.len() > MAX_POOL_COUNT {
+                dexes.retain(|dex| !visited_dexes.contains(&dex.object_id()));
+                dexes.sort_by_key(|dex| std::cmp::Reverse(dex.liquidity()));
+                dexes.truncate(MAX_POOL_COUNT);
+            }
+
+            if dexes.is_empty() {
+                continue;
+            }
+
+            for dex in &dexes {
+                let out_coin_type = dex.coin_out_type();
+                if !visited.contains(&out_coin_type) {
+                    new_stack.push(out_coin_type.clone());
+                }
+                visited_dexes.insert(dex.object_id());
+            }
+            all_hops.insert(coin_type.clone(), dexes);
+        }
+
+        if is_last_hop {
+            break;
+        }
+
+        stack = new_stack;
+    }
+
+    let mut routes = vec![];
+    dfs(coin_in_type, max_hops, &mut vec![], &all_hops, &mut routes);
+
+    Ok(routes.into_iter().map(Path::new).collect())
+}
+
+fn dfs(
+    coin_type: &str,
+    max_hops: usize,
+    path: &mut Vec<DexEnum>,
+    hops: &HashMap<String, Vec<DexEnum>>,
+    routes: &mut Vec<Vec<DexEnum>>,
+) {
+    if base::is_base_coin(coin_type) {
+        routes.push(path.clone());
+        return;
+    }
+    if path.len() >= max_hops {
+        return;
+    }
+    if !hops.contains_key(coin_type) {
+        return;
+    }
+    for dex in hops.get(coin_type).unwrap() {
+        path.push(dex.clone());
+        dfs(&dex.coin_out_type(), max_hops, path, hops, routes);
+        path.pop();
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PathTradeResult {
+    pub path: Path,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub gas_cost: i64,
+    pub cache_misses: u64,
+}
+
+impl PathTradeResult {
+    pub fn new(path: Path, amount_in: u64, trade_res: TradeResult) -> Self {
+        Self {
+            path,
+            amount_in,
+            amount_out: trade_res.amount_out,
+            gas_cost: trade_res.gas_cost,
+            cache_misses: trade_res.cache_misses,
+        }
+    }
+
+    /// Net profit in the base coin, gas included.
+    pub fn profit(&self) -> i128 {
+        if base::is_base_coin(&self.path.coin_in_type()) {
+            let gas_cost = base::gas_in_base(self.gas_cost);
+            if base::is_base_coin(&self.path.coin_out_type()) {
+                return self.amount_out as i128 - self.amount_in as i128 - gas_cost;
+            }
+            0 - gas_cost - self.amount_in as i128
+        } else {
+            0
+        }
+    }
+}
+
+impl fmt::Display for PathTradeResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "PathTradeResult {{ amount_in: {}, amount_out: {}, profit: {}, path: {:?} ... }}",
+            self.amount_in,
+            self.amount_out,
+            self.profit(),
+            self.path
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use simulator::HttpSimulator;
+    use sui_sdk::SUI_COIN_TYPE;
+    use tracing::info;
+
+    use super::*;
+    use crate::{
+        config::tests::TEST_HTTP_URL,
+        graph_path_finder::{GraphConfig, TokenLists},
+    };
+
+    #[tokio::test]
+    async fn test_find_sell_paths() {
+        mev_logger::init_console_logger_with_directives(None, &["poison_dart_core=debug", "dex_indexer=debug"]);
+
+        let simulator_pool = ObjectPool::new(1, move || {
+            tokio::runtime::Runtime::new()
+                .unwrap()
+                .block_on(async { Box::new(HttpSimulator::new(&TEST_HTTP_URL, &None).await) as Box<dyn Simulator> })
+        });
+
+        let defi = Defi::new(TEST_HTTP_URL, Arc::new(simulator_pool), &FlashLoanConfig::default())
+            .await
+            .unwrap();
+
+        let coin_in_type = "0xa8816d3a6e3136e86bc2873b1f94a15cadc8af2703c075f2d546c2ae367f4df9::ocean::OCEAN";
+        let paths = defi.find_sell_paths(coin_in_type).await.unwrap();
+        assert!(!paths.is_empty(), "No sell paths found");
+
+        for path in paths {
+            info!(?path, "sell")
+        }
+    }
+
+    #[tokio::test]
+    async fn test_find_buy_paths() {
+        mev_logger::init_console_logger_with_directives(None, &["poison_dart_core=debug", "dex_indexer=debug"]);
+
+        let simulator_pool = ObjectPool::new(1, move || {
+            tokio::runtime::Runtime::new()
+                .unwrap()
+                .block_on(async { Box::new(HttpSimulator::new(&TEST_HTTP_URL, &None).await) as Box<dyn Simulator> })
+        });
+
+        let defi = Defi::new(TEST_HTTP_URL, Arc::new(simulator_pool), &FlashLoanConfig::default())
+            .await
+            .unwrap();
+
+        let coin_out_type = "0xa8816d3a6e3136e86bc2873b1f94a15cadc8af2703c075f2d546c2ae367f4df9::ocean::OCEAN";
+        let paths = defi.find_buy_paths(coin_out_type).await.unwrap();
+        assert!(!paths.is_empty(), "No buy paths found");
+        for path in paths {
+            info!(?path, "buy")
+        }
+    }
+
+    #[derive(Clone)]
+    struct TestPool {
+        id: ObjectID,
+        coin_in: &'static str,
+        coin_out: &'static str,
+        liquidity: u128,
+    }
+
+    #[async_trait::async_trait]
+    impl Dex for TestPool {
+        async fn extend_trade_tx(
+            &self,
+            _ctx: &mut TradeCtx,
+            _sender: SuiAddress,
+            _coin_in: Argument,
+            _amount_in: Option<u64>,
+        ) -> Result<Argument> {
+            bail!("test pool")
+        }
+
+        fn coin_in_type(&self) -> String {
+            self.coin_in.to_string()
+        }
+
+        fn coin_out_type(&self) -> String {
+            self.coin_out.to_string()
+        }
+
+        fn protocol(&self) -> Protocol {
+            Protocol::Cetus
+        }
+
+        fn liquidity(&self) -> u128 {
+            self.liquidity
+        }
+
+        fn object_id(&self) -> ObjectID {
+            self.id
+        }
+
+        fn flip(&mut self) {
+            std::mem::swap(&mut self.coin_in, &mut self.coin_out);
+        }
+
+        fn is_a2b(&self) -> bool {
+            true
+        }
+
+        async fn swap_tx(
+            &self,
+            _sender: SuiAddress,
+            _recipient: SuiAddress,
+            _amount_in: u64,
+        ) -> Result<TransactionData> {
+            bail!("test pool")
+        }
+    }
+
+    /// Knows a fixed set of pools, every coin has decimals and none is blocked.
+    struct TestPools(Vec<TestPool>);
+
+    #[async_trait::async_trait]
+    impl DexSearcher for TestPools {
+        async fn find_dexes(&self, coin_in_type: &str, coin_out_type: Option<String>) -> Result<Vec<DexEnum>> {
+            Ok(self
+                .0
+                .iter()
+                .filter(|pool| pool.coin_in == coin_in_type)
+                .filter(|pool| {
+                    coin_out_type
+                        .as_deref()
+                        .map_or(true, |coin_out| pool.coin_out == coin_out)
+                })
+                .map(|pool| DexEnum::Other(Box::new(pool.clone())))
+                .collect())
+        }
+
+        async fn find_test_path(&self, _path: &[ObjectID]) -> Result<Path> {
+            bail!("test pools")
+        }
+
+        async fn pool_dexes(&self, _pool_id: &ObjectID, _coin_in_type: &str) -> Result<Vec<DexEnum>> {
+            bail!("test pools")
+        }
+
+        async fn coin_decimals(&self, _coin_type: &str) -> Option<u8> {
+            Some(9)
+        }
+
+        async fn is_blocked_coin(&self, _coin_type: &str) -> bool {
+            false
+        }
+
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sell_paths_follow_graph_settings() {
+        let pool = |id: u8, coin_in, coin_out, liquidity| TestPool {
+            id: ObjectID::from_single_byte(id),
+            coin_in,
+            coin_out,
+            liquidity,
+        };
+        let (coin, other) = ("0xa::a::A", "0xb::b::B");
+        let pools = TestPools(vec![
+            pool(1, coin, SUI_COIN_TYPE, 1_000_000),
+            pool(2, coin, SUI_COIN_TYPE, 5_000),
+            pool(3, coin, other, 1_000_000),
+            pool(4, other, SUI_COIN_TYPE, 1_000_000),
+        ]);
+        let pool_ids = |paths: Vec<Path>| {
+            let mut ids: Vec<Vec<ObjectID>> = paths.iter().map(|path| path.object_ids().collect()).collect();
+            ids.sort();
+            ids
+        };
+        let id = ObjectID::from_single_byte;
+
+        let settings = GraphSettings::default();
+        let paths = sell_paths(&pools, &settings, coin).await.unwrap();
+        assert_eq!(pool_ids(paths), vec![vec![id(1)], vec![id(2)], vec![id(3), id(4)]]);
+
+        let settings = GraphSettings::new(GraphConfig {
+            max_cycle_len: 3,
+            min_pool_liquidity: 10_000,
+            ..Default::default()
+        })
+        .unwrap();
+        let paths = sell_paths(&pools, &settings, coin).await.unwrap();
+        assert_eq!(pool_ids(paths), vec![vec![id(1)]]);
+
+        let settings = GraphSettings::default();
+        settings.set_token_lists(TokenLists::new(None, vec![other.to_string()]));
+        let paths = sell_paths(&pools, &settings, coin).await.unwrap();
+        assert_eq!(pool_ids(paths), vec![vec![id(1)], vec![id(2)]]);
+        settings.set_token_lists(TokenLists::new(None, vec![coin.to_string()]));
+        assert!(sell_paths(&pools, &settings, coin).await.unwrap().is_empty());
+    }
+}
@@ -1,21 +1,21 @@
 use std::{str::FromStr, sync::Arc};
 
+use async_trait::async_trait;
 use eyre::{eyre, OptionExt, Result};
 use simulator::Simulator;
 use sui_sdk::SUI_COIN_TYPE;
 use sui_types::{
-    base_types::ObjectID,
     transaction::{Argument, Command, ObjectArg},
     Identifier, TypeTag, SUI_CLOCK_OBJECT_ID,
 };
-use utils::object::shared_obj_arg;
+use utils::{coin, object::shared_obj_arg};
 
-use super::{trade::FlashResult, TradeCtx};
-
-const NAVI_PROTOCOL: &str = "0x834a86970ae93a73faf4fff16ae40bdb72b91c47be585fff19a2af60a19ddca3";
-const NAVI_POOL: &str = "0x96df0fce3c471489f4debaaa762cf960b3d97820bd1f3f025ff8190730e958c5";
-const NAVI_CONFIG: &str = "0x3672b2bf471a60c30a03325f104f92fb195c9d337ba58072dce764fe2aa5e2dc";
-const NAVI_STORAGE: &str = "0xbb4e2f4b6205c2e2a2db47aeb4f830796ec7c005f88537ee775986639bc442fe";
+use super::{
+    descriptor,
+    flash_lender::{self, FlashLender},
+    trade::FlashResult,
+    upgrades, TradeCtx,
+};
 
 #[derive(Clone)]
 pub struct Navi {
@@ -24,21 +24,22 @@ pub struct Navi {
     config: ObjectArg,
     storage: ObjectArg,
     clock: ObjectArg,
+    fee_bps: u64,
 }
 
 impl Navi {
     // Objects are fetched only once during initialization, without affecting the arbitrage performance.
-    pub async fn new(simulator: Arc<Box<dyn Simulator>>) -> Result<Self> {
+    pub async fn new(simulator: Arc<Box<dyn Simulator>>, fee_bps: u64) -> Result<Self> {
         let pool = simulator
-            .get_object(&ObjectID::from_hex_literal(NAVI_POOL)?)
+            .get_object(&descriptor::object_id("navi", "pool"))
             .await
             .ok_or_eyre("navi pool not found")?;
         let config = simulator
-            .get_object(&ObjectID::from_hex_literal(NAVI_CONFIG)?)
+            .get_object(&descriptor::object_id("navi", "config"))
             .await
             .ok_or_eyre("navi config not found")?;
         let storage = simulator
-            .get_object(&ObjectID::from_hex_literal(NAVI_STORAGE)?)
+            .get_object(&descriptor::object_id("navi", "storage"))
             .await
             .ok_or_eyre("navi storage not found")?;
         let clock = simulator
@@ -52,8 +53,25 @@ impl Navi {
             config: shared_obj_arg(&config, false),
             storage: shared_obj_arg(&storage, true),
             clock: shared_obj_arg(&clock, false),
+            fee_bps,
         })
     }
+}
+
+// Navi pools are per coin; only the SUI pool is wired up.
+#[async_trait]
+impl FlashLender for Navi {
+    fn name(&self) -> &'static str {
+        "navi"
+    }
+
+    fn lends(&self, coin_type: &str) -> bool {
+        coin::is_native_coin(coin_type)
+    }
+
+    fn fee(&self, _coin_type: &str, amount: u64) -> u64 {
+        flash_lender::fee_of(amount, self.fee_bps)
+    }
 
     /*
     public fun flash_loan_with_ctx<CoinType>(
@@ -63,8 +81,8 @@ impl Navi {
         ctx: &mut TxContext
     ): (Balance<CoinType>, FlashLoanReceipt<CoinType>)
     */
-    pub fn extend_flashloan_tx(&self, ctx: &mut TradeCtx, amount_in: u64) -> Result<FlashResult> {
-        let package = ObjectID::from_hex_literal(NAVI_PROTOCOL)?;
+    fn extend_flashloan_tx(&self, ctx: &mut TradeCtx, _coin_type: &str, amount_in: u64) -> Result<FlashResult> {
+        let package = upgrades::package_id(descriptor::address("navi", "package"))?;
         let module = Identifier::new("lending").map_err(|e| eyre!(e))?;
         let function = Identifier::new("flash_loan_with_ctx").map_err(|e| eyre!(e))?;
         let type_arguments = vec![self.sui_coin_type.clone()];
@@ -98,8 +116,15 @@ impl Navi {
         ctx: &mut TxContext
     ): Balance<CoinType>
     */
-    pub fn extend_repay_tx(&self, ctx: &mut TradeCtx, coin: Argument, flash_res: FlashResult) -> Result<Argument> {
-        let package = ObjectID::from_hex_literal(NAVI_PROTOCOL)?;
+    fn extend_repay_tx(
+        &self,
+        ctx: &mut TradeCtx,
+        _coin_type: &str,
+        coin: Argument,
+        _amount: u64,
+        flash_res: FlashResult,
+    ) -> Result<Argument> {
+        let package = upgrades::package_id(descriptor::address("navi", "package"))?;
         let module = Identifier::new("lending").map_err(|e| eyre!(e))?;
         let function = Identifier::new("flash_repay_with_ctx").map_err(|e| eyre!(e))?;
         let type_arguments = vec![self.sui_coin_type.clone()];
@@ -0,0 +1,78 @@
+//! Partner (referral) configuration for protocols that rebate part of the swap fee.
+//!
+//! `registry::register_partners` hands it to the factories of those protocols, whose edges pass our
+//! partner object to their swaps.
+
+use clap::Parser;
+use dex_indexer::types::Protocol;
+use eyre::{ensure, eyre, Result};
+use sui_types::base_types::ObjectID;
+
+// protocols whose swaps take a partner object
+const PARTNER_PROTOCOLS: &[Protocol] = &[Protocol::Cetus];
+
+#[derive(Clone, Debug, Default, Parser)]
+pub struct PartnerConfig {
+    /// Cetus `Partner` object credited with the referral share of every Cetus swap
+    #[arg(long, env = "SUI_CETUS_PARTNER_ID", value_parser = parse_object_id)]
+    pub cetus_partner: Option<ObjectID>,
+
+    /// Partner objects per protocol, as `protocol=object_id`; override `--cetus-partner`
+    #[arg(long, env = "SUI_PARTNERS", value_delimiter = ',', value_parser = parse_protocol_partner)]
+    pub partners: Vec<(Protocol, ObjectID)>,
+}
+
+fn parse_object_id(s: &str) -> Result<ObjectID> {
+    Ok(ObjectID::from_hex_literal(s)?)
+}
+
+fn parse_protocol_partner(s: &str) -> Result<(Protocol, ObjectID)> {
+    let (protocol, partner) = s
+        .split_once('=')
+        .ok_or_else(|| eyre!("expected protocol=object_id: {}", s))?;
+    let protocol = Protocol::try_from(protocol.trim())?;
+    ensure!(
+        PARTNER_PROTOCOLS.contains(&protocol),
+        "{} swaps take no partner",
+        protocol
+    );
+
+    Ok((protocol, parse_object_id(partner.trim())?))
+}
+
+impl PartnerConfig {
+    /// Our partner object for `protocol`, None when swaps there earn us nothing.
+    pub fn partner(&self, protocol: &Protocol) -> Option<ObjectID> {
+        let configured = self.partners.iter().rev().find(|(p, _)| p == protocol);
+        match configured {
+            Some((_, partner)) => Some(*partner),
+            None if *protocol == Protocol::Cetus => self.cetus_partner,
+            None => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partner_per_protocol() {
+        let cetus = ObjectID::from_single_byte(1);
+        let config = PartnerConfig {
+            cetus_partner: Some(ObjectID::from_single_byte(2)),
+            partners: vec![parse_protocol_partner("cetus=0x1").unwrap()],
+        };
+        assert_eq!(config.partner(&Protocol::Cetus), Some(cetus));
+        assert_eq!(config.partner(&Protocol::Turbos), None);
+
+        let config = PartnerConfig {
+            cetus_partner: Some(cetus),
+            partners: vec![],
+        };
+        assert_eq!(config.partner(&Protocol::Cetus), Some(cetus));
+
+        assert!(parse_protocol_partner("turbos=0x1").is_err());
+        assert!(parse_protocol_partner("0x1").is_err());
+    }
+}
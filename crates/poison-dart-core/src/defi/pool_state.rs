@@ -0,0 +1,126 @@
+//! Live state of pool objects, shared by quoting and execution.
+//!
+//! `Defi::pin_path_objects` stores every pool object it reads, at the version it quoted, and the
+//! collectors report every newer version they see a transaction write (`observe_version`). Both
+//! sides read it back: quoting reuses an object the cache saw recently instead of refetching it,
+//! and before submitting, a worker asks `status` whether the objects its decision was made on
+//! moved since, so only objects the cache can't vouch for are read again.
+
+use std::{
+    sync::{Arc, Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+use cached::{Cached, TimedSizedCache};
+use sui_types::{
+    base_types::{ObjectID, SequenceNumber},
+    object::Object,
+};
+use utils::cache::{CacheLimits, CacheStats};
+
+const POOL_STATE_CACHE_SIZE: usize = 50_000;
+const POOL_STATE_TTL: Duration = Duration::from_secs(60);
+// how long a version nobody reported a write over is trusted to still be the latest
+const MAX_TRUSTED_AGE: Duration = Duration::from_secs(2);
+
+struct Entry {
+    version: SequenceNumber,
+    // None once a newer version was observed but not read yet
+    object: Option<Arc<Object>>,
+    seen_at: Instant,
+}
+
+static POOL_STATE: OnceLock<Mutex<TimedSizedCache<ObjectID, Entry>>> = OnceLock::new();
+
+fn pool_state() -> &'static Mutex<TimedSizedCache<ObjectID, Entry>> {
+    POOL_STATE.get_or_init(|| {
+        let limits = CacheLimits::from_env("POOL_STATE", POOL_STATE_CACHE_SIZE, POOL_STATE_TTL);
+        Mutex::new(limits.new_cache())
+    })
+}
+
+/// Whether an object is still at the version a decision was made on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Unchanged,
+    Drifted,
+    /// The cache can't tell; read the object.
+    Unknown,
+}
+
+/// Store an object read from the chain, unless a newer version is already known.
+pub fn insert(object: &Object) {
+    let mut cache = pool_state().lock().unwrap();
+    if matches!(cache.cache_get(&object.id()), Some(entry) if entry.version > object.version()) {
+        return;
+    }
+    cache.cache_set(
+        object.id(),
+        Entry {
+            version: object.version(),
+            object: Some(Arc::new(object.clone())),
+            seen_at: Instant::now(),
+        },
+    );
+}
+
+/// A transaction wrote `id` at `version`. Only objects already cached are tracked.
+pub fn observe_version(id: ObjectID, version: SequenceNumber) {
+    let mut cache = pool_state().lock().unwrap();
+    if let Some(entry) = cache.cache_get_mut(&id) {
+        if version > entry.version {
+            entry.version = version;
+            entry.object = None;
+        }
+        entry.seen_at = Instant::now();
+    }
+}
+
+/// The latest known state of `id`, if it was read recently.
+pub fn get(id: &ObjectID) -> Option<Arc<Object>> {
+    let mut cache = pool_state().lock().unwrap();
+    let entry = cache.cache_get(id)?;
+    if entry.seen_at.elapsed() > MAX_TRUSTED_AGE {
+        return None;
+    }
+    entry.object.clone()
+}
+
+pub fn status(id: &ObjectID, version: SequenceNumber) -> Status {
+    let mut cache = pool_state().lock().unwrap();
+    match cache.cache_get(id) {
+        Some(entry) if entry.version > version => Status::Drifted,
+        Some(entry) if entry.version == version && entry.seen_at.elapsed() <= MAX_TRUSTED_AGE => Status::Unchanged,
+        _ => Status::Unknown,
+    }
+}
+
+pub fn cache_stats() -> CacheStats {
+    CacheStats::of("pool_state", &*pool_state().lock().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use sui_types::base_types::SuiAddress;
+
+    use super::*;
+
+    #[test]
+    fn test_observed_write_drifts_the_quoted_version() {
+        let object = Object::with_id_owner_gas_for_testing(ObjectID::random(), SuiAddress::ZERO, 1);
+        let (id, version) = (object.id(), object.version());
+        assert_eq!(status(&id, version), Status::Unknown);
+
+        insert(&object);
+        assert_eq!(status(&id, version), Status::Unchanged);
+        assert!(get(&id).is_some());
+
+        observe_version(id, SequenceNumber::from_u64(version.value() + 1));
+        assert_eq!(status(&id, version), Status::Drifted);
+        // the new state itself wasn't read yet
+        assert!(get(&id).is_none());
+        // a late read of the old version doesn't roll the entry back
+        insert(&object);
+        assert_eq!(status(&id, version), Status::Drifted);
+    }
+}
@@ -0,0 +1,115 @@
+//! Analytic quotes from the pool state a `Dex` was loaded with.
+//!
+//! `Dex::quote` prices a swap without the simulator: constant-product pools through `cp_math`,
+//! Aftermath's weighted pools and Kriya's stable pools through their own curves, and Cetus and
+//! Turbos through their ticks (`clmm_math`) once those were fetched. A quote is only as fresh as
+//! the cached reserves and ignores anything the Move code does beyond the curve, so it is good for
+//! weighting edges, ranking and discarding paths, never for what gets submitted.
+
+use super::Path;
+
+/// Output of `path` for `amount_in`, hop by hop. None if any hop can't be quoted.
+pub fn path_quote(path: &Path, amount_in: u64) -> Option<u64> {
+    if path.is_empty() {
+        return None;
+    }
+    path.path.iter().try_fold(amount_in, |amount, dex| dex.quote(amount))
+}
+
+/// Round-trip return of the cycle `path` for `amount_in` in basis points: 10_000 gives back
+/// exactly what went in. None if any hop can't be quoted.
+pub fn path_return_bps(path: &Path, amount_in: u64) -> Option<i64> {
+    if amount_in == 0 {
+        return None;
+    }
+    let amount_out = path_quote(path, amount_in)?;
+    Some((amount_out as u128 * 10_000 / amount_in as u128).min(i64::MAX as u128) as i64)
+}
+
+/// One direction of a stable pool on the `x^3 y + x y^3 = k` curve. Reserves are raw amounts,
+/// `scale_*` is `10^decimals` of each coin, and the pool keeps `fee_num / fee_den` of the input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StableSwap {
+    pub reserve_in: u64,
+    pub reserve_out: u64,
+    pub scale_in: u64,
+    pub scale_out: u64,
+    pub fee_num: u64,
+    pub fee_den: u64,
+}
+
+impl StableSwap {
+    const NEWTON_ROUNDS: usize = 255;
+
+    pub fn amount_out(&self, amount_in: u64) -> u64 {
+        if self.scale_in == 0 || self.scale_out == 0 || self.fee_den == 0 {
+            return 0;
+        }
+        let fee = amount_in as u128 * self.fee_num as u128 / self.fee_den as u128;
+        let amount_in = (amount_in as u128 - fee) as f64 / self.scale_in as f64;
+        let x = self.reserve_in as f64 / self.scale_in as f64;
+        let y = self.reserve_out as f64 / self.scale_out as f64;
+        let k = Self::k(x, y);
+
+        let new_y = Self::solve_y(x + amount_in, k, y);
+        let amount_out = (y - new_y).max(0.0) * self.scale_out as f64;
+        // rounded down, never above the reserve
+        (amount_out.floor() as u64).min(self.reserve_out)
+    }
+
+    fn k(x: f64, y: f64) -> f64 {
+        x * y * (x * x + y * y)
+    }
+
+    // Newton's method on f(y) = x^3 y + x y^3 - k, from the current reserve
+    fn solve_y(x: f64, k: f64, mut y: f64) -> f64 {
+        for _ in 0..Self::NEWTON_ROUNDS {
+            let f = Self::k(x, y) - k;
+            let df = x * x * x + 3.0 * x * y * y;
+            if df == 0.0 {
+                break;
+            }
+            let next = y - f / df;
+            if (next - y).abs() <= f64::EPSILON * y.max(1.0) {
+                return next;
+            }
+            y = next;
+        }
+        y
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stable_swap_amount_out() {
+        // balanced pool of two 6-decimal stables, 0.01% fee
+        let pool = StableSwap {
+            reserve_in: 1_000_000_000_000,
+            reserve_out: 1_000_000_000_000,
+            scale_in: 1_000_000,
+            scale_out: 1_000_000,
+            fee_num: 100,
+            fee_den: 1_000_000,
+        };
+        // near the peg the curve is flat: 1000 in, about 999.9 out
+        let out = pool.amount_out(1_000_000_000);
+        assert!((999_800_000..=999_900_000).contains(&out), "{}", out);
+        assert_eq!(pool.amount_out(0), 0);
+
+        // draining most of one side gets expensive
+        let out = pool.amount_out(2_000_000_000_000);
+        assert!(out < pool.reserve_out && out < 1_000_000_000_000, "{}", out);
+
+        // coins of different decimals trade at the scaled price
+        let mixed = StableSwap {
+            scale_out: 1_000_000_000,
+            reserve_out: 1_000_000_000_000_000,
+            ..pool
+        };
+        let out = mixed.amount_out(1_000_000);
+        assert!((999_000_000..=1_000_000_000).contains(&out), "{}", out);
+    }
+}
@@ -0,0 +1,203 @@
+//! Protocol registry: maps a `Protocol` to the factory that turns an indexed pool into `Dex` edges.
+//!
+//! Built-in venues register themselves here when their Cargo feature is enabled, which also compiles
+//! their parsers into `dex-indexer`. `register_dex_factory` replaces the factory of one of them at
+//! startup, before the searcher is used, e.g. to wrap its edges in a `Dex` of the caller's, handed
+//! back as `DexEnum::Other(Box<dyn Dex>)`. `Protocol` is a closed enum: a new venue needs a variant
+//! and an indexer parser of its own. `register_partners` likewise swaps in the factories of the
+//! protocols that rebate swaps to our partner objects.
+//!
+//! `set_protocol_allowlist` restricts the bot to some protocols for the life of the process: the
+//! indexer only loads the pools of the protocols in `indexed_protocols`. A registered protocol can
+//! still be switched off at runtime with `set_disabled_protocols`.
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, OnceLock, RwLock},
+};
+
+use async_trait::async_trait;
+use dex_indexer::types::{Pool, Protocol};
+use eyre::Result;
+use simulator::Simulator;
+
+use super::{partner::PartnerConfig, DexEnum};
+
+#[async_trait]
+pub trait DexFactory: Send + Sync {
+    /// One `Dex` per directed edge of `pool` leaving `coin_in_type` (restricted to `coin_out_type` if set).
+    async fn new_dexes(
+        &self,
+        simulator: Arc<Box<dyn Simulator>>,
+        pool: &Pool,
+        coin_in_type: &str,
+        coin_out_type: Option<String>,
+    ) -> Result<Vec<DexEnum>>;
+}
+
+static REGISTRY: OnceLock<RwLock<HashMap<Protocol, Arc<dyn DexFactory>>>> = OnceLock::new();
+static DISABLED: OnceLock<RwLock<HashSet<Protocol>>> = OnceLock::new();
+static ALLOWLIST: OnceLock<HashSet<Protocol>> = OnceLock::new();
+
+fn registry() -> &'static RwLock<HashMap<Protocol, Arc<dyn DexFactory>>> {
+    REGISTRY.get_or_init(|| RwLock::new(builtin_factories()))
+}
+
+#[allow(dead_code)]
+pub fn register_dex_factory(protocol: Protocol, factory: Arc<dyn DexFactory>) {
+    registry().write().unwrap().insert(protocol, factory);
+}
+
+pub fn dex_factory(protocol: &Protocol) -> Option<Arc<dyn DexFactory>> {
+    registry().read().unwrap().get(protocol).cloned()
+}
+
+/// Only ever use `protocols`. Must run before the indexer is first used; later calls are ignored.
+pub fn set_protocol_allowlist(protocols: HashSet<Protocol>) {
+    let _ = ALLOWLIST.set(protocols);
+}
+
+fn is_protocol_allowed(protocol: &Protocol) -> bool {
+    ALLOWLIST.get().map_or(true, |allowed| allowed.contains(protocol))
+}
+
+/// The indexable protocols with a registered factory, restricted to the allowlist.
+pub fn indexed_protocols() -> Vec<Protocol> {
+    dex_indexer::supported_protocols()
+        .into_iter()
+        .filter(|protocol| dex_factory(protocol).is_some() && is_protocol_allowed(protocol))
+        .collect()
+}
+
+/// Protocols switched off by the operator; pools loaded from then on skip them.
+pub fn set_disabled_protocols(protocols: HashSet<Protocol>) {
+    *DISABLED.get_or_init(Default::default).write().unwrap() = protocols;
+}
+
+pub fn is_protocol_enabled(protocol: &Protocol) -> bool {
+    is_protocol_allowed(protocol) &&
+        DISABLED
+            .get()
+            .map_or(true, |disabled| !disabled.read().unwrap().contains(protocol))
+}
+
+// Pools that are a single a<->b edge.
+#[allow(unused_macros)]
+macro_rules! pair_dex_factory {
+    ($factory:ident, $dex:ty) => {
+        struct $factory;
+
+        #[async_trait]
+        impl DexFactory for $factory {
+            async fn new_dexes(
+                &self,
+                simulator: Arc<Box<dyn Simulator>>,
+                pool: &Pool,
+                coin_in_type: &str,
+                _coin_out_type: Option<String>,
+            ) -> Result<Vec<DexEnum>> {
+                let dex = <$dex>::new(simulator, pool, coin_in_type).await?;
+                Ok(vec![dex.into()])
+            }
+        }
+    };
+}
+
+#[cfg(feature = "turbos")]
+pair_dex_factory!(TurbosFactory, super::turbos::Turbos);
+#[cfg(feature = "flowx_clmm")]
+pair_dex_factory!(FlowxClmmFactory, super::flowx_clmm::FlowxClmm);
+#[cfg(feature = "kriya_amm")]
+pair_dex_factory!(KriyaAmmFactory, super::kriya_amm::KriyaAmm);
+#[cfg(feature = "kriya_clmm")]
+pair_dex_factory!(KriyaClmmFactory, super::kriya_clmm::KriyaClmm);
+#[cfg(feature = "deepbook_v2")]
+pair_dex_factory!(DeepbookV2Factory, super::deepbook_v2::DeepbookV2);
+#[cfg(feature = "deepbook_v3")]
+pair_dex_factory!(DeepbookV3Factory, super::deepbook_v3::DeepbookV3);
+#[cfg(feature = "blue_move")]
+pair_dex_factory!(BlueMoveFactory, super::blue_move::BlueMove);
+
+/// Builds Cetus edges whose swaps credit our partner object, if one is configured.
+#[cfg(feature = "cetus")]
+#[derive(Default)]
+struct CetusFactory {
+    partner: Option<sui_types::base_types::ObjectID>,
+}
+
+#[cfg(feature = "cetus")]
+#[async_trait]
+impl DexFactory for CetusFactory {
+    async fn new_dexes(
+        &self,
+        simulator: Arc<Box<dyn Simulator>>,
+        pool: &Pool,
+        coin_in_type: &str,
+        _coin_out_type: Option<String>,
+    ) -> Result<Vec<DexEnum>> {
+        let dex = super::cetus::Cetus::new(simulator, pool, coin_in_type, self.partner).await?;
+        Ok(vec![dex.into()])
+    }
+}
+
+/// Build the edges of the protocols that rebate swaps to a partner with our partner objects of
+/// `config`. Runs at startup, before the searcher is used, like `register_dex_factory`.
+#[allow(unused_variables)]
+pub fn register_partners(config: &PartnerConfig) {
+    #[cfg(feature = "cetus")]
+    register_dex_factory(
+        Protocol::Cetus,
+        Arc::new(CetusFactory {
+            partner: config.partner(&Protocol::Cetus),
+        }),
+    );
+}
+
+#[cfg(feature = "aftermath")]
+struct AftermathFactory;
+
+#[cfg(feature = "aftermath")]
+#[async_trait]
+impl DexFactory for AftermathFactory {
+    async fn new_dexes(
+        &self,
+        simulator: Arc<Box<dyn Simulator>>,
+        pool: &Pool,
+        coin_in_type: &str,
+        coin_out_type: Option<String>,
+    ) -> Result<Vec<DexEnum>> {
+        Ok(
+            super::aftermath::Aftermath::new(simulator, pool, coin_in_type, coin_out_type)
+                .await?
+                .into_iter()
+                .map(DexEnum::from)
+                .collect(),
+        )
+    }
+}
+
+#[allow(unused_mut)]
+fn builtin_factories() -> HashMap<Protocol, Arc<dyn DexFactory>> {
+    let mut factories: HashMap<Protocol, Arc<dyn DexFactory>> = HashMap::new();
+
+    #[cfg(feature = "cetus")]
+    factories.insert(Protocol::Cetus, Arc::new(CetusFactory::default()));
+    #[cfg(feature = "turbos")]
+    factories.insert(Protocol::Turbos, Arc::new(TurbosFactory));
+    #[cfg(feature = "aftermath")]
+    factories.insert(Protocol::Aftermath, Arc::new(AftermathFactory));
+    #[cfg(feature = "flowx_clmm")]
+    factories.insert(Protocol::FlowxClmm, Arc::new(FlowxClmmFactory));
+    #[cfg(feature = "kriya_amm")]
+    factories.insert(Protocol::KriyaAmm, Arc::new(KriyaAmmFactory));
+    #[cfg(feature = "kriya_clmm")]
+    factories.insert(Protocol::KriyaClmm, Arc::new(KriyaClmmFactory));
+    #[cfg(feature = "deepbook_v2")]
+    factories.insert(Protocol::DeepbookV2, Arc::new(DeepbookV2Factory));
+    #[cfg(feature = "deepbook_v3")]
+    factories.insert(Protocol::DeepbookV3, Arc::new(DeepbookV3Factory));
+    #[cfg(feature = "blue_move")]
+    factories.insert(Protocol::BlueMove, Arc::new(BlueMoveFactory));
+
+    factories
+}
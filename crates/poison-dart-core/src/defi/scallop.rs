@@ -0,0 +1,280 @@
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::{Arc, RwLock},
+};
+
+use async_trait::async_trait;
+use eyre::{bail, eyre, OptionExt, Result};
+use move_core_types::{
+    annotated_value::{MoveStruct, MoveValue},
+    language_storage::StructTag,
+};
+use simulator::Simulator;
+use sui_types::{
+    dynamic_field::{derive_dynamic_field_id, Field},
+    object::Object,
+    transaction::{Argument, Command, ObjectArg},
+    Identifier, TypeTag, MOVE_STDLIB_ADDRESS,
+};
+use tracing::{info, warn};
+use utils::object::{
+    extract_object_id_from_move_struct, extract_struct_from_move_struct, extract_vec_from_move_struct, shared_obj_arg,
+};
+
+use super::{descriptor, flash_lender::FlashLender, trade::FlashResult, upgrades, TradeCtx};
+
+// the market's flash-loan fee rates are per 10_000 of the amount borrowed
+const FEE_SCALE: u128 = 10_000;
+
+#[derive(Clone)]
+pub struct Scallop {
+    version: ObjectArg,
+    market: ObjectArg,
+    simulator: Arc<Box<dyn Simulator>>,
+    // flash-loan fee rate of each coin the market lends, keyed by `type_name`; replaced on refresh
+    fee_rates: Arc<RwLock<Arc<HashMap<String, u64>>>>,
+}
+
+impl Scallop {
+    // Objects are fetched only once during initialization, without affecting the arbitrage performance.
+    pub async fn new(simulator: Arc<Box<dyn Simulator>>) -> Result<Self> {
+        let version = simulator
+            .get_object(&descriptor::object_id("scallop", "version"))
+            .await
+            .ok_or_eyre("scallop version not found")?;
+        let market = simulator
+            .get_object(&descriptor::object_id("scallop", "market"))
+            .await
+            .ok_or_eyre("scallop market not found")?;
+        let fee_rates = fee_rates(simulator.as_ref().as_ref(), &market).await?;
+        if fee_rates.is_empty() {
+            warn!("scallop market lends no coins, nothing will be borrowed from it");
+        }
+
+        Ok(Self {
+            version: shared_obj_arg(&version, false),
+            market: shared_obj_arg(&market, true),
+            simulator,
+            fee_rates: Arc::new(RwLock::new(Arc::new(fee_rates))),
+        })
+    }
+
+    fn fee_of(&self, coin_type: &str, amount: u64) -> Option<u64> {
+        borrow_fee(&self.fee_rates.read().unwrap(), coin_type, amount)
+    }
+}
+
+/// The flash-loan fee rate of each coin the market lends, keyed by `type_name`. The coins are the
+/// keys of `market.vault.balance_sheets`, a `WitTable<_, TypeName, _>` that keeps its keys; their
+/// rates the `u64` values of `market.vault.flash_loan_fees`, whose inner table holds them as
+/// dynamic fields. A coin without a rate can't be borrowed.
+async fn fee_rates(simulator: &dyn Simulator, market: &Object) -> Result<HashMap<String, u64>> {
+    let layout = simulator
+        .get_object_layout(&market.id())
+        .ok_or_eyre("scallop market layout not found")?;
+    let market = MoveStruct::simple_deserialize(contents(market)?, &layout).map_err(|e| eyre!(e))?;
+
+    let vault = extract_struct_from_move_struct(&market, "vault")?;
+    let balance_sheets = extract_struct_from_move_struct(&vault, "balance_sheets")?;
+    let keys = extract_struct_from_move_struct(&balance_sheets, "keys")?;
+    let listed = extract_vec_from_move_struct(&keys, "contents")?
+        .iter()
+        .map(type_name)
+        .collect::<Result<Vec<_>>>()?;
+
+    let fees_table = {
+        let fees = extract_struct_from_move_struct(&vault, "flash_loan_fees")?;
+        let table = extract_struct_from_move_struct(&fees, "table")?;
+        let id = extract_struct_from_move_struct(&table, "id")?;
+        let id = extract_struct_from_move_struct(&id, "id")?;
+        extract_object_id_from_move_struct(&id, "bytes")?
+    };
+    let type_name_tag = TypeTag::Struct(Box::new(StructTag {
+        address: MOVE_STDLIB_ADDRESS,
+        module: Identifier::new("type_name").map_err(|e| eyre!(e))?,
+        name: Identifier::new("TypeName").map_err(|e| eyre!(e))?,
+        type_params: vec![],
+    }));
+    // a TypeName is its ascii name, serialized like a string
+    let field_ids = listed
+        .iter()
+        .map(|name| {
+            Ok(derive_dynamic_field_id(
+                fees_table,
+                &type_name_tag,
+                &bcs::to_bytes(name)?,
+            )?)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut rates = HashMap::new();
+    for (name, field) in listed.into_iter().zip(simulator.get_objects(&field_ids).await) {
+        let Some(field) = field else {
+            continue;
+        };
+        let field: Field<String, u64> = bcs::from_bytes(contents(&field)?)?;
+        rates.insert(name, field.value);
+    }
+    Ok(rates)
+}
+
+fn contents(object: &Object) -> Result<&[u8]> {
+    Ok(object.data.try_as_move().ok_or_eyre("not a move object")?.contents())
+}
+
+/// The name of a `std::type_name::TypeName`, an ascii string.
+fn type_name(value: &MoveValue) -> Result<String> {
+    let MoveValue::Struct(type_name) = value else {
+        bail!("expected a TypeName");
+    };
+    let name = extract_struct_from_move_struct(type_name, "name")?;
+    let bytes = extract_vec_from_move_struct(&name, "bytes")?
+        .iter()
+        .map(|byte| match byte {
+            MoveValue::U8(byte) => Ok(*byte),
+            _ => bail!("expected a byte"),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(String::from_utf8(bytes)?)
+}
+
+/// `coin_type` as `type_name` spells it: full-length address, no `0x`.
+fn type_name_of(coin_type: &str) -> Option<String> {
+    match TypeTag::from_str(coin_type).ok()? {
+        TypeTag::Struct(tag) => Some(tag.to_canonical_string(false)),
+        _ => None,
+    }
+}
+
+/// The fee the market charges to borrow `amount` `coin_type`, as `borrow_flash_loan` computes it:
+/// `amount * rate / 10_000`, plus one unit whenever the rate isn't zero. None when it doesn't lend
+/// the coin.
+fn borrow_fee(rates: &HashMap<String, u64>, coin_type: &str, amount: u64) -> Option<u64> {
+    let rate = *rates.get(&type_name_of(coin_type)?)?;
+    if rate == 0 {
+        return Some(0);
+    }
+    Some((amount as u128 * rate as u128 / FEE_SCALE) as u64 + 1)
+}
+
+#[async_trait]
+impl FlashLender for Scallop {
+    fn name(&self) -> &'static str {
+        "scallop"
+    }
+
+    fn lends(&self, coin_type: &str) -> bool {
+        self.fee_of(coin_type, 0).is_some()
+    }
+
+    fn fee(&self, coin_type: &str, amount: u64) -> u64 {
+        self.fee_of(coin_type, amount).unwrap_or(u64::MAX)
+    }
+
+    // Picks up coins listed and rates changed since the last read.
+    async fn refresh(&self) -> Result<()> {
+        let market = self
+            .simulator
+            .get_object(&descriptor::object_id("scallop", "market"))
+            .await
+            .ok_or_eyre("scallop market not found")?;
+        let rates = fee_rates(self.simulator.as_ref().as_ref(), &market).await?;
+        let mut current = self.fee_rates.write().unwrap();
+        if **current != rates {
+            info!(?rates, "scallop flash-loan fee rates changed");
+            *current = Arc::new(rates);
+        }
+        Ok(())
+    }
+
+    /*
+    public fun borrow_flash_loan<T>(
+        version: &Version,
+        market: &mut Market,
+        amount: u64,
+        ctx: &mut TxContext
+    ): (Coin<T>, FlashLoan<T>)
+    */
+    fn extend_flashloan_tx(&self, ctx: &mut TradeCtx, coin_type: &str, amount_in: u64) -> Result<FlashResult> {
+        let package = upgrades::package_id(descriptor::address("scallop", "package"))?;
+        let module = Identifier::new("flash_loan").map_err(|e| eyre!(e))?;
+        let function = Identifier::new("borrow_flash_loan").map_err(|e| eyre!(e))?;
+        let type_arguments = vec![TypeTag::from_str(coin_type).map_err(|e| eyre!(e))?];
+
+        let arguments = vec![
+            ctx.obj(self.version).map_err(|e| eyre!(e))?,
+            ctx.obj(self.market).map_err(|e| eyre!(e))?,
+            ctx.pure(amount_in).map_err(|e| eyre!(e))?,
+        ];
+
+        ctx.command(Command::move_call(package, module, function, type_arguments, arguments));
+        let last_idx = ctx.last_command_idx();
+
+        Ok(FlashResult {
+            coin_out: Argument::NestedResult(last_idx, 0),
+            receipt: Argument::NestedResult(last_idx, 1),
+            pool: None,
+        })
+    }
+
+    /*
+    public fun repay_flash_loan<T>(
+        version: &Version,
+        market: &mut Market,
+        coin: Coin<T>,
+        loan: FlashLoan<T>,
+        ctx: &mut TxContext
+    )
+    */
+    // The market keeps the whole repayment coin, so exactly the loan and the fee it charged are
+    // split off.
+    fn extend_repay_tx(
+        &self,
+        ctx: &mut TradeCtx,
+        coin_type: &str,
+        coin: Argument,
+        amount: u64,
+        flash_res: FlashResult,
+    ) -> Result<Argument> {
+        let package = upgrades::package_id(descriptor::address("scallop", "package"))?;
+        let module = Identifier::new("flash_loan").map_err(|e| eyre!(e))?;
+        let function = Identifier::new("repay_flash_loan").map_err(|e| eyre!(e))?;
+        let type_arguments = vec![TypeTag::from_str(coin_type).map_err(|e| eyre!(e))?];
+
+        let fee = self
+            .fee_of(coin_type, amount)
+            .ok_or_else(|| eyre!("scallop does not lend {}", coin_type))?;
+        let repay_amount = ctx.pure(amount + fee).map_err(|e| eyre!(e))?;
+        let repay_coin = ctx.split_coin_arg(coin, repay_amount);
+
+        let arguments = vec![
+            ctx.obj(self.version).map_err(|e| eyre!(e))?,
+            ctx.obj(self.market).map_err(|e| eyre!(e))?,
+            repay_coin,
+            flash_res.receipt,
+        ];
+
+        ctx.command(Command::move_call(package, module, function, type_arguments, arguments));
+
+        Ok(coin)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_borrow_fee_of_listed_coins_only() {
+        let sui = "0000000000000000000000000000000000000000000000000000000000000002::sui::SUI";
+        let usdc = "00000000000000000000000000000000000000000000000000000000000000aa::usdc::USDC";
+        let rates = HashMap::from([(sui.to_string(), 6), (usdc.to_string(), 0)]);
+
+        assert_eq!(borrow_fee(&rates, "0x2::sui::SUI", 1_000_000), Some(601));
+        assert_eq!(borrow_fee(&rates, &format!("0x{sui}"), 1_000), Some(1));
+        assert_eq!(borrow_fee(&rates, "0xaa::usdc::USDC", 1_000_000), Some(0));
+        assert_eq!(borrow_fee(&rates, "0xdead::coin::COIN", 1_000_000), None);
+        assert_eq!(borrow_fee(&rates, "u64", 1_000_000), None);
+    }
+}
@@ -13,9 +13,8 @@ use sui_types::{
 };
 use tokio::sync::OnceCell;
 
-use super::TradeCtx;
+use super::{descriptor, TradeCtx};
 
-const SHIO: &str = "0x1889977f0fb56ae730e7bda8e8e32859ce78874458c74910d36121a81a615123";
 static GLOBAL_STATES: OnceCell<Vec<ObjectArg>> = OnceCell::const_new();
 
 #[derive(Clone)]
@@ -51,7 +50,7 @@ impl Shio {
     pub fn submit_bid(&self, ctx: &mut TradeCtx, coin_bid: Argument, bid_amount: u64) -> Result<()> {
         ensure!(bid_amount > 0, "bid_amount must be greater than 0");
 
-        let package = ObjectID::from_hex_literal(SHIO)?;
+        let package = descriptor::object_id("shio", "package");
         let module = Identifier::new("auctioneer").map_err(|e| eyre!(e))?;
         let function = Identifier::new("submit_bid").map_err(|e| eyre!(e))?;
 
@@ -0,0 +1,226 @@
+//! Pre-built flashloan PTBs for hot routes.
+//!
+//! Building a PTB walks every dex of the path (type args, object args, move call layout). For a
+//! route we keep hitting, the result only differs in the trade amount, so once a route is hot we
+//! build it twice with two sentinel amounts and keep the skeleton if the two builds differ only in
+//! the sentinel inputs. Routes whose dexes derive other arguments from the amount (lot rounding,
+//! expected outputs, ...) fail that check and are always built from scratch.
+
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    mem,
+    sync::{Arc, Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+use sui_types::{
+    base_types::SuiAddress,
+    transaction::{CallArg, ProgrammableTransaction},
+};
+use utils::cache::CacheStats;
+
+use super::{Dex, Path};
+
+const SENTINEL_AMOUNTS: (u64, u64) = (1_000_000_007, 1_000_000_009);
+// Routes longer than this are too rare to be worth a template
+const MAX_TEMPLATE_HOPS: usize = 3;
+// Hits before a route gets a template
+const HOT_ROUTE_HITS: u32 = 3;
+// Rebuild templates periodically so package upgrades and object arg changes are picked up
+const TEMPLATE_TTL: Duration = Duration::from_secs(600);
+const MAX_ROUTES: usize = 4096;
+// A full map drops its least recently hit routes down to this many
+const ROUTES_AFTER_EVICTION: usize = MAX_ROUTES * 3 / 4;
+
+pub struct TxTemplate {
+    pt: ProgrammableTransaction,
+    // inputs that carry the trade amount
+    amount_inputs: Vec<usize>,
+}
+
+impl TxTemplate {
+    fn new(a: ProgrammableTransaction, b: ProgrammableTransaction) -> Option<Self> {
+        if a.commands != b.commands || a.inputs.len() != b.inputs.len() {
+            return None;
+        }
+
+        let sentinel_a = bcs::to_bytes(&SENTINEL_AMOUNTS.0).ok()?;
+        let sentinel_b = bcs::to_bytes(&SENTINEL_AMOUNTS.1).ok()?;
+        let mut amount_inputs = vec![];
+        for (idx, (input_a, input_b)) in a.inputs.iter().zip(&b.inputs).enumerate() {
+            if input_a == input_b {
+                continue;
+            }
+            match (input_a, input_b) {
+                (CallArg::Pure(x), CallArg::Pure(y)) if *x == sentinel_a && *y == sentinel_b => amount_inputs.push(idx),
+                _ => return None,
+            }
+        }
+
+        (!amount_inputs.is_empty()).then_some(Self { pt: a, amount_inputs })
+    }
+
+    pub fn fill(&self, amount_in: u64) -> ProgrammableTransaction {
+        let amount = bcs::to_bytes(&amount_in).expect("u64 serializes");
+        let mut pt = self.pt.clone();
+        for idx in &self.amount_inputs {
+            pt.inputs[*idx] = CallArg::Pure(amount.clone());
+        }
+        pt
+    }
+}
+
+struct RouteEntry {
+    hits: u32,
+    last_hit: Instant,
+    // None until the route is hot, or if it cannot be templated
+    template: Option<Arc<TxTemplate>>,
+    built_at: Option<Instant>,
+}
+
+static ROUTES: OnceLock<Mutex<HashMap<u64, RouteEntry>>> = OnceLock::new();
+
+fn routes() -> &'static Mutex<HashMap<u64, RouteEntry>> {
+    ROUTES.get_or_init(Default::default)
+}
+
+// hashed instead of formatted into a string key
+fn route_key(path: &Path, sender: SuiAddress) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    sender.hash(&mut hasher);
+    for dex in &path.path {
+        dex.object_id().hash(&mut hasher);
+        // multi-coin pools have several edges per direction
+        dex.coin_in_type().hash(&mut hasher);
+        dex.coin_out_type().hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+pub enum Lookup {
+    Template(Arc<TxTemplate>),
+    /// The route just became hot (or its template expired): build it with `sentinels()` and
+    /// hand both PTBs to `insert`.
+    Build,
+    Skip,
+}
+
+pub fn sentinels() -> (u64, u64) {
+    SENTINEL_AMOUNTS
+}
+
+pub fn lookup(path: &Path, sender: SuiAddress) -> Lookup {
+    if path.path.len() > MAX_TEMPLATE_HOPS {
+        return Lookup::Skip;
+    }
+
+    lookup_key(&mut routes().lock().unwrap(), route_key(path, sender), Instant::now())
+}
+
+fn lookup_key(routes: &mut HashMap<u64, RouteEntry>, key: u64, now: Instant) -> Lookup {
+    if routes.len() >= MAX_ROUTES && !routes.contains_key(&key) {
+        evict_cold(routes);
+    }
+
+    let entry = routes.entry(key).or_insert_with(|| RouteEntry {
+        hits: 0,
+        last_hit: now,
+        template: None,
+        built_at: None,
+    });
+    entry.hits = entry.hits.saturating_add(1);
+    entry.last_hit = now;
+
+    match entry.built_at {
+        Some(built_at) if built_at.elapsed() < TEMPLATE_TTL => match &entry.template {
+            Some(template) => Lookup::Template(Arc::clone(template)),
+            None => Lookup::Skip,
+        },
+        _ if entry.hits >= HOT_ROUTE_HITS => {
+            // claim the build so concurrent callers don't all do it
+            entry.built_at = Some(Instant::now());
+            entry.template = None;
+            Lookup::Build
+        }
+        _ => Lookup::Skip,
+    }
+}
+
+/// Drop the least recently hit routes, templated or not, down to `ROUTES_AFTER_EVICTION`.
+fn evict_cold(routes: &mut HashMap<u64, RouteEntry>) {
+    let mut last_hits: Vec<Instant> = routes.values().map(|entry| entry.last_hit).collect();
+    let evicted = last_hits.len().saturating_sub(ROUTES_AFTER_EVICTION);
+    if evicted == 0 {
+        return;
+    }
+    // ties with the cutoff go with it, so at least `evicted` routes are dropped
+    let (_, cutoff, _) = last_hits.select_nth_unstable(evicted - 1);
+    let cutoff = *cutoff;
+    routes.retain(|_, entry| entry.last_hit > cutoff);
+}
+
+pub fn insert(path: &Path, sender: SuiAddress, a: ProgrammableTransaction, b: ProgrammableTransaction) {
+    let template = TxTemplate::new(a, b).map(Arc::new);
+    if let Some(entry) = routes().lock().unwrap().get_mut(&route_key(path, sender)) {
+        entry.template = template;
+    }
+}
+
+pub fn cache_stats() -> CacheStats {
+    let routes = routes().lock().unwrap();
+    CacheStats {
+        name: "tx_templates",
+        entries: routes.len(),
+        capacity: Some(MAX_ROUTES),
+        hits: 0,
+        misses: 0,
+        approx_bytes: routes.len() * mem::size_of::<(u64, RouteEntry)>(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sui_types::programmable_transaction_builder::ProgrammableTransactionBuilder;
+
+    use super::*;
+
+    fn build(amount: u64, other: u64) -> ProgrammableTransaction {
+        let mut ptb = ProgrammableTransactionBuilder::new();
+        ptb.pure(amount).unwrap();
+        ptb.pure(other).unwrap();
+        ptb.finish()
+    }
+
+    #[test]
+    fn test_template_fills_amount_inputs() {
+        let (a, b) = sentinels();
+        let template = TxTemplate::new(build(a, 42), build(b, 42)).unwrap();
+
+        assert_eq!(template.fill(123).inputs, build(123, 42).inputs);
+    }
+
+    #[test]
+    fn test_routes_stay_bounded() {
+        let mut routes = HashMap::new();
+        let start = Instant::now();
+        let hot = u64::MAX;
+        for key in 0..(MAX_ROUTES as u64 * 2) {
+            let now = start + Duration::from_millis(key);
+            lookup_key(&mut routes, key, now);
+            lookup_key(&mut routes, hot, now);
+            assert!(routes.len() <= MAX_ROUTES);
+        }
+
+        assert!(routes.contains_key(&hot));
+        assert!(routes.contains_key(&(MAX_ROUTES as u64 * 2 - 1)));
+        assert!(!routes.contains_key(&0));
+    }
+
+    #[test]
+    fn test_template_rejects_amount_derived_inputs() {
+        let (a, b) = sentinels();
+
+        assert!(TxTemplate::new(build(a, a / 10), build(b, b / 10)).is_none());
+    }
+}
@@ -1,5 +1,4 @@
 use std::{
-    collections::HashSet,
     fmt,
     ops::{Deref, DerefMut},
     str::FromStr,
@@ -14,16 +13,25 @@ use sui_json_rpc_types::SuiExecutionStatus;
 use sui_sdk::rpc_types::SuiTransactionBlockEffectsAPI;
 use sui_types::{
     base_types::{ObjectID, ObjectRef, SuiAddress},
+    gas_coin::GAS,
     object::{Object, Owner},
     programmable_transaction_builder::ProgrammableTransactionBuilder,
-    transaction::{Argument, Command, ObjectArg, TransactionData},
-    Identifier, TypeTag, SUI_FRAMEWORK_PACKAGE_ID,
+    transaction::{Argument, Command, ObjectArg, ProgrammableTransaction, TransactionData},
+    Identifier, TypeTag, MOVE_STDLIB_PACKAGE_ID, SUI_FRAMEWORK_PACKAGE_ID,
 };
 use tracing::instrument;
 
-use super::{navi::Navi, shio::Shio, Dex};
+use super::{
+    base,
+    flash_lender::{self, FlashLender, FlashLoanConfig},
+    shio::Shio,
+    template, Dex, DexEnum,
+};
 use crate::{config::*, types::Source};
 
+// Reuse pre-built PTBs for hot routes (see `template`)
+const USE_TX_TEMPLATES: bool = true;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TradeType {
     Swap,
@@ -41,7 +49,7 @@ pub struct FlashResult {
 pub struct Trader {
     simulator_pool: Arc<ObjectPool<Box<dyn Simulator>>>,
     shio: Arc<Shio>,
-    navi: Arc<Navi>,
+    lenders: Arc<Vec<Arc<dyn FlashLender>>>,
 }
 
 #[derive(Default)]
@@ -58,15 +66,15 @@ pub struct TradeResult {
 }
 
 impl Trader {
-    pub async fn new(simulator_pool: Arc<ObjectPool<Box<dyn Simulator>>>) -> Result<Self> {
+    pub async fn new(simulator_pool: Arc<ObjectPool<Box<dyn Simulator>>>, flashloan: &FlashLoanConfig) -> Result<Self> {
         let shio = Arc::new(Shio::new().await?);
         let simulator = simulator_pool.get();
-        let navi = Arc::new(Navi::new(simulator).await?);
+        let lenders = Arc::new(flash_lender::lenders(simulator, flashloan).await?);
 
         Ok(Self {
             simulator_pool,
             shio,
-            navi,
+            lenders,
         })
     }
 
@@ -129,8 +137,12 @@ impl Trader {
         for bc in &resp.balance_changes {
             if bc.owner == Owner::AddressOwner(sender) && bc.coin_type == coin_out {
                 amount_out = bc.amount;
-                if coin_in == coin_out && out_is_native {
-                    amount_out = amount_out + amount_in as i128 + gas_cost as i128;
+                // a cycle's balance change is net of what went in, and of gas when that is the same coin
+                if coin_in == coin_out {
+                    amount_out += amount_in as i128;
+                    if out_is_native {
+                        amount_out += gas_cost as i128;
+                    }
                 }
 
                 ensure!(amount_out >= 0, "negative amount_out {}", amount_out);
@@ -158,8 +170,9 @@ impl Trader {
         let mut ctx = TradeCtx::default();
 
         // 1. prepare coin_in
-        let mocked_sui = coin::mocked_sui(sender, amount_in);
-        let coin_in = mocked_sui.compute_object_reference();
+        let coin_in_type = TypeTag::from_str(&path.coin_in_type()).map_err(|_| eyre!("invalid coin_in_type"))?;
+        let mocked_coin_in = coin::mocked_coin(sender, coin_in_type, amount_in);
+        let coin_in = mocked_coin_in.compute_object_reference();
 
         // 2. swap
         let mut coin_in_arg = ctx.split_coin(coin_in, amount_in)?;
@@ -174,7 +187,7 @@ impl Trader {
 
         let tx_data = TransactionData::new_programmable(sender, gas_coins, tx, GAS_BUDGET, gas_price);
 
-        Ok((tx_data, Some(mocked_sui)))
+        Ok((tx_data, Some(mocked_coin_in)))
     }
 
     pub async fn get_flashloan_trade_tx(
@@ -187,35 +200,81 @@ impl Trader {
         source: Source,
     ) -> Result<(TransactionData, Option<Object>)> {
         ensure!(!path.is_empty(), "empty path");
-        let first_dex = &path.path[0];
-
-        let mut ctx = TradeCtx::default();
 
-        // 1. flashloan
-        let flash_res = if first_dex.support_flashloan() {
-            first_dex.extend_flashloan_tx(&mut ctx, amount_in).await?
+        // shio bids carry a per-trade bid amount and digest constraint, so only public trades are templated
+        let tx = if USE_TX_TEMPLATES && !source.is_shio() {
+            match template::lookup(path, sender) {
+                template::Lookup::Template(template) => template.fill(amount_in),
+                template::Lookup::Build => {
+                    let (a, b) = template::sentinels();
+                    // a route that fails to build with the sentinels just stays untemplated
+                    if let (Ok(tx_a), Ok(tx_b)) = (
+                        self.flashloan_ptb(path, sender, a, source).await,
+                        self.flashloan_ptb(path, sender, b, source).await,
+                    ) {
+                        template::insert(path, sender, tx_a, tx_b);
+                    }
+                    self.flashloan_ptb(path, sender, amount_in, source).await?
+                }
+                template::Lookup::Skip => self.flashloan_ptb(path, sender, amount_in, source).await?,
+            }
         } else {
-            self.navi.extend_flashloan_tx(&mut ctx, amount_in)?
+            self.flashloan_ptb(path, sender, amount_in, source).await?
         };
 
-        // 2. swap
-        let mut coin_in_arg = flash_res.coin_out;
-        let dex_iter: Box<dyn Iterator<Item = &Box<dyn Dex>> + Send> = if first_dex.support_flashloan() {
-            Box::new(path.path.iter().skip(1))
-        } else {
-            Box::new(path.path.iter())
+        // 6. finalize
+        let mut tx_data =
+            TransactionData::new_programmable(sender, gas_coins.clone(), tx.clone(), GAS_BUDGET, gas_price);
+
+        if let Some(opp_tx_digest) = source.opp_tx_digest() {
+            // A Bid MUST have a lexicologically larger transaction digest comparing to opportunity transaction's.
+            let mut gas_budget = GAS_BUDGET;
+            while tx_data.digest() <= opp_tx_digest {
+                gas_budget += 1;
+                tx_data =
+                    TransactionData::new_programmable(sender, gas_coins.clone(), tx.clone(), gas_budget, gas_price);
+            }
         };
-        for (i, dex) in dex_iter.enumerate() {
-            let amount_in = if i == 0 { Some(amount_in) } else { None };
-            coin_in_arg = dex.extend_trade_tx(&mut ctx, sender, coin_in_arg, amount_in).await?;
+
+        Ok((tx_data, None))
+    }
+
+    /// One PTB trading each of `routes`, a path and its input, as its own flashloaned cycle. Every
+    /// cycle repays its own loan, so the routes land or revert together. Only for public trades.
+    pub async fn get_multi_flashloan_trade_tx(
+        &self,
+        routes: &[(Path, u64)],
+        sender: SuiAddress,
+        gas_coins: Vec<ObjectRef>,
+        gas_price: u64,
+    ) -> Result<TransactionData> {
+        ensure!(!routes.is_empty(), "no routes");
+
+        let mut ctx = TradeCtx::default();
+        for (path, amount_in) in routes {
+            ensure!(!path.is_empty(), "empty path");
+            let coin_profit = self.extend_flashloan_cycle(&mut ctx, path, sender, *amount_in).await?;
+            ctx.transfer_arg(sender, coin_profit);
         }
 
-        // 3. repay flashloan
-        let coin_profit = if first_dex.support_flashloan() {
-            first_dex.extend_repay_tx(&mut ctx, coin_in_arg, flash_res).await?
-        } else {
-            self.navi.extend_repay_tx(&mut ctx, coin_in_arg, flash_res)?
-        };
+        Ok(TransactionData::new_programmable(
+            sender,
+            gas_coins,
+            ctx.ptb.finish(),
+            GAS_BUDGET,
+            gas_price,
+        ))
+    }
+
+    async fn flashloan_ptb(
+        &self,
+        path: &Path,
+        sender: SuiAddress,
+        amount_in: u64,
+        source: Source,
+    ) -> Result<ProgrammableTransaction> {
+        let mut ctx = TradeCtx::default();
+        let coin_profit = self.extend_flashloan_cycle(&mut ctx, path, sender, amount_in).await?;
 
         // 4. submit bid
         if source.is_shio() {
@@ -227,23 +286,54 @@ impl Trader {
         // 5. transfer the profit to recipient
         ctx.transfer_arg(sender, coin_profit);
 
-        let tx = ctx.ptb.finish();
+        Ok(ctx.ptb.finish())
+    }
 
-        // 6. finalize
-        let mut tx_data =
-            TransactionData::new_programmable(sender, gas_coins.clone(), tx.clone(), GAS_BUDGET, gas_price);
+    // Flashloan `amount_in`, trade it along `path` and repay, returning the profit coin.
+    async fn extend_flashloan_cycle(
+        &self,
+        ctx: &mut TradeCtx,
+        path: &Path,
+        sender: SuiAddress,
+        amount_in: u64,
+    ) -> Result<Argument> {
+        let first_dex = &path.path[0];
 
-        if let Some(opp_tx_digest) = source.opp_tx_digest() {
-            // A Bid MUST have a lexicologically larger transaction digest comparing to opportunity transaction's.
-            let mut gas_budget = GAS_BUDGET;
-            while tx_data.digest() <= opp_tx_digest {
-                gas_budget += 1;
-                tx_data =
-                    TransactionData::new_programmable(sender, gas_coins.clone(), tx.clone(), gas_budget, gas_price);
-            }
+        // 1. flashloan, from the first pool when it can flash swap, else from the cheapest lender
+        let coin_type = first_dex.coin_in_type();
+        let lender = if first_dex.support_flashloan() {
+            None
+        } else {
+            let lender = flash_lender::cheapest(&self.lenders, &coin_type, amount_in).ok_or_else(|| {
+                eyre!(
+                    "no flash lender for {}, and the first pool does not support flashloans",
+                    coin_type
+                )
+            })?;
+            Some(lender)
+        };
+        let flash_res = match lender {
+            Some(lender) => lender.extend_flashloan_tx(ctx, &coin_type, amount_in)?,
+            None => first_dex.extend_flashloan_tx(ctx, amount_in).await?,
         };
 
-        Ok((tx_data, None))
+        // 2. swap
+        let mut coin_in_arg = flash_res.coin_out;
+        let dex_iter: Box<dyn Iterator<Item = &DexEnum> + Send> = if lender.is_none() {
+            Box::new(path.path.iter().skip(1))
+        } else {
+            Box::new(path.path.iter())
+        };
+        for (i, dex) in dex_iter.enumerate() {
+            let amount_in = if i == 0 { Some(amount_in) } else { None };
+            coin_in_arg = dex.extend_trade_tx(ctx, sender, coin_in_arg, amount_in).await?;
+        }
+
+        // 3. repay flashloan
+        match lender {
+            Some(lender) => lender.extend_repay_tx(ctx, &coin_type, coin_in_arg, amount_in, flash_res),
+            None => first_dex.extend_repay_tx(ctx, coin_in_arg, flash_res).await,
+        }
     }
 }
 
@@ -314,6 +404,36 @@ impl TradeCtx {
         Ok(Argument::Result(last_idx))
     }
 
+    // sui::coin::zero<CoinTypeTag>(ctx);
+    pub fn coin_zero(&mut self, coin_type: TypeTag) -> Result<Argument> {
+        self.build_command(SUI_FRAMEWORK_PACKAGE_ID, "coin", "zero", vec![coin_type], vec![])?;
+
+        let last_idx = self.last_command_idx();
+        Ok(Argument::Result(last_idx))
+    }
+
+    // sui::coin::value(&coin);
+    pub fn coin_value(&mut self, coin: Argument, coin_type: TypeTag) -> Result<Argument> {
+        self.build_command(SUI_FRAMEWORK_PACKAGE_ID, "coin", "value", vec![coin_type], vec![coin])?;
+
+        let last_idx = self.last_command_idx();
+        Ok(Argument::Result(last_idx))
+    }
+
+    // sui::coin::destroy_zero(coin);
+    // aborts if the coin is not empty, which reverts the whole PTB.
+    pub fn coin_destroy_zero(&mut self, coin: Argument, coin_type: TypeTag) -> Result<()> {
+        self.build_command(
+            SUI_FRAMEWORK_PACKAGE_ID,
+            "coin",
+            "destroy_zero",
+            vec![coin_type],
+            vec![coin],
+        )?;
+
+        Ok(())
+    }
+
     // sui::coin::into_balance(coin);
     pub fn coin_into_balance(&mut self, coin: Argument, coin_type: TypeTag) -> Result<Argument> {
         self.build_command(
@@ -328,6 +448,28 @@ impl TradeCtx {
         Ok(Argument::Result(last_idx))
     }
 
+    // std::u64::divide_and_round_up(x, y)
+    pub fn u64_divide_and_round_up(&mut self, x: Argument, y: Argument) -> Result<Argument> {
+        self.build_command(MOVE_STDLIB_PACKAGE_ID, "u64", "divide_and_round_up", vec![], vec![x, y])?;
+
+        let last_idx = self.last_command_idx();
+        Ok(Argument::Result(last_idx))
+    }
+
+    // Aborts unless `value <= max`, which reverts the whole PTB: the excess,
+    // std::u64::diff(std::u64::max(value, max), max), is split off a zero coin.
+    pub fn assert_at_most(&mut self, value: Argument, max: Argument) -> Result<()> {
+        self.build_command(MOVE_STDLIB_PACKAGE_ID, "u64", "max", vec![], vec![value, max])?;
+        let upper = Argument::Result(self.last_command_idx());
+        self.build_command(MOVE_STDLIB_PACKAGE_ID, "u64", "diff", vec![], vec![upper, max])?;
+        let excess = Argument::Result(self.last_command_idx());
+
+        let zero = self.coin_zero(GAS::type_tag())?;
+        let split = self.split_coin_arg(zero, excess);
+        self.coin_destroy_zero(split, GAS::type_tag())?;
+        self.coin_destroy_zero(zero, GAS::type_tag())
+    }
+
     #[inline]
     fn build_command(
         &mut self,
@@ -373,11 +515,11 @@ impl PartialOrd for TradeResult {
 
 #[derive(Default, Clone)]
 pub struct Path {
-    pub path: Vec<Box<dyn Dex>>,
+    pub path: Vec<DexEnum>,
 }
 
 impl Path {
-    pub fn new(path: Vec<Box<dyn Dex>>) -> Self {
+    pub fn new(path: Vec<DexEnum>) -> Self {
         Self { path }
     }
 
@@ -386,9 +528,8 @@ impl Path {
     }
 
     pub fn is_disjoint(&self, other: &Self) -> bool {
-        let a = self.path.iter().collect::<HashSet<_>>();
-        let b = other.path.iter().collect::<HashSet<_>>();
-        a.is_disjoint(&b)
+        // paths are a handful of hops, a nested scan beats building two sets per call
+        !self.path.iter().any(|a| other.path.contains(a))
     }
 
     pub fn coin_in_type(&self) -> String {
@@ -399,6 +540,32 @@ impl Path {
         self.path.last().unwrap().coin_out_type()
     }
 
+    /// Estimated partner rebate in base coin units for a base -> ... -> base path.
+    /// Legs trading the base coin are valued at `amount_in` or `amount_out`; a leg between two
+    /// other coins is valued on the way from the one to the other, by its position in the path.
+    pub fn estimated_partner_rebate(&self, amount_in: u64, amount_out: u64) -> u64 {
+        let hops = self.path.len() as i128;
+        self.path
+            .iter()
+            .enumerate()
+            .map(|(i, dex)| {
+                let notional = if base::is_base_coin(&dex.coin_in_type()) {
+                    amount_in
+                } else if base::is_base_coin(&dex.coin_out_type()) {
+                    amount_out
+                } else {
+                    let (amount_in, amount_out) = (amount_in as i128, amount_out as i128);
+                    (amount_in + (amount_out - amount_in) * i as i128 / hops) as u64
+                };
+                (notional as f64 * dex.partner_rebate_rate()) as u64
+            })
+            .sum()
+    }
+
+    pub fn object_ids(&self) -> impl Iterator<Item = ObjectID> + '_ {
+        self.path.iter().map(|dex| dex.object_id())
+    }
+
     pub fn contains_pool(&self, pool_id: Option<ObjectID>) -> bool {
         if let Some(pool_id) = pool_id {
             self.path.iter().any(|dex| dex.object_id() == pool_id)
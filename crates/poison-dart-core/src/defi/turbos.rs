@@ -2,20 +2,29 @@ use std::sync::Arc;
 
 use dex_indexer::types::{Pool, Protocol};
 use eyre::{ensure, eyre, OptionExt, Result};
-use move_core_types::annotated_value::MoveStruct;
+use move_core_types::{account_address::AccountAddress, annotated_value::MoveStruct, language_storage::StructTag};
+use serde::Deserialize;
 use simulator::Simulator;
 use sui_types::{
     base_types::{ObjectID, ObjectRef, SuiAddress},
+    dynamic_field::{derive_dynamic_field_id, Field},
+    id::UID,
+    object::Object,
     transaction::{Argument, Command, ObjectArg, ProgrammableTransaction, TransactionData},
-    Identifier, TypeTag, SUI_CLOCK_OBJECT_ID,
+    Identifier, TypeTag, SUI_CLOCK_OBJECT_ID, SUI_FRAMEWORK_ADDRESS,
 };
 use tokio::sync::OnceCell;
 use utils::{coin, new_test_sui_client, object::*};
 
-use super::{TradeCtx, CETUS_AGGREGATOR};
+use super::{
+    clmm_math::{self, Clmm, Tick, TickWindow},
+    descriptor, upgrades, TradeCtx,
+};
 use crate::{config::*, defi::Dex};
 
-const VERSIONED: &str = "0xf1cf0e81048df168ebeb1b8030fad24b3e0b53ae827c25053fff0779c1445b6f";
+const FEE_DENOMINATOR: u64 = 1_000_000;
+// tick bitmap words read on each side of the current one, each covers 256 tick spacings
+const TICK_WORDS: i32 = 2;
 
 #[derive(Clone)]
 pub struct ObjectArgs {
@@ -28,7 +37,7 @@ static OBJ_CACHE: OnceCell<ObjectArgs> = OnceCell::const_new();
 async fn get_object_args(simulator: Arc<Box<dyn Simulator>>) -> ObjectArgs {
     OBJ_CACHE
         .get_or_init(|| async {
-            let versioned_id = ObjectID::from_hex_literal(VERSIONED).unwrap();
+            let versioned_id = descriptor::object_id("turbos", "versioned");
             let versioned = simulator.get_object(&versioned_id).await.unwrap();
             let clock = simulator.get_object(&SUI_CLOCK_OBJECT_ID).await.unwrap();
 
@@ -41,11 +50,133 @@ async fn get_object_args(simulator: Arc<Box<dyn Simulator>>) -> ObjectArgs {
         .clone()
 }
 
+/*
+struct Tick has key, store {
+    id: UID,
+    liquidity_gross: u128,
+    liquidity_net: I128,
+    fee_growth_outside_a: u128,
+    fee_growth_outside_b: u128,
+    reward_growths_outside: vector<u128>,
+    initialized: bool,
+}
+*/
+#[derive(Deserialize)]
+struct TurbosTick {
+    _id: UID,
+    _liquidity_gross: u128,
+    liquidity_net: u128,
+    _fee_growth_outside_a: u128,
+    _fee_growth_outside_b: u128,
+    _reward_growths_outside: Vec<u128>,
+    _initialized: bool,
+}
+
+fn contents(object: &Object) -> Result<&[u8]> {
+    Ok(object.data.try_as_move().ok_or_eyre("not a move object")?.contents())
+}
+
+/// The initialized ticks within `TICK_WORDS` bitmap words of `current_tick`. `tick_map` is a
+/// `Table<I32, u256>` of bitmap words, and each tick a dynamic object field of the pool keyed by
+/// its index.
+async fn fetch_ticks(
+    simulator: Arc<Box<dyn Simulator>>,
+    pool_id: ObjectID,
+    package: AccountAddress,
+    tick_map: ObjectID,
+    tick_spacing: u32,
+    current_tick: i32,
+) -> Result<TickWindow> {
+    ensure!(tick_spacing > 0, "zero tick spacing");
+    let i32_tag = TypeTag::Struct(Box::new(StructTag {
+        address: package,
+        module: Identifier::new("i32").map_err(|e| eyre!(e))?,
+        name: Identifier::new("I32").map_err(|e| eyre!(e))?,
+        type_params: vec![],
+    }));
+    let spacing = tick_spacing as i32;
+    let word = current_tick.div_euclid(spacing) >> 8;
+    let words: Vec<i32> = (word - TICK_WORDS..=word + TICK_WORDS).collect();
+
+    let word_ids = words
+        .iter()
+        .map(|pos| {
+            Ok(derive_dynamic_field_id(
+                tick_map,
+                &i32_tag,
+                &bcs::to_bytes(&(*pos as u32))?,
+            )?)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let mut indexes = vec![];
+    for (pos, object) in words.iter().zip(simulator.get_objects(&word_ids).await) {
+        // words without an initialized tick were never written
+        let Some(object) = object else {
+            continue;
+        };
+        let field: Field<u32, [u8; 32]> = bcs::from_bytes(contents(&object)?)?;
+        for bit in 0..256 {
+            if field.value[bit / 8] >> (bit % 8) & 1 == 1 {
+                indexes.push(((pos << 8) + bit as i32) * spacing);
+            }
+        }
+    }
+
+    let wrapper_tag = TypeTag::Struct(Box::new(StructTag {
+        address: SUI_FRAMEWORK_ADDRESS,
+        module: Identifier::new("dynamic_object_field").map_err(|e| eyre!(e))?,
+        name: Identifier::new("Wrapper").map_err(|e| eyre!(e))?,
+        type_params: vec![i32_tag],
+    }));
+    let field_ids = indexes
+        .iter()
+        .map(|index| {
+            Ok(derive_dynamic_field_id(
+                pool_id,
+                &wrapper_tag,
+                &bcs::to_bytes(&(*index as u32))?,
+            )?)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let tick_ids = simulator
+        .get_objects(&field_ids)
+        .await
+        .into_iter()
+        .map(|field| {
+            let field: Field<u32, ObjectID> = bcs::from_bytes(contents(&field.ok_or_eyre("tick field not found")?)?)?;
+            Ok(field.value)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let ticks = indexes
+        .into_iter()
+        .zip(simulator.get_objects(&tick_ids).await)
+        .map(|(index, tick)| {
+            let tick: TurbosTick = bcs::from_bytes(contents(&tick.ok_or_eyre("tick not found")?)?)?;
+            Ok(Tick {
+                index,
+                liquidity_net: tick.liquidity_net as i128,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let word_ticks = 256 * spacing;
+    Ok(TickWindow::new(
+        ticks,
+        (word - TICK_WORDS) * word_ticks,
+        (word + TICK_WORDS + 1) * word_ticks - 1,
+    ))
+}
+
 #[derive(Clone)]
 pub struct Turbos {
     pool: Pool,
     pool_arg: ObjectArg,
     liquidity: u128,
+    sqrt_price: u128,
+    current_tick: i32,
+    fee: u64,
+    // None until the pool's ticks were fetched
+    ticks: Option<Arc<TickWindow>>,
     coin_in_type: String,
     coin_out_type: String,
     type_params: Vec<TypeTag>,
@@ -75,6 +206,12 @@ impl Turbos {
         ensure!(unlocked, "pool is locked");
 
         let liquidity = extract_u128_from_move_struct(&parsed_pool, "liquidity")?;
+        let sqrt_price = extract_u128_from_move_struct(&parsed_pool, "sqrt_price")?;
+        let current_tick = {
+            let index = extract_struct_from_move_struct(&parsed_pool, "tick_current_index")?;
+            extract_u32_from_move_struct(&index, "bits")? as i32
+        };
+        let fee = extract_u32_from_move_struct(&parsed_pool, "fee")? as u64;
 
         let coin_out_type = if pool.token0_type() == coin_in_type {
             pool.token1_type().to_string()
@@ -84,12 +221,35 @@ impl Turbos {
 
         let type_params = parsed_pool.type_.type_params.clone();
 
+        let tick_map = {
+            let tick_map = extract_struct_from_move_struct(&parsed_pool, "tick_map")?;
+            let id = extract_struct_from_move_struct(&tick_map, "id")?;
+            let id = extract_struct_from_move_struct(&id, "id")?;
+            extract_object_id_from_move_struct(&id, "bytes")?
+        };
+        let tick_spacing = extract_u32_from_move_struct(&parsed_pool, "tick_spacing")?;
+        let ticks = clmm_math::ticks(
+            pool.pool,
+            fetch_ticks(
+                Arc::clone(&simulator),
+                pool.pool,
+                parsed_pool.type_.address,
+                tick_map,
+                tick_spacing,
+                current_tick,
+            ),
+        );
+
         let pool_arg = shared_obj_arg(&pool_obj, true);
         let ObjectArgs { versioned, clock } = get_object_args(simulator).await;
 
         Ok(Self {
             pool: pool.clone(),
             liquidity,
+            sqrt_price,
+            current_tick,
+            fee,
+            ticks,
             coin_in_type: coin_in_type.to_string(),
             coin_out_type,
             type_params,
@@ -144,7 +304,7 @@ impl Dex for Turbos {
     ) -> Result<Argument> {
         let function = if self.is_a2b() { "swap_a2b" } else { "swap_b2a" };
 
-        let package = ObjectID::from_hex_literal(CETUS_AGGREGATOR)?;
+        let package = upgrades::package_id(descriptor::address("cetus_aggregator", "package"))?;
         let module = Identifier::new("turbos").map_err(|e| eyre!(e))?;
         let function = Identifier::new(function).map_err(|e| eyre!(e))?;
         let type_arguments = self.type_params.clone();
@@ -176,10 +336,27 @@ impl Dex for Turbos {
         self.pool.pool
     }
 
+    fn pool_arg(&self) -> Option<ObjectArg> {
+        Some(self.pool_arg.clone())
+    }
+
     fn flip(&mut self) {
         std::mem::swap(&mut self.coin_in_type, &mut self.coin_out_type);
     }
 
+    fn quote(&self, amount_in: u64) -> Option<u64> {
+        let pool = Clmm {
+            sqrt_price: self.sqrt_price,
+            current_tick: self.current_tick,
+            liquidity: self.liquidity,
+            fee_num: self.fee,
+            fee_den: FEE_DENOMINATOR,
+            a2b: self.is_a2b(),
+            ticks: Arc::clone(self.ticks.as_ref()?),
+        };
+        pool.amount_out(amount_in)
+    }
+
     fn is_a2b(&self) -> bool {
         self.pool.token_index(&self.coin_in_type) == Some(0)
     }
@@ -217,7 +394,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_turbos_swap_tx() {
-        mev_logger::init_console_logger_with_directives(None, &["arb=debug", "dex_indexer=debug"]);
+        mev_logger::init_console_logger_with_directives(None, &["poison_dart_core=debug", "dex_indexer=debug"]);
 
         let http_simulator = HttpSimulator::new(TEST_HTTP_URL, &None).await;
 
@@ -0,0 +1,412 @@
+//! Protocol package upgrade detection.
+//!
+//! Swap builders resolve their package id through `package_id` instead of using the
+//! descriptor entry directly. `UpgradeMonitor` periodically checks two signals:
+//!
+//! * the `UpgradeCap` of a package (when configured): a new `package` field means the
+//!   protocol published an upgrade. Sui upgrade policies never break public function
+//!   signatures, so we switch to the new id as long as it is readable.
+//! * the version-gate object of a protocol (`package_version` / `version` fields):
+//!   protocols bump it to make old packages abort. If it moves and we could not follow
+//!   the upgrade, the protocol is disabled until someone reviews it.
+//!
+//! Both are read from the RPC, not a simulator: a simulator's copy of them only moves when a
+//! trade touches them, so it can miss a bump.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    sync::{Arc, OnceLock, RwLock},
+    time::Duration,
+};
+
+use clap::Parser;
+use dex_indexer::types::Protocol;
+use eyre::{eyre, OptionExt, Result};
+use simulator::Simulator;
+use sui_sdk::{rpc_types::SuiObjectDataOptions, SuiClient};
+use sui_types::base_types::ObjectID;
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+
+use super::descriptor;
+
+struct WatchedPackage {
+    /// Protocol name in the descriptor
+    name: &'static str,
+    /// Descriptor entry of the package, None when we only call the protocol through the aggregator
+    package: Option<&'static str>,
+    protocols: &'static [Protocol],
+    /// (descriptor entry of the object, u64 field) bumped by the protocol to retire old packages
+    version_gate: Option<(&'static str, &'static str)>,
+}
+
+const WATCHED_PACKAGES: &[WatchedPackage] = &[
+    #[cfg(feature = "cetus")]
+    WatchedPackage {
+        name: "cetus",
+        package: Some("package"),
+        protocols: &[Protocol::Cetus],
+        version_gate: Some(("config", "package_version")),
+    },
+    #[cfg(feature = "turbos")]
+    WatchedPackage {
+        name: "turbos",
+        package: None,
+        protocols: &[Protocol::Turbos],
+        version_gate: Some(("versioned", "version")),
+    },
+    #[cfg(feature = "flowx_clmm")]
+    WatchedPackage {
+        name: "flowx_clmm",
+        package: Some("package"),
+        protocols: &[Protocol::FlowxClmm],
+        version_gate: Some(("versioned", "version")),
+    },
+    #[cfg(feature = "kriya_clmm")]
+    WatchedPackage {
+        name: "kriya_clmm",
+        package: Some("package"),
+        protocols: &[Protocol::KriyaClmm],
+        version_gate: Some(("version", "version")),
+    },
+    #[cfg(feature = "deepbook_v3")]
+    WatchedPackage {
+        name: "deepbook_v3",
+        package: Some("package"),
+        protocols: &[Protocol::DeepbookV3],
+        version_gate: None,
+    },
+    #[cfg(feature = "aftermath")]
+    WatchedPackage {
+        name: "aftermath",
+        package: Some("package"),
+        protocols: &[Protocol::Aftermath],
+        version_gate: None,
+    },
+    WatchedPackage {
+        name: "navi",
+        package: Some("package"),
+        protocols: &[Protocol::Navi],
+        version_gate: None,
+    },
+    WatchedPackage {
+        name: "cetus_aggregator",
+        package: Some("package"),
+        protocols: &[
+            Protocol::Turbos,
+            Protocol::KriyaAmm,
+            Protocol::KriyaClmm,
+            Protocol::DeepbookV2,
+            Protocol::BlueMove,
+        ],
+        version_gate: None,
+    },
+];
+
+#[derive(Default)]
+struct UpgradeState {
+    // original package id => package id to call
+    resolved: HashMap<ObjectID, ObjectID>,
+    disabled: HashSet<Protocol>,
+}
+
+static STATE: OnceLock<RwLock<UpgradeState>> = OnceLock::new();
+
+fn state() -> &'static RwLock<UpgradeState> {
+    STATE.get_or_init(Default::default)
+}
+
+/// The package id to call for a package in the protocol descriptor, following detected upgrades.
+pub fn package_id(package: &str) -> Result<ObjectID> {
+    let original = ObjectID::from_hex_literal(package)?;
+    Ok(state()
+        .read()
+        .unwrap()
+        .resolved
+        .get(&original)
+        .copied()
+        .unwrap_or(original))
+}
+
+pub fn is_protocol_disabled(protocol: &Protocol) -> bool {
+    state().read().unwrap().disabled.contains(protocol)
+}
+
+#[derive(Clone, Debug, Parser)]
+pub struct UpgradeConfig {
+    /// `<package_id>=<upgrade_cap_id>` pairs used to follow package upgrades
+    #[arg(long, env = "SUI_UPGRADE_CAPS", value_delimiter = ',')]
+    pub upgrade_caps: Vec<String>,
+
+    /// Seconds between package upgrade checks
+    #[arg(long, env = "SUI_UPGRADE_CHECK_INTERVAL", default_value_t = 60)]
+    pub upgrade_check_interval: u64,
+}
+
+impl UpgradeConfig {
+    pub fn parse_upgrade_caps(&self) -> Result<HashMap<ObjectID, ObjectID>> {
+        self.upgrade_caps
+            .iter()
+            .map(|pair| {
+                let (package, cap) = pair
+                    .split_once('=')
+                    .ok_or_else(|| eyre!("invalid upgrade cap {}, expected <package>=<cap>", pair))?;
+                Ok((ObjectID::from_hex_literal(package)?, ObjectID::from_hex_literal(cap)?))
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum UpgradeEvent {
+    Switched {
+        name: &'static str,
+        from: ObjectID,
+        to: ObjectID,
+    },
+    Disabled {
+        name: &'static str,
+        protocols: Vec<Protocol>,
+        reason: String,
+    },
+}
+
+impl fmt::Display for UpgradeEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UpgradeEvent::Switched { name, from, to } => {
+                write!(f, "{} package upgraded, switched {} -> {}", name, from, to)
+            }
+            UpgradeEvent::Disabled { name, protocols, reason } => {
+                write!(f, "{} disabled ({:?}), manual review needed: {}", name, protocols, reason)
+            }
+        }
+    }
+}
+
+pub struct UpgradeMonitor {
+    // upgrade caps and version gates are read from here
+    sui: SuiClient,
+    // a new package must be readable by the simulator to be switched to
+    simulator: Arc<dyn Simulator>,
+    upgrade_caps: HashMap<ObjectID, ObjectID>,
+    // version gate values seen at startup (or after the last switch)
+    baseline_versions: HashMap<ObjectID, u64>,
+}
+
+impl UpgradeMonitor {
+    pub fn new(sui: SuiClient, simulator: Arc<dyn Simulator>, config: &UpgradeConfig) -> Result<Self> {
+        Ok(Self {
+            sui,
+            simulator,
+            upgrade_caps: config.parse_upgrade_caps()?,
+            baseline_versions: HashMap::new(),
+        })
+    }
+
+    pub async fn check(&mut self) -> Vec<UpgradeEvent> {
+        let mut events = vec![];
+
+        for watched in WATCHED_PACKAGES {
+            if watched.protocols.iter().all(is_protocol_disabled) {
+                continue;
+            }
+            match self.check_package(watched).await {
+                Ok(Some(event)) => events.push(event),
+                Ok(None) => {}
+                Err(error) => warn!(name = watched.name, ?error, "package upgrade check failed"),
+            }
+        }
+
+        events
+    }
+
+    async fn check_package(&mut self, watched: &WatchedPackage) -> Result<Option<UpgradeEvent>> {
+        let mut switched = None;
+        if let Some(package) = watched.package {
+            let package = descriptor::address(watched.name, package);
+            switched = self.follow_upgrade(watched, package).await?;
+            if watched.protocols.iter().all(is_protocol_disabled) {
+                return Ok(switched);
+            }
+        }
+
+        if let Some((gate, field)) = watched.version_gate {
+            let gate_id = descriptor::object_id(watched.name, gate);
+            let version = self.read_field(&gate_id, field).await?.parse()?;
+            let bumped = observe_version_gate(
+                &mut self.baseline_versions,
+                watched,
+                gate_id,
+                version,
+                switched.is_some(),
+            );
+            if bumped.is_some() {
+                return Ok(bumped);
+            }
+        }
+
+        Ok(switched)
+    }
+
+    async fn follow_upgrade(&self, watched: &WatchedPackage, package: &str) -> Result<Option<UpgradeEvent>> {
+        let original = ObjectID::from_hex_literal(package)?;
+        let current = package_id(package)?;
+
+        let Some(cap_id) = self.upgrade_caps.get(&original) else {
+            return Ok(None);
+        };
+        let latest = ObjectID::from_hex_literal(&self.read_field(cap_id, "package").await?)?;
+        if latest == current {
+            return Ok(None);
+        }
+
+        let readable = self
+            .simulator
+            .get_object(&latest)
+            .await
+            .is_some_and(|obj| obj.is_package());
+        if !readable {
+            return Ok(Some(disable(
+                watched,
+                format!("upgraded to {} but the new package is not readable", latest),
+            )));
+        }
+
+        state().write().unwrap().resolved.insert(original, latest);
+        Ok(Some(UpgradeEvent::Switched {
+            name: watched.name,
+            from: current,
+            to: latest,
+        }))
+    }
+
+    /// The latest value of `field` of `obj_id`, as the RPC renders it.
+    async fn read_field(&self, obj_id: &ObjectID, field: &str) -> Result<String> {
+        let object = self
+            .sui
+            .read_api()
+            .get_object_with_options(*obj_id, SuiObjectDataOptions::default().with_content())
+            .await?
+            .data
+            .ok_or_else(|| eyre!("object not found: {}", obj_id))?;
+
+        Ok(object
+            .content
+            .ok_or_eyre("object has no content")?
+            .try_into_move()
+            .ok_or_eyre("not a move object")?
+            .fields
+            .field_value(field)
+            .ok_or_else(|| eyre!("field not found: {}", field))?
+            .to_string())
+    }
+
+    /// Check forever, forwarding every switch or disable to `alerts`.
+    pub async fn run(mut self, interval: Duration, alerts: mpsc::UnboundedSender<UpgradeEvent>) {
+        // first round only records the version gate baselines
+        self.check().await;
+        info!("package upgrade monitor started");
+
+        loop {
+            tokio::time::sleep(interval).await;
+            for event in self.check().await {
+                match event {
+                    UpgradeEvent::Switched { .. } => warn!("{}", event),
+                    UpgradeEvent::Disabled { .. } => error!("{}", event),
+                }
+                let _ = alerts.send(event);
+            }
+        }
+    }
+}
+
+/// Record `version` as the version gate's baseline; disables the protocols if it moved from the
+/// previous one while no package upgrade was followed.
+fn observe_version_gate(
+    baselines: &mut HashMap<ObjectID, u64>,
+    watched: &WatchedPackage,
+    gate_id: ObjectID,
+    version: u64,
+    switched: bool,
+) -> Option<UpgradeEvent> {
+    let (_, field) = watched.version_gate?;
+    match baselines.insert(gate_id, version) {
+        Some(baseline) if baseline != version && !switched => Some(disable(
+            watched,
+            format!(
+                "{} bumped {} -> {} without a known package upgrade",
+                field, baseline, version
+            ),
+        )),
+        _ => None,
+    }
+}
+
+fn disable(watched: &WatchedPackage, reason: String) -> UpgradeEvent {
+    state()
+        .write()
+        .unwrap()
+        .disabled
+        .extend(watched.protocols.iter().cloned());
+
+    UpgradeEvent::Disabled {
+        name: watched.name,
+        protocols: watched.protocols.to_vec(),
+        reason,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_upgrade_caps() {
+        let config = UpgradeConfig {
+            upgrade_caps: vec!["0x1=0x2".to_string(), "0xa=0xb".to_string()],
+            upgrade_check_interval: 60,
+        };
+        let caps = config.parse_upgrade_caps().unwrap();
+        assert_eq!(caps.len(), 2);
+        assert_eq!(caps[&ObjectID::from_single_byte(1)], ObjectID::from_single_byte(2));
+        assert_eq!(caps[&ObjectID::from_single_byte(0xa)], ObjectID::from_single_byte(0xb));
+
+        for invalid in ["0x1", "0x1=cap", "=0x2"] {
+            let config = UpgradeConfig {
+                upgrade_caps: vec![invalid.to_string()],
+                upgrade_check_interval: 60,
+            };
+            assert!(config.parse_upgrade_caps().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_version_gate_bump_disables_the_protocol() {
+        // a protocol no other test routes through, the disabled set is global
+        let watched = WatchedPackage {
+            name: "volo",
+            package: None,
+            protocols: &[Protocol::Volo],
+            version_gate: Some(("version", "version")),
+        };
+        let gate_id = ObjectID::from_single_byte(1);
+        let mut baselines = HashMap::new();
+
+        // the first read only records the baseline
+        assert!(observe_version_gate(&mut baselines, &watched, gate_id, 3, false).is_none());
+        assert!(observe_version_gate(&mut baselines, &watched, gate_id, 3, false).is_none());
+        // a bump that comes with a followed upgrade is expected
+        assert!(observe_version_gate(&mut baselines, &watched, gate_id, 4, true).is_none());
+        assert!(!is_protocol_disabled(&Protocol::Volo));
+
+        let Some(UpgradeEvent::Disabled { reason, .. }) =
+            observe_version_gate(&mut baselines, &watched, gate_id, 5, false)
+        else {
+            panic!("a bump without an upgrade must disable the protocol");
+        };
+        assert_eq!(reason, "version bumped 4 -> 5 without a known package upgrade");
+        assert!(is_protocol_disabled(&Protocol::Volo));
+    }
+}
@@ -0,0 +1,425 @@
+use std::{
+    str::FromStr,
+    sync::{Mutex, OnceLock},
+    time::Duration,
+};
+
+use cached::{proc_macro::cached, Cached, TimedSizedCache};
+use eyre::{bail, eyre, OptionExt, Result};
+use move_core_types::language_storage::StructTag;
+use serde::{Deserialize, Serialize};
+use simulator::Simulator;
+use sui_sdk::{
+    rpc_types::{SuiObjectData, SuiObjectDataOptions},
+    SuiClient,
+};
+use sui_types::{
+    base_types::{ObjectID, SuiAddress},
+    dynamic_field::{derive_dynamic_field_id, Field},
+    id::UID,
+    object::{Object, Owner},
+    transaction::{InputObjectKind, ObjectReadResult},
+    Identifier, TypeTag, SUI_DENY_LIST_OBJECT_ID, SUI_FRAMEWORK_ADDRESS, SUI_SYSTEM_STATE_OBJECT_ID,
+};
+use utils::cache::{CacheLimits, CacheStats};
+
+const OBJECT_CACHE_SIZE: usize = 10_000;
+const OBJECT_CACHE_TTL: Duration = Duration::from_secs(3600);
+const BLOCKED_COIN_CACHE_SIZE: usize = 100_000;
+const BLOCKED_COIN_CACHE_TTL: Duration = Duration::from_secs(3600);
+// a failed deny list check isn't retried sooner
+const BLOCKED_COIN_RETRY: Duration = Duration::from_secs(60);
+
+/// Wrap an object read from the simulator so it can be passed as a `SimulateCtx` override. A shared
+/// object is taken `mutable` or not, as the transaction passes it.
+pub fn new_object_read_result(object: Object, mutable: bool) -> ObjectReadResult {
+    let input_object_kind = match object.owner() {
+        Owner::Shared { initial_shared_version } => InputObjectKind::SharedMoveObject {
+            id: object.id(),
+            initial_shared_version: *initial_shared_version,
+            mutable,
+        },
+        _ => InputObjectKind::ImmOrOwnedMoveObject(object.compute_object_reference()),
+    };
+
+    ObjectReadResult::new(input_object_kind, object.into())
+}
+
+#[cached(
+    ty = "TimedSizedCache<String, SuiObjectData>",
+    create = r##"{ CacheLimits::from_env("OBJECT", OBJECT_CACHE_SIZE, OBJECT_CACHE_TTL).new_cache() }"##,
+    convert = r##"{ obj_id.to_string() }"##,
+    result = true
+)]
+pub async fn get_object_cache(sui: &SuiClient, obj_id: &str) -> Result<SuiObjectData> {
+    get_object(sui, obj_id).await
+}
+
+pub async fn get_object(sui: &SuiClient, obj_id: &str) -> Result<SuiObjectData> {
+    let obj_id = ObjectID::from_hex_literal(obj_id)?;
+    let opts = SuiObjectDataOptions::full_content();
+    let obj = sui
+        .read_api()
+        .get_object_with_options(obj_id, opts)
+        .await?
+        .into_object()?;
+
+    Ok(obj)
+}
+
+/// Senders the deny lists are checked for, set once at startup.
+static SENDERS: OnceLock<Vec<SuiAddress>> = OnceLock::new();
+
+/// Check the deny lists for `senders`, set before the first check; without senders only a global
+/// pause blocks a coin.
+pub fn set_deny_list_senders(senders: Vec<SuiAddress>) -> Result<()> {
+    utils::set_once(&SENDERS, senders, "deny list senders")
+}
+
+fn senders() -> &'static [SuiAddress] {
+    SENDERS.get().map(Vec::as_slice).unwrap_or_default()
+}
+
+// 0x2::bag::Bag and 0x2::table::Table
+#[derive(Deserialize)]
+struct Collection {
+    id: UID,
+    _size: u64,
+}
+
+// 0x2::deny_list::DenyList
+#[derive(Deserialize)]
+struct DenyList {
+    _id: UID,
+    lists: Collection,
+}
+
+// 0x2::deny_list::PerTypeList, the v1 deny list of all coin types
+#[derive(Deserialize)]
+struct PerTypeList {
+    _id: UID,
+    _denied_count: Collection,
+    denied_addresses: Collection,
+}
+
+// 0x2::deny_list::ConfigKey, keys the v2 config of a coin type
+#[derive(Serialize, Deserialize)]
+struct ConfigKey {
+    per_type_index: u64,
+    per_type_key: Vec<u8>,
+}
+
+// 0x2::config::SettingData<bool>
+#[derive(Debug, Clone, Deserialize)]
+struct SettingData {
+    newer_value_epoch: u64,
+    newer_value: Option<bool>,
+    older_value_opt: Option<bool>,
+}
+
+// 0x2::config::Setting<bool>
+#[derive(Debug, Clone, Deserialize)]
+struct Setting {
+    data: Option<SettingData>,
+}
+
+impl Setting {
+    /// Whether the setting is on in `epoch` or from the next one: a change takes effect the epoch
+    /// after it was made, and transfers are checked against both.
+    fn is_on(&self, epoch: u64) -> bool {
+        let Some(data) = &self.data else {
+            return false;
+        };
+        let current = if epoch > data.newer_value_epoch {
+            data.newer_value
+        } else {
+            data.older_value_opt
+        };
+        current == Some(true) || data.newer_value == Some(true)
+    }
+}
+
+/// The deny list entries of one coin type that concern the senders.
+#[derive(Debug, Default)]
+struct DenyEntries {
+    // a sender is on the v1 list
+    v1_denied: bool,
+    // the v2 `GlobalPauseKey` setting
+    paused: Option<Setting>,
+    // the v2 `AddressKey` settings of the senders that have one
+    denied: Vec<Setting>,
+}
+
+impl DenyEntries {
+    fn blocks(&self, epoch: u64) -> bool {
+        self.v1_denied ||
+            self.paused.as_ref().is_some_and(|setting| setting.is_on(epoch)) ||
+            self.denied.iter().any(|setting| setting.is_on(epoch))
+    }
+}
+
+fn framework_tag(module: &str, name: &str, type_params: Vec<TypeTag>) -> Result<TypeTag> {
+    Ok(TypeTag::Struct(Box::new(StructTag {
+        address: SUI_FRAMEWORK_ADDRESS,
+        module: Identifier::new(module).map_err(|e| eyre!(e))?,
+        name: Identifier::new(name).map_err(|e| eyre!(e))?,
+        type_params,
+    })))
+}
+
+fn contents(object: &Object) -> Result<&[u8]> {
+    Ok(object.data.try_as_move().ok_or_eyre("not a move object")?.contents())
+}
+
+/// The key of `coin_type` in the deny lists: its `type_name` with the full-length address and no
+/// `0x`, so every coin type has entries of its own, whatever else its package defines.
+fn per_type_key(coin_type: &str) -> Result<Vec<u8>> {
+    match TypeTag::from_str(coin_type).map_err(|e| eyre!("invalid coin type {}: {}", coin_type, e))? {
+        TypeTag::Struct(tag) => Ok(tag.to_canonical_string(false).into_bytes()),
+        _ => bail!("not a coin type: {}", coin_type),
+    }
+}
+
+/// The dynamic object field of the deny list holding the v2 config of the coin type keyed by
+/// `per_type_key`.
+fn config_field_id(per_type_key: &[u8]) -> Result<ObjectID> {
+    let key = ConfigKey {
+        per_type_index: 0,
+        per_type_key: per_type_key.to_vec(),
+    };
+    let wrapper_tag = framework_tag(
+        "dynamic_object_field",
+        "Wrapper",
+        vec![framework_tag("deny_list", "ConfigKey", vec![])?],
+    )?;
+    Ok(derive_dynamic_field_id(
+        SUI_DENY_LIST_OBJECT_ID,
+        &wrapper_tag,
+        &bcs::to_bytes(&key)?,
+    )?)
+}
+
+/// The current epoch, the first field of the system state's inner object. That object is a
+/// dynamic field of 0x5 keyed by the system state version.
+async fn current_epoch(simulator: &dyn Simulator) -> Result<u64> {
+    #[derive(Deserialize)]
+    struct SuiSystemState {
+        _id: UID,
+        version: u64,
+    }
+
+    let state = simulator
+        .get_object(&SUI_SYSTEM_STATE_OBJECT_ID)
+        .await
+        .ok_or_eyre("system state not found")?;
+    let state: SuiSystemState = bcs::from_bytes(contents(&state)?)?;
+    let inner_id = derive_dynamic_field_id(
+        SUI_SYSTEM_STATE_OBJECT_ID,
+        &TypeTag::U64,
+        &bcs::to_bytes(&state.version)?,
+    )?;
+    let inner = simulator
+        .get_object(&inner_id)
+        .await
+        .ok_or_eyre("system state inner not found")?;
+    // field id and name, then the inner state starting with its epoch
+    let epoch = contents(&inner)?
+        .get(40..48)
+        .ok_or_eyre("system state inner too short")?;
+    Ok(bcs::from_bytes(epoch)?)
+}
+
+async fn deny_entries(simulator: &dyn Simulator, coin_type: &str) -> Result<DenyEntries> {
+    let key = per_type_key(coin_type)?;
+    let senders = senders();
+    let mut entries = DenyEntries::default();
+
+    // v1: `lists[0]` is the `PerTypeList` of coins, its `denied_addresses` keyed by type
+    let deny_list = simulator
+        .get_object(&SUI_DENY_LIST_OBJECT_ID)
+        .await
+        .ok_or_eyre("deny list not found")?;
+    let deny_list: DenyList = bcs::from_bytes(contents(&deny_list)?)?;
+    let coin_list_id = derive_dynamic_field_id(deny_list.lists.id.object_id(), &TypeTag::U64, &bcs::to_bytes(&0u64)?)?;
+    if let Some(coin_list) = simulator.get_object(&coin_list_id).await {
+        let coin_list: Field<u64, PerTypeList> = bcs::from_bytes(contents(&coin_list)?)?;
+        let denied_id = derive_dynamic_field_id(
+            coin_list.value.denied_addresses.id.object_id(),
+            &TypeTag::Vector(Box::new(TypeTag::U8)),
+            &bcs::to_bytes(&key)?,
+        )?;
+        if let Some(denied) = simulator.get_object(&denied_id).await {
+            // VecSet<address>
+            let denied: Field<Vec<u8>, Vec<SuiAddress>> = bcs::from_bytes(contents(&denied)?)?;
+            entries.v1_denied = denied.value.iter().any(|address| senders.contains(address));
+        }
+    }
+
+    // v2: a `Config` per coin type, created the first time its `DenyCapV2` is used
+    let Some(config_field) = simulator.get_object(&config_field_id(&key)?).await else {
+        return Ok(entries);
+    };
+    let config_field: Field<ConfigKey, ObjectID> = bcs::from_bytes(contents(&config_field)?)?;
+    let config_id = config_field.value;
+
+    let pause_id = derive_dynamic_field_id(
+        config_id,
+        &framework_tag("deny_list", "GlobalPauseKey", vec![])?,
+        // an empty struct has a dummy bool field
+        &bcs::to_bytes(&false)?,
+    )?;
+    if let Some(pause) = simulator.get_object(&pause_id).await {
+        let pause: Field<bool, Setting> = bcs::from_bytes(contents(&pause)?)?;
+        entries.paused = Some(pause.value);
+    }
+
+    let address_tag = framework_tag("deny_list", "AddressKey", vec![])?;
+    let setting_ids = senders
+        .iter()
+        .map(|sender| {
+            Ok(derive_dynamic_field_id(
+                config_id,
+                &address_tag,
+                &bcs::to_bytes(sender)?,
+            )?)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    for setting in simulator.get_objects(&setting_ids).await.into_iter().flatten() {
+        let setting: Field<SuiAddress, Setting> = bcs::from_bytes(contents(&setting)?)?;
+        entries.denied.push(setting.value);
+    }
+
+    Ok(entries)
+}
+
+/// Whether the senders can't trade `coin_type`: the deny list of that exact type denies one of
+/// them, or (v2) pauses it for everyone. Only regulated coins have deny list entries, written
+/// through the `DenyCap` of their type, so coins that merely share a package with one, or that
+/// nobody was ever denied, aren't blocked.
+#[cached(
+    ty = "TimedSizedCache<String, bool>",
+    create = r##"{ CacheLimits::from_env("BLOCKED_COIN", BLOCKED_COIN_CACHE_SIZE, BLOCKED_COIN_CACHE_TTL).new_cache() }"##,
+    convert = r##"{ coin_type.to_string() }"##,
+    result = true
+)]
+async fn blocked_coin(simulator: &dyn Simulator, coin_type: &str) -> Result<bool> {
+    let entries = deny_entries(simulator, coin_type).await?;
+    if !entries.v1_denied && entries.paused.is_none() && entries.denied.is_empty() {
+        return Ok(false);
+    }
+
+    Ok(entries.blocks(current_epoch(simulator).await?))
+}
+
+fn failed_checks() -> &'static Mutex<TimedSizedCache<String, String>> {
+    static FAILED_CHECKS: OnceLock<Mutex<TimedSizedCache<String, String>>> = OnceLock::new();
+    FAILED_CHECKS.get_or_init(|| {
+        let limits = CacheLimits::from_env("BLOCKED_COIN_FAILURE", BLOCKED_COIN_CACHE_SIZE, BLOCKED_COIN_RETRY);
+        Mutex::new(limits.new_cache())
+    })
+}
+
+/// `blocked_coin`, with failed checks remembered apart from the results and not retried for
+/// `BLOCKED_COIN_RETRY`.
+pub async fn is_blocked_coin(simulator: &dyn Simulator, coin_type: &str) -> Result<bool> {
+    if let Some(error) = failed_checks().lock().unwrap().cache_get(&coin_type.to_string()) {
+        bail!("deny list check failed recently: {}", error);
+    }
+
+    blocked_coin(simulator, coin_type).await.inspect_err(|error| {
+        failed_checks()
+            .lock()
+            .unwrap()
+            .cache_set(coin_type.to_string(), format!("{error:#}"));
+    })
+}
+
+pub async fn cache_stats() -> Vec<CacheStats> {
+    vec![
+        CacheStats::of("object", &*GET_OBJECT_CACHE.lock().await),
+        CacheStats::of("blocked_coin", &*BLOCKED_COIN.lock().await),
+        CacheStats::of("blocked_coin_failure", &*failed_checks().lock().unwrap()),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setting(newer_value_epoch: u64, newer_value: Option<bool>, older_value_opt: Option<bool>) -> Setting {
+        Setting {
+            data: Some(SettingData {
+                newer_value_epoch,
+                newer_value,
+                older_value_opt,
+            }),
+        }
+    }
+
+    #[test]
+    fn test_regulated_coin() {
+        // a sender denied in epoch 10 is blocked right away, the denial applies from epoch 11
+        let denied = DenyEntries {
+            denied: vec![setting(10, Some(true), None)],
+            ..Default::default()
+        };
+        assert!(denied.blocks(10));
+        assert!(denied.blocks(11));
+
+        // undenied in epoch 10, still denied until it ends
+        let undenied = DenyEntries {
+            denied: vec![setting(10, Some(false), Some(true))],
+            ..Default::default()
+        };
+        assert!(undenied.blocks(10));
+        assert!(!undenied.blocks(11));
+
+        // a global pause blocks everyone, a lifted one nobody
+        let paused = DenyEntries {
+            paused: Some(setting(3, Some(true), None)),
+            ..Default::default()
+        };
+        assert!(paused.blocks(20));
+        let unpaused = DenyEntries {
+            paused: Some(setting(3, None, Some(true))),
+            ..Default::default()
+        };
+        assert!(!unpaused.blocks(20));
+
+        let v1 = DenyEntries {
+            v1_denied: true,
+            ..Default::default()
+        };
+        assert!(v1.blocks(0));
+    }
+
+    #[test]
+    fn test_unregulated_coin() {
+        // no deny list entries, or a regulated coin that never denied the senders
+        assert!(!DenyEntries::default().blocks(100));
+        let cleared = DenyEntries {
+            denied: vec![Setting { data: None }],
+            ..Default::default()
+        };
+        assert!(!cleared.blocks(100));
+    }
+
+    #[test]
+    fn test_coin_in_shared_package() {
+        let package = "0xdba34672e30cb065b1f93e3ab55318768fd6fef66c15942c9f7cb846e2f900e7";
+        let regulated = per_type_key(&format!("{package}::usdc::USDC")).unwrap();
+        let other = per_type_key(&format!("{package}::treasury::TREASURY")).unwrap();
+        assert_eq!(
+            regulated,
+            b"dba34672e30cb065b1f93e3ab55318768fd6fef66c15942c9f7cb846e2f900e7::usdc::USDC"
+        );
+        // entries of one coin type are never read for another of the same package
+        assert_ne!(config_field_id(&regulated).unwrap(), config_field_id(&other).unwrap());
+
+        // short addresses are keyed at full length
+        assert_eq!(
+            per_type_key("0x2::sui::SUI").unwrap(),
+            b"0000000000000000000000000000000000000000000000000000000000000002::sui::SUI"
+        );
+    }
+}
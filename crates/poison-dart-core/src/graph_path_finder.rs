@@ -0,0 +1,1493 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::ops::ControlFlow;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::time::{Duration, Instant};
+
+use clap::{Parser, ValueEnum};
+use dex_indexer::normalize_coin_type;
+use eyre::{ensure, Result, WrapErr};
+use simulator::{SimEpoch, SimulateCtx};
+use sui_sdk::SuiClient;
+use sui_types::base_types::{ObjectID, SuiAddress};
+use tokio::sync::{mpsc, Semaphore};
+use tokio::task::JoinSet;
+use tracing::{debug, info, Instrument};
+
+use crate::defi::{base, Dex, DexEnum, DexSearcher, Path, TradeType, Trader};
+
+#[derive(Clone, Debug, Parser)]
+pub struct GraphConfig {
+    /// How negative cycles are searched for in the pool graph
+    #[arg(long, env = "SUI_CYCLE_SEARCH", value_enum, default_value_t = CycleSearch::BellmanFord)]
+    pub cycle_search: CycleSearch,
+
+    /// Cycles returned by the top-k search
+    #[arg(long, env = "SUI_TOP_K_CYCLES", default_value_t = 10)]
+    pub top_k_cycles: usize,
+
+    /// Longest cycle, in swaps; tokens farther than this from the base coin stay out of the graph
+    #[arg(long, env = "SUI_MAX_CYCLE_LEN", default_value_t = 4)]
+    pub max_cycle_len: usize,
+
+    /// File of coin types, one per line: only these (and the base coin) enter the graph
+    #[arg(long, env = "SUI_GRAPH_TOKEN_ALLOWLIST")]
+    pub token_allowlist: Option<PathBuf>,
+
+    /// File of coin types, one per line, kept out of the graph
+    #[arg(long, env = "SUI_GRAPH_TOKEN_DENYLIST")]
+    pub token_denylist: Option<PathBuf>,
+
+    /// Pools with less liquidity than this add no edge
+    #[arg(long, env = "SUI_GRAPH_MIN_LIQUIDITY", default_value_t = 0)]
+    pub min_pool_liquidity: u128,
+
+    /// Coin types multi-start searches look for cycles from, in order of preference (default: the
+    /// base coin)
+    #[arg(long, env = "SUI_GRAPH_START_TOKENS", value_delimiter = ',')]
+    pub start_tokens: Vec<String>,
+}
+
+impl Default for GraphConfig {
+    fn default() -> Self {
+        Self {
+            cycle_search: CycleSearch::BellmanFord,
+            top_k_cycles: 10,
+            max_cycle_len: 4,
+            token_allowlist: None,
+            token_denylist: None,
+            min_pool_liquidity: 0,
+            start_tokens: vec![],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CycleSearch {
+    /// Every negative cycle one Bellman-Ford pass finds, unranked
+    BellmanFord,
+    /// The k most profitable cycles through the start token, best first
+    TopK,
+}
+
+/// Tokens admitted to the graph.
+#[derive(Debug, Default)]
+pub struct TokenLists {
+    allow: Option<HashSet<String>>,
+    deny: HashSet<String>,
+}
+
+impl TokenLists {
+    pub fn new(allow: Option<Vec<String>>, deny: Vec<String>) -> Self {
+        let normalize = |coin_types: Vec<String>| coin_types.iter().map(|c| normalize_coin_type(c)).collect();
+        Self {
+            allow: allow.map(normalize),
+            deny: normalize(deny),
+        }
+    }
+
+    pub fn load(allowlist: Option<&std::path::Path>, denylist: Option<&std::path::Path>) -> Result<Self> {
+        Ok(Self {
+            allow: allowlist.map(read_token_list).transpose()?,
+            deny: denylist.map(read_token_list).transpose()?.unwrap_or_default(),
+        })
+    }
+
+    /// The base coin is always admitted.
+    pub fn admits(&self, coin_type: &str) -> bool {
+        if base::is_base_coin(coin_type) {
+            return true;
+        }
+        let coin_type = normalize_coin_type(coin_type);
+        !self.deny.contains(&coin_type) && self.allow.as_ref().map_or(true, |allow| allow.contains(&coin_type))
+    }
+}
+
+/// Coin types one per line; blank lines and `#` comments are skipped.
+fn read_token_list(path: &std::path::Path) -> Result<HashSet<String>> {
+    let content = std::fs::read_to_string(path).wrap_err_with(|| format!("reading token list {}", path.display()))?;
+    Ok(parse_token_list(&content))
+}
+
+fn parse_token_list(content: &str) -> HashSet<String> {
+    content
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter(|line| !line.is_empty())
+        .map(normalize_coin_type)
+        .collect()
+}
+
+/// A `GraphConfig` with its token lists loaded, for the graph and `Defi` path discovery. Clones
+/// share the token lists, so lists set through one reach every holder.
+#[derive(Clone, Debug, Default)]
+pub struct GraphSettings {
+    config: Arc<GraphConfig>,
+    // swapped by `set_token_lists`; graphs built and paths found after that use the new lists
+    tokens: Arc<RwLock<Arc<TokenLists>>>,
+}
+
+impl GraphSettings {
+    /// Check `config` and load its token lists.
+    pub fn new(config: GraphConfig) -> Result<Self> {
+        ensure!(config.max_cycle_len >= 2, "max_cycle_len must be at least 2");
+        let tokens = TokenLists::load(config.token_allowlist.as_deref(), config.token_denylist.as_deref())?;
+        info!(
+            allowed = tokens.allow.as_ref().map(HashSet::len),
+            denied = tokens.deny.len(),
+            "graph token lists loaded"
+        );
+        Ok(Self {
+            config: Arc::new(config),
+            tokens: Arc::new(RwLock::new(Arc::new(tokens))),
+        })
+    }
+
+    pub fn config(&self) -> &GraphConfig {
+        &self.config
+    }
+
+    pub fn token_lists(&self) -> Arc<TokenLists> {
+        Arc::clone(&self.tokens.read().unwrap())
+    }
+
+    /// Replace the token lists; they apply from the next graph rebuild and path search.
+    pub fn set_token_lists(&self, tokens: TokenLists) {
+        info!(
+            allowed = tokens.allow.as_ref().map(HashSet::len),
+            denied = tokens.deny.len(),
+            "graph token lists replaced"
+        );
+        *self.tokens.write().unwrap() = Arc::new(tokens);
+    }
+
+    /// Tokens multi-start searches look for cycles from, the base coin alone when none are
+    /// configured.
+    pub fn start_tokens(&self) -> Vec<String> {
+        let start_tokens = &self.config.start_tokens;
+        if start_tokens.is_empty() {
+            return vec![base::coin_type().to_string()];
+        }
+        start_tokens
+            .iter()
+            .map(|coin_type| normalize_coin_type(coin_type))
+            .collect()
+    }
+}
+
+/// Represents a node in the arbitrage graph
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Node {
+    token_type: String,
+}
+
+/// Represents an edge in the arbitrage graph
+#[derive(Debug, Clone)]
+pub struct Edge {
+    from: Node,
+    to: Node,
+    dex: DexEnum,
+    weight: f64, // Negative log of the exchange rate
+}
+
+/// A graph representation of the DEX ecosystem
+#[derive(Debug, Clone)]
+pub struct ArbitrageGraph {
+    nodes: HashSet<Node>,
+    edges: HashMap<Node, Vec<Edge>>,
+    // pool => position of each of its edges in `edges`
+    pool_edges: HashMap<ObjectID, Vec<(Node, usize)>>,
+    // pools changed since their edges were last quoted
+    dirty_pools: HashSet<ObjectID>,
+    // the mutation log up to here is in `dirty_pools`
+    synced_mutations: u64,
+    built_at: Instant,
+    settings: GraphSettings,
+}
+
+// pool writes kept for graphs to catch up on, a graph further behind re-quotes every pool
+const MUTATION_LOG_SIZE: usize = 100_000;
+
+/// Objects written by the transactions the collectors saw, in order.
+#[derive(Default)]
+struct MutationLog {
+    // sequence number of the next write
+    next: u64,
+    writes: VecDeque<ObjectID>,
+}
+
+fn mutation_log() -> &'static Mutex<MutationLog> {
+    static MUTATIONS: OnceLock<Mutex<MutationLog>> = OnceLock::new();
+    MUTATIONS.get_or_init(Default::default)
+}
+
+/// Record objects a transaction mutated; every graph re-quotes the edges of those that are its
+/// pools before its next search.
+pub fn observe_mutated(object_ids: impl IntoIterator<Item = ObjectID>) {
+    let mut log = mutation_log().lock().unwrap();
+    for id in object_ids {
+        log.writes.push_back(id);
+        log.next += 1;
+    }
+    while log.writes.len() > MUTATION_LOG_SIZE {
+        log.writes.pop_front();
+    }
+}
+
+/// Prices the edges of the graph: the weight of an edge is `-ln(rate * (1 - fee))`, so a cycle
+/// whose weights sum below zero returns more than it took in.
+#[async_trait::async_trait]
+pub trait WeightProvider: Send + Sync {
+    /// Weights of `dexes` in order, None for a pool that could not be quoted.
+    async fn weights(&self, dexes: &[DexEnum]) -> Vec<Option<f64>>;
+}
+
+/// Weight of a constant-product pool from its reserves and fee, no I/O. Rates stay in raw units:
+/// decimals cancel out around a cycle.
+fn reserve_weight(dex: &DexEnum) -> Option<f64> {
+    let cp = dex.constant_product()?;
+    if cp.reserve_in == 0 || cp.reserve_out == 0 || cp.fee_num >= cp.fee_den {
+        return None;
+    }
+    let rate = cp.reserve_out as f64 / cp.reserve_in as f64;
+    let fee = cp.fee_num as f64 / cp.fee_den as f64;
+    Some(-(rate * (1.0 - fee)).ln())
+}
+
+/// Weight of a quoted swap, whose output is already net of the fee.
+fn quote_weight(amount_in: u64, amount_out: u64) -> Option<f64> {
+    (amount_in > 0 && amount_out > 0).then(|| -(amount_out as f64 / amount_in as f64).ln())
+}
+
+/// Weights constant-product pools from their reserves and leaves every other pool out of the graph.
+pub struct ReserveWeights;
+
+#[async_trait::async_trait]
+impl WeightProvider for ReserveWeights {
+    async fn weights(&self, dexes: &[DexEnum]) -> Vec<Option<f64>> {
+        dexes.iter().map(reserve_weight).collect()
+    }
+}
+
+// simulated quotes in flight at once
+const QUOTE_CONCURRENCY: usize = 32;
+
+/// Weights constant-product pools from their reserves, and every other pool by the rate of a swap
+/// of one whole coin: quoted in process when the pool has local math (`Dex::quote`: stable,
+/// weighted and concentrated-liquidity curves), else simulated against its current state,
+/// `QUOTE_CONCURRENCY` at a time.
+#[derive(Clone)]
+pub struct QuoteWeights {
+    trader: Arc<Trader>,
+    dex_searcher: Arc<dyn DexSearcher>,
+    sui: SuiClient,
+}
+
+impl QuoteWeights {
+    pub fn new(trader: Arc<Trader>, dex_searcher: Arc<dyn DexSearcher>, sui: SuiClient) -> Self {
+        Self {
+            trader,
+            dex_searcher,
+            sui,
+        }
+    }
+
+    // one whole coin in
+    async fn quote_amount(&self, dex: &DexEnum) -> Option<u64> {
+        let decimals = self.dex_searcher.coin_decimals(&dex.coin_in_type()).await?;
+        10u64.checked_pow(decimals as u32)
+    }
+
+    async fn quote(&self, dex: DexEnum, sim_ctx: SimulateCtx) -> Option<f64> {
+        let amount_in = self.quote_amount(&dex).await?;
+        // the coin in is mocked, so the quote needs neither a funded sender nor gas coins
+        let res = self
+            .trader
+            .get_trade_result(
+                &Path::new(vec![dex]),
+                SuiAddress::ZERO,
+                amount_in,
+                TradeType::Swap,
+                vec![],
+                sim_ctx,
+            )
+            .await
+            .ok()?;
+        quote_weight(amount_in, res.amount_out)
+    }
+}
+
+#[async_trait::async_trait]
+impl WeightProvider for QuoteWeights {
+    async fn weights(&self, dexes: &[DexEnum]) -> Vec<Option<f64>> {
+        let mut weights: Vec<_> = dexes.iter().map(reserve_weight).collect();
+        for (idx, dex) in dexes.iter().enumerate() {
+            if weights[idx].is_none() {
+                if let Some(amount_in) = self.quote_amount(dex).await {
+                    weights[idx] = dex
+                        .quote(amount_in)
+                        .and_then(|amount_out| quote_weight(amount_in, amount_out));
+                }
+            }
+        }
+        if weights.iter().all(Option::is_some) {
+            return weights;
+        }
+
+        let epoch = match self.sui.governance_api().get_latest_sui_system_state().await {
+            Ok(sys_state) => SimEpoch::from(sys_state),
+            Err(error) => {
+                debug!(?error, "no epoch to quote pools against");
+                return weights;
+            }
+        };
+        let sim_ctx = SimulateCtx::new(epoch, vec![]);
+
+        let permits = Arc::new(Semaphore::new(QUOTE_CONCURRENCY));
+        let mut joinset = JoinSet::new();
+        for (idx, dex) in dexes.iter().enumerate().filter(|(idx, _)| weights[*idx].is_none()) {
+            let quoter = self.clone();
+            let (dex, sim_ctx, permits) = (dex.clone(), sim_ctx.clone(), Arc::clone(&permits));
+            joinset.spawn(
+                async move {
+                    let _permit = permits.acquire_owned().await.ok()?;
+                    quoter.quote(dex, sim_ctx).await.map(|weight| (idx, weight))
+                }
+                .in_current_span(),
+            );
+        }
+        while let Some(quoted) = joinset.join_next().await {
+            if let Ok(Some((idx, weight))) = quoted {
+                weights[idx] = Some(weight);
+            }
+        }
+
+        weights
+    }
+}
+
+impl ArbitrageGraph {
+    /// Create a new arbitrage graph from DEX searcher, with edges priced by `weights`
+    pub async fn new(
+        dex_searcher: Arc<dyn DexSearcher>,
+        weights: &dyn WeightProvider,
+        settings: GraphSettings,
+    ) -> Result<Self> {
+        let mut graph = Self {
+            nodes: HashSet::new(),
+            edges: HashMap::new(),
+            pool_edges: HashMap::new(),
+            dirty_pools: HashSet::new(),
+            synced_mutations: mutation_log().lock().unwrap().next,
+            built_at: Instant::now(),
+            settings,
+        };
+        
+        // Add the base coin as a node
+        let base_node = Node { token_type: base::coin_type().to_string() };
+        graph.nodes.insert(base_node);
+        
+        // Start building the graph from the base coin
+        let mut pending = Vec::new();
+        graph
+            .build_graph(dex_searcher.clone(), base::coin_type(), 0, &mut pending)
+            .await?;
+        
+        // Find other tokens to add to the graph
+        let base_dexes = dex_searcher.find_dexes(base::coin_type(), None).await?;
+        for dex in base_dexes {
+            let token_type = dex.coin_out_type();
+            if !base::is_base_coin(&token_type) {
+                graph
+                    .build_graph(dex_searcher.clone(), &token_type, 1, &mut pending)
+                    .await?;
+            }
+        }
+
+        // the other tokens cycles are searched from root graphs of their own
+        for token_type in graph.settings.start_tokens() {
+            if !base::is_base_coin(&token_type) {
+                graph
+                    .build_graph(dex_searcher.clone(), &token_type, 0, &mut pending)
+                    .await?;
+            }
+        }
+
+        // price every edge in one batch
+        let unpriced = graph.add_edges(pending, weights).await;
+        
+        info!("Built arbitrage graph with {} nodes and {} edges ({} pools unpriced) in {:?}", 
+            graph.nodes.len(), 
+            graph.edges.values().map(|v| v.len()).sum::<usize>(),
+            unpriced,
+            graph.built_at.elapsed());
+        
+        Ok(graph)
+    }
+    
+    /// Build the graph breadth-first from a token `start_depth` swaps away from the base coin, up
+    /// to `max_cycle_len` swaps away; its edges are collected into `pending` to be priced
+    async fn build_graph(
+        &mut self,
+        dex_searcher: Arc<dyn DexSearcher>,
+        start_token: &str,
+        start_depth: usize,
+        pending: &mut Vec<(Node, Node, DexEnum)>,
+    ) -> Result<()> {
+        let config = self.settings.config.clone();
+        let tokens = self.settings.token_lists();
+        let mut visited = HashSet::new();
+        let mut queue = VecDeque::from([(start_token.to_string(), start_depth)]);
+        
+        while let Some((token_type, depth)) = queue.pop_front() {
+            // a token this far out can't be on a short enough cycle through the base coin
+            if depth >= config.max_cycle_len || visited.contains(&token_type) {
+                continue;
+            }
+            visited.insert(token_type.clone());
+
+            if !tokens.admits(&token_type) || !dex_searcher.is_tradeable(&token_type).await {
+                continue;
+            }
+            
+            // Add node for this token
+            let node = Node { token_type: token_type.clone() };
+            self.nodes.insert(node.clone());
+            
+            // Find DEXes for this token
+            let dexes = match dex_searcher.find_dexes(&token_type, None).await {
+                Ok(dexes) => dexes,
+                Err(_) => continue,
+            };
+            
+            // Add edges for each DEX
+            for dex in dexes {
+                let out_token = dex.coin_out_type();
+                if !tokens.admits(&out_token) || dex.liquidity() < config.min_pool_liquidity {
+                    continue;
+                }
+                let to_node = Node { token_type: out_token.clone() };
+                
+                // Add the destination node
+                self.nodes.insert(to_node.clone());
+                
+                pending.push((node.clone(), to_node, dex));
+
+                // Add the out token to the queue if not visited
+                if !visited.contains(&out_token) {
+                    queue.push_back((out_token, depth + 1));
+                }
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Price `pending` edges and add those that could be priced. Returns the number left out.
+    async fn add_edges(&mut self, mut pending: Vec<(Node, Node, DexEnum)>, weights: &dyn WeightProvider) -> usize {
+        // tokens reached from several starts list the same pools again
+        let mut seen = HashSet::new();
+        pending.retain(|(from, _, dex)| seen.insert((from.clone(), dex.object_id())));
+
+        let dexes: Vec<_> = pending.iter().map(|(_, _, dex)| dex.clone()).collect();
+        let weights = weights.weights(&dexes).await;
+
+        let mut unpriced = 0;
+        for ((from, to, dex), weight) in pending.into_iter().zip(weights) {
+            match weight {
+                Some(weight) => self.add_edge(from, to, dex, weight),
+                None => unpriced += 1,
+            }
+        }
+        unpriced
+    }
+
+    fn add_edge(&mut self, from: Node, to: Node, dex: DexEnum, weight: f64) {
+        let pool_id = dex.object_id();
+        let edges = self.edges.entry(from.clone()).or_default();
+        edges.push(Edge {
+            from: from.clone(),
+            to,
+            dex,
+            weight,
+        });
+        self.pool_edges.entry(pool_id).or_default().push((from, edges.len() - 1));
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.edges.values().map(Vec::len).sum()
+    }
+
+    /// Mark a pool whose state changed; its edges are re-quoted on the next `recompute_dirty`.
+    pub fn mark_dirty(&mut self, pool_id: ObjectID) {
+        if self.pool_edges.contains_key(&pool_id) {
+            self.dirty_pools.insert(pool_id);
+        }
+    }
+
+    /// Mark the pools of the graph written since the last call (see `observe_mutated`).
+    pub fn sync_mutations(&mut self) {
+        let log = mutation_log().lock().unwrap();
+        let first = log.next - log.writes.len() as u64;
+        if self.synced_mutations < first {
+            // writes were dropped before this graph saw them
+            self.dirty_pools.extend(self.pool_edges.keys().copied());
+        } else {
+            for pool_id in log.writes.iter().skip((self.synced_mutations - first) as usize) {
+                if self.pool_edges.contains_key(pool_id) {
+                    self.dirty_pools.insert(*pool_id);
+                }
+            }
+        }
+        self.synced_mutations = log.next;
+    }
+
+    /// Re-quote exactly the edges of dirty pools, in one batch. Returns the number of edges
+    /// recomputed. An edge that can no longer be priced stays in the graph at infinite weight.
+    pub async fn recompute_dirty(&mut self, dex_searcher: &dyn DexSearcher, weights: &dyn WeightProvider) -> usize {
+        let mut dirty = Vec::new();
+
+        for pool_id in std::mem::take(&mut self.dirty_pools) {
+            let Some(positions) = self.pool_edges.get(&pool_id) else {
+                continue;
+            };
+
+            for (from, idx) in positions {
+                let Some(edge) = self.edges.get_mut(from).and_then(|edges| edges.get_mut(*idx)) else {
+                    continue;
+                };
+
+                // reload the edge's dex with the pool's new state, the stored one is re-quoted if
+                // the pool can't be read
+                match dex_searcher.pool_dexes(&pool_id, &edge.from.token_type).await {
+                    Ok(dexes) => {
+                        if let Some(dex) = dexes.into_iter().find(|dex| dex.coin_out_type() == edge.to.token_type) {
+                            edge.dex = dex;
+                        }
+                    }
+                    Err(error) => debug!(%pool_id, ?error, "failed to reload pool"),
+                }
+                dirty.push((from.clone(), *idx));
+            }
+        }
+
+        let dexes: Vec<_> = dirty
+            .iter()
+            .map(|(from, idx)| self.edges[from][*idx].dex.clone())
+            .collect();
+        let weights = weights.weights(&dexes).await;
+        for ((from, idx), weight) in dirty.iter().zip(weights) {
+            if let Some(edge) = self.edges.get_mut(from).and_then(|edges| edges.get_mut(*idx)) {
+                edge.weight = weight.unwrap_or(f64::INFINITY);
+            }
+        }
+
+        dirty.len()
+    }
+
+    /// Find negative cycles in the graph using Bellman-Ford algorithm
+    /// These cycles represent arbitrage opportunities
+    pub fn find_arbitrage_opportunities(&self, start_token: &str) -> Vec<Vec<Edge>> {
+        let mut negative_cycles = Vec::new();
+        self.for_each_negative_cycle(start_token, |cycle| {
+            negative_cycles.push(cycle);
+            ControlFlow::Continue(())
+        });
+
+        debug!("Found {} negative cycles", negative_cycles.len());
+        negative_cycles
+    }
+
+    /// Hand each negative cycle to `on_cycle` as soon as relaxation closes it, once per cycle;
+    /// `Break` stops the search.
+    pub fn for_each_negative_cycle(&self, start_token: &str, mut on_cycle: impl FnMut(Vec<Edge>) -> ControlFlow<()>) {
+        let start_node = Node { token_type: start_token.to_string() };
+        if !self.nodes.contains(&start_node) {
+            debug!("Start token {} not found in graph", start_token);
+            return;
+        }
+        
+        // Initialize distance map
+        let mut distances: HashMap<Node, f64> = HashMap::new();
+        let mut predecessors: HashMap<Node, Option<(Node, Edge)>> = HashMap::new();
+        
+        // Set initial distances
+        for node in &self.nodes {
+            distances.insert(node.clone(), if node == &start_node { 0.0 } else { f64::INFINITY });
+            predecessors.insert(node.clone(), None);
+        }
+
+        let mut seen = HashSet::new();
+        let mut emit = |cycle: Vec<Edge>| {
+            if cycle.is_empty() || !seen.insert(rotation_key(&cycle)) {
+                return ControlFlow::Continue(());
+            }
+            on_cycle(cycle)
+        };
+        
+        // Relax edges |V| - 1 times
+        let node_count = self.nodes.len();
+        for _ in 0..node_count - 1 {
+            let mut updated = false;
+            
+            for (node, edges) in &self.edges {
+                let node_dist = *distances.get(node).unwrap();
+                if node_dist == f64::INFINITY {
+                    continue;
+                }
+                
+                for edge in edges {
+                    let to_dist = *distances.get(&edge.to).unwrap();
+                    let new_dist = node_dist + edge.weight;
+                    
+                    if new_dist < to_dist {
+                        distances.insert(edge.to.clone(), new_dist);
+                        predecessors.insert(edge.to.clone(), Some((node.clone(), edge.clone())));
+                        updated = true;
+
+                        if let Some(cycle) = self.closed_cycle(edge, &predecessors) {
+                            if emit(cycle).is_break() {
+                                return;
+                            }
+                        }
+                    }
+                }
+            }
+            
+            if !updated {
+                break;
+            }
+        }
+        
+        // Check for negative cycles
+        for (node, edges) in &self.edges {
+            let node_dist = *distances.get(node).unwrap();
+            if node_dist == f64::INFINITY {
+                continue;
+            }
+            
+            for edge in edges {
+                let to_dist = *distances.get(&edge.to).unwrap();
+                let new_dist = node_dist + edge.weight;
+                
+                if new_dist < to_dist {
+                    // Found a negative cycle
+                    let cycle = self.extract_cycle(&edge.to, &predecessors);
+                    if emit(cycle).is_break() {
+                        return;
+                    }
+                }
+            }
+        }
+    }
+
+    /// The cycle `edge` closes in the predecessor graph, starting at its target: there is one when
+    /// the target is on the predecessor chain of its source.
+    fn closed_cycle(&self, edge: &Edge, predecessors: &HashMap<Node, Option<(Node, Edge)>>) -> Option<Vec<Edge>> {
+        let mut chain = vec![edge];
+        let mut current = &edge.from;
+        // a chain longer than the node count repeats a node, and doesn't lead to the target
+        for _ in 0..self.nodes.len() {
+            if current == &edge.to {
+                return Some(chain.into_iter().rev().cloned().collect());
+            }
+            let (pred, pred_edge) = predecessors.get(current)?.as_ref()?;
+            chain.push(pred_edge);
+            current = pred;
+        }
+        None
+    }
+    
+    /// The `k` most profitable negative cycles through `start_token`, of at most `max_cycle_len`
+    /// swaps, with their estimated log profit (minus their total weight), best first.
+    ///
+    /// SPFA finds one negative cycle at a time. Like Yen's K-shortest paths, each cycle found
+    /// spawns searches that ban one more of its edges, so the next search deviates from it; searches
+    /// run breadth-first over the ban sets and stop after `MAX_SPFA_RUNS_PER_CYCLE * k` runs.
+    pub fn top_negative_cycles(&self, start_token: &str, k: usize) -> Vec<(f64, Vec<Edge>)> {
+        let start_node = Node { token_type: start_token.to_string() };
+        if k == 0 || !self.nodes.contains(&start_node) {
+            return vec![];
+        }
+
+        let mut cycles = Vec::new();
+        let mut seen_cycles = HashSet::new();
+        let mut seen_bans = HashSet::new();
+        let mut frontier = VecDeque::from([Vec::<EdgeKey>::new()]);
+        let mut runs = 0;
+
+        while let Some(banned) = frontier.pop_front() {
+            if runs >= MAX_SPFA_RUNS_PER_CYCLE * k {
+                break;
+            }
+            runs += 1;
+
+            let Some(cycle) = self.spfa_negative_cycle(&start_node, &banned) else {
+                continue;
+            };
+            for edge in &cycle {
+                let mut deviation = banned.clone();
+                deviation.push(EdgeKey::of(edge));
+                deviation.sort();
+                if seen_bans.insert(deviation.clone()) {
+                    frontier.push_back(deviation);
+                }
+            }
+
+            // only cycles through the start token can be traded from it
+            let Some(at) = cycle.iter().position(|edge| edge.from == start_node) else {
+                continue;
+            };
+            let mut cycle = cycle;
+            cycle.rotate_left(at);
+            if cycle.len() > self.settings.config.max_cycle_len {
+                continue;
+            }
+            if seen_cycles.insert(cycle.iter().map(|edge| edge.dex.object_id()).collect::<Vec<_>>()) {
+                let weight: f64 = cycle.iter().map(|edge| edge.weight).sum();
+                cycles.push((-weight, cycle));
+            }
+        }
+
+        cycles.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+        cycles.truncate(k);
+        debug!("Found {} of the top {} cycles in {} SPFA runs", cycles.len(), k, runs);
+        cycles
+    }
+
+    /// Negative cycles through any of `start_tokens`, searched from each of them concurrently with
+    /// the configured `CycleSearch`. A cycle found from several of them is kept once, rotated to
+    /// settle in the first of them in `start_tokens` order, which is returned with it.
+    pub fn multi_start_negative_cycles(&self, start_tokens: &[String]) -> Vec<(String, Vec<Edge>)> {
+        let config = self.settings.config();
+        let found: Vec<Vec<Vec<Edge>>> = std::thread::scope(|scope| {
+            let searches: Vec<_> = start_tokens
+                .iter()
+                .map(|start_token| {
+                    scope.spawn(move || match config.cycle_search {
+                        CycleSearch::BellmanFord => self.find_arbitrage_opportunities(start_token),
+                        CycleSearch::TopK => self
+                            .top_negative_cycles(start_token, config.top_k_cycles)
+                            .into_iter()
+                            .map(|(_, cycle)| cycle)
+                            .collect(),
+                    })
+                })
+                .collect();
+            searches
+                .into_iter()
+                .map(|search| search.join().expect("cycle search panicked"))
+                .collect()
+        });
+
+        let mut seen = HashSet::new();
+        let mut cycles = Vec::new();
+        for mut cycle in found.into_iter().flatten() {
+            if cycle.len() > config.max_cycle_len || !seen.insert(rotation_key(&cycle)) {
+                continue;
+            }
+            // Bellman-Ford also reports cycles it reached that don't pass through its start
+            let Some((settles_in, at)) = start_tokens.iter().find_map(|token| {
+                let at = cycle.iter().position(|edge| edge.from.token_type == *token)?;
+                Some((token, at))
+            }) else {
+                continue;
+            };
+            cycle.rotate_left(at);
+            cycles.push((settles_in.clone(), cycle));
+        }
+
+        debug!(
+            "Found {} distinct cycles from {} start tokens",
+            cycles.len(),
+            start_tokens.len()
+        );
+        cycles
+    }
+
+    /// A negative cycle reachable from `start` without the `banned` edges, if any.
+    fn spfa_negative_cycle(&self, start: &Node, banned: &[EdgeKey]) -> Option<Vec<Edge>> {
+        let node_count = self.nodes.len();
+        let mut distances: HashMap<&Node, f64> = HashMap::from([(start, 0.0)]);
+        // edges on the current shortest path to a node; a path of |V| edges repeats a node
+        let mut hops: HashMap<&Node, usize> = HashMap::from([(start, 0)]);
+        let mut predecessors: HashMap<&Node, &Edge> = HashMap::new();
+        let mut queue = VecDeque::from([start]);
+        let mut queued = HashSet::from([start]);
+
+        while let Some(node) = queue.pop_front() {
+            queued.remove(node);
+            let (node_dist, node_hops) = (distances[node], hops[node]);
+
+            for edge in self.edges.get(node).into_iter().flatten() {
+                if banned.contains(&EdgeKey::of(edge)) {
+                    continue;
+                }
+                let new_dist = node_dist + edge.weight;
+                if new_dist >= distances.get(&edge.to).copied().unwrap_or(f64::INFINITY) {
+                    continue;
+                }
+
+                distances.insert(&edge.to, new_dist);
+                predecessors.insert(&edge.to, edge);
+                hops.insert(&edge.to, node_hops + 1);
+                if node_hops + 1 >= node_count {
+                    match Self::cycle_through(&edge.to, &predecessors) {
+                        Some(cycle) => return Some(cycle),
+                        // the path has been cut short since, and keeps no cycle to propagate
+                        None => continue,
+                    }
+                }
+                if queued.insert(&edge.to) {
+                    queue.push_back(&edge.to);
+                }
+            }
+        }
+
+        None
+    }
+
+    /// The cycle on the predecessor chain of `node`, None if the chain ends without repeating.
+    fn cycle_through(node: &Node, predecessors: &HashMap<&Node, &Edge>) -> Option<Vec<Edge>> {
+        // the first node seen twice walking back is on the cycle
+        let mut visited = HashSet::new();
+        let mut on_cycle = node;
+        while visited.insert(on_cycle) {
+            on_cycle = &predecessors.get(on_cycle)?.from;
+        }
+
+        let mut cycle = Vec::new();
+        let mut current = on_cycle;
+        loop {
+            let edge = predecessors[current];
+            cycle.push(edge.clone());
+            current = &edge.from;
+            if current == on_cycle {
+                break;
+            }
+        }
+        cycle.reverse();
+        Some(cycle)
+    }
+
+    /// Extract a cycle from the predecessor map
+    fn extract_cycle(&self, node: &Node, predecessors: &HashMap<Node, Option<(Node, Edge)>>) -> Vec<Edge> {
+        let mut cycle = Vec::new();
+        let mut visited = HashSet::new();
+        let mut current = node.clone();
+        
+        while !visited.contains(&current) {
+            visited.insert(current.clone());
+            
+            if let Some((pred, edge)) = predecessors.get(&current).unwrap() {
+                cycle.push(edge.clone());
+                current = pred.clone();
+            } else {
+                break;
+            }
+        }
+        
+        // Check if we have a valid cycle
+        if cycle.len() > 1 && cycle.first().unwrap().from.token_type == cycle.last().unwrap().to.token_type {
+            cycle.reverse();
+            return cycle;
+        }
+        
+        Vec::new()
+    }
+    
+    /// Convert a cycle of edges to a Path
+    pub fn cycle_to_path(&self, cycle: &[Edge]) -> Path {
+        let dexes = cycle.iter().map(|edge| edge.dex.clone()).collect();
+        Path::new(dexes)
+    }
+}
+
+// SPFA runs per requested cycle before the top-k search gives up on finding more
+const MAX_SPFA_RUNS_PER_CYCLE: usize = 8;
+
+/// An edge by its pool and direction.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct EdgeKey {
+    pool_id: ObjectID,
+    from: String,
+}
+
+impl EdgeKey {
+    fn of(edge: &Edge) -> Self {
+        Self {
+            pool_id: edge.dex.object_id(),
+            from: edge.from.token_type.clone(),
+        }
+    }
+}
+
+/// The edges of `cycle` from its smallest one on, the same for every rotation of the cycle.
+fn rotation_key(cycle: &[Edge]) -> Vec<EdgeKey> {
+    let keys: Vec<EdgeKey> = cycle.iter().map(EdgeKey::of).collect();
+    let start = (0..keys.len()).min_by_key(|&idx| &keys[idx]).unwrap_or_default();
+    keys[start..].iter().chain(&keys[..start]).cloned().collect()
+}
+
+/// A cycle found by a multi-start search, with the token it starts and ends in.
+#[derive(Debug, Clone)]
+pub struct SettledPath {
+    pub settles_in: String,
+    pub path: Path,
+}
+
+const GRAPH_REBUILD_INTERVAL: Duration = Duration::from_secs(600);
+
+/// A path finder that uses the Bellman-Ford algorithm to find arbitrage opportunities
+pub struct BellmanFordPathFinder {
+    dex_searcher: Arc<dyn DexSearcher>,
+    settings: GraphSettings,
+    weights: Arc<dyn WeightProvider>,
+    // built on first use, then kept up to date by re-quoting dirty pools only; searches run on a
+    // snapshot, a refresh while one runs copies the graph
+    graph: tokio::sync::Mutex<Option<Arc<ArbitrageGraph>>>,
+    metrics: RecomputeMetrics,
+}
+
+impl BellmanFordPathFinder {
+    /// Create a new Bellman-Ford path finder, pricing constant-product pools only
+    pub fn new(dex_searcher: Arc<dyn DexSearcher>, settings: GraphSettings) -> Self {
+        Self {
+            dex_searcher,
+            settings,
+            weights: Arc::new(ReserveWeights),
+            graph: tokio::sync::Mutex::new(None),
+            metrics: RecomputeMetrics::default(),
+        }
+    }
+
+    /// Price edges with `weights` instead, e.g. `QuoteWeights` to cover every pool.
+    pub fn with_weights(mut self, weights: Arc<dyn WeightProvider>) -> Self {
+        self.weights = weights;
+        self
+    }
+
+    /// (nodes, edges) of the graph as of the last search, zero before the first.
+    pub fn graph_size(&self) -> (usize, usize) {
+        (
+            self.metrics.graph_nodes.load(Ordering::Relaxed) as usize,
+            self.metrics.graph_edges.load(Ordering::Relaxed) as usize,
+        )
+    }
+
+    /// Find arbitrage paths starting from the given token, best first with `CycleSearch::TopK`.
+    /// `pool_id` is the pool that just changed: its edges are re-quoted before searching.
+    pub async fn find_arbitrage_paths(&self, start_token: &str, pool_id: Option<ObjectID>) -> Result<Vec<Path>> {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        self.search(start_token, pool_id, tx).await?;
+
+        let mut paths = Vec::new();
+        while let Ok(path) = rx.try_recv() {
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+
+    /// Like `find_arbitrage_paths`, but paths are sent as soon as they are found so sizing can
+    /// start on the first one. Dropping the receiver stops the search.
+    pub fn stream_arbitrage_paths(
+        self: &Arc<Self>,
+        start_token: &str,
+        pool_id: Option<ObjectID>,
+    ) -> mpsc::UnboundedReceiver<Path> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let finder = Arc::clone(self);
+        let start_token = start_token.to_string();
+
+        tokio::spawn(
+            async move {
+                if let Err(error) = finder.search(&start_token, pool_id, tx).await {
+                    debug!(?error, "arbitrage path search failed");
+                }
+            }
+            .in_current_span(),
+        );
+
+        rx
+    }
+
+    /// Find arbitrage paths from every start token (see `GraphSettings::start_tokens`) `held`
+    /// admits, each settling in the first of them it passes through. Rotations of the same cycle
+    /// are reported once. `pool_id` is re-quoted first and, if set, every path goes through it.
+    pub async fn find_multi_start_paths(
+        &self,
+        pool_id: Option<ObjectID>,
+        held: impl Fn(&str) -> bool,
+    ) -> Result<Vec<SettledPath>> {
+        let start_tokens: Vec<String> = self
+            .settings
+            .start_tokens()
+            .into_iter()
+            .filter(|token| held(token))
+            .collect();
+        if start_tokens.is_empty() {
+            return Ok(vec![]);
+        }
+        let graph = self.refreshed_graph(pool_id).await?;
+        let paths = tokio::task::spawn_blocking(move || {
+            graph
+                .multi_start_negative_cycles(&start_tokens)
+                .into_iter()
+                .map(|(settles_in, cycle)| SettledPath {
+                    settles_in,
+                    path: graph.cycle_to_path(&cycle),
+                })
+                .filter(|settled| pool_id.is_none() || settled.path.contains_pool(pool_id))
+                .collect()
+        })
+        .await?;
+
+        Ok(paths)
+    }
+
+    /// A snapshot of the graph, rebuilt when due, with `pool_id` and every pool written since
+    /// re-quoted.
+    async fn refreshed_graph(&self, pool_id: Option<ObjectID>) -> Result<Arc<ArbitrageGraph>> {
+        let mut guard = self.graph.lock().await;
+        // rebuild from scratch now and then to pick up new pools and tokens
+        if guard.as_ref().map_or(true, |graph| graph.built_at.elapsed() > GRAPH_REBUILD_INTERVAL) {
+            *guard = Some(Arc::new(
+                ArbitrageGraph::new(self.dex_searcher.clone(), self.weights.as_ref(), self.settings.clone()).await?,
+            ));
+        }
+        let snapshot = guard.as_mut().expect("graph built above");
+        let graph = Arc::make_mut(snapshot);
+
+        graph.sync_mutations();
+        if let Some(pool_id) = pool_id {
+            graph.mark_dirty(pool_id);
+        }
+        let recomputed = graph
+            .recompute_dirty(self.dex_searcher.as_ref(), self.weights.as_ref())
+            .await;
+        self.metrics.record(recomputed);
+        self.metrics.record_size(graph.node_count(), graph.edge_count());
+
+        Ok(Arc::clone(snapshot))
+    }
+
+    /// Send the paths found from `start_token` to `paths` until it is closed, searching a snapshot
+    /// of the graph off the async runtime. Returns how many were sent.
+    async fn search(
+        &self,
+        start_token: &str,
+        pool_id: Option<ObjectID>,
+        paths: mpsc::UnboundedSender<Path>,
+    ) -> Result<usize> {
+        let graph = self.refreshed_graph(pool_id).await?;
+        let start_token = start_token.to_string();
+
+        let sent = tokio::task::spawn_blocking(move || {
+            let mut sent = 0;
+            let config = graph.settings.config();
+            let mut on_cycle = |cycle: Vec<Edge>| {
+                if cycle.len() > config.max_cycle_len {
+                    return ControlFlow::Continue(());
+                }
+                let path = graph.cycle_to_path(&cycle);
+
+                // Filter by pool_id if specified
+                match pool_id {
+                    Some(pool_id) if !path.contains_pool(Some(pool_id)) => ControlFlow::Continue(()),
+                    _ => match paths.send(path) {
+                        Ok(()) => {
+                            sent += 1;
+                            ControlFlow::Continue(())
+                        }
+                        Err(_) => ControlFlow::Break(()),
+                    },
+                }
+            };
+
+            match config.cycle_search {
+                CycleSearch::BellmanFord => graph.for_each_negative_cycle(&start_token, on_cycle),
+                CycleSearch::TopK => {
+                    for (_, cycle) in graph.top_negative_cycles(&start_token, config.top_k_cycles) {
+                        if on_cycle(cycle).is_break() {
+                            break;
+                        }
+                    }
+                }
+            }
+            sent
+        })
+        .await?;
+
+        Ok(sent)
+    }
+}
+
+const METRICS_WINDOW: Duration = Duration::from_secs(10);
+
+/// Edge recompute volume, logged as a per-second rate every `METRICS_WINDOW`, and the size of
+/// the graph last searched.
+struct RecomputeMetrics {
+    edges: AtomicU64,
+    window_start: Mutex<Instant>,
+    graph_nodes: AtomicU64,
+    graph_edges: AtomicU64,
+}
+
+impl Default for RecomputeMetrics {
+    fn default() -> Self {
+        Self {
+            edges: AtomicU64::new(0),
+            window_start: Mutex::new(Instant::now()),
+            graph_nodes: AtomicU64::new(0),
+            graph_edges: AtomicU64::new(0),
+        }
+    }
+}
+
+impl RecomputeMetrics {
+    fn record(&self, recomputed: usize) {
+        self.edges.fetch_add(recomputed as u64, Ordering::Relaxed);
+
+        let mut window_start = self.window_start.lock().unwrap();
+        let elapsed = window_start.elapsed();
+        if elapsed >= METRICS_WINDOW {
+            let edges = self.edges.swap(0, Ordering::Relaxed);
+            info!(
+                target: "graph_metrics",
+                recomputed_edges_per_sec = edges as f64 / elapsed.as_secs_f64(),
+                "edge weight recompute volume"
+            );
+            *window_start = Instant::now();
+        }
+    }
+
+    fn record_size(&self, nodes: usize, edges: usize) {
+        self.graph_nodes.store(nodes as u64, Ordering::Relaxed);
+        self.graph_edges.store(edges as u64, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::any::Any;
+
+    use dex_indexer::types::Protocol;
+    use eyre::bail;
+    use sui_types::transaction::{Argument, TransactionData};
+
+    use super::*;
+    use crate::defi::TradeCtx;
+
+    #[derive(Clone)]
+    struct TestPool {
+        id: ObjectID,
+        coin_in: &'static str,
+        coin_out: &'static str,
+    }
+
+    #[async_trait::async_trait]
+    impl Dex for TestPool {
+        async fn extend_trade_tx(
+            &self,
+            _ctx: &mut TradeCtx,
+            _sender: SuiAddress,
+            _coin_in: Argument,
+            _amount_in: Option<u64>,
+        ) -> Result<Argument> {
+            bail!("test pool")
+        }
+
+        fn coin_in_type(&self) -> String {
+            self.coin_in.to_string()
+        }
+
+        fn coin_out_type(&self) -> String {
+            self.coin_out.to_string()
+        }
+
+        fn protocol(&self) -> Protocol {
+            Protocol::Cetus
+        }
+
+        fn liquidity(&self) -> u128 {
+            0
+        }
+
+        fn object_id(&self) -> ObjectID {
+            self.id
+        }
+
+        fn flip(&mut self) {
+            std::mem::swap(&mut self.coin_in, &mut self.coin_out);
+        }
+
+        fn is_a2b(&self) -> bool {
+            true
+        }
+
+        async fn swap_tx(
+            &self,
+            _sender: SuiAddress,
+            _recipient: SuiAddress,
+            _amount_in: u64,
+        ) -> Result<TransactionData> {
+            bail!("test pool")
+        }
+    }
+
+    /// A graph of one pool per `(coin_in, coin_out, rate)`, pool ids numbered from 1.
+    fn test_graph(pools: &[(&'static str, &'static str, f64)]) -> ArbitrageGraph {
+        let mut graph = ArbitrageGraph {
+            nodes: HashSet::new(),
+            edges: HashMap::new(),
+            pool_edges: HashMap::new(),
+            dirty_pools: HashSet::new(),
+            synced_mutations: mutation_log().lock().unwrap().next,
+            built_at: Instant::now(),
+            settings: GraphSettings::default(),
+        };
+        for (idx, &(coin_in, coin_out, rate)) in pools.iter().enumerate() {
+            let from = Node {
+                token_type: coin_in.to_string(),
+            };
+            let to = Node {
+                token_type: coin_out.to_string(),
+            };
+            graph.nodes.extend([from.clone(), to.clone()]);
+            let pool = TestPool {
+                id: ObjectID::from_single_byte(idx as u8 + 1),
+                coin_in,
+                coin_out,
+            };
+            graph.add_edge(from, to, DexEnum::Other(Box::new(pool)), -f64::ln(rate));
+        }
+        graph
+    }
+
+    #[test]
+    fn test_top_cycles_are_ranked_by_log_profit() {
+        // A -> B -> A returns 1.2, A -> B -> C -> A 1.1, A -> C -> B -> A 0.6
+        let graph = test_graph(&[
+            ("A", "B", 2.0),
+            ("B", "A", 0.6),
+            ("B", "C", 1.0),
+            ("C", "A", 0.55),
+            ("A", "C", 1.0),
+            ("C", "B", 1.0),
+        ]);
+
+        let cycles = graph.top_negative_cycles("A", 3);
+        let pool_ids: Vec<Vec<ObjectID>> = cycles
+            .iter()
+            .map(|(_, cycle)| cycle.iter().map(|edge| edge.dex.object_id()).collect())
+            .collect();
+        let pool = ObjectID::from_single_byte;
+        assert_eq!(pool_ids, vec![vec![pool(1), pool(2)], vec![pool(1), pool(3), pool(4)]]);
+        assert!((cycles[0].0 - f64::ln(1.2)).abs() < 1e-9);
+        assert!((cycles[1].0 - f64::ln(1.1)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_multi_start_cycles_are_reported_once() {
+        // A -> B -> A returns 1.2 whichever end it starts from
+        let graph = test_graph(&[("A", "B", 2.0), ("B", "A", 0.6)]);
+
+        let cycles = graph.multi_start_negative_cycles(&["B".to_string(), "A".to_string()]);
+        assert_eq!(cycles.len(), 1);
+        let (settles_in, cycle) = &cycles[0];
+        assert_eq!(settles_in, "B");
+        assert_eq!(cycle[0].from.token_type, "B");
+        assert_eq!(cycle[1].to.token_type, "B");
+
+        assert!(graph.multi_start_negative_cycles(&["C".to_string()]).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_multi_start_paths_settle_in_a_held_token() {
+        // A -> B -> A returns 1.2 whichever end it starts from
+        let settings = GraphSettings::new(GraphConfig {
+            start_tokens: vec!["B".to_string(), "A".to_string()],
+            ..Default::default()
+        })
+        .unwrap();
+        // quoted as built, whatever other tests mark dirty
+        let pool = ObjectID::from_single_byte;
+        let weights = TestWeights {
+            rates: Mutex::new(HashMap::from([(pool(1), 2.0), (pool(2), 0.6)])),
+            ..Default::default()
+        };
+        let finder = BellmanFordPathFinder::new(Arc::new(NoPools), settings).with_weights(Arc::new(weights));
+        *finder.graph.lock().await = Some(Arc::new(test_graph(&[("A", "B", 2.0), ("B", "A", 0.6)])));
+
+        let settles_in = |held: &'static [&'static str]| {
+            let finder = &finder;
+            async move {
+                let paths = finder
+                    .find_multi_start_paths(None, |token| held.contains(&token))
+                    .await
+                    .unwrap();
+                paths.into_iter().map(|settled| settled.settles_in).collect::<Vec<_>>()
+            }
+        };
+        assert_eq!(settles_in(&["A", "B"]).await, ["B"]);
+        // the preferred token isn't held, the cycle settles in the next one it passes through
+        assert_eq!(settles_in(&["A"]).await, ["A"]);
+        assert!(settles_in(&["C"]).await.is_empty());
+    }
+
+    /// Knows no pools, so dirty edges are re-quoted as stored.
+    struct NoPools;
+
+    #[async_trait::async_trait]
+    impl DexSearcher for NoPools {
+        async fn find_dexes(&self, _coin_in_type: &str, _coin_out_type: Option<String>) -> Result<Vec<DexEnum>> {
+            bail!("find_dexes called")
+        }
+
+        async fn find_test_path(&self, _path: &[ObjectID]) -> Result<Path> {
+            bail!("no pools")
+        }
+
+        async fn pool_dexes(&self, _pool_id: &ObjectID, _coin_in_type: &str) -> Result<Vec<DexEnum>> {
+            bail!("no pools")
+        }
+
+        async fn coin_decimals(&self, _coin_type: &str) -> Option<u8> {
+            None
+        }
+
+        async fn is_blocked_coin(&self, _coin_type: &str) -> bool {
+            false
+        }
+
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+    }
+
+    /// Prices each pool at its current rate, recording which pools it priced.
+    #[derive(Default)]
+    struct TestWeights {
+        rates: Mutex<HashMap<ObjectID, f64>>,
+        quoted: Mutex<Vec<ObjectID>>,
+    }
+
+    #[async_trait::async_trait]
+    impl WeightProvider for TestWeights {
+        async fn weights(&self, dexes: &[DexEnum]) -> Vec<Option<f64>> {
+            let rates = self.rates.lock().unwrap();
+            dexes
+                .iter()
+                .map(|dex| {
+                    self.quoted.lock().unwrap().push(dex.object_id());
+                    rates.get(&dex.object_id()).map(|rate| -rate.ln())
+                })
+                .collect()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pool_update_requotes_its_own_edges() {
+        let mut graph = test_graph(&[("A", "B", 2.0), ("B", "A", 0.6), ("B", "C", 1.0)]);
+        let pool = ObjectID::from_single_byte;
+        // every pool moved, but only pool 2 and an object outside the graph were seen written
+        let weights = TestWeights {
+            rates: Mutex::new(HashMap::from([(pool(1), 1.0), (pool(2), 0.4), (pool(3), 3.0)])),
+            ..Default::default()
+        };
+        observe_mutated([pool(2), ObjectID::random()]);
+
+        graph.sync_mutations();
+        assert_eq!(graph.recompute_dirty(&NoPools, &weights).await, 1);
+        assert_eq!(*weights.quoted.lock().unwrap(), [pool(2)]);
+        let weight = |from: &str, idx: usize| {
+            graph.edges[&Node {
+                token_type: from.to_string(),
+            }][idx]
+                .weight
+        };
+        assert!((weight("A", 0) + f64::ln(2.0)).abs() < 1e-9);
+        assert!((weight("B", 0) + f64::ln(0.4)).abs() < 1e-9);
+        assert!((weight("B", 1) + f64::ln(1.0)).abs() < 1e-9);
+
+        // nothing written since
+        graph.sync_mutations();
+        assert_eq!(graph.recompute_dirty(&NoPools, &weights).await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_search_stops_at_the_first_cycle_nobody_takes() {
+        // A -> B -> A returns 1.2, A -> C -> A 1.4
+        let pools = [("A", "B", 2.0), ("B", "A", 0.6), ("A", "C", 2.0), ("C", "A", 0.7)];
+        let graph = test_graph(&pools);
+
+        let mut found = 0;
+        graph.for_each_negative_cycle("A", |cycle| {
+            assert!(cycle.iter().map(|edge| edge.weight).sum::<f64>() < 0.0);
+            found += 1;
+            ControlFlow::Break(())
+        });
+        assert_eq!(found, 1);
+
+        // quoted as built, whatever other tests mark dirty
+        let rates = pools
+            .iter()
+            .enumerate()
+            .map(|(idx, &(_, _, rate))| (ObjectID::from_single_byte(idx as u8 + 1), rate));
+        let weights = TestWeights {
+            rates: Mutex::new(rates.collect()),
+            ..Default::default()
+        };
+        let finder =
+            BellmanFordPathFinder::new(Arc::new(NoPools), GraphSettings::default()).with_weights(Arc::new(weights));
+        *finder.graph.lock().await = Some(Arc::new(graph));
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let sent = finder.search("A", None, tx).await.unwrap();
+        assert!(sent >= 1);
+        let mut received = 0;
+        while rx.try_recv().is_ok() {
+            received += 1;
+        }
+        assert_eq!(received, sent);
+
+        // the consumer is gone, the first cycle found ends the search
+        let (tx, rx) = mpsc::unbounded_channel();
+        drop(rx);
+        assert_eq!(finder.search("A", None, tx).await.unwrap(), 0);
+    }
+
+    #[test]
+    fn test_profitable_cycle_weighs_below_zero() {
+        // 1 A -> 2 B -> 1.2 A
+        let cycle = quote_weight(1_000, 2_000).unwrap() + quote_weight(2_000, 1_200).unwrap();
+        assert!(cycle < 0.0);
+        // 1 A -> 2 B -> 0.9 A
+        let cycle = quote_weight(1_000, 2_000).unwrap() + quote_weight(2_000, 900).unwrap();
+        assert!(cycle > 0.0);
+        assert_eq!(quote_weight(1_000, 0), None);
+    }
+
+    #[test]
+    fn test_token_lists() {
+        let deny = parse_token_list("# spam\n0xdead::coin::COIN  # honeypot\n\n 0xbeef::coin::COIN\n");
+        assert_eq!(deny.len(), 2);
+        let tokens = TokenLists { allow: None, deny };
+        assert!(!tokens.admits("0xbeef::coin::COIN"));
+        assert!(tokens.admits("0xcafe::coin::COIN"));
+
+        let tokens = TokenLists {
+            allow: Some(parse_token_list("0xcafe::coin::COIN")),
+            deny: HashSet::new(),
+        };
+        assert!(tokens.admits("0xcafe::coin::COIN"));
+        assert!(!tokens.admits("0xbeef::coin::COIN"));
+        // the base coin can't be listed out
+        assert!(tokens.admits(base::coin_type()));
+    }
+}
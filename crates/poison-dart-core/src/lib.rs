@@ -0,0 +1,36 @@
+//! Route discovery, quoting and venue integrations for Sui arbitrage, usable without the `arb`
+//! binary.
+//!
+//! * [`defi::Defi`] finds buy/sell paths for a coin through the pools known to the dex indexer and
+//!   quotes them against a simulator (`find_best_path_exact_in`).
+//! * [`graph_path_finder::BellmanFordPathFinder`] searches the pool graph for negative cycles,
+//!   i.e. closed loops that return more than they take in. Edges are priced by a
+//!   `WeightProvider`: `ReserveWeights` for constant-product pools only, `QuoteWeights` to also
+//!   price every other pool, in process where its curve has local math and against a simulator
+//!   otherwise.
+//! * [`strategy::sizing::AmountOptimizer`] sizes a path by grid then golden section search
+//!   ([`search`]) and reports each hop's slippage; [`strategy::routes`] picks pool-disjoint cycles
+//!   to trade together.
+//! * [`types`] holds the engine's `Event`s and `Action`s and re-exports the `burberry` collector,
+//!   strategy and executor traits they flow through.
+//! * [`defi::Dex`] is the venue abstraction; built-in venues are behind Cargo features of the same
+//!   name (`cetus`, `turbos`, ...) and others can be added with `defi::registry`.
+//!
+//! ```ignore
+//! let defi = Defi::new(rpc_url, simulator_pool, &FlashLoanConfig::default()).await?;
+//! let paths = defi.find_sell_paths(coin_type).await?;
+//! let best = defi
+//!     .find_best_path_exact_in(&paths, sender, amount_in, TradeType::Swap, &gas_coins, &sim_ctx)
+//!     .await?;
+//! ```
+
+pub mod config;
+pub mod defi;
+pub mod graph_path_finder;
+pub mod search;
+pub mod strategy;
+pub mod types;
+
+#[cfg(test)]
+#[global_allocator]
+static GLOBAL: utils::alloc_counter::CountingAllocator = utils::alloc_counter::CountingAllocator;
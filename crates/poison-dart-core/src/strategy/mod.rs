@@ -0,0 +1,4 @@
+//! Trade sizing and split-route selection, independent of the bot's event loop.
+
+pub mod routes;
+pub mod sizing;
@@ -0,0 +1,155 @@
+//! Split-route execution.
+//!
+//! A search trades the best cycle through the pool that moved. Other cycles of the same coin that
+//! share no pool with it, nor with each other, trade on state it leaves alone, so they can be
+//! sized on their own and executed next to it. `select` picks them greedily, most profitable
+//! first, up to `SUI_MAX_ROUTES` cycles in all and `SUI_ROUTE_CAPITAL_BUDGET` of flashloaned input.
+//!
+//! The routes go out in one PTB that lands or reverts as a whole, or, when no object a route writes
+//! is used by another and the accounts can trade in parallel, as concurrent transactions each
+//! sized and dry run on its own. Shio bids stay single-route. Off unless `SUI_MAX_ROUTES` > 1.
+
+use std::{collections::HashSet, sync::OnceLock};
+
+use clap::Parser;
+use eyre::Result;
+use sui_types::{
+    base_types::ObjectID,
+    transaction::{TransactionData, TransactionDataAPI},
+};
+
+// candidate cycles sized per route wanted, the most promising by quote
+pub const CANDIDATES_PER_ROUTE: usize = 4;
+
+static CONFIG: OnceLock<RouteConfig> = OnceLock::new();
+
+#[derive(Clone, Debug, PartialEq, Parser)]
+pub struct RouteConfig {
+    /// Most pool-disjoint cycles traded together from one opportunity, 1 trades the best only
+    #[arg(long, env = "SUI_MAX_ROUTES", default_value_t = 1)]
+    pub max_routes: usize,
+
+    /// Most input, in base coin units, flashloaned across the routes of one opportunity; no cap
+    /// when unset
+    #[arg(long, env = "SUI_ROUTE_CAPITAL_BUDGET")]
+    pub route_capital_budget: Option<u64>,
+}
+
+impl Default for RouteConfig {
+    fn default() -> Self {
+        Self {
+            max_routes: 1,
+            route_capital_budget: None,
+        }
+    }
+}
+
+/// Install the split-route configuration, before the first search reads it.
+pub fn init(config: RouteConfig) -> Result<()> {
+    utils::set_once(&CONFIG, config, "route config")
+}
+
+pub fn config() -> &'static RouteConfig {
+    CONFIG.get_or_init(RouteConfig::default)
+}
+
+/// A cycle sized on its own.
+#[derive(Debug, Clone)]
+pub struct SizedRoute {
+    pub pools: Vec<ObjectID>,
+    pub amount_in: u64,
+    pub profit: u64,
+}
+
+/// Indices of the `candidates` to trade next to `best`: profitable, pool-disjoint from it and
+/// from each other, most profitable first, while the routes fit `max_routes` and together borrow
+/// no more than `budget`.
+pub fn select(best: &SizedRoute, candidates: &[SizedRoute], max_routes: usize, budget: Option<u64>) -> Vec<usize> {
+    let mut used: HashSet<ObjectID> = best.pools.iter().copied().collect();
+    let mut capital = best.amount_in;
+    let mut order: Vec<usize> = (0..candidates.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(candidates[i].profit));
+
+    let mut selected = vec![];
+    for i in order {
+        if selected.len() + 1 >= max_routes {
+            break;
+        }
+        let route = &candidates[i];
+        if route.profit == 0 || route.pools.iter().any(|pool| used.contains(pool)) {
+            continue;
+        }
+        let Some(total) = capital.checked_add(route.amount_in) else {
+            continue;
+        };
+        if budget.is_some_and(|budget| total > budget) {
+            continue;
+        }
+        capital = total;
+        used.extend(route.pools.iter().copied());
+        selected.push(i);
+    }
+    selected
+}
+
+/// Whether the transactions can run side by side: none writes a shared object another one uses.
+pub fn independent(txs: &[TransactionData]) -> bool {
+    let (mut used, mut written) = (HashSet::new(), HashSet::new());
+    for tx in txs {
+        let shared = tx.shared_input_objects();
+        if shared
+            .iter()
+            .any(|obj| written.contains(&obj.id) || (obj.mutable && used.contains(&obj.id)))
+        {
+            return false;
+        }
+        for obj in shared {
+            if obj.mutable {
+                written.insert(obj.id);
+            }
+            used.insert(obj.id);
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(pools: &[ObjectID], amount_in: u64, profit: u64) -> SizedRoute {
+        SizedRoute {
+            pools: pools.to_vec(),
+            amount_in,
+            profit,
+        }
+    }
+
+    #[test]
+    fn test_select() {
+        let (a, b, c, d, e) = (
+            ObjectID::random(),
+            ObjectID::random(),
+            ObjectID::random(),
+            ObjectID::random(),
+            ObjectID::random(),
+        );
+        let best = route(&[a, b], 100, 50);
+        let candidates = vec![
+            // shares `b` with the best route
+            route(&[b, c], 10, 40),
+            route(&[c, d], 10, 30),
+            // shares `d` with the route above, which profits more
+            route(&[d, e], 10, 20),
+            route(&[e], 10, 10),
+            route(&[ObjectID::random()], 10, 0),
+        ];
+
+        assert_eq!(select(&best, &candidates, 1, None), Vec::<usize>::new());
+        assert_eq!(select(&best, &candidates, 5, None), vec![1, 3]);
+        assert_eq!(select(&best, &candidates, 2, None), vec![1]);
+        // the best route alone spends 100 of the budget
+        assert_eq!(select(&best, &candidates, 5, Some(115)), vec![1]);
+        assert_eq!(select(&best, &candidates, 5, Some(100)), Vec::<usize>::new());
+    }
+}
@@ -0,0 +1,166 @@
+//! Trade sizing for a single path.
+//!
+//! `AmountOptimizer` simulates a path at a coarse grid of inputs, a power of ten apart, then
+//! refines around the best one with golden section search. Along with the most profitable input it
+//! reports the slippage each hop takes at that size: how far the rate the hop realizes falls short
+//! of its rate for a small probe trade.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use eyre::{ensure, Result};
+use futures::future::join_all;
+use simulator::SimulateCtx;
+use sui_types::base_types::{ObjectID, ObjectRef, SuiAddress};
+
+use crate::{
+    defi::{Dex, Path, PathTradeResult, TradeType, Trader},
+    search::{golden_section_search_maximize, SearchGoal},
+};
+
+const GRID_START: u64 = 1_000_000; // 0.001 SUI
+const GRID_POINTS: u32 = 10;
+// a hop's undisturbed rate is quoted with this fraction of the amount entering it
+const PROBE_DIVISOR: u64 = 1_000;
+
+#[derive(Debug, Clone)]
+pub struct HopSlippage {
+    pub pool_id: ObjectID,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    /// Shortfall of the realized rate from the probe rate, 0.01 is 1%
+    pub slippage: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct SizedPath {
+    pub amount_in: u64,
+    /// Net of gas, in base coin units
+    pub profit: i128,
+    pub hops: Vec<HopSlippage>,
+}
+
+pub struct AmountOptimizer {
+    trader: Arc<Trader>,
+    path: Path,
+    sender: SuiAddress,
+    gas_coins: Vec<ObjectRef>,
+    sim_ctx: SimulateCtx,
+}
+
+impl AmountOptimizer {
+    pub fn new(
+        trader: Arc<Trader>,
+        path: Path,
+        sender: SuiAddress,
+        gas_coins: Vec<ObjectRef>,
+        sim_ctx: SimulateCtx,
+    ) -> Self {
+        Self {
+            trader,
+            path,
+            sender,
+            gas_coins,
+            sim_ctx,
+        }
+    }
+
+    /// The most profitable input of the path, grid search then golden section search.
+    pub async fn optimize(&self) -> Result<SizedPath> {
+        let (amount_in, profit) = self.best_size().await?;
+        let hops = self.slippage(amount_in).await?;
+        Ok(SizedPath {
+            amount_in,
+            profit,
+            hops,
+        })
+    }
+
+    /// The most profitable input of the path and its net profit, without the slippage report.
+    pub async fn best_size(&self) -> Result<(u64, i128)> {
+        let grid: Vec<u64> = (1..=GRID_POINTS)
+            .filter_map(|exp| GRID_START.checked_mul(10u64.pow(exp)))
+            .collect();
+        let profits = join_all(grid.iter().map(|&amount_in| self.profit(amount_in))).await;
+        let (mut amount_in, mut profit) = grid
+            .into_iter()
+            .zip(profits)
+            .max_by_key(|(_, profit)| *profit)
+            .unwrap_or_default();
+        ensure!(profit > 0, "no profitable size on the grid");
+
+        let (lower, upper) = (amount_in / 10, amount_in.saturating_mul(10));
+        let (gss_amount_in, _, gss_profit) = golden_section_search_maximize(lower, upper, ProfitGoal, self).await;
+        if gss_profit > profit {
+            (amount_in, profit) = (gss_amount_in, gss_profit);
+        }
+        Ok((amount_in, profit))
+    }
+
+    /// Net profit of a flashloaned trade of `amount_in`, `i128::MIN` if it fails.
+    async fn profit(&self, amount_in: u64) -> i128 {
+        let trade_res = self
+            .trader
+            .get_trade_result(
+                &self.path,
+                self.sender,
+                amount_in,
+                TradeType::Flashloan,
+                self.gas_coins.clone(),
+                self.sim_ctx.clone(),
+            )
+            .await;
+        match trade_res {
+            Ok(trade_res) => PathTradeResult::new(self.path.clone(), amount_in, trade_res).profit(),
+            Err(_) => i128::MIN,
+        }
+    }
+
+    /// Each hop swapped on its own with the amount the previous hop put out.
+    async fn slippage(&self, amount_in: u64) -> Result<Vec<HopSlippage>> {
+        let mut hops = Vec::with_capacity(self.path.path.len());
+        let mut amount = amount_in;
+        for dex in &self.path.path {
+            let probe = (amount / PROBE_DIVISOR).max(1);
+            let hop = Path::new(vec![dex.clone()]);
+            let (amount_out, probe_out) = tokio::try_join!(self.swap(&hop, amount), self.swap(&hop, probe))?;
+
+            let rate = amount_out as f64 / amount as f64;
+            let probe_rate = probe_out as f64 / probe as f64;
+            hops.push(HopSlippage {
+                pool_id: dex.object_id(),
+                amount_in: amount,
+                amount_out,
+                slippage: if probe_rate > 0.0 { 1.0 - rate / probe_rate } else { 0.0 },
+            });
+            amount = amount_out;
+        }
+        Ok(hops)
+    }
+
+    async fn swap(&self, hop: &Path, amount_in: u64) -> Result<u64> {
+        // the coin in is mocked, so the swap needs neither a funded sender nor gas coins
+        let trade_res = self
+            .trader
+            .get_trade_result(
+                hop,
+                SuiAddress::ZERO,
+                amount_in,
+                TradeType::Swap,
+                vec![],
+                self.sim_ctx.clone(),
+            )
+            .await?;
+        Ok(trade_res.amount_out)
+    }
+}
+
+struct ProfitGoal;
+
+#[async_trait]
+impl SearchGoal<AmountOptimizer, u64, i128> for ProfitGoal {
+    async fn evaluate(&self, amount_in: u64, optimizer: &AmountOptimizer) -> (u64, i128) {
+        let profit = optimizer.profit(amount_in).await;
+        (profit.max(0) as u64, profit)
+    }
+}
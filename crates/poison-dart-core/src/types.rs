@@ -0,0 +1,419 @@
+//! Types shared between the library and the services embedding it.
+//!
+//! The bot runs on the `burberry` engine: collectors stream `Event`s to strategies, which submit
+//! `Action`s to executors. The engine traits are re-exported here so collectors and strategies
+//! of other services plug into the same events.
+
+use std::{fmt, str::FromStr};
+
+use burberry::executor::telegram_message::Message;
+pub use burberry::{ActionSubmitter, Collector, CollectorStream, Executor, Strategy};
+use dex_indexer::types::Pool;
+use eyre::{ensure, eyre, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use shio::ShioItem;
+use sui_json_rpc_types::{SuiEvent, SuiTransactionBlockEffects};
+use sui_types::{
+    base_types::{ObjectID, SuiAddress},
+    digests::TransactionDigest,
+    transaction::TransactionData,
+    TypeTag,
+};
+
+use crate::defi::{Dex, DexEnum, Path, PathTradeResult};
+
+/// Where a trigger came from; for Shio auctions, also the bid and timing of the opportunity.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Source {
+    Public,
+    Shio {
+        opp_tx_digest: TransactionDigest,
+        bid_amount: u64,
+        start: u64,
+        arb_found: u64,
+        deadline: u64,
+    },
+    ShioDeadlineMissed {
+        start: u64,
+        arb_found: u64,
+        deadline: u64,
+    },
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Public => write!(f, "Public"),
+            Source::Shio {
+                start,
+                arb_found,
+                deadline,
+                ..
+            } => write!(
+                f,
+                "Shio(start={}, deadline={}, time_window={}ms, arb_found={}, early={}ms)",
+                *start,
+                *deadline,
+                (*deadline).saturating_sub(*start),
+                *arb_found,
+                (*deadline).saturating_sub(*arb_found)
+            ),
+            Source::ShioDeadlineMissed {
+                start,
+                arb_found,
+                deadline,
+            } => write!(
+                f,
+                "ShioDeadlineMissed(start={}, deadline={}, time_window={}ms, arb_found={}, overdue={}ms)",
+                *start,
+                *deadline,
+                (*deadline).saturating_sub(*start),
+                *arb_found,
+                (*arb_found).saturating_sub(*deadline)
+            ),
+        }
+    }
+}
+
+impl Source {
+    pub fn is_shio(&self) -> bool {
+        matches!(self, Source::Shio { .. })
+    }
+
+    pub fn opp_tx_digest(&self) -> Option<TransactionDigest> {
+        match self {
+            Source::Shio { opp_tx_digest, .. } => Some(*opp_tx_digest),
+            _ => None,
+        }
+    }
+
+    pub fn deadline(&self) -> Option<u64> {
+        match self {
+            Source::Shio { deadline, .. } => Some(*deadline),
+            _ => None,
+        }
+    }
+
+    pub fn bid_amount(&self) -> u64 {
+        match self {
+            Source::Shio { bid_amount, .. } => *bid_amount,
+            _ => 0,
+        }
+    }
+
+    pub fn with_bid_amount(self, bid_amount: u64) -> Self {
+        match self {
+            Source::Shio {
+                opp_tx_digest,
+                start,
+                deadline,
+                arb_found,
+                ..
+            } => Source::Shio {
+                opp_tx_digest,
+                bid_amount,
+                start,
+                deadline,
+                arb_found,
+            },
+            _ => self,
+        }
+    }
+
+    pub fn with_arb_found_time(self, arb_found: u64) -> Self {
+        match self {
+            Source::Shio {
+                opp_tx_digest,
+                start,
+                deadline,
+                bid_amount,
+                ..
+            } => {
+                if arb_found < deadline {
+                    Source::Shio {
+                        opp_tx_digest,
+                        bid_amount,
+                        start,
+                        arb_found,
+                        deadline,
+                    }
+                } else {
+                    Source::ShioDeadlineMissed {
+                        start,
+                        arb_found,
+                        deadline,
+                    }
+                }
+            }
+            _ => self,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Action {
+    NotifyViaTelegram(Message),
+    ExecutePublicTx(TransactionData),
+    ShioSubmitBid((TransactionData, u64, TransactionDigest)),
+}
+
+impl From<Message> for Action {
+    fn from(msg: Message) -> Self {
+        Self::NotifyViaTelegram(msg)
+    }
+}
+
+impl From<TransactionData> for Action {
+    fn from(tx_data: TransactionData) -> Self {
+        Self::ExecutePublicTx(tx_data)
+    }
+}
+
+impl From<(TransactionData, u64, TransactionDigest)> for Action {
+    fn from((tx_data, bid_amount, opp_tx_digest): (TransactionData, u64, TransactionDigest)) -> Self {
+        Self::ShioSubmitBid((tx_data, bid_amount, opp_tx_digest))
+    }
+}
+
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Debug)]
+pub enum Event {
+    PublicTx(SuiTransactionBlockEffects, Vec<SuiEvent>),
+    PrivateTx(TransactionData),
+    Shio(ShioItem),
+    /// A newly indexed pool holding a watched coin
+    NewPool(Pool),
+}
+
+impl Event {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Event::PublicTx(..) => "public_tx",
+            Event::PrivateTx(_) => "private_tx",
+            Event::Shio(_) => "shio",
+            Event::NewPool(_) => "new_pool",
+        }
+    }
+}
+
+/// A pool as seen from one direction.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoolInfo {
+    pub protocol: String,
+    pub pool_id: ObjectID,
+    pub coin_in_type: String,
+    pub coin_out_type: String,
+    /// Decimal string: u128 doesn't fit a JSON number
+    pub liquidity: String,
+}
+
+impl From<&DexEnum> for PoolInfo {
+    fn from(dex: &DexEnum) -> Self {
+        Self {
+            protocol: dex.protocol().to_string(),
+            pool_id: dex.object_id(),
+            coin_in_type: dex.coin_in_type(),
+            coin_out_type: dex.coin_out_type(),
+            liquidity: dex.liquidity().to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PathInfo {
+    pub coin_in_type: String,
+    pub coin_out_type: String,
+    pub hops: Vec<PoolInfo>,
+}
+
+impl From<&Path> for PathInfo {
+    fn from(path: &Path) -> Self {
+        Self {
+            coin_in_type: path.coin_in_type(),
+            coin_out_type: path.coin_out_type(),
+            hops: path.path.iter().map(PoolInfo::from).collect(),
+        }
+    }
+}
+
+/// A simulated trade along one path.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuoteInfo {
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub gas_cost: i64,
+    /// Decimal string of the i128 SUI profit
+    pub profit: String,
+    pub path: PathInfo,
+}
+
+impl From<&PathTradeResult> for QuoteInfo {
+    fn from(res: &PathTradeResult) -> Self {
+        Self {
+            amount_in: res.amount_in,
+            amount_out: res.amount_out,
+            gas_cost: res.gas_cost,
+            profit: res.profit().to_string(),
+            path: PathInfo::from(&res.path),
+        }
+    }
+}
+
+fn validate_coin_type(coin_type: &str) -> Result<()> {
+    TypeTag::from_str(coin_type).map_err(|e| eyre!("invalid coin type {}: {}", coin_type, e))?;
+    Ok(())
+}
+
+/// Params of the `find_dexes` JSON-RPC method.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FindDexesParams {
+    pub coin_in_type: String,
+    #[serde(default)]
+    pub coin_out_type: Option<String>,
+}
+
+impl FindDexesParams {
+    pub fn validate(&self) -> Result<()> {
+        validate_coin_type(&self.coin_in_type)?;
+        self.coin_out_type.as_deref().map(validate_coin_type).transpose()?;
+        Ok(())
+    }
+}
+
+/// Params of the `find_arbitrage_paths` JSON-RPC method.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FindArbitragePathsParams {
+    pub start_token: String,
+    /// Only cycles through this pool
+    #[serde(default)]
+    pub pool_id: Option<ObjectID>,
+}
+
+impl FindArbitragePathsParams {
+    pub fn validate(&self) -> Result<()> {
+        validate_coin_type(&self.start_token)
+    }
+}
+
+/// Params of the `simulate_path` JSON-RPC method: the pools of a path starting from SUI.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SimulatePathParams {
+    pub pool_ids: Vec<ObjectID>,
+    pub amount_in: u64,
+    pub sender: SuiAddress,
+    #[serde(default)]
+    pub flashloan: bool,
+}
+
+impl SimulatePathParams {
+    pub fn validate(&self) -> Result<()> {
+        ensure!(!self.pool_ids.is_empty(), "pool_ids is empty");
+        ensure!(self.amount_in > 0, "amount_in must be positive");
+        Ok(())
+    }
+}
+
+pub const JSONRPC_VERSION: &str = "2.0";
+
+/// JSON-RPC 2.0 request. `params` is decoded per method.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RpcRequest {
+    pub jsonrpc: String,
+    #[serde(default)]
+    pub id: Value,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    pub const PARSE_ERROR: i64 = -32700;
+    pub const INVALID_REQUEST: i64 = -32600;
+    pub const METHOD_NOT_FOUND: i64 = -32601;
+    pub const INVALID_PARAMS: i64 = -32602;
+    pub const SERVER_ERROR: i64 = -32000;
+
+    pub fn new(code: i64, message: impl fmt::Display) -> Self {
+        Self {
+            code,
+            message: message.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RpcResponse {
+    pub jsonrpc: String,
+    pub id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<RpcError>,
+}
+
+impl RpcResponse {
+    pub fn result(id: Value, result: Value) -> Self {
+        Self {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            id,
+            result: Some(result),
+            error: None,
+        }
+    }
+
+    pub fn error(id: Value, error: RpcError) -> Self {
+        Self {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            id,
+            result: None,
+            error: Some(error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    const SENDER: &str = "0x0000000000000000000000000000000000000000000000000000000000000001";
+
+    #[test]
+    fn test_rpc_params_are_validated() {
+        let params: SimulatePathParams = serde_json::from_value(json!({
+            "pool_ids": ["0x3c3dd05e348fba5d8bf6958369cc3b33c8e8be85c96e10b1ca6413ad1b2d7787"],
+            "amount_in": 1000,
+            "sender": SENDER,
+        }))
+        .unwrap();
+        assert!(params.validate().is_ok());
+
+        let unknown_field = serde_json::from_value::<SimulatePathParams>(json!({
+            "pool_ids": [], "amount_in": 1, "sender": SENDER, "slippage": 1,
+        }));
+        assert!(unknown_field.is_err());
+
+        let bad_id = serde_json::from_value::<FindArbitragePathsParams>(json!({
+            "start_token": "0x2::sui::SUI", "pool_id": "not-an-id",
+        }));
+        assert!(bad_id.is_err());
+
+        let bad_coin = FindDexesParams {
+            coin_in_type: "sui".to_string(),
+            coin_out_type: None,
+        };
+        assert!(bad_coin.validate().is_err());
+    }
+}
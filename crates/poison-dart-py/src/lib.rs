@@ -0,0 +1,333 @@
+//! Python bindings for the routing engine in `poison-dart-core`.
+//!
+//! Build with `maturin develop -m crates/poison-dart-py/Cargo.toml`, then:
+//!
+//! ```python
+//! import poison_dart
+//!
+//! engine = poison_dart.Engine("http://localhost:9000")
+//! paths = engine.find_sell_paths(coin_type)
+//! quote = engine.quote(paths[0], 1_000_000_000, sender)
+//!
+//! graph = engine.build_graph()
+//! cycles = graph.find_cycles("0x2::sui::SUI")
+//! ```
+//!
+//! Quotes are simulated against live RPC state, or against a recorded node database when the
+//! engine is given `db_path` and `config_path`. Calls release the GIL while they run.
+
+use std::{str::FromStr, sync::Arc};
+
+use object_pool::ObjectPool;
+use poison_dart_core::{
+    defi::{flash_lender::FlashLoanConfig, Defi, Dex, DexSearcher, Path, PathTradeResult, TradeType},
+    graph_path_finder::{ArbitrageGraph, GraphSettings, QuoteWeights, WeightProvider},
+};
+use pyo3::{exceptions::PyRuntimeError, prelude::*};
+use simulator::{DBSimulator, HttpSimulator, SimEpoch, SimulateCtx, Simulator};
+use sui_sdk::{SuiClient, SuiClientBuilder};
+use sui_types::base_types::{ObjectID, SuiAddress};
+use tokio::runtime::Runtime;
+use utils::coin;
+
+fn py_err(error: eyre::Report) -> PyErr {
+    PyRuntimeError::new_err(format!("{error:#}"))
+}
+
+/// One hop of a path.
+#[pyclass(get_all, frozen)]
+#[derive(Clone)]
+pub struct Hop {
+    pub protocol: String,
+    pub pool_id: String,
+    pub coin_in_type: String,
+    pub coin_out_type: String,
+    pub liquidity: u128,
+}
+
+#[pymethods]
+impl Hop {
+    fn __repr__(&self) -> String {
+        format!(
+            "Hop({}, {}, {} -> {})",
+            self.protocol, self.pool_id, self.coin_in_type, self.coin_out_type
+        )
+    }
+}
+
+/// A route through one or more pools.
+#[pyclass(name = "Path", frozen)]
+#[derive(Clone)]
+pub struct PyPath {
+    inner: Path,
+}
+
+#[pymethods]
+impl PyPath {
+    #[getter]
+    fn coin_in_type(&self) -> String {
+        self.inner.coin_in_type()
+    }
+
+    #[getter]
+    fn coin_out_type(&self) -> String {
+        self.inner.coin_out_type()
+    }
+
+    #[getter]
+    fn hops(&self) -> Vec<Hop> {
+        self.inner
+            .path
+            .iter()
+            .map(|dex| Hop {
+                protocol: dex.protocol().to_string(),
+                pool_id: dex.object_id().to_string(),
+                coin_in_type: dex.coin_in_type(),
+                coin_out_type: dex.coin_out_type(),
+                liquidity: dex.liquidity(),
+            })
+            .collect()
+    }
+
+    fn __len__(&self) -> usize {
+        self.inner.path.len()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Path({:?})", self.inner)
+    }
+}
+
+fn wrap_paths(paths: Vec<Path>) -> Vec<PyPath> {
+    paths.into_iter().map(|inner| PyPath { inner }).collect()
+}
+
+/// Result of simulating a path for one input amount.
+#[pyclass(get_all, frozen)]
+pub struct Quote {
+    pub path: PyPath,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub gas_cost: i64,
+    /// Net profit in the base coin (SUI) for base -> base paths, otherwise 0 or what was spent.
+    pub profit: i128,
+}
+
+impl From<PathTradeResult> for Quote {
+    fn from(res: PathTradeResult) -> Self {
+        Self {
+            profit: res.profit(),
+            amount_in: res.amount_in,
+            amount_out: res.amount_out,
+            gas_cost: res.gas_cost,
+            path: PyPath { inner: res.path },
+        }
+    }
+}
+
+#[pymethods]
+impl Quote {
+    fn __repr__(&self) -> String {
+        format!(
+            "Quote(amount_in={}, amount_out={}, gas_cost={}, profit={})",
+            self.amount_in, self.amount_out, self.gas_cost, self.profit
+        )
+    }
+}
+
+/// Path search and quoting against one RPC endpoint.
+#[pyclass(frozen)]
+pub struct Engine {
+    runtime: Arc<Runtime>,
+    defi: Defi,
+    sui: SuiClient,
+}
+
+#[pymethods]
+impl Engine {
+    #[new]
+    #[pyo3(signature = (rpc_url, db_path=None, config_path=None, num_simulators=1))]
+    fn new(
+        py: Python<'_>,
+        rpc_url: String,
+        db_path: Option<String>,
+        config_path: Option<String>,
+        num_simulators: usize,
+    ) -> PyResult<Self> {
+        let runtime = Arc::new(Runtime::new()?);
+
+        py.allow_threads(|| {
+            let simulator_pool = match (db_path, config_path) {
+                (Some(db_path), Some(config_path)) => ObjectPool::new(num_simulators, move || {
+                    Runtime::new().unwrap().block_on(async {
+                        Box::new(DBSimulator::new_slow(&db_path, &config_path, None, None).await) as Box<dyn Simulator>
+                    })
+                }),
+                (None, None) => {
+                    let rpc_url = rpc_url.clone();
+                    ObjectPool::new(num_simulators, move || {
+                        Runtime::new().unwrap().block_on(async {
+                            Box::new(HttpSimulator::new(&rpc_url, &None).await) as Box<dyn Simulator>
+                        })
+                    })
+                }
+                _ => return Err(PyRuntimeError::new_err("db_path and config_path go together")),
+            };
+
+            let (defi, sui) = runtime
+                .block_on(async {
+                    let defi = Defi::new(&rpc_url, Arc::new(simulator_pool), &FlashLoanConfig::default()).await?;
+                    let sui = SuiClientBuilder::default().build(&rpc_url).await?;
+                    eyre::Ok((defi, sui))
+                })
+                .map_err(py_err)?;
+
+            Ok(Self { runtime, defi, sui })
+        })
+    }
+
+    /// Paths that sell `coin_type` for SUI.
+    fn find_sell_paths(&self, py: Python<'_>, coin_type: &str) -> PyResult<Vec<PyPath>> {
+        py.allow_threads(|| self.runtime.block_on(self.defi.find_sell_paths(coin_type)))
+            .map(wrap_paths)
+            .map_err(py_err)
+    }
+
+    /// Paths that buy `coin_type` with SUI.
+    fn find_buy_paths(&self, py: Python<'_>, coin_type: &str) -> PyResult<Vec<PyPath>> {
+        py.allow_threads(|| self.runtime.block_on(self.defi.find_buy_paths(coin_type)))
+            .map(wrap_paths)
+            .map_err(py_err)
+    }
+
+    /// Simulate `path` for `amount_in`, paid from `sender`'s gas coins at the current epoch.
+    #[pyo3(signature = (path, amount_in, sender, flashloan=false))]
+    fn quote(&self, py: Python<'_>, path: PyPath, amount_in: u64, sender: &str, flashloan: bool) -> PyResult<Quote> {
+        self.best_quote(py, vec![path], amount_in, sender, flashloan)
+    }
+
+    /// The best of `paths` for `amount_in`.
+    #[pyo3(signature = (paths, amount_in, sender, flashloan=false))]
+    fn best_quote(
+        &self,
+        py: Python<'_>,
+        paths: Vec<PyPath>,
+        amount_in: u64,
+        sender: &str,
+        flashloan: bool,
+    ) -> PyResult<Quote> {
+        let sender = SuiAddress::from_str(sender).map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+        let paths: Vec<Path> = paths.into_iter().map(|path| path.inner).collect();
+        let trade_type = if flashloan {
+            TradeType::Flashloan
+        } else {
+            TradeType::Swap
+        };
+
+        py.allow_threads(|| {
+            self.runtime.block_on(async {
+                let gas_coins = coin::get_gas_coin_refs(&self.sui, sender, None).await?;
+                let sys_state = self.sui.governance_api().get_latest_sui_system_state().await?;
+                let sim_ctx = SimulateCtx::new(SimEpoch::from(sys_state), vec![]);
+
+                self.defi
+                    .find_best_path_exact_in(&paths, sender, amount_in, trade_type, &gas_coins, &sim_ctx)
+                    .await
+            })
+        })
+        .map(Quote::from)
+        .map_err(py_err)
+    }
+
+    /// Build the token graph over every tradeable pool reachable from SUI.
+    fn build_graph(&self, py: Python<'_>) -> PyResult<Graph> {
+        let dex_searcher = self.defi.get_dex_searcher();
+        let weights: Arc<dyn WeightProvider> = Arc::new(QuoteWeights::new(
+            self.defi.get_trader(),
+            Arc::clone(&dex_searcher),
+            self.sui.clone(),
+        ));
+        let graph = py
+            .allow_threads(|| {
+                self.runtime.block_on(ArbitrageGraph::new(
+                    Arc::clone(&dex_searcher),
+                    weights.as_ref(),
+                    GraphSettings::default(),
+                ))
+            })
+            .map_err(py_err)?;
+
+        Ok(Graph {
+            runtime: Arc::clone(&self.runtime),
+            dex_searcher,
+            weights,
+            graph,
+        })
+    }
+}
+
+/// Token graph for Bellman-Ford cycle search.
+#[pyclass]
+pub struct Graph {
+    runtime: Arc<Runtime>,
+    dex_searcher: Arc<dyn DexSearcher>,
+    weights: Arc<dyn WeightProvider>,
+    graph: ArbitrageGraph,
+}
+
+#[pymethods]
+impl Graph {
+    #[getter]
+    fn node_count(&self) -> usize {
+        self.graph.node_count()
+    }
+
+    #[getter]
+    fn edge_count(&self) -> usize {
+        self.graph.edge_count()
+    }
+
+    /// Negative cycles through `start_token`, as paths.
+    fn find_cycles(&self, py: Python<'_>, start_token: &str) -> Vec<PyPath> {
+        let graph = &self.graph;
+        py.allow_threads(|| {
+            graph
+                .find_arbitrage_opportunities(start_token)
+                .iter()
+                .map(|cycle| PyPath {
+                    inner: graph.cycle_to_path(cycle),
+                })
+                .collect()
+        })
+    }
+
+    /// Re-quote the edges of `pool_ids` from current state. Returns the number of edges updated.
+    fn refresh(&mut self, py: Python<'_>, pool_ids: Vec<String>) -> PyResult<usize> {
+        for pool_id in pool_ids {
+            let pool_id = ObjectID::from_hex_literal(&pool_id).map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+            self.graph.mark_dirty(pool_id);
+        }
+
+        let Self {
+            runtime,
+            dex_searcher,
+            weights,
+            graph,
+        } = self;
+        Ok(py.allow_threads(|| runtime.block_on(graph.recompute_dirty(dex_searcher.as_ref(), weights.as_ref()))))
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Graph(nodes={}, edges={})", self.node_count(), self.edge_count())
+    }
+}
+
+#[pymodule]
+fn poison_dart(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Engine>()?;
+    m.add_class::<Graph>()?;
+    m.add_class::<PyPath>()?;
+    m.add_class::<Hop>()?;
+    m.add_class::<Quote>()?;
+    Ok(())
+}
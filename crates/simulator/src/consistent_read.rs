@@ -0,0 +1,142 @@
+//! Consistent multi-object reads.
+//!
+//! Objects read one by one can straddle a checkpoint: the first pool comes from checkpoint N and
+//! the second from N+1, and a quote built from both prices a state that never existed. We read
+//! the whole set twice and accept it only when no version moved in between (a seqlock-style
+//! read); otherwise we retry a few times before giving up.
+
+use eyre::{bail, eyre, Result};
+use sui_types::{
+    base_types::{ObjectID, SequenceNumber},
+    object::Object,
+};
+use tracing::debug;
+
+use crate::Simulator;
+
+const MAX_CONSISTENT_READ_ATTEMPTS: usize = 3;
+
+/// Read all `obj_ids` at mutually consistent versions, in the order given.
+pub async fn get_objects_consistent(simulator: &dyn Simulator, obj_ids: &[ObjectID]) -> Result<Vec<Object>> {
+    if obj_ids.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let mut objects = read_all(simulator, obj_ids).await?;
+    for attempt in 1..=MAX_CONSISTENT_READ_ATTEMPTS {
+        let reread = read_all(simulator, obj_ids).await?;
+        if versions(&objects) == versions(&reread) {
+            return Ok(reread);
+        }
+
+        debug!(attempt, "objects moved between reads, retrying");
+        objects = reread;
+    }
+
+    bail!(
+        "objects kept changing after {} reads: {:?}",
+        MAX_CONSISTENT_READ_ATTEMPTS + 1,
+        obj_ids
+    )
+}
+
+async fn read_all(simulator: &dyn Simulator, obj_ids: &[ObjectID]) -> Result<Vec<Object>> {
+    simulator
+        .get_objects(obj_ids)
+        .await
+        .into_iter()
+        .zip(obj_ids)
+        .map(|(obj, id)| obj.ok_or_else(|| eyre!("object not found: {}", id)))
+        .collect()
+}
+
+fn versions(objects: &[Object]) -> Vec<SequenceNumber> {
+    objects.iter().map(|obj| obj.version()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, sync::Mutex};
+
+    use async_trait::async_trait;
+    use sui_types::{base_types::SuiAddress, transaction::TransactionData};
+
+    use super::*;
+    use crate::{SimulateCtx, SimulateResult};
+
+    // serves the objects at their version in the read it is at, the last version every read after
+    struct Moving {
+        versions: HashMap<ObjectID, Vec<u64>>,
+        reads: Mutex<usize>,
+    }
+
+    #[async_trait]
+    impl Simulator for Moving {
+        async fn simulate(&self, _tx: TransactionData, _ctx: SimulateCtx) -> Result<SimulateResult> {
+            bail!("not simulated")
+        }
+
+        async fn get_object(&self, _obj_id: &ObjectID) -> Option<Object> {
+            unreachable!("objects are read in batches")
+        }
+
+        async fn get_objects(&self, obj_ids: &[ObjectID]) -> Vec<Option<Object>> {
+            let read = {
+                let mut reads = self.reads.lock().unwrap();
+                *reads += 1;
+                *reads - 1
+            };
+            obj_ids
+                .iter()
+                .map(|id| {
+                    let versions = self.versions.get(id)?;
+                    let version = versions[read.min(versions.len() - 1)];
+                    Some(Object::with_id_owner_version_for_testing(
+                        *id,
+                        SequenceNumber::from_u64(version),
+                        SuiAddress::ZERO,
+                    ))
+                })
+                .collect()
+        }
+
+        fn name(&self) -> &str {
+            "moving"
+        }
+    }
+
+    fn moving(versions: impl IntoIterator<Item = (ObjectID, Vec<u64>)>) -> Moving {
+        Moving {
+            versions: versions.into_iter().collect(),
+            reads: Mutex::new(0),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_consistent_read() {
+        let (a, b) = (ObjectID::random(), ObjectID::random());
+        let v = SequenceNumber::from_u64;
+
+        // nothing moved, the second read is taken
+        let simulator = moving([(a, vec![1]), (b, vec![2])]);
+        let objects = get_objects_consistent(&simulator, &[b, a]).await.unwrap();
+        assert_eq!(versions(&objects), [v(2), v(1)]);
+        assert_eq!(*simulator.reads.lock().unwrap(), 2);
+
+        // `b` moved between the first two reads, the third agrees with the second
+        let simulator = moving([(a, vec![1]), (b, vec![2, 3])]);
+        let objects = get_objects_consistent(&simulator, &[a, b]).await.unwrap();
+        assert_eq!(versions(&objects), [v(1), v(3)]);
+        assert_eq!(*simulator.reads.lock().unwrap(), 3);
+
+        // `b` moves on every read
+        let simulator = moving([(a, vec![1]), (b, (2..10).collect())]);
+        assert!(get_objects_consistent(&simulator, &[a, b]).await.is_err());
+        assert_eq!(*simulator.reads.lock().unwrap(), MAX_CONSISTENT_READ_ATTEMPTS + 1);
+
+        // a missing object fails the read, no object is no read
+        let simulator = moving([(a, vec![1])]);
+        assert!(get_objects_consistent(&simulator, &[a, b]).await.is_err());
+        assert!(get_objects_consistent(&simulator, &[]).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,378 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+    str::FromStr,
+    sync::{Arc, RwLock},
+};
+
+use async_trait::async_trait;
+use eyre::Result;
+use sui_execution::Executor;
+use sui_json_rpc_types::SuiObjectDataOptions;
+use sui_sdk::{SuiClient, SuiClientBuilder};
+use sui_types::{
+    base_types::{ObjectID, SequenceNumber},
+    error::SuiError,
+    metrics::LimitsMetrics,
+    object::{Object, Owner},
+    supported_protocol_versions::ProtocolConfig,
+    transaction::{InputObjectKind, InputObjects, ObjectReadResult, TransactionData, TransactionDataAPI},
+};
+use tracing::{debug, info};
+
+use super::{execute, new_executor};
+use crate::{SimulateCtx, SimulateResult, Simulator};
+
+// limit of `multi_get_object_with_options`
+const MULTI_GET_LIMIT: usize = 50;
+// executions re-run after fetching what the previous one missed
+const MAX_MISS_ROUNDS: usize = 4;
+
+/// Object state shared by every `MemorySimulator` of a process.
+///
+/// Objects are fetched from the node the first time a simulation needs them and kept until a
+/// transaction is seen changing them (`observe_versions`), so pools that didn't trade are never
+/// fetched twice. What the inputs of a transaction read beyond themselves (dynamic fields,
+/// dependency packages) is remembered per input and loaded up front the next time.
+#[derive(Default)]
+pub struct ObjectCache {
+    objects: RwLock<HashMap<ObjectID, Object>>,
+    // input object or package => objects executions read through it
+    related: RwLock<HashMap<ObjectID, HashSet<ObjectID>>>,
+    // ids the node had no object for, e.g. dynamic fields probed before they exist
+    missing: RwLock<HashSet<ObjectID>>,
+}
+
+impl ObjectCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.objects.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn get(&self, id: &ObjectID) -> Option<Object> {
+        self.objects.read().unwrap().get(id).cloned()
+    }
+
+    fn insert(&self, objects: impl IntoIterator<Item = Object>) {
+        let mut cached = self.objects.write().unwrap();
+        for object in objects {
+            cached.insert(object.id(), object);
+        }
+    }
+
+    /// Drop cached objects older than `changed`, they are fetched again when next read.
+    pub fn observe_versions(&self, changed: &[(ObjectID, SequenceNumber)]) {
+        let mut objects = self.objects.write().unwrap();
+        let mut missing = self.missing.write().unwrap();
+        for (id, version) in changed {
+            if objects.get(id).is_some_and(|object| object.version() < *version) {
+                objects.remove(id);
+            }
+            // created since it was found missing
+            missing.remove(id);
+        }
+    }
+
+    fn related(&self, roots: &[ObjectID]) -> HashSet<ObjectID> {
+        let related = self.related.read().unwrap();
+        roots
+            .iter()
+            .filter_map(|root| related.get(root))
+            .flatten()
+            .copied()
+            .collect()
+    }
+
+    fn relate(&self, roots: &[ObjectID], ids: &HashSet<ObjectID>) {
+        let mut related = self.related.write().unwrap();
+        for root in roots {
+            related.entry(*root).or_default().extend(ids.iter().copied());
+        }
+    }
+}
+
+/// Executes transactions locally like `DBSimulator`, against an in-memory `ObjectCache` instead of
+/// a node database, and reads from the node only what the cache doesn't have.
+pub struct MemorySimulator {
+    client: SuiClient,
+    cache: Arc<ObjectCache>,
+    executor: Arc<dyn Executor + Send + Sync>,
+    protocol_config: ProtocolConfig,
+    metrics: Arc<LimitsMetrics>,
+}
+
+impl MemorySimulator {
+    pub async fn new(url: impl AsRef<str>, cache: Arc<ObjectCache>) -> Self {
+        let client = SuiClientBuilder::default()
+            .max_concurrent_requests(2000)
+            .build(url)
+            .await
+            .unwrap();
+        let (protocol_config, executor) = new_executor();
+
+        Self {
+            client,
+            cache,
+            executor,
+            protocol_config,
+            metrics: Arc::new(LimitsMetrics::new(&prometheus::Registry::new())),
+        }
+    }
+
+    /// Load the objects listed in `path`, one id per line as written by `pool-ids`.
+    pub async fn preload(&self, path: &Path) -> Result<usize> {
+        let ids = std::fs::read_to_string(path)?
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| ObjectID::from_str(line.trim()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let loaded = self.fetch_latest(&ids).await?;
+        info!(?path, ids = ids.len(), loaded, "object cache preloaded");
+        Ok(loaded)
+    }
+
+    /// Cache the latest version of `ids`, returns how many the node had.
+    async fn fetch_latest(&self, ids: &[ObjectID]) -> Result<usize> {
+        let mut fetched = 0;
+        for chunk in ids.chunks(MULTI_GET_LIMIT) {
+            let resps = self
+                .client
+                .read_api()
+                .multi_get_object_with_options(chunk.to_vec(), SuiObjectDataOptions::bcs_lossless())
+                .await?;
+            let mut objects = Vec::with_capacity(chunk.len());
+            for (id, resp) in chunk.iter().zip(resps) {
+                match resp.data.and_then(|data| Object::try_from(data).ok()) {
+                    Some(object) => objects.push(object),
+                    None => {
+                        self.cache.missing.write().unwrap().insert(*id);
+                    }
+                }
+            }
+            fetched += objects.len();
+            self.cache.insert(objects);
+        }
+        Ok(fetched)
+    }
+
+    /// Fetch the inputs of `kinds` and the `related` objects the cache lacks, owned inputs at the
+    /// version they are named with. Returns how many objects were fetched.
+    async fn fetch_missing(
+        &self,
+        kinds: &[InputObjectKind],
+        related: &HashSet<ObjectID>,
+        overridden: &HashSet<ObjectID>,
+    ) -> Result<usize> {
+        let cached_or_skipped = |id: &ObjectID| {
+            overridden.contains(id) || self.cache.missing.read().unwrap().contains(id) || self.cache.get(id).is_some()
+        };
+
+        let mut latest: Vec<ObjectID> = vec![];
+        let mut past = vec![];
+        for kind in kinds {
+            match kind {
+                InputObjectKind::MovePackage(id) | InputObjectKind::SharedMoveObject { id, .. } => {
+                    if !cached_or_skipped(id) {
+                        latest.push(*id);
+                    }
+                }
+                InputObjectKind::ImmOrOwnedMoveObject((id, version, _)) => {
+                    let cached = self.cache.get(id).is_some_and(|object| object.version() == *version);
+                    if !cached && !overridden.contains(id) {
+                        past.push((*id, *version));
+                    }
+                }
+            }
+        }
+        latest.extend(related.iter().filter(|id| !cached_or_skipped(id)));
+
+        let mut fetched = self.fetch_latest(&latest).await?;
+        for (id, version) in past {
+            let object = self
+                .client
+                .read_api()
+                .try_get_parsed_past_object(id, version, SuiObjectDataOptions::bcs_lossless())
+                .await?
+                .into_object()
+                .ok()
+                .and_then(|data| Object::try_from(data).ok());
+            if let Some(object) = object {
+                self.cache.insert([object]);
+                fetched += 1;
+            }
+        }
+        Ok(fetched)
+    }
+
+    /// Input objects of `kinds` from the overrides, then the cache, and the cached objects the
+    /// execution may read: inputs and `related` alike.
+    fn resolve(
+        &self,
+        kinds: &[InputObjectKind],
+        related: &HashSet<ObjectID>,
+        overrides: &[ObjectReadResult],
+    ) -> Result<(InputObjects, Vec<ObjectReadResult>), SuiError> {
+        let lookup = |id: &ObjectID| {
+            overrides
+                .iter()
+                .find(|o| o.id() == *id)
+                .and_then(|o| o.as_object().cloned())
+                .or_else(|| self.cache.get(id))
+        };
+
+        let mut inputs = Vec::with_capacity(kinds.len());
+        for kind in kinds {
+            match kind {
+                InputObjectKind::MovePackage(id) | InputObjectKind::SharedMoveObject { id, .. } => {
+                    let object = lookup(id).ok_or_else(|| SuiError::from(kind.object_not_found_error()))?;
+                    inputs.push(ObjectReadResult::new(*kind, object.into()));
+                }
+                InputObjectKind::ImmOrOwnedMoveObject((id, version, _)) => {
+                    // ignore mock objects
+                    if let Some(object) = lookup(id).filter(|object| object.version() == *version) {
+                        inputs.push(ObjectReadResult::new(*kind, object.into()));
+                    }
+                }
+            }
+        }
+
+        let mut readable = inputs.clone();
+        for id in related {
+            if let Some(object) = self.cache.get(id) {
+                let kind = match object.owner() {
+                    Owner::Shared { initial_shared_version } => InputObjectKind::SharedMoveObject {
+                        id: *id,
+                        initial_shared_version: *initial_shared_version,
+                        mutable: true,
+                    },
+                    _ if object.is_package() => InputObjectKind::MovePackage(*id),
+                    _ => InputObjectKind::ImmOrOwnedMoveObject(object.compute_object_reference()),
+                };
+                readable.push(ObjectReadResult::new(kind, object.into()));
+            }
+        }
+
+        Ok((inputs.into(), readable))
+    }
+}
+
+#[async_trait]
+impl Simulator for MemorySimulator {
+    async fn simulate(&self, tx: TransactionData, ctx: SimulateCtx) -> Result<SimulateResult> {
+        let kinds = tx.input_objects()?;
+        let roots: Vec<ObjectID> = kinds
+            .iter()
+            .filter(|kind| !matches!(kind, InputObjectKind::ImmOrOwnedMoveObject(_)))
+            .map(|kind| kind.object_id())
+            .collect();
+        let overridden: HashSet<ObjectID> = ctx.override_objects.iter().map(|o| o.id()).collect();
+
+        let mut fetched = 0;
+        let mut round = 0;
+        loop {
+            let related = self.cache.related(&roots);
+            fetched += self.fetch_missing(&kinds, &related, &overridden).await?;
+            let (input_objects, readable) = self.resolve(&kinds, &related, &ctx.override_objects)?;
+
+            // the caller's overrides come first and win
+            let mut round_ctx = ctx.clone();
+            round_ctx.override_objects.extend(readable);
+            let (mut result, misses) = execute(
+                &self.executor,
+                &self.protocol_config,
+                self.metrics.clone(),
+                input_objects,
+                tx.clone(),
+                round_ctx,
+                None,
+                None,
+            )
+            .await?;
+
+            let misses: HashSet<ObjectID> = {
+                let missing = self.cache.missing.read().unwrap();
+                misses.into_iter().filter(|id| !missing.contains(id)).collect()
+            };
+            round += 1;
+            if misses.is_empty() || round > MAX_MISS_ROUNDS {
+                result.cache_misses = fetched as u64;
+                return Ok(result);
+            }
+            debug!(misses = misses.len(), round, "re-executing with the objects it missed");
+            self.cache.relate(&roots, &misses);
+        }
+    }
+
+    fn name(&self) -> &str {
+        "MemorySimulator"
+    }
+
+    async fn get_object(&self, obj_id: &ObjectID) -> Option<Object> {
+        if let Some(object) = self.cache.get(obj_id) {
+            return Some(object);
+        }
+        self.fetch_latest(&[*obj_id]).await.ok()?;
+        self.cache.get(obj_id)
+    }
+
+    async fn get_objects(&self, obj_ids: &[ObjectID]) -> Vec<Option<Object>> {
+        let uncached: Vec<ObjectID> = obj_ids
+            .iter()
+            .filter(|id| self.cache.get(id).is_none())
+            .copied()
+            .collect();
+        let _ = self.fetch_latest(&uncached).await;
+        obj_ids.iter().map(|id| self.cache.get(id)).collect()
+    }
+
+    fn observe_versions(&self, changed: &[(ObjectID, SequenceNumber)]) {
+        self.cache.observe_versions(changed);
+    }
+
+    fn related_objects(&self, roots: &[ObjectID]) -> Vec<ObjectID> {
+        self.cache.related(roots).into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sui_types::{base_types::SuiAddress, object::OBJECT_START_VERSION};
+
+    use super::*;
+
+    #[test]
+    fn test_observe_versions() {
+        let cache = ObjectCache::new();
+        let coin = Object::new_gas_with_balance_and_owner_for_testing(1_000, SuiAddress::ZERO);
+        let id = coin.id();
+        let version = coin.version();
+        cache.insert([coin]);
+        let absent = ObjectID::random();
+        cache.missing.write().unwrap().insert(absent);
+
+        // an older or equal version leaves the object cached
+        cache.observe_versions(&[(id, OBJECT_START_VERSION), (id, version)]);
+        assert!(cache.get(&id).is_some());
+
+        cache.observe_versions(&[(id, version.next()), (absent, OBJECT_START_VERSION)]);
+        assert!(cache.is_empty());
+        assert!(!cache.missing.read().unwrap().contains(&absent));
+    }
+
+    #[test]
+    fn test_related() {
+        let cache = ObjectCache::new();
+        let (pool, package, field) = (ObjectID::random(), ObjectID::random(), ObjectID::random());
+        cache.relate(&[pool, package], &HashSet::from([field]));
+
+        assert_eq!(cache.related(&[pool]), HashSet::from([field]));
+        assert!(cache.related(&[ObjectID::random()]).is_empty());
+    }
+}
@@ -1,6 +1,8 @@
+mod memory_simulator;
 mod override_cache;
 mod replay_simulator;
 
+pub use memory_simulator::{MemorySimulator, ObjectCache};
 pub use replay_simulator::ReplaySimulator;
 
 use std::{
@@ -32,6 +34,7 @@ use sui_types::{
     effects::TransactionEffects,
     error::SuiError,
     gas::SuiGasStatus,
+    gas_coin::GAS,
     inner_temporary_store::InnerTemporaryStore,
     metrics::LimitsMetrics,
     object::{MoveObject, Object, Owner, OBJECT_START_VERSION},
@@ -146,15 +149,7 @@ impl DBSimulator {
                 .unwrap();
         }
 
-        let mut protocol_config = ProtocolConfig::get_for_version(ProtocolVersion::MAX, Chain::Mainnet);
-
-        protocol_config.object_runtime_max_num_cached_objects = Some(1000000);
-        protocol_config.object_runtime_max_num_cached_objects_system_tx = Some(1000000);
-        protocol_config.object_runtime_max_num_store_entries = Some(1000000);
-        protocol_config.object_runtime_max_num_store_entries_system_tx = Some(1000000);
-
-        let executor =
-            sui_execution::executor(&protocol_config, true, None).expect("Creating an executor should not fail here");
+        let (protocol_config, executor) = new_executor();
 
         Self {
             store: writeback_cache,
@@ -223,32 +218,6 @@ impl DBSimulator {
 
         Ok(input_results.into_iter().flatten().collect::<Vec<_>>().into())
     }
-
-    fn get_mutated_objects(
-        &self,
-        effects: &TransactionEffects,
-        store: &InnerTemporaryStore,
-    ) -> eyre::Result<Vec<ObjectReadResult>> {
-        let mut object_changes = vec![];
-        for (obj_ref, owner) in effects.mutated_excluding_gas() {
-            if let Some(obj) = store.written.get(&obj_ref.0) {
-                let object = ObjectReadResultKind::Object(obj.clone());
-
-                let kind = match owner {
-                    Owner::Shared { initial_shared_version } => InputObjectKind::SharedMoveObject {
-                        id: obj_ref.0,
-                        initial_shared_version,
-                        mutable: true,
-                    },
-                    _ => InputObjectKind::ImmOrOwnedMoveObject(obj_ref),
-                };
-
-                object_changes.push(ObjectReadResult::new(kind, object));
-            }
-        }
-
-        Ok(object_changes)
-    }
 }
 
 #[async_trait]
@@ -256,215 +225,25 @@ impl Simulator for DBSimulator {
     async fn simulate(&self, tx: TransactionData, ctx: SimulateCtx) -> eyre::Result<SimulateResult> {
         let cache_misses_before = self.writeback_metrics.cache_misses_count();
 
-        let SimulateCtx {
-            epoch,
-            mut override_objects,
-            borrowed_coin,
-        } = ctx;
-
-        let mut input_objects = self.get_input_objects(&tx.input_objects()?, epoch.epoch_id)?;
-
-        let sender = tx.sender();
-        let original_gas = tx.gas().to_vec();
-
-        let mock_gas_id =
-            ObjectID::from_str("0x0000000000000000000000000000000000000000000000000000000000001337").unwrap();
-        let use_mock_gas = original_gas.is_empty();
-        let (gas_ref, gas_obj) = if use_mock_gas {
-            let sender = tx.sender();
-            // use a 1B sui coin
-            const MIST_TO_SUI: u64 = 1_000_000_000;
-            const DRY_RUN_SUI: u64 = 1_000_000_000;
-
-            let max_coin_value = MIST_TO_SUI * DRY_RUN_SUI;
-            let gas_object = Object::new_move(
-                MoveObject::new_gas_coin(OBJECT_START_VERSION, mock_gas_id, max_coin_value),
-                Owner::AddressOwner(sender),
-                TransactionDigest::genesis_marker(),
-            );
-            let gas_object_ref = gas_object.compute_object_reference();
-            (vec![gas_object_ref], Some(gas_object))
-        } else {
-            (original_gas, None)
-        };
-
-        let gas_status = match SuiGasStatus::new(tx.gas_budget(), tx.gas_price(), tx.gas_price(), &self.protocol_config)
-            .map_err(|e| eyre::eyre!(e))
-        {
-            Ok(gas_status) => gas_status,
-            Err(e) => {
-                info!("simulate error: {:?}", e);
-                return Err(e);
-            }
-        };
-
-        // extend override objects with mocked gas and borrowed coin
-        if use_mock_gas {
-            let gas_obj = gas_obj.unwrap();
-            let object_read_result = ObjectReadResult {
-                input_object_kind: InputObjectKind::ImmOrOwnedMoveObject(gas_obj.compute_object_reference()),
-                object: ObjectReadResultKind::Object(gas_obj),
-            };
-
-            input_objects.objects.push(object_read_result.clone());
-            override_objects.push(object_read_result);
-        }
-
-        if let Some((borrowed_coin, _borrowed_amount)) = &borrowed_coin {
-            let object_read_result = ObjectReadResult {
-                input_object_kind: InputObjectKind::ImmOrOwnedMoveObject(borrowed_coin.compute_object_reference()),
-                object: ObjectReadResultKind::Object(borrowed_coin.clone()),
-            };
-
-            input_objects.objects.push(object_read_result.clone());
-            override_objects.push(object_read_result);
-        }
-
-        // create override cache
-        let override_cache = if self.with_fallback {
-            OverrideCache::new(Some(self.store.clone()), override_objects)
-        } else {
-            OverrideCache::new(None, override_objects)
-        };
-
-        // update input objects again with override cache
-        for object_read_result in input_objects.objects.iter_mut() {
-            if let ObjectReadResultKind::Object(object) = &object_read_result.object {
-                if let Some(object) = (&override_cache as &dyn ObjectCacheRead).get_object(&object.id()) {
-                    object_read_result.object = ObjectReadResultKind::Object(object);
-                }
-            }
-        }
-
-        let digest = tx.digest();
-        let kind = tx.into_kind();
-        let input_object_kinds = input_objects.object_kinds().cloned().collect::<Vec<_>>();
-
-        let simulate_start = std::time::Instant::now();
-
-        let (inner_temporary_store, effects) = catch_unwind(AssertUnwindSafe(|| {
-            let (inner_temporary_store, _, effects, _) = self.executor.execute_transaction_to_effects(
-                &override_cache,
-                &self.protocol_config,
-                self.metrics.clone(),
-                false,
-                &HashSet::new(),
-                &epoch.epoch_id,
-                epoch.epoch_start_timestamp,
-                CheckedInputObjects::new_with_checked_transaction_inputs(input_objects),
-                gas_ref,
-                gas_status,
-                kind,
-                sender,
-                digest,
-            );
-            (inner_temporary_store, effects)
-        }))
-        .map_err(|e| eyre::eyre!("failed to simulate: {e:?}"))?;
-
-        debug!("simulate tx_data elapsed: {:?}", simulate_start.elapsed());
-
-        let object_changes = self.get_mutated_objects(&effects, &inner_temporary_store)?;
-
-        let executed_db = ExecutedDB {
-            db: &override_cache,
-            temp_store: &inner_temporary_store,
-        };
-
-        // don't let sui calc balance change. we will do it manually
-        let mut balance_changes = if !use_mock_gas {
-            // ignore borrowed coin
-            get_balance_changes_from_effect(
-                &executed_db,
-                &effects,
-                input_object_kinds,
-                borrowed_coin.clone().map(|(obj, _)| vec![obj.id()]),
-            )
-            .await?
-        } else {
-            let mut ignore_ids = vec![mock_gas_id];
-            if let Some((borrowed_coin_obj, _)) = &borrowed_coin {
-                ignore_ids.push(borrowed_coin_obj.id());
-            }
-            get_balance_changes_from_effect(&executed_db, &effects, input_object_kinds, Some(ignore_ids)).await?
-        };
-
-        // Subtract how much we borrowed
-        // TODO: we should borrow any coin other than just sui
-        if let Some((_borrowed_coin_obj, borrowed_amount)) = &borrowed_coin {
-            let mut found = false;
-            if let Some(bc) = balance_changes
-                .iter_mut()
-                .find(|bc| bc.owner == Owner::AddressOwner(sender) && bc.coin_type.to_string() == SUI_COIN_TYPE)
-            {
-                found = true;
-                bc.amount -= *borrowed_amount as i128;
-            }
-
-            if !found {
-                balance_changes.push(BalanceChange {
-                    owner: Owner::AddressOwner(sender),
-                    coin_type: TypeTag::Struct(Box::new(move_core_types::language_storage::StructTag {
-                        address: move_core_types::account_address::AccountAddress::TWO,
-                        module: sui_types::Identifier::new("sui").unwrap(),
-                        name: sui_types::Identifier::new("SUI").unwrap(),
-                        type_params: vec![],
-                    })),
-                    amount: -(*borrowed_amount as i128),
-                });
-            }
-        }
-
-        if use_mock_gas {
-            let mut found = false;
-
-            let init_amount = 1_000_000_000u64 * 1_000_000_000u64;
-            let final_amount = inner_temporary_store
-                .written
-                .get(&mock_gas_id)
-                .unwrap()
-                .as_coin_maybe()
-                .unwrap()
-                .value();
-
-            for bc in balance_changes.iter_mut() {
-                if bc.owner == Owner::AddressOwner(sender) && bc.coin_type.to_string() == SUI_COIN_TYPE {
-                    bc.amount -= (init_amount - final_amount) as i128;
-                    found = true;
-                }
-            }
-
-            if !found {
-                // we manually add a balance change for the mock gas
-                balance_changes.push(BalanceChange {
-                    owner: Owner::AddressOwner(sender),
-                    coin_type: TypeTag::Struct(Box::new(move_core_types::language_storage::StructTag {
-                        address: move_core_types::account_address::AccountAddress::TWO,
-                        module: sui_types::Identifier::new("sui").unwrap(),
-                        name: sui_types::Identifier::new("SUI").unwrap(),
-                        type_params: vec![],
-                    })),
-                    amount: final_amount as i128 - init_amount as i128,
-                });
-            }
-        }
-
-        let mut layout_resolver = self.executor.type_layout_resolver(Box::new(&self.store));
-        let events =
-            SuiTransactionBlockEvents::try_from(inner_temporary_store.events, digest, None, layout_resolver.as_mut())?;
+        let input_objects = self.get_input_objects(&tx.input_objects()?, ctx.epoch.epoch_id)?;
+        let fallback = self.with_fallback.then(|| self.store.clone());
+        let (mut result, _) = execute(
+            &self.executor,
+            &self.protocol_config,
+            self.metrics.clone(),
+            input_objects,
+            tx,
+            ctx,
+            fallback,
+            Some(&self.store),
+        )
+        .await?;
 
-        let cache_misses = self
+        result.cache_misses = self
             .writeback_metrics
             .cache_misses_count()
             .saturating_sub(cache_misses_before);
-
-        Ok(SimulateResult {
-            effects: SuiTransactionBlockEffects::try_from(effects)?,
-            events,
-            object_changes,
-            balance_changes,
-            cache_misses,
-        })
+        Ok(result)
     }
 
     fn name(&self) -> &str {
@@ -497,6 +276,258 @@ impl Simulator for DBSimulator {
     }
 }
 
+/// The latest mainnet protocol config, with object runtime limits lifted, and its executor.
+fn new_executor() -> (ProtocolConfig, Arc<dyn Executor + Send + Sync>) {
+    let mut protocol_config = ProtocolConfig::get_for_version(ProtocolVersion::MAX, Chain::Mainnet);
+
+    protocol_config.object_runtime_max_num_cached_objects = Some(1000000);
+    protocol_config.object_runtime_max_num_cached_objects_system_tx = Some(1000000);
+    protocol_config.object_runtime_max_num_store_entries = Some(1000000);
+    protocol_config.object_runtime_max_num_store_entries_system_tx = Some(1000000);
+
+    let executor =
+        sui_execution::executor(&protocol_config, true, None).expect("Creating an executor should not fail here");
+    (protocol_config, executor)
+}
+
+/// Executes `tx` on `input_objects`, reading anything else from `ctx.override_objects`, then from
+/// `fallback`. A tx without gas pays with a mock coin. Event layouts are resolved from
+/// `layout_store`, else from the same objects as the execution. Returns the ids no store had.
+#[allow(clippy::too_many_arguments)]
+async fn execute(
+    executor: &Arc<dyn Executor + Send + Sync>,
+    protocol_config: &ProtocolConfig,
+    metrics: Arc<LimitsMetrics>,
+    mut input_objects: InputObjects,
+    tx: TransactionData,
+    ctx: SimulateCtx,
+    fallback: Option<Arc<WritebackCache>>,
+    layout_store: Option<&Arc<WritebackCache>>,
+) -> eyre::Result<(SimulateResult, HashSet<ObjectID>)> {
+    let SimulateCtx {
+        epoch,
+        mut override_objects,
+        borrowed_coin,
+    } = ctx;
+
+    let sender = tx.sender();
+    let original_gas = tx.gas().to_vec();
+
+    let mock_gas_id = ObjectID::from_str("0x0000000000000000000000000000000000000000000000000000000000001337").unwrap();
+    let use_mock_gas = original_gas.is_empty();
+    let (gas_ref, gas_obj) = if use_mock_gas {
+        let sender = tx.sender();
+        // use a 1B sui coin
+        const MIST_TO_SUI: u64 = 1_000_000_000;
+        const DRY_RUN_SUI: u64 = 1_000_000_000;
+
+        let max_coin_value = MIST_TO_SUI * DRY_RUN_SUI;
+        let gas_object = Object::new_move(
+            MoveObject::new_gas_coin(OBJECT_START_VERSION, mock_gas_id, max_coin_value),
+            Owner::AddressOwner(sender),
+            TransactionDigest::genesis_marker(),
+        );
+        let gas_object_ref = gas_object.compute_object_reference();
+        (vec![gas_object_ref], Some(gas_object))
+    } else {
+        (original_gas, None)
+    };
+
+    let gas_status = match SuiGasStatus::new(tx.gas_budget(), tx.gas_price(), tx.gas_price(), protocol_config)
+        .map_err(|e| eyre::eyre!(e))
+    {
+        Ok(gas_status) => gas_status,
+        Err(e) => {
+            info!("simulate error: {:?}", e);
+            return Err(e);
+        }
+    };
+
+    // extend override objects with mocked gas and borrowed coin
+    if use_mock_gas {
+        let gas_obj = gas_obj.unwrap();
+        let object_read_result = ObjectReadResult {
+            input_object_kind: InputObjectKind::ImmOrOwnedMoveObject(gas_obj.compute_object_reference()),
+            object: ObjectReadResultKind::Object(gas_obj),
+        };
+
+        input_objects.objects.push(object_read_result.clone());
+        override_objects.push(object_read_result);
+    }
+
+    if let Some((borrowed_coin, _borrowed_amount)) = &borrowed_coin {
+        let object_read_result = ObjectReadResult {
+            input_object_kind: InputObjectKind::ImmOrOwnedMoveObject(borrowed_coin.compute_object_reference()),
+            object: ObjectReadResultKind::Object(borrowed_coin.clone()),
+        };
+
+        input_objects.objects.push(object_read_result.clone());
+        override_objects.push(object_read_result);
+    }
+
+    // create override cache
+    let override_cache = OverrideCache::new(fallback, override_objects);
+
+    // update input objects again with override cache
+    for object_read_result in input_objects.objects.iter_mut() {
+        if let ObjectReadResultKind::Object(object) = &object_read_result.object {
+            if let Some(object) = (&override_cache as &dyn ObjectCacheRead).get_object(&object.id()) {
+                object_read_result.object = ObjectReadResultKind::Object(object);
+            }
+        }
+    }
+
+    let digest = tx.digest();
+    let kind = tx.into_kind();
+    let input_object_kinds = input_objects.object_kinds().cloned().collect::<Vec<_>>();
+
+    let simulate_start = std::time::Instant::now();
+
+    let (inner_temporary_store, effects) = catch_unwind(AssertUnwindSafe(|| {
+        let (inner_temporary_store, _, effects, _) = executor.execute_transaction_to_effects(
+            &override_cache,
+            protocol_config,
+            metrics,
+            false,
+            &HashSet::new(),
+            &epoch.epoch_id,
+            epoch.epoch_start_timestamp,
+            CheckedInputObjects::new_with_checked_transaction_inputs(input_objects),
+            gas_ref,
+            gas_status,
+            kind,
+            sender,
+            digest,
+        );
+        (inner_temporary_store, effects)
+    }))
+    .map_err(|e| eyre::eyre!("failed to simulate: {e:?}"))?;
+
+    debug!("simulate tx_data elapsed: {:?}", simulate_start.elapsed());
+
+    let object_changes = get_mutated_objects(&effects, &inner_temporary_store)?;
+
+    let executed_db = ExecutedDB {
+        db: &override_cache,
+        temp_store: &inner_temporary_store,
+    };
+
+    // don't let sui calc balance change. we will do it manually
+    let mut balance_changes = if !use_mock_gas {
+        // ignore borrowed coin
+        get_balance_changes_from_effect(
+            &executed_db,
+            &effects,
+            input_object_kinds,
+            borrowed_coin.clone().map(|(obj, _)| vec![obj.id()]),
+        )
+        .await?
+    } else {
+        let mut ignore_ids = vec![mock_gas_id];
+        if let Some((borrowed_coin_obj, _)) = &borrowed_coin {
+            ignore_ids.push(borrowed_coin_obj.id());
+        }
+        get_balance_changes_from_effect(&executed_db, &effects, input_object_kinds, Some(ignore_ids)).await?
+    };
+
+    // Subtract how much we borrowed
+    if let Some((borrowed_coin_obj, borrowed_amount)) = &borrowed_coin {
+        let borrowed_type = borrowed_coin_obj.coin_type_maybe().unwrap_or_else(GAS::type_tag);
+        let mut found = false;
+        if let Some(bc) = balance_changes
+            .iter_mut()
+            .find(|bc| bc.owner == Owner::AddressOwner(sender) && bc.coin_type == borrowed_type)
+        {
+            found = true;
+            bc.amount -= *borrowed_amount as i128;
+        }
+
+        if !found {
+            balance_changes.push(BalanceChange {
+                owner: Owner::AddressOwner(sender),
+                coin_type: borrowed_type,
+                amount: -(*borrowed_amount as i128),
+            });
+        }
+    }
+
+    if use_mock_gas {
+        let mut found = false;
+
+        let init_amount = 1_000_000_000u64 * 1_000_000_000u64;
+        let final_amount = inner_temporary_store
+            .written
+            .get(&mock_gas_id)
+            .unwrap()
+            .as_coin_maybe()
+            .unwrap()
+            .value();
+
+        for bc in balance_changes.iter_mut() {
+            if bc.owner == Owner::AddressOwner(sender) && bc.coin_type.to_string() == SUI_COIN_TYPE {
+                bc.amount -= (init_amount - final_amount) as i128;
+                found = true;
+            }
+        }
+
+        if !found {
+            // we manually add a balance change for the mock gas
+            balance_changes.push(BalanceChange {
+                owner: Owner::AddressOwner(sender),
+                coin_type: TypeTag::Struct(Box::new(move_core_types::language_storage::StructTag {
+                    address: move_core_types::account_address::AccountAddress::TWO,
+                    module: sui_types::Identifier::new("sui").unwrap(),
+                    name: sui_types::Identifier::new("SUI").unwrap(),
+                    type_params: vec![],
+                })),
+                amount: final_amount as i128 - init_amount as i128,
+            });
+        }
+    }
+
+    let mut layout_resolver = match layout_store {
+        Some(store) => executor.type_layout_resolver(Box::new(store)),
+        None => executor.type_layout_resolver(Box::new(&override_cache)),
+    };
+    let events =
+        SuiTransactionBlockEvents::try_from(inner_temporary_store.events, digest, None, layout_resolver.as_mut())?;
+
+    let misses = override_cache.misses();
+    let result = SimulateResult {
+        effects: SuiTransactionBlockEffects::try_from(effects)?,
+        events,
+        object_changes,
+        balance_changes,
+        cache_misses: 0,
+    };
+    Ok((result, misses))
+}
+
+fn get_mutated_objects(
+    effects: &TransactionEffects,
+    store: &InnerTemporaryStore,
+) -> eyre::Result<Vec<ObjectReadResult>> {
+    let mut object_changes = vec![];
+    for (obj_ref, owner) in effects.mutated_excluding_gas() {
+        if let Some(obj) = store.written.get(&obj_ref.0) {
+            let object = ObjectReadResultKind::Object(obj.clone());
+
+            let kind = match owner {
+                Owner::Shared { initial_shared_version } => InputObjectKind::SharedMoveObject {
+                    id: obj_ref.0,
+                    initial_shared_version,
+                    mutable: true,
+                },
+                _ => InputObjectKind::ImmOrOwnedMoveObject(obj_ref),
+            };
+
+            object_changes.push(ObjectReadResult::new(kind, object));
+        }
+    }
+
+    Ok(object_changes)
+}
+
 struct ExecutedDB<'a> {
     db: &'a OverrideCache,
     temp_store: &'a InnerTemporaryStore,
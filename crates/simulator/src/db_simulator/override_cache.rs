@@ -1,4 +1,4 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashSet};
 use std::sync::{Arc, RwLock};
 use std::time::{SystemTime, UNIX_EPOCH};
 
@@ -58,6 +58,9 @@ pub struct OverrideCache {
 
     // when we reach fallback, we record the versioned object here
     pub versioned_cache: RwLock<BTreeMap<(ObjectID, SequenceNumber), Object>>,
+
+    // objects found neither in the overrides nor in the fallback
+    misses: RwLock<HashSet<ObjectID>>,
 }
 
 impl OverrideCache {
@@ -66,9 +69,22 @@ impl OverrideCache {
             fallback,
             overrides,
             versioned_cache: RwLock::new(BTreeMap::new()),
+            misses: RwLock::new(HashSet::new()),
         }
     }
 
+    /// Ids read during execution that no store had.
+    pub fn misses(&self) -> HashSet<ObjectID> {
+        self.misses.read().unwrap().clone()
+    }
+
+    fn record_miss<T>(&self, object_id: &ObjectID, found: Option<T>) -> Option<T> {
+        if found.is_none() {
+            self.misses.write().unwrap().insert(*object_id);
+        }
+        found
+    }
+
     pub fn get_override(&self, object_id: &ObjectID) -> Option<ObjectReadResult> {
         if object_id == &SUI_CLOCK_OBJECT_ID {
             return Some(ObjectReadResult {
@@ -189,11 +205,12 @@ impl ObjectCacheRead for OverrideCache {
         }
 
         warn!("❗️ [get_package_object] override missing: {:?}", id);
-        if let Some(ref fallback) = self.fallback {
-            fallback.get_package_object(id)
+        let package = if let Some(ref fallback) = self.fallback {
+            fallback.get_package_object(id)?
         } else {
-            Ok(None)
-        }
+            None
+        };
+        Ok(self.record_miss(id, package))
     }
 
     fn force_reload_system_packages(&self, system_package_ids: &[ObjectID]) {
@@ -221,9 +238,9 @@ impl ObjectCacheRead for OverrideCache {
             if let Some(obj) = obj.clone() {
                 self.versioned_cache.write().unwrap().insert((*id, obj.version()), obj);
             }
-            obj
+            self.record_miss(id, obj)
         } else {
-            None
+            self.record_miss(id, None)
         }
     }
 
@@ -298,11 +315,12 @@ impl ObjectCacheRead for OverrideCache {
             "❗️ [get_object_by_key] override missing: {:?}, version: {:?}",
             object_id, version
         );
-        if let Some(ref fallback) = self.fallback {
+        let object = if let Some(ref fallback) = self.fallback {
             fallback.get_object_by_key(object_id, version)
         } else {
             None
-        }
+        };
+        self.record_miss(object_id, object)
     }
 
     fn multi_get_objects_by_key(&self, object_keys: &[ObjectKey]) -> Vec<Option<Object>> {
@@ -333,7 +351,8 @@ impl ObjectCacheRead for OverrideCache {
         if object.version() <= version {
             return Some(object);
         }
-        None
+        // a dynamic field written after its overridden parent, read it as of the parent
+        self.fallback.as_ref()?.find_object_lt_or_eq_version(object_id, version)
     }
 
     fn get_lock(&self, obj_ref: ObjectRef, epoch_store: &AuthorityPerEpochStore) -> SuiLockResult {
@@ -78,4 +78,20 @@ impl Simulator for HttpSimulator {
             .try_into()
             .ok()
     }
+
+    async fn get_objects(&self, obj_ids: &[ObjectID]) -> Vec<Option<Object>> {
+        let resp = self
+            .client
+            .read_api()
+            .multi_get_object_with_options(obj_ids.to_vec(), SuiObjectDataOptions::bcs_lossless())
+            .await;
+
+        match resp {
+            Ok(resp) => resp
+                .into_iter()
+                .map(|obj| obj.data.and_then(|data| data.try_into().ok()))
+                .collect(),
+            Err(_) => vec![None; obj_ids.len()],
+        }
+    }
 }
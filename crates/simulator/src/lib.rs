@@ -1,3 +1,4 @@
+mod consistent_read;
 mod db_simulator;
 mod http_simulator;
 
@@ -6,7 +7,7 @@ use eyre::Result;
 use move_core_types::annotated_value::MoveStructLayout;
 use sui_json_rpc_types::{BalanceChange, SuiTransactionBlockEffects, SuiTransactionBlockEvents};
 use sui_types::{
-    base_types::ObjectID,
+    base_types::{ObjectID, SequenceNumber},
     committee::EpochId,
     messages_checkpoint::CheckpointTimestamp,
     object::Object,
@@ -14,7 +15,8 @@ use sui_types::{
     transaction::{ObjectReadResult, TransactionData},
 };
 
-pub use db_simulator::{DBSimulator, ReplaySimulator};
+pub use consistent_read::get_objects_consistent;
+pub use db_simulator::{DBSimulator, MemorySimulator, ObjectCache, ReplaySimulator};
 pub use http_simulator::HttpSimulator;
 
 #[derive(Debug, Clone)]
@@ -73,12 +75,13 @@ impl SimulateCtx {
 }
 
 impl SimEpoch {
+    /// Whether the epoch is over, i.e. its gas price may have changed.
     pub fn is_stale(&self) -> bool {
         (std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap()
-            .as_millis() as u64)
-            < self.epoch_start_timestamp + self.epoch_duration_ms
+            .as_millis() as u64) >=
+            self.epoch_start_timestamp + self.epoch_duration_ms
     }
 }
 
@@ -88,7 +91,27 @@ pub trait Simulator: Sync + Send {
     async fn get_object(&self, obj_id: &ObjectID) -> Option<Object>;
     fn name(&self) -> &str;
 
+    /// Read several objects at once. Implementations backed by a remote node should override
+    /// this with a single batched request.
+    async fn get_objects(&self, obj_ids: &[ObjectID]) -> Vec<Option<Object>> {
+        let mut objects = Vec::with_capacity(obj_ids.len());
+        for obj_id in obj_ids {
+            objects.push(self.get_object(obj_id).await);
+        }
+        objects
+    }
+
     fn get_object_layout(&self, _: &ObjectID) -> Option<MoveStructLayout> {
         None
     }
+
+    /// A transaction left these objects at these versions (deleted or wrapped ones included), for
+    /// simulators that keep object state of their own.
+    fn observe_versions(&self, _changed: &[(ObjectID, SequenceNumber)]) {}
+
+    /// Objects executions through `roots` were seen reading beyond them (dynamic fields,
+    /// packages), for simulators that learn them.
+    fn related_objects(&self, _roots: &[ObjectID]) -> Vec<ObjectID> {
+        vec![]
+    }
 }
@@ -0,0 +1,45 @@
+//! Allocation counting for tests: wraps the system allocator and counts allocations made by the
+//! current thread, so hot-path helpers can assert they stay allocation-free once warmed up.
+//!
+//! Each crate that uses `count_allocations` installs the allocator in its test build:
+//!
+//! ```ignore
+//! #[cfg(test)]
+//! #[global_allocator]
+//! static GLOBAL: utils::alloc_counter::CountingAllocator = utils::alloc_counter::CountingAllocator;
+//! ```
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+};
+
+pub struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+/// Run `f` and return its result with the number of allocations it made on this thread. Always 0
+/// unless `CountingAllocator` is the global allocator.
+pub fn count_allocations<R>(f: impl FnOnce() -> R) -> (R, usize) {
+    let before = ALLOCATIONS.with(Cell::get);
+    let result = f();
+    (result, ALLOCATIONS.with(Cell::get) - before)
+}
@@ -0,0 +1,109 @@
+//! Size/TTL limits and usage metrics for the in-memory caches.
+//!
+//! Every long-lived cache is a bounded LRU (`cached::TimedSizedCache`): the least recently used
+//! entry is evicted once `size` is reached and entries older than `ttl` are refetched. Limits can
+//! be overridden per cache with `SUI_CACHE_<NAME>_SIZE` and `SUI_CACHE_<NAME>_TTL_SECS`.
+
+use std::{env, mem, time::Duration};
+
+use cached::{Cached, TimedSizedCache};
+use tracing::info;
+
+#[derive(Debug, Clone, Copy)]
+pub struct CacheLimits {
+    pub size: usize,
+    pub ttl: Duration,
+}
+
+impl CacheLimits {
+    /// `name` is the cache name in SCREAMING_SNAKE_CASE, e.g. `COIN_DECIMALS`.
+    pub fn from_env(name: &str, size: usize, ttl: Duration) -> Self {
+        let size = env_parse(&format!("SUI_CACHE_{name}_SIZE")).unwrap_or(size);
+        let ttl = env_parse(&format!("SUI_CACHE_{name}_TTL_SECS"))
+            .map(Duration::from_secs)
+            .unwrap_or(ttl);
+
+        Self { size: size.max(1), ttl }
+    }
+
+    pub fn new_cache<K, V>(&self) -> TimedSizedCache<K, V>
+    where
+        K: std::hash::Hash + Eq + Clone,
+    {
+        TimedSizedCache::with_size_and_lifespan(self.size, self.ttl.as_secs().max(1))
+    }
+}
+
+fn env_parse<T: std::str::FromStr>(key: &str) -> Option<T> {
+    env::var(key).ok().and_then(|v| v.parse().ok())
+}
+
+#[derive(Debug, Clone)]
+pub struct CacheStats {
+    pub name: &'static str,
+    pub entries: usize,
+    pub capacity: Option<usize>,
+    pub hits: u64,
+    pub misses: u64,
+    /// Entry count times the in-line entry size; heap data owned by keys and values is not included.
+    pub approx_bytes: usize,
+}
+
+impl CacheStats {
+    pub fn of<K, V>(name: &'static str, cache: &impl Cached<K, V>) -> Self {
+        let entries = cache.cache_size();
+        Self {
+            name,
+            entries,
+            capacity: cache.cache_capacity(),
+            hits: cache.cache_hits().unwrap_or_default(),
+            misses: cache.cache_misses().unwrap_or_default(),
+            approx_bytes: entries * mem::size_of::<(K, V)>(),
+        }
+    }
+
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            return 0.0;
+        }
+        self.hits as f64 / lookups as f64
+    }
+}
+
+pub fn log_cache_stats(stats: &[CacheStats]) {
+    for s in stats {
+        info!(
+            target: "cache_metrics",
+            cache = s.name,
+            entries = s.entries,
+            capacity = ?s.capacity,
+            hits = s.hits,
+            misses = s.misses,
+            hit_rate = format!("{:.3}", s.hit_rate()),
+            approx_bytes = s.approx_bytes,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_evicts_least_recently_used() {
+        let limits = CacheLimits::from_env("TEST_LRU", 2, Duration::from_secs(60));
+        let mut cache = limits.new_cache::<u32, u32>();
+        cache.cache_set(1, 1);
+        cache.cache_set(2, 2);
+        cache.cache_get(&1);
+        cache.cache_set(3, 3);
+
+        assert!(cache.cache_get(&1).is_some());
+        assert!(cache.cache_get(&2).is_none());
+
+        let stats = CacheStats::of("test", &cache);
+        assert_eq!(stats.entries, 2);
+        assert_eq!(stats.capacity, Some(2));
+    }
+}
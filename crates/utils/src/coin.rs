@@ -3,8 +3,11 @@ use std::str::FromStr;
 use eyre::{eyre, Result};
 use sui_sdk::{rpc_types::Coin, SuiClient, SUI_COIN_TYPE};
 use sui_types::{
-    base_types::{ObjectID, ObjectRef, SuiAddress},
-    object::Object,
+    base_types::{MoveObjectType, ObjectID, ObjectRef, SuiAddress},
+    digests::TransactionDigest,
+    gas_coin::GAS,
+    object::{MoveObject, Object, Owner, OBJECT_START_VERSION},
+    TypeTag,
 };
 
 pub async fn get_gas_coin_refs(
@@ -54,12 +57,25 @@ pub async fn get_coin(sui: &SuiClient, owner: SuiAddress, coin_type: &str, min_b
         .ok_or_else(|| eyre!("No coins with balance >= {}", min_balance))
 }
 
+const MOCKED_COIN_ID: &str = "0x0000000000000000000000000000000000000000000000000000000000001338";
+
 pub fn mocked_sui(owner: SuiAddress, amount: u64) -> Object {
-    Object::with_id_owner_gas_for_testing(
-        ObjectID::from_str("0x0000000000000000000000000000000000000000000000000000000000001338").unwrap(),
-        owner,
+    Object::with_id_owner_gas_for_testing(ObjectID::from_str(MOCKED_COIN_ID).unwrap(), owner, amount)
+}
+
+/// A mocked `Coin<coin_type>` owned by `owner`, for simulating swaps out of coins we don't hold.
+pub fn mocked_coin(owner: SuiAddress, coin_type: TypeTag, amount: u64) -> Object {
+    if coin_type == GAS::type_tag() {
+        return mocked_sui(owner, amount);
+    }
+
+    let coin = MoveObject::new_coin(
+        MoveObjectType::coin(coin_type),
+        OBJECT_START_VERSION,
+        ObjectID::from_str(MOCKED_COIN_ID).unwrap(),
         amount,
-    )
+    );
+    Object::new_move(coin, Owner::AddressOwner(owner), TransactionDigest::genesis_marker())
 }
 
 pub fn is_native_coin(coin_type: &str) -> bool {
@@ -1,9 +1,14 @@
+pub mod alloc_counter;
+pub mod cache;
 pub mod coin;
 pub mod heartbeat;
 pub mod link;
 pub mod object;
+pub mod price;
 pub mod telegram;
 
+use std::sync::OnceLock;
+
 use burberry::executor::telegram_message::{escape, MessageBuilder, TelegramMessageDispatcher};
 use sui_sdk::{SuiClient, SuiClientBuilder};
 use tokio::runtime::{Builder, Handle, RuntimeFlavor};
@@ -92,6 +97,18 @@ fn send_panic_to_telegram(cmdline: &str, msg: &str) {
     }
 }
 
+/// Install the process-wide `value` of `name` in `cell`. Installing the value it already holds is
+/// fine; a different one is an error instead of being dropped, as is one installed after the
+/// default was read.
+pub fn set_once<T: PartialEq + std::fmt::Debug>(cell: &OnceLock<T>, value: T, name: &str) -> eyre::Result<()> {
+    let mut value = Some(value);
+    let current = cell.get_or_init(|| value.take().unwrap());
+    match value {
+        Some(value) if *current != value => eyre::bail!("{} already set to {:?}, not {:?}", name, current, value),
+        _ => Ok(()),
+    }
+}
+
 pub fn current_time_ms() -> u64 {
     std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
@@ -105,3 +122,18 @@ pub async fn new_test_sui_client() -> SuiClient {
         .await
         .unwrap()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_once_rejects_a_different_value() {
+        let cell = OnceLock::new();
+        set_once(&cell, 1, "test config").unwrap();
+        set_once(&cell, 1, "test config").unwrap();
+
+        assert!(set_once(&cell, 2, "test config").is_err());
+        assert_eq!(cell.get(), Some(&1));
+    }
+}
@@ -0,0 +1,36 @@
+//! SUI/USD spot price used to value trades at execution time.
+
+use std::time::{Duration, Instant};
+
+use eyre::{eyre, Result};
+use tokio::sync::Mutex;
+
+const SUI_USD_TICKER_URL: &str = "https://api.binance.com/api/v3/ticker/price?symbol=SUIUSDT";
+const PRICE_TTL: Duration = Duration::from_secs(60);
+
+static SUI_USD: Mutex<Option<(f64, Instant)>> = Mutex::const_new(None);
+
+/// Latest SUI/USD price, refreshed at most once a minute.
+pub async fn sui_usd_price() -> Result<f64> {
+    let mut cached = SUI_USD.lock().await;
+    if let Some((price, fetched_at)) = *cached {
+        if fetched_at.elapsed() < PRICE_TTL {
+            return Ok(price);
+        }
+    }
+
+    let price = fetch_sui_usd_price().await?;
+    *cached = Some((price, Instant::now()));
+    Ok(price)
+}
+
+async fn fetch_sui_usd_price() -> Result<f64> {
+    let client = reqwest::Client::builder().timeout(Duration::from_secs(5)).build()?;
+    let resp: serde_json::Value = client.get(SUI_USD_TICKER_URL).send().await?.json().await?;
+
+    resp["price"]
+        .as_str()
+        .ok_or_else(|| eyre!("invalid ticker response: {}", resp))?
+        .parse::<f64>()
+        .map_err(|e| eyre!(e))
+}